@@ -18,5 +18,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("RESPONSE={:?}", response.into_inner());
 
+    let state = client.aircraft_state("4840d6").await?;
+
+    println!("STATE={:?}", state);
+
     Ok(())
 }
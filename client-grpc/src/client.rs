@@ -27,6 +27,8 @@ cfg_if::cfg_if! {
 impl crate::service::Client<RpcServiceClient<Channel>> for TelemetryClient {
     type ReadyRequest = ReadyRequest;
     type ReadyResponse = ReadyResponse;
+    type AircraftStateRequest = AircraftStateRequest;
+    type AircraftStateResponse = AircraftStateResponse;
 
     async fn is_ready(
         &self,
@@ -37,6 +39,15 @@ impl crate::service::Client<RpcServiceClient<Channel>> for TelemetryClient {
         grpc_debug!("request: {:?}", request);
         self.get_client().await?.is_ready(request).await
     }
+
+    async fn get_aircraft_state(
+        &self,
+        request: Self::AircraftStateRequest,
+    ) -> Result<tonic::Response<Self::AircraftStateResponse>, tonic::Status> {
+        grpc_info!("{} client.", self.get_name());
+        grpc_debug!("request: {:?}", request);
+        self.get_client().await?.get_aircraft_state(request).await
+    }
 }
 
 #[cfg(feature = "stub_client")]
@@ -44,6 +55,8 @@ impl crate::service::Client<RpcServiceClient<Channel>> for TelemetryClient {
 impl crate::service::Client<RpcServiceClient<Channel>> for TelemetryClient {
     type ReadyRequest = ReadyRequest;
     type ReadyResponse = ReadyResponse;
+    type AircraftStateRequest = AircraftStateRequest;
+    type AircraftStateResponse = AircraftStateResponse;
 
     async fn is_ready(
         &self,
@@ -53,6 +66,17 @@ impl crate::service::Client<RpcServiceClient<Channel>> for TelemetryClient {
         grpc_debug!("(MOCK) request: {:?}", request);
         Ok(tonic::Response::new(ReadyResponse { ready: true }))
     }
+
+    async fn get_aircraft_state(
+        &self,
+        request: Self::AircraftStateRequest,
+    ) -> Result<tonic::Response<Self::AircraftStateResponse>, tonic::Status> {
+        grpc_warn!("(MOCK) {} client.", self.get_name());
+        grpc_debug!("(MOCK) request: {:?}", request);
+        Ok(tonic::Response::new(crate::mock::aircraft_state(
+            &request.identifier,
+        )))
+    }
 }
 
 #[cfg(test)]
@@ -90,4 +114,25 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap().into_inner().ready, true);
     }
+
+    // a server backed by redis only knows the aircraft it tracks
+    #[tokio::test]
+    #[cfg(any(feature = "stub_client", feature = "stub_backends"))]
+    async fn test_client_get_aircraft_state_request() {
+        let name = "telemetry";
+        let (server_host, server_port) =
+            lib_common::grpc::get_endpoint_from_env("GRPC_HOST", "GRPC_PORT");
+
+        let client: TelemetryClient = GrpcClient::new_client(&server_host, server_port, name);
+        let request = AircraftStateRequest {
+            identifier: "4840d6".to_string(),
+        };
+
+        let result = client.get_aircraft_state(request).await;
+        println!("{:?}", result);
+        assert!(result.is_ok());
+        let state = result.unwrap().into_inner();
+        assert_eq!(state.identifier, "4840d6");
+        assert_eq!(state.status(), AircraftStatus::Airborne);
+    }
 }
@@ -15,6 +15,97 @@ pub struct ReadyResponse {
     #[prost(bool, tag = "1")]
     pub ready: bool,
 }
+/// Aircraft State Request object
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AircraftStateRequest {
+    /// Identifier of the aircraft, as pushed to svc-gis
+    #[prost(string, tag = "1")]
+    pub identifier: ::prost::alloc::string::String,
+}
+/// Last position of an aircraft
+#[derive(Copy)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AircraftPosition {
+    /// Latitude in degrees
+    #[prost(double, tag = "1")]
+    pub latitude: f64,
+    /// Longitude in degrees
+    #[prost(double, tag = "2")]
+    pub longitude: f64,
+    /// Altitude in meters
+    #[prost(double, tag = "3")]
+    pub altitude_meters: f64,
+}
+/// Last velocity of an aircraft
+#[derive(Copy)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AircraftVelocity {
+    /// Horizontal ground speed in meters per second
+    #[prost(float, tag = "1")]
+    pub velocity_horizontal_ground_mps: f32,
+    /// Vertical speed in meters per second, positive up
+    #[prost(float, tag = "2")]
+    pub velocity_vertical_mps: f32,
+    /// Track angle in degrees clockwise from true north
+    #[prost(float, tag = "3")]
+    pub track_angle_degrees: f32,
+}
+/// Aircraft State Response object
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AircraftStateResponse {
+    /// Identifier of the aircraft
+    #[prost(string, tag = "1")]
+    pub identifier: ::prost::alloc::string::String,
+    /// Last accepted position
+    #[prost(message, optional, tag = "2")]
+    pub position: ::core::option::Option<AircraftPosition>,
+    /// Last reported velocity, if any
+    #[prost(message, optional, tag = "3")]
+    pub velocity: ::core::option::Option<AircraftVelocity>,
+    /// Status of the aircraft
+    #[prost(enumeration = "AircraftStatus", tag = "4")]
+    pub status: i32,
+    /// Milliseconds since the last position was accepted
+    #[prost(uint64, tag = "5")]
+    pub age_ms: u64,
+}
+/// Status of an aircraft
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum AircraftStatus {
+    /// Not reported by the aircraft
+    Unknown = 0,
+    /// In flight
+    Airborne = 1,
+    /// On the surface
+    OnGround = 2,
+}
+impl AircraftStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            AircraftStatus::Unknown => "UNKNOWN",
+            AircraftStatus::Airborne => "AIRBORNE",
+            AircraftStatus::OnGround => "ON_GROUND",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "UNKNOWN" => Some(Self::Unknown),
+            "AIRBORNE" => Some(Self::Airborne),
+            "ON_GROUND" => Some(Self::OnGround),
+            _ => None,
+        }
+    }
+}
 /// Generated client implementations.
 #[cfg(not(tarpaulin_include))]
 pub mod rpc_service_client {
@@ -122,5 +213,31 @@ pub mod rpc_service_client {
             req.extensions_mut().insert(GrpcMethod::new("grpc.RpcService", "isReady"));
             self.inner.unary(req, path, codec).await
         }
+        /// Latest state of an aircraft
+        pub async fn get_aircraft_state(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AircraftStateRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::AircraftStateResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/grpc.RpcService/getAircraftState",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("grpc.RpcService", "getAircraftState"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
@@ -1,8 +1,11 @@
 #![doc = include_str!("../README.md")]
 
 pub mod client;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod prelude;
 pub mod service;
+pub mod state;
 
 //use client::*;
 
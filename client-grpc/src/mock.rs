@@ -0,0 +1,22 @@
+//! Mock data of the svc-telemetry gRPC types
+
+use crate::client::{AircraftPosition, AircraftStateResponse, AircraftStatus, AircraftVelocity};
+
+/// State of an airborne aircraft, received a second ago
+pub fn aircraft_state(identifier: &str) -> AircraftStateResponse {
+    AircraftStateResponse {
+        identifier: identifier.to_string(),
+        position: Some(AircraftPosition {
+            latitude: 52.37,
+            longitude: 4.9,
+            altitude_meters: 1000.0,
+        }),
+        velocity: Some(AircraftVelocity {
+            velocity_horizontal_ground_mps: 120.0,
+            velocity_vertical_mps: 2.5,
+            track_angle_degrees: 90.0,
+        }),
+        status: AircraftStatus::Airborne as i32,
+        age_ms: 1000,
+    }
+}
@@ -2,6 +2,7 @@
 
 pub use super::client as telemetry;
 pub use super::service::Client as TelemetryServiceClient;
+pub use super::state::{AircraftState, AircraftStates};
 pub use telemetry::TelemetryClient;
 
 pub use lib_common::grpc::Client;
@@ -11,6 +11,10 @@ where
     type ReadyRequest;
     /// The type expected for ReadyResponse structs.
     type ReadyResponse;
+    /// The type expected for AircraftStateRequest structs.
+    type AircraftStateRequest;
+    /// The type expected for AircraftStateResponse structs.
+    type AircraftStateResponse;
 
     /// Returns a [`tonic::Response`] containing a [`ReadyResponse`](Self::ReadyResponse)
     /// Takes an [`ReadyRequest`](Self::ReadyRequest).
@@ -38,4 +42,38 @@ where
         &self,
         request: Self::ReadyRequest,
     ) -> Result<tonic::Response<Self::ReadyResponse>, tonic::Status>;
+
+    /// Returns a [`tonic::Response`] containing an [`AircraftStateResponse`](Self::AircraftStateResponse)
+    /// Takes an [`AircraftStateRequest`](Self::AircraftStateRequest).
+    ///
+    /// The response holds the last position, velocity and status of the
+    ///  aircraft, with the time elapsed since its position was received.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`tonic::Status`] with [`tonic::Code::NotFound`] if the aircraft isn't tracked,
+    ///  [`tonic::Code::InvalidArgument`] if no identifier is given, and
+    ///  [`tonic::Code::Unavailable`] if the state cache can't be reached.
+    ///
+    /// # Examples
+    /// ```
+    /// use lib_common::grpc::get_endpoint_from_env;
+    /// use svc_telemetry_client_grpc::prelude::*;
+    ///
+    /// async fn example () -> Result<(), Box<dyn std::error::Error>> {
+    ///     let (host, port) = get_endpoint_from_env("SERVER_HOSTNAME", "SERVER_PORT_GRPC");
+    ///     let client = TelemetryClient::new_client(&host, port, "telemetry");
+    ///     let response = client
+    ///         .get_aircraft_state(telemetry::AircraftStateRequest {
+    ///             identifier: "4840d6".to_string(),
+    ///         })
+    ///         .await?;
+    ///     println!("RESPONSE={:?}", response.into_inner());
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn get_aircraft_state(
+        &self,
+        request: Self::AircraftStateRequest,
+    ) -> Result<tonic::Response<Self::AircraftStateResponse>, tonic::Status>;
 }
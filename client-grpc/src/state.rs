@@ -0,0 +1,149 @@
+//! Typed queries of the latest state of aircraft
+
+use crate::client::{
+    AircraftPosition, AircraftStateRequest, AircraftStateResponse, AircraftStatus,
+    AircraftVelocity, TelemetryClient,
+};
+use crate::service::Client as ServiceClient;
+use std::time::Duration;
+use tonic::async_trait;
+
+/// Latest state of an aircraft
+#[derive(Debug, Clone, PartialEq)]
+pub struct AircraftState {
+    /// Identifier of the aircraft
+    pub identifier: String,
+
+    /// Last accepted position
+    pub position: AircraftPosition,
+
+    /// Last reported velocity
+    pub velocity: Option<AircraftVelocity>,
+
+    /// Status of the aircraft, unknown if it doesn't report it
+    pub status: AircraftStatus,
+
+    /// Time since the position was received
+    pub age: Duration,
+}
+
+impl AircraftState {
+    /// If the aircraft reported being in flight
+    pub fn is_airborne(&self) -> bool {
+        self.status == AircraftStatus::Airborne
+    }
+}
+
+impl TryFrom<AircraftStateResponse> for AircraftState {
+    type Error = tonic::Status;
+
+    fn try_from(response: AircraftStateResponse) -> Result<Self, Self::Error> {
+        let status = response.status();
+        let position = response
+            .position
+            .ok_or_else(|| tonic::Status::internal("aircraft state without position."))?;
+
+        Ok(AircraftState {
+            identifier: response.identifier,
+            position,
+            velocity: response.velocity,
+            status,
+            age: Duration::from_millis(response.age_ms),
+        })
+    }
+}
+
+/// Typed wrappers of the aircraft state RPC
+#[async_trait]
+pub trait AircraftStates {
+    /// Latest state of an aircraft, none if it isn't tracked
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`tonic::Status`] of failed requests, other than
+    ///  [`tonic::Code::NotFound`].
+    ///
+    /// # Examples
+    /// ```
+    /// use lib_common::grpc::get_endpoint_from_env;
+    /// use svc_telemetry_client_grpc::prelude::*;
+    ///
+    /// async fn example () -> Result<(), Box<dyn std::error::Error>> {
+    ///     let (host, port) = get_endpoint_from_env("SERVER_HOSTNAME", "SERVER_PORT_GRPC");
+    ///     let client = TelemetryClient::new_client(&host, port, "telemetry");
+    ///     match client.aircraft_state("4840d6").await? {
+    ///         Some(state) if state.is_airborne() => println!("airborne at {:?}", state.position),
+    ///         Some(state) => println!("{:?}", state.status),
+    ///         None => println!("not tracked"),
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn aircraft_state(
+        &self,
+        identifier: &str,
+    ) -> Result<Option<AircraftState>, tonic::Status>;
+}
+
+#[async_trait]
+impl AircraftStates for TelemetryClient {
+    async fn aircraft_state(
+        &self,
+        identifier: &str,
+    ) -> Result<Option<AircraftState>, tonic::Status> {
+        let request = AircraftStateRequest {
+            identifier: identifier.to_string(),
+        };
+
+        match self.get_aircraft_state(request).await {
+            Ok(response) => AircraftState::try_from(response.into_inner()).map(Some),
+            Err(status) if status.code() == tonic::Code::NotFound => Ok(None),
+            Err(status) => Err(status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_aircraft_state_from_response() {
+        let response = crate::mock::aircraft_state("4840d6");
+        let state = AircraftState::try_from(response.clone()).unwrap();
+        assert_eq!(state.identifier, "4840d6");
+        assert_eq!(Some(state.position), response.position);
+        assert_eq!(state.age, Duration::from_millis(response.age_ms));
+        assert!(state.is_airborne());
+
+        let response = AircraftStateResponse {
+            status: AircraftStatus::OnGround as i32,
+            ..response
+        };
+        let state = AircraftState::try_from(response.clone()).unwrap();
+        assert_eq!(state.status, AircraftStatus::OnGround);
+        assert!(!state.is_airborne());
+
+        let response = AircraftStateResponse {
+            position: None,
+            ..response
+        };
+        let status = AircraftState::try_from(response).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Internal);
+    }
+
+    #[tokio::test]
+    #[cfg(any(feature = "stub_client", feature = "stub_backends"))]
+    async fn test_client_aircraft_state() {
+        use lib_common::grpc::Client;
+
+        let (server_host, server_port) =
+            lib_common::grpc::get_endpoint_from_env("GRPC_HOST", "GRPC_PORT");
+        let client = TelemetryClient::new_client(&server_host, server_port, "telemetry");
+
+        let state = client.aircraft_state("4840d6").await.unwrap().unwrap();
+        assert_eq!(state.identifier, "4840d6");
+        assert!(state.is_airborne());
+    }
+}
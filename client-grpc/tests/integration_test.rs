@@ -42,6 +42,30 @@ async fn test_is_ready(client: &TelemetryClient) {
     }));
 }
 
+async fn test_get_aircraft_state(client: &TelemetryClient) {
+    // Start the logger.
+    let mut logger = Logger::start();
+
+    // The aircraft may not be tracked, or snapshots disabled on the server,
+    //  only the request reaching the service is checked
+    let result = client.aircraft_state("4840d6").await;
+    println!("{:?}", result);
+
+    // Search for the expected log message
+    let expected = get_log_string("get_aircraft_state");
+    println!("expected message: {}", expected);
+    assert!(logger.any(|log| {
+        if log.target().contains("app::") {
+            println!("{}", log.target());
+            let message = log.args();
+            println!("{:?}", message);
+            log.args() == expected
+        } else {
+            false
+        }
+    }));
+}
+
 #[tokio::test]
 async fn test_grpc() {
     let (_, server_port) = lib_common::grpc::get_endpoint_from_env("GRPC_HOST", "GRPC_PORT");
@@ -50,4 +74,5 @@ async fn test_grpc() {
     let client = TelemetryClient::new_client(&server_host, server_port, SERVICE_NAME);
 
     test_is_ready(&client).await;
+    test_get_aircraft_state(&client).await;
 }
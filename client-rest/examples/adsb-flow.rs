@@ -1,40 +1,29 @@
 //! Simulates a flow of ADS-B with multiple reporters
 
+use adsb_gen::fleet::{Area, Fleet};
 use futures_lite::stream::StreamExt;
 use hyper::StatusCode;
 use hyper::{Body, Client, Method, Request};
 use lib_common::grpc::get_endpoint_from_env;
+use svc_telemetry_client_rest::subscriber::TelemetrySubscriber;
+
+/// Simulated aircraft
+const AIRCRAFT: usize = 5;
+
+/// Frames sent per second by each reporter
+const FRAMES_PER_SECOND: f64 = 2.0;
+
+/// Seed of the simulated traffic, shared by the reporters
+const SEED: u64 = 42;
 
 async fn mq_listener() -> Result<(), ()> {
     let mq_addr = format!("amqp://rabbitmq:5672");
 
-    // Establish connection to RabbitMQ node
-    println!("(mq_listener) connecting to MQ server at {}...", mq_addr);
-    let result = lapin::Connection::connect(&mq_addr, lapin::ConnectionProperties::default()).await;
-    let mq_connection = result.map_err(|e| {
-        println!("(mq_listener) could not connect to MQ server at {mq_addr}.");
-        println!("(mq_listener) error: {:?}", e);
-    })?;
-
-    // Create channel
-    println!("(mq_listener) creating channel at {}...", mq_addr);
-    let mq_channel = mq_connection.create_channel().await.map_err(|e| {
-        println!("(mq_listener) could not create channel at {mq_addr}.");
-        println!("(mq_listener) error: {:?}", e);
-    })?;
-
-    let mut consumer = mq_channel
-        .basic_consume(
-            "netrid_id",
-            "mq_listener",
-            lapin::options::BasicConsumeOptions::default(),
-            lapin::types::FieldTable::default(),
-        )
-        .await
-        .unwrap();
-
-    while let Some(delivery) = consumer.next().await {
-        println!("received message {:?}", delivery);
+    // Reconnects to the MQ server as needed
+    println!("(mq_listener) subscribing to MQ server at {}...", mq_addr);
+    let mut packets = TelemetrySubscriber::new(&mq_addr).subscribe_adsb();
+    while let Some(packet) = packets.next().await {
+        println!("received message {:?}", packet);
     }
 
     Ok(())
@@ -45,26 +34,18 @@ async fn adsb(url: String) {
         .pool_idle_timeout(std::time::Duration::from_secs(10))
         .build_http();
 
-    let uri = format!("{}/telemetry/adsb", url);
+    let uri = format!("{}/v1/telemetry/adsb", url);
 
     // TODO(R5): different reporter ID
 
-    let mut count: u8 = 0;
-    let mut odd_flag = 1;
-    loop {
-        // Using example from 3.1: https://airmetar.main.jp/radio/ADS-B%20Decoding%20Guide.pdf
-        let payload = match odd_flag {
-            0 => [
-                0x8D, 0x40, 0x62, 0x1D, 0x58, 0xC3, 0x82, 0xD6, 0x90, 0xC8, 0xAC, 0x28, 0x63, count,
-            ],
-            _ => [
-                0x8D, 0x40, 0x62, 0x1D, 0x58, 0xC3, 0x86, 0x43, 0x5C, 0xC4, 0x12, 0x69, 0x2A, count,
-            ],
-        };
-
-        count += 1;
-        odd_flag ^= 1;
-
+    // Reporters receive the same aircraft
+    let area = Area {
+        latitude: 52.37,
+        longitude: 4.89,
+        radius_km: 20.0,
+    };
+    let mut fleet = Fleet::new(AIRCRAFT, area, FRAMES_PER_SECOND, SEED);
+    while let Some(payload) = fleet.next_frame() {
         let req: Request<Body> = Request::builder()
             .method(Method::POST)
             .uri(uri.clone())
@@ -85,7 +66,7 @@ async fn adsb(url: String) {
             }
         }
 
-        std::thread::sleep(std::time::Duration::from_millis(500)); // twice a second
+        tokio::time::sleep(std::time::Duration::from_secs_f64(1.0 / FRAMES_PER_SECOND)).await;
     }
 }
 
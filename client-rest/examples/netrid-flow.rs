@@ -4,45 +4,17 @@ use futures_lite::stream::StreamExt;
 use hyper::{body::Bytes, Body, Client, Method, Request, StatusCode};
 use lib_common::grpc::get_endpoint_from_env;
 use packed_struct::PackedStruct;
-use svc_gis_client_grpc::prelude::types::AircraftId;
 use svc_telemetry_client_rest::netrid_types::*;
+use svc_telemetry_client_rest::subscriber::TelemetrySubscriber;
 
 async fn mq_listener() -> Result<(), ()> {
     let mq_addr = format!("amqp://rabbitmq:5672");
 
-    // Establish connection to RabbitMQ node
-    println!("(mq_listener) connecting to MQ server at {}...", mq_addr);
-    let mq_connection =
-        lapin::Connection::connect(&mq_addr, lapin::ConnectionProperties::default())
-            .await
-            .map_err(|e| {
-                println!("(mq_listener) could not connect to MQ server at {mq_addr}.");
-                println!("(mq_listener) error: {:?}", e);
-            })?;
-
-    // Create channel
-    println!("(mq_listener) creating channel at {}...", mq_addr);
-    let mq_channel = mq_connection.create_channel().await.map_err(|e| {
-        println!("(mq_listener) could not create channel at {mq_addr}.");
-        println!("(mq_listener) error: {:?}", e);
-    })?;
-
-    let mut consumer = mq_channel
-        .basic_consume(
-            "netrid_id",
-            "mq_listener",
-            lapin::options::BasicConsumeOptions::default(),
-            lapin::types::FieldTable::default(),
-        )
-        .await
-        .unwrap();
-
-    while let Some(delivery) = consumer.next().await {
-        if let Ok(id) = serde_json::from_slice::<AircraftId>(&delivery.unwrap().data) {
-            println!("id: {:?}", id);
-        } else {
-            println!("error: could not deserialize id message");
-        }
+    // Reconnects to the MQ server as needed
+    println!("(mq_listener) subscribing to MQ server at {}...", mq_addr);
+    let mut identifications = TelemetrySubscriber::new(&mq_addr).subscribe_identifications();
+    while let Some(id) = identifications.next().await {
+        println!("id: {:?}", id);
     }
 
     Ok(())
@@ -53,7 +25,7 @@ async fn netrid(reporter: i32, url: String) -> () {
         .pool_idle_timeout(std::time::Duration::from_secs(10))
         .build_http();
 
-    let uri = format!("{url}/telemetry/netrid");
+    let uri = format!("{url}/v1/telemetry/netrid");
     let identifier = format!("aircraft{reporter}");
 
     // FAILED PUSH WITH NO CREDENTIALS
@@ -97,10 +69,14 @@ async fn netrid(reporter: i32, url: String) -> () {
 
     // LOGIN
     let req = Request::builder()
-        .method(Method::GET)
-        .uri(format!("{url}/telemetry/login"))
-        .header("content-type", "text/plain")
-        .body(Bytes::from(identifier.clone()).into())
+        .method(Method::POST)
+        .uri(format!("{url}/v1/telemetry/login"))
+        .header("content-type", "application/json")
+        .body(
+            serde_json::json!({ "identifier": identifier })
+                .to_string()
+                .into(),
+        )
         .unwrap();
 
     let resp = match client.request(req).await {
@@ -118,10 +94,10 @@ async fn netrid(reporter: i32, url: String) -> () {
     };
 
     let body = resp.into_body();
-    let token = hyper::body::to_bytes(body).await.unwrap();
-    let token = String::from_utf8(token.to_vec()).unwrap();
-    let token = token.trim_matches('"').replace("\"", "");
-    println!("Token: {}", token);
+    let body = hyper::body::to_bytes(body).await.unwrap();
+    let login: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let token = login["token"].as_str().unwrap().to_string();
+    println!("Token: {}, expires at {}", token, login["expires_at"]);
 
     for _ in 0..10 {
         let req = Request::builder()
@@ -35,10 +35,10 @@ async fn evaluate(
 }
 
 async fn adsb(url: &str, client: &Client<HttpConnector>) {
-    let uri = format!("{}/telemetry/adsb", url);
+    let uri = format!("{}/v1/telemetry/adsb", url);
     let max: u8 = 4;
 
-    // POST /telemetry/adsb NOMINAL
+    // POST /v1/telemetry/adsb NOMINAL
     println!(
         "Send {} packets with different ICAO addresses, expect \
         response body value of 1 each time",
@@ -65,7 +65,7 @@ async fn adsb(url: &str, client: &Client<HttpConnector>) {
         }
     }
 
-    // POST /telemetry/adsb REPEAT MESSAGES
+    // POST /v1/telemetry/adsb REPEAT MESSAGES
     let payload: [u8; 14] = [
         0x8D, 0x48, 0x40, max, 0x20, 0x2C, 0xC3, 0x71, 0xC3, 0x2C, 0xE0, 0x57, 0x60, 0x98,
     ];
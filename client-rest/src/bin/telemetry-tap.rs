@@ -0,0 +1,122 @@
+//! Prints the telemetry published by svc-telemetry
+//!
+//! Taps the selected message queues without taking messages from their
+//!  consumers, e.g. `telemetry-tap --queue netrid_pos --identifier drone-1`.
+
+use clap::{Parser, ValueEnum};
+use futures::StreamExt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use svc_telemetry_client_rest::subscriber::TelemetrySubscriber;
+use svc_telemetry_client_rest::tap::{TapMessage, TAP_QUEUES};
+
+/// Output format of the messages
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// Indented JSON, headed by the queue and identifier
+    Pretty,
+
+    /// One JSON object per line
+    Json,
+}
+
+/// Command line options
+#[derive(Parser, Debug)]
+#[command(about = "Prints the telemetry published by svc-telemetry")]
+struct Cli {
+    /// Address of the RabbitMQ server
+    #[arg(long, env = "AMQP_ADDRESS", default_value = "amqp://rabbitmq:5672")]
+    address: String,
+
+    /// Queue to tap, repeated for several, all if omitted
+    #[arg(long = "queue", value_parser = tap_queue)]
+    queues: Vec<String>,
+
+    /// Only print messages about this aircraft or vehicle, repeated for several
+    #[arg(long = "identifier")]
+    identifiers: Vec<String>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Pretty)]
+    format: Format,
+
+    /// File to write to instead of the standard output
+    #[arg(long)]
+    output: Option<String>,
+}
+
+/// Validates the name of a queue
+fn tap_queue(queue: &str) -> Result<String, String> {
+    match TAP_QUEUES.iter().any(|(name, _)| *name == queue) {
+        true => Ok(queue.to_string()),
+        false => {
+            let names: Vec<&str> = TAP_QUEUES.iter().map(|(name, _)| *name).collect();
+            Err(format!("expected one of: {}", names.join(", ")))
+        }
+    }
+}
+
+/// Message formatted for output
+fn format(message: &TapMessage, format: Format) -> String {
+    match format {
+        Format::Pretty => message.to_pretty(),
+        Format::Json => message.to_json_line(),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let mut output: Box<dyn Write> = match &cli.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout()),
+    };
+
+    let queues: Vec<&str> = cli.queues.iter().map(String::as_str).collect();
+    let mut messages = TelemetrySubscriber::new(&cli.address)
+        .tap(&queues)
+        .ok_or("unknown queue")?;
+
+    eprintln!("tapping {} ...", cli.address);
+    while let Some(message) = messages.next().await {
+        if !message.matches(&cli.identifiers) {
+            continue;
+        }
+
+        writeln!(output, "{}", format(&message, cli.format))?;
+        output.flush()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli() {
+        let cli = Cli::try_parse_from([
+            "telemetry-tap",
+            "--queue",
+            "netrid_pos",
+            "--queue",
+            "adsb",
+            "--identifier",
+            "drone-1",
+            "--format",
+            "json",
+        ])
+        .unwrap();
+        assert_eq!(cli.queues, vec!["netrid_pos", "adsb"]);
+        assert_eq!(cli.identifiers, vec!["drone-1"]);
+        assert_eq!(cli.format, Format::Json);
+        assert_eq!(cli.output, None);
+
+        let cli = Cli::try_parse_from(["telemetry-tap"]).unwrap();
+        assert!(cli.queues.is_empty());
+        assert_eq!(cli.format, Format::Pretty);
+
+        assert!(Cli::try_parse_from(["telemetry-tap", "--queue", "unknown"]).is_err());
+    }
+}
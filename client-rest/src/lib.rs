@@ -11,3 +11,20 @@ pub mod netrid_types {
 pub mod adsb_types {
     include!("../../server/src/msg/adsb.rs");
 }
+
+/// Types for vehicle health reports (temporary)
+//  TODO(R5): Move health types to a separate crate
+pub mod health_types {
+    include!("../../server/src/msg/health.rs");
+}
+
+/// Envelope of items published to the telemetry message queue
+pub mod envelope {
+    include!("../../server/src/amqp/envelope.rs");
+}
+
+/// Subscriber of the telemetry message queues
+pub mod subscriber;
+
+/// Taps of the telemetry message queues
+pub mod tap;
@@ -0,0 +1,384 @@
+//! Subscriber of the telemetry message queues
+//!
+//! Each subscription holds its own RabbitMQ connection, which is
+//!  re-established whenever it drops. Items which can't be deserialized
+//!  are skipped.
+//!
+//! Subscriptions, like taps (see [`crate::tap`]), consume a queue of their
+//!  own bound to the routing key of the queue they subscribe to, so they
+//!  receive a copy of every message routed to it while they are connected
+//!  and don't take messages from the consumers of the shared queue.
+
+use crate::adsb_types::AircraftEnrichment;
+use crate::envelope::TelemetryEnvelope;
+use crate::tap::{queue_name, routing_key, TapMessage, EXCHANGE_NAME_TELEMETRY};
+use futures::future::ready;
+use futures::stream::{self, Stream, StreamExt};
+use lapin::message::Delivery;
+use lapin::options::{BasicConsumeOptions, QueueBindOptions, QueueDeclareOptions};
+use lapin::types::FieldTable;
+use lapin::{Connection, ConnectionProperties, Consumer};
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::time::Duration;
+use svc_gis_client_grpc::prelude::types::{AircraftId, AircraftPosition, AircraftVelocity};
+
+/// Queue of raw ADS-B packets
+pub const QUEUE_NAME_ADSB: &str = "adsb";
+
+/// Queue of ADS-B identifications, with the raw emitter category
+pub const QUEUE_NAME_ADSB_ID: &str = "adsb_id";
+
+/// Queue of ADS-B aircraft callsigns and squawks
+pub const QUEUE_NAME_ADSB_ENRICHMENT: &str = "adsb_enrichment";
+
+/// Queue of Network Remote ID identification messages
+pub const QUEUE_NAME_NETRID_ID: &str = "netrid_id";
+
+/// Queue of Network Remote ID positions
+pub const QUEUE_NAME_NETRID_POSITION: &str = "netrid_pos";
+
+/// Queue of Network Remote ID velocities
+pub const QUEUE_NAME_NETRID_VELOCITY: &str = "netrid_vel";
+
+/// Queue of positions extrapolated by svc-telemetry
+pub const QUEUE_NAME_PREDICTED_POSITION: &str = "predicted_pos";
+
+/// Queue of watchlist alerts
+pub const QUEUE_NAME_WATCHLIST: &str = "watchlist";
+
+/// Queue of aircraft emergencies
+pub const QUEUE_NAME_ALERT: &str = "alert";
+
+/// Default time to wait before reconnecting
+const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Subscribes to the queues svc-telemetry publishes to
+#[derive(Debug, Clone)]
+pub struct TelemetrySubscriber {
+    /// Address of the RabbitMQ server, e.g. `amqp://rabbitmq:5672`
+    address: String,
+
+    /// Time to wait before reconnecting
+    reconnect_delay: Duration,
+}
+
+/// State of a single subscription
+struct Subscription {
+    /// Address of the RabbitMQ server
+    address: String,
+
+    /// Routing keys bound to the exclusive queue of this subscription
+    routing_keys: Vec<String>,
+
+    /// Time to wait before reconnecting
+    reconnect_delay: Duration,
+
+    /// Open connection and its consumer, if connected
+    consumer: Option<(Connection, Consumer)>,
+}
+
+/// Connect to the server and consume a queue of its own, bound to the
+///  routing keys
+///
+/// The queue is named by the server, exclusive to the connection and
+///  deleted with it: each connection declares and binds a new one.
+async fn connect(
+    address: &str,
+    routing_keys: &[String],
+) -> Result<(Connection, Consumer), lapin::Error> {
+    let connection = Connection::connect(address, ConnectionProperties::default()).await?;
+
+    let channel = connection.create_channel().await?;
+    let options = QueueDeclareOptions {
+        exclusive: true,
+        auto_delete: true,
+        ..Default::default()
+    };
+    let queue = channel
+        .queue_declare("", options, FieldTable::default())
+        .await?
+        .name()
+        .to_string();
+
+    for key in routing_keys {
+        channel
+            .queue_bind(
+                &queue,
+                EXCHANGE_NAME_TELEMETRY,
+                key,
+                QueueBindOptions::default(),
+                FieldTable::default(),
+            )
+            .await?;
+    }
+
+    // the queue only holds copies for this subscription
+    let consumer = channel
+        .basic_consume(
+            &queue,
+            "",
+            BasicConsumeOptions {
+                no_ack: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    Ok((connection, consumer))
+}
+
+/// Wait for the next item of a consumer, connecting with `connect` as
+///  needed
+///
+/// The handle connected with the consumer is kept open along with it.
+///  Once the consumer fails or ends, it is dropped and connected again
+///  after `delay`, as are failed connections.
+async fn next_item<H, C, T, E, F, Fut>(
+    consumer: &mut Option<(H, C)>,
+    mut connect: F,
+    delay: Duration,
+) -> T
+where
+    C: Stream<Item = Result<T, E>> + Unpin,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(H, C), lapin::Error>>,
+{
+    loop {
+        let Some((_, stream)) = consumer.as_mut() else {
+            match connect().await {
+                Ok(connected) => *consumer = Some(connected),
+                Err(_) => tokio::time::sleep(delay).await,
+            }
+
+            continue;
+        };
+
+        match stream.next().await {
+            Some(Ok(item)) => return item,
+            // connection lost or consumer cancelled
+            _ => {
+                *consumer = None;
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
+
+impl Subscription {
+    /// Wait for the next message, reconnecting as needed
+    async fn next_message(&mut self) -> Delivery {
+        let (address, routing_keys) = (&self.address, &self.routing_keys);
+        next_item(
+            &mut self.consumer,
+            || connect(address, routing_keys),
+            self.reconnect_delay,
+        )
+        .await
+    }
+}
+
+/// Deserialize an enveloped item, None if malformed
+fn decode<T: DeserializeOwned>(data: &[u8]) -> Option<TelemetryEnvelope<T>> {
+    serde_json::from_slice(data).ok()
+}
+
+impl TelemetrySubscriber {
+    /// Create a subscriber of the RabbitMQ server at the given address
+    pub fn new(address: &str) -> Self {
+        TelemetrySubscriber {
+            address: address.to_string(),
+            reconnect_delay: DEFAULT_RECONNECT_DELAY,
+        }
+    }
+
+    /// Set the time to wait before reconnecting
+    pub fn with_reconnect_delay(mut self, reconnect_delay: Duration) -> Self {
+        self.reconnect_delay = reconnect_delay;
+        self
+    }
+
+    /// Deliveries of a subscription to the given routing keys
+    fn deliveries(&self, routing_keys: Vec<String>) -> impl Stream<Item = Delivery> + Send + Unpin {
+        let subscription = Subscription {
+            address: self.address.clone(),
+            routing_keys,
+            reconnect_delay: self.reconnect_delay,
+            consumer: None,
+        };
+
+        Box::pin(stream::unfold(
+            subscription,
+            |mut subscription| async move {
+                let message = subscription.next_message().await;
+                Some((message, subscription))
+            },
+        ))
+    }
+
+    /// Raw messages routed to a queue
+    ///
+    /// Queues which can't be tapped are read as the routing key of their
+    ///  messages.
+    pub fn subscribe_raw(&self, queue: &str) -> impl Stream<Item = Vec<u8>> + Send + Unpin {
+        let routing_key = routing_key(queue).unwrap_or(queue).to_string();
+        self.deliveries(vec![routing_key])
+            .map(|delivery| delivery.data)
+    }
+
+    /// Copies of the messages routed to the given queues, all of the
+    ///  [`TAP_QUEUES`](crate::tap::TAP_QUEUES) if empty
+    ///
+    /// Unlike subscriptions, taps don't take messages from the other
+    ///  consumers of the queues. None if a queue can't be tapped.
+    pub fn tap(&self, queues: &[&str]) -> Option<impl Stream<Item = TapMessage> + Send + Unpin> {
+        let routing_keys = match queues.is_empty() {
+            true => crate::tap::TAP_QUEUES
+                .iter()
+                .map(|(_, key)| key.to_string())
+                .collect(),
+            false => queues
+                .iter()
+                .map(|queue| routing_key(queue).map(str::to_string))
+                .collect::<Option<Vec<_>>>()?,
+        };
+
+        let messages = self.deliveries(routing_keys).filter_map(|delivery| {
+            let queue = queue_name(delivery.routing_key.as_str());
+            ready(queue.map(|queue| TapMessage {
+                queue: queue.to_string(),
+                data: delivery.data,
+            }))
+        });
+
+        Some(messages)
+    }
+
+    /// Enveloped items of a queue
+    pub fn subscribe<T>(
+        &self,
+        queue: &str,
+    ) -> impl Stream<Item = TelemetryEnvelope<T>> + Send + Unpin
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        self.subscribe_raw(queue)
+            .filter_map(|message| ready(decode(&message)))
+    }
+
+    /// ADS-B packets as received by svc-telemetry
+    pub fn subscribe_adsb(&self) -> impl Stream<Item = Vec<u8>> + Send + Unpin {
+        self.subscribe_raw(QUEUE_NAME_ADSB)
+    }
+
+    /// Aircraft identifications received over ADS-B, enveloped with the
+    ///  raw emitter category of the aircraft
+    pub fn subscribe_adsb_identifications(
+        &self,
+    ) -> impl Stream<Item = TelemetryEnvelope<AircraftId>> + Send + Unpin {
+        self.subscribe(QUEUE_NAME_ADSB_ID)
+    }
+
+    /// Callsigns and squawks of ADS-B aircraft
+    pub fn subscribe_adsb_enrichments(
+        &self,
+    ) -> impl Stream<Item = AircraftEnrichment> + Send + Unpin {
+        self.subscribe(QUEUE_NAME_ADSB_ENRICHMENT)
+            .map(|envelope| envelope.data)
+    }
+
+    /// Aircraft declaring an emergency, published once per change
+    pub fn subscribe_alerts(&self) -> impl Stream<Item = AircraftEnrichment> + Send + Unpin {
+        self.subscribe(QUEUE_NAME_ALERT)
+            .map(|envelope| envelope.data)
+    }
+
+    /// Aircraft identifications
+    pub fn subscribe_identifications(&self) -> impl Stream<Item = AircraftId> + Send + Unpin {
+        self.subscribe(QUEUE_NAME_NETRID_ID)
+            .map(|envelope| envelope.data)
+    }
+
+    /// Aircraft positions reported by the aircraft
+    pub fn subscribe_positions(&self) -> impl Stream<Item = AircraftPosition> + Send + Unpin {
+        self.subscribe(QUEUE_NAME_NETRID_POSITION)
+            .map(|envelope| envelope.data)
+    }
+
+    /// Aircraft positions extrapolated during gaps in coverage
+    pub fn subscribe_predicted_positions(
+        &self,
+    ) -> impl Stream<Item = AircraftPosition> + Send + Unpin {
+        self.subscribe(QUEUE_NAME_PREDICTED_POSITION)
+            .map(|envelope| envelope.data)
+    }
+
+    /// Aircraft velocities
+    pub fn subscribe_velocities(&self) -> impl Stream<Item = AircraftVelocity> + Send + Unpin {
+        self.subscribe(QUEUE_NAME_NETRID_VELOCITY)
+            .map(|envelope| envelope.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let envelope = decode::<u32>(br#"{"version":1,"predicted":true,"data":5}"#).unwrap();
+        assert!(envelope.predicted);
+        assert_eq!(envelope.data, 5);
+
+        assert!(decode::<u32>(br#"{"version":1,"data":"five"}"#).is_none());
+        assert!(decode::<u32>(b"\x8d\x48").is_none());
+    }
+
+    #[test]
+    fn test_reconnect_delay() {
+        let subscriber = TelemetrySubscriber::new("amqp://localhost:5672");
+        assert_eq!(subscriber.reconnect_delay, DEFAULT_RECONNECT_DELAY);
+
+        let subscriber = subscriber.with_reconnect_delay(Duration::from_millis(100));
+        assert_eq!(subscriber.reconnect_delay, Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let delay = Duration::from_millis(10);
+        let mut attempts = 0;
+
+        // the first connection fails, the second drops after an item
+        let mut consumer = None;
+        let mut connect = || {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                let items: Vec<Result<u32, ()>> = match attempt {
+                    1 => return Err(lapin::Error::ChannelsLimitReached),
+                    2 => vec![Ok(1), Err(())],
+                    _ => vec![Ok(attempt)],
+                };
+                Ok::<_, lapin::Error>((attempt, stream::iter(items)))
+            }
+        };
+
+        let start = std::time::Instant::now();
+        assert_eq!(next_item(&mut consumer, &mut connect, delay).await, 1);
+        assert!(start.elapsed() >= delay);
+        assert_eq!(consumer.as_ref().map(|(handle, _)| *handle), Some(2));
+
+        assert_eq!(next_item(&mut consumer, &mut connect, delay).await, 3);
+        assert!(start.elapsed() >= 2 * delay);
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_tap_queues() {
+        let subscriber = TelemetrySubscriber::new("amqp://localhost:5672");
+        assert!(subscriber.tap(&[]).is_some());
+        assert!(subscriber.tap(&["adsb", "netrid_pos"]).is_some());
+        assert!(subscriber.tap(&["adsb", "unknown"]).is_none());
+    }
+}
@@ -0,0 +1,213 @@
+//! Taps of the telemetry message queues, for troubleshooting
+//!
+//! A tap receives copies of the messages routed to the queues it selects,
+//!  through its own exclusive queue bound to the `telemetry` exchange. The
+//!  consumers of the shared queues keep receiving every message, while
+//!  consuming a shared queue directly would take its messages from them.
+
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+/// Exchange svc-telemetry publishes to
+pub const EXCHANGE_NAME_TELEMETRY: &str = "telemetry";
+
+/// Queues which can be tapped, with the routing key of their messages
+///
+/// Operator identifiers published without privacy protection are left out.
+pub const TAP_QUEUES: [(&str, &str); 19] = [
+    ("adsb", "adsb"),
+    ("adsb_id", "adsb:id"),
+    ("adsb_enrichment", "adsb:enrichment"),
+    ("adsb_state", "adsb:state"),
+    ("netrid_id", "netrid:id"),
+    ("netrid_pos", "netrid:pos"),
+    ("netrid_vel", "netrid:vel"),
+    ("netrid_state", "netrid:state"),
+    ("netrid_operator", "netrid:operator"),
+    ("predicted_pos", "predicted:pos"),
+    ("watchlist", "telemetry:watchlist"),
+    ("alert", "telemetry:alert"),
+    ("vehicle_health", "vehicle:health"),
+    ("c2_status", "c2:status"),
+    ("weather", "weather"),
+    ("coverage", "coverage:summary"),
+    ("anomaly", "telemetry:anomaly"),
+    ("nonconformance", "telemetry:nonconformance"),
+    ("system", "telemetry:system"),
+];
+
+/// Queue of raw ADS-B packets, not enveloped
+const QUEUE_NAME_ADSB: &str = "adsb";
+
+/// Routing key of the messages of a queue, None if it can't be tapped
+pub fn routing_key(queue: &str) -> Option<&'static str> {
+    TAP_QUEUES
+        .iter()
+        .find(|(name, _)| *name == queue)
+        .map(|(_, key)| *key)
+}
+
+/// Queue of the messages published with a routing key
+pub fn queue_name(routing_key: &str) -> Option<&'static str> {
+    TAP_QUEUES
+        .iter()
+        .find(|(_, key)| *key == routing_key)
+        .map(|(name, _)| *name)
+}
+
+/// Message received by a tap
+#[derive(Debug, Clone, PartialEq)]
+pub struct TapMessage {
+    /// Queue the message was routed to
+    pub queue: String,
+
+    /// Body of the message, a JSON envelope or a raw packet
+    pub data: Vec<u8>,
+}
+
+/// Lower case hex representation of bytes
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+impl TapMessage {
+    /// Body of the message as JSON: the envelope, or the hex encoded raw
+    ///  packet
+    pub fn body(&self) -> Value {
+        if self.queue == QUEUE_NAME_ADSB {
+            return Value::String(to_hex(&self.data));
+        }
+
+        serde_json::from_slice(&self.data)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&self.data).into_owned()))
+    }
+
+    /// Identifier of the aircraft or vehicle the message is about, if any
+    ///
+    /// The ICAO address (hex) of raw ADS-B packets, the `identifier` of
+    ///  the enveloped item otherwise.
+    pub fn identifier(&self) -> Option<String> {
+        if self.queue == QUEUE_NAME_ADSB {
+            return self.data.get(1..4).map(to_hex);
+        }
+
+        let body = self.body();
+        body.pointer("/data/identifier")
+            .or_else(|| body.get("identifier"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    }
+
+    /// If the message is about one of the identifiers, any if empty
+    ///
+    /// Identifiers are compared ignoring case, as ICAO addresses are hex.
+    pub fn matches(&self, identifiers: &[String]) -> bool {
+        if identifiers.is_empty() {
+            return true;
+        }
+
+        self.identifier().is_some_and(|identifier| {
+            identifiers
+                .iter()
+                .any(|wanted| wanted.eq_ignore_ascii_case(&identifier))
+        })
+    }
+
+    /// The message as a single JSON line
+    pub fn to_json_line(&self) -> String {
+        json!({
+            "queue": self.queue,
+            "identifier": self.identifier(),
+            "message": self.body(),
+        })
+        .to_string()
+    }
+
+    /// The message formatted for reading, headed by its queue and identifier
+    pub fn to_pretty(&self) -> String {
+        let identifier = self.identifier().unwrap_or_else(|| "-".to_string());
+        let body = serde_json::to_string_pretty(&self.body()).unwrap_or_default();
+        format!("[{}] {identifier}\n{body}", self.queue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(queue: &str, data: &[u8]) -> TapMessage {
+        TapMessage {
+            queue: queue.to_string(),
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_routing_key() {
+        assert_eq!(routing_key("netrid_pos"), Some("netrid:pos"));
+        assert_eq!(queue_name("netrid:pos"), Some("netrid_pos"));
+        assert_eq!(routing_key("netrid_operator_full"), None);
+        assert_eq!(queue_name("netrid:operator:full"), None);
+        for (queue, key) in TAP_QUEUES {
+            assert_eq!(queue_name(key), Some(queue));
+        }
+    }
+
+    #[test]
+    fn test_identifier() {
+        let packet = [
+            0x8D, 0x48, 0x40, 0xD6, 0x20, 0x2C, 0xC3, 0x71, 0xC3, 0x2C, 0xE0, 0x57, 0x60, 0x98,
+        ];
+        let raw = message("adsb", &packet);
+        assert_eq!(raw.identifier(), Some("4840d6".to_string()));
+        assert_eq!(
+            raw.body(),
+            Value::String("8d4840d6202cc371c32ce0576098".into())
+        );
+        assert_eq!(message("adsb", &[0x8D]).identifier(), None);
+
+        let position = message(
+            "netrid_pos",
+            br#"{"version":1,"data":{"identifier":"drone-1","position":{}}}"#,
+        );
+        assert_eq!(position.identifier(), Some("drone-1".to_string()));
+
+        let coverage = message("coverage", br#"{"version":1,"data":{"cells":[]}}"#);
+        assert_eq!(coverage.identifier(), None);
+
+        // unreadable messages are shown as text
+        let garbled = message("netrid_pos", b"{garbled");
+        assert_eq!(garbled.body(), Value::String("{garbled".into()));
+        assert_eq!(garbled.identifier(), None);
+    }
+
+    #[test]
+    fn test_matches() {
+        let raw = message("adsb", &[0x8D, 0x48, 0x40, 0xD6]);
+        assert!(raw.matches(&[]));
+        assert!(raw.matches(&["4840D6".to_string()]));
+        assert!(raw.matches(&["drone-1".to_string(), "4840d6".to_string()]));
+        assert!(!raw.matches(&["4840d7".to_string()]));
+
+        let coverage = message("coverage", br#"{"version":1,"data":{}}"#);
+        assert!(coverage.matches(&[]));
+        assert!(!coverage.matches(&["4840d6".to_string()]));
+    }
+
+    #[test]
+    fn test_format() {
+        let position = message("netrid_pos", br#"{"version":1,"data":{"identifier":"d"}}"#);
+        let line: Value = serde_json::from_str(&position.to_json_line()).unwrap();
+        assert_eq!(line["queue"], "netrid_pos");
+        assert_eq!(line["identifier"], "d");
+        assert_eq!(line["message"]["data"]["identifier"], "d");
+        assert!(!position.to_json_line().contains('\n'));
+
+        let pretty = position.to_pretty();
+        assert!(pretty.starts_with("[netrid_pos] d\n{"));
+        assert!(pretty.contains("\n  \"version\": 1"));
+    }
+}
@@ -0,0 +1,35 @@
+//! Benchmarks of the CPR position decoding, run for every ADS-B position
+
+use criterion::{black_box, Criterion};
+use svc_telemetry::msg::adsb::{decode_cpr, encode_cpr};
+
+/// Decodes CPR pairs from the equator to the poles
+fn bench_decode_cpr(c: &mut Criterion) {
+    let pairs: Vec<_> = (-89..=89)
+        .map(|latitude| {
+            let latitude = latitude as f64 + 0.25;
+            let (lon_even, lat_even) = encode_cpr(0, 4.9, latitude).unwrap();
+            let (lon_odd, lat_odd) = encode_cpr(1, 4.9, latitude).unwrap();
+            (lat_even, lon_even, lat_odd, lon_odd)
+        })
+        .collect();
+
+    c.bench_function("decode_cpr", |b| {
+        b.iter(|| {
+            for (lat_even, lon_even, lat_odd, lon_odd) in &pairs {
+                let _ = black_box(decode_cpr(
+                    black_box(*lat_even),
+                    black_box(*lon_even),
+                    black_box(*lat_odd),
+                    black_box(*lon_odd),
+                ));
+            }
+        })
+    });
+}
+
+fn main() {
+    let mut criterion = Criterion::default().configure_from_args();
+    bench_decode_cpr(&mut criterion);
+    criterion.final_summary();
+}
@@ -0,0 +1,118 @@
+//! Benchmarks of the deduplication increments, sent one by one or batched
+//!
+//! Redis is simulated by a single threaded store spending a fixed time on
+//!  each round trip (reading the request, writing the reply) and on each
+//!  command, as a real server does.
+
+use criterion::Criterion;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use svc_telemetry::cache::batch::{IncrementBackend, IncrementBatcher};
+use svc_telemetry::cache::packet_key;
+use svc_telemetry::cache::pool::CacheError;
+
+/// Server time of a round trip
+const ROUND_TRIP_COST: Duration = Duration::from_micros(20);
+
+/// Server time of a command
+const COMMAND_COST: Duration = Duration::from_micros(1);
+
+/// Packets received per iteration, each reported by 3 receivers
+const PACKETS: usize = 2_000;
+
+/// Busy waits, the store being single threaded
+fn spin(duration: Duration) {
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        std::hint::spin_loop();
+    }
+}
+
+/// Simulated Redis store
+#[derive(Clone, Default)]
+struct SimulatedRedis {
+    counts: Arc<Mutex<HashMap<String, u32>>>,
+}
+
+impl IncrementBackend for SimulatedRedis {
+    fn increment_batch<'a>(
+        &'a mut self,
+        keys: &'a [(String, u32)],
+    ) -> BoxFuture<'a, Result<Vec<u32>, CacheError>> {
+        Box::pin(async move {
+            let mut counts = self.counts.lock().unwrap();
+            spin(ROUND_TRIP_COST);
+
+            // INCR and PEXPIRE of each key
+            let values = keys
+                .iter()
+                .map(|(key, _)| {
+                    spin(COMMAND_COST * 2);
+                    let count = counts.entry(key.clone()).or_default();
+                    *count += 1;
+                    *count
+                })
+                .collect();
+
+            Ok(values)
+        })
+    }
+}
+
+/// Keys of the reports of an iteration
+fn keys() -> Vec<String> {
+    (0..PACKETS * 3)
+        .map(|i| packet_key("adsb", &((i / 3) as u64).to_be_bytes()))
+        .collect()
+}
+
+/// Increments sent concurrently, one round trip each
+fn bench_direct(c: &mut Criterion, runtime: &tokio::runtime::Runtime) {
+    let keys = keys();
+    c.bench_function("dedup_direct", |b| {
+        b.iter(|| {
+            let backend = SimulatedRedis::default();
+            runtime.block_on(futures::future::join_all(keys.iter().map(|key| {
+                let mut backend = backend.clone();
+                let keys = [(key.clone(), 1000)];
+                tokio::spawn(async move {
+                    let values = backend.increment_batch(&keys).await;
+                    values.map(|values| values[0])
+                })
+            })))
+        })
+    });
+}
+
+/// Increments sent concurrently, batched within 1 ms
+fn bench_batched(c: &mut Criterion, runtime: &tokio::runtime::Runtime) {
+    let keys = keys();
+    c.bench_function("dedup_batched", |b| {
+        b.iter(|| {
+            let batcher = IncrementBatcher::spawn(SimulatedRedis::default(), 1);
+
+            runtime.block_on(futures::future::join_all(keys.iter().map(|key| {
+                let batcher = batcher.clone();
+                let key = key.clone();
+                tokio::spawn(async move { batcher.increment(&key, 1000).await })
+            })))
+        })
+    });
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    // tasks are spawned on the runtime
+    let _runtime = runtime.enter();
+
+    let mut criterion = Criterion::default().sample_size(10).configure_from_args();
+    bench_direct(&mut criterion, &runtime);
+    bench_batched(&mut criterion, &runtime);
+    criterion.final_summary();
+}
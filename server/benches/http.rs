@@ -0,0 +1,111 @@
+//! Load test of the REST server, reporting packets over HTTP/1.1 with a
+//!  connection per request, over kept-alive HTTP/1.1 connections, and
+//!  multiplexed on an HTTP/2 connection
+//!
+//! The server is bound with the HTTP settings of the default configuration
+//!  and answers each report after a fixed processing time, as the handlers
+//!  do once the packet is cached.
+
+use axum::body::Body;
+use axum::http::Request;
+use axum::routing::post;
+use axum::Router;
+use criterion::Criterion;
+use futures::stream::{self, StreamExt};
+use hyper::client::HttpConnector;
+use hyper::Client;
+use std::net::SocketAddr;
+use std::time::Duration;
+use svc_telemetry::rest::server::bind;
+use svc_telemetry::Config;
+
+/// Server time of a report
+const PROCESSING_TIME: Duration = Duration::from_micros(200);
+
+/// Reports per iteration
+const REPORTS: usize = 2_000;
+
+/// Reports in flight at once, as from as many receivers
+const CONCURRENCY: usize = 100;
+
+/// Starts the server, returning its address
+fn serve(runtime: &tokio::runtime::Runtime) -> SocketAddr {
+    let app = Router::new().route(
+        "/telemetry/netrid",
+        post(|body: axum::body::Bytes| async move {
+            tokio::time::sleep(PROCESSING_TIME).await;
+            body.len().to_string()
+        }),
+    );
+
+    // the listener is registered with the runtime
+    let _runtime = runtime.enter();
+    let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+    let server = bind(&addr, &Config::default())
+        .unwrap()
+        .serve(app.into_make_service());
+
+    let addr = server.local_addr();
+    runtime.spawn(server);
+    addr
+}
+
+/// Sends the reports of an iteration
+async fn report(client: &Client<HttpConnector>, addr: SocketAddr) {
+    let uri = format!("http://{addr}/telemetry/netrid");
+    stream::iter(0..REPORTS)
+        .map(|_| {
+            let request = Request::post(&uri)
+                .body(Body::from(vec![0x02; 25]))
+                .unwrap();
+
+            client.request(request)
+        })
+        .buffer_unordered(CONCURRENCY)
+        .for_each(|response| async move {
+            assert!(response.unwrap().status().is_success());
+        })
+        .await;
+}
+
+/// Benchmarks the reports sent by a client
+fn bench_client(
+    c: &mut Criterion,
+    runtime: &tokio::runtime::Runtime,
+    addr: SocketAddr,
+    name: &str,
+    client: Client<HttpConnector>,
+) {
+    c.bench_function(name, |b| b.iter(|| runtime.block_on(report(&client, addr))));
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let addr = serve(&runtime);
+
+    let mut criterion = Criterion::default().sample_size(10).configure_from_args();
+
+    // a handshake for each report
+    let client = Client::builder().pool_max_idle_per_host(0).build_http();
+    bench_client(
+        &mut criterion,
+        &runtime,
+        addr,
+        "http1_connection_per_request",
+        client,
+    );
+
+    // a connection for each report in flight, reused by the following ones
+    let client = Client::builder().build_http();
+    bench_client(&mut criterion, &runtime, addr, "http1_keep_alive", client);
+
+    // a single connection
+    let client = Client::builder().http2_only(true).build_http();
+    bench_client(&mut criterion, &runtime, addr, "http2", client);
+
+    criterion.final_summary();
+}
@@ -0,0 +1,69 @@
+//! Alerts on the loss of C2 links of aircraft
+
+use super::envelope::TelemetryEnvelope;
+use crate::clock::SharedClock;
+use crate::msg::c2::{C2LinkLoss, SharedC2Links};
+use crate::stats::{Dependency, Stats};
+
+/// Interval between checks for aircraft which stopped reporting their link
+const C2_LINK_CHECK_INTERVAL_MS: u64 = 1000;
+
+/// Publishes the loss of a C2 link to the alert queue
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need rabbitmq backend to test
+pub async fn publish_loss(mq_channel: &super::MqChannel, stats: &Stats, loss: &C2LinkLoss) {
+    amqp_warn!(
+        "C2 link of {} lost ({:?}), last reported at {}.",
+        loss.identifier,
+        loss.cause,
+        loss.last_report
+    );
+
+    let Ok(msg) = serde_json::to_vec(&TelemetryEnvelope::new(loss)) else {
+        amqp_warn!("could not serialize C2 link loss.");
+        return;
+    };
+
+    let _ = mq_channel
+        .basic_publish(
+            super::EXCHANGE_NAME_TELEMETRY,
+            super::ROUTING_KEY_ALERT,
+            lapin::options::BasicPublishOptions::default(),
+            &msg,
+            lapin::BasicProperties::default(),
+        )
+        .await
+        .map_err(|e| {
+            amqp_warn!("could not publish C2 link loss: {e}.");
+            stats.record_error(Dependency::Amqp);
+        });
+}
+
+/// Periodically alerts on aircraft which stopped reporting their C2 link
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need rabbitmq backend to test
+pub async fn c2_link_loop(
+    links: SharedC2Links,
+    mq_channel: super::MqChannel,
+    stats: Stats,
+    clock: SharedClock,
+) {
+    amqp_info!("checking C2 links every {C2_LINK_CHECK_INTERVAL_MS} ms.");
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_millis(C2_LINK_CHECK_INTERVAL_MS));
+    loop {
+        interval.tick().await;
+
+        let losses = match links.lock() {
+            Ok(mut links) => links.expire(clock.now()),
+            Err(e) => {
+                amqp_error!("could not lock C2 links: {e}");
+                continue;
+            }
+        };
+
+        for loss in losses {
+            publish_loss(&mq_channel, &stats, &loss).await;
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Publishes periodic summaries of the coverage of the receivers
+
+use super::envelope::TelemetryEnvelope;
+use crate::clock::SharedClock;
+use crate::msg::coverage::{CoverageSummary, SharedCoverage};
+use crate::stats::{Dependency, Stats};
+
+/// Publishes the coverage summary of a receiver
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need rabbitmq backend to test
+async fn publish_summary(mq_channel: &super::MqChannel, stats: &Stats, summary: &CoverageSummary) {
+    let Ok(msg) = serde_json::to_vec(&TelemetryEnvelope::new(summary)) else {
+        amqp_warn!("could not serialize coverage summary.");
+        return;
+    };
+
+    let _ = mq_channel
+        .basic_publish(
+            super::EXCHANGE_NAME_TELEMETRY,
+            super::ROUTING_KEY_COVERAGE,
+            lapin::options::BasicPublishOptions::default(),
+            &msg,
+            lapin::BasicProperties::default(),
+        )
+        .await
+        .map_err(|e| {
+            amqp_warn!("could not publish coverage summary: {e}.");
+            stats.record_error(Dependency::Amqp);
+        });
+}
+
+/// Periodically drops cells not observed recently and publishes the
+///  coverage of each receiver
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need rabbitmq backend to test
+pub async fn coverage_loop(
+    coverage: SharedCoverage,
+    mq_channel: super::MqChannel,
+    stats: Stats,
+    interval_ms: u32,
+    clock: SharedClock,
+) {
+    amqp_info!("publishing coverage summaries every {interval_ms} ms.");
+    let mut interval = tokio::time::interval(std::time::Duration::from_millis(interval_ms as u64));
+    loop {
+        interval.tick().await;
+
+        let now = clock.now();
+        let summaries = match coverage.lock() {
+            Ok(mut coverage) => {
+                coverage.expire(now);
+                coverage.summaries(now)
+            }
+            Err(e) => {
+                amqp_error!("could not lock coverage map: {e}");
+                continue;
+            }
+        };
+
+        for summary in summaries {
+            publish_summary(&mq_channel, &stats, &summary).await;
+        }
+    }
+}
@@ -0,0 +1,330 @@
+/// Telemetry Envelope
+///  Wraps every JSON item published to the telemetry exchange.
+///  This file is also included by the REST client for consumers.
+use serde::{Deserialize, Serialize};
+
+/// Current version of the [`TelemetryEnvelope`] format
+pub const TELEMETRY_ENVELOPE_VERSION: u8 = 1;
+
+/// How the packet carrying an item was received, as declared by the
+///  receiver
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct SignalMetadata {
+    /// Latitude of the receiver in degrees
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receiver_latitude: Option<f64>,
+
+    /// Longitude of the receiver in degrees
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receiver_longitude: Option<f64>,
+
+    /// Received signal strength in dBm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rssi_dbm: Option<f32>,
+
+    /// Signal to noise ratio in dB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snr_db: Option<f32>,
+
+    /// MAC address of the broadcaster, for Remote ID messages forwarded
+    ///  with their Bluetooth or WiFi NAN framing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broadcaster_mac: Option<MacAddress>,
+}
+
+/// MAC address, most significant byte first
+///  Written as six colon separated pairs of hex digits, e.g.
+///  `0a:1b:2c:3d:4e:5f`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MacAddress(pub [u8; 6]);
+
+impl std::fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+impl std::str::FromStr for MacAddress {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut address = [0; 6];
+        let mut octets = s.split(':');
+        for byte in address.iter_mut() {
+            let octet = octets.next().filter(|octet| octet.len() == 2);
+            *byte = octet
+                .and_then(|octet| u8::from_str_radix(octet, 16).ok())
+                .ok_or("invalid MAC address")?;
+        }
+
+        match octets.next() {
+            None => Ok(MacAddress(address)),
+            Some(_) => Err("invalid MAC address"),
+        }
+    }
+}
+
+impl Serialize for MacAddress {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MacAddress {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        address.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Accuracy of a position, as 95% bounds of its error
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct PositionAccuracy {
+    /// Horizontal accuracy in meters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub horizontal_meters: Option<f32>,
+
+    /// Vertical accuracy in meters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vertical_meters: Option<f32>,
+}
+
+impl PositionAccuracy {
+    /// Accuracy of the given 95% bounds, `None` if both are unknown
+    pub fn from_bounds(
+        horizontal_meters: Option<f32>,
+        vertical_meters: Option<f32>,
+    ) -> Option<Self> {
+        let accuracy = PositionAccuracy {
+            horizontal_meters,
+            vertical_meters,
+        };
+
+        (accuracy != PositionAccuracy::default()).then_some(accuracy)
+    }
+}
+
+/// Time spent by svc-telemetry on the packet carrying an item, to tell
+///  its share of the delay of the item from the share of the consumers
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProcessingLatency {
+    /// When the packet was received, in milliseconds since the Unix epoch.
+    ///  Packets queued in `ingest` mode keep the time of their ingest.
+    pub received_ms: i64,
+
+    /// Microseconds from the reception of the packet to the decoding of
+    ///  the item
+    pub decode_us: u64,
+
+    /// Microseconds from the decoding of the item to its publication
+    pub publish_us: u64,
+}
+
+/// Wrapper of telemetry items published to the message queue
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TelemetryEnvelope<T> {
+    /// Version of the envelope format
+    pub version: u8,
+
+    /// If the item was extrapolated by svc-telemetry
+    ///  rather than reported by the aircraft
+    #[serde(default)]
+    pub predicted: bool,
+
+    /// Raw ADS-B emitter category of the aircraft (e.g. `A3`),
+    ///  for identification items received over ADS-B
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emitter_category: Option<String>,
+
+    /// Reception of the packet, if the receiver declared it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<SignalMetadata>,
+
+    /// Accuracy of position items, if the aircraft reported it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accuracy: Option<PositionAccuracy>,
+
+    /// Time spent on the item, for items decoded from a received packet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<ProcessingLatency>,
+
+    /// Remote ID protocol version of the message carrying the item
+    ///  (0 for ASTM F3411-19, 1 for F3411-20, 2 for F3411-22a)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<u8>,
+
+    /// The telemetry item
+    pub data: T,
+}
+
+impl<T> TelemetryEnvelope<T> {
+    /// Wrap an item reported by an aircraft
+    pub fn new(data: T) -> Self {
+        TelemetryEnvelope {
+            version: TELEMETRY_ENVELOPE_VERSION,
+            predicted: false,
+            emitter_category: None,
+            signal: None,
+            accuracy: None,
+            latency: None,
+            protocol_version: None,
+            data,
+        }
+    }
+
+    /// Wrap an item extrapolated by svc-telemetry
+    pub fn predicted(data: T) -> Self {
+        TelemetryEnvelope {
+            version: TELEMETRY_ENVELOPE_VERSION,
+            predicted: true,
+            emitter_category: None,
+            signal: None,
+            accuracy: None,
+            latency: None,
+            protocol_version: None,
+            data,
+        }
+    }
+
+    /// Attach the raw ADS-B emitter category of the aircraft
+    pub fn with_emitter_category(mut self, emitter_category: String) -> Self {
+        self.emitter_category = Some(emitter_category);
+        self
+    }
+
+    /// Attach the reception of the packet carrying the item
+    pub fn with_signal(mut self, signal: SignalMetadata) -> Self {
+        self.signal = Some(signal);
+        self
+    }
+
+    /// Attach the accuracy of a position item
+    pub fn with_accuracy(mut self, accuracy: PositionAccuracy) -> Self {
+        self.accuracy = Some(accuracy);
+        self
+    }
+
+    /// Attach the time spent on the item
+    pub fn with_latency(mut self, latency: ProcessingLatency) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Attach the Remote ID protocol version of the message carrying the
+    ///  item
+    pub fn with_protocol_version(mut self, protocol_version: u8) -> Self {
+        self.protocol_version = Some(protocol_version);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accuracy_from_bounds() {
+        assert_eq!(PositionAccuracy::from_bounds(None, None), None);
+        assert_eq!(
+            PositionAccuracy::from_bounds(Some(30.), Some(45.)),
+            Some(PositionAccuracy {
+                horizontal_meters: Some(30.),
+                vertical_meters: Some(45.),
+            })
+        );
+        assert_eq!(
+            PositionAccuracy::from_bounds(None, Some(45.)).and_then(|a| a.horizontal_meters),
+            None
+        );
+    }
+
+    #[test]
+    fn test_envelope_serde() {
+        let envelope = TelemetryEnvelope::predicted(5_u32);
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert_eq!(json, r#"{"version":1,"predicted":true,"data":5}"#);
+
+        // older producers didn't set the predicted flag
+        let envelope: TelemetryEnvelope<u32> =
+            serde_json::from_str(r#"{"version":1,"data":5}"#).unwrap();
+        assert_eq!(envelope, TelemetryEnvelope::new(5));
+
+        let envelope = TelemetryEnvelope::new(5_u32).with_emitter_category("A3".to_string());
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"predicted":false,"emitter_category":"A3","data":5}"#
+        );
+
+        let signal = SignalMetadata {
+            rssi_dbm: Some(-72.5),
+            ..Default::default()
+        };
+        let envelope = TelemetryEnvelope::new(5_u32).with_signal(signal);
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"predicted":false,"signal":{"rssi_dbm":-72.5},"data":5}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<TelemetryEnvelope<u32>>(&json).unwrap(),
+            envelope
+        );
+
+        let accuracy = PositionAccuracy {
+            horizontal_meters: Some(30.0),
+            vertical_meters: None,
+        };
+        let envelope = TelemetryEnvelope::new(5_u32).with_accuracy(accuracy);
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"predicted":false,"accuracy":{"horizontal_meters":30.0},"data":5}"#
+        );
+
+        let latency = ProcessingLatency {
+            received_ms: 1_700_000_000_000,
+            decode_us: 250,
+            publish_us: 1200,
+        };
+        let envelope = TelemetryEnvelope::new(5_u32).with_latency(latency);
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"predicted":false,"latency":{"received_ms":1700000000000,"decode_us":250,"publish_us":1200},"data":5}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<TelemetryEnvelope<u32>>(&json).unwrap(),
+            envelope
+        );
+    }
+
+    #[test]
+    fn test_mac_address() {
+        let address = MacAddress([0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f]);
+        assert_eq!(address.to_string(), "0a:1b:2c:3d:4e:5f");
+        assert_eq!("0A:1b:2c:3d:4e:5f".parse(), Ok(address));
+        for invalid in [
+            "",
+            "0a:1b:2c:3d:4e",
+            "0a:1b:2c:3d:4e:5f:60",
+            "0a:1b:2c:3d:4e:5",
+            "0a-1b-2c-3d-4e-5f",
+        ] {
+            assert!(invalid.parse::<MacAddress>().is_err(), "{invalid}");
+        }
+
+        let signal = SignalMetadata {
+            broadcaster_mac: Some(address),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&signal).unwrap();
+        assert_eq!(json, r#"{"broadcaster_mac":"0a:1b:2c:3d:4e:5f"}"#);
+        assert_eq!(
+            serde_json::from_str::<SignalMetadata>(&json).unwrap(),
+            signal
+        );
+        assert!(serde_json::from_str::<SignalMetadata>(r#"{"broadcaster_mac":"0a"}"#).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+//! In-process replacement of the RabbitMQ exchanges, for running without
+//!  backends
+//!
+//! Published messages are routed to capped in-memory queues through the
+//!  same bindings as the RabbitMQ queues, so they can be read back through
+//!  the development routes instead of a RabbitMQ consumer.
+
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+
+/// Queues hold up to this many messages, the oldest are dropped first
+pub const MEMORY_QUEUE_MAX_LEN: usize = 1000;
+
+/// Binding of a queue to an exchange
+#[derive(Debug, Clone)]
+struct Binding {
+    /// Exchange routing messages to the queue
+    exchange: String,
+
+    /// Topic pattern of the routing keys of the messages
+    pattern: String,
+
+    /// Queue receiving the messages
+    queue: String,
+}
+
+/// Queues and their bindings
+#[derive(Debug, Default)]
+struct Broker {
+    /// Bindings of the queues
+    bindings: Vec<Binding>,
+
+    /// Messages of each queue, oldest first
+    queues: HashMap<String, VecDeque<Vec<u8>>>,
+}
+
+/// If a routing key matches a topic pattern, where `*` matches
+///  one word and `#` zero or more words, separated by dots
+pub fn topic_matches(pattern: &str, routing_key: &str) -> bool {
+    /// Matches the remaining words
+    fn matches(pattern: &[&str], words: &[&str]) -> bool {
+        match (pattern.split_first(), words.split_first()) {
+            (None, None) => true,
+            (Some((&"#", rest)), _) => {
+                matches(rest, words) || (!words.is_empty() && matches(pattern, &words[1..]))
+            }
+            (Some((&"*", rest)), Some((_, words))) => matches(rest, words),
+            (Some((expected, rest)), Some((word, words))) => {
+                expected == word && matches(rest, words)
+            }
+            _ => false,
+        }
+    }
+
+    let pattern: Vec<&str> = pattern.split('.').collect();
+    let words: Vec<&str> = routing_key.split('.').collect();
+    matches(&pattern, &words)
+}
+
+/// Body of a message as JSON: the envelope, or the hex encoded packet
+pub fn message_json(message: &[u8]) -> Value {
+    serde_json::from_slice(message).unwrap_or_else(|_| Value::String(hex::encode(message)))
+}
+
+/// Channel publishing to in-memory queues, used like a [`lapin::Channel`]
+#[derive(Debug, Clone, Default)]
+pub struct MemoryChannel {
+    /// Queues shared by the clones of the channel
+    broker: Arc<Mutex<Broker>>,
+}
+
+/// Channel of the process, see [`MemoryChannel::shared`]
+static CHANNEL: OnceLock<MemoryChannel> = OnceLock::new();
+
+impl MemoryChannel {
+    /// Channel shared by the servers of the process, like a RabbitMQ node
+    pub fn shared() -> Self {
+        CHANNEL.get_or_init(Default::default).clone()
+    }
+
+    /// Lock the queues
+    fn broker(&self) -> MutexGuard<'_, Broker> {
+        self.broker.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Declare a queue, if needed, and bind it to an exchange
+    pub fn bind(&self, queue: &str, exchange: &str, pattern: &str) {
+        let mut broker = self.broker();
+        broker.queues.entry(queue.to_string()).or_default();
+        let exists = broker.bindings.iter().any(|binding| {
+            binding.queue == queue && binding.exchange == exchange && binding.pattern == pattern
+        });
+
+        if !exists {
+            broker.bindings.push(Binding {
+                exchange: exchange.to_string(),
+                pattern: pattern.to_string(),
+                queue: queue.to_string(),
+            });
+        }
+    }
+
+    /// Publish a message to the queues bound to the exchange with a
+    ///  matching routing key. Messages matching no binding are dropped.
+    pub async fn basic_publish(
+        &self,
+        exchange: &str,
+        routing_key: &str,
+        _options: lapin::options::BasicPublishOptions,
+        payload: &[u8],
+        _properties: lapin::BasicProperties,
+    ) -> Result<(), lapin::Error> {
+        let mut broker = self.broker();
+        let queues: Vec<String> = broker
+            .bindings
+            .iter()
+            .filter(|binding| {
+                binding.exchange == exchange && topic_matches(&binding.pattern, routing_key)
+            })
+            .map(|binding| binding.queue.clone())
+            .collect();
+
+        for queue in queues {
+            let messages = broker.queues.entry(queue).or_default();
+            messages.push_back(payload.to_vec());
+            if messages.len() > MEMORY_QUEUE_MAX_LEN {
+                messages.pop_front();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Number of messages in a queue, none if the queue wasn't declared
+    pub fn message_count(&self, queue: &str) -> Option<usize> {
+        self.broker().queues.get(queue).map(VecDeque::len)
+    }
+
+    /// Take the messages of a queue, oldest first. None if the queue
+    ///  wasn't declared.
+    pub fn drain(&self, queue: &str) -> Option<Vec<Vec<u8>>> {
+        self.broker()
+            .queues
+            .get_mut(queue)
+            .map(|messages| messages.drain(..).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_matches() {
+        assert!(topic_matches("netrid:pos", "netrid:pos"));
+        assert!(!topic_matches("netrid:pos", "netrid:vel"));
+        assert!(topic_matches("#", "adsb"));
+        assert!(topic_matches("#", "a.b.c"));
+        assert!(topic_matches("a.*", "a.b"));
+        assert!(!topic_matches("a.*", "a.b.c"));
+        assert!(!topic_matches("a.*", "a"));
+        assert!(topic_matches("a.#", "a"));
+        assert!(topic_matches("a.#.c", "a.b.b.c"));
+        assert!(!topic_matches("a.#.c", "a.b.d"));
+    }
+
+    #[test]
+    fn test_message_json() {
+        assert_eq!(
+            message_json(br#"{"version":1}"#),
+            serde_json::json!({"version": 1})
+        );
+        assert_eq!(message_json(&[0x8d, 0x48]), Value::String("8d48".into()));
+    }
+
+    #[tokio::test]
+    async fn test_publish() {
+        let channel = MemoryChannel::default();
+        channel.bind("netrid_pos", "telemetry", "netrid:pos");
+        channel.bind("netrid_pos", "telemetry", "netrid:pos");
+        channel.bind("raw", "raw", "#");
+
+        let publish = |exchange: &'static str, key: &'static str, payload: &'static [u8]| {
+            let channel = channel.clone();
+            async move {
+                channel
+                    .basic_publish(
+                        exchange,
+                        key,
+                        Default::default(),
+                        payload,
+                        Default::default(),
+                    )
+                    .await
+                    .unwrap()
+            }
+        };
+
+        publish("telemetry", "netrid:pos", b"1").await;
+        publish("telemetry", "netrid:vel", b"2").await;
+        publish("raw", "netrid", b"3").await;
+        publish("raw", "adsb", b"4").await;
+
+        assert_eq!(channel.drain("netrid_pos"), Some(vec![b"1".to_vec()]));
+        assert_eq!(channel.drain("netrid_pos"), Some(vec![]));
+        assert_eq!(
+            channel.drain("raw"),
+            Some(vec![b"3".to_vec(), b"4".to_vec()])
+        );
+        assert_eq!(channel.drain("netrid_vel"), None);
+
+        // full queues drop the oldest messages
+        for _ in 0..MEMORY_QUEUE_MAX_LEN {
+            publish("telemetry", "netrid:pos", b"old").await;
+        }
+        publish("telemetry", "netrid:pos", b"new").await;
+        let messages = channel.drain("netrid_pos").unwrap();
+        assert_eq!(messages.len(), MEMORY_QUEUE_MAX_LEN);
+        assert_eq!(messages.last().unwrap(), b"new");
+    }
+}
@@ -2,19 +2,63 @@
 
 #[macro_use]
 pub mod macros;
+pub mod c2;
+pub mod coverage;
+pub mod mirror;
 pub mod pool;
+pub mod predict;
+pub mod queue;
+pub mod raw;
+pub mod system;
+
+/// Wrapper of published telemetry items
+pub mod envelope;
+
+#[cfg(any(test, feature = "memory_backends"))]
+pub mod memory;
+
 use crate::config::Config;
 use snafu::prelude::Snafu;
 
 /// Name of the AMQP exchange for telemetry messages
 pub const EXCHANGE_NAME_TELEMETRY: &str = "telemetry";
 
+/// Name of the AMQP exchange for packets as received
+pub const EXCHANGE_NAME_RAW: &str = "raw";
+
+/// Name of the AMQP queue for packets as received, of any protocol
+pub const QUEUE_NAME_RAW: &str = "raw";
+
+/// Routing key for ADSB packets as received
+pub const ROUTING_KEY_RAW_ADSB: &str = "adsb";
+
+/// Routing key for NETRID packets as received
+pub const ROUTING_KEY_RAW_NETRID: &str = "netrid";
+
 /// Name of the AMQP queue for ADSB messages
 pub const QUEUE_NAME_ADSB: &str = "adsb";
 
 /// Routing key for ADSB messages
 pub const ROUTING_KEY_ADSB: &str = "adsb";
 
+/// Name of the AMQP queue for ADSB identification messages
+pub const QUEUE_NAME_ADSB_ID: &str = "adsb_id";
+
+/// Routing key for ADSB identification messages
+pub const ROUTING_KEY_ADSB_ID: &str = "adsb:id";
+
+/// Name of the AMQP queue for ADSB aircraft callsigns and squawks
+pub const QUEUE_NAME_ADSB_ENRICHMENT: &str = "adsb_enrichment";
+
+/// Routing key for ADSB aircraft callsigns and squawks
+pub const ROUTING_KEY_ADSB_ENRICHMENT: &str = "adsb:enrichment";
+
+/// Name of the AMQP queue for combined ADSB aircraft states
+pub const QUEUE_NAME_ADSB_STATE: &str = "adsb_state";
+
+/// Routing key for combined ADSB aircraft states
+pub const ROUTING_KEY_ADSB_STATE: &str = "adsb:state";
+
 /// Name of the AMQP queue for NETRID identification messages
 pub const QUEUE_NAME_NETRID_ID: &str = "netrid_id";
 
@@ -33,6 +77,86 @@ pub const ROUTING_KEY_NETRID_POSITION: &str = "netrid:pos";
 /// Routing key for NETRID Velocity messages
 pub const ROUTING_KEY_NETRID_VELOCITY: &str = "netrid:vel";
 
+/// Name of the AMQP queue for combined NETRID states
+pub const QUEUE_NAME_NETRID_STATE: &str = "netrid_state";
+
+/// Routing key for combined NETRID states
+pub const ROUTING_KEY_NETRID_STATE: &str = "netrid:state";
+
+/// Name of the AMQP queue for predicted position messages
+pub const QUEUE_NAME_PREDICTED_POSITION: &str = "predicted_pos";
+
+/// Routing key for predicted position messages
+pub const ROUTING_KEY_PREDICTED_POSITION: &str = "predicted:pos";
+
+/// Name of the AMQP queue for watchlist alerts
+pub const QUEUE_NAME_WATCHLIST: &str = "watchlist";
+
+/// Routing key for watchlist alerts
+pub const ROUTING_KEY_WATCHLIST: &str = "telemetry:watchlist";
+
+/// Name of the AMQP queue for emergency alerts
+pub const QUEUE_NAME_ALERT: &str = "alert";
+
+/// Routing key for emergency alerts
+pub const ROUTING_KEY_ALERT: &str = "telemetry:alert";
+
+/// Name of the AMQP queue for scrubbed NETRID operator messages
+pub const QUEUE_NAME_NETRID_OPERATOR: &str = "netrid_operator";
+
+/// Routing key for scrubbed NETRID operator messages
+pub const ROUTING_KEY_NETRID_OPERATOR: &str = "netrid:operator";
+
+/// Name of the AMQP queue for unscrubbed NETRID operator messages,
+///  reserved for authorized consumers
+pub const QUEUE_NAME_NETRID_OPERATOR_FULL: &str = "netrid_operator_full";
+
+/// Routing key for unscrubbed NETRID operator messages
+pub const ROUTING_KEY_NETRID_OPERATOR_FULL: &str = "netrid:operator:full";
+
+/// Name of the AMQP queue for vehicle health reports
+pub const QUEUE_NAME_VEHICLE_HEALTH: &str = "vehicle_health";
+
+/// Routing key for vehicle health reports
+pub const ROUTING_KEY_VEHICLE_HEALTH: &str = "vehicle:health";
+
+/// Name of the AMQP queue for C2 link states
+pub const QUEUE_NAME_C2_STATUS: &str = "c2_status";
+
+/// Routing key for C2 link states
+pub const ROUTING_KEY_C2_STATUS: &str = "c2:status";
+
+/// Name of the AMQP queue for ground station weather
+pub const QUEUE_NAME_WEATHER: &str = "weather";
+
+/// Routing key for ground station weather
+pub const ROUTING_KEY_WEATHER: &str = "weather";
+
+/// Name of the AMQP queue for receiver coverage summaries
+pub const QUEUE_NAME_COVERAGE: &str = "coverage";
+
+/// Routing key for receiver coverage summaries
+pub const ROUTING_KEY_COVERAGE: &str = "coverage:summary";
+
+/// Name of the AMQP queue for anomalies found in the telemetry
+pub const QUEUE_NAME_ANOMALY: &str = "anomaly";
+
+/// Routing key for anomalies found in the telemetry
+pub const ROUTING_KEY_ANOMALY: &str = "telemetry:anomaly";
+
+/// Name of the AMQP queue for positions outside the corridor of their
+///  flight plan
+pub const QUEUE_NAME_NONCONFORMANCE: &str = "nonconformance";
+
+/// Routing key for positions outside the corridor of their flight plan
+pub const ROUTING_KEY_NONCONFORMANCE: &str = "telemetry:nonconformance";
+
+/// Name of the AMQP queue for health state changes of the dependencies
+pub const QUEUE_NAME_SYSTEM: &str = "system";
+
+/// Routing key for health state changes of the dependencies
+pub const ROUTING_KEY_SYSTEM: &str = "telemetry:system";
+
 /// Custom Error type for MQ errors
 #[derive(Debug, Snafu, Clone, Copy, PartialEq)]
 pub enum AMQPError {
@@ -65,11 +189,72 @@ pub enum AMQPError {
     CouldNotDeclareExchange,
 }
 
-/// Initializes the AMQP connection. Creates the telemetry exchange and queues.
-#[cfg(not(test))]
+impl AMQPError {
+    /// Stable code of the error, for logs and statistics
+    pub fn code(&self) -> &'static str {
+        match self {
+            AMQPError::CouldNotPublish => "amqp_publish",
+            AMQPError::CouldNotConnect => "amqp_connect",
+            AMQPError::MissingConfiguration => "amqp_config",
+            AMQPError::CouldNotCreateChannel => "amqp_channel",
+            AMQPError::CouldNotDeclareQueue => "amqp_declare_queue",
+            AMQPError::CouldNotBindQueue => "amqp_bind_queue",
+            AMQPError::CouldNotDeclareExchange => "amqp_declare_exchange",
+        }
+    }
+}
+
+/// Channel publishing telemetry, copied to the mirrors
+#[cfg(not(any(test, feature = "memory_backends")))]
+pub type MqChannel = mirror::MirroredChannel;
+
+/// Channel publishing telemetry, to in-memory queues
+#[cfg(any(test, feature = "memory_backends"))]
+pub type MqChannel = memory::MemoryChannel;
+
+/// Queues bound to the telemetry exchange, with their routing key
+pub fn telemetry_queues(config: &Config) -> Vec<(&'static str, &'static str)> {
+    let mut queues = vec![
+        (QUEUE_NAME_ADSB, ROUTING_KEY_ADSB),
+        (QUEUE_NAME_ADSB_ID, ROUTING_KEY_ADSB_ID),
+        (QUEUE_NAME_ADSB_ENRICHMENT, ROUTING_KEY_ADSB_ENRICHMENT),
+        (QUEUE_NAME_ADSB_STATE, ROUTING_KEY_ADSB_STATE),
+        (QUEUE_NAME_NETRID_ID, ROUTING_KEY_NETRID_ID),
+        (QUEUE_NAME_NETRID_POSITION, ROUTING_KEY_NETRID_POSITION),
+        (QUEUE_NAME_NETRID_VELOCITY, ROUTING_KEY_NETRID_VELOCITY),
+        (QUEUE_NAME_NETRID_STATE, ROUTING_KEY_NETRID_STATE),
+        (QUEUE_NAME_NETRID_OPERATOR, ROUTING_KEY_NETRID_OPERATOR),
+        (
+            QUEUE_NAME_PREDICTED_POSITION,
+            ROUTING_KEY_PREDICTED_POSITION,
+        ),
+        (QUEUE_NAME_WATCHLIST, ROUTING_KEY_WATCHLIST),
+        (QUEUE_NAME_ALERT, ROUTING_KEY_ALERT),
+        (QUEUE_NAME_VEHICLE_HEALTH, ROUTING_KEY_VEHICLE_HEALTH),
+        (QUEUE_NAME_C2_STATUS, ROUTING_KEY_C2_STATUS),
+        (QUEUE_NAME_WEATHER, ROUTING_KEY_WEATHER),
+        (QUEUE_NAME_COVERAGE, ROUTING_KEY_COVERAGE),
+        (QUEUE_NAME_ANOMALY, ROUTING_KEY_ANOMALY),
+        (QUEUE_NAME_NONCONFORMANCE, ROUTING_KEY_NONCONFORMANCE),
+        (QUEUE_NAME_SYSTEM, ROUTING_KEY_SYSTEM),
+    ];
+
+    if config.privacy_full_fidelity_enabled {
+        queues.push((
+            QUEUE_NAME_NETRID_OPERATOR_FULL,
+            ROUTING_KEY_NETRID_OPERATOR_FULL,
+        ));
+    }
+
+    queues
+}
+
+/// Initializes the AMQP connection. Creates the telemetry exchange and queues,
+///  and starts copying the published messages to the mirrors.
+#[cfg(not(any(test, feature = "memory_backends")))]
 #[cfg(not(tarpaulin_include))]
 // no_coverage: (Rnever) need rabbitmq backend running, integration tests
-pub async fn init_mq(config: Config) -> Result<lapin::Channel, AMQPError> {
+pub async fn init_mq(config: Config) -> Result<MqChannel, AMQPError> {
     // Establish connection to RabbitMQ node
     let pool = pool::AMQPPool::new(config.clone())?;
     let amqp_connection = pool.get_connection().await?;
@@ -84,6 +269,16 @@ pub async fn init_mq(config: Config) -> Result<lapin::Channel, AMQPError> {
         AMQPError::CouldNotCreateChannel
     })?;
 
+    declare(&amqp_channel, &config).await?;
+    Ok(mirror::MirroredChannel::new(amqp_channel, &config))
+}
+
+/// Declares the telemetry exchange and queues, and the exchange of packets
+///  as received if enabled
+#[cfg(not(any(test, feature = "memory_backends")))]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (Rnever) need rabbitmq backend running, integration tests
+async fn declare(amqp_channel: &lapin::Channel, config: &Config) -> Result<(), AMQPError> {
     //
     // Declare a topic exchange
     //
@@ -105,20 +300,15 @@ pub async fn init_mq(config: Config) -> Result<lapin::Channel, AMQPError> {
     //
     // Declare and Bind Queues
     //
-    let queues = [
-        (QUEUE_NAME_ADSB, ROUTING_KEY_ADSB),
-        (QUEUE_NAME_NETRID_ID, ROUTING_KEY_NETRID_ID),
-        (QUEUE_NAME_NETRID_POSITION, ROUTING_KEY_NETRID_POSITION),
-        (QUEUE_NAME_NETRID_VELOCITY, ROUTING_KEY_NETRID_VELOCITY),
-    ];
-
+    let limits = queue::QueueLimits::new(config);
+    let queues = telemetry_queues(config);
     for (queue, routing_key) in queues.iter() {
         amqp_info!("creating queue '{queue}'...");
         amqp_channel
             .queue_declare(
                 queue,
                 lapin::options::QueueDeclareOptions::default(),
-                lapin::types::FieldTable::default(),
+                limits.arguments(),
             )
             .await
             .map_err(|e| {
@@ -144,13 +334,128 @@ pub async fn init_mq(config: Config) -> Result<lapin::Channel, AMQPError> {
             })?;
     }
 
-    Ok(amqp_channel)
+    if config.raw_exchange_enabled {
+        declare_raw(amqp_channel, &limits).await?;
+    }
+
+    Ok(())
 }
 
-/// Initializes the AMQP connection. Creates the telemetry exchange and queues.
-#[cfg(test)]
+/// Declares the exchange of packets as received, and a queue
+///  receiving all of them
+#[cfg(not(any(test, feature = "memory_backends")))]
 #[cfg(not(tarpaulin_include))]
-// no_coverage: (Rnever) this is a stub
-pub async fn init_mq(_config: Config) -> Result<(), AMQPError> {
-    Ok(())
+// no_coverage: (R5) need rabbitmq backend to test
+async fn declare_raw(
+    amqp_channel: &lapin::Channel,
+    limits: &queue::QueueLimits,
+) -> Result<(), AMQPError> {
+    amqp_info!("declaring exchange '{EXCHANGE_NAME_RAW}'...");
+    amqp_channel
+        .exchange_declare(
+            EXCHANGE_NAME_RAW,
+            lapin::ExchangeKind::Topic,
+            lapin::options::ExchangeDeclareOptions::default(),
+            lapin::types::FieldTable::default(),
+        )
+        .await
+        .map_err(|e| {
+            amqp_error!("could not declare exchange '{EXCHANGE_NAME_RAW}'.");
+            amqp_debug!("error: {:?}", e);
+            AMQPError::CouldNotDeclareExchange
+        })?;
+
+    amqp_info!("creating queue '{QUEUE_NAME_RAW}'...");
+    amqp_channel
+        .queue_declare(
+            QUEUE_NAME_RAW,
+            lapin::options::QueueDeclareOptions::default(),
+            limits.arguments(),
+        )
+        .await
+        .map_err(|e| {
+            amqp_error!("could not declare queue '{QUEUE_NAME_RAW}'.");
+            amqp_debug!("error: {:?}", e);
+            AMQPError::CouldNotDeclareQueue
+        })?;
+
+    amqp_info!("binding queue '{QUEUE_NAME_RAW}' to exchange '{EXCHANGE_NAME_RAW}'...");
+    amqp_channel
+        .queue_bind(
+            QUEUE_NAME_RAW,
+            EXCHANGE_NAME_RAW,
+            "#",
+            lapin::options::QueueBindOptions::default(),
+            lapin::types::FieldTable::default(),
+        )
+        .await
+        .map_err(|e| {
+            amqp_error!("could not bind queue '{QUEUE_NAME_RAW}' to exchange.");
+            amqp_debug!("error: {:?}", e);
+            AMQPError::CouldNotBindQueue
+        })
+}
+
+/// Creates the telemetry queues in memory, and the queue of packets as
+///  received if enabled
+#[cfg(all(not(test), feature = "memory_backends"))]
+pub async fn init_mq(config: Config) -> Result<MqChannel, AMQPError> {
+    amqp_info!("holding queues in memory.");
+    if !mirror::parse_mirrors(&config.amqp_mirrors).is_empty() {
+        amqp_warn!("mirrors are not supported in memory, AMQP_MIRRORS ignored.");
+    }
+
+    if queue::QueueLimits::new(&config).is_bounded() {
+        amqp_warn!("queue bounds are not supported in memory, AMQP_QUEUE_* ignored.");
+    }
+
+    let channel = memory::MemoryChannel::shared();
+    for (queue, routing_key) in telemetry_queues(&config) {
+        channel.bind(queue, EXCHANGE_NAME_TELEMETRY, routing_key);
+    }
+
+    if config.raw_exchange_enabled {
+        channel.bind(QUEUE_NAME_RAW, EXCHANGE_NAME_RAW, "#");
+    }
+
+    Ok(channel)
+}
+
+/// Creates the telemetry queues and the queue of packets as received on
+///  a channel of its own, for the tests to drain
+#[cfg(test)]
+pub async fn init_mq(config: Config) -> Result<MqChannel, AMQPError> {
+    let channel = memory::MemoryChannel::default();
+    for (queue, routing_key) in telemetry_queues(&config) {
+        channel.bind(queue, EXCHANGE_NAME_TELEMETRY, routing_key);
+    }
+
+    channel.bind(QUEUE_NAME_RAW, EXCHANGE_NAME_RAW, "#");
+    Ok(channel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telemetry_queues() {
+        let mut config = Config {
+            privacy_full_fidelity_enabled: false,
+            ..Default::default()
+        };
+        let queues = telemetry_queues(&config);
+        assert!(queues.contains(&(QUEUE_NAME_NETRID_POSITION, ROUTING_KEY_NETRID_POSITION)));
+        assert!(!queues
+            .iter()
+            .any(|(queue, _)| *queue == QUEUE_NAME_NETRID_OPERATOR_FULL));
+
+        config.privacy_full_fidelity_enabled = true;
+        let full = telemetry_queues(&config);
+        assert_eq!(full.len(), queues.len() + 1);
+        assert!(full.contains(&(
+            QUEUE_NAME_NETRID_OPERATOR_FULL,
+            ROUTING_KEY_NETRID_OPERATOR_FULL
+        )));
+    }
 }
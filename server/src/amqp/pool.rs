@@ -1,7 +1,150 @@
 //! AMQP connection pool implementation
 
 use super::AMQPError;
-use deadpool_lapin::{Object, Pool, Runtime};
+use crate::tls::TlsFiles;
+use core::fmt::{Debug, Formatter};
+use deadpool::managed::{self, Metrics, RecycleError, RecycleResult};
+use deadpool::Runtime;
+use lapin::tcp::{HandshakeResult, OwnedIdentity, OwnedTLSConfig, TcpStream};
+use lapin::uri::{AMQPScheme, AMQPUri};
+use lapin::{Connection, ConnectionProperties};
+use std::time::Duration;
+
+/// Pool of AMQP connections
+type Pool = managed::Pool<Manager>;
+
+/// AMQP connection taken from the pool
+pub type Object = managed::Object<Manager>;
+
+/// Opens the connections of the pool, over TLS for an `amqps://` URL
+///
+/// Unlike the manager of `deadpool_lapin`, the name expected in the
+///  certificate of the server can differ from the host of the URL.
+pub struct Manager {
+    /// Address of the server
+    url: String,
+
+    /// Properties of each connection
+    properties: ConnectionProperties,
+
+    /// Certificate authorities trusted instead of the system ones, in PEM
+    cert_chain: Option<String>,
+
+    /// Client certificate and key as a PKCS#12 archive without password
+    identity: Option<Vec<u8>>,
+
+    /// Name expected in the certificate of the server, the host of the URL
+    ///  if none
+    server_name: Option<String>,
+}
+
+impl Debug for Manager {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Manager")
+            .field("url", &self.url)
+            .field("server_name", &self.server_name)
+            .finish()
+    }
+}
+
+impl Manager {
+    /// Manager of the connections to the configured server
+    fn new(config: &crate::config::Config, url: String) -> Result<Self, String> {
+        let files = TlsFiles::load(
+            &config.amqp_tls_ca_file,
+            &config.amqp_tls_cert_file,
+            &config.amqp_tls_key_file,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let cert_chain = files
+            .ca
+            .clone()
+            .map(String::from_utf8)
+            .transpose()
+            .map_err(|_| "certificate authorities must be in PEM".to_string())?;
+
+        let identity = files.client_pkcs12("").map_err(|e| e.to_string())?;
+        let server_name = Some(config.amqp_tls_server_name.trim())
+            .filter(|name| !name.is_empty())
+            .map(str::to_string);
+
+        Ok(Manager {
+            url,
+            properties: config.amqp.connection_properties.clone(),
+            cert_chain,
+            identity,
+            server_name,
+        })
+    }
+}
+
+/// Opens a TCP connection to the server of `uri`, with a TLS handshake
+///  expecting `server_name` for `amqps://`
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need rabbitmq backend to test
+// the result type is the one of the connectors of lapin
+#[allow(clippy::result_large_err)]
+fn connect(uri: &AMQPUri, server_name: &str, tls: OwnedTLSConfig) -> HandshakeResult {
+    let address = format!("{}:{}", uri.authority.host, uri.authority.port);
+    let stream = match uri.query.connection_timeout {
+        Some(timeout) => TcpStream::connect_timeout(address, Duration::from_millis(timeout)),
+        None => TcpStream::connect(address),
+    }?;
+
+    let stream = match uri.scheme {
+        AMQPScheme::AMQP => stream,
+        AMQPScheme::AMQPS => stream.into_tls(server_name, tls.as_ref())?,
+    };
+
+    stream.set_nonblocking(true)?;
+    Ok(stream)
+}
+
+#[async_trait::async_trait]
+impl managed::Manager for Manager {
+    type Type = Connection;
+    type Error = lapin::Error;
+
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) need rabbitmq backend to test
+    #[allow(clippy::result_large_err)]
+    async fn create(&self) -> Result<Connection, lapin::Error> {
+        let uri: AMQPUri = self
+            .url
+            .parse()
+            .map_err(|e: String| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+        let server_name = self
+            .server_name
+            .clone()
+            .unwrap_or_else(|| uri.authority.host.clone());
+
+        let tls = OwnedTLSConfig {
+            identity: self.identity.clone().map(|der| OwnedIdentity {
+                der,
+                password: String::new(),
+            }),
+            cert_chain: self.cert_chain.clone(),
+        };
+
+        Connection::connector(
+            uri,
+            Box::new(move |uri| connect(uri, &server_name, tls)),
+            self.properties.clone(),
+        )
+        .await
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) need rabbitmq backend to test
+    async fn recycle(&self, conn: &mut Connection, _: &Metrics) -> RecycleResult<lapin::Error> {
+        match conn.status().connected() {
+            true => Ok(()),
+            false => Err(RecycleError::StaticMessage("lapin connection closed")),
+        }
+    }
+}
 
 /// Represents a pool of connections to a amqp server
 ///
@@ -26,12 +169,21 @@ impl AMQPPool {
         })?;
 
         amqp_info!("(AMQPPool new) creating pool at {:?}...", details);
-        // no_coverage: this won't fail
-        let pool: Pool = cfg.create_pool(Some(Runtime::Tokio1)).map_err(|e| {
-            amqp_error!("(AMQPPool new) could not create pool: {}", e);
+        let manager = Manager::new(&config, details).map_err(|e| {
+            amqp_error!("(AMQPPool new) invalid TLS configuration: {}", e);
             AMQPError::CouldNotConnect
         })?;
 
+        // no_coverage: this won't fail
+        let pool: Pool = Pool::builder(manager)
+            .config(cfg.pool.unwrap_or_default())
+            .runtime(Runtime::Tokio1)
+            .build()
+            .map_err(|e| {
+                amqp_error!("(AMQPPool new) could not create pool: {}", e);
+                AMQPError::CouldNotConnect
+            })?;
+
         Ok(Self { pool })
     }
 
@@ -0,0 +1,61 @@
+//! Publishes extrapolated positions of aircraft during short telemetry gaps
+
+use super::envelope::TelemetryEnvelope;
+use crate::clock::SharedClock;
+use crate::config::Config;
+use crate::msg::track::SharedTracks;
+use lib_common::time::Duration;
+
+/// Periodically publishes predicted positions of aircraft which
+///  stopped reporting their position recently
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need rabbitmq backend to test
+pub async fn prediction_loop(
+    config: Config,
+    tracks: SharedTracks,
+    mq_channel: super::MqChannel,
+    clock: SharedClock,
+) {
+    let interval_ms = config.prediction_interval_ms.max(1);
+    let (Some(min_gap), Some(max_gap)) = (
+        Duration::try_milliseconds(interval_ms as i64),
+        Duration::try_milliseconds(config.prediction_max_gap_ms as i64),
+    ) else {
+        amqp_error!("invalid prediction configuration.");
+        return;
+    };
+
+    amqp_info!("publishing predicted positions every {interval_ms} ms.");
+    let mut interval = tokio::time::interval(std::time::Duration::from_millis(interval_ms as u64));
+    loop {
+        interval.tick().await;
+
+        let positions = match tracks.lock() {
+            Ok(tracks) => tracks.predict(clock.now(), min_gap, max_gap),
+            Err(e) => {
+                amqp_error!("could not lock tracks: {e}");
+                continue;
+            }
+        };
+
+        for position in positions {
+            let Ok(msg) = serde_json::to_vec(&TelemetryEnvelope::predicted(&position)) else {
+                amqp_warn!("could not serialize predicted position.");
+                continue;
+            };
+
+            let _ = mq_channel
+                .basic_publish(
+                    super::EXCHANGE_NAME_TELEMETRY,
+                    super::ROUTING_KEY_PREDICTED_POSITION,
+                    lapin::options::BasicPublishOptions::default(),
+                    &msg,
+                    lapin::BasicProperties::default(),
+                )
+                .await
+                .map_err(|e| {
+                    amqp_warn!("could not publish predicted position: {e}.");
+                });
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Bounds and depths of the telemetry queues
+//!
+//! A queue nobody consumes grows until the RabbitMQ node runs out of
+//!  memory. The queues are declared with the message TTL, maximum length
+//!  and queue mode set by `AMQP_QUEUE_*`. Queues at their maximum length
+//!  drop their oldest messages without telling the publishers, so the
+//!  depth of each queue is polled on a channel of its own, and the polls
+//!  finding a queue full are counted as overflows by the statistics.
+//!
+//! The polls also read the consumers of each queue. A queue holding
+//!  `AMQP_QUEUE_LAG_THRESHOLD` messages or more is lagging: its consumers
+//!  fall behind the published telemetry, or are gone. A warning is logged
+//!  when a queue starts lagging and a notice once it caught up.
+
+use super::{telemetry_queues, QUEUE_NAME_RAW};
+use crate::config::Config;
+use crate::stats::Stats;
+use lapin::types::{AMQPValue, FieldTable};
+use std::collections::HashSet;
+
+/// Queue argument of the time a message can wait for a consumer
+const ARGUMENT_MESSAGE_TTL: &str = "x-message-ttl";
+
+/// Queue argument of the number of messages a queue holds
+const ARGUMENT_MAX_LENGTH: &str = "x-max-length";
+
+/// Queue argument of where the messages are kept
+const ARGUMENT_QUEUE_MODE: &str = "x-queue-mode";
+
+/// Interval between connection attempts while RabbitMQ is unreachable
+#[cfg(not(any(test, feature = "memory_backends")))]
+const DEPTH_RECONNECT_MS: u64 = 5000;
+
+/// Bounds of the telemetry queues
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueueLimits {
+    /// Messages are dropped after waiting this long, 0 keeps them
+    pub message_ttl_ms: u32,
+
+    /// Oldest messages are dropped beyond this many, 0 for no limit
+    pub max_length: u32,
+
+    /// If messages are kept on disk instead of in memory
+    pub lazy: bool,
+}
+
+impl QueueLimits {
+    /// Bounds set by the configuration
+    pub fn new(config: &Config) -> Self {
+        QueueLimits {
+            message_ttl_ms: config.amqp_queue_message_ttl_ms,
+            max_length: config.amqp_queue_max_length,
+            lazy: config.amqp_queue_lazy,
+        }
+    }
+
+    /// If any bound is set
+    pub fn is_bounded(&self) -> bool {
+        *self != QueueLimits::default()
+    }
+
+    /// Arguments declaring a queue with these bounds
+    pub fn arguments(&self) -> FieldTable {
+        let mut arguments = FieldTable::default();
+        if self.message_ttl_ms > 0 {
+            arguments.insert(
+                ARGUMENT_MESSAGE_TTL.into(),
+                AMQPValue::LongLongInt(self.message_ttl_ms as i64),
+            );
+        }
+
+        if self.max_length > 0 {
+            arguments.insert(
+                ARGUMENT_MAX_LENGTH.into(),
+                AMQPValue::LongLongInt(self.max_length as i64),
+            );
+        }
+
+        if self.lazy {
+            arguments.insert(
+                ARGUMENT_QUEUE_MODE.into(),
+                AMQPValue::LongString("lazy".into()),
+            );
+        }
+
+        arguments
+    }
+
+    /// If a queue holding this many messages drops its oldest ones
+    pub fn is_full(&self, depth: u32) -> bool {
+        self.max_length > 0 && depth >= self.max_length
+    }
+}
+
+/// Messages and consumers of a queue when polled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct QueuePoll {
+    /// Messages waiting in the queue
+    messages: u32,
+
+    /// Consumers of the queue, None if unknown
+    consumers: Option<u32>,
+}
+
+/// Queues whose consumers are falling behind
+#[derive(Debug, Clone, Default)]
+struct LagMonitor {
+    /// Messages from which a queue is lagging, 0 disables the monitoring
+    threshold: u32,
+
+    /// Queues lagging when last polled
+    lagging: HashSet<String>,
+}
+
+impl LagMonitor {
+    /// Monitoring queues from the given number of messages
+    fn new(threshold: u32) -> Self {
+        LagMonitor {
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    /// If the consumers of a queue are falling behind, logging when they
+    ///  start and when they caught up
+    fn observe(&mut self, queue: &str, poll: &QueuePoll) -> bool {
+        let lagging = self.threshold > 0 && poll.messages >= self.threshold;
+        match (lagging, self.lagging.contains(queue)) {
+            (true, false) => {
+                let consumers = poll
+                    .consumers
+                    .map_or("unknown".to_string(), |consumers| consumers.to_string());
+                amqp_warn!(
+                    "consumers of queue '{queue}' are falling behind: {} messages waiting, {consumers} consumers.",
+                    poll.messages
+                );
+                self.lagging.insert(queue.to_string());
+            }
+            (false, true) => {
+                amqp_info!(
+                    "consumers of queue '{queue}' caught up: {} messages waiting.",
+                    poll.messages
+                );
+                self.lagging.remove(queue);
+            }
+            _ => (),
+        }
+
+        lagging
+    }
+}
+
+/// Names of the queues declared by [`super::init_mq`]
+pub fn declared_queues(config: &Config) -> Vec<&'static str> {
+    let mut queues: Vec<&'static str> = telemetry_queues(config)
+        .into_iter()
+        .map(|(queue, _)| queue)
+        .collect();
+
+    if config.raw_exchange_enabled {
+        queues.push(QUEUE_NAME_RAW);
+    }
+
+    queues
+}
+
+/// Records the depths and consumers of the queues in the statistics
+fn record_polls(
+    stats: &Stats,
+    limits: &QueueLimits,
+    lags: &mut LagMonitor,
+    polls: &[(&str, QueuePoll)],
+) {
+    for (queue, poll) in polls {
+        let full = limits.is_full(poll.messages);
+        if full {
+            amqp_warn!("queue '{queue}' is full, dropping its oldest messages.");
+        }
+
+        stats.record_queue_depth(queue, poll.messages, full);
+        if let Some(consumers) = poll.consumers {
+            stats.record_queue_consumers(queue, consumers);
+        }
+
+        if lags.observe(queue, poll) {
+            stats.record_queue_lag(queue);
+        }
+    }
+}
+
+/// Messages and consumers of a queue, declaring it passively
+#[cfg(not(any(test, feature = "memory_backends")))]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (Rnever) need rabbitmq backend running, integration tests
+async fn poll(channel: &lapin::Channel, queue: &str) -> Result<QueuePoll, lapin::Error> {
+    let options = lapin::options::QueueDeclareOptions {
+        passive: true,
+        ..Default::default()
+    };
+
+    channel
+        .queue_declare(queue, options, FieldTable::default())
+        .await
+        .map(|queue| QueuePoll {
+            messages: queue.message_count(),
+            consumers: Some(queue.consumer_count()),
+        })
+}
+
+/// Periodically polls the depths and consumers of the declared queues
+///
+/// The polls have a connection of their own: RabbitMQ closes the channel
+///  of a passive declaration of a missing queue.
+#[cfg(not(any(test, feature = "memory_backends")))]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (Rnever) need rabbitmq backend running, integration tests
+pub async fn depth_loop(config: Config, stats: Stats) {
+    let limits = QueueLimits::new(&config);
+    let mut lags = LagMonitor::new(config.amqp_queue_lag_threshold);
+    let queues = declared_queues(&config);
+    let period = std::time::Duration::from_millis(config.amqp_queue_poll_interval_ms as u64);
+    let reconnect = std::time::Duration::from_millis(DEPTH_RECONNECT_MS);
+    amqp_info!("polling the depths of the queues every {period:?}.");
+
+    loop {
+        let connected = async {
+            let pool = super::pool::AMQPPool::new(config.clone())?;
+            let connection = pool.get_connection().await?;
+            let channel = connection.create_channel().await.map_err(|e| {
+                amqp_debug!("error: {:?}", e);
+                super::AMQPError::CouldNotCreateChannel
+            })?;
+
+            Ok::<_, super::AMQPError>((pool, connection, channel))
+        };
+
+        let (_pool, _connection, channel) = match connected.await {
+            Ok(connected) => connected,
+            Err(e) => {
+                amqp_warn!("could not connect to poll the queues: {e}");
+                tokio::time::sleep(reconnect).await;
+                continue;
+            }
+        };
+
+        let mut interval = tokio::time::interval(period);
+        while channel.status().connected() {
+            interval.tick().await;
+
+            let mut polls = vec![];
+            for queue in &queues {
+                match poll(&channel, queue).await {
+                    Ok(poll) => polls.push((*queue, poll)),
+                    Err(e) => {
+                        amqp_warn!("could not get the depth of queue '{queue}': {e}");
+                        stats.record_error(crate::stats::Dependency::Amqp);
+                        break;
+                    }
+                }
+            }
+
+            record_polls(&stats, &limits, &mut lags, &polls);
+        }
+    }
+}
+
+/// Periodically polls the depths of the queues held in memory, which
+///  are bounded by [`super::memory::MEMORY_QUEUE_MAX_LEN`]
+#[cfg(all(not(test), feature = "memory_backends"))]
+pub async fn depth_loop(config: Config, stats: Stats) {
+    let limits = QueueLimits {
+        max_length: super::memory::MEMORY_QUEUE_MAX_LEN as u32,
+        ..Default::default()
+    };
+
+    let mut lags = LagMonitor::new(config.amqp_queue_lag_threshold);
+    let queues = declared_queues(&config);
+    let period = std::time::Duration::from_millis(config.amqp_queue_poll_interval_ms as u64);
+    let channel = super::memory::MemoryChannel::shared();
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        let polls: Vec<(&str, QueuePoll)> = queues
+            .iter()
+            .filter_map(|queue| {
+                let poll = QueuePoll {
+                    messages: channel.message_count(queue)? as u32,
+                    consumers: None,
+                };
+
+                Some((*queue, poll))
+            })
+            .collect();
+
+        record_polls(&stats, &limits, &mut lags, &polls);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arguments() {
+        assert!(QueueLimits::default().arguments().inner().is_empty());
+        assert!(!QueueLimits::default().is_bounded());
+
+        let config = Config {
+            amqp_queue_message_ttl_ms: 60000,
+            amqp_queue_max_length: 100,
+            amqp_queue_lazy: true,
+            ..Default::default()
+        };
+        let limits = QueueLimits::new(&config);
+        assert!(limits.is_bounded());
+
+        let arguments = limits.arguments();
+        let arguments = arguments.inner();
+        assert_eq!(arguments.len(), 3);
+        assert_eq!(
+            arguments.get(ARGUMENT_MESSAGE_TTL),
+            Some(&AMQPValue::LongLongInt(60000))
+        );
+        assert_eq!(
+            arguments.get(ARGUMENT_MAX_LENGTH),
+            Some(&AMQPValue::LongLongInt(100))
+        );
+        assert_eq!(
+            arguments.get(ARGUMENT_QUEUE_MODE),
+            Some(&AMQPValue::LongString("lazy".into()))
+        );
+
+        // only the maximum length
+        let limits = QueueLimits {
+            max_length: 10,
+            ..Default::default()
+        };
+        assert_eq!(limits.arguments().inner().len(), 1);
+    }
+
+    #[test]
+    fn test_is_full() {
+        let limits = QueueLimits {
+            max_length: 10,
+            ..Default::default()
+        };
+        assert!(!limits.is_full(9));
+        assert!(limits.is_full(10));
+        assert!(!QueueLimits::default().is_full(u32::MAX));
+    }
+
+    #[test]
+    fn test_declared_queues() {
+        let mut config = Config {
+            raw_exchange_enabled: false,
+            ..Default::default()
+        };
+        let queues = declared_queues(&config);
+        assert_eq!(queues.len(), telemetry_queues(&config).len());
+        assert!(!queues.contains(&QUEUE_NAME_RAW));
+
+        config.raw_exchange_enabled = true;
+        assert!(declared_queues(&config).contains(&QUEUE_NAME_RAW));
+    }
+
+    fn polled(messages: u32, consumers: Option<u32>) -> QueuePoll {
+        QueuePoll {
+            messages,
+            consumers,
+        }
+    }
+
+    #[test]
+    fn test_record_polls() {
+        let stats = Stats::default();
+        let limits = QueueLimits {
+            max_length: 10,
+            ..Default::default()
+        };
+        let mut lags = LagMonitor::new(0);
+
+        let polls = [
+            ("adsb", polled(10, Some(0))),
+            ("netrid_pos", polled(3, None)),
+        ];
+        record_polls(&stats, &limits, &mut lags, &polls);
+        let polls = [
+            ("adsb", polled(12, Some(1))),
+            ("netrid_pos", polled(4, None)),
+        ];
+        record_polls(&stats, &limits, &mut lags, &polls);
+
+        let summary = stats.summary(Default::default());
+        assert_eq!(summary.queue_depths["adsb"], 12);
+        assert_eq!(summary.queue_depths["netrid_pos"], 4);
+        assert_eq!(summary.queue_overflows["adsb"], 2);
+        assert!(!summary.queue_overflows.contains_key("netrid_pos"));
+        assert_eq!(summary.queue_consumers["adsb"], 1);
+        assert!(!summary.queue_consumers.contains_key("netrid_pos"));
+        assert!(summary.queue_lags.is_empty());
+    }
+
+    #[test]
+    fn test_lag_monitor() {
+        let stats = Stats::default();
+        let limits = QueueLimits::default();
+        let mut lags = LagMonitor::new(100);
+
+        for messages in [99, 100, 250, 99, 100] {
+            let polls = [("adsb", polled(messages, Some(2)))];
+            record_polls(&stats, &limits, &mut lags, &polls);
+        }
+
+        assert_eq!(stats.summary(Default::default()).queue_lags["adsb"], 3);
+        assert!(lags.lagging.contains("adsb"));
+        assert!(!lags.observe("adsb", &polled(0, Some(2))));
+        assert!(lags.lagging.is_empty());
+    }
+}
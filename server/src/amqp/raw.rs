@@ -0,0 +1,191 @@
+//! Publishes received packets unmodified, with how they were received
+//!
+//! Archival consumers store the bitstream as received from each
+//!  reporter, including duplicates, on the `raw` exchange. The packet is
+//!  the message body, its reception is described by message headers.
+//!
+//! Packets can't be scrubbed without being modified: Remote ID packets
+//!  carrying personal data of the operator (System and Operator ID
+//!  messages) aren't published.
+
+use super::envelope::SignalMetadata;
+use crate::stats::{Dependency, Stats};
+use lapin::types::{AMQPValue, FieldTable, LongString};
+use lib_common::time::{DateTime, SecondsFormat, Utc};
+
+/// AMQP message header holding the identifier of the reporter
+pub const AMQP_HEADER_REPORTER: &str = "reporter";
+
+/// AMQP message header holding when the packet was received (RFC 3339)
+pub const AMQP_HEADER_RECEIVED: &str = "received";
+
+/// AMQP message header holding the endpoint the packet was posted to
+pub const AMQP_HEADER_ENDPOINT: &str = "endpoint";
+
+/// AMQP message header holding the latitude of the receiver
+pub const AMQP_HEADER_RECEIVER_LATITUDE: &str = "receiver_latitude";
+
+/// AMQP message header holding the longitude of the receiver
+pub const AMQP_HEADER_RECEIVER_LONGITUDE: &str = "receiver_longitude";
+
+/// AMQP message header holding the received signal strength in dBm
+pub const AMQP_HEADER_RSSI: &str = "rssi_dbm";
+
+/// AMQP message header holding the signal to noise ratio in dB
+pub const AMQP_HEADER_SNR: &str = "snr_db";
+
+/// AMQP message header holding the MAC address of the broadcaster
+pub const AMQP_HEADER_BROADCASTER_MAC: &str = "broadcaster_mac";
+
+/// How a packet was received
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reception {
+    /// Endpoint the packet was posted to, e.g. `/telemetry/adsb`
+    pub endpoint: &'static str,
+
+    /// Identifier of the reporter, if the endpoint requires a token
+    pub reporter: Option<String>,
+
+    /// When the packet was received
+    pub received: DateTime<Utc>,
+
+    /// Signal metadata declared by the receiver
+    pub signal: Option<SignalMetadata>,
+}
+
+impl Reception {
+    /// A packet received at `received`
+    pub fn new(
+        endpoint: &'static str,
+        reporter: Option<String>,
+        signal: Option<SignalMetadata>,
+        received: DateTime<Utc>,
+    ) -> Self {
+        Reception {
+            endpoint,
+            reporter,
+            received,
+            signal,
+        }
+    }
+
+    /// Message properties describing the reception
+    pub fn amqp_properties(&self) -> lapin::BasicProperties {
+        let mut headers = FieldTable::default();
+        let mut insert = |header: &str, value: String| {
+            headers.insert(
+                header.into(),
+                AMQPValue::LongString(LongString::from(value)),
+            );
+        };
+
+        insert(AMQP_HEADER_ENDPOINT, self.endpoint.to_string());
+        insert(
+            AMQP_HEADER_RECEIVED,
+            self.received.to_rfc3339_opts(SecondsFormat::Millis, true),
+        );
+        if let Some(reporter) = &self.reporter {
+            insert(AMQP_HEADER_REPORTER, reporter.clone());
+        }
+
+        if let Some(signal) = &self.signal {
+            if let Some(mac) = signal.broadcaster_mac {
+                insert(AMQP_HEADER_BROADCASTER_MAC, mac.to_string());
+            }
+
+            let values = [
+                (AMQP_HEADER_RECEIVER_LATITUDE, signal.receiver_latitude),
+                (AMQP_HEADER_RECEIVER_LONGITUDE, signal.receiver_longitude),
+                (AMQP_HEADER_RSSI, signal.rssi_dbm.map(f64::from)),
+                (AMQP_HEADER_SNR, signal.snr_db.map(f64::from)),
+            ];
+
+            for (header, value) in values {
+                if let Some(value) = value {
+                    headers.insert(header.into(), AMQPValue::Double(value));
+                }
+            }
+        }
+
+        lapin::BasicProperties::default()
+            .with_timestamp(self.received.timestamp().max(0) as u64)
+            .with_headers(headers)
+    }
+}
+
+/// Publishes a received packet to the `raw` exchange
+///  Publishing is best effort, failures are logged only.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need rabbitmq backend to test
+pub async fn publish(
+    mq_channel: &super::MqChannel,
+    stats: &Stats,
+    routing_key: &str,
+    payload: &[u8],
+    reception: &Reception,
+) {
+    let _ = mq_channel
+        .basic_publish(
+            super::EXCHANGE_NAME_RAW,
+            routing_key,
+            lapin::options::BasicPublishOptions::default(),
+            payload,
+            reception.amqp_properties(),
+        )
+        .await
+        .map_err(|e| {
+            amqp_warn!("could not publish raw packet ({routing_key}): {e}.");
+            stats.record_error(Dependency::Amqp);
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amqp::envelope::MacAddress;
+
+    #[test]
+    fn test_amqp_properties() {
+        let received = DateTime::from_timestamp(1_700_000_000, 250_000_000).unwrap();
+        let reception = Reception {
+            endpoint: "/telemetry/netrid",
+            reporter: Some("drone-1".to_string()),
+            received,
+            signal: Some(SignalMetadata {
+                rssi_dbm: Some(-81.5),
+                broadcaster_mac: Some(MacAddress([0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f])),
+                ..Default::default()
+            }),
+        };
+
+        let properties = reception.amqp_properties();
+        assert_eq!(properties.timestamp(), &Some(1_700_000_000));
+
+        let headers = properties.headers().clone().unwrap();
+        let header = |name: &str| headers.inner().get(name).cloned();
+        assert_eq!(
+            header(AMQP_HEADER_ENDPOINT),
+            Some(AMQPValue::LongString(LongString::from("/telemetry/netrid")))
+        );
+        assert_eq!(
+            header(AMQP_HEADER_RECEIVED),
+            Some(AMQPValue::LongString(LongString::from(
+                "2023-11-14T22:13:20.250Z"
+            )))
+        );
+        assert_eq!(
+            header(AMQP_HEADER_REPORTER),
+            Some(AMQPValue::LongString(LongString::from("drone-1")))
+        );
+        assert_eq!(header(AMQP_HEADER_RSSI), Some(AMQPValue::Double(-81.5)));
+        assert_eq!(header(AMQP_HEADER_RECEIVER_LATITUDE), None);
+        assert_eq!(
+            header(AMQP_HEADER_BROADCASTER_MAC),
+            Some(AMQPValue::LongString(LongString::from("0a:1b:2c:3d:4e:5f")))
+        );
+
+        let reception = Reception::new("/telemetry/adsb", None, None, Utc::now());
+        let headers = reception.amqp_properties().headers().clone().unwrap();
+        assert!(!headers.inner().contains_key(AMQP_HEADER_REPORTER));
+    }
+}
@@ -0,0 +1,88 @@
+//! Publishes the health state changes of the dependencies
+
+use super::envelope::TelemetryEnvelope;
+use crate::cache::pool::TelemetryPool;
+use crate::clock::SharedClock;
+use crate::grpc::client::GrpcClients;
+use crate::msg::system::{HealthMonitor, HealthState, HealthTransition};
+use crate::stats::{Dependency, Stats};
+
+/// Hash read to check Redis, never written
+pub(crate) const REDIS_PROBE_KEY: &str = "health";
+
+/// Publishes a health state change to the system queue
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need rabbitmq backend to test
+async fn publish_transition(
+    mq_channel: &super::MqChannel,
+    stats: &Stats,
+    transition: &HealthTransition,
+) {
+    match transition.state {
+        HealthState::Down => amqp_warn!(
+            "{:?} down: {}.",
+            transition.dependency,
+            transition.reason.as_deref().unwrap_or("unknown")
+        ),
+        HealthState::Up => amqp_warn!(
+            "{:?} up again, down since {}.",
+            transition.dependency,
+            transition.previous_since
+        ),
+    }
+
+    let Ok(msg) = serde_json::to_vec(&TelemetryEnvelope::new(transition)) else {
+        amqp_warn!("could not serialize health state change.");
+        return;
+    };
+
+    let _ = mq_channel
+        .basic_publish(
+            super::EXCHANGE_NAME_TELEMETRY,
+            super::ROUTING_KEY_SYSTEM,
+            lapin::options::BasicPublishOptions::default(),
+            &msg,
+            lapin::BasicProperties::default(),
+        )
+        .await
+        .map_err(|e| {
+            amqp_warn!("could not publish health state change: {e}.");
+            stats.record_error(Dependency::Amqp);
+        });
+}
+
+/// Periodically checks Redis, svc-gis and svc-storage, publishing the
+///  changes of their health state
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis and rabbitmq backends to test
+pub async fn health_loop(
+    grpc_clients: GrpcClients,
+    mut tlm_pool: TelemetryPool,
+    mq_channel: super::MqChannel,
+    stats: Stats,
+    interval_ms: u32,
+    clock: SharedClock,
+) {
+    amqp_info!("checking dependencies every {interval_ms} ms.");
+    let mut monitor = HealthMonitor::new(clock.now());
+    let mut interval = tokio::time::interval(std::time::Duration::from_millis(interval_ms as u64));
+    loop {
+        interval.tick().await;
+
+        let redis = tlm_pool
+            .hash_get(REDIS_PROBE_KEY, REDIS_PROBE_KEY)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+
+        let mut results = crate::rest::api::health::readiness(&grpc_clients).await;
+        results.push((Dependency::Redis, redis));
+
+        let now = clock.now();
+        for (dependency, result) in results {
+            if let Some(transition) = monitor.record(dependency, result, now) {
+                publish_transition(&mq_channel, &stats, &transition).await;
+            }
+        }
+    }
+}
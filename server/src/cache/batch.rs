@@ -0,0 +1,316 @@
+//! Micro-batching of the deduplication increments
+//!
+//! Each received packet increments the count of its key. At high packet
+//!  rates the round trip of each increment dominates, so increments
+//!  requested within `DEDUP_BATCH_WINDOW_MS` are collected and sent as one
+//!  pipeline per hash tag of their keys (see [`super::hash_tag`]): the keys
+//!  of a pipeline share a Redis Cluster slot, and the pipelines of a batch
+//!  are sent concurrently.
+
+use super::pool::{CacheError, TelemetryPool};
+use super::{hash_tag, TelemetryPools};
+use core::fmt::{Debug, Formatter};
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use tokio::sync::{mpsc, oneshot};
+
+/// Maximum number of increments sent in a batch, sent before the end of
+///  the window if reached
+pub const MAX_BATCH_KEYS: usize = 256;
+
+/// Backend of the batched increments
+pub trait IncrementBackend: Clone + Send + Sync + 'static {
+    /// Increments the keys with their expiration times, returning their
+    ///  values in order
+    fn increment_batch<'a>(
+        &'a mut self,
+        keys: &'a [(String, u32)],
+    ) -> BoxFuture<'a, Result<Vec<u32>, CacheError>>;
+}
+
+impl IncrementBackend for TelemetryPool {
+    fn increment_batch<'a>(
+        &'a mut self,
+        keys: &'a [(String, u32)],
+    ) -> BoxFuture<'a, Result<Vec<u32>, CacheError>> {
+        Box::pin(TelemetryPool::increment_batch(self, keys))
+    }
+}
+
+/// Increment of a key, answered with its value
+struct Request {
+    /// Key to increment
+    key: String,
+
+    /// Expiration time of the key if created
+    expiration_ms: u32,
+
+    /// Value of the key after the increment
+    reply: oneshot::Sender<Result<u32, CacheError>>,
+}
+
+/// Collects the increments of a window into batches, sent by a task
+#[derive(Clone)]
+pub struct IncrementBatcher {
+    /// Requests to the collecting task
+    sender: mpsc::UnboundedSender<Request>,
+}
+
+impl Debug for IncrementBatcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("IncrementBatcher").finish()
+    }
+}
+
+impl IncrementBatcher {
+    /// Spawns the task collecting the increments of each window, must be
+    ///  called within a Tokio runtime
+    pub fn spawn<B: IncrementBackend>(backend: B, window_ms: u32) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let window = std::time::Duration::from_millis(window_ms as u64);
+        tokio::spawn(collect(backend, receiver, window));
+        IncrementBatcher { sender }
+    }
+
+    /// Increments a key in the next batch, returning its value
+    pub async fn increment(&self, key: &str, expiration_ms: u32) -> Result<u32, CacheError> {
+        let (reply, value) = oneshot::channel();
+        let request = Request {
+            key: key.to_string(),
+            expiration_ms,
+            reply,
+        };
+
+        self.sender.send(request).map_err(|_| {
+            cache_error!("increment batching task stopped.");
+            CacheError::OperationFailed
+        })?;
+
+        value.await.map_err(|_| {
+            cache_error!("increment batch dropped.");
+            CacheError::OperationFailed
+        })?
+    }
+}
+
+/// Collects the requests received within a window of the first one, or
+///  up to [`MAX_BATCH_KEYS`], sending each batch without waiting for the
+///  previous ones
+async fn collect<B: IncrementBackend>(
+    backend: B,
+    mut receiver: mpsc::UnboundedReceiver<Request>,
+    window: std::time::Duration,
+) {
+    while let Some(first) = receiver.recv().await {
+        let deadline = tokio::time::Instant::now() + window;
+        let mut batch = vec![first];
+        while batch.len() < MAX_BATCH_KEYS {
+            match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(request)) => batch.push(request),
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        tokio::spawn(flush(backend.clone(), batch));
+    }
+
+    cache_debug!("increment batching stopped.");
+}
+
+/// Groups items by the hash tag of their keys, keeping their order
+fn by_hash_tag<T>(items: Vec<T>, key: impl Fn(&T) -> &str) -> HashMap<String, Vec<T>> {
+    let mut groups: HashMap<String, Vec<T>> = HashMap::new();
+    for item in items {
+        groups
+            .entry(hash_tag(key(&item)).to_string())
+            .or_default()
+            .push(item);
+    }
+
+    groups
+}
+
+/// Sends a batch as one pipeline per hash tag
+async fn flush<B: IncrementBackend>(backend: B, batch: Vec<Request>) {
+    cache_debug!("sending batch of {} increments.", batch.len());
+    let groups = by_hash_tag(batch, |request| &request.key);
+    futures::future::join_all(
+        groups
+            .into_values()
+            .map(|requests| send(backend.clone(), requests)),
+    )
+    .await;
+}
+
+/// Sends the increments of a pipeline, answering each request
+async fn send<B: IncrementBackend>(mut backend: B, requests: Vec<Request>) {
+    let keys: Vec<(String, u32)> = requests
+        .iter()
+        .map(|request| (request.key.clone(), request.expiration_ms))
+        .collect();
+
+    match backend.increment_batch(&keys).await {
+        Ok(values) if values.len() == requests.len() => {
+            for (request, value) in requests.into_iter().zip(values) {
+                let _ = request.reply.send(Ok(value));
+            }
+        }
+        result => {
+            let e = result.err().unwrap_or(CacheError::OperationFailed);
+            for request in requests {
+                let _ = request.reply.send(Err(e));
+            }
+        }
+    }
+}
+
+/// Counter of the packets received with the same key
+#[derive(Debug, Clone)]
+pub enum DedupCounter {
+    /// Each increment is a round trip
+    Direct(TelemetryPool),
+
+    /// Increments are batched
+    Batched(IncrementBatcher),
+}
+
+impl DedupCounter {
+    /// Counter of the keys of the pool, batching the increments of each
+    ///  window if it isn't 0
+    pub fn new(tlm_pool: TelemetryPool, window_ms: u32) -> Self {
+        match window_ms {
+            0 => DedupCounter::Direct(tlm_pool),
+            _ => DedupCounter::Batched(IncrementBatcher::spawn(tlm_pool, window_ms)),
+        }
+    }
+
+    /// See [`TelemetryPool::increment`]
+    pub async fn increment(&self, key: &str, expiration_ms: u32) -> Result<u32, CacheError> {
+        match self {
+            DedupCounter::Direct(tlm_pool) => tlm_pool.clone().increment(key, expiration_ms).await,
+            DedupCounter::Batched(batcher) => batcher.increment(key, expiration_ms).await,
+        }
+    }
+}
+
+/// Packet counters of each protocol
+///
+/// Remote ID packets count their distinct reporters instead, see
+///  [`crate::rest::api::quorum`].
+#[derive(Debug, Clone)]
+pub struct DedupCounters {
+    /// ADS-B packets
+    pub adsb: DedupCounter,
+}
+
+impl DedupCounters {
+    /// Counters of the keys of the pools, see [`DedupCounter::new`]
+    pub fn new(tlm_pools: &TelemetryPools, window_ms: u32) -> Self {
+        DedupCounters {
+            adsb: DedupCounter::new(tlm_pools.adsb.clone(), window_ms),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::packet_key;
+    use std::sync::{Arc, Mutex};
+
+    /// Counts in memory, recording the keys of each round trip
+    #[derive(Clone, Default)]
+    struct Recorder {
+        counts: Arc<Mutex<HashMap<String, u32>>>,
+        round_trips: Arc<Mutex<Vec<Vec<String>>>>,
+    }
+
+    impl IncrementBackend for Recorder {
+        fn increment_batch<'a>(
+            &'a mut self,
+            keys: &'a [(String, u32)],
+        ) -> BoxFuture<'a, Result<Vec<u32>, CacheError>> {
+            let mut counts = self.counts.lock().unwrap();
+            let values = keys
+                .iter()
+                .map(|(key, _)| {
+                    let count = counts.entry(key.clone()).or_default();
+                    *count += 1;
+                    *count
+                })
+                .collect();
+
+            self.round_trips
+                .lock()
+                .unwrap()
+                .push(keys.iter().map(|(key, _)| key.clone()).collect());
+            Box::pin(async move { Ok(values) })
+        }
+    }
+
+    #[test]
+    fn test_by_hash_tag() {
+        let keys = vec!["adsb:{a}:1", "adsb:{b}:2", "netrid:{a}:3", "untagged"];
+        let groups = by_hash_tag(keys, |key| key);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups["a"], vec!["adsb:{a}:1", "netrid:{a}:3"]);
+        assert_eq!(groups["b"], vec!["adsb:{b}:2"]);
+        assert_eq!(groups[""], vec!["untagged"]);
+    }
+
+    #[tokio::test]
+    async fn test_batcher() {
+        let backend = Recorder::default();
+        let batcher = IncrementBatcher::spawn(backend.clone(), 20);
+
+        // the same packet from 3 reporters, and 2 other packets
+        let key = packet_key("adsb", &[1]);
+        let keys = [
+            key.clone(),
+            packet_key("adsb", &[2]),
+            key.clone(),
+            packet_key("adsb", &[3]),
+            key.clone(),
+        ];
+
+        let values =
+            futures::future::join_all(keys.iter().map(|key| batcher.increment(key, 1000))).await;
+        let values: Vec<u32> = values.into_iter().map(Result::unwrap).collect();
+        assert_eq!(values, vec![1, 1, 2, 1, 3]);
+
+        // a single batch, in a round trip per hash tag
+        let round_trips = backend.round_trips.lock().unwrap().clone();
+        let tags: std::collections::HashSet<&str> = keys.iter().map(|key| hash_tag(key)).collect();
+        assert_eq!(round_trips.len(), tags.len());
+        for keys in &round_trips {
+            assert!(keys.iter().all(|key| hash_tag(key) == hash_tag(&keys[0])));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batcher_max_keys() {
+        let backend = Recorder::default();
+
+        // the window is never reached
+        let batcher = IncrementBatcher::spawn(backend.clone(), 60_000);
+        let keys: Vec<String> = (0..MAX_BATCH_KEYS).map(|i| format!("{{a}}:{i}")).collect();
+        let values =
+            futures::future::join_all(keys.iter().map(|key| batcher.increment(key, 1000))).await;
+        assert!(values.into_iter().all(|value| matches!(value, Ok(1))));
+        assert_eq!(backend.round_trips.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_counter() {
+        let config = crate::Config::default();
+        let tlm_pool = TelemetryPool::new(config, "adsb").await.unwrap();
+        assert!(matches!(
+            DedupCounter::new(tlm_pool.clone(), 0),
+            DedupCounter::Direct(_)
+        ));
+
+        let counter = DedupCounter::new(tlm_pool, 5);
+        assert!(matches!(counter, DedupCounter::Batched(_)));
+        assert_eq!(counter.increment("adsb:{a}:1", 1000).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,117 @@
+//! Queues of svc-gis
+//!
+//! svc-gis reads its items from Redis streams named by the
+//!  `REDIS_KEY_AIRCRAFT_*` constants of its types. Each item type is bound
+//!  to its queue by [`GisItem`]: items are pushed and read by type, never
+//!  by key, so an item can't land in the queue of another type.
+
+use core::fmt::{Debug, Display, Formatter};
+use serde::{de::DeserializeOwned, Serialize};
+use svc_gis_client_grpc::prelude::types::{
+    AircraftId, AircraftPosition, AircraftVelocity, REDIS_KEY_AIRCRAFT_ID,
+    REDIS_KEY_AIRCRAFT_POSITION, REDIS_KEY_AIRCRAFT_VELOCITY,
+};
+
+/// Consumer group of svc-gis reading the GIS queues
+pub const GIS_CONSUMER_GROUP: &str = "svc-gis";
+
+/// Field of a GIS queue entry holding the JSON serialized item
+pub const GIS_ITEM_FIELD: &str = "item";
+
+/// Field flagging a GIS queue entry replayed with its original timestamps,
+///  e.g. by a backfill: such entries are never dropped as stale
+pub const GIS_HISTORICAL_FIELD: &str = "historical";
+
+/// Items left unacknowledged by a consumer for this long are
+///  handed to the next consumer reading a batch
+pub const GIS_PENDING_IDLE_MS: usize = 30000;
+
+/// A queue read by svc-gis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GisQueue {
+    /// Identifications of aircraft
+    Identification,
+
+    /// Positions of aircraft
+    Position,
+
+    /// Velocities of aircraft
+    Velocity,
+}
+
+impl GisQueue {
+    /// All the queues
+    pub const ALL: [GisQueue; 3] = [
+        GisQueue::Identification,
+        GisQueue::Position,
+        GisQueue::Velocity,
+    ];
+
+    /// Redis key of the queue, as defined by svc-gis
+    pub fn key(self) -> &'static str {
+        match self {
+            GisQueue::Identification => REDIS_KEY_AIRCRAFT_ID,
+            GisQueue::Position => REDIS_KEY_AIRCRAFT_POSITION,
+            GisQueue::Velocity => REDIS_KEY_AIRCRAFT_VELOCITY,
+        }
+    }
+
+    /// If items of the queue are dropped once older than `GIS_STALE_AFTER_MS`,
+    ///  only positions are: the last identification and velocity of an
+    ///  aircraft stay relevant until the next one
+    pub fn expires(self) -> bool {
+        matches!(self, GisQueue::Position)
+    }
+}
+
+impl Display for GisQueue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+/// Item of a svc-gis queue
+pub trait GisItem: Serialize + DeserializeOwned + Debug {
+    /// Queue holding the items of this type
+    const QUEUE: GisQueue;
+}
+
+impl GisItem for AircraftId {
+    const QUEUE: GisQueue = GisQueue::Identification;
+}
+
+impl GisItem for AircraftPosition {
+    const QUEUE: GisQueue = GisQueue::Position;
+}
+
+impl GisItem for AircraftVelocity {
+    const QUEUE: GisQueue = GisQueue::Velocity;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_queue_keys() {
+        let keys: HashSet<&str> = GisQueue::ALL.iter().map(|queue| queue.key()).collect();
+        assert_eq!(keys.len(), GisQueue::ALL.len());
+        assert!(keys.iter().all(|key| !key.is_empty()));
+        assert_eq!(GisQueue::Position.to_string(), REDIS_KEY_AIRCRAFT_POSITION);
+    }
+
+    #[test]
+    fn test_item_queues() {
+        assert_eq!(AircraftId::QUEUE.key(), REDIS_KEY_AIRCRAFT_ID);
+        assert_eq!(AircraftPosition::QUEUE.key(), REDIS_KEY_AIRCRAFT_POSITION);
+        assert_eq!(AircraftVelocity::QUEUE.key(), REDIS_KEY_AIRCRAFT_VELOCITY);
+    }
+
+    #[test]
+    fn test_queue_expires() {
+        assert!(GisQueue::Position.expires());
+        assert!(!GisQueue::Identification.expires());
+        assert!(!GisQueue::Velocity.expires());
+    }
+}
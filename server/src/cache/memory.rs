@@ -0,0 +1,1014 @@
+//! In-memory replacement of the Redis server, for running without backends
+//!
+//! Keys, hashes and streams behave like their Redis counterparts for the
+//!  commands used by the pools: values expire, streams are capped and are
+//!  read through consumer groups keeping delivered entries pending until
+//!  acknowledged. Nothing is persisted, the store lives as long as the process.
+
+use super::pool::{CacheError, PurgeBatch};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+/// ID and fields of a stream entry
+pub type StreamEntryFields = (String, HashMap<String, String>);
+
+/// Value of a key
+#[derive(Debug)]
+enum Value {
+    /// Plain value, also used for counters
+    String(String),
+
+    /// Fields of a hash
+    Hash(HashMap<String, String>),
+
+    /// Stream with its consumer groups
+    Stream(Stream),
+
+    /// Values of a list, first to last
+    List(VecDeque<String>),
+
+    /// Members of a set
+    Set(HashSet<String>),
+}
+
+/// Value of a key with its expiration time
+#[derive(Debug)]
+struct Entry {
+    /// Value of the key
+    value: Value,
+
+    /// The key is deleted once this time has passed
+    expires: Option<Instant>,
+
+    /// Time of the last read or write of the key, as for `OBJECT IDLETIME`
+    accessed: Instant,
+}
+
+impl Entry {
+    /// Value written now
+    fn new(value: Value, expires: Option<Instant>) -> Self {
+        Entry {
+            value,
+            expires,
+            accessed: Instant::now(),
+        }
+    }
+}
+
+/// Capped log of entries read through consumer groups
+#[derive(Debug, Default)]
+struct Stream {
+    /// Sequence number and fields of the entries, oldest first
+    entries: VecDeque<(u64, HashMap<String, String>)>,
+
+    /// Sequence number of the last entry added
+    last_seq: u64,
+
+    /// Consumer groups by name
+    groups: HashMap<String, Group>,
+}
+
+/// Consumer group of a stream
+#[derive(Debug, Default)]
+struct Group {
+    /// Sequence number of the last entry delivered to the group
+    last_delivered: u64,
+
+    /// Entries delivered but not acknowledged, by sequence number
+    pending: HashMap<u64, Pending>,
+}
+
+/// Entry delivered to a consumer but not acknowledged yet
+#[derive(Debug)]
+struct Pending {
+    /// Consumer the entry was delivered to
+    consumer: String,
+
+    /// Time of the last delivery
+    delivered: Instant,
+
+    /// Number of deliveries
+    count: u64,
+}
+
+/// ID of a stream entry from its sequence number
+fn entry_id(seq: u64) -> String {
+    format!("{seq}-0")
+}
+
+/// Sequence number of a stream entry from its ID
+fn entry_seq(id: &str) -> Option<u64> {
+    id.split_once('-')?.0.parse().ok()
+}
+
+/// Expiration time of a value set now
+fn expires(expiration_ms: u32) -> Option<Instant> {
+    Some(Instant::now() + Duration::from_millis(expiration_ms as u64))
+}
+
+/// Keys stored in memory, shared by the pools of the process
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    /// Values by key
+    keys: Mutex<HashMap<String, Entry>>,
+}
+
+/// Store of the process, see [`MemoryStore::shared`]
+static STORE: OnceLock<Arc<MemoryStore>> = OnceLock::new();
+
+impl MemoryStore {
+    /// Store shared by all pools of the process, like a Redis server
+    pub fn shared() -> Arc<Self> {
+        STORE.get_or_init(Default::default).clone()
+    }
+
+    /// Lock the keys, dropping the expired ones
+    fn keys(&self) -> MutexGuard<'_, HashMap<String, Entry>> {
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        keys.retain(|_, entry| entry.expires.is_none_or(|expires| expires > now));
+        keys
+    }
+
+    /// Entry of a key, created by `value` if missing, recording the access
+    fn access<'a>(
+        keys: &'a mut HashMap<String, Entry>,
+        key: &str,
+        value: impl FnOnce() -> Entry,
+    ) -> &'a mut Entry {
+        let entry = keys.entry(key.to_string()).or_insert_with(value);
+        entry.accessed = Instant::now();
+        entry
+    }
+
+    /// Value of a key if present, recording the access
+    fn read<'a>(keys: &'a mut HashMap<String, Entry>, key: &str) -> Option<&'a Value> {
+        let entry = keys.get_mut(key)?;
+        entry.accessed = Instant::now();
+        Some(&entry.value)
+    }
+
+    /// Increment a counter, creating it with an expiration time if needed.
+    ///  The expiration time of an existing counter isn't extended.
+    ///
+    /// Returns the value of the counter.
+    pub fn increment(&self, key: &str, expiration_ms: u32) -> Result<u32, CacheError> {
+        let mut keys = self.keys();
+        let entry = Self::access(&mut keys, key, || {
+            Entry::new(Value::String("0".to_string()), expires(expiration_ms))
+        });
+
+        let Value::String(value) = &mut entry.value else {
+            return Err(CacheError::OperationFailed);
+        };
+
+        let count = value
+            .parse::<u32>()
+            .map_err(|_| CacheError::OperationFailed)?
+            + 1;
+
+        *value = count.to_string();
+        Ok(count)
+    }
+
+    /// Set the value of multiple keys with an expiration time
+    pub fn multiple_set(&self, keyvals: Vec<(String, String)>, expiration_ms: u32) {
+        let mut keys = self.keys();
+        for (key, value) in keyvals {
+            keys.insert(
+                key,
+                Entry::new(Value::String(value), expires(expiration_ms)),
+            );
+        }
+    }
+
+    /// Get the value of multiple keys, failing if any of them is missing
+    pub fn multiple_get(&self, keys: &[String]) -> Result<Vec<String>, CacheError> {
+        let mut stored = self.keys();
+        keys.iter()
+            .map(|key| match Self::read(&mut stored, key) {
+                Some(Value::String(value)) => Ok(value.clone()),
+                _ => Err(CacheError::OperationFailed),
+            })
+            .collect()
+    }
+
+    /// Set a key if it doesn't exist yet, with an expiration time
+    ///
+    /// Returns `true` if the key was set.
+    pub fn set_if_absent(&self, key: &str, expiration_ms: u32) -> bool {
+        let mut keys = self.keys();
+        if keys.contains_key(key) {
+            return false;
+        }
+
+        keys.insert(
+            key.to_string(),
+            Entry::new(Value::String("1".to_string()), expires(expiration_ms)),
+        );
+
+        true
+    }
+
+    /// Add a member to a set, refreshing the expiration time of the set
+    ///
+    /// Returns if the member was added, and the number of members.
+    pub fn set_add(
+        &self,
+        key: &str,
+        member: &str,
+        expiration_ms: u32,
+    ) -> Result<(bool, u32), CacheError> {
+        let mut keys = self.keys();
+        let entry = Self::access(&mut keys, key, || {
+            Entry::new(Value::Set(HashSet::new()), None)
+        });
+
+        let Value::Set(members) = &mut entry.value else {
+            return Err(CacheError::OperationFailed);
+        };
+
+        let added = members.insert(member.to_string());
+        let count = members.len() as u32;
+        entry.expires = expires(expiration_ms);
+        Ok((added, count))
+    }
+
+    /// Delete a key
+    pub fn delete(&self, key: &str) {
+        self.keys().remove(key);
+    }
+
+    /// Scan up to `count` keys starting with `prefix` from `cursor`, in key
+    ///  order, deleting the strings and hashes ending with one of `suffixes`
+    ///  which weren't read or written for `max_idle`
+    pub fn purge_idle(
+        &self,
+        prefix: &str,
+        cursor: u64,
+        count: u32,
+        suffixes: &[&str],
+        max_idle: Duration,
+    ) -> PurgeBatch {
+        let mut keys = self.keys();
+        let mut matching: Vec<&String> =
+            keys.keys().filter(|key| key.starts_with(prefix)).collect();
+        matching.sort();
+
+        let batch: Vec<String> = matching
+            .into_iter()
+            .skip(cursor as usize)
+            .take(count as usize)
+            .cloned()
+            .collect();
+
+        let scanned = batch.len() as u32;
+        let mut purged = 0;
+        for key in batch {
+            let idle = keys.get(&key).is_some_and(|entry| {
+                entry.accessed.elapsed() > max_idle && !matches!(entry.value, Value::Stream(_))
+            });
+
+            if idle && suffixes.iter().any(|suffix| key.ends_with(suffix)) {
+                keys.remove(&key);
+                purged += 1;
+            }
+        }
+
+        // deleted keys shift the following ones
+        let next = cursor + (scanned - purged) as u64;
+        PurgeBatch {
+            cursor: if scanned < count { 0 } else { next },
+            scanned,
+            purged,
+        }
+    }
+
+    /// Prepend a value to a list, trimming the list to its `max_len` first
+    ///  values and refreshing its expiration time
+    pub fn list_push(
+        &self,
+        key: &str,
+        value: &str,
+        max_len: usize,
+        expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        let mut keys = self.keys();
+        let entry = Self::access(&mut keys, key, || {
+            Entry::new(Value::List(VecDeque::new()), None)
+        });
+
+        let Value::List(values) = &mut entry.value else {
+            return Err(CacheError::OperationFailed);
+        };
+
+        values.push_front(value.to_string());
+        values.truncate(max_len.max(1));
+        entry.expires = expires(expiration_ms);
+        Ok(())
+    }
+
+    /// Values of a list, first to last, none if missing
+    pub fn list_range(&self, key: &str) -> Result<Vec<String>, CacheError> {
+        match Self::read(&mut self.keys(), key) {
+            None => Ok(vec![]),
+            Some(Value::List(values)) => Ok(values.iter().cloned().collect()),
+            Some(_) => Err(CacheError::OperationFailed),
+        }
+    }
+
+    /// Update the fields of a hash, creating it if needed, and refresh
+    ///  the expiration time of the whole hash
+    fn update_hash(
+        &self,
+        key: &str,
+        expiration_ms: u32,
+        update: impl FnOnce(&mut HashMap<String, String>) -> Result<(), CacheError>,
+    ) -> Result<(), CacheError> {
+        let mut keys = self.keys();
+        let entry = Self::access(&mut keys, key, || {
+            Entry::new(Value::Hash(HashMap::new()), None)
+        });
+
+        let Value::Hash(fields) = &mut entry.value else {
+            return Err(CacheError::OperationFailed);
+        };
+
+        update(fields)?;
+        entry.expires = expires(expiration_ms);
+        Ok(())
+    }
+
+    /// Set fields of a hash, refreshing the expiration time of the whole hash
+    pub fn hash_set(
+        &self,
+        key: &str,
+        fields: &[(String, String)],
+        expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        self.update_hash(key, expiration_ms, |hash| {
+            hash.extend(fields.iter().cloned());
+            Ok(())
+        })
+    }
+
+    /// Increment fields of a hash by one, refreshing the expiration time
+    ///  of the whole hash
+    pub fn hash_increment(
+        &self,
+        key: &str,
+        fields: &[&str],
+        expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        self.update_hash(key, expiration_ms, |hash| {
+            for field in fields {
+                let value = hash.entry(field.to_string()).or_insert("0".to_string());
+                let count = value
+                    .parse::<i64>()
+                    .map_err(|_| CacheError::OperationFailed)?;
+
+                *value = (count + 1).to_string();
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Get all fields of a hash, none if missing
+    pub fn hash_get_all(&self, key: &str) -> Result<HashMap<String, String>, CacheError> {
+        match Self::read(&mut self.keys(), key) {
+            None => Ok(HashMap::new()),
+            Some(Value::Hash(fields)) => Ok(fields.clone()),
+            Some(_) => Err(CacheError::OperationFailed),
+        }
+    }
+
+    /// Get a field of a hash, none if missing
+    pub fn hash_get(&self, key: &str, field: &str) -> Result<Option<String>, CacheError> {
+        match Self::read(&mut self.keys(), key) {
+            None => Ok(None),
+            Some(Value::Hash(fields)) => Ok(fields.get(field).cloned()),
+            Some(_) => Err(CacheError::OperationFailed),
+        }
+    }
+
+    /// Delete fields of a hash, and the hash once empty
+    pub fn hash_delete(&self, key: &str, fields: &[String]) -> Result<(), CacheError> {
+        let mut keys = self.keys();
+        let Some(entry) = keys.get_mut(key) else {
+            return Ok(());
+        };
+
+        entry.accessed = Instant::now();
+        let Value::Hash(hash) = &mut entry.value else {
+            return Err(CacheError::OperationFailed);
+        };
+
+        for field in fields {
+            hash.remove(field);
+        }
+
+        if hash.is_empty() {
+            keys.remove(key);
+        }
+
+        Ok(())
+    }
+
+    /// Apply an operation to a stream, creating it if needed
+    fn with_stream<T>(
+        &self,
+        key: &str,
+        operation: impl FnOnce(&mut Stream) -> Result<T, CacheError>,
+    ) -> Result<T, CacheError> {
+        let mut keys = self.keys();
+        let entry = Self::access(&mut keys, key, || {
+            Entry::new(Value::Stream(Stream::default()), None)
+        });
+
+        let Value::Stream(stream) = &mut entry.value else {
+            return Err(CacheError::OperationFailed);
+        };
+
+        operation(stream)
+    }
+
+    /// Append an entry to a stream, trimming the stream to `max_len` entries
+    ///
+    /// Returns the ID of the entry and the number of oldest entries trimmed.
+    pub fn stream_add(
+        &self,
+        key: &str,
+        fields: &[(&str, String)],
+        max_len: usize,
+    ) -> Result<(String, usize), CacheError> {
+        self.with_stream(key, |stream| {
+            stream.last_seq += 1;
+            let fields = fields
+                .iter()
+                .map(|(field, value)| (field.to_string(), value.clone()))
+                .collect();
+
+            stream.entries.push_back((stream.last_seq, fields));
+            let trimmed = stream.entries.len().saturating_sub(max_len);
+            for (seq, _) in stream.entries.drain(..trimmed) {
+                // trimmed entries can no longer be reclaimed
+                for group in stream.groups.values_mut() {
+                    group.pending.remove(&seq);
+                }
+            }
+
+            Ok((entry_id(stream.last_seq), trimmed))
+        })
+    }
+
+    /// Create a consumer group reading the stream from the start, or only
+    ///  the entries added from now on. Existing groups are left as-is.
+    pub fn stream_create_group(
+        &self,
+        key: &str,
+        group: &str,
+        from_start: bool,
+    ) -> Result<(), CacheError> {
+        self.with_stream(key, |stream| {
+            let last_delivered = match from_start {
+                true => 0,
+                false => stream.last_seq,
+            };
+
+            stream
+                .groups
+                .entry(group.to_string())
+                .or_insert_with(|| Group {
+                    last_delivered,
+                    pending: HashMap::new(),
+                });
+
+            Ok(())
+        })
+    }
+
+    /// Read up to `count` entries not yet delivered to the group, keeping
+    ///  them pending until acknowledged
+    pub fn stream_read_group(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+    ) -> Result<Vec<StreamEntryFields>, CacheError> {
+        self.with_stream(key, |stream| {
+            let group = stream
+                .groups
+                .get_mut(group)
+                .ok_or(CacheError::OperationFailed)?;
+
+            let entries: Vec<StreamEntryFields> = stream
+                .entries
+                .iter()
+                .filter(|(seq, _)| *seq > group.last_delivered)
+                .take(count)
+                .map(|(seq, fields)| (entry_id(*seq), fields.clone()))
+                .collect();
+
+            let now = Instant::now();
+            for (id, _) in &entries {
+                let Some(seq) = entry_seq(id) else {
+                    continue;
+                };
+
+                group.last_delivered = seq;
+                group.pending.insert(
+                    seq,
+                    Pending {
+                        consumer: consumer.to_string(),
+                        delivered: now,
+                        count: 1,
+                    },
+                );
+            }
+
+            Ok(entries)
+        })
+    }
+
+    /// Take over up to `count` entries delivered to another consumer of the
+    ///  group but not acknowledged for at least `min_idle_ms`
+    pub fn stream_reclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle_ms: usize,
+        count: usize,
+    ) -> Result<Vec<StreamEntryFields>, CacheError> {
+        self.with_stream(key, |stream| {
+            let group = stream
+                .groups
+                .get_mut(group)
+                .ok_or(CacheError::OperationFailed)?;
+
+            let now = Instant::now();
+            let min_idle = Duration::from_millis(min_idle_ms as u64);
+            let mut entries = vec![];
+            for (seq, fields) in &stream.entries {
+                if entries.len() >= count {
+                    break;
+                }
+
+                let Some(pending) = group.pending.get_mut(seq) else {
+                    continue;
+                };
+
+                if now.duration_since(pending.delivered) < min_idle {
+                    continue;
+                }
+
+                pending.consumer = consumer.to_string();
+                pending.delivered = now;
+                pending.count += 1;
+                entries.push((entry_id(*seq), fields.clone()));
+            }
+
+            Ok(entries)
+        })
+    }
+
+    /// Acknowledge processed entries of a stream
+    pub fn stream_ack(&self, key: &str, group: &str, ids: &[String]) -> Result<(), CacheError> {
+        self.with_stream(key, |stream| {
+            if let Some(group) = stream.groups.get_mut(group) {
+                for seq in ids.iter().filter_map(|id| entry_seq(id)) {
+                    group.pending.remove(&seq);
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Number of times each of the given pending entries was delivered
+    ///
+    /// Entries no longer pending are left out.
+    pub fn stream_deliveries(
+        &self,
+        key: &str,
+        group: &str,
+        ids: &[String],
+    ) -> Result<HashMap<String, u64>, CacheError> {
+        self.with_stream(key, |stream| {
+            let Some(group) = stream.groups.get(group) else {
+                return Ok(HashMap::new());
+            };
+
+            Ok(ids
+                .iter()
+                .filter_map(|id| {
+                    let pending = group.pending.get(&entry_seq(id)?)?;
+                    Some((id.clone(), pending.count))
+                })
+                .collect())
+        })
+    }
+
+    /// Up to `count` entries of a stream, oldest first, following the
+    ///  entry `after` if set
+    pub fn stream_range(
+        &self,
+        key: &str,
+        after: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<StreamEntryFields>, CacheError> {
+        let after = match after {
+            Some(id) => entry_seq(id).ok_or(CacheError::OperationFailed)?,
+            None => 0,
+        };
+
+        self.with_stream(key, |stream| {
+            Ok(stream
+                .entries
+                .iter()
+                .filter(|(seq, _)| *seq > after)
+                .take(count)
+                .map(|(seq, fields)| (entry_id(*seq), fields.clone()))
+                .collect())
+        })
+    }
+
+    /// Entries of a stream with the given IDs
+    ///
+    /// Entries no longer in the stream are left out.
+    pub fn stream_entries(
+        &self,
+        key: &str,
+        ids: &[String],
+    ) -> Result<Vec<StreamEntryFields>, CacheError> {
+        self.with_stream(key, |stream| {
+            Ok(ids
+                .iter()
+                .filter_map(|id| {
+                    let seq = entry_seq(id)?;
+                    let (_, fields) = stream.entries.iter().find(|(s, _)| *s == seq)?;
+                    Some((entry_id(seq), fields.clone()))
+                })
+                .collect())
+        })
+    }
+
+    /// Delete entries of a stream, along with their pending deliveries
+    ///
+    /// Returns the number of entries deleted.
+    pub fn stream_remove(&self, key: &str, ids: &[String]) -> Result<usize, CacheError> {
+        self.with_stream(key, |stream| {
+            let seqs: Vec<u64> = ids.iter().filter_map(|id| entry_seq(id)).collect();
+            let len = stream.entries.len();
+            stream.entries.retain(|(seq, _)| !seqs.contains(seq));
+            for group in stream.groups.values_mut() {
+                group.pending.retain(|seq, _| !seqs.contains(seq));
+            }
+
+            Ok(len - stream.entries.len())
+        })
+    }
+
+    /// Consumer of a pending entry
+    #[cfg(test)]
+    fn pending_consumer(&self, key: &str, group: &str, id: &str) -> Option<String> {
+        self.with_stream(key, |stream| {
+            Ok(stream
+                .groups
+                .get(group)
+                .and_then(|group| group.pending.get(&entry_seq(id)?))
+                .map(|pending| pending.consumer.clone()))
+        })
+        .ok()
+        .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(value: &str) -> Vec<(&'static str, String)> {
+        vec![("item", value.to_string())]
+    }
+
+    #[test]
+    fn test_increment() {
+        let store = MemoryStore::default();
+        assert_eq!(store.increment("packet", 1000).unwrap(), 1);
+        assert_eq!(store.increment("packet", 1000).unwrap(), 2);
+        assert_eq!(store.increment("other", 1000).unwrap(), 1);
+
+        // expired counters start over
+        assert_eq!(store.increment("short", 0).unwrap(), 1);
+        assert_eq!(store.increment("short", 0).unwrap(), 1);
+
+        // not a counter
+        store.hash_set("hash", &[], 1000).unwrap();
+        assert!(store.increment("hash", 1000).is_err());
+    }
+
+    #[test]
+    fn test_list() {
+        let store = MemoryStore::default();
+        assert!(store.list_range("trail").unwrap().is_empty());
+
+        for value in ["1", "2", "3"] {
+            store.list_push("trail", value, 2, 1000).unwrap();
+        }
+        assert_eq!(store.list_range("trail").unwrap(), vec!["3", "2"]);
+
+        // expired lists are empty
+        store.list_push("short", "1", 2, 0).unwrap();
+        assert!(store.list_range("short").unwrap().is_empty());
+
+        // not a list
+        store.hash_set("hash", &[], 1000).unwrap();
+        assert!(store.list_push("hash", "1", 2, 1000).is_err());
+        assert!(store.list_range("hash").is_err());
+    }
+
+    #[test]
+    fn test_set() {
+        let store = MemoryStore::default();
+        assert_eq!(store.set_add("reporters", "a", 1000), Ok((true, 1)));
+        assert_eq!(store.set_add("reporters", "a", 1000), Ok((false, 1)));
+        assert_eq!(store.set_add("reporters", "b", 1000), Ok((true, 2)));
+
+        // expired sets are empty
+        store.set_add("short", "a", 0).unwrap();
+        assert_eq!(store.set_add("short", "b", 1000), Ok((true, 1)));
+
+        // not a set
+        store.hash_set("hash", &[], 1000).unwrap();
+        assert!(store.set_add("hash", "a", 1000).is_err());
+    }
+
+    #[test]
+    fn test_values() {
+        let store = MemoryStore::default();
+        store.multiple_set(
+            vec![("a".into(), "1".into()), ("b".into(), "2".into())],
+            1000,
+        );
+        assert_eq!(
+            store.multiple_get(&["b".into(), "a".into()]).unwrap(),
+            vec!["2", "1"]
+        );
+        assert!(store.multiple_get(&["a".into(), "c".into()]).is_err());
+
+        assert!(store.set_if_absent("stored", 1000));
+        assert!(!store.set_if_absent("stored", 1000));
+        store.delete("stored");
+        assert!(store.set_if_absent("stored", 1000));
+
+        // expired keys are absent
+        assert!(store.set_if_absent("expired", 0));
+        assert!(store.set_if_absent("expired", 0));
+    }
+
+    #[test]
+    fn test_hash() {
+        let store = MemoryStore::default();
+        assert!(store.hash_get_all("hash").unwrap().is_empty());
+
+        store
+            .hash_set("hash", &[("a".into(), "x".into())], 1000)
+            .unwrap();
+        store
+            .hash_increment("hash", &["n", "n", "m"], 1000)
+            .unwrap();
+        let hash = store.hash_get_all("hash").unwrap();
+        assert_eq!(hash.len(), 3);
+        assert_eq!(hash["a"], "x");
+        assert_eq!(hash["n"], "2");
+        assert_eq!(hash["m"], "1");
+        assert_eq!(store.hash_get("hash", "a").unwrap(), Some("x".to_string()));
+        assert_eq!(store.hash_get("hash", "b").unwrap(), None);
+        assert_eq!(store.hash_get("missing", "a").unwrap(), None);
+
+        // not a number
+        assert!(store.hash_increment("hash", &["a"], 1000).is_err());
+
+        store
+            .hash_delete("hash", &["a".into(), "n".into()])
+            .unwrap();
+        assert_eq!(store.hash_get_all("hash").unwrap().len(), 1);
+        store.hash_delete("hash", &["m".into()]).unwrap();
+        assert!(store.hash_get_all("hash").unwrap().is_empty());
+
+        // not a hash
+        store.multiple_set(vec![("value".into(), "1".into())], 1000);
+        assert!(store.hash_get_all("value").is_err());
+        assert!(store.hash_get("value", "a").is_err());
+    }
+
+    #[test]
+    fn test_purge_idle() {
+        let store = MemoryStore::default();
+        let max_idle = Duration::from_millis(500);
+        let idle = |key: &str| {
+            let past = Instant::now().checked_sub(Duration::from_secs(1)).unwrap();
+            store.keys().get_mut(key).unwrap().accessed = past;
+        };
+
+        store.multiple_set(vec![("tlm:a:1:state".into(), "x".into())], 60000);
+        store
+            .hash_set("tlm:a:2:state", &[("a".into(), "x".into())], 60000)
+            .unwrap();
+        store.stream_add("tlm:a:3:state", &fields("x"), 10).unwrap();
+        store.keys().insert(
+            "tlm:a:4:state".into(),
+            Entry::new(Value::String("x".into()), None),
+        );
+        store.keys().insert(
+            "tlm:a:5:session".into(),
+            Entry::new(Value::Hash(HashMap::new()), None),
+        );
+        store.keys().insert(
+            "tlm:b:6:state".into(),
+            Entry::new(Value::String("x".into()), None),
+        );
+        let keys = store.keys().keys().cloned().collect::<Vec<_>>();
+        keys.iter().for_each(|key| idle(key));
+
+        // reads reset the idle time
+        store.hash_get("tlm:a:2:state", "a").unwrap();
+
+        let batch = store.purge_idle("tlm:a:", 0, 4, &[":state"], max_idle);
+        assert_eq!(
+            batch,
+            PurgeBatch {
+                cursor: 2,
+                scanned: 4,
+                purged: 2
+            }
+        );
+
+        let batch = store.purge_idle("tlm:a:", batch.cursor, 4, &[":state"], max_idle);
+        assert_eq!(
+            batch,
+            PurgeBatch {
+                cursor: 0,
+                scanned: 1,
+                purged: 0
+            }
+        );
+
+        let suffixes = [":state", ":session"];
+        let batch = store.purge_idle("tlm:a:", 0, 4, &suffixes, max_idle);
+        assert_eq!(batch.purged, 1);
+        assert_eq!(store.keys().len(), 3);
+        assert!(store.keys().contains_key("tlm:a:2:state"));
+        assert!(store.keys().contains_key("tlm:b:6:state"));
+    }
+
+    #[test]
+    fn test_stream_group() {
+        let store = MemoryStore::default();
+        store.stream_add("stream", &fields("old"), 10).unwrap();
+
+        // new entries only, or from the start
+        store.stream_create_group("stream", "new", false).unwrap();
+        store.stream_create_group("stream", "all", true).unwrap();
+        let (id, trimmed) = store.stream_add("stream", &fields("new"), 10).unwrap();
+        assert_eq!(trimmed, 0);
+
+        let entries = store.stream_read_group("stream", "new", "c1", 10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, id);
+        assert_eq!(entries[0].1["item"], "new");
+        assert!(store
+            .stream_read_group("stream", "new", "c1", 10)
+            .unwrap()
+            .is_empty());
+
+        let entries = store.stream_read_group("stream", "all", "c1", 1).unwrap();
+        assert_eq!(entries[0].1["item"], "old");
+        let entries = store.stream_read_group("stream", "all", "c1", 10).unwrap();
+        assert_eq!(entries[0].1["item"], "new");
+
+        // unknown group
+        assert!(store
+            .stream_read_group("stream", "unknown", "c1", 10)
+            .is_err());
+    }
+
+    #[test]
+    fn test_stream_pending() {
+        let store = MemoryStore::default();
+        store.stream_create_group("stream", "group", true).unwrap();
+        let (first, _) = store.stream_add("stream", &fields("1"), 10).unwrap();
+        let (second, _) = store.stream_add("stream", &fields("2"), 10).unwrap();
+        store
+            .stream_read_group("stream", "group", "c1", 10)
+            .unwrap();
+
+        let ids = vec![first.clone(), second.clone()];
+        let deliveries = store.stream_deliveries("stream", "group", &ids).unwrap();
+        assert_eq!(deliveries[&first], 1);
+
+        // not idle long enough
+        assert!(store
+            .stream_reclaim("stream", "group", "c2", 60000, 10)
+            .unwrap()
+            .is_empty());
+
+        store
+            .stream_ack("stream", "group", std::slice::from_ref(&first))
+            .unwrap();
+        let reclaimed = store
+            .stream_reclaim("stream", "group", "c2", 0, 10)
+            .unwrap();
+        assert_eq!(reclaimed.len(), 1);
+        assert_eq!(reclaimed[0].0, second);
+        assert_eq!(
+            store.pending_consumer("stream", "group", &second),
+            Some("c2".to_string())
+        );
+
+        let deliveries = store.stream_deliveries("stream", "group", &ids).unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[&second], 2);
+    }
+
+    #[test]
+    fn test_stream_trim() {
+        let store = MemoryStore::default();
+        store.stream_create_group("stream", "group", true).unwrap();
+        for item in 0..3 {
+            store
+                .stream_add("stream", &fields(&item.to_string()), 5)
+                .unwrap();
+        }
+
+        store.stream_read_group("stream", "group", "c1", 1).unwrap();
+        let (_, trimmed) = store.stream_add("stream", &fields("3"), 2).unwrap();
+        assert_eq!(trimmed, 2);
+
+        // the oldest entries are gone, pending or not
+        assert!(store
+            .stream_reclaim("stream", "group", "c2", 0, 10)
+            .unwrap()
+            .is_empty());
+
+        let entries = store
+            .stream_read_group("stream", "group", "c1", 10)
+            .unwrap();
+        let items: Vec<&str> = entries.iter().map(|(_, f)| f["item"].as_str()).collect();
+        assert_eq!(items, vec!["2", "3"]);
+    }
+
+    #[test]
+    fn test_stream_range() {
+        let store = MemoryStore::default();
+        let ids: Vec<String> = (0..5)
+            .map(|item| {
+                let (id, _) = store
+                    .stream_add("stream", &fields(&item.to_string()), 10)
+                    .unwrap();
+                id
+            })
+            .collect();
+
+        let items = |entries: Vec<StreamEntryFields>| -> Vec<String> {
+            entries
+                .into_iter()
+                .map(|(_, f)| f["item"].clone())
+                .collect()
+        };
+
+        let page = store.stream_range("stream", None, 2).unwrap();
+        assert_eq!(page[1].0, ids[1]);
+        assert_eq!(items(page), vec!["0", "1"]);
+
+        let page = store.stream_range("stream", Some(&ids[1]), 10).unwrap();
+        assert_eq!(items(page), vec!["2", "3", "4"]);
+        assert!(store.stream_range("stream", Some("garbled"), 2).is_err());
+
+        let wanted = vec![ids[3].clone(), ids[0].clone(), entry_id(42)];
+        let entries = store.stream_entries("stream", &wanted).unwrap();
+        assert_eq!(items(entries), vec!["3", "0"]);
+
+        // pending deliveries of removed entries can't be reclaimed
+        store.stream_create_group("stream", "group", true).unwrap();
+        store
+            .stream_read_group("stream", "group", "c1", 10)
+            .unwrap();
+        assert_eq!(store.stream_remove("stream", &wanted).unwrap(), 2);
+        let reclaimed = store
+            .stream_reclaim("stream", "group", "c2", 0, 10)
+            .unwrap();
+        assert_eq!(items(reclaimed), vec!["1", "2", "4"]);
+        assert_eq!(store.stream_remove("stream", &wanted).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_entry_id() {
+        assert_eq!(entry_seq(&entry_id(42)), Some(42));
+        assert_eq!(entry_seq("garbled"), None);
+    }
+}
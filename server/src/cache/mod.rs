@@ -3,7 +3,21 @@
 
 #[macro_use]
 pub mod macros;
+pub mod batch;
+pub mod gis;
 pub mod pool;
+pub mod retention;
+pub mod snapshot;
+
+#[cfg(not(any(test, feature = "memory_backends")))]
+mod stream;
+
+#[cfg(any(test, feature = "memory_backends"))]
+pub mod memory;
+
+use crate::stats::Stats;
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::Deserialize;
 
 /// Wrapper struct for our Redis Pools
 #[derive(Clone, Debug)]
@@ -14,6 +28,39 @@ pub struct TelemetryPools {
     pub adsb: pool::TelemetryPool,
 }
 
+/// Number of entries trimmed from a capped stream by appending an entry
+pub fn trimmed(len_before: usize, len_after: usize) -> usize {
+    (len_before + 1).saturating_sub(len_after)
+}
+
+/// Logs and counts the oldest entries trimmed from a full stream
+pub fn record_trimmed(stats: &Stats, key: &str, trimmed: usize) {
+    if trimmed > 0 {
+        cache_warn!("stream {key} full, dropped {trimmed} oldest entries.");
+        stats.record_dropped(key, trimmed as u64);
+    }
+}
+
+/// Network timestamp of a JSON serialized queue item
+#[derive(Deserialize)]
+struct Stamped {
+    /// When the item was received
+    timestamp_network: DateTime<Utc>,
+}
+
+/// If a JSON serialized item was received more than `max_age_ms` before
+///  `now`. Items without network timestamp, or a `max_age_ms` of 0,
+///  are never stale.
+pub fn is_stale(item: &str, now: DateTime<Utc>, max_age_ms: u32) -> bool {
+    if max_age_ms == 0 {
+        return false;
+    }
+
+    serde_json::from_str::<Stamped>(item).is_ok_and(|stamped| {
+        now - stamped.timestamp_network > Duration::milliseconds(max_age_ms as i64)
+    })
+}
+
 /// Convert bytes to a key
 pub fn bytes_to_key(bytes: &[u8]) -> String {
     bytes
@@ -21,6 +68,44 @@ pub fn bytes_to_key(bytes: &[u8]) -> String {
         .fold("".to_string(), |acc, byte| format!("{acc}{:02x}", byte))
 }
 
+/// Number of bytes of the payload digest kept in packet keys
+const PACKET_KEY_DIGEST_BYTES: usize = 16;
+
+/// Key identifying the content of a packet of the given type, e.g. to
+///  count how many times it was reported
+///
+/// The payload is hashed with SHA-256, truncated to 128 bits: keys have
+///  the same length for all protocols and payloads. The type is part of
+///  the key, the same bytes received as different protocols are counted
+///  separately.
+///
+/// Keys are spread over 16 shards by the first hex digit of the digest,
+///  held in a hash tag (`adsb:{a}:a3f0...`): keys of a shard share a
+///  Redis Cluster slot, so their increments can be pipelined together.
+pub fn packet_key(packet_type: &str, payload: &[u8]) -> String {
+    let digest = bytes_to_key(&openssl::sha::sha256(payload)[..PACKET_KEY_DIGEST_BYTES]);
+    format!("{packet_type}:{{{}}}:{digest}", &digest[..1])
+}
+
+/// Hash tag of a key, the part Redis Cluster hashes to find its slot,
+///  empty for keys without hash tag
+pub fn hash_tag(key: &str) -> &str {
+    key.split_once('{')
+        .and_then(|(_, rest)| rest.split_once('}'))
+        .map_or("", |(tag, _)| tag)
+}
+
+/// Convert a key created by [`bytes_to_key`] back to bytes
+pub fn key_to_bytes(key: &str) -> Option<Vec<u8>> {
+    key.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -31,4 +116,173 @@ mod tests {
         let key = bytes_to_key(&frame);
         assert_eq!(key, "01020304");
     }
+
+    #[test]
+    fn test_packet_key() {
+        let payload = hex::decode("8D4840D6202CC371C32CE0576098").unwrap();
+        let key = packet_key("adsb", &payload);
+        assert_eq!(key.len(), "adsb:{0}:".len() + 2 * PACKET_KEY_DIGEST_BYTES);
+        assert!(key.starts_with("adsb:{"));
+        assert_eq!(packet_key("adsb", &payload), key);
+
+        // the same bytes as another protocol, in the same shard
+        assert_ne!(packet_key("netrid", &payload), key);
+        assert!(packet_key("netrid", &payload).ends_with(&key["adsb:".len()..]));
+
+        // SHA-256 of the empty payload
+        assert_eq!(packet_key("", &[]), ":{e}:e3b0c44298fc1c149afbf4c8996fb924");
+    }
+
+    #[test]
+    fn test_hash_tag() {
+        assert_eq!(hash_tag(&packet_key("", &[])), "e");
+        assert_eq!(hash_tag("tlm:adsb:adsb:{7}:7a01"), "7");
+        assert_eq!(hash_tag("tlm:adsb:4840d6:state"), "");
+        assert_eq!(hash_tag("tlm:{unterminated"), "");
+
+        // all shards are used
+        let tags: std::collections::HashSet<String> = (0..=u8::MAX)
+            .map(|byte| hash_tag(&packet_key("adsb", &[byte])).to_string())
+            .collect();
+        assert_eq!(tags.len(), 16);
+    }
+
+    #[test]
+    fn test_packet_key_collisions() {
+        let mut keys = std::collections::HashSet::new();
+
+        // every payload of up to two bytes
+        keys.insert(packet_key("adsb", &[]));
+        for first in 0..=u8::MAX {
+            keys.insert(packet_key("adsb", &[first]));
+            for second in 0..=u8::MAX {
+                keys.insert(packet_key("adsb", &[first, second]));
+            }
+        }
+        assert_eq!(keys.len(), 1 + 256 + 256 * 256);
+
+        // frames differing by a single bit, or by trailing zeros
+        keys.clear();
+        let frame = [0u8; 14];
+        keys.insert(packet_key("adsb", &frame));
+        for bit in 0..frame.len() * 8 {
+            let mut flipped = frame;
+            flipped[bit / 8] ^= 1 << (bit % 8);
+            keys.insert(packet_key("adsb", &flipped));
+        }
+        for len in 0..frame.len() {
+            keys.insert(packet_key("adsb", &frame[..len]));
+        }
+        assert_eq!(keys.len(), 1 + frame.len() * 8 + frame.len());
+    }
+
+    #[test]
+    fn test_key_to_bytes() {
+        let frame = vec![0x01, 0xab, 0xFF, 0x00];
+        let key = bytes_to_key(&frame);
+        assert_eq!(key_to_bytes(&key), Some(frame));
+        assert_eq!(key_to_bytes("abc"), None);
+        assert_eq!(key_to_bytes("zz"), None);
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let now = Utc::now();
+        let item = |age_ms: i64| {
+            serde_json::json!({
+                "identifier": "4840d6",
+                "timestamp_network": now - Duration::milliseconds(age_ms),
+            })
+            .to_string()
+        };
+
+        assert!(!is_stale(&item(1000), now, 5000));
+        assert!(is_stale(&item(6000), now, 5000));
+
+        // disabled
+        assert!(!is_stale(&item(6000), now, 0));
+
+        // not timestamped
+        assert!(!is_stale(r#"{"identifier": "4840d6"}"#, now, 5000));
+        assert!(!is_stale("{", now, 5000));
+    }
+
+    #[test]
+    fn test_reclaimed_not_stale() {
+        use super::gis::{GIS_CONSUMER_GROUP, GIS_ITEM_FIELD, GIS_PENDING_IDLE_MS};
+        use super::memory::MemoryStore;
+
+        // an item read by a consumer which crashed, received just long
+        //  enough ago to be reclaimed by the next consumer
+        let now = Utc::now();
+        let idle = Duration::try_milliseconds(GIS_PENDING_IDLE_MS as i64).unwrap();
+        let item = serde_json::json!({
+            "identifier": "4840d6",
+            "timestamp_network": now - idle,
+        })
+        .to_string();
+
+        let store = MemoryStore::default();
+        store
+            .stream_create_group("gis", GIS_CONSUMER_GROUP, true)
+            .unwrap();
+        store
+            .stream_add("gis", &[(GIS_ITEM_FIELD, item)], 10)
+            .unwrap();
+        store
+            .stream_read_group("gis", GIS_CONSUMER_GROUP, "c1", 10)
+            .unwrap();
+        let reclaimed = store
+            .stream_reclaim("gis", GIS_CONSUMER_GROUP, "c2", 0, 10)
+            .unwrap();
+        assert_eq!(reclaimed.len(), 1);
+        let item = &reclaimed[0].1[GIS_ITEM_FIELD];
+
+        // kept by default, and by the settings the service starts with
+        let config = crate::Config::default();
+        assert!(!is_stale(item, now, config.gis_stale_after_ms));
+
+        assert!(!is_stale(item, now, GIS_PENDING_IDLE_MS as u32 + 1));
+
+        // a stale time of the idle time drops it once reclaimed
+        let later = now + Duration::try_milliseconds(1).unwrap();
+        assert!(is_stale(item, later, GIS_PENDING_IDLE_MS as u32));
+    }
+
+    #[test]
+    fn test_trimmed() {
+        // below capacity
+        assert_eq!(trimmed(0, 1), 0);
+        assert_eq!(trimmed(99, 100), 0);
+
+        // trimming is approximate, a full stream may grow a little
+        assert_eq!(trimmed(100, 101), 0);
+
+        // overrun, the oldest entries were dropped
+        assert_eq!(trimmed(100, 100), 1);
+        assert_eq!(trimmed(110, 100), 11);
+    }
+
+    #[test]
+    fn test_stream_overrun() {
+        use super::memory::MemoryStore;
+        use std::collections::HashMap;
+
+        let store = MemoryStore::default();
+        let stats = Stats::default();
+        for item in 0..5 {
+            let (_, trimmed) = store
+                .stream_add("stream", &[("item", item.to_string())], 3)
+                .unwrap();
+            record_trimmed(&stats, "stream", trimmed);
+        }
+
+        // the oldest entries were dropped
+        let entries = store.stream_range("stream", None, 10).unwrap();
+        let items: Vec<&str> = entries.iter().map(|(_, f)| f["item"].as_str()).collect();
+        assert_eq!(items, vec!["2", "3", "4"]);
+
+        let summary = stats.summary(HashMap::new());
+        assert_eq!(summary.dropped_entries["stream"], 2);
+    }
 }
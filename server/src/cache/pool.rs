@@ -2,24 +2,47 @@
 
 use core::fmt::{Debug, Formatter};
 
-#[cfg(not(test))]
+#[cfg(not(any(test, feature = "memory_backends")))]
+use crate::tls::TlsFiles;
+#[cfg(not(any(test, feature = "memory_backends")))]
 use deadpool_redis::{redis, Pool, Runtime};
 
-use serde::Serialize;
+use super::gis::{GisItem, GisQueue};
+#[cfg(not(test))]
+use super::gis::{GIS_CONSUMER_GROUP, GIS_HISTORICAL_FIELD, GIS_ITEM_FIELD, GIS_PENDING_IDLE_MS};
+use crate::stats::Stats;
+use lib_common::time::{DateTime, Utc};
 use snafu::prelude::Snafu;
+use std::collections::HashMap;
 
 /// Represents a pool of connections to a Redis server.
 ///
 /// The [`TelemetryPool`] struct provides a managed pool of connections to a Redis server.
 /// It allows clients to acquire and release connections from the pool and handles
 /// connection management, such as connection pooling and reusing connections.
-#[cfg(not(test))]
+#[cfg(not(any(test, feature = "memory_backends")))]
 #[derive(Clone)]
 pub struct TelemetryPool {
     /// The underlying pool of Redis connections.
     pool: Pool,
     /// The string prepended to the key being stored.
     key_folder: String,
+    /// Counts the entries dropped from full streams and the waits for
+    ///  connections.
+    stats: Stats,
+}
+
+/// Pool of the keys of a microservice, held in memory instead of
+///  a Redis server. See [`super::memory::MemoryStore`].
+#[cfg(all(not(test), feature = "memory_backends"))]
+#[derive(Clone)]
+pub struct TelemetryPool {
+    /// Keys of all pools of the process.
+    store: std::sync::Arc<super::memory::MemoryStore>,
+    /// The string prepended to the key being stored.
+    key_folder: String,
+    /// Counts the entries dropped from full streams.
+    stats: Stats,
 }
 
 /// Represents a pool of connections to a Redis server.
@@ -33,10 +56,31 @@ pub struct TelemetryPool {
 
 /// Represents a pool of connections to a Redis server for GIS-related data
 #[derive(Clone)]
-#[cfg(not(test))]
+#[cfg(not(any(test, feature = "memory_backends")))]
 pub struct GisPool {
     /// The underlying pool of Redis connections.
     pool: Pool,
+    /// Queues are trimmed to about this many items.
+    max_len: usize,
+    /// Positions received longer ago than this are dropped when read, 0 keeps them.
+    stale_after_ms: u32,
+    /// Counts the items dropped from full queues, stale items and the
+    ///  waits for connections.
+    stats: Stats,
+}
+
+/// GIS queues held in memory instead of a Redis server
+#[derive(Clone)]
+#[cfg(all(not(test), feature = "memory_backends"))]
+pub struct GisPool {
+    /// Keys of all pools of the process.
+    store: std::sync::Arc<super::memory::MemoryStore>,
+    /// Queues are trimmed to this many items.
+    max_len: usize,
+    /// Positions received longer ago than this are dropped when read, 0 keeps them.
+    stale_after_ms: u32,
+    /// Counts the items dropped from full queues and stale items.
+    stats: Stats,
 }
 
 #[derive(Clone, Copy)]
@@ -58,7 +102,7 @@ impl Debug for GisPool {
 }
 
 /// Represents errors that can occur during cache operations.
-#[derive(Debug, Clone, Copy, Snafu)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Snafu)]
 pub enum CacheError {
     /// Could not build configuration for cache.
     #[snafu(display("Could not build configuration for cache."))]
@@ -68,93 +112,360 @@ pub enum CacheError {
     #[snafu(display("Could not connect to redis pool."))]
     CouldNotConnect,
 
+    /// No connection of the Redis pool became free in time.
+    #[snafu(display("No connection of the redis pool became free in time."))]
+    PoolExhausted,
+
     /// The operation on the Redis cache failed.
     #[snafu(display("The operation on the redis cache failed."))]
     OperationFailed,
+
+    /// The value could not be serialized for the cache.
+    #[snafu(display("Could not serialize the value for the cache."))]
+    CouldNotSerialize,
+}
+
+impl CacheError {
+    /// Stable code of the error, for logs and statistics
+    pub fn code(&self) -> &'static str {
+        match self {
+            CacheError::CouldNotConfigure => "cache_config",
+            CacheError::CouldNotConnect => "cache_connect",
+            CacheError::PoolExhausted => "cache_pool_exhausted",
+            CacheError::OperationFailed => "cache_operation",
+            CacheError::CouldNotSerialize => "cache_serialize",
+        }
+    }
+}
+
+/// Outcome of purging a batch of scanned keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PurgeBatch {
+    /// Cursor of the next batch, 0 once all keys were scanned
+    pub cursor: u64,
+
+    /// Keys examined
+    pub scanned: u32,
+
+    /// Keys deleted
+    pub purged: u32,
 }
 
 #[cfg(test)]
 impl GisPool {
     /// Create a new GisPool
-    pub async fn new(_config: crate::config::Config) -> Result<Self, ()> {
+    pub async fn new(_config: crate::config::Config) -> Result<Self, CacheError> {
         println!("(MOCK) creating pool...");
         Ok(GisPool {})
     }
 
-    /// Push items onto a redis queue
-    pub async fn push<T>(&mut self, _item: T, _queue_key: &str) -> Result<(), ()>
-    where
-        T: Serialize + Debug,
-    {
+    /// Count the items dropped from full queues in the given statistics
+    pub fn with_stats(self, _stats: Stats) -> Self {
+        self
+    }
+
+    /// Push an item onto the redis queue of its type
+    pub async fn push<T: GisItem>(&mut self, _item: T) -> Result<(), CacheError> {
         println!("(MOCK) pushing...");
         Ok(())
     }
+
+    /// Push an item replayed with its original timestamps onto the redis
+    ///  queue of its type, never dropped as stale
+    pub async fn push_historical<T: GisItem>(&mut self, _item: T) -> Result<(), CacheError> {
+        println!("(MOCK) pushing historical item...");
+        Ok(())
+    }
+
+    /// Read a batch of up to `count` items from the queue of their type as
+    ///  a consumer of the svc-gis group, see [`GisPool::ack`]
+    pub async fn read_batch<T: GisItem>(
+        &mut self,
+        _consumer: &str,
+        _count: usize,
+        _now: DateTime<Utc>,
+    ) -> Result<Vec<(String, T)>, CacheError> {
+        Ok(vec![])
+    }
+
+    /// Acknowledge items of a queue processed by the consumer
+    pub async fn ack(&mut self, _queue: GisQueue, _ids: &[String]) -> Result<(), CacheError> {
+        Ok(())
+    }
 }
 
-#[cfg(not(test))]
+/// Name of the pool of the svc-gis queues, selecting its settings and
+///  statistics
+#[cfg(not(any(test, feature = "memory_backends")))]
+const POOL_NAME_GIS: &str = "gis";
+
+/// Settings of a pool, the shared `redis.pool` settings overridden by
+///  those of the pool
+#[cfg(not(any(test, feature = "memory_backends")))]
+fn pool_config(config: &crate::config::Config, pool: &str) -> deadpool_redis::PoolConfig {
+    let mut pool_config = config.redis.get_pool_config();
+    let (max_size, wait_timeout_ms) = config.redis_pool_limits(pool);
+    if max_size > 0 {
+        pool_config.max_size = max_size as usize;
+    }
+
+    if wait_timeout_ms > 0 {
+        pool_config.timeouts.wait = Some(std::time::Duration::from_millis(wait_timeout_ms as u64));
+    }
+
+    pool_config
+}
+
+/// Gets a connection from a pool, recording the wait and the state of
+///  the pool in the statistics
+#[cfg(not(any(test, feature = "memory_backends")))]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+async fn checkout(
+    pool: &Pool,
+    name: &str,
+    stats: &Stats,
+) -> Result<deadpool_redis::Connection, CacheError> {
+    let start = std::time::Instant::now();
+    let connection = pool.get().await;
+    let exhausted = matches!(connection, Err(deadpool_redis::PoolError::Timeout(_)));
+    stats.record_pool_checkout(name, pool.status(), start.elapsed(), exhausted);
+
+    connection.map_err(|e| {
+        let error = match exhausted {
+            true => CacheError::PoolExhausted,
+            false => CacheError::CouldNotConnect,
+        };
+
+        cache_error!("[{}] no connection of redis pool {name}: {e}", error.code());
+        stats.record_error_code(error.code());
+        error
+    })
+}
+
+/// Creates the pool of connections to Redis, with the TLS certificates
+///  of the configuration if any (used with a `rediss://` URL)
+///
+/// The settings of the pool are selected by its name, see
+///  [`crate::config::Config::redis_pool_limits`].
+#[cfg(not(any(test, feature = "memory_backends")))]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+fn create_pool(config: &crate::config::Config, pool: &str) -> Result<Pool, String> {
+    let cfg: &deadpool_redis::Config = &config.redis;
+    let files = TlsFiles::load(
+        &config.redis_tls_ca_file,
+        &config.redis_tls_cert_file,
+        &config.redis_tls_key_file,
+    )
+    .map_err(|e| e.to_string())?;
+
+    if files.is_empty() {
+        return cfg
+            .builder()
+            .map_err(|e| e.to_string())?
+            .config(pool_config(config, pool))
+            .runtime(Runtime::Tokio1)
+            .build()
+            .map_err(|e| e.to_string());
+    }
+
+    let Some(url) = cfg.url.as_deref() else {
+        return Err("no connection address found".to_string());
+    };
+
+    let certificates = redis::TlsCertificates {
+        client_tls: files
+            .client
+            .map(|(client_cert, client_key)| redis::ClientTlsConfig {
+                client_cert,
+                client_key,
+            }),
+        root_cert: files.ca,
+    };
+
+    let client = redis::Client::build_with_tls(url, certificates).map_err(|e| e.to_string())?;
+    let manager = deadpool_redis::Manager::new(client.get_connection_info().clone())
+        .map_err(|e| e.to_string())?;
+
+    Pool::builder(manager)
+        .config(pool_config(config, pool))
+        .runtime(Runtime::Tokio1)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(any(test, feature = "memory_backends")))]
 #[cfg(not(tarpaulin_include))]
 // no_coverage: (R5) need redis backend to test
 impl GisPool {
     /// Create a new GisPool
-    pub async fn new(config: crate::config::Config) -> Result<Self, ()> {
-        let cfg: deadpool_redis::Config = config.redis;
-        let details = cfg.url.clone().ok_or_else(|| {
+    pub async fn new(config: crate::config::Config) -> Result<Self, CacheError> {
+        let details = config.redis.url.clone().ok_or_else(|| {
             cache_error!("(GisPool new) no connection address found.");
+            CacheError::CouldNotConfigure
         })?;
 
         cache_info!("(GisPool new) creating pool at {:?}...", details);
 
-        let pool = cfg.create_pool(Some(Runtime::Tokio1)).map_err(|e| {
+        let pool = create_pool(&config, POOL_NAME_GIS).map_err(|e| {
             cache_error!("(GisPool new) could not create pool: {}", e);
+            CacheError::CouldNotConfigure
         })?;
 
-        Ok(GisPool { pool })
-    }
+        let mut gis_pool = GisPool {
+            pool,
+            max_len: config.gis_stream_max_len as usize,
+            stale_after_ms: config.gis_stale_after_ms,
+            stats: Stats::default(),
+        };
 
-    /// Push items onto a redis queue
-    pub async fn push<T>(&mut self, item: T, queue_key: &str) -> Result<(), ()>
-    where
-        T: Serialize + Debug,
-    {
-        if queue_key.is_empty() {
-            cache_error!("queue key cannot be empty.");
-            return Err(());
+        // Items pushed before svc-gis first connects must not be skipped
+        for queue in GisQueue::ALL {
+            if let Err(e) = gis_pool.create_consumer_group(queue).await {
+                cache_warn!("(GisPool new) could not create consumer group of {queue}: {e}");
+            }
         }
 
-        let serialized = serde_json::to_vec(&item).map_err(|e| {
+        Ok(gis_pool)
+    }
+
+    /// Count the items dropped from full queues in the given statistics
+    pub fn with_stats(mut self, stats: Stats) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Get a connection from the pool
+    async fn connection(&self) -> Result<deadpool_redis::Connection, CacheError> {
+        checkout(&self.pool, POOL_NAME_GIS, &self.stats).await
+    }
+
+    /// Create the consumer group of svc-gis on a queue, reading
+    ///  from the start of the queue
+    async fn create_consumer_group(&mut self, queue: GisQueue) -> Result<(), CacheError> {
+        let mut connection = self.connection().await?;
+        super::stream::create_group(&mut connection, queue.key(), GIS_CONSUMER_GROUP, "0").await
+    }
+
+    /// Push an item onto the redis queue of its type
+    ///  The queue is a stream capped to about `gis_stream_max_len` items.
+    pub async fn push<T: GisItem>(&mut self, item: T) -> Result<(), CacheError> {
+        self.add(item, false).await
+    }
+
+    /// Push an item replayed with its original timestamps onto the redis
+    ///  queue of its type, flagged with [`GIS_HISTORICAL_FIELD`] so it
+    ///  isn't dropped as stale
+    pub async fn push_historical<T: GisItem>(&mut self, item: T) -> Result<(), CacheError> {
+        self.add(item, true).await
+    }
+
+    /// Add an item to the queue of its type
+    async fn add<T: GisItem>(&mut self, item: T, historical: bool) -> Result<(), CacheError> {
+        let queue = T::QUEUE;
+        let serialized = serde_json::to_string(&item).map_err(|e| {
             cache_error!("could not serialize item {:#?}: {e}", item);
+            CacheError::CouldNotSerialize
         })?;
 
-        let mut connection = self.pool.get().await.map_err(|e| {
-            cache_error!("could not connect to redis deadpool: {e}");
-        })?;
+        let mut fields = vec![(GIS_ITEM_FIELD, serialized)];
+        if historical {
+            fields.push((GIS_HISTORICAL_FIELD, true.to_string()));
+        }
 
-        let result = redis::pipe()
-            .atomic()
-            .lpush(queue_key, serialized)
-            .query_async(&mut connection)
-            .await
-            .map_err(|e| {
-                cache_error!("Operation failed, redis error: {}", e);
-            })?;
+        let mut connection = self.connection().await?;
+        let (_, trimmed) =
+            super::stream::add(&mut connection, queue.key(), &fields, self.max_len).await?;
+        super::record_trimmed(&self.stats, queue.key(), trimmed);
 
-        let redis::Value::Bulk(values) = result else {
-            cache_error!("Operation failed, unexpected redis response: {:?}", result);
+        Ok(())
+    }
+
+    /// Read a batch of up to `count` items from the queue of their type
+    ///  as a consumer of the [`GIS_CONSUMER_GROUP`], see [`GisPool::ack`]
+    ///
+    /// Items left pending by a crashed consumer for [`GIS_PENDING_IDLE_MS`]
+    ///  are reclaimed before new items are read. Items that can't be
+    ///  deserialized, and positions received more than `gis_stale_after_ms`
+    ///  before `now`, are acknowledged and dropped: a consumer catching up
+    ///  on a backlog skips positions superseded long ago. Historical items,
+    ///  replayed on purpose, are never stale.
+    pub async fn read_batch<T: GisItem>(
+        &mut self,
+        consumer: &str,
+        count: usize,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(String, T)>, CacheError> {
+        let queue = T::QUEUE;
+        let mut connection = self.connection().await?;
+        let mut entries = super::stream::reclaim(
+            &mut connection,
+            queue.key(),
+            GIS_CONSUMER_GROUP,
+            consumer,
+            GIS_PENDING_IDLE_MS,
+            count,
+        )
+        .await?;
+
+        if entries.len() < count {
+            let new_entries = super::stream::read_group(
+                &mut connection,
+                queue.key(),
+                GIS_CONSUMER_GROUP,
+                consumer,
+                count - entries.len(),
+                None,
+            )
+            .await?;
+
+            entries.extend(new_entries);
+        }
 
-            return Err(());
+        let stale_after_ms = match queue.expires() {
+            true => self.stale_after_ms,
+            false => 0,
         };
 
-        match values.len() {
-            1 => Ok(()),
-            _ => {
-                cache_error!("Operation failed, unexpected redis response: {:?}", values);
-                Err(())
+        let mut items = vec![];
+        let mut dropped = vec![];
+        let mut stale = 0;
+        for (id, fields) in entries {
+            let item = fields.get(GIS_ITEM_FIELD);
+            let historical = fields.contains_key(GIS_HISTORICAL_FIELD);
+            if !historical && item.is_some_and(|item| super::is_stale(item, now, stale_after_ms)) {
+                stale += 1;
+                dropped.push(id);
+                continue;
+            }
+
+            match item.and_then(|item| serde_json::from_str::<T>(item).ok()) {
+                Some(item) => items.push((id, item)),
+                None => {
+                    cache_warn!("dropping malformed item {id} of {queue}.");
+                    dropped.push(id);
+                }
             }
         }
+
+        if stale > 0 {
+            cache_debug!("dropped {stale} stale item(s) of {queue}.");
+            self.stats.record_stale(queue.key(), stale);
+        }
+
+        super::stream::ack(&mut connection, queue.key(), GIS_CONSUMER_GROUP, &dropped).await?;
+        Ok(items)
+    }
+
+    /// Acknowledge items of a queue processed by the consumer
+    pub async fn ack(&mut self, queue: GisQueue, ids: &[String]) -> Result<(), CacheError> {
+        let mut connection = self.connection().await?;
+        super::stream::ack(&mut connection, queue.key(), GIS_CONSUMER_GROUP, ids).await
     }
 }
 
-#[cfg(not(test))]
+#[cfg(not(any(test, feature = "memory_backends")))]
 #[cfg(not(tarpaulin_include))]
 // no_coverage: (R5) need redis backend to test
 impl TelemetryPool {
@@ -164,16 +475,16 @@ impl TelemetryPool {
     ///  This is used to differentiate keys inserted into Redis by different
     ///  microservices. For example, an ADS-B key in svc-telemetry might be
     ///  formatted `telemetry:adsb:1234567890`.
-    pub async fn new(config: crate::config::Config, key_folder: &str) -> Result<Self, ()> {
+    pub async fn new(config: crate::config::Config, key_folder: &str) -> Result<Self, CacheError> {
         if key_folder.is_empty() {
             cache_error!("(TelemetryPool new) key folder cannot be empty.");
-            return Err(());
+            return Err(CacheError::CouldNotConfigure);
         }
 
         // the .env file must have REDIS__URL="redis://\<host\>:\<port\>"
-        let cfg: deadpool_redis::Config = config.redis;
-        let details = cfg.url.clone().ok_or_else(|| {
+        let details = config.redis.url.clone().ok_or_else(|| {
             cache_error!("(TelemetryPool new) no connection address found.");
+            CacheError::CouldNotConfigure
         })?;
 
         cache_info!(
@@ -182,17 +493,30 @@ impl TelemetryPool {
             details
         );
 
-        let pool = cfg.create_pool(Some(Runtime::Tokio1)).map_err(|e| {
+        let pool = create_pool(&config, key_folder).map_err(|e| {
             cache_error!("(TelemetryPool new) could not create pool: {}", e);
+            CacheError::CouldNotConfigure
         })?;
 
         cache_info!("(TelemetryPool new) pool created.");
         Ok(TelemetryPool {
             pool,
             key_folder: String::from(key_folder),
+            stats: Stats::default(),
         })
     }
 
+    /// Count the entries dropped from full streams in the given statistics
+    pub fn with_stats(mut self, stats: Stats) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Folder prepended to the keys of the pool
+    pub fn key_folder(&self) -> &str {
+        &self.key_folder
+    }
+
     /// If the key didn't exist, inserts the key with an expiration time.
     /// If the key exists, increments the key and doesn't extend the expiration time.
     ///
@@ -201,10 +525,7 @@ impl TelemetryPool {
         let key = format!("{}:{}", &self.key_folder, key);
         cache_info!("entry with key {}.", &key);
 
-        let mut connection = self.pool.get().await.map_err(|e| {
-            cache_error!("could not connect to redis deadpool: {e}");
-            CacheError::CouldNotConnect
-        })?;
+        let mut connection = self.connection().await?;
 
         let result = redis::pipe()
             .atomic()
@@ -251,6 +572,46 @@ impl TelemetryPool {
         Ok(value as u32)
     }
 
+    /// [`increment`](Self::increment) of multiple keys with their expiration
+    ///  times, in a single round trip
+    ///
+    /// Returns the values of the keys in order. On Redis Cluster, the keys
+    ///  must hash to the same slot, see [`crate::cache::hash_tag`].
+    pub async fn increment_batch(
+        &mut self,
+        keys: &[(String, u32)],
+    ) -> Result<Vec<u32>, CacheError> {
+        cache_debug!("entry with {} keys.", keys.len());
+        if keys.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut connection = self.connection().await?;
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (key, expiration_ms) in keys {
+            let key = format!("{}:{}", &self.key_folder, key);
+            pipe.cmd("INCR")
+                .arg(&key)
+                .cmd("PEXPIRE")
+                .arg(&key)
+                .arg(*expiration_ms)
+                .ignore();
+        }
+
+        let values: Vec<i64> = pipe.query_async(&mut connection).await.map_err(|e| {
+            cache_error!("Operation failed, redis error: {}", e);
+            CacheError::OperationFailed
+        })?;
+
+        if values.len() != keys.len() || values.iter().any(|value| *value < 1) {
+            cache_error!("operation failed, unexpected values: {:?}", values);
+            return Err(CacheError::OperationFailed);
+        }
+
+        Ok(values.into_iter().map(|value| value as u32).collect())
+    }
+
     ///
     /// Set the value of multiple keys
     ///
@@ -259,10 +620,7 @@ impl TelemetryPool {
         keyvals: Vec<(String, String)>,
         expiration_ms: u32,
     ) -> Result<(), CacheError> {
-        let mut connection = self.pool.get().await.map_err(|e| {
-            cache_error!("could not connect to redis deadpool: {e}");
-            CacheError::CouldNotConnect
-        })?;
+        let mut connection = self.connection().await?;
 
         let mut pipe = redis::pipe();
         let mut pipe_ref = pipe.atomic();
@@ -295,10 +653,7 @@ impl TelemetryPool {
         &mut self,
         keys: Vec<String>,
     ) -> Result<Vec<T>, CacheError> {
-        let mut connection = self.pool.get().await.map_err(|e| {
-            cache_error!("could not connect to redis deadpool: {e}");
-            CacheError::CouldNotConnect
-        })?;
+        let mut connection = self.connection().await?;
 
         let result = redis::pipe()
             .atomic()
@@ -343,51 +698,1299 @@ impl TelemetryPool {
 
         Ok(values)
     }
-}
 
-#[cfg(test)]
-#[cfg(not(tarpaulin_include))]
-// no_coverage: (R5) need redis backend to test
-impl TelemetryPool {
-    /// Create a new TelemetryPool
-    /// The 'key_folder' argument is prepended to the key being stored. The
-    ///  complete key will take the format \<folder\>:\<subset\>:\<subset\>:\<key\>.
-    ///  This is used to differentiate keys inserted into Redis by different
-    ///  microservices. For example, an ADS-B key in svc-telemetry might be
-    ///  formatted `telemetry:adsb:1234567890`.
-    pub async fn new(_config: crate::config::Config, key_folder: &str) -> Result<Self, ()> {
-        cache_info!("pool created.");
-        Ok(TelemetryPool {
-            key_folder: String::from(key_folder),
-        })
+    ///
+    /// Set a field of a hash, refreshing the expiration time of the whole hash
+    ///
+    pub async fn hash_set(
+        &mut self,
+        key: &str,
+        field: &str,
+        value: &str,
+        expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        let key = format!("{}:{}", &self.key_folder, key);
+        let mut connection = self.connection().await?;
+
+        redis::pipe()
+            .atomic()
+            .hset(&key, field, value)
+            .ignore()
+            .pexpire(&key, expiration_ms as usize)
+            .ignore()
+            .query_async::<_, ()>(&mut connection)
+            .await
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })
     }
 
-    /// If the key didn't exist, inserts the key with an expiration time.
-    /// If the key exists, increments the key and doesn't extend the expiration time.
     ///
-    /// Returns the order in which this specific key was received (1 for first time).
-    pub async fn increment(&mut self, _key: &str, _expiration_ms: u32) -> Result<u32, CacheError> {
-        Ok(1)
+    /// Get a field of a hash
+    ///
+    pub async fn hash_get(&mut self, key: &str, field: &str) -> Result<Option<String>, CacheError> {
+        let key = format!("{}:{}", &self.key_folder, key);
+        let mut connection = self.connection().await?;
+
+        redis::cmd("HGET")
+            .arg(key)
+            .arg(field)
+            .query_async::<_, Option<String>>(&mut connection)
+            .await
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })
     }
 
     ///
-    /// Set the value of multiple keys
+    /// Get all fields of a hash
     ///
-    pub async fn multiple_set(
+    pub async fn hash_get_all(&mut self, key: &str) -> Result<HashMap<String, String>, CacheError> {
+        let key = format!("{}:{}", &self.key_folder, key);
+        let mut connection = self.connection().await?;
+
+        redis::cmd("HGETALL")
+            .arg(key)
+            .query_async::<_, HashMap<String, String>>(&mut connection)
+            .await
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })
+    }
+
+    ///
+    /// Set fields of a hash, refreshing the expiration time of the whole hash
+    ///
+    pub async fn hash_set_multiple(
         &mut self,
-        _keyvals: Vec<(String, String)>,
-        _expiration_ms: u32,
+        key: &str,
+        fields: &[(String, String)],
+        expiration_ms: u32,
     ) -> Result<(), CacheError> {
-        Ok(())
+        if fields.is_empty() {
+            return Ok(());
+        }
+
+        let key = format!("{}:{}", &self.key_folder, key);
+        let mut connection = self.connection().await?;
+
+        redis::pipe()
+            .atomic()
+            .hset_multiple(&key, fields)
+            .ignore()
+            .pexpire(&key, expiration_ms as usize)
+            .ignore()
+            .query_async::<_, ()>(&mut connection)
+            .await
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })
     }
 
     ///
-    /// Get the value of multiple keys
+    /// Delete fields of a hash
     ///
-    pub async fn multiple_get<T: std::str::FromStr>(
+    pub async fn hash_delete(&mut self, key: &str, fields: &[String]) -> Result<(), CacheError> {
+        if fields.is_empty() {
+            return Ok(());
+        }
+
+        let key = format!("{}:{}", &self.key_folder, key);
+        let mut connection = self.connection().await?;
+
+        redis::cmd("HDEL")
+            .arg(key)
+            .arg(fields)
+            .query_async::<_, u32>(&mut connection)
+            .await
+            .map(|_| ())
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })
+    }
+
+    ///
+    /// Increment fields of a hash by one, refreshing the expiration time of the whole hash
+    ///
+    pub async fn hash_increment(
         &mut self,
-        _keys: Vec<String>,
-    ) -> Result<Vec<T>, CacheError> {
-        Ok(vec![])
+        key: &str,
+        fields: &[&str],
+        expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        let key = format!("{}:{}", &self.key_folder, key);
+        let mut connection = self.connection().await?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for field in fields {
+            pipe.hincr(&key, *field, 1).ignore();
+        }
+
+        pipe.pexpire(&key, expiration_ms as usize)
+            .ignore()
+            .query_async::<_, ()>(&mut connection)
+            .await
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })
+    }
+
+    ///
+    /// Set a key if it doesn't exist yet, with an expiration time
+    ///
+    /// Returns `true` if the key was set.
+    pub async fn set_if_absent(
+        &mut self,
+        key: &str,
+        expiration_ms: u32,
+    ) -> Result<bool, CacheError> {
+        let key = format!("{}:{}", &self.key_folder, key);
+        let mut connection = self.connection().await?;
+
+        redis::cmd("SET")
+            .arg(key)
+            .arg(1)
+            .arg("NX")
+            .arg("PX")
+            .arg(expiration_ms)
+            .query_async::<_, Option<String>>(&mut connection)
+            .await
+            .map(|result| result.is_some())
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })
+    }
+
+    ///
+    /// Add a member to a set, refreshing the expiration time of the set
+    ///
+    /// Returns if the member was added, and the number of members.
+    pub async fn set_add(
+        &mut self,
+        key: &str,
+        member: &str,
+        expiration_ms: u32,
+    ) -> Result<(bool, u32), CacheError> {
+        let key = format!("{}:{}", &self.key_folder, key);
+        let mut connection = self.connection().await?;
+
+        redis::pipe()
+            .atomic()
+            .sadd(&key, member)
+            .scard(&key)
+            .pexpire(&key, expiration_ms as usize)
+            .ignore()
+            .query_async::<_, (u32, u32)>(&mut connection)
+            .await
+            .map(|(added, members)| (added > 0, members))
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })
+    }
+
+    ///
+    /// Delete a key
+    ///
+    pub async fn delete(&mut self, key: &str) -> Result<(), CacheError> {
+        let key = format!("{}:{}", &self.key_folder, key);
+        let mut connection = self.connection().await?;
+
+        redis::cmd("DEL")
+            .arg(key)
+            .query_async::<_, u32>(&mut connection)
+            .await
+            .map(|_| ())
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })
+    }
+
+    ///
+    /// Prepend a value to a list, trimming the list to its `max_len` newest
+    ///  values and refreshing its expiration time
+    ///
+    pub async fn list_push(
+        &mut self,
+        key: &str,
+        value: &str,
+        max_len: usize,
+        expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        let key = format!("{}:{}", &self.key_folder, key);
+        let mut connection = self.connection().await?;
+
+        redis::pipe()
+            .atomic()
+            .lpush(&key, value)
+            .ignore()
+            .ltrim(&key, 0, max_len.max(1) as isize - 1)
+            .ignore()
+            .pexpire(&key, expiration_ms as usize)
+            .ignore()
+            .query_async::<_, ()>(&mut connection)
+            .await
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })
+    }
+
+    ///
+    /// Values of a list, newest first
+    ///
+    pub async fn list_range(&mut self, key: &str) -> Result<Vec<String>, CacheError> {
+        let key = format!("{}:{}", &self.key_folder, key);
+        let mut connection = self.connection().await?;
+
+        redis::cmd("LRANGE")
+            .arg(key)
+            .arg(0)
+            .arg(-1)
+            .query_async::<_, Vec<String>>(&mut connection)
+            .await
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })
+    }
+
+    ///
+    /// Append an entry to a stream, trimming the stream to about `max_len` entries
+    ///
+    pub async fn stream_add(
+        &mut self,
+        stream: &str,
+        fields: &[(&str, String)],
+        max_len: usize,
+    ) -> Result<String, CacheError> {
+        let key = format!("{}:{}", &self.key_folder, stream);
+        let mut connection = self.connection().await?;
+        let (id, trimmed) = super::stream::add(&mut connection, &key, fields, max_len).await?;
+        super::record_trimmed(&self.stats, &key, trimmed);
+
+        Ok(id)
+    }
+
+    ///
+    /// Create a consumer group reading new entries of a stream,
+    ///  creating the stream if needed. Existing groups are left as-is.
+    ///
+    pub async fn stream_create_group(
+        &mut self,
+        stream: &str,
+        group: &str,
+    ) -> Result<(), CacheError> {
+        let key = format!("{}:{}", &self.key_folder, stream);
+        let mut connection = self.connection().await?;
+        super::stream::create_group(&mut connection, &key, group, "$").await
+    }
+
+    ///
+    /// Read up to `count` entries of a stream not yet delivered to the group,
+    ///  waiting up to `block_ms` for new entries
+    ///
+    /// Returns the ID and fields of each entry.
+    pub async fn stream_read_group(
+        &mut self,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+        block_ms: usize,
+    ) -> Result<Vec<(String, HashMap<String, String>)>, CacheError> {
+        let key = format!("{}:{}", &self.key_folder, stream);
+        let mut connection = self.connection().await?;
+        super::stream::read_group(
+            &mut connection,
+            &key,
+            group,
+            consumer,
+            count,
+            Some(block_ms),
+        )
+        .await
+    }
+
+    ///
+    /// Take over entries of a stream left pending by another consumer
+    ///  of the group for at least `min_idle_ms`
+    ///
+    pub async fn stream_reclaim(
+        &mut self,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+        min_idle_ms: usize,
+        count: usize,
+    ) -> Result<Vec<(String, HashMap<String, String>)>, CacheError> {
+        let key = format!("{}:{}", &self.key_folder, stream);
+        let mut connection = self.connection().await?;
+        super::stream::reclaim(&mut connection, &key, group, consumer, min_idle_ms, count).await
+    }
+
+    ///
+    /// Acknowledge processed entries of a stream
+    ///
+    pub async fn stream_ack(
+        &mut self,
+        stream: &str,
+        group: &str,
+        ids: &[String],
+    ) -> Result<(), CacheError> {
+        let key = format!("{}:{}", &self.key_folder, stream);
+        let mut connection = self.connection().await?;
+        super::stream::ack(&mut connection, &key, group, ids).await
+    }
+
+    ///
+    /// Number of times each of the given pending entries of a stream was delivered
+    ///
+    pub async fn stream_deliveries(
+        &mut self,
+        stream: &str,
+        group: &str,
+        ids: &[String],
+    ) -> Result<HashMap<String, u64>, CacheError> {
+        let key = format!("{}:{}", &self.key_folder, stream);
+        let mut connection = self.connection().await?;
+        super::stream::deliveries(&mut connection, &key, group, ids).await
+    }
+
+    ///
+    /// Up to `count` entries of a stream, oldest first, following the
+    ///  entry `after` if set
+    ///
+    pub async fn stream_range(
+        &mut self,
+        stream: &str,
+        after: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<(String, HashMap<String, String>)>, CacheError> {
+        let key = format!("{}:{}", &self.key_folder, stream);
+        let mut connection = self.connection().await?;
+        super::stream::range(&mut connection, &key, after, count).await
+    }
+
+    ///
+    /// Entries of a stream with the given IDs, leaving out deleted entries
+    ///
+    pub async fn stream_entries(
+        &mut self,
+        stream: &str,
+        ids: &[String],
+    ) -> Result<Vec<(String, HashMap<String, String>)>, CacheError> {
+        let key = format!("{}:{}", &self.key_folder, stream);
+        let mut connection = self.connection().await?;
+        super::stream::entries(&mut connection, &key, ids).await
+    }
+
+    ///
+    /// Delete entries of a stream, returning the number deleted
+    ///
+    pub async fn stream_remove(
+        &mut self,
+        stream: &str,
+        ids: &[String],
+    ) -> Result<usize, CacheError> {
+        let key = format!("{}:{}", &self.key_folder, stream);
+        let mut connection = self.connection().await?;
+        super::stream::remove(&mut connection, &key, ids).await
+    }
+
+    ///
+    /// Scan up to about `count` keys of the pool from `cursor`, deleting those
+    ///  ending with one of `suffixes` which weren't read or written for
+    ///  `max_idle_ms`, according to `OBJECT IDLETIME`
+    ///
+    pub async fn purge_idle(
+        &mut self,
+        cursor: u64,
+        count: u32,
+        suffixes: &[&str],
+        max_idle_ms: u32,
+    ) -> Result<PurgeBatch, CacheError> {
+        let pattern = format!("{}:*", &self.key_folder);
+        let mut connection = self.connection().await?;
+
+        let (cursor, keys) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(pattern)
+            .arg("COUNT")
+            .arg(count)
+            .query_async::<_, (u64, Vec<String>)>(&mut connection)
+            .await
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })?;
+
+        let scanned = keys.len() as u32;
+        let keys: Vec<String> = keys
+            .into_iter()
+            .filter(|key| suffixes.iter().any(|suffix| key.ends_with(suffix)))
+            .collect();
+
+        if keys.is_empty() {
+            return Ok(PurgeBatch {
+                cursor,
+                scanned,
+                purged: 0,
+            });
+        }
+
+        // unlike other commands, it doesn't reset the idle time of the keys
+        let mut pipe = redis::pipe();
+        for key in &keys {
+            pipe.cmd("OBJECT").arg("IDLETIME").arg(key);
+        }
+
+        // not available with an LFU eviction policy, nothing is purged then
+        //  rather than keys which may be in use; none for keys which expired
+        //  since the scan
+        let idle = pipe
+            .query_async::<_, Vec<Option<u64>>>(&mut connection)
+            .await
+            .map_err(|e| {
+                cache_error!("could not get idle time of keys: {e}");
+                CacheError::OperationFailed
+            })?;
+
+        let max_idle_s = max_idle_ms as u64 / 1000;
+        let stale: Vec<&String> = keys
+            .iter()
+            .zip(idle)
+            .filter(|(_, idle)| idle.is_some_and(|s| s > max_idle_s))
+            .map(|(key, _)| key)
+            .collect();
+
+        if stale.is_empty() {
+            return Ok(PurgeBatch {
+                cursor,
+                scanned,
+                purged: 0,
+            });
+        }
+
+        let purged = redis::cmd("UNLINK")
+            .arg(&stale)
+            .query_async::<_, u32>(&mut connection)
+            .await
+            .map_err(|e| {
+                cache_error!("Operation failed, redis error: {}", e);
+                CacheError::OperationFailed
+            })?;
+
+        Ok(PurgeBatch {
+            cursor,
+            scanned,
+            purged,
+        })
+    }
+
+    /// Get a connection from the pool
+    async fn connection(&self) -> Result<deadpool_redis::Connection, CacheError> {
+        checkout(&self.pool, &self.key_folder, &self.stats).await
+    }
+}
+
+/// Interval of the checks for new entries of a stream while waiting
+#[cfg(all(not(test), feature = "memory_backends"))]
+const MEMORY_STREAM_POLL_MS: u64 = 50;
+
+#[cfg(all(not(test), feature = "memory_backends"))]
+impl GisPool {
+    /// Create a new GisPool, held in memory
+    pub async fn new(config: crate::config::Config) -> Result<Self, CacheError> {
+        cache_info!("(GisPool new) holding queues in memory.");
+        let store = super::memory::MemoryStore::shared();
+
+        // Items pushed before svc-gis first connects must not be skipped
+        for queue in GisQueue::ALL {
+            store
+                .stream_create_group(queue.key(), GIS_CONSUMER_GROUP, true)
+                .inspect_err(|e| {
+                    cache_error!("(GisPool new) could not create consumer group of {queue}: {e}");
+                })?;
+        }
+
+        Ok(GisPool {
+            store,
+            max_len: config.gis_stream_max_len as usize,
+            stale_after_ms: config.gis_stale_after_ms,
+            stats: Stats::default(),
+        })
+    }
+
+    /// Count the items dropped from full queues in the given statistics
+    pub fn with_stats(mut self, stats: Stats) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Push an item onto the queue of its type, capped to
+    ///  `gis_stream_max_len` items
+    pub async fn push<T: GisItem>(&mut self, item: T) -> Result<(), CacheError> {
+        self.add(item, false)
+    }
+
+    /// Push an item replayed with its original timestamps onto the queue
+    ///  of its type, never dropped as stale
+    pub async fn push_historical<T: GisItem>(&mut self, item: T) -> Result<(), CacheError> {
+        self.add(item, true)
+    }
+
+    /// Add an item to the queue of its type
+    fn add<T: GisItem>(&mut self, item: T, historical: bool) -> Result<(), CacheError> {
+        let queue = T::QUEUE;
+        let serialized = serde_json::to_string(&item).map_err(|e| {
+            cache_error!("could not serialize item {:#?}: {e}", item);
+            CacheError::CouldNotSerialize
+        })?;
+
+        let mut fields = vec![(GIS_ITEM_FIELD, serialized)];
+        if historical {
+            fields.push((GIS_HISTORICAL_FIELD, true.to_string()));
+        }
+
+        let (_, trimmed) = self.store.stream_add(queue.key(), &fields, self.max_len)?;
+        super::record_trimmed(&self.stats, queue.key(), trimmed);
+
+        Ok(())
+    }
+
+    /// Read a batch of up to `count` items from the queue of their type
+    ///  as a consumer of the [`GIS_CONSUMER_GROUP`], see [`GisPool::ack`]
+    pub async fn read_batch<T: GisItem>(
+        &mut self,
+        consumer: &str,
+        count: usize,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(String, T)>, CacheError> {
+        let queue = T::QUEUE;
+        let mut entries = self.store.stream_reclaim(
+            queue.key(),
+            GIS_CONSUMER_GROUP,
+            consumer,
+            GIS_PENDING_IDLE_MS,
+            count,
+        )?;
+
+        if entries.len() < count {
+            entries.extend(self.store.stream_read_group(
+                queue.key(),
+                GIS_CONSUMER_GROUP,
+                consumer,
+                count - entries.len(),
+            )?);
+        }
+
+        let stale_after_ms = match queue.expires() {
+            true => self.stale_after_ms,
+            false => 0,
+        };
+
+        let mut items = vec![];
+        let mut dropped = vec![];
+        let mut stale = 0;
+        for (id, fields) in entries {
+            let item = fields.get(GIS_ITEM_FIELD);
+            let historical = fields.contains_key(GIS_HISTORICAL_FIELD);
+            if !historical && item.is_some_and(|item| super::is_stale(item, now, stale_after_ms)) {
+                stale += 1;
+                dropped.push(id);
+                continue;
+            }
+
+            match item.and_then(|item| serde_json::from_str::<T>(item).ok()) {
+                Some(item) => items.push((id, item)),
+                None => {
+                    cache_warn!("dropping malformed item {id} of {queue}.");
+                    dropped.push(id);
+                }
+            }
+        }
+
+        if stale > 0 {
+            cache_debug!("dropped {stale} stale item(s) of {queue}.");
+            self.stats.record_stale(queue.key(), stale);
+        }
+
+        self.store
+            .stream_ack(queue.key(), GIS_CONSUMER_GROUP, &dropped)?;
+        Ok(items)
+    }
+
+    /// Acknowledge items of a queue processed by the consumer
+    pub async fn ack(&mut self, queue: GisQueue, ids: &[String]) -> Result<(), CacheError> {
+        self.store.stream_ack(queue.key(), GIS_CONSUMER_GROUP, ids)
+    }
+}
+
+#[cfg(all(not(test), feature = "memory_backends"))]
+impl TelemetryPool {
+    /// Create a new TelemetryPool, held in memory
+    /// The 'key_folder' argument is prepended to the key being stored, see
+    ///  the Redis implementation.
+    pub async fn new(_config: crate::config::Config, key_folder: &str) -> Result<Self, CacheError> {
+        cache_info!("(TelemetryPool new) holding keys of {key_folder} in memory.");
+        Ok(TelemetryPool {
+            store: super::memory::MemoryStore::shared(),
+            key_folder: String::from(key_folder),
+            stats: Stats::default(),
+        })
+    }
+
+    /// Count the entries dropped from full streams in the given statistics
+    pub fn with_stats(mut self, stats: Stats) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Folder prepended to the keys of the pool
+    pub fn key_folder(&self) -> &str {
+        &self.key_folder
+    }
+
+    /// Key prefixed with the folder of the pool
+    fn key(&self, key: &str) -> String {
+        format!("{}:{}", &self.key_folder, key)
+    }
+
+    /// If the key didn't exist, inserts the key with an expiration time.
+    /// If the key exists, increments the key and doesn't extend the expiration time.
+    ///
+    /// Returns the order in which this specific key was received (1 for first time).
+    pub async fn increment(&mut self, key: &str, expiration_ms: u32) -> Result<u32, CacheError> {
+        self.store.increment(&self.key(key), expiration_ms)
+    }
+
+    /// [`increment`](Self::increment) of multiple keys with their expiration
+    ///  times
+    pub async fn increment_batch(
+        &mut self,
+        keys: &[(String, u32)],
+    ) -> Result<Vec<u32>, CacheError> {
+        keys.iter()
+            .map(|(key, expiration_ms)| self.store.increment(&self.key(key), *expiration_ms))
+            .collect()
+    }
+
+    ///
+    /// Set the value of multiple keys
+    ///
+    pub async fn multiple_set(
+        &mut self,
+        keyvals: Vec<(String, String)>,
+        expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        self.store.multiple_set(keyvals, expiration_ms);
+        Ok(())
+    }
+
+    ///
+    /// Get the value of multiple keys
+    ///
+    pub async fn multiple_get<T: std::str::FromStr>(
+        &mut self,
+        keys: Vec<String>,
+    ) -> Result<Vec<T>, CacheError> {
+        self.store
+            .multiple_get(&keys)?
+            .iter()
+            .map(|value| T::from_str(value).map_err(|_| CacheError::OperationFailed))
+            .collect()
+    }
+
+    ///
+    /// Set a field of a hash, refreshing the expiration time of the whole hash
+    ///
+    pub async fn hash_set(
+        &mut self,
+        key: &str,
+        field: &str,
+        value: &str,
+        expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        let fields = [(field.to_string(), value.to_string())];
+        self.store.hash_set(&self.key(key), &fields, expiration_ms)
+    }
+
+    ///
+    /// Get a field of a hash
+    ///
+    pub async fn hash_get(&mut self, key: &str, field: &str) -> Result<Option<String>, CacheError> {
+        self.store.hash_get(&self.key(key), field)
+    }
+
+    ///
+    /// Get all fields of a hash
+    ///
+    pub async fn hash_get_all(&mut self, key: &str) -> Result<HashMap<String, String>, CacheError> {
+        self.store.hash_get_all(&self.key(key))
+    }
+
+    ///
+    /// Set fields of a hash, refreshing the expiration time of the whole hash
+    ///
+    pub async fn hash_set_multiple(
+        &mut self,
+        key: &str,
+        fields: &[(String, String)],
+        expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        if fields.is_empty() {
+            return Ok(());
+        }
+
+        self.store.hash_set(&self.key(key), fields, expiration_ms)
+    }
+
+    ///
+    /// Delete fields of a hash
+    ///
+    pub async fn hash_delete(&mut self, key: &str, fields: &[String]) -> Result<(), CacheError> {
+        self.store.hash_delete(&self.key(key), fields)
+    }
+
+    ///
+    /// Increment fields of a hash by one, refreshing the expiration time of the whole hash
+    ///
+    pub async fn hash_increment(
+        &mut self,
+        key: &str,
+        fields: &[&str],
+        expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        self.store
+            .hash_increment(&self.key(key), fields, expiration_ms)
+    }
+
+    ///
+    /// Set a key if it doesn't exist yet, with an expiration time
+    ///
+    /// Returns `true` if the key was set.
+    pub async fn set_if_absent(
+        &mut self,
+        key: &str,
+        expiration_ms: u32,
+    ) -> Result<bool, CacheError> {
+        Ok(self.store.set_if_absent(&self.key(key), expiration_ms))
+    }
+
+    ///
+    /// Add a member to a set, refreshing the expiration time of the set
+    ///
+    /// Returns if the member was added, and the number of members.
+    pub async fn set_add(
+        &mut self,
+        key: &str,
+        member: &str,
+        expiration_ms: u32,
+    ) -> Result<(bool, u32), CacheError> {
+        self.store.set_add(&self.key(key), member, expiration_ms)
+    }
+
+    ///
+    /// Delete a key
+    ///
+    pub async fn delete(&mut self, key: &str) -> Result<(), CacheError> {
+        self.store.delete(&self.key(key));
+        Ok(())
+    }
+
+    ///
+    /// Prepend a value to a list, trimming the list to its `max_len` newest
+    ///  values and refreshing its expiration time
+    ///
+    pub async fn list_push(
+        &mut self,
+        key: &str,
+        value: &str,
+        max_len: usize,
+        expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        self.store
+            .list_push(&self.key(key), value, max_len, expiration_ms)
+    }
+
+    ///
+    /// Values of a list, newest first
+    ///
+    pub async fn list_range(&mut self, key: &str) -> Result<Vec<String>, CacheError> {
+        self.store.list_range(&self.key(key))
+    }
+
+    ///
+    /// Append an entry to a stream, trimming the stream to `max_len` entries
+    ///
+    pub async fn stream_add(
+        &mut self,
+        stream: &str,
+        fields: &[(&str, String)],
+        max_len: usize,
+    ) -> Result<String, CacheError> {
+        let key = self.key(stream);
+        let (id, trimmed) = self.store.stream_add(&key, fields, max_len)?;
+        super::record_trimmed(&self.stats, &key, trimmed);
+
+        Ok(id)
+    }
+
+    ///
+    /// Create a consumer group reading new entries of a stream,
+    ///  creating the stream if needed. Existing groups are left as-is.
+    ///
+    pub async fn stream_create_group(
+        &mut self,
+        stream: &str,
+        group: &str,
+    ) -> Result<(), CacheError> {
+        self.store
+            .stream_create_group(&self.key(stream), group, false)
+    }
+
+    ///
+    /// Read up to `count` entries of a stream not yet delivered to the group,
+    ///  waiting up to `block_ms` for new entries
+    ///
+    /// Returns the ID and fields of each entry.
+    pub async fn stream_read_group(
+        &mut self,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+        block_ms: usize,
+    ) -> Result<Vec<(String, HashMap<String, String>)>, CacheError> {
+        let key = self.key(stream);
+        let deadline =
+            tokio::time::Instant::now() + std::time::Duration::from_millis(block_ms as u64);
+
+        loop {
+            let entries = self.store.stream_read_group(&key, group, consumer, count)?;
+
+            if !entries.is_empty() || tokio::time::Instant::now() >= deadline {
+                return Ok(entries);
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(MEMORY_STREAM_POLL_MS)).await;
+        }
+    }
+
+    ///
+    /// Take over entries of a stream left pending by another consumer
+    ///  of the group for at least `min_idle_ms`
+    ///
+    pub async fn stream_reclaim(
+        &mut self,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+        min_idle_ms: usize,
+        count: usize,
+    ) -> Result<Vec<(String, HashMap<String, String>)>, CacheError> {
+        self.store
+            .stream_reclaim(&self.key(stream), group, consumer, min_idle_ms, count)
+    }
+
+    ///
+    /// Acknowledge processed entries of a stream
+    ///
+    pub async fn stream_ack(
+        &mut self,
+        stream: &str,
+        group: &str,
+        ids: &[String],
+    ) -> Result<(), CacheError> {
+        self.store.stream_ack(&self.key(stream), group, ids)
+    }
+
+    ///
+    /// Number of times each of the given pending entries of a stream was delivered
+    ///
+    pub async fn stream_deliveries(
+        &mut self,
+        stream: &str,
+        group: &str,
+        ids: &[String],
+    ) -> Result<HashMap<String, u64>, CacheError> {
+        self.store.stream_deliveries(&self.key(stream), group, ids)
+    }
+
+    ///
+    /// Up to `count` entries of a stream, oldest first, following the
+    ///  entry `after` if set
+    ///
+    pub async fn stream_range(
+        &mut self,
+        stream: &str,
+        after: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<(String, HashMap<String, String>)>, CacheError> {
+        self.store.stream_range(&self.key(stream), after, count)
+    }
+
+    ///
+    /// Entries of a stream with the given IDs, leaving out deleted entries
+    ///
+    pub async fn stream_entries(
+        &mut self,
+        stream: &str,
+        ids: &[String],
+    ) -> Result<Vec<(String, HashMap<String, String>)>, CacheError> {
+        self.store.stream_entries(&self.key(stream), ids)
+    }
+
+    ///
+    /// Delete entries of a stream, returning the number deleted
+    ///
+    pub async fn stream_remove(
+        &mut self,
+        stream: &str,
+        ids: &[String],
+    ) -> Result<usize, CacheError> {
+        self.store.stream_remove(&self.key(stream), ids)
+    }
+
+    ///
+    /// Scan up to `count` keys of the pool from `cursor`, deleting those
+    ///  ending with one of `suffixes` which weren't read or written for
+    ///  `max_idle_ms`
+    ///
+    pub async fn purge_idle(
+        &mut self,
+        cursor: u64,
+        count: u32,
+        suffixes: &[&str],
+        max_idle_ms: u32,
+    ) -> Result<PurgeBatch, CacheError> {
+        let max_idle = std::time::Duration::from_millis(max_idle_ms as u64);
+        Ok(self
+            .store
+            .purge_idle(&self.key(""), cursor, count, suffixes, max_idle))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+impl TelemetryPool {
+    /// Create a new TelemetryPool
+    /// The 'key_folder' argument is prepended to the key being stored. The
+    ///  complete key will take the format \<folder\>:\<subset\>:\<subset\>:\<key\>.
+    ///  This is used to differentiate keys inserted into Redis by different
+    ///  microservices. For example, an ADS-B key in svc-telemetry might be
+    ///  formatted `telemetry:adsb:1234567890`.
+    pub async fn new(_config: crate::config::Config, key_folder: &str) -> Result<Self, CacheError> {
+        cache_info!("pool created.");
+        Ok(TelemetryPool {
+            key_folder: String::from(key_folder),
+        })
+    }
+
+    /// Count the entries dropped from full streams in the given statistics
+    pub fn with_stats(self, _stats: Stats) -> Self {
+        self
+    }
+
+    /// Folder prepended to the keys of the pool
+    pub fn key_folder(&self) -> &str {
+        &self.key_folder
+    }
+
+    /// If the key didn't exist, inserts the key with an expiration time.
+    /// If the key exists, increments the key and doesn't extend the expiration time.
+    ///
+    /// Returns the order in which this specific key was received (1 for first time).
+    pub async fn increment(&mut self, _key: &str, _expiration_ms: u32) -> Result<u32, CacheError> {
+        Ok(1)
+    }
+
+    /// [`increment`](Self::increment) of multiple keys with their expiration
+    ///  times
+    pub async fn increment_batch(
+        &mut self,
+        keys: &[(String, u32)],
+    ) -> Result<Vec<u32>, CacheError> {
+        Ok(vec![1; keys.len()])
+    }
+
+    ///
+    /// Set the value of multiple keys
+    ///
+    pub async fn multiple_set(
+        &mut self,
+        _keyvals: Vec<(String, String)>,
+        _expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        Ok(())
+    }
+
+    ///
+    /// Get the value of multiple keys
+    ///
+    pub async fn multiple_get<T: std::str::FromStr>(
+        &mut self,
+        _keys: Vec<String>,
+    ) -> Result<Vec<T>, CacheError> {
+        Ok(vec![])
+    }
+
+    ///
+    /// Set a field of a hash, refreshing the expiration time of the whole hash
+    ///
+    pub async fn hash_set(
+        &mut self,
+        _key: &str,
+        _field: &str,
+        _value: &str,
+        _expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        Ok(())
+    }
+
+    ///
+    /// Get a field of a hash
+    ///
+    pub async fn hash_get(
+        &mut self,
+        _key: &str,
+        _field: &str,
+    ) -> Result<Option<String>, CacheError> {
+        Ok(None)
+    }
+
+    ///
+    /// Get all fields of a hash
+    ///
+    pub async fn hash_get_all(
+        &mut self,
+        _key: &str,
+    ) -> Result<HashMap<String, String>, CacheError> {
+        Ok(HashMap::new())
+    }
+
+    ///
+    /// Set fields of a hash, refreshing the expiration time of the whole hash
+    ///
+    pub async fn hash_set_multiple(
+        &mut self,
+        _key: &str,
+        _fields: &[(String, String)],
+        _expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        Ok(())
+    }
+
+    ///
+    /// Delete fields of a hash
+    ///
+    pub async fn hash_delete(&mut self, _key: &str, _fields: &[String]) -> Result<(), CacheError> {
+        Ok(())
+    }
+
+    ///
+    /// Increment fields of a hash by one, refreshing the expiration time of the whole hash
+    ///
+    pub async fn hash_increment(
+        &mut self,
+        _key: &str,
+        _fields: &[&str],
+        _expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        Ok(())
+    }
+
+    ///
+    /// Set a key if it doesn't exist yet, with an expiration time
+    ///
+    /// Returns `true` if the key was set.
+    pub async fn set_if_absent(
+        &mut self,
+        _key: &str,
+        _expiration_ms: u32,
+    ) -> Result<bool, CacheError> {
+        Ok(true)
+    }
+
+    ///
+    /// Add a member to a set, refreshing the expiration time of the set
+    ///
+    /// Returns if the member was added, and the number of members.
+    pub async fn set_add(
+        &mut self,
+        _key: &str,
+        _member: &str,
+        _expiration_ms: u32,
+    ) -> Result<(bool, u32), CacheError> {
+        Ok((true, 1))
+    }
+
+    ///
+    /// Delete a key
+    ///
+    pub async fn delete(&mut self, _key: &str) -> Result<(), CacheError> {
+        Ok(())
+    }
+
+    ///
+    /// Prepend a value to a list, trimming the list to its `max_len` newest
+    ///  values and refreshing its expiration time
+    ///
+    pub async fn list_push(
+        &mut self,
+        _key: &str,
+        _value: &str,
+        _max_len: usize,
+        _expiration_ms: u32,
+    ) -> Result<(), CacheError> {
+        Ok(())
+    }
+
+    ///
+    /// Values of a list, newest first
+    ///
+    pub async fn list_range(&mut self, _key: &str) -> Result<Vec<String>, CacheError> {
+        Ok(vec![])
+    }
+
+    ///
+    /// Append an entry to a stream, trimming the stream to about `max_len` entries
+    ///
+    pub async fn stream_add(
+        &mut self,
+        _stream: &str,
+        _fields: &[(&str, String)],
+        _max_len: usize,
+    ) -> Result<String, CacheError> {
+        Ok("0-1".to_string())
+    }
+
+    ///
+    /// Create a consumer group reading new entries of a stream,
+    ///  creating the stream if needed. Existing groups are left as-is.
+    ///
+    pub async fn stream_create_group(
+        &mut self,
+        _stream: &str,
+        _group: &str,
+    ) -> Result<(), CacheError> {
+        Ok(())
+    }
+
+    ///
+    /// Read up to `count` entries of a stream not yet delivered to the group,
+    ///  waiting up to `block_ms` for new entries
+    ///
+    /// Returns the ID and fields of each entry.
+    pub async fn stream_read_group(
+        &mut self,
+        _stream: &str,
+        _group: &str,
+        _consumer: &str,
+        _count: usize,
+        _block_ms: usize,
+    ) -> Result<Vec<(String, HashMap<String, String>)>, CacheError> {
+        Ok(vec![])
+    }
+
+    ///
+    /// Take over entries of a stream left pending by another consumer
+    ///  of the group for at least `min_idle_ms`
+    ///
+    pub async fn stream_reclaim(
+        &mut self,
+        _stream: &str,
+        _group: &str,
+        _consumer: &str,
+        _min_idle_ms: usize,
+        _count: usize,
+    ) -> Result<Vec<(String, HashMap<String, String>)>, CacheError> {
+        Ok(vec![])
+    }
+
+    ///
+    /// Acknowledge processed entries of a stream
+    ///
+    pub async fn stream_ack(
+        &mut self,
+        _stream: &str,
+        _group: &str,
+        _ids: &[String],
+    ) -> Result<(), CacheError> {
+        Ok(())
+    }
+
+    ///
+    /// Number of times each of the given pending entries of a stream was delivered
+    ///
+    pub async fn stream_deliveries(
+        &mut self,
+        _stream: &str,
+        _group: &str,
+        _ids: &[String],
+    ) -> Result<HashMap<String, u64>, CacheError> {
+        Ok(HashMap::new())
+    }
+
+    ///
+    /// Up to `count` entries of a stream, oldest first, following the
+    ///  entry `after` if set
+    ///
+    pub async fn stream_range(
+        &mut self,
+        _stream: &str,
+        _after: Option<&str>,
+        _count: usize,
+    ) -> Result<Vec<(String, HashMap<String, String>)>, CacheError> {
+        Ok(vec![])
+    }
+
+    ///
+    /// Entries of a stream with the given IDs, leaving out deleted entries
+    ///
+    pub async fn stream_entries(
+        &mut self,
+        _stream: &str,
+        _ids: &[String],
+    ) -> Result<Vec<(String, HashMap<String, String>)>, CacheError> {
+        Ok(vec![])
+    }
+
+    ///
+    /// Delete entries of a stream, returning the number deleted
+    ///
+    pub async fn stream_remove(
+        &mut self,
+        _stream: &str,
+        _ids: &[String],
+    ) -> Result<usize, CacheError> {
+        Ok(0)
+    }
+
+    ///
+    /// Scan up to about `count` keys of the pool from `cursor`, deleting those
+    ///  ending with one of `suffixes` which weren't read or written for
+    ///  `max_idle_ms`, according to `OBJECT IDLETIME`
+    ///
+    pub async fn purge_idle(
+        &mut self,
+        _cursor: u64,
+        _count: u32,
+        _suffixes: &[&str],
+        _max_idle_ms: u32,
+    ) -> Result<PurgeBatch, CacheError> {
+        Ok(PurgeBatch::default())
     }
 }
@@ -0,0 +1,127 @@
+//! Retention of the per-aircraft keys of the cache
+//!
+//! The latest state, session and similar keys of an aircraft expire on
+//!  their own, but a key left without an expiration time (by an earlier
+//!  release, or by hand) stays until deleted. The keys of each pool are
+//!  periodically scanned, deleting those which weren't read or written for
+//!  `RETENTION_MAX_IDLE_MS`, whether they can expire or not. Aircraft which
+//!  stopped reporting are also removed from the track snapshot.
+
+use super::pool::TelemetryPool;
+use crate::clock::SharedClock;
+use crate::config::Config;
+use crate::stats::{Dependency, Stats};
+use std::time::Duration;
+
+/// Suffixes of the per-aircraft keys which are purged
+pub const PURGED_KEY_SUFFIXES: [&str; 7] = [
+    ":state",
+    ":session",
+    ":reporter",
+    ":enrichment",
+    ":c2",
+    ":auth",
+    ":trail",
+];
+
+/// Pause after examining `scanned` keys, keeping a purge under
+///  `keys_per_second` (0 for no limit)
+fn batch_delay(scanned: u32, keys_per_second: u32) -> Duration {
+    match keys_per_second {
+        0 => Duration::ZERO,
+        limit => Duration::from_secs_f64(scanned as f64 / limit as f64),
+    }
+}
+
+/// Scans all keys of a pool, deleting the idle per-aircraft keys
+///
+/// Returns the number of keys deleted.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn purge(config: &Config, tlm_pool: &mut TelemetryPool, stats: &Stats) -> u64 {
+    let mut cursor = 0;
+    let mut purged = 0;
+    loop {
+        let batch = match tlm_pool
+            .purge_idle(
+                cursor,
+                config.retention_scan_count.max(1),
+                &PURGED_KEY_SUFFIXES,
+                config.retention_max_idle_ms,
+            )
+            .await
+        {
+            Ok(batch) => batch,
+            Err(e) => {
+                cache_warn!("could not purge keys of {}: {e}", tlm_pool.key_folder());
+                stats.record_error(Dependency::Redis);
+                break;
+            }
+        };
+
+        purged += batch.purged as u64;
+        if batch.cursor == 0 {
+            break;
+        }
+
+        cursor = batch.cursor;
+        tokio::time::sleep(batch_delay(batch.scanned, config.retention_keys_per_second)).await;
+    }
+
+    stats.record_purged(tlm_pool.key_folder(), purged);
+    purged
+}
+
+/// Periodically purges the idle keys of the pools, and the stale aircraft
+///  of the snapshot if given
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn retention_loop(
+    config: Config,
+    mut tlm_pools: Vec<TelemetryPool>,
+    mut snapshot_pool: Option<TelemetryPool>,
+    stats: Stats,
+    clock: SharedClock,
+) {
+    let interval_ms = config.retention_purge_interval_ms.max(1);
+    cache_info!(
+        "purging keys idle for {} ms every {interval_ms} ms.",
+        config.retention_max_idle_ms
+    );
+
+    let mut interval = tokio::time::interval(Duration::from_millis(interval_ms as u64));
+    loop {
+        interval.tick().await;
+
+        for tlm_pool in tlm_pools.iter_mut() {
+            let purged = purge(&config, tlm_pool, &stats).await;
+            if purged > 0 {
+                cache_info!("purged {purged} idle keys of {}.", tlm_pool.key_folder());
+            }
+        }
+
+        let Some(tlm_pool) = snapshot_pool.as_mut() else {
+            continue;
+        };
+
+        match super::snapshot::prune_snapshot(tlm_pool, clock.now()).await {
+            Ok(pruned) => stats.record_purged(tlm_pool.key_folder(), pruned as u64),
+            Err(e) => {
+                cache_warn!("could not prune track snapshot: {e}");
+                stats.record_error(Dependency::Redis);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_delay() {
+        assert_eq!(batch_delay(500, 5000), Duration::from_millis(100));
+        assert_eq!(batch_delay(0, 5000), Duration::ZERO);
+        assert_eq!(batch_delay(500, 0), Duration::ZERO);
+    }
+}
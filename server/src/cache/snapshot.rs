@@ -0,0 +1,181 @@
+//! Snapshots of the latest state of tracked aircraft
+//!
+//! Track state is kept in memory by each instance. It is periodically
+//!  written to a Redis hash shared by all instances, one field per
+//!  aircraft, so downstream services restarting can fetch the current
+//!  picture instead of waiting for new packets.
+
+use super::pool::{CacheError, TelemetryPool};
+use crate::config::Config;
+use crate::msg::track::{SharedTracks, TrackSnapshot};
+use lib_common::time::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Hash holding the latest state of each aircraft
+const SNAPSHOT_KEY: &str = "aircraft";
+
+/// The snapshot expires if no instance writes it for 10 minutes
+const CACHE_EXPIRE_MS_SNAPSHOT: u32 = 600000;
+
+/// Splits the fields of the snapshot hash into the current state of
+///  aircraft, sorted by identifier, and the fields to remove
+fn parse(fields: HashMap<String, String>, now: DateTime<Utc>) -> (Vec<TrackSnapshot>, Vec<String>) {
+    let mut current = vec![];
+    let mut stale = vec![];
+    for (identifier, value) in fields {
+        match serde_json::from_str::<TrackSnapshot>(&value) {
+            Ok(track) if !track.is_stale(now) => current.push(track),
+            Ok(_) => stale.push(identifier),
+            Err(e) => {
+                cache_warn!("could not parse snapshot of {identifier}: {e}");
+                stale.push(identifier);
+            }
+        }
+    }
+
+    current.sort_by(|a, b| a.position.identifier.cmp(&b.position.identifier));
+    (current, stale)
+}
+
+/// Latest state of the aircraft tracked by any instance at `now`
+///  Aircraft which stopped reporting are removed from the snapshot.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn read_snapshot(
+    tlm_pool: &mut TelemetryPool,
+    now: DateTime<Utc>,
+) -> Result<Vec<TrackSnapshot>, CacheError> {
+    let fields = tlm_pool.hash_get_all(SNAPSHOT_KEY).await?;
+    let (current, stale) = parse(fields, now);
+    if let Err(e) = tlm_pool.hash_delete(SNAPSHOT_KEY, &stale).await {
+        cache_warn!("could not remove stale aircraft from snapshot: {e}");
+    }
+
+    Ok(current)
+}
+
+/// Removes the aircraft which stopped reporting by `now` from the
+///  snapshot, returning their number
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn prune_snapshot(
+    tlm_pool: &mut TelemetryPool,
+    now: DateTime<Utc>,
+) -> Result<usize, CacheError> {
+    let fields = tlm_pool.hash_get_all(SNAPSHOT_KEY).await?;
+    let (_, stale) = parse(fields, now);
+    tlm_pool.hash_delete(SNAPSHOT_KEY, &stale).await?;
+    Ok(stale.len())
+}
+
+/// Latest state of an aircraft tracked by any instance, none if unknown
+///  or if it stopped reporting by `now`
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn read_aircraft(
+    tlm_pool: &mut TelemetryPool,
+    identifier: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<TrackSnapshot>, CacheError> {
+    let Some(value) = tlm_pool.hash_get(SNAPSHOT_KEY, identifier).await? else {
+        return Ok(None);
+    };
+
+    let track = serde_json::from_str::<TrackSnapshot>(&value).map_err(|e| {
+        cache_warn!("could not parse snapshot of {identifier}: {e}");
+        CacheError::OperationFailed
+    })?;
+
+    Ok(Some(track).filter(|track| !track.is_stale(now)))
+}
+
+/// Periodically writes the tracks of this instance to the snapshot
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn snapshot_loop(config: Config, tracks: SharedTracks, mut tlm_pool: TelemetryPool) {
+    let interval_ms = config.snapshot_interval_ms.max(1);
+    cache_info!("writing track snapshots every {interval_ms} ms.");
+    let mut interval = tokio::time::interval(std::time::Duration::from_millis(interval_ms as u64));
+    loop {
+        interval.tick().await;
+
+        let snapshot = match tracks.lock() {
+            Ok(tracks) => tracks.snapshot(),
+            Err(e) => {
+                cache_error!("could not lock tracks: {e}");
+                continue;
+            }
+        };
+
+        let fields: Vec<(String, String)> = snapshot
+            .iter()
+            .filter_map(|track| {
+                serde_json::to_string(track)
+                    .map(|value| (track.position.identifier.clone(), value))
+                    .map_err(|e| cache_warn!("could not serialize track snapshot: {e}"))
+                    .ok()
+            })
+            .collect();
+
+        if let Err(e) = tlm_pool
+            .hash_set_multiple(SNAPSHOT_KEY, &fields, CACHE_EXPIRE_MS_SNAPSHOT)
+            .await
+        {
+            cache_warn!("could not write track snapshot: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lib_common::time::Duration;
+    use svc_gis_client_grpc::prelude::types::{AircraftPosition, Position};
+
+    fn track(identifier: &str, updated: DateTime<Utc>) -> TrackSnapshot {
+        TrackSnapshot {
+            position: AircraftPosition {
+                identifier: identifier.to_string(),
+                position: Position {
+                    latitude: 52.0,
+                    longitude: 5.0,
+                    altitude_meters: 100.0,
+                },
+                timestamp_network: updated,
+                timestamp_asset: None,
+            },
+            velocity: None,
+            updated,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let now = Utc::now();
+        let fields = HashMap::from([
+            (
+                "b".to_string(),
+                serde_json::to_string(&track("b", now)).unwrap(),
+            ),
+            (
+                "a".to_string(),
+                serde_json::to_string(&track("a", now)).unwrap(),
+            ),
+            (
+                "old".to_string(),
+                serde_json::to_string(&track("old", now - Duration::hours(1))).unwrap(),
+            ),
+            ("garbage".to_string(), "{".to_string()),
+        ]);
+
+        let (current, mut stale) = parse(fields, now);
+        let identifiers: Vec<&str> = current
+            .iter()
+            .map(|track| track.position.identifier.as_str())
+            .collect();
+        assert_eq!(identifiers, vec!["a", "b"]);
+
+        stale.sort();
+        assert_eq!(stale, vec!["garbage", "old"]);
+    }
+}
@@ -0,0 +1,294 @@
+//! Redis stream commands shared by the cache pools
+//!  Streams are read through consumer groups, so entries remain pending
+//!  until a consumer acknowledges them and can be reclaimed after a crash.
+
+use super::pool::CacheError;
+use deadpool_redis::{redis, Connection};
+use std::collections::HashMap;
+
+/// ID and fields of a stream entry
+pub type StreamEntryFields = (String, HashMap<String, String>);
+
+/// Append an entry to a stream, trimming the stream to about `max_len` entries
+///
+/// Returns the ID of the entry and the number of oldest entries trimmed.
+pub async fn add(
+    connection: &mut Connection,
+    key: &str,
+    fields: &[(&str, String)],
+    max_len: usize,
+) -> Result<(String, usize), CacheError> {
+    let (len_before, id, len_after) = redis::pipe()
+        .atomic()
+        .cmd("XLEN")
+        .arg(key)
+        .cmd("XADD")
+        .arg(key)
+        .arg("MAXLEN")
+        .arg("~")
+        .arg(max_len)
+        .arg("*")
+        .arg(fields)
+        .cmd("XLEN")
+        .arg(key)
+        .query_async::<_, (usize, String, usize)>(connection)
+        .await
+        .map_err(|e| {
+            cache_error!("Operation failed, redis error: {}", e);
+            CacheError::OperationFailed
+        })?;
+
+    Ok((id, super::trimmed(len_before, len_after)))
+}
+
+/// Create a consumer group starting at `start_id`, creating the stream
+///  if needed. Existing groups are left as-is.
+pub async fn create_group(
+    connection: &mut Connection,
+    key: &str,
+    group: &str,
+    start_id: &str,
+) -> Result<(), CacheError> {
+    let result = redis::cmd("XGROUP")
+        .arg("CREATE")
+        .arg(key)
+        .arg(group)
+        .arg(start_id)
+        .arg("MKSTREAM")
+        .query_async::<_, ()>(connection)
+        .await;
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) if e.code() == Some("BUSYGROUP") => Ok(()),
+        Err(e) => {
+            cache_error!("Operation failed, redis error: {}", e);
+            Err(CacheError::OperationFailed)
+        }
+    }
+}
+
+/// Read up to `count` entries not yet delivered to the group,
+///  waiting up to `block_ms` for new entries if set
+pub async fn read_group(
+    connection: &mut Connection,
+    key: &str,
+    group: &str,
+    consumer: &str,
+    count: usize,
+    block_ms: Option<usize>,
+) -> Result<Vec<StreamEntryFields>, CacheError> {
+    let mut cmd = redis::cmd("XREADGROUP");
+    cmd.arg("GROUP")
+        .arg(group)
+        .arg(consumer)
+        .arg("COUNT")
+        .arg(count);
+
+    if let Some(block_ms) = block_ms {
+        cmd.arg("BLOCK").arg(block_ms);
+    }
+
+    let result = cmd
+        .arg("STREAMS")
+        .arg(key)
+        .arg(">")
+        .query_async::<_, Option<Vec<(String, Vec<StreamEntryFields>)>>>(connection)
+        .await
+        .map_err(|e| {
+            cache_error!("Operation failed, redis error: {}", e);
+            CacheError::OperationFailed
+        })?;
+
+    Ok(result
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|(_, entries)| entries)
+        .collect())
+}
+
+/// Take over up to `count` entries delivered to another consumer of the
+///  group but not acknowledged for at least `min_idle_ms`
+///
+/// Entries deleted from the stream while pending are skipped.
+pub async fn reclaim(
+    connection: &mut Connection,
+    key: &str,
+    group: &str,
+    consumer: &str,
+    min_idle_ms: usize,
+    count: usize,
+) -> Result<Vec<StreamEntryFields>, CacheError> {
+    let result = redis::cmd("XAUTOCLAIM")
+        .arg(key)
+        .arg(group)
+        .arg(consumer)
+        .arg(min_idle_ms)
+        .arg("0-0")
+        .arg("COUNT")
+        .arg(count)
+        .query_async::<_, redis::Value>(connection)
+        .await
+        .map_err(|e| {
+            cache_error!("Operation failed, redis error: {}", e);
+            CacheError::OperationFailed
+        })?;
+
+    // [next start id, claimed entries, (deleted ids)]
+    let redis::Value::Bulk(values) = result else {
+        cache_error!("Operation failed, unexpected redis response: {:?}", result);
+        return Err(CacheError::OperationFailed);
+    };
+
+    let Some(redis::Value::Bulk(entries)) = values.get(1) else {
+        cache_error!("Operation failed, unexpected redis response: {:?}", values);
+        return Err(CacheError::OperationFailed);
+    };
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| redis::from_redis_value::<StreamEntryFields>(entry).ok())
+        .collect())
+}
+
+/// Number of times each of the given pending entries was delivered
+///
+/// Entries no longer pending are left out.
+pub async fn deliveries(
+    connection: &mut Connection,
+    key: &str,
+    group: &str,
+    ids: &[String],
+) -> Result<HashMap<String, u64>, CacheError> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut pipe = redis::pipe();
+    for id in ids {
+        pipe.cmd("XPENDING")
+            .arg(key)
+            .arg(group)
+            .arg(id)
+            .arg(id)
+            .arg(1);
+    }
+
+    // [[id, consumer, idle ms, deliveries]] per entry
+    let results = pipe
+        .query_async::<_, Vec<Vec<(String, String, u64, u64)>>>(connection)
+        .await
+        .map_err(|e| {
+            cache_error!("Operation failed, redis error: {}", e);
+            CacheError::OperationFailed
+        })?;
+
+    Ok(results
+        .into_iter()
+        .flatten()
+        .map(|(id, _, _, deliveries)| (id, deliveries))
+        .collect())
+}
+
+/// Acknowledge processed entries of a stream
+pub async fn ack(
+    connection: &mut Connection,
+    key: &str,
+    group: &str,
+    ids: &[String],
+) -> Result<(), CacheError> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    redis::cmd("XACK")
+        .arg(key)
+        .arg(group)
+        .arg(ids)
+        .query_async::<_, u32>(connection)
+        .await
+        .map(|_| ())
+        .map_err(|e| {
+            cache_error!("Operation failed, redis error: {}", e);
+            CacheError::OperationFailed
+        })
+}
+
+/// Up to `count` entries of a stream, oldest first, following the entry
+///  `after` if set, else from the start of the stream
+pub async fn range(
+    connection: &mut Connection,
+    key: &str,
+    after: Option<&str>,
+    count: usize,
+) -> Result<Vec<StreamEntryFields>, CacheError> {
+    // exclusive start
+    let start = match after {
+        Some(id) => format!("({id}"),
+        None => "-".to_string(),
+    };
+
+    redis::cmd("XRANGE")
+        .arg(key)
+        .arg(start)
+        .arg("+")
+        .arg("COUNT")
+        .arg(count)
+        .query_async::<_, Vec<StreamEntryFields>>(connection)
+        .await
+        .map_err(|e| {
+            cache_error!("Operation failed, redis error: {}", e);
+            CacheError::OperationFailed
+        })
+}
+
+/// Entries of a stream with the given IDs
+///
+/// Entries no longer in the stream are left out.
+pub async fn entries(
+    connection: &mut Connection,
+    key: &str,
+    ids: &[String],
+) -> Result<Vec<StreamEntryFields>, CacheError> {
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut pipe = redis::pipe();
+    for id in ids {
+        pipe.cmd("XRANGE").arg(key).arg(id).arg(id);
+    }
+
+    let results = pipe
+        .query_async::<_, Vec<Vec<StreamEntryFields>>>(connection)
+        .await
+        .map_err(|e| {
+            cache_error!("Operation failed, redis error: {}", e);
+            CacheError::OperationFailed
+        })?;
+
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// Delete entries of a stream
+///
+/// Returns the number of entries deleted.
+pub async fn remove(
+    connection: &mut Connection,
+    key: &str,
+    ids: &[String],
+) -> Result<usize, CacheError> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    redis::cmd("XDEL")
+        .arg(key)
+        .arg(ids)
+        .query_async::<_, usize>(connection)
+        .await
+        .map_err(|e| {
+            cache_error!("Operation failed, redis error: {}", e);
+            CacheError::OperationFailed
+        })
+}
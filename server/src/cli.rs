@@ -0,0 +1,291 @@
+//! Operational tasks run from the server binary instead of the server
+
+use crate::amqp::init_mq;
+use crate::amqp::system::REDIS_PROBE_KEY;
+use crate::cache::pool::TelemetryPool;
+use crate::cache::retention;
+use crate::grpc::client::GrpcClients;
+use crate::msg::adsb::ADSB_SIZE_BYTES;
+use crate::msg::netrid::{
+    AuthenticationMessage, BasicMessage, Frame, LocationMessage, MessagePack, MessageType,
+    OperatorIdMessage, SystemMessage,
+};
+use crate::rest::api::receipt::ReceiptSigner;
+use crate::rest::server::invalid_entries;
+use crate::stats::{Dependency, Stats};
+use crate::Config;
+use adsb_deku::deku::DekuContainerRead;
+use clap::Subcommand;
+use packed_struct::PackedStruct;
+use std::fmt::{Debug, Display};
+
+/// Length of a Remote ID frame, a header and a message
+const REMOTE_ID_FRAME_LENGTH: usize = 25;
+
+/// Tasks run instead of the server
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Check the configuration, and the connection to each dependency
+    CheckConfig,
+
+    /// Print the decoded content of packets, one per line in hex
+    ///  (14 bytes for ADS-B, 25 for a Remote ID frame, more for a Remote
+    ///  ID message pack)
+    Decode {
+        /// File holding the packets, lines starting with `#` are skipped
+        file: String,
+    },
+
+    /// Purge the idle per-aircraft keys of the cache now, see
+    ///  `RETENTION_MAX_IDLE_MS`
+    PurgeCache {
+        /// Purge keys idle for this long instead of `RETENTION_MAX_IDLE_MS`
+        #[arg(long)]
+        max_idle_ms: Option<u32>,
+    },
+}
+
+/// Runs a task, failing with a description of the problem
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis and rabbitmq backends to test
+pub async fn run(command: Command, config: Config) -> Result<(), String> {
+    match command {
+        Command::CheckConfig => check_config(&config).await,
+        Command::Decode { file } => {
+            let text = std::fs::read_to_string(&file)
+                .map_err(|e| format!("could not read {file}: {e}"))?;
+
+            for report in decode(&text) {
+                println!("{report}\n");
+            }
+
+            Ok(())
+        }
+        Command::PurgeCache { max_idle_ms } => {
+            let config = Config {
+                retention_max_idle_ms: max_idle_ms.unwrap_or(config.retention_max_idle_ms),
+                ..config
+            };
+
+            purge_cache(&config).await
+        }
+    }
+}
+
+/// Reports the invalid settings and the unavailable dependencies
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis and rabbitmq backends to test
+async fn check_config(config: &Config) -> Result<(), String> {
+    let mut problems = invalid_entries(config);
+    if let Err(e) = ReceiptSigner::load(config) {
+        problems.push(format!("could not load the delivery receipt key: {e}"));
+    }
+
+    let redis = match TelemetryPool::new(config.clone(), "tlm:adsb").await {
+        Ok(mut tlm_pool) => tlm_pool
+            .hash_get(REDIS_PROBE_KEY, REDIS_PROBE_KEY)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(format!("could not create pool: {e}")),
+    };
+
+    let amqp = init_mq(config.clone())
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string());
+
+    let grpc_clients = GrpcClients::default(config.clone());
+    let mut results = vec![(Dependency::Redis, redis), (Dependency::Amqp, amqp)];
+    results.extend(crate::rest::api::health::readiness(&grpc_clients).await);
+
+    for (dependency, result) in results {
+        match result {
+            Ok(()) => println!("{dependency:?}: ok"),
+            Err(e) => problems.push(format!("{dependency:?} unavailable: {e}")),
+        }
+    }
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+
+    match problems.len() {
+        0 => Ok(()),
+        count => Err(format!("configuration check found {count} problem(s)")),
+    }
+}
+
+/// Purges the idle keys of the ADS-B and Network Remote ID pools
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+async fn purge_cache(config: &Config) -> Result<(), String> {
+    let stats = Stats::default();
+    for key_folder in ["tlm:adsb", "tlm:netrid"] {
+        let mut tlm_pool = TelemetryPool::new(config.clone(), key_folder)
+            .await
+            .map_err(|e| format!("could not create pool of {key_folder}: {e}"))?;
+
+        let purged = retention::purge(config, &mut tlm_pool, &stats).await;
+        println!("{key_folder}: purged {purged} idle keys");
+    }
+
+    match stats
+        .summary(Default::default())
+        .dependency_errors
+        .is_empty()
+    {
+        true => Ok(()),
+        false => Err("could not purge all keys, see the logs".to_string()),
+    }
+}
+
+/// Decoded content of each packet of a file, or why it couldn't be decoded
+pub fn decode(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let hex: String = line
+                .trim_start_matches("0x")
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect();
+
+            match hex::decode(&hex) {
+                Ok(bytes) => format!("{line}\n{}", decode_packet(&bytes)),
+                Err(e) => format!("{line}\ninvalid hex: {e}"),
+            }
+        })
+        .collect()
+}
+
+/// Decoded content of a packet, told apart by its length
+fn decode_packet(bytes: &[u8]) -> String {
+    match bytes.len() {
+        ADSB_SIZE_BYTES => {
+            let Ok(payload) = <[u8; ADSB_SIZE_BYTES]>::try_from(bytes) else {
+                return "invalid ADS-B packet".to_string();
+            };
+
+            match adsb_deku::Frame::from_bytes((&payload, 0)) {
+                Ok((_, frame)) => format!("ADS-B {frame:#?}"),
+                Err(e) => format!("invalid ADS-B packet: {e}"),
+            }
+        }
+        REMOTE_ID_FRAME_LENGTH => {
+            let frame = <[u8; REMOTE_ID_FRAME_LENGTH]>::try_from(bytes)
+                .ok()
+                .and_then(|bytes| Frame::unpack(&bytes).ok());
+
+            match frame {
+                Some(frame) => decode_frame(&frame),
+                None => "invalid Remote ID frame".to_string(),
+            }
+        }
+        _ => match MessagePack::unpack(bytes) {
+            Ok(pack) => pack
+                .frames
+                .iter()
+                .map(decode_frame)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Err(e) => format!(
+                "invalid Remote ID message pack ({} bytes): {e}",
+                bytes.len()
+            ),
+        },
+    }
+}
+
+/// Debug output of a Remote ID message, or why it couldn't be unpacked
+fn message<T: PackedStruct<ByteArray = [u8; 24]> + Debug>(bytes: &[u8; 24]) -> String {
+    match T::unpack(bytes) {
+        Ok(message) => format!("{message:#?}"),
+        Err(e) => format!("invalid message: {e}"),
+    }
+}
+
+/// A decoded value, or why it couldn't be decoded
+fn decoded<T: Display, E: Debug>(value: Result<T, E>) -> String {
+    match value {
+        Ok(value) => value.to_string(),
+        Err(e) => format!("{e:?}"),
+    }
+}
+
+/// Decoded content of a Remote ID frame
+fn decode_frame(frame: &Frame) -> String {
+    let header = format!(
+        "Remote ID {:?} message, protocol version {}",
+        frame.header.message_type, frame.header.protocol_version
+    );
+
+    let body = match frame.header.message_type {
+        MessageType::Basic => message::<BasicMessage>(&frame.message),
+        MessageType::Location => match LocationMessage::unpack(&frame.message) {
+            Ok(location) => format!(
+                "{location:#?}\nlatitude: {}, longitude: {}, altitude (m): {}, speed (m/s): {}, direction (deg): {}",
+                location.decode_latitude(),
+                location.decode_longitude(),
+                decoded(location.decode_altitude()),
+                decoded(location.decode_speed()),
+                decoded(location.decode_direction()),
+            ),
+            Err(e) => format!("invalid message: {e}"),
+        },
+        MessageType::Authentication => message::<AuthenticationMessage>(&frame.message),
+        MessageType::System => message::<SystemMessage>(&frame.message),
+        MessageType::OperatorId => message::<OperatorIdMessage>(&frame.message),
+        MessageType::SelfId | MessageType::MessagePack => "not decoded".to_string(),
+    };
+
+    format!("{header}\n{body}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let mut location = [0; REMOTE_ID_FRAME_LENGTH];
+        location[0] = 0x12;
+
+        let text = format!(
+            "# captured packets\n\n8D4840D6202CC371C32CE0576098\n{}\n0x0102\nzz\n",
+            hex::encode(location)
+        );
+
+        let reports = decode(&text);
+        assert_eq!(reports.len(), 4);
+        assert!(reports[0].contains("ADS-B"));
+        assert!(reports[1].starts_with(&format!(
+            "{}\nRemote ID Location message, protocol version 2\n",
+            hex::encode(location)
+        )));
+        assert!(reports[1].ends_with(
+            "latitude: 0, longitude: 0, altitude (m): UnknownAltitude, speed (m/s): 0, direction (deg): 0"
+        ));
+        assert!(reports[2].ends_with("invalid Remote ID message pack (2 bytes): Invalid length"));
+        assert!(reports[3].starts_with("zz\ninvalid hex"));
+    }
+
+    #[test]
+    fn test_decode_packet() {
+        let mut bytes = [0; REMOTE_ID_FRAME_LENGTH];
+        bytes[0] = 0x32;
+        assert_eq!(
+            decode_packet(&bytes),
+            "Remote ID SelfId message, protocol version 2\nnot decoded"
+        );
+
+        // a pack of a Basic and a Self ID message
+        let mut pack = vec![0xF2, 25, 2];
+        pack.extend([0x02; REMOTE_ID_FRAME_LENGTH]);
+        pack.extend(bytes);
+        let report = decode_packet(&pack);
+        assert!(report.starts_with("Remote ID Basic message, protocol version 2\nBasicMessage {"));
+        assert!(report.ends_with("Remote ID SelfId message, protocol version 2\nnot decoded"));
+    }
+}
@@ -0,0 +1,93 @@
+//! Source of the current time
+//!
+//! Handlers read the time from the [`Clock`] of the pipeline rather than
+//!  from the system, so timestamps decoded relative to the current time
+//!  can be tested at a fixed time with a [`MockClock`].
+
+use lib_common::time::{DateTime, Duration, Utc};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Clock shared by the handlers of a pipeline
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Source of the current time
+pub trait Clock: Send + Sync + Debug {
+    /// The current time
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// The system clock, shared
+    pub fn shared() -> SharedClock {
+        Arc::new(SystemClock)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock standing still until set or advanced, clones share the time
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    /// The time returned
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl MockClock {
+    /// Clock stopped at the given time
+    pub fn new(now: DateTime<Utc>) -> Self {
+        MockClock {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Set the time
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) = now;
+    }
+
+    /// Move the time forward
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) += duration;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = MockClock::new(start);
+        let shared: SharedClock = Arc::new(clock.clone());
+        assert_eq!(shared.now(), start);
+
+        clock.advance(Duration::seconds(90));
+        assert_eq!(shared.now(), start + Duration::seconds(90));
+
+        clock.set(start);
+        assert_eq!(shared.now(), start);
+    }
+
+    #[test]
+    fn test_system_clock() {
+        let before = Utc::now();
+        let now = SystemClock::shared().now();
+        assert!(now >= before && now <= Utc::now());
+    }
+}
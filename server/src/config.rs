@@ -2,15 +2,81 @@
 //!
 //! Define and implement config options for module
 
+use crate::cache::gis::GIS_PENDING_IDLE_MS;
+use crate::sink::SinkKind;
 use anyhow::Result;
-use config::{ConfigError, Environment};
+use config::{ConfigError, Environment, File, FileFormat};
 use dotenv::dotenv;
 use lapin::ConnectionProperties;
 use serde::Deserialize;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Configuration file read if present, see [`config_files`]
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Parts of the telemetry pipeline run by a server instance
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerMode {
+    /// Receive telemetry and push it to the backends in the same process
+    #[default]
+    All,
+
+    /// Validate and deduplicate received telemetry, then queue it on Redis streams
+    Ingest,
+
+    /// Consume queued telemetry from Redis streams and push it to the backends
+    Dispatcher,
+}
+
+/// Handling of a login with an identifier which has an active session
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionPolicy {
+    /// Sessions of the same identifier are active concurrently
+    #[default]
+    Allow,
+
+    /// The login is rejected until the active session expires
+    Reject,
+
+    /// The login invalidates the active session
+    Replace,
+}
+
+/// Protection of operator identifiers published by this service
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OperatorIdPrivacy {
+    /// Replaced by a keyed hash, still identical for the same operator
+    #[default]
+    Hash,
+
+    /// Only the first characters are kept
+    Truncate,
+
+    /// Published as received
+    Keep,
+}
+
+/// Format of the log lines written to the console
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Encoders of the log configuration file
+    #[default]
+    Text,
+
+    /// A JSON object per line, with the request fields
+    Json,
+}
 
 /// struct holding configuration options
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
+    /// parts of the telemetry pipeline run by this instance
+    pub mode: ServerMode,
     /// port to be used for gRPC server
     pub docker_port_grpc: u16,
     /// port to be used for REST server
@@ -29,12 +95,12 @@ pub struct Config {
     pub redis: deadpool_redis::Config,
     /// path to log configuration YAML file
     pub log_config: String,
-    /// Ring buffer size
-    pub ringbuffer_size_bytes: u16,
     /// Cadence for pushes to svc-gis
     pub gis_push_cadence_ms: u16,
     /// Maximum message size for gRPC message to svc-gis
     pub gis_max_message_size_bytes: u16,
+    /// Queues to svc-gis are trimmed to about this many items
+    pub gis_stream_max_len: u32,
     /// Rate limit - requests per second for REST requests
     pub rest_request_limit_per_second: u8,
     /// Enforces a limit on the concurrent number of requests the underlying service can handle
@@ -42,6 +108,269 @@ pub struct Config {
     /// Full url (including port number) to be allowed as request origin for
     /// REST requests
     pub rest_cors_allowed_origin: String,
+    /// Check an idempotency key before storing a packet, so retries aren't stored twice
+    pub storage_idempotency_enabled: bool,
+    /// Position reports of the same aircraft closer in time than this are merged
+    pub track_merge_window_ms: u32,
+    /// Publish extrapolated positions during short telemetry gaps
+    pub prediction_enabled: bool,
+    /// Interval between predicted positions of an aircraft
+    pub prediction_interval_ms: u32,
+    /// Positions are no longer predicted after this long without a report
+    pub prediction_max_gap_ms: u32,
+    /// Smooth velocities of each aircraft before pushing them to svc-gis
+    pub velocity_filter_enabled: bool,
+    /// Alpha gain of the velocity filter (0.0 - 1.0), weight of new measurements
+    pub velocity_filter_alpha: f32,
+    /// Beta gain of the velocity filter (0.0 - 1.0), weight of the rate of change
+    pub velocity_filter_beta: f32,
+    /// Comma separated ICAO addresses (hex) or Remote ID identifiers to alert on
+    pub watchlist: String,
+    /// Reject packets of reporters whose error rate exceeds `reporter_max_error_rate`
+    pub reporter_quarantine_enabled: bool,
+    /// Share of unusable packets (0.0 - 1.0) above which a reporter is quarantined
+    pub reporter_max_error_rate: f32,
+    /// Packets needed from a reporter before it can be quarantined
+    pub reporter_min_packets: u32,
+    /// Accept Remote ID packets relayed on behalf of other aircraft
+    pub netrid_relay_enabled: bool,
+    /// Consecutive failed gRPC calls before a service is considered down
+    pub grpc_breaker_failure_threshold: u32,
+    /// Time calls to a service are rejected once it is considered down,
+    ///  doubled after every failed probe
+    pub grpc_breaker_open_ms: u32,
+    /// Longest time calls to a service are rejected before probing it again
+    pub grpc_breaker_max_open_ms: u32,
+    /// Queued packets read at once above which the dispatcher catches up,
+    ///  dispatching several batches concurrently
+    pub dispatcher_backlog_threshold: u32,
+    /// Batches dispatched concurrently while catching up
+    pub dispatcher_max_in_flight: u16,
+    /// Times a queued packet is retried after failing to be pushed
+    ///  before it is dropped
+    pub dispatcher_max_retries: u16,
+    /// Comma separated outputs of decoded telemetry, in push order
+    ///  (gis, storage, amqp, kafka, coverage, anomaly, trail, netrid_state,
+    ///  conformance or noop)
+    pub telemetry_sinks: String,
+    /// Base URL of the Kafka REST proxy used by the kafka sink
+    pub kafka_rest_url: String,
+    /// Kafka topic the kafka sink produces to
+    pub kafka_topic: String,
+    /// Time the kafka sink waits for the REST proxy to take a record
+    pub kafka_timeout_ms: u32,
+    /// Prefix of the REST routes, e.g. when mounted by a gateway
+    pub rest_base_path: String,
+    /// Serve the deprecated `GET /telemetry/login`, taking the identifier as raw body
+    pub rest_legacy_login_enabled: bool,
+    /// Handling of logins with an identifier which has an active session
+    pub session_policy: SessionPolicy,
+    /// format of the console logs, text or json
+    pub log_format: LogFormat,
+    /// protection of published operator identifiers: hash, truncate or keep
+    pub privacy_operator_id: OperatorIdPrivacy,
+    /// characters of operator identifiers kept when truncated
+    pub privacy_operator_id_prefix_length: u8,
+    /// key of the operator identifier hash, random per process if empty
+    pub privacy_hash_key: String,
+    /// decimal places of the published operator latitude and longitude
+    pub privacy_location_decimals: u8,
+    /// if unscrubbed operator data is also published to its own routing key
+    pub privacy_full_fidelity_enabled: bool,
+    /// C2 links not reported for this long are declared lost and alerted,
+    ///  0 disables the detection of silent aircraft
+    pub c2_link_timeout_ms: u32,
+    /// if the tracked aircraft are periodically written to a Redis snapshot
+    pub snapshot_enabled: bool,
+    /// interval between snapshots of the tracked aircraft
+    pub snapshot_interval_ms: u32,
+    /// if received packets are published unmodified to the `raw` exchange
+    pub raw_exchange_enabled: bool,
+    /// length of the geohash of the cells of the coverage map (1 to 12)
+    pub coverage_geohash_precision: u32,
+    /// interval between coverage summaries published to RabbitMQ, 0 to disable
+    pub coverage_summary_interval_ms: u32,
+    /// number of reports of an identical packet needed before it is pushed downstream, once
+    pub reporter_quorum: u32,
+    /// minimum interval between two published states of an ADS-B aircraft
+    pub adsb_state_interval_ms: u32,
+    /// location of the ADS-B receivers as `latitude,longitude` in degrees, the
+    ///  last resort reference to decode surface positions, empty if unknown
+    pub adsb_receiver_location: String,
+    /// Operating regions of Remote ID reporters, `reporter=latitude,longitude,radius_km`
+    ///  separated by semicolons. Positions outside the region of their reporter are rejected
+    pub reporter_regions: String,
+    /// Ground speed implied by consecutive positions above which a position jump
+    ///  is detected, in meters per second
+    pub anomaly_max_speed_mps: f32,
+    /// Vertical speed above which an impossible climb (or descent) is detected,
+    ///  in meters per second
+    pub anomaly_max_climb_rate_mps: f32,
+    /// Distance between simultaneous positions of an identifier from different
+    ///  reporters above which an identifier collision is detected
+    pub anomaly_collision_distance_meters: u32,
+    /// Positions from different reporters closer in time than this are
+    ///  compared for identifier collisions
+    pub anomaly_collision_window_ms: u32,
+    /// Comma separated URLs of RabbitMQ nodes in other regions, every message
+    ///  published to `AMQP__URL` is copied to each of them
+    pub amqp_mirrors: String,
+    /// Most svc-storage inserts per second of the deployment, 0 for no limit.
+    ///  Packets beyond the budget are spilled to a Redis backlog stored later
+    pub storage_inserts_per_second: u32,
+    /// Number of instances pushing to svc-storage, each inserting its share
+    ///  of `storage_inserts_per_second`
+    pub storage_insert_instances: u32,
+    /// Percentage of the insert budget of an instance reserved for storing
+    ///  the backlog (1 - 99), so it drains during sustained bursts
+    pub storage_backlog_reserved_percent: u8,
+    /// What handlers do when a dependency fails, as comma separated
+    ///  `dependency=action` entries (see [`crate::degradation`])
+    pub degradation_policy: String,
+    /// Interval between checks of the dependencies, whose health state
+    ///  changes are published to the system queue. 0 disables the checks
+    pub health_check_interval_ms: u32,
+    /// Time within which another token subject claiming a UAS ID, or another
+    ///  callsign of an ICAO address, is an identity conflict. 0 disables the detection
+    pub identity_conflict_window_ms: u32,
+    /// Positions of the svc-gis queue received longer ago than this are dropped
+    ///  when read, 0 keeps them. Must exceed the time after which pending
+    ///  items are reclaimed, so reclaimed items aren't dropped
+    pub gis_stale_after_ms: u32,
+    /// Credentials of the fleet gateways allowed to log in aircraft in bulk,
+    ///  comma separated `gateway=secret` entries, empty to disable bulk logins
+    pub gateway_credentials: String,
+    /// API keys of feeders without login, comma separated `reporter=digest` entries,
+    ///  the digest being the hex SHA-256 of the key
+    pub api_keys: String,
+    /// Secret required of the `/admin` routes in the `X-Admin-Secret` header,
+    ///  empty to refuse all admin requests
+    pub admin_secret: String,
+    /// If responses of the read routes are gzip compressed for clients accepting it.
+    ///  Telemetry routes are never compressed
+    pub rest_compression_enabled: bool,
+    /// Time the deduplication increments are collected for before they are sent
+    ///  in a pipeline per shard of their keys, 0 sends each increment on its own
+    pub dedup_batch_window_ms: u32,
+    /// Messages left unconsumed in a telemetry queue for this long are dropped,
+    ///  0 keeps them until consumed
+    pub amqp_queue_message_ttl_ms: u32,
+    /// Telemetry queues hold up to this many messages, the oldest are dropped
+    ///  first, 0 for unbounded queues
+    pub amqp_queue_max_length: u32,
+    /// If the telemetry queues keep their messages on disk instead of in memory
+    pub amqp_queue_lazy: bool,
+    /// Interval between polls of the depths of the telemetry queues, reported
+    ///  by the statistics endpoint, 0 disables the polls
+    pub amqp_queue_poll_interval_ms: u32,
+    /// How aircraft identifiers are written, as comma separated `kind.setting=value`
+    ///  entries (see [`crate::msg::identifier`]), empty for the formats of earlier releases
+    pub identifier_rules: String,
+    /// Whether the REST server also serves HTTP/2 (prior knowledge) on its port
+    pub rest_http2_enabled: bool,
+    /// Maximum number of concurrent requests of an HTTP/2 connection
+    pub rest_http2_max_concurrent_streams: u32,
+    /// Interval of the keep-alive probes of idle REST connections (TCP, and HTTP/2
+    ///  pings), 0 to disable them
+    pub rest_keep_alive_interval_ms: u32,
+    /// Time after which an HTTP/2 connection whose keep-alive ping isn't
+    ///  acknowledged is closed
+    pub rest_keep_alive_timeout_ms: u32,
+    /// Port of a second REST listener serving the read and admin routes, the
+    ///  listener of `docker_port_rest` then only serving telemetry reports (0 to serve
+    ///  all routes on `docker_port_rest`)
+    pub rest_admin_port: u16,
+    /// Issuer (`iss`) of the tokens issued, and required of the tokens presented,
+    ///  if set
+    pub jwt_issuer: String,
+    /// Audience (`aud`) of the tokens issued, and required of the tokens presented,
+    ///  if set
+    pub jwt_audience: String,
+    /// Comma separated algorithms the tokens of the identity provider may be
+    ///  signed with (e.g. `RS256,ES256`), each key of its JSON Web Key Set being
+    ///  bound to the algorithm it declares
+    pub jwt_algorithms: String,
+    /// URL of the JSON Web Key Set of the identity provider, verifying the tokens
+    ///  presented with a key ID (`kid`), if set
+    pub jwt_jwks_url: String,
+    /// Interval at which the JSON Web Key Set is fetched again
+    pub jwt_jwks_refresh_interval_ms: u32,
+    /// Ed25519 private key (PEM) signing the delivery receipts of Remote ID packets,
+    ///  if set
+    pub receipt_key_file: String,
+    /// Interval of the purges of per-aircraft cache keys idle for longer than
+    ///  `retention_max_idle_ms`, 0 to disable
+    pub retention_purge_interval_ms: u32,
+    /// Time after which per-aircraft cache keys not read or written are purged
+    pub retention_max_idle_ms: u32,
+    /// Keys examined by each SCAN of a purge
+    pub retention_scan_count: u32,
+    /// Maximum keys examined per second by a purge, 0 for no limit
+    pub retention_keys_per_second: u32,
+    /// PEM file of the certificate authorities trusted for a `rediss://` server,
+    ///  the system ones if empty
+    pub redis_tls_ca_file: String,
+    /// PEM file of the client certificate presented to a `rediss://` server
+    pub redis_tls_cert_file: String,
+    /// PEM file of the private key of `redis_tls_cert_file`
+    pub redis_tls_key_file: String,
+    /// PEM file of the certificate authorities trusted for an `amqps://` server,
+    ///  the system ones if empty
+    pub amqp_tls_ca_file: String,
+    /// PEM file of the client certificate presented to an `amqps://` server
+    pub amqp_tls_cert_file: String,
+    /// PEM file of the private key of `amqp_tls_cert_file`
+    pub amqp_tls_key_file: String,
+    /// Name expected in the certificate of an `amqps://` server, the host
+    ///  of the URL if empty
+    pub amqp_tls_server_name: String,
+    /// Positions kept in the trail of each aircraft by the `trail` sink
+    pub trail_max_points: u32,
+    /// Trails of aircraft without new positions for this long are deleted
+    pub trail_expire_ms: u32,
+    /// Components of the Remote ID state vectors received longer ago are flagged
+    ///  as stale
+    pub netrid_state_stale_ms: u32,
+    /// URL of the corridor assigned to a flight plan or mission, used by the
+    ///  conformance sink. `{mission}` is replaced by the UUID of the mission.
+    pub conformance_corridor_url: String,
+    /// Corridors are fetched again after this long
+    pub conformance_refresh_ms: u32,
+    /// Connections of the ADS-B cache pool, 0 for `redis.pool.max_size`
+    pub redis_adsb_pool_max_size: u32,
+    /// Time to wait for a free connection of the ADS-B cache pool, 0 for
+    ///  `redis.pool.timeouts.wait`
+    pub redis_adsb_pool_wait_timeout_ms: u32,
+    /// Connections of the Remote ID cache pool, 0 for `redis.pool.max_size`
+    pub redis_netrid_pool_max_size: u32,
+    /// Time to wait for a free connection of the Remote ID cache pool, 0 for
+    ///  `redis.pool.timeouts.wait`
+    pub redis_netrid_pool_wait_timeout_ms: u32,
+    /// Connections of the svc-gis queues pool, 0 for `redis.pool.max_size`
+    pub redis_gis_pool_max_size: u32,
+    /// Time to wait for a free connection of the svc-gis queues pool, 0 for
+    ///  `redis.pool.timeouts.wait`
+    pub redis_gis_pool_wait_timeout_ms: u32,
+    /// Delay before retrying to initialize Redis or RabbitMQ at startup, doubled
+    ///  after each failed attempt
+    pub startup_retry_initial_ms: u32,
+    /// Longest delay between attempts to initialize Redis or RabbitMQ
+    pub startup_retry_max_ms: u32,
+    /// Time to wait for Redis and RabbitMQ at startup before exiting, 0 to exit
+    ///  at the first failure
+    pub startup_max_wait_ms: u32,
+    /// Messages waiting in a telemetry queue from which its consumers are reported
+    ///  as falling behind, 0 disables the reports
+    pub amqp_queue_lag_threshold: u32,
+    /// Delay of the positions served by the public read routes, 0 serves them
+    ///  as received
+    pub privacy_public_delay_ms: u32,
+    /// Length of the geohash cell to whose center the public read routes snap
+    ///  the positions of Remote ID aircraft, 0 serves them as received
+    pub privacy_public_geohash_precision: u8,
+    /// Comma separated identifiers (ICAO address in hex or Remote ID identifier)
+    ///  of aircraft whose telemetry is rejected at ingest
+    pub blocklist: String,
 }
 
 impl Default for Config {
@@ -55,6 +384,7 @@ impl Config {
     /// Default values for Config
     pub fn new() -> Self {
         Config {
+            mode: ServerMode::All,
             docker_port_grpc: 50051,
             docker_port_rest: 8000,
             storage_port_grpc: 50051,
@@ -72,22 +402,139 @@ impl Config {
                 connection_properties: ConnectionProperties::default(),
             },
             log_config: String::from("log4rs.yaml"),
-            ringbuffer_size_bytes: 4096,
             gis_push_cadence_ms: 50,
             gis_max_message_size_bytes: 2048,
+            gis_stream_max_len: 100000,
             rest_request_limit_per_second: 2,
             rest_concurrency_limit_per_service: 5,
             rest_cors_allowed_origin: String::from("http://localhost:3000"),
+            storage_idempotency_enabled: true,
+            track_merge_window_ms: 500,
+            prediction_enabled: false,
+            prediction_interval_ms: 1000,
+            prediction_max_gap_ms: 5000,
+            velocity_filter_enabled: false,
+            velocity_filter_alpha: 0.5,
+            velocity_filter_beta: 0.1,
+            watchlist: String::new(),
+            reporter_quarantine_enabled: false,
+            reporter_max_error_rate: 0.5,
+            reporter_min_packets: 100,
+            netrid_relay_enabled: false,
+            grpc_breaker_failure_threshold: 5,
+            grpc_breaker_open_ms: 1000,
+            grpc_breaker_max_open_ms: 60000,
+            dispatcher_backlog_threshold: 200,
+            dispatcher_max_in_flight: 4,
+            dispatcher_max_retries: 5,
+            telemetry_sinks: String::from("gis,amqp,storage,trail"),
+            kafka_rest_url: String::new(),
+            kafka_topic: String::from("telemetry"),
+            kafka_timeout_ms: 2000,
+            rest_base_path: String::new(),
+            rest_legacy_login_enabled: true,
+            session_policy: SessionPolicy::Allow,
+            log_format: LogFormat::Text,
+            privacy_operator_id: OperatorIdPrivacy::Hash,
+            privacy_operator_id_prefix_length: 3,
+            privacy_hash_key: String::new(),
+            privacy_location_decimals: 2,
+            privacy_full_fidelity_enabled: false,
+            c2_link_timeout_ms: 10000,
+            snapshot_enabled: false,
+            snapshot_interval_ms: 5000,
+            raw_exchange_enabled: false,
+            coverage_geohash_precision: 5,
+            coverage_summary_interval_ms: 60000,
+            reporter_quorum: 1,
+            adsb_state_interval_ms: 1000,
+            adsb_receiver_location: String::new(),
+            reporter_regions: String::new(),
+            anomaly_max_speed_mps: 340.0,
+            anomaly_max_climb_rate_mps: 60.0,
+            anomaly_collision_distance_meters: 5000,
+            anomaly_collision_window_ms: 2000,
+            amqp_mirrors: String::new(),
+            storage_inserts_per_second: 0,
+            storage_insert_instances: 1,
+            storage_backlog_reserved_percent: 10,
+            degradation_policy: String::from(crate::degradation::DEFAULT_DEGRADATION_POLICY),
+            health_check_interval_ms: 5000,
+            identity_conflict_window_ms: 60000,
+            gis_stale_after_ms: 0,
+            gateway_credentials: String::new(),
+            api_keys: String::new(),
+            admin_secret: String::new(),
+            rest_compression_enabled: true,
+            dedup_batch_window_ms: 0,
+            amqp_queue_message_ttl_ms: 0,
+            amqp_queue_max_length: 0,
+            amqp_queue_lazy: false,
+            amqp_queue_poll_interval_ms: 10000,
+            identifier_rules: String::new(),
+            rest_http2_enabled: true,
+            rest_http2_max_concurrent_streams: 200,
+            rest_keep_alive_interval_ms: 20_000,
+            rest_keep_alive_timeout_ms: 20_000,
+            rest_admin_port: 0,
+            jwt_issuer: String::new(),
+            jwt_audience: String::new(),
+            jwt_algorithms: "HS256".to_string(),
+            jwt_jwks_url: String::new(),
+            jwt_jwks_refresh_interval_ms: 300_000,
+            receipt_key_file: String::new(),
+            retention_purge_interval_ms: 3_600_000,
+            retention_max_idle_ms: 3_600_000,
+            retention_scan_count: 500,
+            retention_keys_per_second: 5000,
+            redis_tls_ca_file: String::new(),
+            redis_tls_cert_file: String::new(),
+            redis_tls_key_file: String::new(),
+            amqp_tls_ca_file: String::new(),
+            amqp_tls_cert_file: String::new(),
+            amqp_tls_key_file: String::new(),
+            amqp_tls_server_name: String::new(),
+            trail_max_points: 120,
+            trail_expire_ms: 600_000,
+            netrid_state_stale_ms: 5000,
+            conformance_corridor_url: String::new(),
+            conformance_refresh_ms: 60000,
+            redis_adsb_pool_max_size: 0,
+            redis_adsb_pool_wait_timeout_ms: 0,
+            redis_netrid_pool_max_size: 0,
+            redis_netrid_pool_wait_timeout_ms: 0,
+            redis_gis_pool_max_size: 0,
+            redis_gis_pool_wait_timeout_ms: 0,
+            startup_retry_initial_ms: 500,
+            startup_retry_max_ms: 10000,
+            startup_max_wait_ms: 120000,
+            amqp_queue_lag_threshold: 1000,
+            privacy_public_delay_ms: 0,
+            privacy_public_geohash_precision: 0,
+            blocklist: String::new(),
         }
     }
 
-    /// Create a new `Config` object using environment variables
+    /// Create a new `Config` object using environment variables, over the
+    ///  settings of the configuration files if any (see [`config_files`])
+    ///
+    /// Fails with every problem found by [`Config::problems`].
     pub fn try_from_env() -> Result<Self, ConfigError> {
         // read .env file if present
         dotenv().ok();
         let default_config = Config::default();
 
-        config::Config::builder()
+        let files = config_files(
+            std::env::var("CONFIG_FILE").ok().as_deref(),
+            std::env::var("CONFIG_PROFILE").ok().as_deref(),
+        );
+
+        let config: Config = files
+            .into_iter()
+            .fold(config::Config::builder(), |builder, (path, required)| {
+                builder.add_source(File::new(&path, FileFormat::Toml).required(required))
+            })
+            .set_default("mode", "all")?
             .set_default("docker_port_grpc", default_config.docker_port_grpc)?
             .set_default("docker_port_rest", default_config.docker_port_rest)?
             .set_default("log_config", default_config.log_config)?
@@ -103,19 +550,536 @@ impl Config {
                 "rest_cors_allowed_origin",
                 default_config.rest_cors_allowed_origin,
             )?
-            .set_default(
-                "ringbuffer_size_bytes",
-                default_config.ringbuffer_size_bytes,
-            )?
             .set_default("gis_push_cadence_ms", default_config.gis_push_cadence_ms)?
             .set_default(
                 "gis_max_message_size_bytes",
                 default_config.gis_max_message_size_bytes,
             )?
+            .set_default("gis_stream_max_len", default_config.gis_stream_max_len)?
+            .set_default(
+                "storage_idempotency_enabled",
+                default_config.storage_idempotency_enabled,
+            )?
+            .set_default(
+                "track_merge_window_ms",
+                default_config.track_merge_window_ms,
+            )?
+            .set_default("prediction_enabled", default_config.prediction_enabled)?
+            .set_default(
+                "prediction_interval_ms",
+                default_config.prediction_interval_ms,
+            )?
+            .set_default(
+                "prediction_max_gap_ms",
+                default_config.prediction_max_gap_ms,
+            )?
+            .set_default(
+                "velocity_filter_enabled",
+                default_config.velocity_filter_enabled,
+            )?
+            .set_default(
+                "velocity_filter_alpha",
+                default_config.velocity_filter_alpha as f64,
+            )?
+            .set_default(
+                "velocity_filter_beta",
+                default_config.velocity_filter_beta as f64,
+            )?
+            .set_default("watchlist", default_config.watchlist)?
+            .set_default(
+                "reporter_quarantine_enabled",
+                default_config.reporter_quarantine_enabled,
+            )?
+            .set_default(
+                "reporter_max_error_rate",
+                default_config.reporter_max_error_rate as f64,
+            )?
+            .set_default("reporter_min_packets", default_config.reporter_min_packets)?
+            .set_default("netrid_relay_enabled", default_config.netrid_relay_enabled)?
+            .set_default(
+                "grpc_breaker_failure_threshold",
+                default_config.grpc_breaker_failure_threshold,
+            )?
+            .set_default("grpc_breaker_open_ms", default_config.grpc_breaker_open_ms)?
+            .set_default(
+                "grpc_breaker_max_open_ms",
+                default_config.grpc_breaker_max_open_ms,
+            )?
+            .set_default(
+                "dispatcher_backlog_threshold",
+                default_config.dispatcher_backlog_threshold,
+            )?
+            .set_default(
+                "dispatcher_max_in_flight",
+                default_config.dispatcher_max_in_flight,
+            )?
+            .set_default(
+                "dispatcher_max_retries",
+                default_config.dispatcher_max_retries,
+            )?
+            .set_default("telemetry_sinks", default_config.telemetry_sinks)?
+            .set_default("kafka_rest_url", default_config.kafka_rest_url)?
+            .set_default("kafka_topic", default_config.kafka_topic)?
+            .set_default("kafka_timeout_ms", default_config.kafka_timeout_ms)?
+            .set_default("rest_base_path", default_config.rest_base_path)?
+            .set_default(
+                "rest_legacy_login_enabled",
+                default_config.rest_legacy_login_enabled,
+            )?
+            .set_default("session_policy", "allow")?
+            .set_default("log_format", "text")?
+            .set_default("privacy_operator_id", "hash")?
+            .set_default(
+                "privacy_operator_id_prefix_length",
+                default_config.privacy_operator_id_prefix_length,
+            )?
+            .set_default("privacy_hash_key", default_config.privacy_hash_key)?
+            .set_default(
+                "privacy_location_decimals",
+                default_config.privacy_location_decimals,
+            )?
+            .set_default(
+                "privacy_full_fidelity_enabled",
+                default_config.privacy_full_fidelity_enabled,
+            )?
+            .set_default("c2_link_timeout_ms", default_config.c2_link_timeout_ms)?
+            .set_default("snapshot_enabled", default_config.snapshot_enabled)?
+            .set_default("snapshot_interval_ms", default_config.snapshot_interval_ms)?
+            .set_default("raw_exchange_enabled", default_config.raw_exchange_enabled)?
+            .set_default(
+                "coverage_geohash_precision",
+                default_config.coverage_geohash_precision,
+            )?
+            .set_default(
+                "coverage_summary_interval_ms",
+                default_config.coverage_summary_interval_ms,
+            )?
+            .set_default("reporter_quorum", default_config.reporter_quorum)?
+            .set_default(
+                "adsb_state_interval_ms",
+                default_config.adsb_state_interval_ms,
+            )?
+            .set_default(
+                "adsb_receiver_location",
+                default_config.adsb_receiver_location,
+            )?
+            .set_default("reporter_regions", default_config.reporter_regions)?
+            .set_default(
+                "anomaly_max_speed_mps",
+                default_config.anomaly_max_speed_mps as f64,
+            )?
+            .set_default(
+                "anomaly_max_climb_rate_mps",
+                default_config.anomaly_max_climb_rate_mps as f64,
+            )?
+            .set_default(
+                "anomaly_collision_distance_meters",
+                default_config.anomaly_collision_distance_meters,
+            )?
+            .set_default(
+                "anomaly_collision_window_ms",
+                default_config.anomaly_collision_window_ms,
+            )?
+            .set_default("amqp_mirrors", default_config.amqp_mirrors)?
+            .set_default(
+                "storage_inserts_per_second",
+                default_config.storage_inserts_per_second,
+            )?
+            .set_default(
+                "storage_insert_instances",
+                default_config.storage_insert_instances,
+            )?
+            .set_default(
+                "storage_backlog_reserved_percent",
+                default_config.storage_backlog_reserved_percent,
+            )?
+            .set_default("degradation_policy", default_config.degradation_policy)?
+            .set_default(
+                "health_check_interval_ms",
+                default_config.health_check_interval_ms,
+            )?
+            .set_default(
+                "identity_conflict_window_ms",
+                default_config.identity_conflict_window_ms,
+            )?
+            .set_default("gis_stale_after_ms", default_config.gis_stale_after_ms)?
+            .set_default("gateway_credentials", default_config.gateway_credentials)?
+            .set_default("api_keys", default_config.api_keys)?
+            .set_default("admin_secret", default_config.admin_secret)?
+            .set_default(
+                "rest_compression_enabled",
+                default_config.rest_compression_enabled,
+            )?
+            .set_default(
+                "dedup_batch_window_ms",
+                default_config.dedup_batch_window_ms,
+            )?
+            .set_default(
+                "amqp_queue_message_ttl_ms",
+                default_config.amqp_queue_message_ttl_ms,
+            )?
+            .set_default(
+                "amqp_queue_max_length",
+                default_config.amqp_queue_max_length,
+            )?
+            .set_default("amqp_queue_lazy", default_config.amqp_queue_lazy)?
+            .set_default(
+                "amqp_queue_poll_interval_ms",
+                default_config.amqp_queue_poll_interval_ms,
+            )?
+            .set_default("identifier_rules", default_config.identifier_rules)?
+            .set_default("rest_http2_enabled", default_config.rest_http2_enabled)?
+            .set_default(
+                "rest_http2_max_concurrent_streams",
+                default_config.rest_http2_max_concurrent_streams,
+            )?
+            .set_default(
+                "rest_keep_alive_interval_ms",
+                default_config.rest_keep_alive_interval_ms,
+            )?
+            .set_default(
+                "rest_keep_alive_timeout_ms",
+                default_config.rest_keep_alive_timeout_ms,
+            )?
+            .set_default("rest_admin_port", default_config.rest_admin_port)?
+            .set_default("jwt_issuer", default_config.jwt_issuer)?
+            .set_default("jwt_audience", default_config.jwt_audience)?
+            .set_default("jwt_algorithms", default_config.jwt_algorithms)?
+            .set_default("jwt_jwks_url", default_config.jwt_jwks_url)?
+            .set_default(
+                "jwt_jwks_refresh_interval_ms",
+                default_config.jwt_jwks_refresh_interval_ms,
+            )?
+            .set_default("receipt_key_file", default_config.receipt_key_file)?
+            .set_default(
+                "retention_purge_interval_ms",
+                default_config.retention_purge_interval_ms,
+            )?
+            .set_default(
+                "retention_max_idle_ms",
+                default_config.retention_max_idle_ms,
+            )?
+            .set_default("retention_scan_count", default_config.retention_scan_count)?
+            .set_default(
+                "retention_keys_per_second",
+                default_config.retention_keys_per_second,
+            )?
+            .set_default("redis_tls_ca_file", default_config.redis_tls_ca_file)?
+            .set_default("redis_tls_cert_file", default_config.redis_tls_cert_file)?
+            .set_default("redis_tls_key_file", default_config.redis_tls_key_file)?
+            .set_default("amqp_tls_ca_file", default_config.amqp_tls_ca_file)?
+            .set_default("amqp_tls_cert_file", default_config.amqp_tls_cert_file)?
+            .set_default("amqp_tls_key_file", default_config.amqp_tls_key_file)?
+            .set_default("amqp_tls_server_name", default_config.amqp_tls_server_name)?
+            .set_default("trail_max_points", default_config.trail_max_points)?
+            .set_default("trail_expire_ms", default_config.trail_expire_ms)?
+            .set_default(
+                "netrid_state_stale_ms",
+                default_config.netrid_state_stale_ms,
+            )?
+            .set_default(
+                "conformance_corridor_url",
+                default_config.conformance_corridor_url,
+            )?
+            .set_default(
+                "conformance_refresh_ms",
+                default_config.conformance_refresh_ms,
+            )?
+            .set_default(
+                "redis_adsb_pool_max_size",
+                default_config.redis_adsb_pool_max_size,
+            )?
+            .set_default(
+                "redis_adsb_pool_wait_timeout_ms",
+                default_config.redis_adsb_pool_wait_timeout_ms,
+            )?
+            .set_default(
+                "redis_netrid_pool_max_size",
+                default_config.redis_netrid_pool_max_size,
+            )?
+            .set_default(
+                "redis_netrid_pool_wait_timeout_ms",
+                default_config.redis_netrid_pool_wait_timeout_ms,
+            )?
+            .set_default(
+                "redis_gis_pool_max_size",
+                default_config.redis_gis_pool_max_size,
+            )?
+            .set_default(
+                "redis_gis_pool_wait_timeout_ms",
+                default_config.redis_gis_pool_wait_timeout_ms,
+            )?
+            .set_default(
+                "startup_retry_initial_ms",
+                default_config.startup_retry_initial_ms,
+            )?
+            .set_default("startup_retry_max_ms", default_config.startup_retry_max_ms)?
+            .set_default("startup_max_wait_ms", default_config.startup_max_wait_ms)?
+            .set_default(
+                "amqp_queue_lag_threshold",
+                default_config.amqp_queue_lag_threshold,
+            )?
+            .set_default(
+                "privacy_public_delay_ms",
+                default_config.privacy_public_delay_ms,
+            )?
+            .set_default(
+                "privacy_public_geohash_precision",
+                default_config.privacy_public_geohash_precision,
+            )?
+            .set_default("blocklist", default_config.blocklist)?
             .add_source(Environment::default().separator("__"))
             .build()?
-            .try_deserialize()
+            .try_deserialize()?;
+
+        match config.problems() {
+            problems if problems.is_empty() => Ok(config),
+            problems => Err(ConfigError::Message(format!(
+                "invalid configuration: {}",
+                problems.join("; ")
+            ))),
+        }
     }
+
+    /// Size and connection wait timeout in milliseconds of a Redis pool,
+    ///  0 where the shared `redis.pool` settings apply
+    ///
+    /// Pools are named by the last segment of their key folder (`adsb`,
+    ///  `netrid`) or `gis` for the svc-gis queues. Other pools only use the
+    ///  shared settings.
+    pub fn redis_pool_limits(&self, pool: &str) -> (u32, u32) {
+        match pool.rsplit(':').next().unwrap_or(pool) {
+            "adsb" => (
+                self.redis_adsb_pool_max_size,
+                self.redis_adsb_pool_wait_timeout_ms,
+            ),
+            "netrid" => (
+                self.redis_netrid_pool_max_size,
+                self.redis_netrid_pool_wait_timeout_ms,
+            ),
+            "gis" => (
+                self.redis_gis_pool_max_size,
+                self.redis_gis_pool_wait_timeout_ms,
+            ),
+            _ => (0, 0),
+        }
+    }
+
+    /// Settings out of their range, or missing settings they depend on
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let mut check = |valid: bool, problem: &str| {
+            if !valid {
+                problems.push(problem.to_string());
+            }
+        };
+
+        // Redis and RabbitMQ are only replaced by the in-memory stores
+        let backends = !cfg!(feature = "memory_backends");
+        check(
+            !backends || self.redis.url.is_some(),
+            "redis.url (REDIS__URL) is required",
+        );
+        check(
+            !backends || self.amqp.url.is_some(),
+            "amqp.url (AMQP__URL) is required",
+        );
+
+        check(
+            self.gis_push_cadence_ms > 0,
+            "gis_push_cadence_ms must be greater than 0",
+        );
+        check(
+            self.gis_max_message_size_bytes > 0,
+            "gis_max_message_size_bytes must be greater than 0",
+        );
+        check(
+            self.gis_stream_max_len > 0,
+            "gis_stream_max_len must be greater than 0",
+        );
+        check(
+            self.gis_stale_after_ms == 0 || self.gis_stale_after_ms as usize > GIS_PENDING_IDLE_MS,
+            "gis_stale_after_ms must be 0 or greater than the idle time of reclaimed items",
+        );
+        check(
+            self.storage_insert_instances > 0,
+            "storage_insert_instances must be greater than 0",
+        );
+        check(
+            (1..100).contains(&self.storage_backlog_reserved_percent),
+            "storage_backlog_reserved_percent must be between 1 and 99",
+        );
+        check(
+            self.rest_request_limit_per_second > 0,
+            "rest_request_limit_per_second must be greater than 0",
+        );
+        check(
+            self.rest_concurrency_limit_per_service > 0,
+            "rest_concurrency_limit_per_service must be greater than 0",
+        );
+        check(
+            self.rest_admin_port == 0 || self.rest_admin_port != self.docker_port_rest,
+            "rest_admin_port must differ from docker_port_rest",
+        );
+        check(
+            !self.rest_http2_enabled || self.rest_http2_max_concurrent_streams > 0,
+            "rest_http2_max_concurrent_streams must be greater than 0 with HTTP/2",
+        );
+        check(
+            !self.prediction_enabled || self.prediction_interval_ms > 0,
+            "prediction_interval_ms must be greater than 0 when predicting positions",
+        );
+        check(
+            !self.prediction_enabled || self.prediction_max_gap_ms >= self.prediction_interval_ms,
+            "prediction_max_gap_ms must be at least prediction_interval_ms",
+        );
+        check(
+            (0.0..=1.0).contains(&self.velocity_filter_alpha),
+            "velocity_filter_alpha must be between 0.0 and 1.0",
+        );
+        check(
+            (0.0..=1.0).contains(&self.velocity_filter_beta),
+            "velocity_filter_beta must be between 0.0 and 1.0",
+        );
+        check(
+            (0.0..=1.0).contains(&self.reporter_max_error_rate),
+            "reporter_max_error_rate must be between 0.0 and 1.0",
+        );
+        check(
+            self.grpc_breaker_failure_threshold > 0,
+            "grpc_breaker_failure_threshold must be greater than 0",
+        );
+        check(
+            self.grpc_breaker_max_open_ms >= self.grpc_breaker_open_ms,
+            "grpc_breaker_max_open_ms must be at least grpc_breaker_open_ms",
+        );
+        check(
+            self.dispatcher_max_in_flight > 0,
+            "dispatcher_max_in_flight must be greater than 0",
+        );
+        check(
+            !self.snapshot_enabled || self.snapshot_interval_ms > 0,
+            "snapshot_interval_ms must be greater than 0 when snapshots are enabled",
+        );
+        check(
+            (1..=12).contains(&self.coverage_geohash_precision),
+            "coverage_geohash_precision must be between 1 and 12",
+        );
+        check(
+            self.privacy_public_geohash_precision <= 12,
+            "privacy_public_geohash_precision must be between 0 and 12",
+        );
+        check(
+            self.reporter_quorum > 0,
+            "reporter_quorum must be greater than 0",
+        );
+        check(
+            self.anomaly_max_speed_mps > 0.0,
+            "anomaly_max_speed_mps must be greater than 0",
+        );
+        check(
+            self.anomaly_max_climb_rate_mps > 0.0,
+            "anomaly_max_climb_rate_mps must be greater than 0",
+        );
+        check(
+            self.jwt_jwks_url.trim().is_empty() || self.jwt_jwks_refresh_interval_ms > 0,
+            "jwt_jwks_refresh_interval_ms must be greater than 0 with jwt_jwks_url",
+        );
+        check(
+            self.jwt_jwks_url.trim().is_empty()
+                || self
+                    .jwt_jwks_url
+                    .trim()
+                    .to_ascii_lowercase()
+                    .starts_with("https://"),
+            "jwt_jwks_url must be an https:// URL",
+        );
+        check(
+            self.retention_purge_interval_ms == 0 || self.retention_scan_count > 0,
+            "retention_scan_count must be greater than 0 when purging keys",
+        );
+        check(
+            self.telemetry_sinks
+                .split(',')
+                .filter(|sink| !sink.trim().is_empty())
+                .all(|sink| SinkKind::from_str(sink).is_ok()),
+            "telemetry_sinks must only list known sinks",
+        );
+        check(
+            !self
+                .telemetry_sinks
+                .split(',')
+                .any(|sink| sink.trim().eq_ignore_ascii_case("kafka"))
+                || !self.kafka_rest_url.trim().is_empty(),
+            "kafka_rest_url is required by the kafka sink",
+        );
+        check(
+            self.kafka_timeout_ms > 0,
+            "kafka_timeout_ms must be greater than 0",
+        );
+        check(
+            !self
+                .telemetry_sinks
+                .split(',')
+                .any(|sink| sink.trim().eq_ignore_ascii_case("conformance"))
+                || self.conformance_corridor_url.contains("{mission}"),
+            "conformance_corridor_url with {mission} is required by the conformance sink",
+        );
+        check(
+            self.conformance_refresh_ms > 0,
+            "conformance_refresh_ms must be greater than 0",
+        );
+        check(
+            self.startup_retry_initial_ms > 0
+                && self.startup_retry_initial_ms <= self.startup_retry_max_ms,
+            "startup_retry_initial_ms must be between 1 and startup_retry_max_ms",
+        );
+
+        for (name, cert, key) in [
+            ("redis", &self.redis_tls_cert_file, &self.redis_tls_key_file),
+            ("amqp", &self.amqp_tls_cert_file, &self.amqp_tls_key_file),
+        ] {
+            check(
+                cert.trim().is_empty() == key.trim().is_empty(),
+                &format!("{name}_tls_cert_file and {name}_tls_key_file must be set together"),
+            );
+        }
+
+        problems
+    }
+}
+
+/// Configuration files read, with whether each is required, in increasing
+///  precedence. The environment takes precedence over all of them.
+///
+/// The base file is `CONFIG_FILE`, or an optional `config.toml`. With a
+///  `CONFIG_PROFILE`, the file of the profile next to it (e.g.
+///  `config.production.toml`) overrides it.
+pub fn config_files(file: Option<&str>, profile: Option<&str>) -> Vec<(String, bool)> {
+    let base = file
+        .map(str::trim)
+        .filter(|file| !file.is_empty())
+        .map(|file| (file.to_string(), true))
+        .unwrap_or((DEFAULT_CONFIG_FILE.to_string(), false));
+
+    let profile = profile
+        .map(str::trim)
+        .filter(|profile| !profile.is_empty())
+        .map(|profile| {
+            let path = Path::new(&base.0);
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let name = match path.extension() {
+                Some(extension) => format!("{stem}.{profile}.{}", extension.to_string_lossy()),
+                None => format!("{stem}.{profile}"),
+            };
+
+            (
+                path.with_file_name(name).to_string_lossy().into_owned(),
+                true,
+            )
+        });
+
+    std::iter::once(base).chain(profile).collect()
 }
 
 #[cfg(test)]
@@ -129,6 +1093,7 @@ mod tests {
 
         let config = Config::default();
 
+        assert_eq!(config.mode, ServerMode::All);
         assert_eq!(config.docker_port_grpc, 50051);
         assert_eq!(config.docker_port_rest, 8000);
         assert_eq!(config.storage_port_grpc, 50051);
@@ -141,15 +1106,125 @@ mod tests {
         assert!(config.redis.pool.is_none());
         assert!(config.redis.connection.is_none());
         assert_eq!(config.log_config, String::from("log4rs.yaml"));
-        assert_eq!(config.ringbuffer_size_bytes, 4096);
         assert_eq!(config.gis_push_cadence_ms, 50);
         assert_eq!(config.gis_max_message_size_bytes, 2048);
+        assert_eq!(config.gis_stream_max_len, 100000);
         assert_eq!(config.rest_concurrency_limit_per_service, 5);
         assert_eq!(config.rest_request_limit_per_second, 2);
         assert_eq!(
             config.rest_cors_allowed_origin,
             String::from("http://localhost:3000")
         );
+        assert!(config.storage_idempotency_enabled);
+        assert_eq!(config.track_merge_window_ms, 500);
+        assert!(!config.prediction_enabled);
+        assert_eq!(config.prediction_interval_ms, 1000);
+        assert_eq!(config.prediction_max_gap_ms, 5000);
+        assert!(!config.velocity_filter_enabled);
+        assert_eq!(config.velocity_filter_alpha, 0.5);
+        assert_eq!(config.velocity_filter_beta, 0.1);
+        assert!(config.watchlist.is_empty());
+        assert!(!config.reporter_quarantine_enabled);
+        assert_eq!(config.reporter_max_error_rate, 0.5);
+        assert_eq!(config.reporter_min_packets, 100);
+        assert!(!config.netrid_relay_enabled);
+        assert_eq!(config.grpc_breaker_failure_threshold, 5);
+        assert_eq!(config.grpc_breaker_open_ms, 1000);
+        assert_eq!(config.grpc_breaker_max_open_ms, 60000);
+        assert_eq!(config.dispatcher_backlog_threshold, 200);
+        assert_eq!(config.dispatcher_max_in_flight, 4);
+        assert_eq!(config.dispatcher_max_retries, 5);
+        assert_eq!(
+            config.telemetry_sinks,
+            String::from("gis,amqp,storage,trail")
+        );
+        assert_eq!(config.kafka_rest_url, String::new());
+        assert_eq!(config.kafka_topic, String::from("telemetry"));
+        assert_eq!(config.kafka_timeout_ms, 2000);
+        assert_eq!(config.rest_base_path, String::new());
+        assert!(config.rest_legacy_login_enabled);
+        assert_eq!(config.session_policy, SessionPolicy::Allow);
+        assert_eq!(config.log_format, LogFormat::Text);
+        assert_eq!(config.privacy_operator_id, OperatorIdPrivacy::Hash);
+        assert_eq!(config.privacy_operator_id_prefix_length, 3);
+        assert_eq!(config.privacy_hash_key, String::new());
+        assert_eq!(config.privacy_location_decimals, 2);
+        assert!(!config.privacy_full_fidelity_enabled);
+        assert_eq!(config.c2_link_timeout_ms, 10000);
+        assert!(!config.snapshot_enabled);
+        assert_eq!(config.snapshot_interval_ms, 5000);
+        assert!(!config.raw_exchange_enabled);
+        assert_eq!(config.coverage_geohash_precision, 5);
+        assert_eq!(config.coverage_summary_interval_ms, 60000);
+        assert_eq!(config.reporter_quorum, 1);
+        assert_eq!(config.adsb_state_interval_ms, 1000);
+        assert_eq!(config.adsb_receiver_location, String::new());
+        assert_eq!(config.reporter_regions, String::new());
+        assert_eq!(config.anomaly_max_speed_mps, 340.0);
+        assert_eq!(config.anomaly_max_climb_rate_mps, 60.0);
+        assert_eq!(config.anomaly_collision_distance_meters, 5000);
+        assert_eq!(config.anomaly_collision_window_ms, 2000);
+        assert_eq!(config.amqp_mirrors, String::new());
+        assert_eq!(config.storage_inserts_per_second, 0);
+        assert_eq!(config.storage_insert_instances, 1);
+        assert_eq!(config.storage_backlog_reserved_percent, 10);
+        assert_eq!(
+            config.degradation_policy,
+            String::from(crate::degradation::DEFAULT_DEGRADATION_POLICY)
+        );
+        assert_eq!(config.health_check_interval_ms, 5000);
+        assert_eq!(config.identity_conflict_window_ms, 60000);
+        assert_eq!(config.gis_stale_after_ms, 0);
+        assert_eq!(config.gateway_credentials, String::new());
+        assert_eq!(config.api_keys, String::new());
+        assert_eq!(config.admin_secret, String::new());
+        assert!(config.rest_compression_enabled);
+        assert_eq!(config.dedup_batch_window_ms, 0);
+        assert_eq!(config.amqp_queue_message_ttl_ms, 0);
+        assert_eq!(config.amqp_queue_max_length, 0);
+        assert!(!config.amqp_queue_lazy);
+        assert_eq!(config.amqp_queue_poll_interval_ms, 10000);
+        assert_eq!(config.identifier_rules, String::new());
+        assert!(config.rest_http2_enabled);
+        assert_eq!(config.rest_http2_max_concurrent_streams, 200);
+        assert_eq!(config.rest_keep_alive_interval_ms, 20_000);
+        assert_eq!(config.rest_keep_alive_timeout_ms, 20_000);
+        assert_eq!(config.rest_admin_port, 0);
+        assert_eq!(config.jwt_issuer, String::new());
+        assert_eq!(config.jwt_audience, String::new());
+        assert_eq!(config.jwt_algorithms, "HS256".to_string());
+        assert_eq!(config.jwt_jwks_url, String::new());
+        assert_eq!(config.jwt_jwks_refresh_interval_ms, 300_000);
+        assert_eq!(config.receipt_key_file, String::new());
+        assert_eq!(config.retention_purge_interval_ms, 3_600_000);
+        assert_eq!(config.retention_max_idle_ms, 3_600_000);
+        assert_eq!(config.retention_scan_count, 500);
+        assert_eq!(config.retention_keys_per_second, 5000);
+        assert_eq!(config.redis_tls_ca_file, String::new());
+        assert_eq!(config.redis_tls_cert_file, String::new());
+        assert_eq!(config.redis_tls_key_file, String::new());
+        assert_eq!(config.amqp_tls_ca_file, String::new());
+        assert_eq!(config.amqp_tls_cert_file, String::new());
+        assert_eq!(config.amqp_tls_key_file, String::new());
+        assert_eq!(config.amqp_tls_server_name, String::new());
+        assert_eq!(config.trail_max_points, 120);
+        assert_eq!(config.trail_expire_ms, 600_000);
+        assert_eq!(config.netrid_state_stale_ms, 5000);
+        assert_eq!(config.conformance_corridor_url, String::new());
+        assert_eq!(config.conformance_refresh_ms, 60000);
+        assert_eq!(config.redis_adsb_pool_max_size, 0);
+        assert_eq!(config.redis_adsb_pool_wait_timeout_ms, 0);
+        assert_eq!(config.redis_netrid_pool_max_size, 0);
+        assert_eq!(config.redis_netrid_pool_wait_timeout_ms, 0);
+        assert_eq!(config.redis_gis_pool_max_size, 0);
+        assert_eq!(config.redis_gis_pool_wait_timeout_ms, 0);
+        assert_eq!(config.startup_retry_initial_ms, 500);
+        assert_eq!(config.startup_retry_max_ms, 10000);
+        assert_eq!(config.startup_max_wait_ms, 120000);
+        assert_eq!(config.amqp_queue_lag_threshold, 1000);
+        assert_eq!(config.privacy_public_delay_ms, 0);
+        assert_eq!(config.privacy_public_geohash_precision, 0);
+        assert!(config.blocklist.is_empty());
         ut_info!("Success.");
     }
 
@@ -158,6 +1233,7 @@ mod tests {
         lib_common::logger::get_log_handle().await;
         ut_info!("Start.");
 
+        std::env::set_var("MODE", "dispatcher");
         std::env::set_var("DOCKER_PORT_GRPC", "6789");
         std::env::set_var("DOCKER_PORT_REST", "9876");
         std::env::set_var("STORAGE_HOST_GRPC", "test_host_grpc");
@@ -176,31 +1252,195 @@ mod tests {
         std::env::set_var("RINGBUFFER_SIZE_BYTES", "4096");
         std::env::set_var("GIS_PUSH_CADENCE_MS", "255");
         std::env::set_var("GIS_MAX_MESSAGE_SIZE_BYTES", "255");
+        std::env::set_var("GIS_STREAM_MAX_LEN", "5000");
         std::env::set_var("REST_CONCURRENCY_LIMIT_PER_SERVICE", "255");
         std::env::set_var("REST_REQUEST_LIMIT_PER_SECOND", "255");
         std::env::set_var(
             "REST_CORS_ALLOWED_ORIGIN",
             "https://allowed.origin.host:443",
         );
+        std::env::set_var("STORAGE_IDEMPOTENCY_ENABLED", "false");
+        std::env::set_var("TRACK_MERGE_WINDOW_MS", "250");
+        std::env::set_var("PREDICTION_ENABLED", "true");
+        std::env::set_var("PREDICTION_INTERVAL_MS", "500");
+        std::env::set_var("PREDICTION_MAX_GAP_MS", "3000");
+        std::env::set_var("VELOCITY_FILTER_ENABLED", "true");
+        std::env::set_var("VELOCITY_FILTER_ALPHA", "0.25");
+        std::env::set_var("VELOCITY_FILTER_BETA", "0.05");
+        std::env::set_var("WATCHLIST", "a1b2c3,drone-1");
+        std::env::set_var("REPORTER_QUARANTINE_ENABLED", "true");
+        std::env::set_var("REPORTER_MAX_ERROR_RATE", "0.25");
+        std::env::set_var("REPORTER_MIN_PACKETS", "50");
+        std::env::set_var("NETRID_RELAY_ENABLED", "true");
+        std::env::set_var("GRPC_BREAKER_FAILURE_THRESHOLD", "3");
+        std::env::set_var("GRPC_BREAKER_OPEN_MS", "500");
+        std::env::set_var("GRPC_BREAKER_MAX_OPEN_MS", "10000");
+        std::env::set_var("DISPATCHER_BACKLOG_THRESHOLD", "100");
+        std::env::set_var("DISPATCHER_MAX_IN_FLIGHT", "8");
+        std::env::set_var("DISPATCHER_MAX_RETRIES", "2");
+        std::env::set_var("TELEMETRY_SINKS", "amqp,kafka");
+        std::env::set_var("KAFKA_REST_URL", "http://test_kafka:8082");
+        std::env::set_var("KAFKA_TOPIC", "test_topic");
+        std::env::set_var("KAFKA_TIMEOUT_MS", "500");
+        std::env::set_var("REST_BASE_PATH", "/svc-telemetry");
+        std::env::set_var("REST_LEGACY_LOGIN_ENABLED", "false");
+        std::env::set_var("SESSION_POLICY", "replace");
+        std::env::set_var("LOG_FORMAT", "json");
+        std::env::set_var("PRIVACY_OPERATOR_ID", "truncate");
+        std::env::set_var("PRIVACY_OPERATOR_ID_PREFIX_LENGTH", "5");
+        std::env::set_var("PRIVACY_HASH_KEY", "secret");
+        std::env::set_var("PRIVACY_LOCATION_DECIMALS", "3");
+        std::env::set_var("PRIVACY_FULL_FIDELITY_ENABLED", "true");
+        std::env::set_var("C2_LINK_TIMEOUT_MS", "5000");
+        std::env::set_var("SNAPSHOT_ENABLED", "true");
+        std::env::set_var("SNAPSHOT_INTERVAL_MS", "1000");
+        std::env::set_var("RAW_EXCHANGE_ENABLED", "true");
+        std::env::set_var("COVERAGE_GEOHASH_PRECISION", "6");
+        std::env::set_var("COVERAGE_SUMMARY_INTERVAL_MS", "30000");
+        std::env::set_var("REPORTER_QUORUM", "2");
+        std::env::set_var("ADSB_STATE_INTERVAL_MS", "5000");
+        std::env::set_var("ADSB_RECEIVER_LOCATION", "52.3,4.8");
+        std::env::set_var(
+            "REPORTER_REGIONS",
+            "station-1=52.3,4.8,50;station-2=48.8,2.3,30",
+        );
+        std::env::set_var("ANOMALY_MAX_SPEED_MPS", "120.0");
+        std::env::set_var("ANOMALY_MAX_CLIMB_RATE_MPS", "25.0");
+        std::env::set_var("ANOMALY_COLLISION_DISTANCE_METERS", "2000");
+        std::env::set_var("ANOMALY_COLLISION_WINDOW_MS", "1000");
+        std::env::set_var(
+            "AMQP_MIRRORS",
+            "amqp://rabbitmq-eu:5672,amqp://rabbitmq-us:5672",
+        );
+        std::env::set_var("STORAGE_INSERTS_PER_SECOND", "200");
+        std::env::set_var("STORAGE_INSERT_INSTANCES", "4");
+        std::env::set_var("STORAGE_BACKLOG_RESERVED_PERCENT", "25");
+        std::env::set_var("DEGRADATION_POLICY", "redis=degrade,storage=buffer");
+        std::env::set_var("HEALTH_CHECK_INTERVAL_MS", "2000");
+        std::env::set_var("IDENTITY_CONFLICT_WINDOW_MS", "30000");
+        std::env::set_var("GIS_STALE_AFTER_MS", "60000");
+        std::env::set_var(
+            "GATEWAY_CREDENTIALS",
+            "gateway-1=secret-1,gateway-2=secret-2",
+        );
+        std::env::set_var(
+            "API_KEYS",
+            "sdr-station-1=ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        );
+        std::env::set_var("ADMIN_SECRET", "admin-secret");
+        std::env::set_var("REST_COMPRESSION_ENABLED", "false");
+        std::env::set_var("DEDUP_BATCH_WINDOW_MS", "2");
+        std::env::set_var("AMQP_QUEUE_MESSAGE_TTL_MS", "60000");
+        std::env::set_var("AMQP_QUEUE_MAX_LENGTH", "100000");
+        std::env::set_var("AMQP_QUEUE_LAZY", "true");
+        std::env::set_var("AMQP_QUEUE_POLL_INTERVAL_MS", "2000");
+        std::env::set_var("IDENTIFIER_RULES", "icao.case=upper,icao.pad=6");
+        std::env::set_var("REST_HTTP2_ENABLED", "false");
+        std::env::set_var("REST_HTTP2_MAX_CONCURRENT_STREAMS", "500");
+        std::env::set_var("REST_KEEP_ALIVE_INTERVAL_MS", "10000");
+        std::env::set_var("REST_KEEP_ALIVE_TIMEOUT_MS", "5000");
+        std::env::set_var("REST_ADMIN_PORT", "8001");
+        std::env::set_var("JWT_ISSUER", "https://id.example.com");
+        std::env::set_var("JWT_AUDIENCE", "svc-telemetry");
+        std::env::set_var("JWT_ALGORITHMS", "HS256,RS256");
+        std::env::set_var("JWT_JWKS_URL", "https://id.example.com/jwks.json");
+        std::env::set_var("JWT_JWKS_REFRESH_INTERVAL_MS", "60000");
+        std::env::set_var("RECEIPT_KEY_FILE", "/etc/svc-telemetry/receipt.pem");
+        std::env::set_var("RETENTION_PURGE_INTERVAL_MS", "60000");
+        std::env::set_var("RETENTION_MAX_IDLE_MS", "7200000");
+        std::env::set_var("RETENTION_SCAN_COUNT", "100");
+        std::env::set_var("RETENTION_KEYS_PER_SECOND", "1000");
+        std::env::set_var("REDIS_TLS_CA_FILE", "/etc/svc-telemetry/redis-ca.pem");
+        std::env::set_var("REDIS_TLS_CERT_FILE", "/etc/svc-telemetry/redis.pem");
+        std::env::set_var("REDIS_TLS_KEY_FILE", "/etc/svc-telemetry/redis.key");
+        std::env::set_var("AMQP_TLS_CA_FILE", "/etc/svc-telemetry/amqp-ca.pem");
+        std::env::set_var("AMQP_TLS_CERT_FILE", "/etc/svc-telemetry/amqp.pem");
+        std::env::set_var("AMQP_TLS_KEY_FILE", "/etc/svc-telemetry/amqp.key");
+        std::env::set_var("AMQP_TLS_SERVER_NAME", "rabbitmq.internal");
+
+        // files under the environment, the profile over the base file
+        let dir = std::env::temp_dir().join(format!("config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("telemetry.toml");
+        std::fs::write(
+            &file,
+            "kafka_topic = \"file_topic\"\n[redis.pool.timeouts.create]\nsecs = 5\nnanos = 0\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("telemetry.test.toml"),
+            "[redis.pool.timeouts.create]\nsecs = 7\nnanos = 0\n",
+        )
+        .unwrap();
+        std::env::set_var("CONFIG_FILE", &file);
+        std::env::set_var("CONFIG_PROFILE", "test");
+
+        std::env::set_var("TRAIL_MAX_POINTS", "60");
+        std::env::set_var("TRAIL_EXPIRE_MS", "300000");
+        std::env::set_var("NETRID_STATE_STALE_MS", "3000");
+        std::env::set_var(
+            "CONFORMANCE_CORRIDOR_URL",
+            "http://test_scheduler:8000/flight-plans/{mission}/corridor",
+        );
+        std::env::set_var("CONFORMANCE_REFRESH_MS", "30000");
+        std::env::set_var("REDIS_ADSB_POOL_MAX_SIZE", "32");
+        std::env::set_var("REDIS_ADSB_POOL_WAIT_TIMEOUT_MS", "250");
+        std::env::set_var("REDIS_NETRID_POOL_MAX_SIZE", "24");
+        std::env::set_var("REDIS_NETRID_POOL_WAIT_TIMEOUT_MS", "200");
+        std::env::set_var("REDIS_GIS_POOL_MAX_SIZE", "16");
+        std::env::set_var("REDIS_GIS_POOL_WAIT_TIMEOUT_MS", "100");
+        std::env::set_var("STARTUP_RETRY_INITIAL_MS", "250");
+        std::env::set_var("STARTUP_RETRY_MAX_MS", "5000");
+        std::env::set_var("STARTUP_MAX_WAIT_MS", "60000");
+        std::env::set_var("AMQP_QUEUE_LAG_THRESHOLD", "5000");
+        std::env::set_var("PRIVACY_PUBLIC_DELAY_MS", "30000");
+        std::env::set_var("PRIVACY_PUBLIC_GEOHASH_PRECISION", "6");
+        std::env::set_var("BLOCKLIST", "a1b2c3,test-drone");
         let config = Config::try_from_env();
+        std::env::remove_var("CONFIG_FILE");
+        std::env::remove_var("CONFIG_PROFILE");
+        std::fs::remove_dir_all(dir).unwrap();
         assert!(config.is_ok());
         let config = config.unwrap();
 
+        assert_eq!(config.kafka_topic, "test_topic");
+        assert_eq!(
+            config.redis.pool.unwrap().timeouts.create,
+            Some(std::time::Duration::from_secs(7))
+        );
+
+        assert_eq!(config.mode, ServerMode::Dispatcher);
         assert_eq!(config.docker_port_grpc, 6789);
         assert_eq!(config.storage_port_grpc, 12345);
         assert_eq!(config.storage_host_grpc, String::from("test_host_grpc"));
         assert_eq!(config.gis_port_grpc, 12345);
         assert_eq!(config.gis_host_grpc, String::from("test_host_grpc"));
         assert_eq!(config.log_config, String::from("config_file.yaml"));
-        assert_eq!(config.ringbuffer_size_bytes, 4096);
         assert_eq!(config.gis_push_cadence_ms, 255);
         assert_eq!(config.gis_max_message_size_bytes, 255);
+        assert_eq!(config.gis_stream_max_len, 5000);
         assert_eq!(config.rest_concurrency_limit_per_service, 255);
         assert_eq!(config.rest_request_limit_per_second, 255);
         assert_eq!(
             config.rest_cors_allowed_origin,
             String::from("https://allowed.origin.host:443")
         );
+        assert!(!config.storage_idempotency_enabled);
+        assert_eq!(config.track_merge_window_ms, 250);
+        assert!(config.prediction_enabled);
+        assert_eq!(config.prediction_interval_ms, 500);
+        assert_eq!(config.prediction_max_gap_ms, 3000);
+        assert!(config.velocity_filter_enabled);
+        assert_eq!(config.velocity_filter_alpha, 0.25);
+        assert_eq!(config.velocity_filter_beta, 0.05);
+        assert_eq!(config.watchlist, String::from("a1b2c3,drone-1"));
+        assert!(config.reporter_quarantine_enabled);
+        assert_eq!(config.reporter_max_error_rate, 0.25);
+        assert_eq!(config.reporter_min_packets, 50);
+        assert!(config.netrid_relay_enabled);
+        assert_eq!(config.grpc_breaker_failure_threshold, 3);
+        assert_eq!(config.grpc_breaker_open_ms, 500);
+        assert_eq!(config.grpc_breaker_max_open_ms, 10000);
         assert_eq!(
             config.amqp.url,
             Some(String::from("amqp://test_rabbitmq:5672"))
@@ -211,7 +1451,245 @@ mod tests {
             Some(String::from("redis://test_redis:6379"))
         );
         assert!(config.redis.pool.is_some());
+        assert_eq!(config.dispatcher_backlog_threshold, 100);
+        assert_eq!(config.dispatcher_max_in_flight, 8);
+        assert_eq!(config.dispatcher_max_retries, 2);
+        assert_eq!(config.telemetry_sinks, String::from("amqp,kafka"));
+        assert_eq!(
+            config.kafka_rest_url,
+            String::from("http://test_kafka:8082")
+        );
+        assert_eq!(config.kafka_topic, String::from("test_topic"));
+        assert_eq!(config.kafka_timeout_ms, 500);
+        assert_eq!(config.rest_base_path, String::from("/svc-telemetry"));
+        assert!(!config.rest_legacy_login_enabled);
+        assert_eq!(config.session_policy, SessionPolicy::Replace);
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.privacy_operator_id, OperatorIdPrivacy::Truncate);
+        assert_eq!(config.privacy_operator_id_prefix_length, 5);
+        assert_eq!(config.privacy_hash_key, String::from("secret"));
+        assert_eq!(config.privacy_location_decimals, 3);
+        assert!(config.privacy_full_fidelity_enabled);
+        assert_eq!(config.c2_link_timeout_ms, 5000);
+        assert!(config.snapshot_enabled);
+        assert_eq!(config.snapshot_interval_ms, 1000);
+        assert!(config.raw_exchange_enabled);
+        assert_eq!(config.coverage_geohash_precision, 6);
+        assert_eq!(config.coverage_summary_interval_ms, 30000);
+        assert_eq!(config.reporter_quorum, 2);
+        assert_eq!(config.adsb_state_interval_ms, 5000);
+        assert_eq!(config.adsb_receiver_location, String::from("52.3,4.8"));
+        assert_eq!(
+            config.reporter_regions,
+            String::from("station-1=52.3,4.8,50;station-2=48.8,2.3,30")
+        );
+        assert_eq!(config.anomaly_max_speed_mps, 120.0);
+        assert_eq!(config.anomaly_max_climb_rate_mps, 25.0);
+        assert_eq!(config.anomaly_collision_distance_meters, 2000);
+        assert_eq!(config.anomaly_collision_window_ms, 1000);
+        assert_eq!(
+            config.amqp_mirrors,
+            String::from("amqp://rabbitmq-eu:5672,amqp://rabbitmq-us:5672")
+        );
+        assert_eq!(config.storage_inserts_per_second, 200);
+        assert_eq!(config.storage_insert_instances, 4);
+        assert_eq!(config.storage_backlog_reserved_percent, 25);
+        assert_eq!(
+            config.degradation_policy,
+            String::from("redis=degrade,storage=buffer")
+        );
+        assert_eq!(config.health_check_interval_ms, 2000);
+        assert_eq!(config.identity_conflict_window_ms, 30000);
+        assert_eq!(config.gis_stale_after_ms, 60000);
+        assert_eq!(
+            config.gateway_credentials,
+            String::from("gateway-1=secret-1,gateway-2=secret-2")
+        );
+        assert_eq!(
+            config.api_keys,
+            String::from(
+                "sdr-station-1=ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+            )
+        );
+        assert_eq!(config.admin_secret, String::from("admin-secret"));
+        assert!(!config.rest_compression_enabled);
+        assert_eq!(config.dedup_batch_window_ms, 2);
+        assert_eq!(config.amqp_queue_message_ttl_ms, 60000);
+        assert_eq!(config.amqp_queue_max_length, 100000);
+        assert!(config.amqp_queue_lazy);
+        assert_eq!(config.amqp_queue_poll_interval_ms, 2000);
+        assert_eq!(config.identifier_rules, "icao.case=upper,icao.pad=6");
+        assert!(!config.rest_http2_enabled);
+        assert_eq!(config.rest_http2_max_concurrent_streams, 500);
+        assert_eq!(config.rest_keep_alive_interval_ms, 10_000);
+        assert_eq!(config.rest_keep_alive_timeout_ms, 5_000);
+        assert_eq!(config.rest_admin_port, 8001);
+        assert_eq!(config.jwt_issuer, "https://id.example.com");
+        assert_eq!(config.jwt_audience, "svc-telemetry");
+        assert_eq!(config.jwt_algorithms, "HS256,RS256");
+        assert_eq!(config.jwt_jwks_url, "https://id.example.com/jwks.json");
+        assert_eq!(config.jwt_jwks_refresh_interval_ms, 60_000);
+        assert_eq!(config.receipt_key_file, "/etc/svc-telemetry/receipt.pem");
+        assert_eq!(config.retention_purge_interval_ms, 60000);
+        assert_eq!(config.retention_max_idle_ms, 7200000);
+        assert_eq!(config.retention_scan_count, 100);
+        assert_eq!(config.retention_keys_per_second, 1000);
+        assert_eq!(config.redis_tls_ca_file, "/etc/svc-telemetry/redis-ca.pem");
+        assert_eq!(config.redis_tls_cert_file, "/etc/svc-telemetry/redis.pem");
+        assert_eq!(config.redis_tls_key_file, "/etc/svc-telemetry/redis.key");
+        assert_eq!(config.amqp_tls_ca_file, "/etc/svc-telemetry/amqp-ca.pem");
+        assert_eq!(config.amqp_tls_cert_file, "/etc/svc-telemetry/amqp.pem");
+        assert_eq!(config.amqp_tls_key_file, "/etc/svc-telemetry/amqp.key");
+        assert_eq!(config.amqp_tls_server_name, "rabbitmq.internal");
+        assert_eq!(config.trail_max_points, 60);
+        assert_eq!(config.trail_expire_ms, 300000);
+        assert_eq!(config.netrid_state_stale_ms, 3000);
+        assert_eq!(
+            config.conformance_corridor_url,
+            String::from("http://test_scheduler:8000/flight-plans/{mission}/corridor")
+        );
+        assert_eq!(config.conformance_refresh_ms, 30000);
+        assert_eq!(config.redis_adsb_pool_max_size, 32);
+        assert_eq!(config.redis_adsb_pool_wait_timeout_ms, 250);
+        assert_eq!(config.redis_netrid_pool_max_size, 24);
+        assert_eq!(config.redis_netrid_pool_wait_timeout_ms, 200);
+        assert_eq!(config.redis_gis_pool_max_size, 16);
+        assert_eq!(config.redis_gis_pool_wait_timeout_ms, 100);
+        assert_eq!(config.startup_retry_initial_ms, 250);
+        assert_eq!(config.startup_retry_max_ms, 5000);
+        assert_eq!(config.startup_max_wait_ms, 60000);
+        assert_eq!(config.amqp_queue_lag_threshold, 5000);
+        assert_eq!(config.privacy_public_delay_ms, 30000);
+        assert_eq!(config.privacy_public_geohash_precision, 6);
+        assert_eq!(config.blocklist, String::from("a1b2c3,test-drone"));
 
         ut_info!("Success.");
     }
+
+    #[test]
+    fn test_problems() {
+        let config = Config {
+            redis: deadpool_redis::Config::from_url("redis://localhost:6379"),
+            amqp: deadpool_lapin::Config {
+                url: Some("amqp://localhost:5672".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(config.problems().is_empty());
+
+        let problems = Config {
+            gis_push_cadence_ms: 0,
+            gis_stale_after_ms: 30000,
+            storage_insert_instances: 0,
+            storage_backlog_reserved_percent: 100,
+            velocity_filter_alpha: 1.5,
+            prediction_enabled: true,
+            prediction_interval_ms: 2000,
+            prediction_max_gap_ms: 1000,
+            rest_admin_port: config.docker_port_rest,
+            jwt_jwks_url: "http://id.example.com/jwks.json".to_string(),
+            telemetry_sinks: "gis,kafka,conformance,unknown".to_string(),
+            kafka_timeout_ms: 0,
+            redis_tls_cert_file: "redis.pem".to_string(),
+            startup_retry_initial_ms: 20000,
+            ..config.clone()
+        }
+        .problems();
+        assert_eq!(
+            problems,
+            vec![
+                "gis_push_cadence_ms must be greater than 0",
+                "gis_stale_after_ms must be 0 or greater than the idle time of reclaimed items",
+                "storage_insert_instances must be greater than 0",
+                "storage_backlog_reserved_percent must be between 1 and 99",
+                "rest_admin_port must differ from docker_port_rest",
+                "prediction_max_gap_ms must be at least prediction_interval_ms",
+                "velocity_filter_alpha must be between 0.0 and 1.0",
+                "jwt_jwks_url must be an https:// URL",
+                "telemetry_sinks must only list known sinks",
+                "kafka_rest_url is required by the kafka sink",
+                "kafka_timeout_ms must be greater than 0",
+                "conformance_corridor_url with {mission} is required by the conformance sink",
+                "startup_retry_initial_ms must be between 1 and startup_retry_max_ms",
+                "redis_tls_cert_file and redis_tls_key_file must be set together",
+            ]
+        );
+
+        let problems = Config::default().problems();
+        assert_eq!(
+            problems,
+            vec![
+                "redis.url (REDIS__URL) is required",
+                "amqp.url (AMQP__URL) is required",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_problems_gis_stale_after_ms() {
+        let problems = |gis_stale_after_ms: u32| {
+            Config {
+                gis_stale_after_ms,
+                ..Default::default()
+            }
+            .problems()
+            .into_iter()
+            .filter(|problem| problem.starts_with("gis_stale_after_ms"))
+            .count()
+        };
+
+        // reclaimed items must not be dropped as stale
+        assert_eq!(problems(0), 0);
+        assert_eq!(problems(GIS_PENDING_IDLE_MS as u32), 1);
+        assert_eq!(problems(GIS_PENDING_IDLE_MS as u32 + 1), 0);
+    }
+
+    #[test]
+    fn test_redis_pool_limits() {
+        let config = Config {
+            redis_adsb_pool_max_size: 32,
+            redis_adsb_pool_wait_timeout_ms: 250,
+            redis_gis_pool_max_size: 8,
+            ..Default::default()
+        };
+
+        assert_eq!(config.redis_pool_limits("tlm:adsb"), (32, 250));
+        assert_eq!(config.redis_pool_limits("adsb"), (32, 250));
+        assert_eq!(config.redis_pool_limits("gis"), (8, 0));
+        assert_eq!(config.redis_pool_limits("tlm:netrid"), (0, 0));
+        assert_eq!(config.redis_pool_limits("tlm:snapshot"), (0, 0));
+    }
+
+    #[test]
+    fn test_config_files() {
+        assert_eq!(
+            config_files(None, None),
+            vec![("config.toml".to_string(), false)]
+        );
+        assert_eq!(
+            config_files(Some(" "), Some("production")),
+            vec![
+                ("config.toml".to_string(), false),
+                ("config.production.toml".to_string(), true)
+            ]
+        );
+        assert_eq!(
+            config_files(Some("/etc/svc-telemetry/telemetry.toml"), Some("staging")),
+            vec![
+                ("/etc/svc-telemetry/telemetry.toml".to_string(), true),
+                (
+                    "/etc/svc-telemetry/telemetry.staging.toml".to_string(),
+                    true
+                )
+            ]
+        );
+        assert_eq!(
+            config_files(Some("settings"), Some("dev")),
+            vec![
+                ("settings".to_string(), true),
+                ("settings.dev".to_string(), true)
+            ]
+        );
+    }
 }
@@ -0,0 +1,192 @@
+//! Degradation policy of the handlers
+//!
+//! What happens to received telemetry when a dependency fails is looked up
+//!  here instead of being decided by each handler, see `DEGRADATION_POLICY`.
+//!  Dependencies the telemetry can't be accepted without, such as the Redis
+//!  streams of the dispatchers in `ingest` mode, always reject it.
+
+use crate::stats::Dependency;
+use hyper::StatusCode;
+use std::str::FromStr;
+
+/// Policy applied if `DEGRADATION_POLICY` doesn't list a dependency
+pub const DEFAULT_DEGRADATION_POLICY: &str =
+    "redis=reject,gis=reject,storage=reject,amqp=degrade,kafka=degrade";
+
+/// What to do with telemetry when a dependency fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Degradation {
+    /// Accept the telemetry, skipping what needs the dependency
+    Degrade,
+
+    /// Reject the telemetry with 503, for the client to retry it
+    Reject,
+
+    /// Accept the telemetry, holding it until the dependency recovers.
+    ///  Only svc-storage inserts can be held, in the storage backlog.
+    Buffer,
+}
+
+impl FromStr for Degradation {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "degrade" => Ok(Degradation::Degrade),
+            "reject" => Ok(Degradation::Reject),
+            "buffer" => Ok(Degradation::Buffer),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Parse a policy entry formatted `dependency=action`, none if the
+///  dependency is unknown or can't take the action
+pub fn parse_entry(entry: &str) -> Option<(Dependency, Degradation)> {
+    let (dependency, action) = entry.split_once('=')?;
+    let dependency = match dependency.trim().to_lowercase().as_str() {
+        "redis" => Dependency::Redis,
+        "gis" => Dependency::Gis,
+        "amqp" => Dependency::Amqp,
+        "storage" => Dependency::Storage,
+        "kafka" => Dependency::Kafka,
+        _ => return None,
+    };
+
+    let action = Degradation::from_str(action).ok()?;
+    let supported = action != Degradation::Buffer || dependency == Dependency::Storage;
+    supported.then_some((dependency, action))
+}
+
+/// Action taken on the failure of each dependency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DegradationPolicy {
+    /// Redis cache: deduplication, authentication pages and CPR pairs
+    redis: Degradation,
+
+    /// svc-gis queues
+    gis: Degradation,
+
+    /// RabbitMQ
+    amqp: Degradation,
+
+    /// svc-storage
+    storage: Degradation,
+
+    /// Kafka REST proxy
+    kafka: Degradation,
+}
+
+impl Default for DegradationPolicy {
+    fn default() -> Self {
+        DegradationPolicy::new(DEFAULT_DEGRADATION_POLICY)
+    }
+}
+
+impl DegradationPolicy {
+    /// Comma separated entries of a policy, see [`parse_entry`]
+    pub fn entries(list: &str) -> impl Iterator<Item = &str> {
+        list.split(',').filter(|entry| !entry.trim().is_empty())
+    }
+
+    /// Policy of the listed entries over the default policy, skipping
+    ///  invalid entries
+    pub fn new(list: &str) -> Self {
+        // each dependency is set by the default policy
+        let mut policy = DegradationPolicy {
+            redis: Degradation::Reject,
+            gis: Degradation::Reject,
+            amqp: Degradation::Reject,
+            storage: Degradation::Reject,
+            kafka: Degradation::Reject,
+        };
+
+        let entries = Self::entries(DEFAULT_DEGRADATION_POLICY).chain(Self::entries(list));
+        for (dependency, action) in entries.filter_map(parse_entry) {
+            *policy.action_mut(dependency) = action;
+        }
+
+        policy
+    }
+
+    /// Mutable action of a dependency
+    fn action_mut(&mut self, dependency: Dependency) -> &mut Degradation {
+        match dependency {
+            Dependency::Redis => &mut self.redis,
+            Dependency::Gis => &mut self.gis,
+            Dependency::Amqp => &mut self.amqp,
+            Dependency::Storage => &mut self.storage,
+            Dependency::Kafka => &mut self.kafka,
+        }
+    }
+
+    /// Action taken on a failure of the dependency
+    pub fn action(&self, dependency: Dependency) -> Degradation {
+        match dependency {
+            Dependency::Redis => self.redis,
+            Dependency::Gis => self.gis,
+            Dependency::Amqp => self.amqp,
+            Dependency::Storage => self.storage,
+            Dependency::Kafka => self.kafka,
+        }
+    }
+
+    /// If svc-storage inserts are buffered in the storage backlog
+    pub fn buffers_storage(&self) -> bool {
+        self.storage == Degradation::Buffer
+    }
+
+    /// Outcome of a failure of a dependency the handler can do without:
+    ///  carry on without it, or the status rejecting the telemetry
+    pub fn on_failure(&self, dependency: Dependency) -> Result<(), StatusCode> {
+        match self.action(dependency) {
+            Degradation::Reject => Err(StatusCode::SERVICE_UNAVAILABLE),
+            Degradation::Degrade | Degradation::Buffer => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entry() {
+        assert_eq!(
+            parse_entry("redis=degrade"),
+            Some((Dependency::Redis, Degradation::Degrade))
+        );
+        assert_eq!(
+            parse_entry(" Storage = BUFFER "),
+            Some((Dependency::Storage, Degradation::Buffer))
+        );
+
+        // only svc-storage inserts can be buffered
+        assert_eq!(parse_entry("amqp=buffer"), None);
+        assert_eq!(parse_entry("unknown=reject"), None);
+        assert_eq!(parse_entry("redis=retry"), None);
+        assert_eq!(parse_entry("redis"), None);
+    }
+
+    #[test]
+    fn test_policy() {
+        let policy = DegradationPolicy::default();
+        assert_eq!(policy.action(Dependency::Redis), Degradation::Reject);
+        assert_eq!(policy.action(Dependency::Amqp), Degradation::Degrade);
+        assert_eq!(
+            policy.on_failure(Dependency::Gis),
+            Err(StatusCode::SERVICE_UNAVAILABLE)
+        );
+        assert_eq!(policy.on_failure(Dependency::Kafka), Ok(()));
+
+        // listed dependencies override the default, invalid entries are skipped
+        let policy = DegradationPolicy::new("redis=degrade, storage=buffer,gis=buffer,,amqp");
+        assert_eq!(policy.action(Dependency::Redis), Degradation::Degrade);
+        assert_eq!(policy.action(Dependency::Storage), Degradation::Buffer);
+        assert_eq!(policy.action(Dependency::Gis), Degradation::Reject);
+        assert_eq!(policy.action(Dependency::Amqp), Degradation::Degrade);
+        assert_eq!(policy.on_failure(Dependency::Storage), Ok(()));
+        assert!(policy.buffers_storage());
+        assert!(!DegradationPolicy::default().buffers_storage());
+    }
+}
@@ -0,0 +1,891 @@
+//! Separation of telemetry ingest and backend pushes
+//!
+//! In `ingest` mode the REST server validates and deduplicates received
+//!  packets, then appends them to a Redis stream per protocol. Instances in
+//!  `dispatcher` mode share a consumer group on each stream, so every queued
+//!  packet is pushed to the configured sinks by one dispatcher.
+
+#[macro_use]
+pub mod macros;
+
+use crate::amqp::envelope::SignalMetadata;
+use crate::cache::pool::{CacheError, TelemetryPool};
+use lib_common::time::{DateTime, Utc};
+use std::collections::HashMap;
+
+#[cfg(not(test))]
+use crate::cache::{batch::DedupCounters, pool::GisPool, TelemetryPools};
+#[cfg(not(test))]
+use crate::degradation::DegradationPolicy;
+#[cfg(not(test))]
+use crate::grpc::client::GrpcClients;
+#[cfg(not(test))]
+use crate::logging::context;
+#[cfg(not(test))]
+use crate::rest::api::{adsb, netrid, Pipeline};
+#[cfg(not(test))]
+use crate::sink::SinkKind;
+#[cfg(not(test))]
+use hyper::StatusCode;
+
+/// Name of the stream within the key folder of each telemetry pool
+pub const STREAM_KEY: &str = "stream";
+
+/// Consumer group shared by all dispatchers
+pub const CONSUMER_GROUP: &str = "dispatcher";
+
+/// Streams are trimmed to about this many entries
+const STREAM_MAX_LEN: usize = 100_000;
+
+/// Maximum number of entries dispatched as a batch
+#[cfg(not(test))]
+const READ_COUNT: usize = 100;
+
+/// Time to wait for new entries before reading again
+#[cfg(not(test))]
+const READ_BLOCK_MS: usize = 1000;
+
+/// Entries left unacknowledged for this long are retried, either because
+///  a backend was unavailable or the dispatcher reading them crashed
+#[cfg(not(test))]
+const PENDING_IDLE_MS: usize = 30000;
+
+/// Field holding the hex encoded packet
+const FIELD_PAYLOAD: &str = "payload";
+
+/// Field holding the identifier the reporter was authorized with
+const FIELD_IDENTIFIER: &str = "identifier";
+
+/// Field holding the login session of the reporter
+const FIELD_SESSION: &str = "session";
+
+/// Field holding the flight plan or mission declared by the session
+const FIELD_MISSION: &str = "mission";
+
+/// Field holding the signal metadata declared by the receiver, as JSON
+const FIELD_SIGNAL: &str = "signal";
+
+/// Field holding when the packet was received, in milliseconds since the
+///  Unix epoch
+const FIELD_RECEIVED: &str = "received";
+
+/// A received packet waiting for dispatch
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamEntry {
+    /// The packet as received
+    pub payload: Vec<u8>,
+
+    /// Identifier of the reporter, if the endpoint requires authorization
+    pub identifier: Option<String>,
+
+    /// Login session of the reporter, if the endpoint requires authorization
+    pub session: Option<String>,
+
+    /// Flight plan or mission declared by the session of the reporter
+    pub mission: Option<String>,
+
+    /// Signal metadata declared by the receiver
+    pub signal: Option<SignalMetadata>,
+
+    /// When the packet was received, unknown for entries queued by older
+    ///  versions
+    pub received: Option<DateTime<Utc>>,
+}
+
+impl StreamEntry {
+    /// Fields of the stream entry
+    pub fn to_fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![(FIELD_PAYLOAD, crate::cache::bytes_to_key(&self.payload))];
+        if let Some(identifier) = &self.identifier {
+            fields.push((FIELD_IDENTIFIER, identifier.clone()));
+        }
+
+        if let Some(session) = &self.session {
+            fields.push((FIELD_SESSION, session.clone()));
+        }
+
+        if let Some(mission) = &self.mission {
+            fields.push((FIELD_MISSION, mission.clone()));
+        }
+
+        if let Some(signal) = self.signal.and_then(|s| serde_json::to_string(&s).ok()) {
+            fields.push((FIELD_SIGNAL, signal));
+        }
+
+        if let Some(received) = self.received {
+            fields.push((FIELD_RECEIVED, received.timestamp_millis().to_string()));
+        }
+
+        fields
+    }
+
+    /// Read an entry from its stream fields
+    pub fn from_fields(fields: &HashMap<String, String>) -> Option<Self> {
+        let payload = crate::cache::key_to_bytes(fields.get(FIELD_PAYLOAD)?)?;
+        Some(StreamEntry {
+            payload,
+            identifier: fields.get(FIELD_IDENTIFIER).cloned(),
+            session: fields.get(FIELD_SESSION).cloned(),
+            mission: fields.get(FIELD_MISSION).cloned(),
+            signal: fields
+                .get(FIELD_SIGNAL)
+                .and_then(|signal| serde_json::from_str(signal).ok()),
+            received: fields
+                .get(FIELD_RECEIVED)
+                .and_then(|received| received.parse().ok())
+                .and_then(DateTime::from_timestamp_millis),
+        })
+    }
+}
+
+/// Queue a received packet for dispatch
+pub async fn enqueue(pool: &mut TelemetryPool, entry: &StreamEntry) -> Result<(), CacheError> {
+    pool.stream_add(STREAM_KEY, &entry.to_fields(), STREAM_MAX_LEN)
+        .await
+        .map(|_| ())
+}
+
+/// Protocol of the packets in a stream
+#[cfg(not(test))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    /// ADS-B packets
+    Adsb,
+
+    /// Network Remote ID packets
+    Netrid,
+}
+
+/// Decode and push a single queued packet
+#[cfg(not(test))]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis and AMQP backends to test
+async fn dispatch(
+    source: Source,
+    entry: StreamEntry,
+    pipeline: &Pipeline,
+) -> Result<(), StatusCode> {
+    let pipeline = pipeline.clone();
+    let received = entry.received.unwrap_or_else(|| pipeline.clock.now());
+    match source {
+        Source::Adsb => {
+            let payload =
+                <[u8; crate::msg::adsb::ADSB_SIZE_BYTES]>::try_from(entry.payload.as_slice())
+                    .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+            adsb::process_adsb(payload, entry.signal, received, pipeline).await
+        }
+        Source::Netrid => {
+            let identifier = entry.identifier.ok_or(StatusCode::BAD_REQUEST)?;
+            let frame = netrid::decode_frame(&entry.payload)?;
+            let (session, mission, signal) = (entry.session, entry.mission, entry.signal);
+            netrid::process_netrid(
+                identifier, session, mission, signal, received, frame, pipeline,
+            )
+            .await
+        }
+    }
+}
+
+/// Number of batches dispatched concurrently
+///
+/// Entries are dispatched in order as a single batch, unless more than
+///  `threshold` are waiting. The backlog is then split across up to
+///  `max_in_flight` batches.
+fn batch_count(waiting: usize, threshold: usize, max_in_flight: usize) -> usize {
+    if waiting <= threshold {
+        return 1;
+    }
+
+    max_in_flight.clamp(1, waiting)
+}
+
+/// Batch of the entries of an aircraft, out of `batches`
+///
+/// Aircraft are told apart by the identifier of the reporter, or by the
+///  ICAO address of ADS-B packets. Malformed entries go to the first batch.
+fn batch_of(fields: &HashMap<String, String>, batches: usize) -> usize {
+    use std::hash::{Hash, Hasher};
+
+    let Some(entry) = StreamEntry::from_fields(fields) else {
+        return 0;
+    };
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    match &entry.identifier {
+        Some(identifier) => identifier.hash(&mut hasher),
+        None => entry.payload.get(1..4).hash(&mut hasher),
+    }
+
+    (hasher.finish() % batches.max(1) as u64) as usize
+}
+
+/// Split entries into `batches` batches, the entries of an aircraft all
+///  in the same batch and in the order they were queued
+fn partition(
+    entries: Vec<(String, HashMap<String, String>)>,
+    batches: usize,
+) -> Vec<Vec<(String, HashMap<String, String>)>> {
+    let mut partitions = vec![vec![]; batches.max(1)];
+    for (id, fields) in entries {
+        let batch = batch_of(&fields, partitions.len());
+        partitions[batch].push((id, fields));
+    }
+
+    partitions.retain(|batch| !batch.is_empty());
+    partitions
+}
+
+/// Time an entry has been waiting, from its stream ID (`<ms>-<sequence>`)
+fn entry_age_ms(id: &str, now_ms: i64) -> Option<i64> {
+    let (ms, _) = id.split_once('-')?;
+    Some((now_ms - ms.parse::<i64>().ok()?).max(0))
+}
+
+/// Outcome of dispatching a batch of entries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct BatchResult {
+    /// IDs of the entries pushed, or which can never be pushed
+    done: Vec<String>,
+
+    /// IDs of the entries which failed due to an unavailable backend
+    failed: Vec<String>,
+}
+
+impl BatchResult {
+    /// Combine the outcomes of two batches
+    fn merge(mut self, other: BatchResult) -> Self {
+        self.done.extend(other.done);
+        self.failed.extend(other.failed);
+        self
+    }
+}
+
+/// Failed entries delivered more than `max_retries` times after the
+///  first attempt, which are dropped instead of being retried again
+///
+/// Entries without a known delivery count are retried.
+fn exhausted(
+    failed: &[String],
+    deliveries: &HashMap<String, u64>,
+    max_retries: u16,
+) -> Vec<String> {
+    failed
+        .iter()
+        .filter(|id| {
+            deliveries
+                .get(*id)
+                .is_some_and(|count| *count > max_retries as u64)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Dispatch a batch of entries in order
+#[cfg(not(test))]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis and AMQP backends to test
+async fn dispatch_batch(
+    source: Source,
+    batch: Vec<(String, HashMap<String, String>)>,
+    pipeline: &Pipeline,
+) -> BatchResult {
+    let mut result = BatchResult::default();
+    for (id, fields) in batch {
+        let Some(entry) = StreamEntry::from_fields(&fields) else {
+            dispatcher_warn!("dropping malformed {source:?} entry {id}.");
+            result.done.push(id);
+            continue;
+        };
+
+        let dispatched = dispatch(source, entry, pipeline);
+        match context::scope(id.clone(), dispatched).await {
+            Err(code) if code.is_server_error() => {
+                dispatcher_warn!("could not dispatch {source:?} entry {id}: {code}.");
+                result.failed.push(id);
+            }
+            _ => result.done.push(id),
+        }
+    }
+
+    result
+}
+
+/// Consume the stream of one protocol until the task is cancelled
+///
+/// Entries are acknowledged once pushed, or if they can never be pushed.
+///  Entries which failed due to an unavailable backend remain pending
+///  and are retried first after [`PENDING_IDLE_MS`], up to
+///  `dispatcher_max_retries` times. Reading pauses after failures, so
+///  new entries queue up while a backend is down.
+///
+/// While more than `dispatcher_backlog_threshold` entries are waiting,
+///  they are dispatched as concurrent batches to catch up. Each aircraft
+///  keeps to a single batch, so its packets are still pushed in order.
+#[cfg(not(test))]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis and AMQP backends to test
+async fn consume(
+    source: Source,
+    mut stream_pool: TelemetryPool,
+    consumer: String,
+    pipeline: Pipeline,
+) {
+    while let Err(e) = stream_pool
+        .stream_create_group(STREAM_KEY, CONSUMER_GROUP)
+        .await
+    {
+        dispatcher_warn!("could not create {source:?} consumer group, retrying: {e}");
+        tokio::time::sleep(std::time::Duration::from_millis(READ_BLOCK_MS as u64)).await;
+    }
+
+    let threshold = pipeline.config.dispatcher_backlog_threshold as usize;
+    let max_in_flight = (pipeline.config.dispatcher_max_in_flight as usize).max(1);
+    let read_count = READ_COUNT * max_in_flight;
+    let max_retries = pipeline.config.dispatcher_max_retries;
+
+    dispatcher_info!("consuming {source:?} stream as '{consumer}'.");
+    loop {
+        let reclaimed = stream_pool
+            .stream_reclaim(
+                STREAM_KEY,
+                CONSUMER_GROUP,
+                &consumer,
+                PENDING_IDLE_MS,
+                read_count,
+            )
+            .await
+            .unwrap_or_else(|e| {
+                dispatcher_warn!("could not reclaim pending {source:?} entries: {e}");
+                vec![]
+            });
+
+        let entries = match stream_pool
+            .stream_read_group(
+                STREAM_KEY,
+                CONSUMER_GROUP,
+                &consumer,
+                read_count,
+                READ_BLOCK_MS,
+            )
+            .await
+        {
+            Ok(entries) => entries,
+            Err(e) => {
+                dispatcher_warn!("could not read {source:?} stream: {e}");
+                tokio::time::sleep(std::time::Duration::from_millis(READ_BLOCK_MS as u64)).await;
+                continue;
+            }
+        };
+
+        // how far behind this dispatcher is, reclaimed entries are retries
+        let now_ms = pipeline.clock.now().timestamp_millis();
+        let backlog_age_ms = entries
+            .first()
+            .and_then(|(id, _)| entry_age_ms(id, now_ms))
+            .unwrap_or(0);
+
+        let entries: Vec<_> = reclaimed.into_iter().chain(entries).collect();
+        let n_batches = batch_count(entries.len(), threshold, max_in_flight);
+        dispatcher_debug!(
+            "{source:?} backlog: {} entries, oldest waiting {backlog_age_ms} ms.",
+            entries.len()
+        );
+
+        if n_batches > 1 {
+            dispatcher_info!(
+                "{source:?} backlog of {} entries waiting up to {backlog_age_ms} ms, dispatching {n_batches} batches concurrently.",
+                entries.len()
+            );
+        }
+
+        let batches = partition(entries, n_batches)
+            .into_iter()
+            .map(|batch| dispatch_batch(source, batch, &pipeline));
+
+        let result = futures::future::join_all(batches)
+            .await
+            .into_iter()
+            .fold(BatchResult::default(), BatchResult::merge);
+
+        let mut done = result.done;
+        if !result.failed.is_empty() {
+            let deliveries = stream_pool
+                .stream_deliveries(STREAM_KEY, CONSUMER_GROUP, &result.failed)
+                .await
+                .unwrap_or_else(|e| {
+                    dispatcher_warn!("could not get {source:?} delivery counts: {e}");
+                    HashMap::new()
+                });
+
+            let exhausted = exhausted(&result.failed, &deliveries, max_retries);
+            for id in &exhausted {
+                dispatcher_error!("dropping {source:?} entry {id}, failed {max_retries} retries.");
+            }
+
+            done.extend(exhausted);
+        }
+
+        if let Err(e) = stream_pool
+            .stream_ack(STREAM_KEY, CONSUMER_GROUP, &done)
+            .await
+        {
+            dispatcher_warn!("could not acknowledge {source:?} entries: {e}");
+        }
+
+        if !result.failed.is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(READ_BLOCK_MS as u64)).await;
+        }
+    }
+}
+
+/// Name of this instance within the consumer groups
+pub(crate) fn consumer_name() -> String {
+    use rand::{distributions::Alphanumeric, Rng};
+
+    let suffix: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .map(char::from)
+        .collect();
+
+    match std::env::var("HOSTNAME") {
+        Ok(host) if !host.is_empty() => format!("{host}-{suffix}"),
+        _ => suffix,
+    }
+}
+
+/// Why the dispatcher could not start
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DispatcherError {
+    /// A Redis pool could not be created
+    Cache(CacheError),
+
+    /// The RabbitMQ channel could not be created
+    Amqp(crate::amqp::AMQPError),
+}
+
+impl DispatcherError {
+    /// Stable code of the error, for logs and statistics
+    pub fn code(&self) -> &'static str {
+        match self {
+            DispatcherError::Cache(e) => e.code(),
+            DispatcherError::Amqp(e) => e.code(),
+        }
+    }
+}
+
+impl std::fmt::Display for DispatcherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DispatcherError::Cache(e) => write!(f, "could not create redis pool: {e}"),
+            DispatcherError::Amqp(e) => write!(f, "could not create RabbitMQ channel: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for DispatcherError {}
+
+impl From<CacheError> for DispatcherError {
+    fn from(e: CacheError) -> Self {
+        DispatcherError::Cache(e)
+    }
+}
+
+impl From<crate::amqp::AMQPError> for DispatcherError {
+    fn from(e: crate::amqp::AMQPError) -> Self {
+        DispatcherError::Amqp(e)
+    }
+}
+
+/// Starts consuming the ingest streams and pushing their packets to the backends
+///
+/// Runs until a shutdown signal is received. Errors are logged with their
+///  code before being returned.
+#[cfg(not(test))]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis and AMQP backends to test
+pub async fn dispatcher(
+    config: crate::Config,
+    shutdown_rx: Option<tokio::sync::oneshot::Receiver<()>>,
+) -> Result<(), DispatcherError> {
+    dispatcher_info!("entry.");
+    start(config, shutdown_rx).await.inspect_err(|e| {
+        dispatcher_error!("[{}] {e}, exiting.", e.code());
+    })
+}
+
+/// Starts the dispatcher, see [`dispatcher`]
+#[cfg(not(test))]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis and AMQP backends to test
+async fn start(
+    config: crate::Config,
+    shutdown_rx: Option<tokio::sync::oneshot::Receiver<()>>,
+) -> Result<(), DispatcherError> {
+    let stats = crate::stats::Stats::default();
+    let tlm_pools = TelemetryPools {
+        adsb: TelemetryPool::new(config.clone(), "tlm:adsb")
+            .await?
+            .with_stats(stats.clone()),
+        netrid: TelemetryPool::new(config.clone(), "tlm:netrid")
+            .await?
+            .with_stats(stats.clone()),
+    };
+
+    // Blocking stream reads hold a connection, keep them apart from the backends
+    let streams = TelemetryPools {
+        adsb: TelemetryPool::new(config.clone(), "tlm:adsb").await?,
+        netrid: TelemetryPool::new(config.clone(), "tlm:netrid").await?,
+    };
+
+    crate::startup::wait_for_redis(&config, &tlm_pools.adsb).await?;
+    let mq_channel = crate::startup::wait_for_amqp(&config).await?;
+    crate::startup::set_ready();
+
+    let clock = crate::clock::SystemClock::shared();
+    let tracks = crate::msg::track::TrackMerger::shared(config.track_merge_window_ms);
+    if config.prediction_enabled {
+        tokio::spawn(crate::amqp::predict::prediction_loop(
+            config.clone(),
+            tracks.clone(),
+            mq_channel.clone(),
+            clock.clone(),
+        ));
+    }
+
+    let snapshot_pool = TelemetryPool::new(config.clone(), "tlm:snapshot")
+        .await?
+        .with_stats(stats.clone());
+    if config.snapshot_enabled {
+        tokio::spawn(crate::cache::snapshot::snapshot_loop(
+            config.clone(),
+            tracks.clone(),
+            snapshot_pool.clone(),
+        ));
+    }
+
+    let sinks = SinkKind::parse_list(&config.telemetry_sinks);
+    let coverage =
+        crate::msg::coverage::CoverageMap::shared(config.coverage_geohash_precision as usize);
+    if sinks.contains(&SinkKind::Coverage) && config.coverage_summary_interval_ms > 0 {
+        tokio::spawn(crate::amqp::coverage::coverage_loop(
+            coverage.clone(),
+            mq_channel.clone(),
+            stats.clone(),
+            config.coverage_summary_interval_ms,
+            clock.clone(),
+        ));
+    }
+
+    let degradation = DegradationPolicy::new(&config.degradation_policy);
+    let grpc_clients = GrpcClients::default(config.clone());
+    if (grpc_clients.storage_budget.is_limited() || degradation.buffers_storage())
+        && sinks.contains(&SinkKind::Storage)
+    {
+        tokio::spawn(crate::sink::storage::backlog_loop(
+            grpc_clients.clone(),
+            TelemetryPool::new(config.clone(), "tlm:adsb").await?,
+            stats.clone(),
+            consumer_name(),
+        ));
+    }
+
+    if config.health_check_interval_ms > 0 {
+        tokio::spawn(crate::amqp::system::health_loop(
+            grpc_clients.clone(),
+            tlm_pools.adsb.clone(),
+            mq_channel.clone(),
+            stats.clone(),
+            config.health_check_interval_ms,
+            clock.clone(),
+        ));
+    }
+
+    let identifiers = crate::msg::identifier::IdentifierRules::new(&config.identifier_rules);
+    let pipeline = Pipeline {
+        config: std::sync::Arc::new(config.clone()),
+        mq_channel: mq_channel.clone(),
+        dedup: DedupCounters::new(&tlm_pools, config.dedup_batch_window_ms),
+        tlm_pools,
+        gis_pool: GisPool::new(config.clone())
+            .await?
+            .with_stats(stats.clone()),
+        weather_pool: TelemetryPool::new(config.clone(), "tlm:weather")
+            .await?
+            .with_stats(stats.clone()),
+        snapshot_pool,
+        grpc_clients,
+        tracks,
+        filters: crate::msg::filter::VelocityFilters::shared(
+            config.velocity_filter_enabled,
+            config.velocity_filter_alpha,
+            config.velocity_filter_beta,
+        ),
+        stats,
+        watchlist: crate::msg::watchlist::Watchlist::shared(
+            &identifiers.resolve_list(&config.watchlist),
+        ),
+        blocklist: std::sync::Arc::new(crate::msg::blocklist::Blocklist::new(
+            &identifiers.resolve_list(&config.blocklist),
+        )),
+        backfill: crate::msg::backfill::BackfillStatus::shared(),
+        c2_links: crate::msg::c2::C2LinkMonitor::shared(config.c2_link_timeout_ms),
+        coverage,
+        sinks: std::sync::Arc::default(),
+        privacy: std::sync::Arc::new(crate::msg::privacy::Privacy::new(&config)),
+        identifiers: std::sync::Arc::new(identifiers),
+        geofence: std::sync::Arc::new(crate::msg::geofence::Geofence::new(
+            &config.reporter_regions,
+        )),
+        anomalies: std::sync::Arc::new(crate::msg::anomaly::AnomalyDetectors::new(&config)),
+        conformance: crate::msg::conformance::ConformanceMonitor::shared(
+            config.conformance_refresh_ms,
+        ),
+        clock,
+        degradation,
+        receipts: None,
+    }
+    .with_sinks(&sinks);
+
+    let consumer = consumer_name();
+    let tasks = [
+        tokio::spawn(consume(
+            Source::Adsb,
+            streams.adsb,
+            consumer.clone(),
+            pipeline.clone(),
+        )),
+        tokio::spawn(consume(Source::Netrid, streams.netrid, consumer, pipeline)),
+    ];
+
+    crate::shutdown_signal("dispatcher", shutdown_rx).await;
+    for task in tasks {
+        task.abort();
+    }
+
+    dispatcher_info!("shutdown.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amqp::envelope::MacAddress;
+
+    #[test]
+    fn test_stream_entry_fields() {
+        let entry = StreamEntry {
+            payload: vec![0x8d, 0x00, 0xff],
+            identifier: Some("test".to_string()),
+            session: Some("Xk2r9QaZ".to_string()),
+            mission: Some("7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5e".to_string()),
+            signal: Some(SignalMetadata {
+                receiver_latitude: Some(52.37),
+                receiver_longitude: Some(-4.9),
+                rssi_dbm: Some(-81.5),
+                snr_db: None,
+                broadcaster_mac: Some(MacAddress([0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f])),
+            }),
+            received: DateTime::from_timestamp_millis(1_700_000_000_123),
+        };
+
+        let fields = entry
+            .to_fields()
+            .into_iter()
+            .map(|(field, value)| (field.to_string(), value))
+            .collect::<HashMap<String, String>>();
+
+        assert_eq!(fields.get(FIELD_PAYLOAD), Some(&"8d00ff".to_string()));
+        assert_eq!(StreamEntry::from_fields(&fields), Some(entry));
+
+        let entry = StreamEntry {
+            payload: vec![0x01],
+            identifier: None,
+            session: None,
+            mission: None,
+            signal: None,
+            received: None,
+        };
+        let fields = entry
+            .to_fields()
+            .into_iter()
+            .map(|(field, value)| (field.to_string(), value))
+            .collect::<HashMap<String, String>>();
+        assert_eq!(StreamEntry::from_fields(&fields), Some(entry));
+
+        // payload is required
+        assert_eq!(StreamEntry::from_fields(&HashMap::new()), None);
+    }
+
+    #[test]
+    fn test_batch_count() {
+        // a single batch below the threshold
+        assert_eq!(batch_count(0, 200, 4), 1);
+        assert_eq!(batch_count(150, 200, 4), 1);
+        assert_eq!(batch_count(200, 200, 4), 1);
+
+        // backlog split across batches
+        assert_eq!(batch_count(400, 200, 4), 4);
+        assert_eq!(batch_count(3, 2, 4), 3);
+        assert_eq!(batch_count(400, 200, 0), 1);
+    }
+
+    /// Stream fields of an ADS-B packet of the given aircraft, tagged with
+    ///  its position in the stream
+    fn adsb_fields(icao: u32, seq: u8) -> HashMap<String, String> {
+        let icao = icao.to_be_bytes();
+        let entry = StreamEntry {
+            payload: vec![0x8d, icao[1], icao[2], icao[3], seq],
+            identifier: None,
+            session: None,
+            mission: None,
+            signal: None,
+            received: None,
+        };
+
+        entry
+            .to_fields()
+            .into_iter()
+            .map(|(field, value)| (field.to_string(), value))
+            .collect()
+    }
+
+    #[test]
+    fn test_partition() {
+        let aircraft = [0x4840d6, 0xa1b2c3, 0x3c6444, 0x400f2e, 0x06a0a8];
+        let entries: Vec<_> = (0..50u8)
+            .map(|seq| {
+                let icao = aircraft[seq as usize % aircraft.len()];
+                (format!("{seq}-0"), adsb_fields(icao, seq))
+            })
+            .collect();
+
+        let batches = partition(entries.clone(), 4);
+        assert!(batches.len() <= 4);
+        assert_eq!(batches.iter().map(Vec::len).sum::<usize>(), entries.len());
+
+        // each aircraft in a single batch, in stream order
+        for icao in aircraft {
+            let fields = adsb_fields(icao, 0);
+            let holding: Vec<_> = batches
+                .iter()
+                .filter(|batch| {
+                    batch
+                        .iter()
+                        .any(|(_, f)| f[FIELD_PAYLOAD][..8] == fields[FIELD_PAYLOAD][..8])
+                })
+                .collect();
+            assert_eq!(holding.len(), 1);
+
+            let sequence: Vec<u8> = holding[0]
+                .iter()
+                .filter(|(_, f)| f[FIELD_PAYLOAD][..8] == fields[FIELD_PAYLOAD][..8])
+                .map(|(id, _)| id.split('-').next().unwrap().parse().unwrap())
+                .collect();
+            let mut sorted = sequence.clone();
+            sorted.sort();
+            assert_eq!(sequence, sorted);
+            assert_eq!(sequence.len(), 10);
+        }
+
+        // a single batch keeps the stream order
+        let batches = partition(entries.clone(), 1);
+        assert_eq!(batches, vec![entries]);
+        assert!(partition(vec![], 4).is_empty());
+    }
+
+    #[test]
+    fn test_batch_of() {
+        let fields = |identifier: &str| {
+            let entry = StreamEntry {
+                payload: vec![0x01],
+                identifier: Some(identifier.to_string()),
+                session: None,
+                mission: None,
+                signal: None,
+                received: None,
+            };
+
+            entry
+                .to_fields()
+                .into_iter()
+                .map(|(field, value)| (field.to_string(), value))
+                .collect::<HashMap<String, String>>()
+        };
+
+        assert_eq!(
+            batch_of(&fields("drone-1"), 8),
+            batch_of(&fields("drone-1"), 8)
+        );
+        assert!(batch_of(&fields("drone-2"), 8) < 8);
+        assert_eq!(batch_of(&HashMap::new(), 8), 0);
+        assert_eq!(batch_of(&fields("drone-1"), 0), 0);
+    }
+
+    #[test]
+    fn test_exhausted() {
+        let failed = vec!["1-0".to_string(), "2-0".to_string(), "3-0".to_string()];
+        let deliveries = HashMap::from([("1-0".to_string(), 1), ("2-0".to_string(), 6)]);
+
+        // first attempt and 5 retries
+        assert_eq!(exhausted(&failed, &deliveries, 5), vec!["2-0".to_string()]);
+        assert_eq!(exhausted(&failed, &deliveries, 6), Vec::<String>::new());
+
+        // no retries
+        assert_eq!(
+            exhausted(&failed, &deliveries, 0),
+            vec!["1-0".to_string(), "2-0".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_batch_result_merge() {
+        let a = BatchResult {
+            done: vec!["1-0".to_string()],
+            failed: vec!["2-0".to_string()],
+        };
+        let b = BatchResult {
+            done: vec!["3-0".to_string()],
+            failed: vec![],
+        };
+
+        let result = BatchResult::default().merge(a).merge(b);
+        assert_eq!(result.done, vec!["1-0".to_string(), "3-0".to_string()]);
+        assert_eq!(result.failed, vec!["2-0".to_string()]);
+    }
+
+    #[test]
+    fn test_entry_age_ms() {
+        assert_eq!(entry_age_ms("1700000000000-0", 1700000001500), Some(1500));
+        assert_eq!(entry_age_ms("1700000000000-3", 1700000000000), Some(0));
+
+        // clock skew between redis and the dispatcher
+        assert_eq!(entry_age_ms("1700000002000-0", 1700000001000), Some(0));
+        assert_eq!(entry_age_ms("malformed", 0), None);
+        assert_eq!(entry_age_ms("x-0", 0), None);
+    }
+
+    #[test]
+    fn test_consumer_name() {
+        // consumers must not collide, even on the same host
+        assert_ne!(consumer_name(), consumer_name());
+    }
+
+    #[tokio::test]
+    async fn test_enqueue() {
+        let mut pool = TelemetryPool::new(crate::Config::default(), "test")
+            .await
+            .unwrap();
+        let entry = StreamEntry {
+            payload: vec![0x01],
+            identifier: None,
+            session: None,
+            mission: None,
+            signal: None,
+            received: None,
+        };
+
+        assert!(enqueue(&mut pool, &entry).await.is_ok());
+    }
+}
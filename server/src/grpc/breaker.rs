@@ -0,0 +1,300 @@
+//! Circuit breaking of downstream gRPC services
+//!
+//! After consecutive failed calls, a service is considered down and
+//!  calls to it fail immediately instead of waiting for a timeout. Once
+//!  open for a while, a single probe call is let through: its success
+//!  closes the breaker, its failure reopens it for twice as long.
+
+use serde::Serialize;
+use std::fmt::{self, Display};
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// State of a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls go through
+    Closed,
+
+    /// The service is considered down, calls are rejected
+    Open,
+
+    /// A probe call is in flight
+    HalfOpen,
+}
+
+/// Error of a call through a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerError<E> {
+    /// The call was rejected without reaching the service
+    Open,
+
+    /// The call failed
+    Failed(E),
+}
+
+impl<E: Display> Display for BreakerError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BreakerError::Open => write!(f, "circuit breaker open"),
+            BreakerError::Failed(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Thresholds of a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakerConfig {
+    /// Consecutive failures opening the breaker
+    pub failure_threshold: u32,
+
+    /// Time the breaker stays open before the first probe
+    pub open: Duration,
+
+    /// Longest time the breaker stays open between probes
+    pub max_open: Duration,
+}
+
+impl From<&crate::Config> for BreakerConfig {
+    fn from(config: &crate::Config) -> Self {
+        BreakerConfig {
+            failure_threshold: config.grpc_breaker_failure_threshold.max(1),
+            open: Duration::from_millis(config.grpc_breaker_open_ms as u64),
+            max_open: Duration::from_millis(config.grpc_breaker_max_open_ms as u64),
+        }
+    }
+}
+
+/// Mutable state of a circuit breaker
+#[derive(Debug)]
+struct Breaker {
+    /// Current state
+    state: BreakerState,
+
+    /// Consecutive failures while closed
+    failures: u32,
+
+    /// When the breaker was opened or the last probe started
+    since: Instant,
+
+    /// Time to wait before the next probe
+    open: Duration,
+}
+
+impl Breaker {
+    /// If a call may go through, starting a probe if due
+    fn allow(&mut self, now: Instant) -> bool {
+        match self.state {
+            BreakerState::Closed => true,
+            // a probe which never completed is retried
+            BreakerState::Open | BreakerState::HalfOpen => {
+                if now.duration_since(self.since) < self.open {
+                    return false;
+                }
+
+                self.state = BreakerState::HalfOpen;
+                self.since = now;
+                true
+            }
+        }
+    }
+
+    /// Close the breaker after a successful call
+    fn success(&mut self, config: &BreakerConfig) {
+        self.state = BreakerState::Closed;
+        self.failures = 0;
+        self.open = config.open;
+    }
+
+    /// Count a failed call, returns true if the breaker opened
+    fn failure(&mut self, config: &BreakerConfig, now: Instant) -> bool {
+        match self.state {
+            BreakerState::Closed => {
+                self.failures = self.failures.saturating_add(1);
+                if self.failures < config.failure_threshold {
+                    return false;
+                }
+
+                self.open = config.open;
+            }
+            BreakerState::HalfOpen => {
+                self.open = (self.open * 2).min(config.max_open);
+            }
+            // a call started before the breaker opened
+            BreakerState::Open => return false,
+        }
+
+        self.state = BreakerState::Open;
+        self.since = now;
+        true
+    }
+}
+
+/// Circuit breaker shared by the clones of a gRPC client
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    /// Name of the service, for logging
+    name: &'static str,
+
+    /// Thresholds of the breaker
+    config: BreakerConfig,
+
+    /// The breaker state
+    inner: Arc<Mutex<Breaker>>,
+}
+
+impl CircuitBreaker {
+    /// Create a closed circuit breaker
+    pub fn new(name: &'static str, config: BreakerConfig) -> Self {
+        CircuitBreaker {
+            name,
+            config,
+            inner: Arc::new(Mutex::new(Breaker {
+                state: BreakerState::Closed,
+                failures: 0,
+                since: Instant::now(),
+                open: config.open,
+            })),
+        }
+    }
+
+    /// Lock the breaker, which remains usable if a holder panicked
+    fn lock(&self) -> MutexGuard<'_, Breaker> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Current state of the breaker
+    pub fn state(&self) -> BreakerState {
+        self.lock().state
+    }
+
+    /// Make a call through the breaker
+    ///
+    /// The call isn't awaited if the breaker is open.
+    pub async fn call<T, E>(
+        &self,
+        call: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, BreakerError<E>> {
+        if !self.lock().allow(Instant::now()) {
+            grpc_debug!("{} circuit breaker open, call rejected.", self.name);
+            return Err(BreakerError::Open);
+        }
+
+        match call.await {
+            Ok(value) => {
+                let mut breaker = self.lock();
+                if breaker.state != BreakerState::Closed {
+                    grpc_info!("{} recovered, circuit breaker closed.", self.name);
+                }
+
+                breaker.success(&self.config);
+                Ok(value)
+            }
+            Err(e) => {
+                let mut breaker = self.lock();
+                if breaker.failure(&self.config, Instant::now()) {
+                    grpc_warn!(
+                        "{} considered down, circuit breaker open for {:?}.",
+                        self.name,
+                        breaker.open
+                    );
+                }
+
+                Err(BreakerError::Failed(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BreakerConfig {
+        BreakerConfig {
+            failure_threshold: 2,
+            open: Duration::from_millis(100),
+            max_open: Duration::from_millis(300),
+        }
+    }
+
+    fn breaker() -> Breaker {
+        Breaker {
+            state: BreakerState::Closed,
+            failures: 0,
+            since: Instant::now(),
+            open: config().open,
+        }
+    }
+
+    #[test]
+    fn test_breaker_opens_after_threshold() {
+        let config = config();
+        let mut breaker = breaker();
+        let now = Instant::now();
+
+        assert!(!breaker.failure(&config, now));
+        assert!(breaker.allow(now));
+
+        // successes reset the count
+        breaker.success(&config);
+        assert!(!breaker.failure(&config, now));
+        assert!(breaker.failure(&config, now));
+        assert_eq!(breaker.state, BreakerState::Open);
+        assert!(!breaker.allow(now + Duration::from_millis(99)));
+    }
+
+    #[test]
+    fn test_breaker_probes() {
+        let config = config();
+        let mut breaker = breaker();
+        let now = Instant::now();
+        breaker.failure(&config, now);
+        breaker.failure(&config, now);
+
+        // a single probe once open long enough
+        let now = now + Duration::from_millis(100);
+        assert!(breaker.allow(now));
+        assert_eq!(breaker.state, BreakerState::HalfOpen);
+        assert!(!breaker.allow(now));
+
+        // failed probe, open twice as long
+        assert!(breaker.failure(&config, now));
+        assert_eq!(breaker.open, Duration::from_millis(200));
+        assert!(!breaker.allow(now + Duration::from_millis(199)));
+
+        let now = now + Duration::from_millis(200);
+        assert!(breaker.allow(now));
+        breaker.failure(&config, now);
+        assert_eq!(breaker.open, config.max_open);
+
+        // successful probe
+        let now = now + config.max_open;
+        assert!(breaker.allow(now));
+        breaker.success(&config);
+        assert_eq!(breaker.state, BreakerState::Closed);
+        assert_eq!(breaker.open, config.open);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_call() {
+        let breaker = CircuitBreaker::new("test", config());
+
+        let result = breaker.call(async { Err::<(), _>("down") }).await;
+        assert_eq!(result, Err(BreakerError::Failed("down")));
+        let _ = breaker.call(async { Err::<(), _>("down") }).await;
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        let result = breaker.call(async { Ok::<_, &str>(1) }).await;
+        assert_eq!(result, Err(BreakerError::Open));
+        assert_eq!(
+            format!("{}", BreakerError::<&str>::Open),
+            "circuit breaker open"
+        );
+    }
+}
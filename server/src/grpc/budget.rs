@@ -0,0 +1,182 @@
+//! Rate limiting of downstream gRPC calls
+//!
+//! A token bucket refilled at a steady rate holds at most a second of
+//!  calls, so bursts of telemetry are spread over time instead of being
+//!  passed on to the downstream service as-is.
+//!
+//! Buckets are kept per instance: a budget of the deployment is split
+//!  evenly between the instances calling the service.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Token bucket of a call budget
+#[derive(Debug)]
+struct TokenBucket {
+    /// Calls allowed per second, also the capacity of the bucket
+    rate: f64,
+
+    /// Calls currently allowed
+    tokens: f64,
+
+    /// When the bucket was last refilled
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    /// Full bucket
+    fn new(rate: u32, now: Instant) -> Self {
+        TokenBucket {
+            rate: rate as f64,
+            tokens: rate as f64,
+            refilled: now,
+        }
+    }
+
+    /// Take a token, or the time to wait until one is available
+    fn take(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.refilled = now.max(self.refilled);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+    }
+}
+
+/// Call budget shared by the clones of a gRPC client, unlimited if
+///  created with a rate of 0
+#[derive(Debug, Clone)]
+pub struct CallBudget {
+    /// The token bucket, none if unlimited
+    inner: Option<Arc<Mutex<TokenBucket>>>,
+}
+
+impl CallBudget {
+    /// Budget of `rate` calls per second
+    pub fn new(rate: u32) -> Self {
+        let inner = match rate {
+            0 => None,
+            rate => Some(Arc::new(Mutex::new(TokenBucket::new(rate, Instant::now())))),
+        };
+
+        CallBudget { inner }
+    }
+
+    /// Share of an instance of a budget of `rate` calls per second split
+    ///  between `instances`, as the budget of calls made as they come and
+    ///  the budget reserved for `reserved_percent` of the share
+    ///
+    /// Each limited budget allows at least a call per second.
+    pub fn split(rate: u32, instances: u32, reserved_percent: u8) -> (Self, Self) {
+        if rate == 0 {
+            return (CallBudget::new(0), CallBudget::new(0));
+        }
+
+        let share = (rate / instances.max(1)).max(1);
+        let reserved = (share * reserved_percent.min(100) as u32 / 100).max(1);
+        let remaining = share.saturating_sub(reserved).max(1);
+        (CallBudget::new(remaining), CallBudget::new(reserved))
+    }
+
+    /// If calls are limited
+    pub fn is_limited(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Lock the bucket, which remains usable if a holder panicked
+    fn lock(inner: &Mutex<TokenBucket>) -> MutexGuard<'_, TokenBucket> {
+        inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Take a call from the budget if one is left
+    pub fn try_acquire(&self) -> bool {
+        match &self.inner {
+            Some(inner) => Self::lock(inner).take(Instant::now()).is_ok(),
+            None => true,
+        }
+    }
+
+    /// Take a call from the budget, waiting until one is left
+    pub async fn acquire(&self) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+
+        loop {
+            let wait = match Self::lock(inner).take(Instant::now()) {
+                Ok(()) => return,
+                Err(wait) => wait,
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(2, now);
+
+        // a second of burst
+        assert!(bucket.take(now).is_ok());
+        assert!(bucket.take(now).is_ok());
+        assert_eq!(bucket.take(now), Err(Duration::from_millis(500)));
+
+        let later = now + Duration::from_millis(250);
+        assert_eq!(bucket.take(later), Err(Duration::from_millis(250)));
+        assert!(bucket.take(now + Duration::from_millis(500)).is_ok());
+
+        // idle buckets don't fill beyond their rate
+        let idle = now + Duration::from_secs(60);
+        assert!(bucket.take(idle).is_ok());
+        assert!(bucket.take(idle).is_ok());
+        assert!(bucket.take(idle).is_err());
+    }
+
+    #[test]
+    fn test_call_budget_split() {
+        let rates = |(budget, reserved): (CallBudget, CallBudget)| {
+            let count = |budget: CallBudget| (0..1000).filter(|_| budget.try_acquire()).count();
+            (count(budget), count(reserved))
+        };
+
+        assert_eq!(rates(CallBudget::split(0, 4, 10)), (1000, 1000));
+        assert_eq!(rates(CallBudget::split(200, 1, 10)), (180, 20));
+        assert_eq!(rates(CallBudget::split(200, 4, 10)), (45, 5));
+
+        // shares too small to split still allow calls
+        assert_eq!(rates(CallBudget::split(3, 4, 10)), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_call_budget() {
+        let unlimited = CallBudget::new(0);
+        assert!(!unlimited.is_limited());
+        assert!((0..1000).all(|_| unlimited.try_acquire()));
+        unlimited.acquire().await;
+
+        let budget = CallBudget::new(100);
+        assert!(budget.is_limited());
+        assert_eq!(
+            (0..200).filter(|_| budget.clone().try_acquire()).count(),
+            100
+        );
+
+        // waits for the next token, 10ms at 100 calls per second
+        let start = Instant::now();
+        budget.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(5));
+    }
+}
@@ -1,4 +1,8 @@
 //! gRPC client helpers implementation
+use super::breaker::{BreakerConfig, BreakerState, CircuitBreaker};
+use super::budget::CallBudget;
+use crate::stats::Dependency;
+use std::collections::HashMap;
 use svc_gis_client_grpc::prelude::Client;
 use svc_gis_client_grpc::prelude::GisClient;
 use svc_storage_client_grpc::prelude::Clients;
@@ -10,16 +14,53 @@ pub struct GrpcClients {
     pub storage: Clients,
     /// A GrpcClient provided by the svc_gis_grpc_client module
     pub gis: GisClient,
+    /// Circuit breakers of the clients
+    pub breakers: Breakers,
+    /// Insert budget of svc-storage of the instance, see `STORAGE_INSERTS_PER_SECOND`
+    pub storage_budget: CallBudget,
+    /// Insert budget of svc-storage reserved for storing the backlog
+    pub storage_backlog_budget: CallBudget,
+}
+
+/// Circuit breakers of the downstream services
+#[derive(Clone, Debug)]
+pub struct Breakers {
+    /// Breaker of svc-storage calls
+    pub storage: CircuitBreaker,
+    /// Breaker of svc-gis calls
+    pub gis: CircuitBreaker,
+}
+
+impl Breakers {
+    /// Current state of each breaker
+    pub fn states(&self) -> HashMap<Dependency, BreakerState> {
+        HashMap::from([
+            (Dependency::Storage, self.storage.state()),
+            (Dependency::Gis, self.gis.state()),
+        ])
+    }
 }
 
 impl GrpcClients {
     /// Create new GrpcClients with defaults
     pub fn default(config: crate::config::Config) -> Self {
+        let breaker_config = BreakerConfig::from(&config);
         let storage_clients = Clients::new(config.storage_host_grpc, config.storage_port_grpc);
+        let (storage_budget, storage_backlog_budget) = CallBudget::split(
+            config.storage_inserts_per_second,
+            config.storage_insert_instances,
+            config.storage_backlog_reserved_percent,
+        );
 
         GrpcClients {
             storage: storage_clients,
             gis: GisClient::new_client(&config.gis_host_grpc, config.gis_port_grpc, "gis"),
+            breakers: Breakers {
+                storage: CircuitBreaker::new("svc-storage", breaker_config),
+                gis: CircuitBreaker::new("svc-gis", breaker_config),
+            },
+            storage_budget,
+            storage_backlog_budget,
         }
     }
 }
@@ -46,6 +87,10 @@ mod tests {
         ut_debug!("gis: {:?}", gis);
         assert_eq!(gis.get_name(), "gis");
 
+        let states = clients.breakers.states();
+        assert_eq!(states[&Dependency::Storage], BreakerState::Closed);
+        assert_eq!(states[&Dependency::Gis], BreakerState::Closed);
+
         ut_info!("Success.");
     }
 }
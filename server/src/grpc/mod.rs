@@ -3,5 +3,7 @@
 
 #[macro_use]
 pub mod macros;
+pub mod breaker;
+pub mod budget;
 pub mod client;
 pub mod server;
@@ -5,19 +5,110 @@ pub mod grpc_server {
     tonic::include_proto!("grpc");
 }
 pub use grpc_server::rpc_service_server::{RpcService, RpcServiceServer};
-pub use grpc_server::{ReadyRequest, ReadyResponse};
+pub use grpc_server::{
+    AircraftPosition, AircraftStateRequest, AircraftStateResponse, AircraftStatus,
+    AircraftVelocity, ReadyRequest, ReadyResponse,
+};
 
+use crate::cache::pool::TelemetryPool;
+use crate::clock::{SharedClock, SystemClock};
+use crate::msg::adsb::OperationalStatus;
+use crate::msg::identifier::IdentifierRules;
+use crate::msg::track::TrackSnapshot;
 use crate::shutdown_signal;
 use crate::Config;
 
-use std::fmt::Debug;
+use lib_common::time::{DateTime, Utc};
+use std::fmt::{self, Debug, Display, Formatter};
 use std::net::SocketAddr;
 use tonic::transport::Server;
 use tonic::{Request, Response, Status};
 
+/// Caches the aircraft states are read from
+#[derive(Debug, Clone)]
+pub struct StateCaches {
+    /// Snapshot of the aircraft tracked by all instances
+    pub snapshot: TelemetryPool,
+
+    /// Combined state vectors of ADS-B aircraft
+    pub adsb: TelemetryPool,
+}
+
 /// struct to implement the gRPC server functions
-#[derive(Debug, Default, Copy, Clone)]
-pub struct ServerImpl {}
+#[derive(Debug, Clone)]
+pub struct ServerImpl {
+    /// Caches of the aircraft states, none if they could not be created
+    #[cfg_attr(feature = "stub_server", allow(dead_code))]
+    caches: Option<StateCaches>,
+
+    /// Rules resolving the requested identifiers
+    #[cfg_attr(feature = "stub_server", allow(dead_code))]
+    identifiers: IdentifierRules,
+
+    /// Clock the age of the aircraft states is measured with
+    clock: SharedClock,
+}
+
+impl Default for ServerImpl {
+    fn default() -> Self {
+        ServerImpl {
+            caches: None,
+            identifiers: IdentifierRules::default(),
+            clock: SystemClock::shared(),
+        }
+    }
+}
+
+impl ServerImpl {
+    /// Server reading the aircraft states from the given caches
+    pub fn with_caches(caches: StateCaches) -> Self {
+        ServerImpl {
+            caches: Some(caches),
+            ..Default::default()
+        }
+    }
+
+    /// Resolve the requested identifiers with the given rules, so clients
+    ///  can keep using earlier identifier formats
+    pub fn with_identifiers(mut self, identifiers: IdentifierRules) -> Self {
+        self.identifiers = identifiers;
+        self
+    }
+}
+
+/// Status of an aircraft from its last ADS-B operational status, unknown
+///  for aircraft without one
+fn aircraft_status(status: Option<&OperationalStatus>) -> AircraftStatus {
+    match status {
+        Some(status) if status.surface => AircraftStatus::OnGround,
+        Some(_) => AircraftStatus::Airborne,
+        None => AircraftStatus::Unknown,
+    }
+}
+
+/// State of a tracked aircraft at `now`
+fn aircraft_state(
+    track: TrackSnapshot,
+    status: Option<OperationalStatus>,
+    now: DateTime<Utc>,
+) -> AircraftStateResponse {
+    let position = track.position.position;
+    AircraftStateResponse {
+        identifier: track.position.identifier,
+        position: Some(AircraftPosition {
+            latitude: position.latitude,
+            longitude: position.longitude,
+            altitude_meters: position.altitude_meters,
+        }),
+        velocity: track.velocity.map(|velocity| AircraftVelocity {
+            velocity_horizontal_ground_mps: velocity.velocity_horizontal_ground_mps,
+            velocity_vertical_mps: velocity.velocity_vertical_mps,
+            track_angle_degrees: velocity.track_angle_degrees,
+        }),
+        status: aircraft_status(status.as_ref()) as i32,
+        age_ms: (now - track.updated).num_milliseconds().max(0) as u64,
+    }
+}
 
 #[cfg(not(feature = "stub_server"))]
 #[tonic::async_trait]
@@ -33,8 +124,81 @@ impl RpcService for ServerImpl {
         let response = ReadyResponse { ready: true };
         Ok(Response::new(response))
     }
+
+    /// Returns the latest position, velocity and status of an aircraft,
+    ///  not found if it isn't tracked
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) need redis backend to test
+    async fn get_aircraft_state(
+        &self,
+        request: Request<AircraftStateRequest>,
+    ) -> Result<Response<AircraftStateResponse>, Status> {
+        grpc_info!("telemetry server.");
+        grpc_debug!("request: {:?}", request);
+        let identifier = self.identifiers.resolve(&request.into_inner().identifier);
+        if identifier.is_empty() {
+            return Err(Status::invalid_argument("no aircraft identifier."));
+        }
+
+        let Some(caches) = &self.caches else {
+            grpc_warn!("no aircraft state cache.");
+            return Err(Status::unavailable("aircraft states are unavailable."));
+        };
+
+        let now = self.clock.now();
+        let mut snapshot_pool = caches.snapshot.clone();
+        let track = crate::cache::snapshot::read_aircraft(&mut snapshot_pool, &identifier, now)
+            .await
+            .map_err(|e| {
+                grpc_error!("could not read the state of {identifier}: {e}");
+                Status::unavailable("aircraft states are unavailable.")
+            })?
+            .ok_or_else(|| Status::not_found(format!("aircraft {identifier} isn't tracked.")))?;
+
+        // aircraft other than ADS-B don't report their status
+        let mut adsb_pool = caches.adsb.clone();
+        let status = crate::rest::api::state::operational_status(&mut adsb_pool, &identifier)
+            .await
+            .unwrap_or_else(|e| {
+                grpc_warn!("could not read the status of {identifier}: {e}");
+                None
+            });
+
+        Ok(Response::new(aircraft_state(track, status, now)))
+    }
+}
+
+/// Why the gRPC server could not start, or stopped serving
+#[derive(Debug)]
+pub enum GrpcServerError {
+    /// The listening address is invalid
+    InvalidAddress(String),
+
+    /// Serving failed
+    Serve(tonic::transport::Error),
+}
+
+impl GrpcServerError {
+    /// Stable code of the error, for logs and statistics
+    pub fn code(&self) -> &'static str {
+        match self {
+            GrpcServerError::InvalidAddress(_) => "grpc_address",
+            GrpcServerError::Serve(_) => "grpc_serve",
+        }
+    }
+}
+
+impl Display for GrpcServerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GrpcServerError::InvalidAddress(e) => write!(f, "invalid gRPC address: {e}"),
+            GrpcServerError::Serve(e) => write!(f, "could not start gRPC server: {e}"),
+        }
+    }
 }
 
+impl std::error::Error for GrpcServerError {}
+
 /// Starts the grpc servers for this microservice using the provided configuration
 ///
 /// # Example:
@@ -47,38 +211,61 @@ impl RpcService for ServerImpl {
 ///     Ok(())
 /// }
 /// ```
-pub async fn grpc_server(config: Config, shutdown_rx: Option<tokio::sync::oneshot::Receiver<()>>) {
+pub async fn grpc_server(
+    config: Config,
+    shutdown_rx: Option<tokio::sync::oneshot::Receiver<()>>,
+) -> Result<(), GrpcServerError> {
     grpc_debug!("entry.");
 
     // Grpc Server
     let grpc_port = config.docker_port_grpc;
-    let full_grpc_addr: SocketAddr = match format!("[::]:{}", grpc_port).parse() {
-        Ok(addr) => addr,
-        Err(e) => {
-            grpc_error!("Failed to parse gRPC address: {}", e);
-            return;
+    let full_grpc_addr: SocketAddr = format!("[::]:{}", grpc_port).parse().map_err(|e| {
+        let e = GrpcServerError::InvalidAddress(format!("{e}"));
+        grpc_error!("[{}] {e}", e.code());
+        e
+    })?;
+
+    let imp = match (
+        TelemetryPool::new(config.clone(), "tlm:snapshot").await,
+        TelemetryPool::new(config.clone(), "tlm:adsb").await,
+    ) {
+        (Ok(snapshot), Ok(adsb)) => ServerImpl::with_caches(StateCaches { snapshot, adsb }),
+        _ => {
+            grpc_warn!("could not create the aircraft state caches, states are unavailable.");
+            ServerImpl::default()
         }
-    };
+    }
+    .with_identifiers(IdentifierRules::new(&config.identifier_rules));
 
-    let imp = ServerImpl::default();
+    // Not serving until the REST server or dispatcher initialized Redis and
+    //  RabbitMQ
     let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
     health_reporter
-        .set_serving::<RpcServiceServer<ServerImpl>>()
+        .set_not_serving::<RpcServiceServer<ServerImpl>>()
         .await;
+    tokio::spawn(async move {
+        crate::startup::ready().await;
+        grpc_info!("dependencies ready, serving.");
+        health_reporter
+            .set_serving::<RpcServiceServer<ServerImpl>>()
+            .await;
+    });
 
     //start server
     grpc_info!("Starting gRPC services on: {}.", full_grpc_addr);
-    match Server::builder()
+    Server::builder()
         .add_service(health_service)
         .add_service(RpcServiceServer::new(imp))
         .serve_with_shutdown(full_grpc_addr, shutdown_signal("grpc", shutdown_rx))
         .await
-    {
-        Ok(_) => grpc_info!("gRPC server running at: {}.", full_grpc_addr),
-        Err(e) => {
-            grpc_error!("could not start gRPC server: {}", e);
-        }
-    };
+        .map_err(|e| {
+            let e = GrpcServerError::Serve(e);
+            grpc_error!("[{}] {e}", e.code());
+            e
+        })?;
+
+    grpc_info!("gRPC server running at: {}.", full_grpc_addr);
+    Ok(())
 }
 
 #[cfg(feature = "stub_server")]
@@ -93,6 +280,31 @@ impl RpcService for ServerImpl {
         let response = ReadyResponse { ready: true };
         Ok(Response::new(response))
     }
+
+    async fn get_aircraft_state(
+        &self,
+        request: Request<AircraftStateRequest>,
+    ) -> Result<Response<AircraftStateResponse>, Status> {
+        grpc_warn!("(MOCK) telemetry server.");
+        grpc_debug!("(MOCK) request: {:?}", request);
+        let now = self.clock.now();
+        let track = TrackSnapshot {
+            position: svc_gis_client_grpc::prelude::types::AircraftPosition {
+                identifier: request.into_inner().identifier,
+                position: svc_gis_client_grpc::prelude::types::Position {
+                    latitude: 52.37,
+                    longitude: 4.9,
+                    altitude_meters: 1000.0,
+                },
+                timestamp_network: now,
+                timestamp_asset: None,
+            },
+            velocity: None,
+            updated: now,
+        };
+
+        Ok(Response::new(aircraft_state(track, None, now)))
+    }
 }
 
 #[cfg(test)]
@@ -108,6 +320,81 @@ mod tests {
         assert!(result.ready);
     }
 
+    #[cfg(not(feature = "stub_server"))]
+    #[tokio::test]
+    async fn test_grpc_server_aircraft_state() {
+        let request = |identifier: &str| {
+            Request::new(AircraftStateRequest {
+                identifier: identifier.to_string(),
+            })
+        };
+
+        let imp = ServerImpl::default();
+        let status = imp.get_aircraft_state(request("")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        let status = imp.get_aircraft_state(request("4840d6")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+
+        // nothing is cached by the test pools
+        let caches = StateCaches {
+            snapshot: TelemetryPool::new(Config::default(), "snapshot")
+                .await
+                .unwrap(),
+            adsb: TelemetryPool::new(Config::default(), "adsb").await.unwrap(),
+        };
+        let imp = ServerImpl::with_caches(caches);
+        let status = imp.get_aircraft_state(request("4840d6")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[test]
+    fn test_aircraft_state() {
+        use lib_common::time::Duration;
+        use svc_gis_client_grpc::prelude::types::Position;
+
+        let now: DateTime<Utc> = "2024-05-01T10:00:00Z".parse().unwrap();
+        let updated = now - Duration::try_milliseconds(1500).unwrap();
+        let track = TrackSnapshot {
+            position: svc_gis_client_grpc::prelude::types::AircraftPosition {
+                identifier: "4840d6".to_string(),
+                position: Position {
+                    latitude: 52.37,
+                    longitude: 4.9,
+                    altitude_meters: 1000.0,
+                },
+                timestamp_network: updated,
+                timestamp_asset: None,
+            },
+            velocity: None,
+            updated,
+        };
+
+        let state = aircraft_state(track.clone(), None, now);
+        assert_eq!(state.identifier, "4840d6");
+        assert_eq!(state.position.unwrap().altitude_meters, 1000.0);
+        assert_eq!(state.velocity, None);
+        assert_eq!(state.status(), AircraftStatus::Unknown);
+        assert_eq!(state.age_ms, 1500);
+
+        let mut status = OperationalStatus {
+            version: 2,
+            surface: true,
+            nacp: None,
+            horizontal_accuracy_meters: None,
+            vertical_accuracy_meters: None,
+            nic_supplement_a: None,
+            sil: None,
+            sil_per_sample: None,
+            nic_baro: None,
+            dimensions: None,
+        };
+        let state = aircraft_state(track.clone(), Some(status), now);
+        assert_eq!(state.status(), AircraftStatus::OnGround);
+        status.surface = false;
+        let state = aircraft_state(track, Some(status), now);
+        assert_eq!(state.status(), AircraftStatus::Airborne);
+    }
+
     #[tokio::test]
     async fn test_grpc_server_start_and_shutdown() {
         use tokio::time::{sleep, Duration};
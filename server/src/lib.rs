@@ -6,10 +6,19 @@ pub mod test_util;
 
 pub mod amqp;
 pub mod cache;
+pub mod cli;
+pub mod clock;
 pub mod config;
+pub mod degradation;
+pub mod dispatcher;
 pub mod grpc;
+pub mod logging;
 pub mod msg;
 pub mod rest;
+pub mod sink;
+pub mod startup;
+pub mod stats;
+pub mod tls;
 
 pub use crate::config::Config;
 pub use clap::Parser;
@@ -20,6 +29,10 @@ pub struct Cli {
     /// Target file to write the OpenAPI Spec
     #[arg(long)]
     pub openapi: Option<String>,
+
+    /// Task to run instead of the server
+    #[command(subcommand)]
+    pub command: Option<cli::Command>,
 }
 
 /// Tokio signal handler that will wait for a user to press CTRL+C.
@@ -0,0 +1,105 @@
+//! Fields of the request being handled, added to JSON log lines
+//!
+//! The fields are kept per task, so they follow a request across `.await`
+//!  points but not into tasks it spawns.
+
+use serde::Serialize;
+use std::cell::RefCell;
+use std::future::Future;
+
+tokio::task_local! {
+    static CONTEXT: RefCell<LogContext>;
+}
+
+/// Fields describing the request being handled
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LogContext {
+    /// Identifier of the REST request, or of the dispatched stream entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+
+    /// Identifier of the aircraft the telemetry is about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aircraft: Option<String>,
+
+    /// Protocol and message type of the packet, e.g. `netrid:location`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub packet_type: Option<&'static str>,
+}
+
+/// Runs a request with its own log fields
+pub async fn scope<F: Future>(request_id: String, f: F) -> F::Output {
+    let context = LogContext {
+        request_id: Some(request_id),
+        ..Default::default()
+    };
+
+    CONTEXT.scope(RefCell::new(context), f).await
+}
+
+/// Sets the aircraft of the request, ignored outside of [`scope`]
+pub fn set_aircraft(identifier: &str) {
+    let _ = CONTEXT.try_with(|context| {
+        if let Ok(mut context) = context.try_borrow_mut() {
+            context.aircraft = Some(identifier.to_string());
+        }
+    });
+}
+
+/// Sets the packet type of the request, ignored outside of [`scope`]
+pub fn set_packet_type(packet_type: &'static str) {
+    let _ = CONTEXT.try_with(|context| {
+        if let Ok(mut context) = context.try_borrow_mut() {
+            context.packet_type = Some(packet_type);
+        }
+    });
+}
+
+/// Fields of the request handled by the current task, if any
+pub fn current() -> Option<LogContext> {
+    CONTEXT
+        .try_with(|context| context.try_borrow().ok().map(|context| context.clone()))
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scope() {
+        assert!(current().is_none());
+        set_aircraft("ignored");
+
+        let context = scope("req-1".to_string(), async {
+            set_aircraft("abc123");
+            tokio::task::yield_now().await;
+            set_packet_type("adsb:position");
+            current()
+        })
+        .await;
+
+        assert_eq!(
+            context,
+            Some(LogContext {
+                request_id: Some("req-1".to_string()),
+                aircraft: Some("abc123".to_string()),
+                packet_type: Some("adsb:position"),
+            })
+        );
+        assert!(current().is_none());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let context = LogContext {
+            request_id: Some("req-1".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&context).unwrap(),
+            r#"{"request_id":"req-1"}"#
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Encoder writing each log line as a JSON object with the request fields
+
+use super::context::{self, LogContext};
+use lib_common::time::{SecondsFormat, Utc};
+use log::Record;
+use log4rs::config::{Deserialize, Deserializers};
+use log4rs::encode::{Encode, Write};
+use serde::Serialize;
+
+/// Kind of the encoder in log4rs configurations
+pub const ENCODER_KIND: &str = "json_fields";
+
+/// A log line
+#[derive(Debug, Serialize)]
+struct Line<'a> {
+    time: String,
+    level: &'a str,
+    target: &'a str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    module_path: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<u32>,
+    #[serde(flatten)]
+    context: Option<LogContext>,
+}
+
+impl<'a> Line<'a> {
+    fn new(record: &'a Record, context: Option<LogContext>) -> Self {
+        Line {
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true),
+            level: record.level().as_str(),
+            target: record.target(),
+            message: record.args().to_string(),
+            module_path: record.module_path(),
+            file: record.file(),
+            line: record.line(),
+            context,
+        }
+    }
+}
+
+/// Writes a JSON object per line, with the fields of the request
+///  handled by the logging task (see [`context`])
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonFieldsEncoder;
+
+impl Encode for JsonFieldsEncoder {
+    fn encode(&self, w: &mut dyn Write, record: &Record) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut *w, &Line::new(record, context::current()))?;
+        w.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// Configuration of the [`JsonFieldsEncoder`], which has no options
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JsonFieldsEncoderConfig {}
+
+/// Creates [`JsonFieldsEncoder`]s from log4rs configurations
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonFieldsEncoderDeserializer;
+
+impl Deserialize for JsonFieldsEncoderDeserializer {
+    type Trait = dyn Encode;
+    type Config = JsonFieldsEncoderConfig;
+
+    fn deserialize(
+        &self,
+        _: JsonFieldsEncoderConfig,
+        _: &Deserializers,
+    ) -> anyhow::Result<Box<dyn Encode>> {
+        Ok(Box::new(JsonFieldsEncoder))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encoded line of a record, in a single statement for the arguments to live
+    fn encoded(context: Option<LogContext>) -> serde_json::Value {
+        serde_json::to_value(Line::new(
+            &Record::builder()
+                .args(format_args!("received packet."))
+                .level(log::Level::Info)
+                .target("app::rest")
+                .line(Some(12))
+                .build(),
+            context,
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_line() {
+        let context = LogContext {
+            request_id: Some("req-1".to_string()),
+            aircraft: Some("abc123".to_string()),
+            packet_type: Some("adsb:velocity"),
+        };
+
+        let line = encoded(Some(context));
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["target"], "app::rest");
+        assert_eq!(line["message"], "received packet.");
+        assert_eq!(line["line"], 12);
+        assert_eq!(line["request_id"], "req-1");
+        assert_eq!(line["aircraft"], "abc123");
+        assert_eq!(line["packet_type"], "adsb:velocity");
+        assert!(line.get("file").is_none());
+
+        let line = encoded(None);
+        assert!(line.get("request_id").is_none());
+        assert!(line["time"].is_string());
+    }
+}
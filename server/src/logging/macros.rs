@@ -0,0 +1,4 @@
+//! log macro's for logging configuration logging
+
+use lib_common::log_macros;
+log_macros!("logging", "app::logging");
@@ -0,0 +1,387 @@
+//! Runtime changes of the log configuration
+//!
+//! The log configuration file (`LOG_CONFIG`) is re-read on `SIGHUP`, and
+//!  per-target levels can be overridden without a restart (`PUT
+//!  /admin/log_level`). Overrides are applied on top of the file and are
+//!  kept until reset or until the next `SIGHUP`.
+//!
+//! With `LOG_FORMAT=json`, the encoders of the file are replaced by the
+//!  [`json`] encoder, adding the fields of the request being handled.
+
+#[macro_use]
+pub mod macros;
+pub mod context;
+pub mod json;
+
+use crate::config::LogFormat;
+use json::JsonFieldsEncoderDeserializer;
+
+use hyper::StatusCode;
+use log::LevelFilter;
+use log4rs::config::{Config, Deserializers, Logger, RawConfig};
+use serde_yaml::{Mapping, Value};
+use snafu::prelude::Snafu;
+use std::collections::BTreeMap;
+use tokio::sync::Mutex;
+
+/// Target name adjusting the level of the root logger
+pub const ROOT_TARGET: &str = "root";
+
+/// Changes applied on top of the configuration file
+#[derive(Debug, Clone)]
+struct Settings {
+    /// Format of the log lines
+    format: LogFormat,
+
+    /// Levels set at runtime, per target
+    overrides: BTreeMap<String, LevelFilter>,
+}
+
+/// Settings of the logger of this process
+static SETTINGS: Mutex<Settings> = Mutex::const_new(Settings {
+    format: LogFormat::Text,
+    overrides: BTreeMap::new(),
+});
+
+/// Error while changing the log configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Snafu)]
+pub enum LoggingError {
+    /// The configuration file could not be read
+    #[snafu(display("Could not read the log configuration file."))]
+    Read,
+
+    /// The configuration file is not a valid log4rs configuration
+    #[snafu(display("Invalid log configuration."))]
+    Invalid,
+
+    /// The logger of this process can't be reconfigured
+    #[snafu(display("Logger not initialized."))]
+    Uninitialized,
+}
+
+impl From<LoggingError> for StatusCode {
+    fn from(e: LoggingError) -> Self {
+        match e {
+            LoggingError::Uninitialized => StatusCode::SERVICE_UNAVAILABLE,
+            LoggingError::Read | LoggingError::Invalid => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Replaces the encoder of every appender by the [`json`] encoder
+fn use_json_encoders(config: &mut Value) {
+    let Some(appenders) = config.get_mut("appenders").and_then(Value::as_mapping_mut) else {
+        return;
+    };
+
+    let mut encoder = Mapping::new();
+    encoder.insert("kind".into(), json::ENCODER_KIND.into());
+    for (_, appender) in appenders.iter_mut() {
+        if let Some(appender) = appender.as_mapping_mut() {
+            appender.insert("encoder".into(), encoder.clone().into());
+        }
+    }
+}
+
+/// Parses a log4rs configuration
+///
+/// YAML being a superset of JSON, both formats are accepted.
+fn parse(source: &str, format: LogFormat) -> Result<RawConfig, LoggingError> {
+    let mut config: Value = serde_yaml::from_str(source).map_err(|e| {
+        logging_error!("could not parse log configuration: {e}");
+        LoggingError::Invalid
+    })?;
+
+    if format == LogFormat::Json {
+        use_json_encoders(&mut config);
+    }
+
+    serde_yaml::from_value(config).map_err(|e| {
+        logging_error!("invalid log configuration: {e}");
+        LoggingError::Invalid
+    })
+}
+
+/// Deserializers of log4rs components, including the [`json`] encoder
+fn deserializers() -> Deserializers {
+    let mut deserializers = Deserializers::default();
+    deserializers.insert(json::ENCODER_KIND, JsonFieldsEncoderDeserializer);
+    deserializers
+}
+
+/// Builds the logger configuration with the levels overridden at runtime
+fn build(
+    raw: &RawConfig,
+    overrides: &BTreeMap<String, LevelFilter>,
+) -> Result<Config, LoggingError> {
+    let (appenders, mut errors) = raw.appenders_lossy(&deserializers());
+    if !errors.is_empty() {
+        logging_warn!("some appenders could not be created.");
+        errors.handle();
+    }
+
+    let mut root = raw.root();
+    let mut loggers = raw.loggers();
+    for (target, level) in overrides {
+        if target == ROOT_TARGET {
+            root.set_level(*level);
+            continue;
+        }
+
+        // Loggers can't be modified, configured ones are rebuilt
+        let logger = match loggers.iter().position(|logger| logger.name() == target) {
+            Some(index) => {
+                let configured = loggers.swap_remove(index);
+                Logger::builder()
+                    .appenders(configured.appenders().to_vec())
+                    .additive(configured.additive())
+            }
+            None => Logger::builder(),
+        };
+
+        loggers.push(logger.build(target.clone(), *level));
+    }
+
+    Config::builder()
+        .appenders(appenders)
+        .loggers(loggers)
+        .build(root)
+        .map_err(|e| {
+            logging_error!("invalid log configuration: {e}");
+            LoggingError::Invalid
+        })
+}
+
+/// Reads the configuration file and applies it with the given settings
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) needs the logger of the process
+async fn apply(path: &str, settings: &Settings) -> Result<(), LoggingError> {
+    let source = tokio::fs::read_to_string(path).await.map_err(|e| {
+        logging_error!("could not read log configuration file {path}: {e}");
+        LoggingError::Read
+    })?;
+
+    let raw = parse(&source, settings.format)?;
+    let config = build(&raw, &settings.overrides)?;
+    let Some(handle) = lib_common::logger::get_log_handle().await else {
+        logging_error!("no log handle available.");
+        return Err(LoggingError::Uninitialized);
+    };
+
+    handle.set_config(config);
+    Ok(())
+}
+
+/// Overrides the level of a target, or resets it to the configured level
+///
+/// The override is only kept if the new configuration could be applied.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) needs the logger of the process
+pub async fn set_level(
+    path: &str,
+    target: &str,
+    level: Option<LevelFilter>,
+) -> Result<BTreeMap<String, LevelFilter>, LoggingError> {
+    let mut settings = SETTINGS.lock().await;
+    let mut updated = settings.clone();
+    match level {
+        Some(level) => updated.overrides.insert(target.to_string(), level),
+        None => updated.overrides.remove(target),
+    };
+
+    apply(path, &updated).await?;
+    match level {
+        Some(level) => logging_info!("log level of {target} set to {level}."),
+        None => logging_info!("log level of {target} reset."),
+    }
+
+    *settings = updated;
+    Ok(settings.overrides.clone())
+}
+
+/// Sets the format of the log lines, from startup on
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) needs the logger of the process
+pub async fn set_format(path: &str, format: LogFormat) -> Result<(), LoggingError> {
+    let mut settings = SETTINGS.lock().await;
+    let mut updated = settings.clone();
+    updated.format = format;
+
+    apply(path, &updated).await?;
+    logging_info!("log format set to {format:?}.");
+    *settings = updated;
+    Ok(())
+}
+
+/// Re-reads the configuration file, dropping the runtime overrides
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) needs the logger of the process
+pub async fn reload(path: &str) -> Result<(), LoggingError> {
+    let mut settings = SETTINGS.lock().await;
+    let updated = Settings {
+        format: settings.format,
+        overrides: BTreeMap::new(),
+    };
+
+    apply(path, &updated).await?;
+    *settings = updated;
+    logging_info!("reloaded log configuration from {path}.");
+    Ok(())
+}
+
+/// Reloads the log configuration each time the process receives `SIGHUP`
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) needs signals sent to the process
+pub async fn reload_on_hangup(path: String) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            logging_error!("could not listen for SIGHUP: {e}");
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        logging_info!("received SIGHUP.");
+        let _ = reload(&path).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+appenders:
+  stdout:
+    kind: console
+root:
+  level: info
+  appenders:
+    - stdout
+loggers:
+  app::rest:
+    level: warn
+    appenders:
+      - stdout
+    additive: false
+"#;
+
+    #[test]
+    fn test_build() {
+        let raw = parse(CONFIG, LogFormat::Text).unwrap();
+        let config = build(&raw, &BTreeMap::new()).unwrap();
+        assert_eq!(config.root().level(), LevelFilter::Info);
+        assert_eq!(config.loggers().len(), 1);
+        assert_eq!(config.loggers()[0].level(), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn test_build_overrides() {
+        let raw = parse(CONFIG, LogFormat::Text).unwrap();
+        let overrides = BTreeMap::from([
+            (ROOT_TARGET.to_string(), LevelFilter::Warn),
+            ("app::rest".to_string(), LevelFilter::Debug),
+            ("backend::sink".to_string(), LevelFilter::Trace),
+        ]);
+
+        let config = build(&raw, &overrides).unwrap();
+        assert_eq!(config.root().level(), LevelFilter::Warn);
+        assert_eq!(config.root().appenders(), ["stdout"]);
+
+        let rest = config
+            .loggers()
+            .iter()
+            .find(|logger| logger.name() == "app::rest")
+            .unwrap();
+        assert_eq!(rest.level(), LevelFilter::Debug);
+        assert_eq!(rest.appenders(), ["stdout"]);
+        assert!(!rest.additive());
+
+        let sink = config
+            .loggers()
+            .iter()
+            .find(|logger| logger.name() == "backend::sink")
+            .unwrap();
+        assert_eq!(sink.level(), LevelFilter::Trace);
+        assert!(sink.appenders().is_empty());
+        assert!(sink.additive());
+    }
+
+    #[test]
+    fn test_build_unknown_appender() {
+        let raw = parse(
+            r#"
+root:
+  level: info
+  appenders:
+    - missing
+"#,
+            LogFormat::Text,
+        )
+        .unwrap();
+        assert_eq!(
+            build(&raw, &BTreeMap::new()).unwrap_err(),
+            LoggingError::Invalid
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(
+            parse("root: [", LogFormat::Text).unwrap_err(),
+            LoggingError::Invalid
+        );
+    }
+
+    #[test]
+    fn test_use_json_encoders() {
+        let mut config: Value = serde_yaml::from_str(
+            r#"
+appenders:
+  stdout:
+    kind: console
+    encoder:
+      pattern: "{m}{n}"
+  requests:
+    kind: file
+    path: "logs/requests.log"
+root:
+  level: info
+"#,
+        )
+        .unwrap();
+
+        use_json_encoders(&mut config);
+        for name in ["stdout", "requests"] {
+            let appender = &config["appenders"][name];
+            assert_eq!(appender["encoder"]["kind"], json::ENCODER_KIND);
+            assert!(appender["encoder"].get("pattern").is_none());
+        }
+        assert_eq!(config["appenders"]["requests"]["path"], "logs/requests.log");
+
+        let raw = serde_yaml::from_value(config).unwrap();
+        assert!(build(&raw, &BTreeMap::new()).is_ok());
+    }
+
+    #[test]
+    fn test_parse_json_format() {
+        let raw = parse(CONFIG, LogFormat::Json).unwrap();
+        let config = build(&raw, &BTreeMap::new()).unwrap();
+        assert_eq!(config.appenders().len(), 1);
+    }
+
+    #[test]
+    fn test_error_status() {
+        assert_eq!(
+            StatusCode::from(LoggingError::Uninitialized),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            StatusCode::from(LoggingError::Read),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
@@ -23,18 +23,40 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .or_else(|e| Ok::<(), String>(log::error!("(main) {}", e)))?;
     info!("(main) Server startup.");
 
+    if config.log_format == config::LogFormat::Json {
+        let _ = logging::set_format(&config.log_config, config.log_format).await;
+    }
+
+    // Operators can reload the log configuration with SIGHUP
+    tokio::spawn(logging::reload_on_hangup(config.log_config.clone()));
+
     // Allow option to only generate the spec file to a given location
     // use `make rust-openapi` to generate the OpenAPI specification
     let args = Cli::parse();
     if let Some(target) = args.openapi {
-        return generate_openapi_spec::<ApiDoc>(&target).map_err(|e| e.into());
+        return generate_openapi_spec::<ApiDoc>(&target, &config.rest_base_path)
+            .map_err(|e| e.into());
     }
 
-    // REST Server
-    tokio::spawn(rest_server(config.clone(), None));
+    // Operational tasks, see `svc-telemetry help`
+    if let Some(command) = args.command {
+        return cli::run(command, config).await.map_err(|e| e.into());
+    }
+
+    // REST Server, or the dispatcher pushing telemetry queued by REST servers
+    match config.mode {
+        config::ServerMode::Dispatcher => {
+            info!("(main) running in dispatcher mode.");
+            tokio::spawn(dispatcher::dispatcher(config.clone(), None));
+        }
+        mode => {
+            info!("(main) running in {:?} mode.", mode);
+            tokio::spawn(rest_server(config.clone(), None));
+        }
+    }
 
     // GRPC Server
-    tokio::spawn(grpc_server(config, None)).await?;
+    tokio::spawn(grpc_server(config, None)).await??;
 
     info!("(main) server shutdown.");
 
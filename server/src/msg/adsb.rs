@@ -1,10 +1,85 @@
 /// Functions for parsing ADS-B packets
+use adsb_deku::adsb::TypeCoding;
 use adsb_deku::Sign;
+use lib_common::time::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display, Formatter};
+use svc_gis_client_grpc::prelude::types::{AircraftPosition, AircraftVelocity};
 
 /// Expected size of ADSB packets
 pub const ADSB_SIZE_BYTES: usize = 14;
 
+/// Downlink format of Comm-B identity replies
+pub const DF_COMM_B_IDENTITY_REPLY: u8 = 21;
+
+/// Downlink formats of ADS-B extended squitters
+const DF_EXTENDED_SQUITTERS: [u8; 2] = [17, 18];
+
+/// Type code of aircraft status messages
+const TC_AIRCRAFT_STATUS: u8 = 28;
+
+/// Type code of target state and status messages
+const TC_TARGET_STATE: u8 = 29;
+
+/// Subtype of target state and status messages from version 2 transponders
+const ST_TARGET_STATE_V2: u8 = 1;
+
+/// Type code of aircraft operational status messages
+const TC_OPERATIONAL_STATUS: u8 = 31;
+
+/// Subtype of operational status messages sent by surface aircraft
+const ST_OPERATIONAL_STATUS_SURFACE: u8 = 1;
+
+/// Horizontal 95% containment bounds in meters, indexed by NACp
+///  (NACp 0 is unknown)
+const NACP_BOUNDS_METERS: [f32; 12] = [
+    f32::NAN,
+    18520.,
+    7408.,
+    3704.,
+    1852.,
+    926.,
+    555.6,
+    185.2,
+    92.6,
+    30.,
+    10.,
+    3.,
+];
+
+/// Vertical 95% bounds in meters, indexed by GVA (GVA 0 is unknown)
+const GVA_BOUNDS_METERS: [f32; 3] = [f32::NAN, 150., 45.];
+
+/// Upper bounds of the length and width of an aircraft in meters,
+///  indexed by its length/width code (code 0 is no data)
+const DIMENSIONS_METERS: [(f32, f32); 16] = [
+    (0., 0.),
+    (15., 23.),
+    (25., 28.5),
+    (25., 34.),
+    (35., 33.),
+    (35., 38.),
+    (45., 39.5),
+    (45., 45.),
+    (55., 45.),
+    (55., 52.),
+    (65., 59.5),
+    (65., 67.),
+    (75., 72.5),
+    (75., 80.),
+    (85., 80.),
+    (f32::INFINITY, 90.),
+];
+
+/// Subtype of aircraft status messages holding the emergency status and squawk
+const ST_EMERGENCY_STATUS: u8 = 1;
+
+/// Surveillance status of airborne positions during an emergency
+const SS_PERMANENT_ALERT: u8 = 1;
+
+/// Generator polynomial of the Mode S parity
+const MODES_CRC_GENERATOR: u32 = 0x1FFF409;
+
 /// Possible errors decoding ADSB packets
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum DecodeError {
@@ -16,6 +91,9 @@ pub enum DecodeError {
 
     /// Invalid Aircraft Subtype (subtype is not 1, 2, 3, 4)
     InvalidSubtype,
+
+    /// The decoded latitude is beyond the poles
+    InvalidLatitude,
 }
 
 /// Possible errors encoding ADSB packets
@@ -31,6 +109,7 @@ impl Display for DecodeError {
             DecodeError::CrossedLatitudeZones => write!(f, "Crossed latitude zones"),
             DecodeError::UnsupportedSubtype => write!(f, "Unsupported subtype"),
             DecodeError::InvalidSubtype => write!(f, "Invalid subtype"),
+            DecodeError::InvalidLatitude => write!(f, "Invalid latitude"),
         }
     }
 }
@@ -86,32 +165,92 @@ fn modulus(x: f64, y: f64) -> f64 {
     x - y * ((x / y).floor())
 }
 
+/// Transition latitudes of the number of longitude zones, in degrees
+///
+/// Latitudes up to the first have 59 zones, each following one removes a
+///  zone. Latitudes beyond 87 degrees have a single zone.
+const NL_TRANSITION_LATITUDES: [f64; 58] = [
+    10.47047130,
+    14.82817437,
+    18.18626357,
+    21.02939493,
+    23.54504487,
+    25.82924707,
+    27.93898710,
+    29.91135686,
+    31.77209708,
+    33.53993436,
+    35.22899598,
+    36.85025108,
+    38.41241892,
+    39.92256684,
+    41.38651832,
+    42.80914012,
+    44.19454951,
+    45.54626723,
+    46.86733252,
+    48.16039128,
+    49.42776439,
+    50.67150166,
+    51.89342469,
+    53.09516153,
+    54.27817472,
+    55.44378444,
+    56.59318756,
+    57.72747354,
+    58.84763776,
+    59.95459277,
+    61.04917774,
+    62.13216659,
+    63.20427479,
+    64.26616523,
+    65.31845310,
+    66.36171008,
+    67.39646774,
+    68.42322022,
+    69.44242631,
+    70.45451075,
+    71.45986473,
+    72.45884545,
+    73.45177442,
+    74.43893416,
+    75.42056257,
+    76.39684391,
+    77.36789461,
+    78.33374083,
+    79.29428225,
+    80.24923213,
+    81.19801349,
+    82.13956981,
+    83.07199445,
+    83.99173563,
+    84.89166191,
+    85.75541621,
+    86.53536998,
+    87.00000000,
+];
+
 ///
 /// Finds the number of longitude zones, given a latitude angle
 ///
 /// Assuming number of zones (NZ) is 15 for Mode-S CPR encoding.
 fn nl(lat: f64) -> f64 {
-    use std::f64::consts::PI;
-    const NZ: f64 = 30.; // NZ * 2
+    let lat = lat.abs();
+    let first_above = NL_TRANSITION_LATITUDES.partition_point(|transition| *transition < lat);
+    (1 + NL_TRANSITION_LATITUDES.len() - first_above) as f64
+}
 
-    //
-    // Numerator
-    let numerator: f64 = 2. * PI;
+/// Maximum time between the even and odd messages of an airborne CPR
+///  pair, beyond which the aircraft may have left the decoded zone
+pub const CPR_MAX_PAIR_AGE_MS: u64 = 3500;
 
-    //
-    // Denominator
-    let a = 1. - (PI / NZ).cos();
-    let b = (1. + (2. * (PI * lat / 180.)).cos()) / 2.;
-    let x = a / b;
-    let denominator = (1. - x)
-        // acos is undefined for values outside of [-1, 1]
-        .clamp(-1., 1.)
-        .acos();
+/// Maximum time between the even and odd messages of a surface CPR pair
+pub const CPR_SURFACE_MAX_PAIR_AGE_MS: u64 = 25000;
 
-    // Result
-    let result = numerator / denominator;
-    // println!("(nl) result: {} (num: {}, denom: {})", result, numerator, denominator);
-    result.floor()
+/// If CPR messages received at the given times (milliseconds) are close
+///  enough to be decoded together
+pub fn is_cpr_pair(received_ms: i64, other_received_ms: i64, max_age_ms: u64) -> bool {
+    received_ms.abs_diff(other_received_ms) <= max_age_ms
 }
 
 /// Decodes the CPR format
@@ -143,6 +282,11 @@ pub fn decode_cpr(
         lat_odd -= 360.;
     }
 
+    // only possible with corrupted CPR values
+    if lat_even.abs() > 90. || lat_odd.abs() > 90. {
+        return Err(DecodeError::InvalidLatitude);
+    }
+
     let latitude: f64 = lat_even; // We trigger on receiving the odd packet
     let nl_le: f64 = nl(lat_even);
     let nl_lo: f64 = nl(lat_odd);
@@ -166,6 +310,75 @@ pub fn decode_cpr(
     Ok((latitude, longitude))
 }
 
+/// Of the candidates, the closest to the reference in degrees, accounting
+///  for the wrap around of longitudes
+fn closest(candidates: impl Iterator<Item = f64>, reference: f64) -> f64 {
+    let distance = |value: f64| {
+        let difference = modulus(value - reference, 360.);
+        difference.min(360. - difference)
+    };
+
+    candidates
+        .min_by(|a, b| distance(*a).total_cmp(&distance(*b)))
+        .unwrap_or(reference)
+}
+
+/// Decodes the CPR format of surface positions
+/// <https://mode-s.org/decode/content/ads-b/4-surface-position.html>
+///
+/// Surface positions are encoded in 90 degree zones, each pair of
+///  messages matches four positions. The one closest to the reference
+///  location `(latitude, longitude)` is returned, which must be within
+///  45 NM of the aircraft: e.g. the receiver location or the last known
+///  position of the aircraft.
+pub fn decode_cpr_surface(
+    lat_cpr_even: u32,
+    lon_cpr_even: u32,
+    lat_cpr_odd: u32,
+    lon_cpr_odd: u32,
+    reference: (f64, f64),
+) -> Result<(f64, f64), DecodeError> {
+    let (reference_latitude, reference_longitude) = reference;
+    let lat_cpr_even: f64 = lat_cpr_even as f64 / 131072.;
+    let lon_cpr_even: f64 = lon_cpr_even as f64 / 131072.;
+    let lat_cpr_odd: f64 = lat_cpr_odd as f64 / 131072.;
+    let lon_cpr_odd: f64 = lon_cpr_odd as f64 / 131072.;
+    let lat_index: f64 = (59. * lat_cpr_even - 60. * lat_cpr_odd + 0.5).floor();
+    let dlat_even = 1.5; // 90. / 60.;
+    let dlat_odd = 90. / 59.;
+
+    //
+    // Compute Latitude, north or south of the equator
+    let lat_even: f64 = dlat_even * (lat_cpr_even + modulus(lat_index, 60.));
+    let lat_odd: f64 = dlat_odd * (lat_cpr_odd + modulus(lat_index, 59.));
+    let lat_even = closest([lat_even, lat_even - 90.].into_iter(), reference_latitude);
+    let lat_odd = closest([lat_odd, lat_odd - 90.].into_iter(), reference_latitude);
+
+    // only possible with corrupted CPR values
+    if lat_even.abs() > 90. || lat_odd.abs() > 90. {
+        return Err(DecodeError::InvalidLatitude);
+    }
+
+    let latitude: f64 = lat_even; // We trigger on receiving the odd packet
+    let nl_le: f64 = nl(lat_even);
+    if nl_le != nl(lat_odd) {
+        return Err(DecodeError::CrossedLatitudeZones);
+    }
+
+    //
+    // Compute Longitude, in one of the four quadrants
+    let ni = if nl_le < 1. { 1. } else { nl_le };
+    let dlon: f64 = 90. / ni;
+    let m: f64 = (lon_cpr_even * (nl_le - 1.) - lon_cpr_odd * nl_le + 0.5).floor();
+    let longitude: f64 = dlon * (modulus(m, ni) + lon_cpr_even);
+    let candidates = (0..4).map(|quadrant| {
+        let longitude = longitude + 90. * quadrant as f64;
+        modulus(longitude + 180., 360.) - 180.
+    });
+
+    Ok((latitude, closest(candidates, reference_longitude)))
+}
+
 /// Encodes latitude and longitude in CPR format
 /// <https://mode-s.org/decode/content/ads-b/3-airborne-position.html#cpr-zones>
 pub fn encode_cpr(cpr_flag: u8, longitude: f64, latitude: f64) -> Result<(u32, u32), EncodeError> {
@@ -179,7 +392,8 @@ pub fn encode_cpr(cpr_flag: u8, longitude: f64, latitude: f64) -> Result<(u32, u
     let dlat = 360. / (60. - i);
     let yz = (SCALAR * modulus(latitude, dlat) / dlat + 0.5).floor();
     let cpr_latitude = modulus(yz, SCALAR);
-    let rlat = dlat * ((latitude / dlat).floor() + cpr_latitude / SCALAR);
+    // yz before wrapping, a latitude rounded up to the next zone is in it
+    let rlat = dlat * ((latitude / dlat).floor() + yz / SCALAR);
     let dlon = 360. / 1.0_f64.max(nl(rlat) - i);
     let xz = (SCALAR * modulus(longitude, dlon) / dlon + 0.5).floor();
     let cpr_longitude = modulus(xz, SCALAR);
@@ -234,7 +448,7 @@ pub fn decode_speed_direction(
         _ => return Err(DecodeError::InvalidSubtype),
     };
 
-    let speed_knots = ((vx.pow(2) + vy.pow(2)) as f32).sqrt();
+    let speed_knots = (vx as f32).hypot(vy as f32);
     let speed_mps = speed_knots * 0.514444;
     let mut direction = (vx as f32).atan2(vy as f32) * DIRECTION_COEFFICIENT;
 
@@ -260,9 +474,516 @@ pub fn decode_vertical_speed(vrate_sign: Sign, vrate_value: u16) -> Result<f32,
     Ok(speed_mps)
 }
 
+/// ADS-B emitter category, from the type code and category of
+///  aircraft identification messages
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EmitterCategory {
+    /// No category information (category 0)
+    NoInformation,
+
+    /// Reserved combination of type code and category
+    Reserved,
+
+    /// Surface emergency vehicle (C1)
+    SurfaceEmergencyVehicle,
+
+    /// Surface service vehicle (C3)
+    SurfaceServiceVehicle,
+
+    /// Ground obstruction (C4 - C7)
+    GroundObstruction,
+
+    /// Glider or sailplane (B1)
+    Glider,
+
+    /// Lighter-than-air (B2)
+    LighterThanAir,
+
+    /// Parachutist or skydiver (B3)
+    Parachutist,
+
+    /// Ultralight, hang-glider or paraglider (B4)
+    Ultralight,
+
+    /// Unmanned aerial vehicle (B6)
+    Uav,
+
+    /// Space or trans-atmospheric vehicle (B7)
+    SpaceVehicle,
+
+    /// Light, less than 7000 kg (A1)
+    Light,
+
+    /// Medium 1, between 7000 and 34000 kg (A2)
+    Medium1,
+
+    /// Medium 2, between 34000 and 136000 kg (A3)
+    Medium2,
+
+    /// High vortex aircraft (A4)
+    HighVortex,
+
+    /// Heavy, more than 136000 kg (A5)
+    Heavy,
+
+    /// High performance (more than 5 g) and high speed (more than 400 kt) (A6)
+    HighPerformance,
+
+    /// Rotorcraft (A7)
+    Rotorcraft,
+}
+
+impl EmitterCategory {
+    /// Emitter category of a type code and category
+    pub fn new(type_coding: TypeCoding, category: u8) -> Self {
+        match (type_coding, category) {
+            (TypeCoding::D, _) => EmitterCategory::Reserved,
+            (_, 0) => EmitterCategory::NoInformation,
+            (TypeCoding::C, 1) => EmitterCategory::SurfaceEmergencyVehicle,
+            (TypeCoding::C, 3) => EmitterCategory::SurfaceServiceVehicle,
+            (TypeCoding::C, 4..=7) => EmitterCategory::GroundObstruction,
+            (TypeCoding::B, 1) => EmitterCategory::Glider,
+            (TypeCoding::B, 2) => EmitterCategory::LighterThanAir,
+            (TypeCoding::B, 3) => EmitterCategory::Parachutist,
+            (TypeCoding::B, 4) => EmitterCategory::Ultralight,
+            (TypeCoding::B, 6) => EmitterCategory::Uav,
+            (TypeCoding::B, 7) => EmitterCategory::SpaceVehicle,
+            (TypeCoding::A, 1) => EmitterCategory::Light,
+            (TypeCoding::A, 2) => EmitterCategory::Medium1,
+            (TypeCoding::A, 3) => EmitterCategory::Medium2,
+            (TypeCoding::A, 4) => EmitterCategory::HighVortex,
+            (TypeCoding::A, 5) => EmitterCategory::Heavy,
+            (TypeCoding::A, 6) => EmitterCategory::HighPerformance,
+            (TypeCoding::A, 7) => EmitterCategory::Rotorcraft,
+            _ => EmitterCategory::Reserved,
+        }
+    }
+}
+
+/// Raw emitter category as usually written, e.g. `A3`
+pub fn emitter_category_code(type_coding: TypeCoding, category: u8) -> String {
+    let set = match type_coding {
+        TypeCoding::A => 'A',
+        TypeCoding::B => 'B',
+        TypeCoding::C => 'C',
+        TypeCoding::D => 'D',
+    };
+
+    format!("{set}{category}")
+}
+
+/// Mode A identity code (squawk), four octal digits
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Squawk(pub u16);
+
+impl Display for Squawk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04o}", self.0)
+    }
+}
+
+/// Emergency declared by an aircraft
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Emergency {
+    /// Unlawful interference (7500)
+    Hijack,
+
+    /// Radio failure (7600)
+    RadioFailure,
+
+    /// General emergency (7700)
+    General,
+
+    /// Emergency signalled by the surveillance status, squawk unknown
+    Unspecified,
+}
+
+impl Squawk {
+    /// Emergency signalled by the squawk, if any
+    pub fn emergency(&self) -> Option<Emergency> {
+        match self.0 {
+            0o7500 => Some(Emergency::Hijack),
+            0o7600 => Some(Emergency::RadioFailure),
+            0o7700 => Some(Emergency::General),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes a 13 bit identity code field
+///  (C1 A1 C2 A2 C4 A4 X B1 D1 B2 D2 B4 D4)
+pub fn decode_identity_code(code: u16) -> Squawk {
+    let bit = |n: u16| (code >> n) & 1;
+    let a = bit(7) << 2 | bit(9) << 1 | bit(11);
+    let b = bit(1) << 2 | bit(3) << 1 | bit(5);
+    let c = bit(8) << 2 | bit(10) << 1 | bit(12);
+    let d = bit(0) << 2 | bit(2) << 1 | bit(4);
+
+    Squawk(a << 9 | b << 6 | c << 3 | d)
+}
+
+/// Downlink format of a Mode S packet
+pub fn get_downlink_format(bytes: &[u8]) -> Option<u8> {
+    bytes.first().map(|byte| byte >> 3)
+}
+
+/// Mode S parity (CRC-24) of the given bytes
+pub fn modes_crc(bytes: &[u8]) -> u32 {
+    let mut crc: u32 = 0;
+    for byte in bytes {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= MODES_CRC_GENERATOR;
+            }
+        }
+    }
+
+    crc & 0xFFFFFF
+}
+
+/// ICAO address of a Comm-B identity reply (DF21), whose parity is
+///  overlaid with the address
+///
+/// Any frame yields an address: corrupted replies yield the wrong one.
+pub fn get_reply_icao_address(bytes: &[u8]) -> Option<u32> {
+    if get_downlink_format(bytes) != Some(DF_COMM_B_IDENTITY_REPLY)
+        || bytes.len() != ADSB_SIZE_BYTES
+    {
+        return None;
+    }
+
+    let (data, parity) = bytes.split_at(ADSB_SIZE_BYTES - 3);
+    let parity = u32::from_be_bytes([0, parity[0], parity[1], parity[2]]);
+    Some(modes_crc(data) ^ parity)
+}
+
+/// Squawk of a Comm-B identity reply (DF21) or of an ADS-B emergency
+///  status message (type code 28, subtype 1)
+pub fn get_squawk(bytes: &[u8]) -> Option<Squawk> {
+    let df = get_downlink_format(bytes)?;
+    let code = match (df, bytes.len()) {
+        (DF_COMM_B_IDENTITY_REPLY, ADSB_SIZE_BYTES) => (bytes[2], bytes[3]),
+        (df, ADSB_SIZE_BYTES) if DF_EXTENDED_SQUITTERS.contains(&df) => {
+            let tc = bytes[4] >> 3;
+            let st = bytes[4] & 0x7;
+            if tc != TC_AIRCRAFT_STATUS || st != ST_EMERGENCY_STATUS {
+                return None;
+            }
+
+            (bytes[5], bytes[6])
+        }
+        _ => return None,
+    };
+
+    let code = ((code.0 & 0x1F) as u16) << 8 | code.1 as u16;
+    Some(decode_identity_code(code))
+}
+
+/// If an ADS-B airborne position signals an emergency in its
+///  surveillance status
+pub fn get_emergency_status(bytes: &[u8; ADSB_SIZE_BYTES]) -> bool {
+    let tc = bytes[4] >> 3;
+    let airborne_position = matches!(tc, 9..=18 | 20..=22);
+    let ss = (bytes[4] >> 1) & 0x3;
+    airborne_position && ss == SS_PERMANENT_ALERT
+}
+
+/// Intentions of an aircraft from a target state and status message
+///  (type code 29, subtype 1)
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TargetState {
+    /// Altitude selected in the MCP/FCU or FMS, in meters
+    pub selected_altitude_meters: Option<f32>,
+
+    /// If the selected altitude comes from the flight management system
+    pub selected_altitude_fms: bool,
+
+    /// Barometric pressure setting in hectopascals
+    pub barometric_setting_hpa: Option<f32>,
+
+    /// Selected heading in degrees from north
+    pub selected_heading_degrees: Option<f32>,
+
+    /// Engaged autopilot modes, if the aircraft reports them
+    pub modes: Option<AutopilotModes>,
+}
+
+/// Autopilot modes reported in a target state and status message
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutopilotModes {
+    /// Autopilot engaged
+    pub autopilot: bool,
+
+    /// Vertical navigation mode
+    pub vnav: bool,
+
+    /// Altitude hold mode
+    pub altitude_hold: bool,
+
+    /// Approach mode
+    pub approach: bool,
+
+    /// Lateral navigation mode
+    pub lnav: bool,
+}
+
+/// Decodes a target state and status message (type code 29, subtype 1)
+///
+/// adsb_deku doesn't decode version 2 target states, the fields are read
+///  from the ME field directly.
+pub fn get_target_state(bytes: &[u8; ADSB_SIZE_BYTES]) -> Option<TargetState> {
+    let field = me_field(bytes)?;
+    if field(1, 5) as u8 != TC_TARGET_STATE || field(6, 2) as u8 != ST_TARGET_STATE_V2 {
+        return None;
+    }
+
+    // 0 means no data, values are offset by one
+    let selected_altitude_meters = match field(10, 11) {
+        0 => None,
+        altitude => Some((altitude - 1) as f32 * 32. * 0.3048),
+    };
+
+    let barometric_setting_hpa = match field(21, 9) {
+        0 => None,
+        setting => Some(800. + (setting - 1) as f32 * 0.8),
+    };
+
+    let selected_heading_degrees = (field(30, 1) == 1).then(|| field(31, 9) as f32 * 180. / 256.);
+
+    let modes = (field(47, 1) == 1).then(|| AutopilotModes {
+        autopilot: field(48, 1) == 1,
+        vnav: field(49, 1) == 1,
+        altitude_hold: field(50, 1) == 1,
+        approach: field(52, 1) == 1,
+        lnav: field(54, 1) == 1,
+    });
+
+    Some(TargetState {
+        selected_altitude_meters,
+        selected_altitude_fms: field(9, 1) == 1,
+        barometric_setting_hpa,
+        selected_heading_degrees,
+        modes,
+    })
+}
+
+/// Fields of the ME field (bits 1 to 56) of an extended squitter
+///
+/// Returns a reader of `len` bits starting at the 1-based bit `start`,
+///  or `None` if the packet isn't an extended squitter.
+fn me_field(bytes: &[u8; ADSB_SIZE_BYTES]) -> Option<impl Fn(u32, u32) -> u64> {
+    let df = get_downlink_format(bytes)?;
+    if !DF_EXTENDED_SQUITTERS.contains(&df) {
+        return None;
+    }
+
+    let me = bytes[4..11]
+        .iter()
+        .fold(0u64, |me, byte| me << 8 | *byte as u64);
+    Some(move |start: u32, len: u32| (me >> (57 - start - len)) & ((1 << len) - 1))
+}
+
+/// Horizontal 95% containment bound in meters of a navigation accuracy
+///  category for position (NACp), `None` if unknown
+///
+/// The categories match the NETRID horizontal accuracy codes.
+pub fn nacp_bound_meters(nacp: u8) -> Option<f32> {
+    NACP_BOUNDS_METERS
+        .get(nacp as usize)
+        .copied()
+        .filter(|bound| !bound.is_nan())
+}
+
+/// Upper bounds of the dimensions of an aircraft
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AircraftDimensions {
+    /// Maximum length in meters, `None` if longer than 85 meters
+    pub length_max_meters: Option<f32>,
+
+    /// Maximum width (wingspan) in meters
+    pub width_max_meters: f32,
+}
+
+impl AircraftDimensions {
+    /// Dimensions of a length/width code, `None` if no data
+    pub fn from_code(code: u8) -> Option<Self> {
+        if code == 0 {
+            return None;
+        }
+
+        let (length, width) = *DIMENSIONS_METERS.get(code as usize)?;
+
+        Some(AircraftDimensions {
+            length_max_meters: length.is_finite().then_some(length),
+            width_max_meters: width,
+        })
+    }
+}
+
+/// Accuracy and integrity of an aircraft from an operational status
+///  message (type code 31)
+///
+/// Version 0 transponders don't report accuracy nor integrity.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationalStatus {
+    /// ADS-B version of the transponder (0, 1 or 2)
+    pub version: u8,
+
+    /// If the aircraft is on the surface
+    pub surface: bool,
+
+    /// Navigation accuracy category for position
+    pub nacp: Option<u8>,
+
+    /// Horizontal 95% containment bound in meters, from the NACp
+    pub horizontal_accuracy_meters: Option<f32>,
+
+    /// Vertical 95% bound in meters, from the geometric vertical accuracy
+    ///  of airborne version 2 aircraft
+    pub vertical_accuracy_meters: Option<f32>,
+
+    /// NIC supplement A, refining the navigation integrity category of
+    ///  the position messages
+    pub nic_supplement_a: Option<bool>,
+
+    /// Source integrity level (0 to 3)
+    pub sil: Option<u8>,
+
+    /// If the SIL probability is per sample instead of per hour
+    pub sil_per_sample: Option<bool>,
+
+    /// If the barometric altitude is cross-checked, airborne only
+    pub nic_baro: Option<bool>,
+
+    /// Dimensions of the aircraft, surface only
+    pub dimensions: Option<AircraftDimensions>,
+}
+
+/// Decodes an aircraft operational status message (type code 31)
+///
+/// Like target states, these aren't decoded by adsb_deku.
+pub fn get_operational_status(bytes: &[u8; ADSB_SIZE_BYTES]) -> Option<OperationalStatus> {
+    let field = me_field(bytes)?;
+    if field(1, 5) as u8 != TC_OPERATIONAL_STATUS {
+        return None;
+    }
+
+    let surface = match field(6, 3) as u8 {
+        0 => false,
+        ST_OPERATIONAL_STATUS_SURFACE => true,
+        _ => return None,
+    };
+
+    let version = field(41, 3) as u8;
+    let mut status = OperationalStatus {
+        version,
+        surface,
+        nacp: None,
+        horizontal_accuracy_meters: None,
+        vertical_accuracy_meters: None,
+        nic_supplement_a: None,
+        sil: None,
+        sil_per_sample: None,
+        nic_baro: None,
+        dimensions: None,
+    };
+
+    if version == 0 {
+        return Some(status);
+    }
+
+    let nacp = field(45, 4) as u8;
+    status.nacp = Some(nacp);
+    status.horizontal_accuracy_meters = nacp_bound_meters(nacp);
+    status.nic_supplement_a = Some(field(44, 1) == 1);
+    status.sil = Some(field(51, 2) as u8);
+
+    if surface {
+        status.dimensions = AircraftDimensions::from_code(field(21, 4) as u8);
+    } else {
+        status.nic_baro = Some(field(53, 1) == 1);
+    }
+
+    if version >= 2 {
+        status.sil_per_sample = Some(field(55, 1) == 1);
+        if !surface {
+            status.vertical_accuracy_meters = GVA_BOUNDS_METERS
+                .get(field(49, 2) as usize)
+                .copied()
+                .filter(|bound| !bound.is_nan());
+        }
+    }
+
+    Some(status)
+}
+
+/// Latest known state of an ADS-B aircraft, combined from the messages
+///  it broadcast separately
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AircraftState {
+    /// ICAO address of the aircraft (hex)
+    pub identifier: String,
+
+    /// Callsign from the last identification message
+    pub callsign: Option<String>,
+
+    /// Raw emitter category from the last identification message, e.g. `A3`
+    pub emitter_category: Option<String>,
+
+    /// Last decoded position
+    pub position: Option<AircraftPosition>,
+
+    /// Last decoded velocity
+    pub velocity: Option<AircraftVelocity>,
+
+    /// Last target state and status
+    pub target_state: Option<TargetState>,
+
+    /// Last operational status, with the accuracy and integrity of the
+    ///  positions
+    pub operational_status: Option<OperationalStatus>,
+
+    /// If another aircraft recently claimed the ICAO address, see the
+    ///  identity conflicts
+    #[serde(default)]
+    pub contested: bool,
+
+    /// When the state was published
+    pub timestamp_network: DateTime<Utc>,
+}
+
+/// Details of an ADS-B aircraft published alongside its telemetry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AircraftEnrichment {
+    /// ICAO address of the aircraft (hex)
+    pub identifier: String,
+
+    /// Callsign from the last identification message
+    pub callsign: Option<String>,
+
+    /// Last squawk of the aircraft, four octal digits
+    pub squawk: Option<String>,
+
+    /// Emergency declared by the aircraft, if any
+    pub emergency: Option<Emergency>,
+
+    /// When the last message of the aircraft was received
+    pub timestamp_network: DateTime<Utc>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use adsb_deku::Sign;
+    use proptest::prelude::{any, prop_assert, prop_oneof, proptest, Just, Strategy};
+
+    /// Sign of a velocity component
+    fn sign() -> impl Strategy<Value = Sign> {
+        prop_oneof![Just(Sign::Positive), Just(Sign::Negative)]
+    }
 
     #[test]
     /// See 3.2.4 NL(lat) of https://airmetar.main.jp/radio/ADS-B%20Decoding%20Guide.pdf
@@ -270,8 +991,17 @@ mod tests {
         assert_eq!(nl(0.), 59.);
         assert_eq!(nl(87.), 2.);
         assert_eq!(nl(-87.), 2.);
-        // assert_eq!(nl(87.1), 1.); TODO(R5) incorrect around the poles
-        // assert_eq!(nl(-87.1), 1.); TODO(R5) switch to lookup table
+        assert_eq!(nl(87.1), 1.);
+        assert_eq!(nl(-87.1), 1.);
+        assert_eq!(nl(90.), 1.);
+
+        // boundaries of the decoding guide
+        assert_eq!(nl(10.47047130), 59.);
+        assert_eq!(nl(10.4705), 58.);
+        assert_eq!(nl(-14.83), 57.);
+        assert_eq!(nl(52.25720), 36.);
+        assert_eq!(nl(86.53536998), 3.);
+        assert_eq!(nl(86.5354), 2.);
     }
 
     #[test]
@@ -293,6 +1023,49 @@ mod tests {
         assert!((longitude - 3.91937).abs() < 0.0001);
     }
 
+    #[test]
+    /// See https://mode-s.org/decode/content/ads-b/4-surface-position.html
+    fn test_decode_cpr_surface() {
+        let (lat_even, lon_even) = (115609, 116941);
+        let (lat_odd, lon_odd) = (39199, 110269);
+        let receiver = (51.990, 4.375);
+        let (latitude, longitude) =
+            decode_cpr_surface(lat_even, lon_even, lat_odd, lon_odd, receiver).unwrap();
+
+        // the guide decodes the odd message at 52.32061, 4.73473
+        assert!((latitude - 52.32304).abs() < 0.0001);
+        assert!((longitude - 4.73047).abs() < 0.0001);
+
+        // the same messages in other quadrants and hemispheres
+        let reference = (52.0, 179.0);
+        let (_, longitude) =
+            decode_cpr_surface(lat_even, lon_even, lat_odd, lon_odd, reference).unwrap();
+        assert!((longitude - (4.73047 - 180.)).abs() < 0.0001);
+
+        let reference = (52.0, -85.3);
+        let (_, longitude) =
+            decode_cpr_surface(lat_even, lon_even, lat_odd, lon_odd, reference).unwrap();
+        assert!((longitude - (4.73047 - 90.)).abs() < 0.0001);
+
+        let reference = (-38.0, 4.4);
+        let (latitude, _) =
+            decode_cpr_surface(lat_even, lon_even, lat_odd, lon_odd, reference).unwrap();
+        assert!((latitude - (52.32304 - 90.)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_is_cpr_pair() {
+        assert!(is_cpr_pair(10_000, 6_500, CPR_MAX_PAIR_AGE_MS));
+        assert!(is_cpr_pair(6_500, 10_000, CPR_MAX_PAIR_AGE_MS));
+        assert!(!is_cpr_pair(10_000, 6_499, CPR_MAX_PAIR_AGE_MS));
+        assert!(is_cpr_pair(30_000, 6_000, CPR_SURFACE_MAX_PAIR_AGE_MS));
+        assert!(!is_cpr_pair(
+            i64::MAX,
+            i64::MIN,
+            CPR_SURFACE_MAX_PAIR_AGE_MS
+        ));
+    }
+
     #[test]
     fn test_decode_altitude() {
         let alt = 0b110000111000;
@@ -448,4 +1221,299 @@ mod tests {
         assert!((expected_latitude_cpr as f64 - cpr_latitude as f64).abs() < tolerance_latitude);
         assert!((expected_longitude_cpr as f64 - cpr_longitude as f64).abs() < tolerance_longitude);
     }
+
+    #[test]
+    fn test_emitter_category() {
+        assert_eq!(
+            EmitterCategory::new(TypeCoding::D, 3),
+            EmitterCategory::Reserved
+        );
+        assert_eq!(
+            EmitterCategory::new(TypeCoding::A, 0),
+            EmitterCategory::NoInformation
+        );
+        assert_eq!(
+            EmitterCategory::new(TypeCoding::C, 2),
+            EmitterCategory::Reserved
+        );
+        assert_eq!(
+            EmitterCategory::new(TypeCoding::C, 6),
+            EmitterCategory::GroundObstruction
+        );
+        assert_eq!(EmitterCategory::new(TypeCoding::B, 6), EmitterCategory::Uav);
+        assert_eq!(
+            EmitterCategory::new(TypeCoding::B, 5),
+            EmitterCategory::Reserved
+        );
+        assert_eq!(
+            EmitterCategory::new(TypeCoding::A, 3),
+            EmitterCategory::Medium2
+        );
+        assert_eq!(
+            EmitterCategory::new(TypeCoding::A, 8),
+            EmitterCategory::Reserved
+        );
+    }
+
+    #[test]
+    fn test_emitter_category_code() {
+        assert_eq!(emitter_category_code(TypeCoding::A, 3), "A3");
+        assert_eq!(emitter_category_code(TypeCoding::C, 0), "C0");
+    }
+
+    #[test]
+    fn test_decode_identity_code() {
+        assert_eq!(decode_identity_code(0xAAA), Squawk(0o7700));
+        assert_eq!(decode_identity_code(0x1515), Squawk(0o0077));
+        assert_eq!(decode_identity_code(0), Squawk(0));
+        assert_eq!(Squawk(0o7700).to_string(), "7700");
+        assert_eq!(Squawk(0o0077).to_string(), "0077");
+    }
+
+    #[test]
+    fn test_squawk_emergency() {
+        assert_eq!(Squawk(0o7500).emergency(), Some(Emergency::Hijack));
+        assert_eq!(Squawk(0o7600).emergency(), Some(Emergency::RadioFailure));
+        assert_eq!(Squawk(0o7700).emergency(), Some(Emergency::General));
+        assert_eq!(Squawk(0o1200).emergency(), None);
+    }
+
+    #[test]
+    fn test_modes_crc() {
+        let bytes = [
+            0x8D, 0x48, 0x40, 0xD6, 0x20, 0x2C, 0xC3, 0x71, 0xC3, 0x2C, 0xE0, 0x57, 0x60, 0x98,
+        ];
+        assert_eq!(modes_crc(&bytes[..11]), 0x576098);
+
+        // the parity of an extended squitter isn't overlaid
+        assert_eq!(get_reply_icao_address(&bytes), None);
+        assert_eq!(get_reply_icao_address(&[0x28]), None);
+    }
+
+    #[test]
+    fn test_get_squawk() {
+        // DF21 reply of 4840d6 squawking 7700
+        let mut bytes = [0u8; ADSB_SIZE_BYTES];
+        bytes[0] = DF_COMM_B_IDENTITY_REPLY << 3;
+        bytes[2] = 0x0A;
+        bytes[3] = 0xAA;
+        let parity = (modes_crc(&bytes[..11]) ^ 0x4840D6).to_be_bytes();
+        bytes[11..].copy_from_slice(&parity[1..]);
+
+        assert_eq!(get_squawk(&bytes), Some(Squawk(0o7700)));
+        assert_eq!(get_reply_icao_address(&bytes), Some(0x4840D6));
+
+        // short replies
+        assert_eq!(get_squawk(&bytes[..7]), None);
+        assert_eq!(get_reply_icao_address(&bytes[..7]), None);
+
+        // ADS-B emergency status
+        let mut bytes = [0u8; ADSB_SIZE_BYTES];
+        bytes[0] = 0x8D;
+        bytes[4] = TC_AIRCRAFT_STATUS << 3 | ST_EMERGENCY_STATUS;
+        bytes[5] = 0x2A; // general emergency, squawk bits
+        bytes[6] = 0xAA;
+        assert_eq!(get_squawk(&bytes), Some(Squawk(0o7700)));
+
+        // other subtype
+        bytes[4] = TC_AIRCRAFT_STATUS << 3 | 2;
+        assert_eq!(get_squawk(&bytes), None);
+
+        // airborne position
+        bytes[4] = 11 << 3;
+        assert_eq!(get_squawk(&bytes), None);
+        assert_eq!(get_squawk(&[]), None);
+    }
+
+    #[test]
+    fn test_get_emergency_status() {
+        let mut bytes = [0u8; ADSB_SIZE_BYTES];
+        bytes[4] = 11 << 3 | SS_PERMANENT_ALERT << 1;
+        assert!(get_emergency_status(&bytes));
+
+        // temporary alert
+        bytes[4] = 11 << 3 | 2 << 1;
+        assert!(!get_emergency_status(&bytes));
+
+        // identification
+        bytes[4] = 4 << 3 | SS_PERMANENT_ALERT << 1;
+        assert!(!get_emergency_status(&bytes));
+    }
+
+    #[test]
+    fn test_get_target_state() {
+        // 8DA05629EA21485CBF3F8CADAEEB
+        let bytes: [u8; ADSB_SIZE_BYTES] = [
+            0x8D, 0xA0, 0x56, 0x29, 0xEA, 0x21, 0x48, 0x5C, 0xBF, 0x3F, 0x8C, 0xAD, 0xAE, 0xEB,
+        ];
+
+        let state = get_target_state(&bytes).unwrap();
+        let selected_altitude_ft = state.selected_altitude_meters.unwrap() / 0.3048;
+        assert!((selected_altitude_ft - 16992.).abs() < 0.1);
+        assert!(!state.selected_altitude_fms);
+        assert!((state.barometric_setting_hpa.unwrap() - 1012.8).abs() < 0.01);
+        assert!((state.selected_heading_degrees.unwrap() - 66.797).abs() < 0.001);
+        assert_eq!(
+            state.modes,
+            Some(AutopilotModes {
+                autopilot: true,
+                vnav: true,
+                altitude_hold: false,
+                approach: false,
+                lnav: true,
+            })
+        );
+
+        // aircraft status
+        let mut other = bytes;
+        other[4] = TC_AIRCRAFT_STATUS << 3 | ST_EMERGENCY_STATUS;
+        assert_eq!(get_target_state(&other), None);
+
+        // no data
+        let mut empty = [0u8; ADSB_SIZE_BYTES];
+        empty[0] = 0x8D;
+        empty[4] = TC_TARGET_STATE << 3 | ST_TARGET_STATE_V2 << 1;
+        let state = get_target_state(&empty).unwrap();
+        assert_eq!(state.selected_altitude_meters, None);
+        assert_eq!(state.barometric_setting_hpa, None);
+        assert_eq!(state.selected_heading_degrees, None);
+        assert_eq!(state.modes, None);
+    }
+
+    #[test]
+    fn test_get_operational_status() {
+        let mut bytes = [0u8; ADSB_SIZE_BYTES];
+        bytes[0] = 0x8D;
+
+        // airborne, version 2, NACp 9, GVA 2, SIL 3, NIC baro
+        bytes[4] = TC_OPERATIONAL_STATUS << 3;
+        bytes[9] = 0b0100_1001; // version (3 bits), NIC-A, NACp (4 bits)
+        bytes[10] = 0b1011_1010; // GVA, SIL, NIC baro, HRD, SIL supplement
+        let status = get_operational_status(&bytes).unwrap();
+        assert_eq!(status.version, 2);
+        assert!(!status.surface);
+        assert_eq!(status.nacp, Some(9));
+        assert_eq!(status.horizontal_accuracy_meters, Some(30.));
+        assert_eq!(status.vertical_accuracy_meters, Some(45.));
+        assert_eq!(status.nic_supplement_a, Some(false));
+        assert_eq!(status.sil, Some(3));
+        assert_eq!(status.sil_per_sample, Some(true));
+        assert_eq!(status.nic_baro, Some(true));
+        assert_eq!(status.dimensions, None);
+
+        // surface, version 1, length/width code 15
+        bytes[4] = TC_OPERATIONAL_STATUS << 3 | ST_OPERATIONAL_STATUS_SURFACE;
+        bytes[6] = 0x0F;
+        bytes[9] = 0b0011_0000; // version 1, NIC-A, NACp 0
+        let status = get_operational_status(&bytes).unwrap();
+        assert_eq!(status.version, 1);
+        assert!(status.surface);
+        assert_eq!(status.nacp, Some(0));
+        assert_eq!(status.horizontal_accuracy_meters, None);
+        assert_eq!(status.vertical_accuracy_meters, None);
+        assert_eq!(status.nic_supplement_a, Some(true));
+        assert_eq!(status.sil_per_sample, None);
+        assert_eq!(status.nic_baro, None);
+        assert_eq!(
+            status.dimensions,
+            Some(AircraftDimensions {
+                length_max_meters: None,
+                width_max_meters: 90.,
+            })
+        );
+
+        // version 0
+        bytes[9] = 0;
+        let status = get_operational_status(&bytes).unwrap();
+        assert_eq!(status.version, 0);
+        assert_eq!(status.nacp, None);
+        assert_eq!(status.horizontal_accuracy_meters, None);
+        assert_eq!(status.dimensions, None);
+
+        // reserved subtype
+        bytes[4] = TC_OPERATIONAL_STATUS << 3 | 2;
+        assert_eq!(get_operational_status(&bytes), None);
+    }
+
+    #[test]
+    fn test_aircraft_dimensions() {
+        assert_eq!(AircraftDimensions::from_code(0), None);
+        assert_eq!(
+            AircraftDimensions::from_code(1),
+            Some(AircraftDimensions {
+                length_max_meters: Some(15.),
+                width_max_meters: 23.,
+            })
+        );
+        assert_eq!(AircraftDimensions::from_code(16), None);
+        assert_eq!(nacp_bound_meters(0), None);
+        assert_eq!(nacp_bound_meters(11), Some(3.));
+        assert_eq!(nacp_bound_meters(12), None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_decode_cpr_arbitrary(
+            lat_even in 0u32..131072,
+            lon_even in 0u32..131072,
+            lat_odd in 0u32..131072,
+            lon_odd in 0u32..131072,
+        ) {
+            if let Ok((latitude, longitude)) = decode_cpr(lat_even, lon_even, lat_odd, lon_odd) {
+                prop_assert!((-90. ..=90.).contains(&latitude), "{latitude}");
+                prop_assert!((-180. ..180.).contains(&longitude), "{longitude}");
+            }
+        }
+
+        #[test]
+        fn test_decode_cpr_surface_arbitrary(
+            lat_even in 0u32..131072,
+            lon_even in 0u32..131072,
+            lat_odd in 0u32..131072,
+            lon_odd in 0u32..131072,
+            reference in (-90.0f64..=90.0, -180.0f64..180.0),
+        ) {
+            let decoded = decode_cpr_surface(lat_even, lon_even, lat_odd, lon_odd, reference);
+            if let Ok((latitude, longitude)) = decoded {
+                prop_assert!((-90. ..=90.).contains(&latitude), "{latitude}");
+                prop_assert!((-180. ..180.).contains(&longitude), "{longitude}");
+            }
+        }
+
+        #[test]
+        fn test_cpr_round_trip(latitude in -80.0f64..80.0, longitude in -180.0f64..180.0) {
+            let (lon_even, lat_even) = encode_cpr(0, longitude, latitude).unwrap();
+            let (lon_odd, lat_odd) = encode_cpr(1, longitude, latitude).unwrap();
+
+            // pairs straddling a longitude zone boundary are rejected
+            if let Ok((lat, lon)) = decode_cpr(lat_even, lon_even, lat_odd, lon_odd) {
+                prop_assert!((lat - latitude).abs() < 1e-3, "{lat} {latitude}");
+                let delta = (lon - longitude).rem_euclid(360.);
+                prop_assert!(delta.min(360. - delta) < 1e-3, "{lon} {longitude}");
+            }
+        }
+
+        #[test]
+        fn test_decode_speed_direction_arbitrary(
+            st in any::<u8>(),
+            ew_sign in sign(),
+            ew_vel in any::<u16>(),
+            ns_sign in sign(),
+            ns_vel in any::<u16>(),
+        ) {
+            if let Ok((speed, direction)) = decode_speed_direction(st, ew_sign, ew_vel, ns_sign, ns_vel) {
+                prop_assert!(speed >= 0.);
+                prop_assert!((0. ..=360.).contains(&direction), "{direction}");
+            }
+        }
+
+        #[test]
+        fn test_decode_arbitrary_bytes(bytes in any::<[u8; ADSB_SIZE_BYTES]>()) {
+            let _ = get_squawk(&bytes);
+            let _ = get_reply_icao_address(&bytes);
+            let _ = get_emergency_status(&bytes);
+            let _ = get_target_state(&bytes);
+            let _ = get_operational_status(&bytes);
+        }
+    }
 }
@@ -0,0 +1,620 @@
+//! Detection of anomalies in decoded telemetry
+//!
+//! Detectors inspect each item pushed to the `anomaly` sink and report
+//!  what looks spoofed or physically impossible: positions jumping further
+//!  than an aircraft can fly, impossible climb rates, and an identifier
+//!  reported at distant places at once. Custom detectors are added with
+//!  [`register`] before the servers start.
+
+use super::geofence::distance_meters;
+use crate::config::Config;
+use crate::sink::{EventData, EventSource, TelemetryEvent};
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use svc_gis_client_grpc::prelude::types::AircraftPosition;
+
+/// Reporters are forgotten if they sent no position for this long
+const DETECTOR_EXPIRE_MS: i64 = 60000;
+
+/// Number of tracked reporters above which expired ones are removed
+const DETECTOR_PRUNE_THRESHOLD: usize = 1024;
+
+/// Detector shared between the pipelines of the process
+pub type SharedDetector = Arc<dyn AnomalyDetector>;
+
+/// Anomaly found in the telemetry of an aircraft
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Anomaly {
+    /// Name of the detector which found the anomaly
+    pub detector: String,
+
+    /// ICAO address (hex) or Remote ID identifier of the aircraft
+    pub identifier: String,
+
+    /// Protocol the telemetry was received with
+    pub source: EventSource,
+
+    /// Login session of the reporter, for Remote ID telemetry
+    pub session: Option<String>,
+
+    /// Flight plan or mission declared by the session
+    pub mission: Option<String>,
+
+    /// What was detected
+    pub description: String,
+
+    /// Measured value, if the detector compares it to a limit
+    pub value: Option<f64>,
+
+    /// Limit the value exceeds
+    pub limit: Option<f64>,
+
+    /// Latitude of the position the anomaly was found at, in degrees
+    pub latitude: Option<f64>,
+
+    /// Longitude of the position the anomaly was found at, in degrees
+    pub longitude: Option<f64>,
+
+    /// When the anomalous telemetry was received
+    pub timestamp_network: DateTime<Utc>,
+}
+
+impl Anomaly {
+    /// Anomaly found in an event
+    pub fn new(detector: &str, event: &TelemetryEvent, description: String) -> Self {
+        let (latitude, longitude) = match &event.data {
+            EventData::Position(item) => {
+                (Some(item.position.latitude), Some(item.position.longitude))
+            }
+            _ => (None, None),
+        };
+
+        Anomaly {
+            detector: detector.to_string(),
+            identifier: event.identifier.clone(),
+            source: event.source,
+            session: event.session.clone(),
+            mission: event.mission.clone(),
+            description,
+            value: None,
+            limit: None,
+            latitude,
+            longitude,
+            timestamp_network: event.received,
+        }
+    }
+
+    /// Set the measured value and the limit it exceeds
+    pub fn with_value(mut self, value: f64, limit: f64) -> Self {
+        self.value = Some(value);
+        self.limit = Some(limit);
+        self
+    }
+}
+
+/// Inspects decoded telemetry for anomalies
+///
+/// Detectors are called with every event pushed to the `anomaly` sink, from
+///  concurrent request handlers. State kept between events must be behind
+///  a lock.
+pub trait AnomalyDetector: Send + Sync + Debug {
+    /// Name of the detector, published with its anomalies
+    fn name(&self) -> &'static str;
+
+    /// Inspect an event, returning the anomalies found
+    fn inspect(&self, event: &TelemetryEvent) -> Vec<Anomaly>;
+}
+
+/// Aircraft as reported by a single reporter
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct StreamKey {
+    /// Identifier of the aircraft
+    identifier: String,
+
+    /// Protocol of the reporter
+    source: EventSource,
+
+    /// Login session of the reporter
+    session: Option<String>,
+}
+
+impl StreamKey {
+    /// Stream an event belongs to
+    fn of(event: &TelemetryEvent) -> Self {
+        StreamKey {
+            identifier: event.identifier.clone(),
+            source: event.source,
+            session: event.session.clone(),
+        }
+    }
+}
+
+/// Position of an aircraft at a time
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Sample {
+    /// Latitude in degrees
+    latitude: f64,
+
+    /// Longitude in degrees
+    longitude: f64,
+
+    /// Altitude in meters
+    altitude_meters: f64,
+
+    /// When the aircraft was there, as reported by the aircraft if known
+    timestamp: DateTime<Utc>,
+
+    /// When the position was received
+    received: DateTime<Utc>,
+}
+
+impl Sample {
+    /// Sample of a position received at a time
+    fn new(item: &AircraftPosition, received: DateTime<Utc>) -> Self {
+        Sample {
+            latitude: item.position.latitude,
+            longitude: item.position.longitude,
+            altitude_meters: item.position.altitude_meters,
+            timestamp: item.timestamp_asset.unwrap_or(item.timestamp_network),
+            received,
+        }
+    }
+
+    /// Seconds elapsed since an earlier sample
+    fn seconds_since(&self, earlier: &Sample) -> f64 {
+        (self.timestamp - earlier.timestamp).num_milliseconds() as f64 / 1000.0
+    }
+
+    /// Horizontal distance to another sample
+    fn distance_meters(&self, other: &Sample) -> f64 {
+        distance_meters(
+            (self.latitude, self.longitude),
+            (other.latitude, other.longitude),
+        )
+    }
+}
+
+/// Last position of each stream
+#[derive(Debug, Default)]
+struct LastSamples {
+    /// Last position by stream
+    samples: HashMap<StreamKey, Sample>,
+}
+
+impl LastSamples {
+    /// Record the position of a stream, returning the previous one
+    ///
+    /// Positions older than the previous one are not recorded and return
+    ///  None, they can't be compared meaningfully.
+    fn update(&mut self, key: StreamKey, sample: Sample) -> Option<Sample> {
+        if self.samples.len() > DETECTOR_PRUNE_THRESHOLD {
+            let expire = Duration::try_milliseconds(DETECTOR_EXPIRE_MS).unwrap_or(Duration::zero());
+            self.samples
+                .retain(|_, last| sample.received - last.received < expire);
+        }
+
+        match self.samples.get(&key) {
+            Some(previous) if sample.timestamp <= previous.timestamp => None,
+            _ => self.samples.insert(key, sample),
+        }
+    }
+}
+
+/// Lock a detector state, recovering it if a detector panicked
+fn lock<T>(state: &Mutex<T>) -> MutexGuard<'_, T> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Detects positions further from the previous one of their reporter than
+///  the aircraft could have flown
+#[derive(Debug)]
+pub struct PositionJumpDetector {
+    /// Highest plausible ground speed
+    max_speed_mps: f64,
+
+    /// Last position of each stream
+    last: Mutex<LastSamples>,
+}
+
+impl PositionJumpDetector {
+    /// Detect implied ground speeds above the given limit
+    pub fn new(max_speed_mps: f64) -> Self {
+        PositionJumpDetector {
+            max_speed_mps,
+            last: Mutex::default(),
+        }
+    }
+}
+
+impl AnomalyDetector for PositionJumpDetector {
+    fn name(&self) -> &'static str {
+        "position_jump"
+    }
+
+    fn inspect(&self, event: &TelemetryEvent) -> Vec<Anomaly> {
+        let EventData::Position(item) = &event.data else {
+            return vec![];
+        };
+
+        let sample = Sample::new(item, event.received);
+        let Some(previous) = lock(&self.last).update(StreamKey::of(event), sample) else {
+            return vec![];
+        };
+
+        let distance = sample.distance_meters(&previous);
+        let speed = distance / sample.seconds_since(&previous);
+        if speed <= self.max_speed_mps {
+            return vec![];
+        }
+
+        let description = format!(
+            "moved {distance:.0} m in {:.1} s",
+            sample.seconds_since(&previous)
+        );
+        vec![Anomaly::new(self.name(), event, description).with_value(speed, self.max_speed_mps)]
+    }
+}
+
+/// Detects vertical speeds no aircraft reaches, as reported in velocities
+///  or implied by consecutive altitudes of a reporter
+#[derive(Debug)]
+pub struct ClimbRateDetector {
+    /// Highest plausible vertical speed, climbing or descending
+    max_climb_rate_mps: f64,
+
+    /// Last position of each stream
+    last: Mutex<LastSamples>,
+}
+
+impl ClimbRateDetector {
+    /// Detect vertical speeds above the given limit
+    pub fn new(max_climb_rate_mps: f64) -> Self {
+        ClimbRateDetector {
+            max_climb_rate_mps,
+            last: Mutex::default(),
+        }
+    }
+}
+
+impl AnomalyDetector for ClimbRateDetector {
+    fn name(&self) -> &'static str {
+        "climb_rate"
+    }
+
+    fn inspect(&self, event: &TelemetryEvent) -> Vec<Anomaly> {
+        let (rate, description) = match &event.data {
+            EventData::Velocity(item) => {
+                let rate = item.velocity_vertical_mps as f64;
+                (rate, format!("reported a vertical speed of {rate:.1} m/s"))
+            }
+            EventData::Position(item) => {
+                let sample = Sample::new(item, event.received);
+                let Some(previous) = lock(&self.last).update(StreamKey::of(event), sample) else {
+                    return vec![];
+                };
+
+                let change = sample.altitude_meters - previous.altitude_meters;
+                let seconds = sample.seconds_since(&previous);
+                (
+                    change / seconds,
+                    format!("changed altitude by {change:.0} m in {seconds:.1} s"),
+                )
+            }
+            _ => return vec![],
+        };
+
+        if rate.abs() <= self.max_climb_rate_mps {
+            return vec![];
+        }
+
+        vec![Anomaly::new(self.name(), event, description)
+            .with_value(rate.abs(), self.max_climb_rate_mps)]
+    }
+}
+
+/// Detects an identifier reported by different reporters at the same time
+///  at distant positions, e.g. two aircraft broadcasting the same identifier
+#[derive(Debug)]
+pub struct IdentifierCollisionDetector {
+    /// Distance between simultaneous positions above which they can't be
+    ///  the same aircraft
+    max_distance_meters: f64,
+
+    /// Positions closer in time than this are simultaneous
+    window: Duration,
+
+    /// Latest position of each reporter, by identifier
+    latest: Mutex<HashMap<String, Vec<(StreamKey, Sample)>>>,
+}
+
+impl IdentifierCollisionDetector {
+    /// Detect simultaneous positions further apart than the given distance
+    pub fn new(max_distance_meters: f64, window_ms: u32) -> Self {
+        IdentifierCollisionDetector {
+            max_distance_meters,
+            window: Duration::try_milliseconds(window_ms as i64).unwrap_or(Duration::zero()),
+            latest: Mutex::default(),
+        }
+    }
+}
+
+impl AnomalyDetector for IdentifierCollisionDetector {
+    fn name(&self) -> &'static str {
+        "identifier_collision"
+    }
+
+    fn inspect(&self, event: &TelemetryEvent) -> Vec<Anomaly> {
+        let EventData::Position(item) = &event.data else {
+            return vec![];
+        };
+
+        let key = StreamKey::of(event);
+        let sample = Sample::new(item, event.received);
+        let mut latest = lock(&self.latest);
+        if latest.len() > DETECTOR_PRUNE_THRESHOLD {
+            latest.retain(|_, reporters| {
+                reporters
+                    .iter()
+                    .any(|(_, last)| sample.received - last.received < self.window)
+            });
+        }
+
+        // positions of the other reporters are only kept while simultaneous
+        let reporters = latest.entry(event.identifier.clone()).or_default();
+        reporters.retain(|(other, last)| {
+            *other != key && (sample.timestamp - last.timestamp).abs() <= self.window
+        });
+
+        let furthest = reporters
+            .iter()
+            .map(|(_, last)| sample.distance_meters(last))
+            .fold(0.0, f64::max);
+
+        reporters.push((key, sample));
+        if furthest <= self.max_distance_meters {
+            return vec![];
+        }
+
+        let description = format!("reported {furthest:.0} m away by another reporter");
+        vec![Anomaly::new(self.name(), event, description)
+            .with_value(furthest, self.max_distance_meters)]
+    }
+}
+
+/// Custom detectors added to every pipeline, see [`register`]
+static REGISTERED: Mutex<Vec<SharedDetector>> = Mutex::new(Vec::new());
+
+/// Add a custom detector to the pipelines created afterwards
+///
+/// Meant to be called by binaries embedding the servers, before starting
+///  them. The detector is shared by all pipelines of the process.
+pub fn register(detector: SharedDetector) {
+    lock(&REGISTERED).push(detector);
+}
+
+/// Detectors each event is inspected by, in order
+#[derive(Debug, Default)]
+pub struct AnomalyDetectors {
+    /// The detectors, in order
+    detectors: Vec<SharedDetector>,
+}
+
+impl AnomalyDetectors {
+    /// The baseline detectors, followed by the registered ones
+    pub fn new(config: &Config) -> Self {
+        let baseline: [SharedDetector; 3] = [
+            Arc::new(PositionJumpDetector::new(
+                config.anomaly_max_speed_mps as f64,
+            )),
+            Arc::new(ClimbRateDetector::new(
+                config.anomaly_max_climb_rate_mps as f64,
+            )),
+            Arc::new(IdentifierCollisionDetector::new(
+                config.anomaly_collision_distance_meters as f64,
+                config.anomaly_collision_window_ms,
+            )),
+        ];
+
+        let detectors = baseline
+            .into_iter()
+            .chain(lock(&REGISTERED).iter().cloned())
+            .collect();
+
+        AnomalyDetectors { detectors }
+    }
+
+    /// Add a detector
+    pub fn with_detector(mut self, detector: SharedDetector) -> Self {
+        self.detectors.push(detector);
+        self
+    }
+
+    /// Names of the detectors, in order
+    pub fn names(&self) -> Vec<&'static str> {
+        self.detectors
+            .iter()
+            .map(|detector| detector.name())
+            .collect()
+    }
+
+    /// Anomalies found by the detectors in an event
+    pub fn inspect(&self, event: &TelemetryEvent) -> Vec<Anomaly> {
+        self.detectors
+            .iter()
+            .flat_map(|detector| detector.inspect(event))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use svc_gis_client_grpc::prelude::types::{AircraftVelocity, Position};
+
+    fn position(
+        latitude: f64,
+        altitude_meters: f64,
+        timestamp: DateTime<Utc>,
+        session: &str,
+    ) -> TelemetryEvent {
+        let item = AircraftPosition {
+            identifier: "drone-1".to_string(),
+            position: Position {
+                latitude,
+                longitude: 4.9,
+                altitude_meters,
+            },
+            timestamp_network: timestamp,
+            timestamp_asset: None,
+        };
+
+        TelemetryEvent::new(
+            EventSource::Netrid,
+            "drone-1",
+            EventData::Position(item),
+            Utc::now(),
+        )
+        .with_session(session.to_string())
+    }
+
+    /// Detector flagging every event
+    #[derive(Debug)]
+    struct EveryEvent;
+
+    impl AnomalyDetector for EveryEvent {
+        fn name(&self) -> &'static str {
+            "every_event"
+        }
+
+        fn inspect(&self, event: &TelemetryEvent) -> Vec<Anomaly> {
+            vec![Anomaly::new(self.name(), event, "seen".to_string())]
+        }
+    }
+
+    #[test]
+    fn test_position_jump() {
+        let detector = PositionJumpDetector::new(100.0);
+        let now = Utc::now();
+        assert!(detector
+            .inspect(&position(52.0, 100.0, now, "a"))
+            .is_empty());
+
+        // about 55 m/s
+        let later = now + Duration::seconds(2);
+        assert!(detector
+            .inspect(&position(52.001, 100.0, later, "a"))
+            .is_empty());
+
+        // about 1100 m/s
+        let later = later + Duration::seconds(1);
+        let anomalies = detector.inspect(&position(52.011, 100.0, later, "a"));
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].detector, "position_jump");
+        assert_eq!(anomalies[0].latitude, Some(52.011));
+        assert_eq!(anomalies[0].limit, Some(100.0));
+        assert!((anomalies[0].value.unwrap() - 1112.0).abs() < 5.0);
+
+        // older positions and other reporters are not compared
+        assert!(detector
+            .inspect(&position(53.0, 100.0, now, "a"))
+            .is_empty());
+        assert!(detector
+            .inspect(&position(53.0, 100.0, later, "b"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_climb_rate() {
+        let detector = ClimbRateDetector::new(50.0);
+        let now = Utc::now();
+        assert!(detector
+            .inspect(&position(52.0, 100.0, now, "a"))
+            .is_empty());
+
+        let later = now + Duration::seconds(2);
+        assert!(detector
+            .inspect(&position(52.0, 180.0, later, "a"))
+            .is_empty());
+
+        // descending 150 m/s
+        let later = later + Duration::seconds(2);
+        let anomalies = detector.inspect(&position(52.0, -120.0, later, "a"));
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].value, Some(150.0));
+
+        let velocity = AircraftVelocity {
+            identifier: "drone-1".to_string(),
+            velocity_horizontal_ground_mps: 20.0,
+            velocity_horizontal_air_mps: None,
+            velocity_vertical_mps: 80.0,
+            track_angle_degrees: 90.0,
+            timestamp_network: now,
+            timestamp_asset: None,
+        };
+        let event = TelemetryEvent::new(
+            EventSource::Adsb,
+            "4840d6",
+            EventData::Velocity(velocity),
+            Utc::now(),
+        );
+        let anomalies = detector.inspect(&event);
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].latitude, None);
+        assert_eq!(anomalies[0].value, Some(80.0));
+    }
+
+    #[test]
+    fn test_identifier_collision() {
+        let detector = IdentifierCollisionDetector::new(5000.0, 2000);
+        let now = Utc::now();
+        assert!(detector
+            .inspect(&position(52.0, 100.0, now, "a"))
+            .is_empty());
+
+        // a nearby reporter hearing the same aircraft
+        let later = now + Duration::milliseconds(500);
+        assert!(detector
+            .inspect(&position(52.01, 100.0, later, "b"))
+            .is_empty());
+
+        // about 110 km away at the same time
+        let anomalies = detector.inspect(&position(53.0, 100.0, later, "c"));
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].detector, "identifier_collision");
+        assert_eq!(anomalies[0].session, Some("c".to_string()));
+        assert!(anomalies[0].value.unwrap() > 100_000.0);
+
+        // no longer simultaneous
+        let later = now + Duration::seconds(10);
+        assert!(detector
+            .inspect(&position(52.0, 100.0, later, "a"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_detectors() {
+        let detectors = AnomalyDetectors::new(&Config::default());
+        assert_eq!(
+            detectors.names(),
+            vec!["position_jump", "climb_rate", "identifier_collision"]
+        );
+
+        let detectors = detectors.with_detector(Arc::new(EveryEvent));
+        let now = Utc::now();
+        let anomalies = detectors.inspect(&position(52.0, 100.0, now, "a"));
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].detector, "every_event");
+
+        let later = now + Duration::seconds(1);
+        let anomalies = detectors.inspect(&position(53.0, 100.0, later, "a"));
+        let names: Vec<&str> = anomalies.iter().map(|a| a.detector.as_str()).collect();
+        assert_eq!(names, vec!["position_jump", "every_event"]);
+
+        register(Arc::new(EveryEvent));
+        let detectors = AnomalyDetectors::new(&Config::default());
+        assert_eq!(detectors.names().last(), Some(&"every_event"));
+    }
+}
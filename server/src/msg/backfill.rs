@@ -0,0 +1,203 @@
+//! Replay of the ADS-B packets stored in svc-storage
+//!
+//! Positions received while svc-gis was down never reach it: once it is
+//!  back, the packets stored over the outage are decoded again, in order
+//!  of reception, and their positions pushed with their original
+//!  timestamps. Only airborne positions are replayed, surface positions
+//!  are decoded near a reference location which isn't stored.
+
+use super::adsb::{
+    decode_altitude, decode_cpr, get_adsb_icao_address, is_cpr_pair, ADSB_SIZE_BYTES,
+    CPR_MAX_PAIR_AGE_MS,
+};
+use super::identifier::IdentifierRules;
+use adsb_deku::adsb::ME::AirbornePositionBaroAltitude as AirbornePosition;
+use adsb_deku::deku::DekuContainerRead;
+use adsb_deku::CPRFormat;
+use lib_common::time::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use svc_gis_client_grpc::prelude::types::{AircraftPosition, Position};
+use utoipa::ToSchema;
+
+/// Progress of the backfill shared between request handlers
+pub type SharedBackfill = Arc<Mutex<BackfillStatus>>;
+
+/// Latest odd CPR message of an aircraft: latitude, longitude and
+///  reception time in milliseconds
+type OddCpr = (u32, u32, i64);
+
+/// Decodes the positions of stored packets, replayed in order of reception
+#[derive(Debug, Default)]
+pub struct PositionReplay {
+    /// Latest odd CPR message of each aircraft, by ICAO address
+    odd: HashMap<u32, OddCpr>,
+}
+
+impl PositionReplay {
+    /// Position of a stored packet, if it completes a CPR pair
+    ///
+    /// Positions are decoded from even messages paired with the latest odd
+    ///  message of the aircraft, and are stamped with the reception of the
+    ///  even message.
+    pub fn replay(
+        &mut self,
+        payload: &[u8; ADSB_SIZE_BYTES],
+        received: DateTime<Utc>,
+        identifiers: &IdentifierRules,
+    ) -> Option<AircraftPosition> {
+        let (_, frame) = adsb_deku::Frame::from_bytes((payload, 0)).ok()?;
+        let adsb_deku::DF::ADSB(msg) = &frame.df else {
+            return None;
+        };
+
+        let AirbornePosition(position) = &msg.me else {
+            return None;
+        };
+
+        let icao = get_adsb_icao_address(&msg.icao.0);
+        let received_ms = received.timestamp_millis();
+        if position.odd_flag == CPRFormat::Odd {
+            let odd = (position.lat_cpr, position.lon_cpr, received_ms);
+            self.odd.insert(icao, odd);
+            return None;
+        }
+
+        let (lat_cpr_odd, lon_cpr_odd, odd_received_ms) = *self.odd.get(&icao)?;
+        if !is_cpr_pair(received_ms, odd_received_ms, CPR_MAX_PAIR_AGE_MS) {
+            return None;
+        }
+
+        let (latitude, longitude) =
+            decode_cpr(position.lat_cpr, position.lon_cpr, lat_cpr_odd, lon_cpr_odd).ok()?;
+
+        Some(AircraftPosition {
+            identifier: identifiers.icao(icao),
+            position: Position {
+                latitude,
+                longitude,
+                altitude_meters: decode_altitude(position.alt?) as f64,
+            },
+            timestamp_network: received,
+            timestamp_asset: None,
+        })
+    }
+}
+
+/// Progress of the latest backfill
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct BackfillStatus {
+    /// If a backfill is running
+    pub running: bool,
+
+    /// Start of the replayed period
+    pub from: Option<DateTime<Utc>>,
+
+    /// End of the replayed period
+    pub to: Option<DateTime<Utc>>,
+
+    /// Stored packets read so far
+    pub packets: u64,
+
+    /// Positions pushed to svc-gis so far
+    pub positions: u64,
+
+    /// Why the backfill stopped before the end of the period, if it did
+    pub error: Option<String>,
+}
+
+impl BackfillStatus {
+    /// Create a backfill status shared between request handlers
+    pub fn shared() -> SharedBackfill {
+        Arc::new(Mutex::new(BackfillStatus::default()))
+    }
+
+    /// Start the backfill of a period, returns false if one is running
+    pub fn start(&mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> bool {
+        if self.running {
+            return false;
+        }
+
+        *self = BackfillStatus {
+            running: true,
+            from: Some(from),
+            to: Some(to),
+            ..Default::default()
+        };
+
+        true
+    }
+
+    /// Count the packets read and the positions pushed
+    pub fn record(&mut self, packets: u64, positions: u64) {
+        self.packets += packets;
+        self.positions += positions;
+    }
+
+    /// Stop the backfill, with the error which interrupted it if any
+    pub fn finish(&mut self, error: Option<String>) {
+        self.running = false;
+        self.error = error;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lib_common::time::Duration;
+
+    fn payload(hex: &str) -> [u8; ADSB_SIZE_BYTES] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_replay() {
+        // https://mode-s.org/decode/content/ads-b/3-airborne-position.html
+        let odd = payload("8D40621D58C386435CC412692AD6");
+        let even = payload("8D40621D58C382D690C8AC2863A7");
+        let identifiers = IdentifierRules::new("");
+        let received = Utc::now();
+        let later = received + Duration::try_milliseconds(2000).unwrap();
+
+        // even messages are decoded once paired with an odd message
+        let mut replay = PositionReplay::default();
+        assert!(replay.replay(&even, received, &identifiers).is_none());
+        assert!(replay.replay(&odd, received, &identifiers).is_none());
+        let position = replay.replay(&even, later, &identifiers).unwrap();
+        assert_eq!(position.identifier, identifiers.icao(0x40621d));
+        assert_eq!(position.timestamp_network, later);
+        assert!((position.position.latitude - 52.2572).abs() < 0.0001);
+        assert!((position.position.longitude - 3.91937).abs() < 0.0001);
+
+        // the odd message is too old to pair with
+        let stale = received + Duration::try_milliseconds(5000).unwrap();
+        assert!(replay.replay(&even, stale, &identifiers).is_none());
+
+        // identification messages have no position
+        let identification = payload("8D4840D6202CC371C32CE0576098");
+        assert!(replay
+            .replay(&identification, later, &identifiers)
+            .is_none());
+    }
+
+    #[test]
+    fn test_status() {
+        let shared = BackfillStatus::shared();
+        let mut status = shared.lock().unwrap();
+        let from = Utc::now();
+        let to = from + Duration::try_hours(1).unwrap();
+
+        assert!(status.start(from, to));
+        assert!(!status.start(from, to));
+        status.record(10, 2);
+        status.record(5, 1);
+        assert_eq!((status.packets, status.positions), (15, 3));
+
+        status.finish(Some("svc-storage unavailable".to_string()));
+        assert!(!status.running);
+        assert!(status.start(from, to));
+        assert_eq!(status.packets, 0);
+        assert!(status.error.is_none());
+    }
+}
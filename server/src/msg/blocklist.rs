@@ -0,0 +1,206 @@
+//! Blocklist of aircraft whose telemetry is rejected
+//!
+//! Known spoofers, or test devices reporting in production, are rejected
+//!  at ingest by their ICAO address (in hex) or Remote ID identifier.
+//!  Aircraft are blocked by the configuration, or through the admin API
+//!  in a cache hash shared by all instances. Each instance screens
+//!  packets against a copy of that hash read at most
+//!  [`CACHED_ENTRIES_TTL_MS`] before, so an aircraft blocked on another
+//!  instance is rejected everywhere within that time.
+
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use utoipa::ToSchema;
+
+/// Cache key of the hash of the aircraft blocked through the admin API,
+///  by identifier
+pub const BLOCKLIST_KEY: &str = "blocklist";
+
+/// Reason of the aircraft blocked by the configuration
+pub const CONFIGURED_REASON: &str = "listed in BLOCKLIST";
+
+/// Time a copy of the blocklist hash is screened against before it is
+///  read again from the cache
+pub const CACHED_ENTRIES_TTL_MS: i64 = 5_000;
+
+/// Blocked aircraft
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BlocklistEntry {
+    /// Identifier of the aircraft, as listed in the blocklist
+    #[schema(example = "a1b2c3")]
+    pub identifier: String,
+
+    /// Why the aircraft is blocked
+    #[schema(example = "spoofed positions")]
+    pub reason: String,
+
+    /// When the aircraft was blocked, none if blocked by the configuration
+    pub added_at: Option<DateTime<Utc>>,
+}
+
+/// Identifiers are matched regardless of case and surrounding whitespace
+pub fn normalize(identifier: &str) -> String {
+    identifier.trim().to_lowercase()
+}
+
+/// Aircraft blocked by the configuration, and the copy of the blocklist
+///  hash last read from the cache
+#[derive(Debug, Default)]
+pub struct Blocklist {
+    /// Blocked identifiers
+    identifiers: HashSet<String>,
+
+    /// When the blocklist hash was last read, and its entries
+    cached: Mutex<Option<(DateTime<Utc>, HashMap<String, String>)>>,
+}
+
+impl Blocklist {
+    /// Create a blocklist from a comma separated list of identifiers
+    pub fn new(identifiers: &str) -> Self {
+        Blocklist {
+            identifiers: identifiers
+                .split(',')
+                .map(normalize)
+                .filter(|identifier| !identifier.is_empty())
+                .collect(),
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// If an aircraft is blocked by the configuration
+    pub fn contains(&self, identifier: &str) -> bool {
+        self.identifiers.contains(&normalize(identifier))
+    }
+
+    /// Why an aircraft is blocked in the copy of the blocklist hash, if
+    ///  it is, or none if that copy is missing or expired at `now`
+    pub fn cached_reason(&self, identifier: &str, now: DateTime<Utc>) -> Option<Option<String>> {
+        let cached = self.cached.lock().ok()?;
+        let (read_at, entries) = cached.as_ref()?;
+        let ttl = Duration::try_milliseconds(CACHED_ENTRIES_TTL_MS).unwrap_or(Duration::zero());
+        if now < *read_at || now - *read_at >= ttl {
+            return None;
+        }
+
+        Some(
+            entries
+                .get(&normalize(identifier))
+                .map(|value| reason(value)),
+        )
+    }
+
+    /// Keeps a copy of the blocklist hash, read at `now`
+    pub fn cache(&self, entries: HashMap<String, String>, now: DateTime<Utc>) {
+        if let Ok(mut cached) = self.cached.lock() {
+            *cached = Some((now, entries));
+        }
+    }
+
+    /// Drops the copy of the blocklist hash, once changed by this instance
+    pub fn invalidate(&self) {
+        if let Ok(mut cached) = self.cached.lock() {
+            *cached = None;
+        }
+    }
+
+    /// Entries of the configured aircraft and of those blocked in the
+    ///  cache hash, sorted by identifier
+    ///
+    /// Cached entries which can't be parsed are skipped, configured
+    ///  entries take precedence.
+    pub fn entries(&self, cached: &HashMap<String, String>) -> Vec<BlocklistEntry> {
+        let configured = self.identifiers.iter().map(|identifier| BlocklistEntry {
+            identifier: identifier.clone(),
+            reason: CONFIGURED_REASON.to_string(),
+            added_at: None,
+        });
+
+        let cached = cached
+            .iter()
+            .filter(|(identifier, _)| !self.identifiers.contains(*identifier))
+            .filter_map(|(_, value)| serde_json::from_str::<BlocklistEntry>(value).ok());
+
+        let mut entries: Vec<BlocklistEntry> = configured.chain(cached).collect();
+        entries.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+        entries
+    }
+}
+
+/// Reason of a blocklist hash value, empty if it can't be parsed
+pub fn reason(value: &str) -> String {
+    serde_json::from_str::<BlocklistEntry>(value)
+        .map(|entry| entry.reason)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(entry: &BlocklistEntry) -> (String, String) {
+        (
+            entry.identifier.clone(),
+            serde_json::to_string(entry).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_contains() {
+        let blocklist = Blocklist::new(" A1B2C3,, test-drone ");
+        assert!(blocklist.contains("a1b2c3"));
+        assert!(blocklist.contains(" TEST-DRONE"));
+        assert!(!blocklist.contains("ffffff"));
+        assert!(!blocklist.contains(""));
+        assert!(!Blocklist::default().contains("a1b2c3"));
+    }
+
+    #[test]
+    fn test_cached_reason() {
+        let blocklist = Blocklist::default();
+        let now = Utc::now();
+        assert_eq!(blocklist.cached_reason("b2c3d4", now), None);
+
+        let spoofer = BlocklistEntry {
+            identifier: "b2c3d4".to_string(),
+            reason: "spoofed positions".to_string(),
+            added_at: Some(now),
+        };
+        blocklist.cache([cached(&spoofer)].into(), now);
+        let reason = blocklist.cached_reason(" B2C3D4", now);
+        assert_eq!(reason, Some(Some("spoofed positions".to_string())));
+        assert_eq!(blocklist.cached_reason("a1b2c3", now), Some(None));
+
+        let expired = now + Duration::try_milliseconds(CACHED_ENTRIES_TTL_MS).unwrap();
+        assert_eq!(blocklist.cached_reason("b2c3d4", expired), None);
+
+        blocklist.invalidate();
+        assert_eq!(blocklist.cached_reason("b2c3d4", now), None);
+    }
+
+    #[test]
+    fn test_entries() {
+        let blocklist = Blocklist::new("test-drone,a1b2c3");
+        let spoofer = BlocklistEntry {
+            identifier: "b2c3d4".to_string(),
+            reason: "spoofed positions".to_string(),
+            added_at: Some(Utc::now()),
+        };
+        let duplicate = BlocklistEntry {
+            identifier: "a1b2c3".to_string(),
+            reason: "duplicate".to_string(),
+            added_at: Some(Utc::now()),
+        };
+
+        let mut hash: HashMap<String, String> = [cached(&spoofer), cached(&duplicate)].into();
+        hash.insert("c3d4e5".to_string(), "invalid".to_string());
+
+        let entries = blocklist.entries(&hash);
+        let identifiers: Vec<&str> = entries.iter().map(|e| e.identifier.as_str()).collect();
+        assert_eq!(identifiers, vec!["a1b2c3", "b2c3d4", "test-drone"]);
+        assert_eq!(entries[0].reason, CONFIGURED_REASON);
+        assert_eq!(entries[0].added_at, None);
+        assert_eq!(entries[1], spoofer);
+    }
+}
@@ -0,0 +1,291 @@
+//! Command and control (C2) link monitoring
+//!
+//! Aircraft flown beyond visual line of sight report the state of their
+//!  C2 link. A link is lost when the aircraft reports no active link, or
+//!  stops reporting for longer than the configured timeout. Each loss is
+//!  alerted once, until the aircraft reports an active link again.
+
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use utoipa::ToSchema;
+
+/// C2 link monitor shared between request handlers
+pub type SharedC2Links = Arc<Mutex<C2LinkMonitor>>;
+
+/// Type of the active C2 link
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum C2LinkType {
+    /// No active link, the aircraft flies its lost link procedure
+    None,
+
+    /// Direct radio link
+    Radio,
+
+    /// Cellular network (LTE, 5G)
+    Cellular,
+
+    /// Satellite link
+    Satellite,
+
+    /// Any other link
+    Other,
+}
+
+/// State of the C2 link reported by an aircraft
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct C2LinkReport {
+    /// Type of the active link
+    pub link_type: C2LinkType,
+
+    /// Received signal strength in dBm
+    #[serde(default)]
+    #[schema(example = -78.0)]
+    pub rssi_dbm: Option<f32>,
+
+    /// Round trip latency in milliseconds
+    #[serde(default)]
+    #[schema(example = 120)]
+    pub latency_ms: Option<u32>,
+
+    /// Share of packets received, in percent
+    #[serde(default)]
+    pub link_quality_percent: Option<u8>,
+
+    /// When the aircraft measured the link
+    #[serde(default)]
+    pub timestamp_asset: Option<DateTime<Utc>>,
+}
+
+/// C2 link state of an aircraft, as published and cached
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct C2LinkStatus {
+    /// Identifier of the aircraft, from its token
+    pub identifier: String,
+
+    /// The reported link state
+    #[serde(flatten)]
+    pub report: C2LinkReport,
+
+    /// When the report was received
+    pub timestamp_network: DateTime<Utc>,
+}
+
+/// Why a C2 link was declared lost
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum C2LossCause {
+    /// The aircraft reported no active link
+    NoLink,
+
+    /// The aircraft stopped reporting its link
+    Timeout,
+}
+
+/// Loss of the C2 link of an aircraft, alerted once per loss
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct C2LinkLoss {
+    /// Identifier of the aircraft
+    pub identifier: String,
+
+    /// Why the link was declared lost
+    pub cause: C2LossCause,
+
+    /// When the last report of the aircraft was received
+    pub last_report: DateTime<Utc>,
+
+    /// When the loss was detected
+    pub timestamp_network: DateTime<Utc>,
+}
+
+/// Last report of a monitored aircraft
+#[derive(Debug, Clone, Copy)]
+struct LinkState {
+    /// When the last report was received
+    last_report: DateTime<Utc>,
+
+    /// If the loss of the link was already alerted
+    lost: bool,
+}
+
+/// Detects the loss of C2 links per aircraft
+#[derive(Debug)]
+pub struct C2LinkMonitor {
+    /// Links not reported for this long are lost
+    timeout: Duration,
+
+    /// Last report of each monitored aircraft
+    links: HashMap<String, LinkState>,
+}
+
+impl C2LinkMonitor {
+    /// Create a monitor declaring links lost after the given timeout
+    pub fn new(timeout_ms: u32) -> Self {
+        C2LinkMonitor {
+            timeout: Duration::try_milliseconds(timeout_ms as i64).unwrap_or(Duration::zero()),
+            links: HashMap::new(),
+        }
+    }
+
+    /// Create a monitor shared between request handlers
+    pub fn shared(timeout_ms: u32) -> SharedC2Links {
+        Arc::new(Mutex::new(C2LinkMonitor::new(timeout_ms)))
+    }
+
+    /// Record a link report
+    ///
+    /// Returns the loss to alert if the aircraft newly reports no active link.
+    pub fn report(&mut self, status: &C2LinkStatus) -> Option<C2LinkLoss> {
+        let no_link = status.report.link_type == C2LinkType::None;
+        let previous = self.links.insert(
+            status.identifier.clone(),
+            LinkState {
+                last_report: status.timestamp_network,
+                lost: no_link,
+            },
+        );
+
+        let alerted = previous.is_some_and(|state| state.lost);
+        (no_link && !alerted).then(|| C2LinkLoss {
+            identifier: status.identifier.clone(),
+            cause: C2LossCause::NoLink,
+            last_report: status.timestamp_network,
+            timestamp_network: status.timestamp_network,
+        })
+    }
+
+    /// Record a heartbeat, the aircraft being online without reporting
+    ///  its link
+    ///
+    /// Aircraft are monitored from their first heartbeat. A link declared
+    ///  lost stays lost until an active link is reported.
+    pub fn heartbeat(&mut self, identifier: &str, now: DateTime<Utc>) {
+        self.links
+            .entry(identifier.to_string())
+            .and_modify(|state| state.last_report = state.last_report.max(now))
+            .or_insert(LinkState {
+                last_report: now,
+                lost: false,
+            });
+    }
+
+    /// Forget the aircraft which stopped reporting
+    ///
+    /// Returns the losses to alert, for aircraft whose link wasn't already
+    ///  declared lost. Aircraft reporting again are monitored anew.
+    pub fn expire(&mut self, now: DateTime<Utc>) -> Vec<C2LinkLoss> {
+        let mut losses = vec![];
+        self.links.retain(|identifier, state| {
+            if now - state.last_report < self.timeout {
+                return true;
+            }
+
+            if !state.lost {
+                losses.push(C2LinkLoss {
+                    identifier: identifier.clone(),
+                    cause: C2LossCause::Timeout,
+                    last_report: state.last_report,
+                    timestamp_network: now,
+                });
+            }
+
+            false
+        });
+
+        losses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(link_type: C2LinkType, timestamp_network: DateTime<Utc>) -> C2LinkStatus {
+        C2LinkStatus {
+            identifier: "drone-1".to_string(),
+            report: C2LinkReport {
+                link_type,
+                rssi_dbm: Some(-78.0),
+                latency_ms: Some(120),
+                link_quality_percent: None,
+                timestamp_asset: None,
+            },
+            timestamp_network,
+        }
+    }
+
+    #[test]
+    fn test_report_no_link() {
+        let now = Utc::now();
+        let mut monitor = C2LinkMonitor::new(10_000);
+        assert!(monitor.report(&status(C2LinkType::Cellular, now)).is_none());
+
+        // alerted once until an active link is reported again
+        let loss = monitor.report(&status(C2LinkType::None, now)).unwrap();
+        assert_eq!(loss.identifier, "drone-1");
+        assert_eq!(loss.cause, C2LossCause::NoLink);
+        assert!(monitor.report(&status(C2LinkType::None, now)).is_none());
+
+        assert!(monitor
+            .report(&status(C2LinkType::Satellite, now))
+            .is_none());
+        assert!(monitor.report(&status(C2LinkType::None, now)).is_some());
+    }
+
+    #[test]
+    fn test_expire() {
+        let now = Utc::now();
+        let mut monitor = C2LinkMonitor::new(10_000);
+        monitor.report(&status(C2LinkType::Radio, now));
+        assert!(monitor.expire(now + Duration::seconds(9)).is_empty());
+
+        let later = now + Duration::seconds(10);
+        let losses = monitor.expire(later);
+        assert_eq!(losses.len(), 1);
+        assert_eq!(losses[0].cause, C2LossCause::Timeout);
+        assert_eq!(losses[0].last_report, now);
+        assert_eq!(losses[0].timestamp_network, later);
+
+        // forgotten once expired
+        assert!(monitor.expire(later + Duration::seconds(10)).is_empty());
+
+        // links already declared lost are not alerted again
+        monitor.report(&status(C2LinkType::None, now));
+        assert!(monitor.expire(later).is_empty());
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let now = Utc::now();
+        let mut monitor = C2LinkMonitor::new(10_000);
+
+        // heartbeats postpone the timeout
+        monitor.heartbeat("drone-1", now);
+        monitor.heartbeat("drone-1", now + Duration::seconds(5));
+        assert!(monitor.expire(now + Duration::seconds(14)).is_empty());
+
+        let losses = monitor.expire(now + Duration::seconds(15));
+        assert_eq!(losses.len(), 1);
+        assert_eq!(losses[0].last_report, now + Duration::seconds(5));
+
+        // but don't restore a lost link
+        monitor.report(&status(C2LinkType::None, now));
+        monitor.heartbeat("drone-1", now + Duration::seconds(1));
+        assert!(monitor.report(&status(C2LinkType::None, now)).is_none());
+        assert!(monitor.expire(now + Duration::seconds(20)).is_empty());
+    }
+
+    #[test]
+    fn test_status_json() {
+        let value = serde_json::to_value(status(C2LinkType::Cellular, Utc::now())).unwrap();
+        assert_eq!(value["identifier"], "drone-1");
+        assert_eq!(value["link_type"], "cellular");
+        assert_eq!(value["latency_ms"], 120);
+
+        let report: C2LinkReport = serde_json::from_str(r#"{"link_type": "none"}"#).unwrap();
+        assert_eq!(report.link_type, C2LinkType::None);
+        assert_eq!(report.rssi_dbm, None);
+    }
+}
@@ -0,0 +1,449 @@
+//! Conformance of the aircraft to the corridors of their flight plans
+//!
+//! Aircraft logged in with a flight plan or mission (see
+//!  [`crate::msg::mission`]) are expected to fly within its assigned 4D
+//!  corridor: a polyline of waypoints, with a lateral and vertical
+//!  tolerance and, if the waypoints are timed, a time tolerance. Positions
+//!  outside of it are nonconforming, and alerted once per excursion.
+
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use svc_gis_client_grpc::prelude::types::AircraftPosition;
+
+/// Mean radius of the Earth
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// Conformance monitor shared between the sinks
+pub type SharedConformance = Arc<Mutex<ConformanceMonitor>>;
+
+/// Waypoint of a corridor
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CorridorPoint {
+    /// Latitude in degrees
+    pub latitude: f64,
+
+    /// Longitude in degrees
+    pub longitude: f64,
+
+    /// Altitude in meters
+    pub altitude_meters: f64,
+
+    /// When the aircraft is planned at the waypoint, if timed
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// Corridor assigned to a flight plan or mission
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Corridor {
+    /// Waypoints of the centerline, in flight order
+    pub waypoints: Vec<CorridorPoint>,
+
+    /// Largest horizontal distance to the centerline
+    pub lateral_tolerance_meters: f64,
+
+    /// Largest vertical distance to the centerline
+    pub vertical_tolerance_meters: f64,
+
+    /// Largest difference to the planned time, if the waypoints are timed
+    #[serde(default)]
+    pub time_tolerance_seconds: Option<u32>,
+}
+
+/// How a position deviates from its corridor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Deviation {
+    /// Too far from the centerline horizontally
+    Lateral,
+
+    /// Too far above or below the centerline
+    Vertical,
+
+    /// Ahead of the planned time
+    Early,
+
+    /// Behind the planned time
+    Late,
+}
+
+/// Position of an aircraft outside the corridor of its flight plan
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Nonconformance {
+    /// Identifier of the aircraft
+    pub identifier: String,
+
+    /// Flight plan or mission declared by the aircraft
+    pub mission: String,
+
+    /// How the position deviates from the corridor
+    pub deviations: Vec<Deviation>,
+
+    /// Horizontal distance to the centerline
+    pub lateral_distance_meters: f64,
+
+    /// Vertical distance to the centerline, positive above
+    pub vertical_distance_meters: f64,
+
+    /// Seconds ahead (negative) or behind (positive) the planned time, if
+    ///  the corridor is timed
+    pub time_offset_seconds: Option<f64>,
+
+    /// Latitude of the position in degrees
+    pub latitude: f64,
+
+    /// Longitude of the position in degrees
+    pub longitude: f64,
+
+    /// Altitude of the position in meters
+    pub altitude_meters: f64,
+
+    /// When the position was received
+    pub timestamp_network: DateTime<Utc>,
+}
+
+/// Closest point of the centerline to a position
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Closest {
+    /// Horizontal distance in meters
+    lateral: f64,
+
+    /// Vertical distance in meters, positive above
+    vertical: f64,
+
+    /// Seconds behind the planned time, if both waypoints are timed
+    time_offset: Option<f64>,
+}
+
+/// Coordinates in meters of a waypoint, east and north of a position
+fn project(point: &CorridorPoint, latitude: f64, longitude: f64) -> (f64, f64) {
+    let east = (point.longitude - longitude).to_radians() * latitude.to_radians().cos();
+    let north = (point.latitude - latitude).to_radians();
+    (east * EARTH_RADIUS_METERS, north * EARTH_RADIUS_METERS)
+}
+
+impl Corridor {
+    /// Closest point of a segment to a position, at the origin of the
+    ///  projection
+    fn closest_on_segment(
+        from: &CorridorPoint,
+        to: &CorridorPoint,
+        position: &AircraftPosition,
+    ) -> Closest {
+        let (latitude, longitude) = (position.position.latitude, position.position.longitude);
+        let (x1, y1) = project(from, latitude, longitude);
+        let (x2, y2) = project(to, latitude, longitude);
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let length = dx * dx + dy * dy;
+        let ratio = match length > 0.0 {
+            true => (-(x1 * dx + y1 * dy) / length).clamp(0.0, 1.0),
+            false => 0.0,
+        };
+
+        let (x, y) = (x1 + ratio * dx, y1 + ratio * dy);
+        let altitude = from.altitude_meters + ratio * (to.altitude_meters - from.altitude_meters);
+        let time_offset = from.timestamp.zip(to.timestamp).map(|(start, end)| {
+            let planned = (end - start).num_milliseconds() as f64 * ratio;
+            let actual = (position.timestamp_network - start).num_milliseconds() as f64;
+            (actual - planned) / 1000.0
+        });
+
+        Closest {
+            lateral: x.hypot(y),
+            vertical: position.position.altitude_meters - altitude,
+            time_offset,
+        }
+    }
+
+    /// Closest point of the centerline to a position, None without
+    ///  waypoints
+    fn closest(&self, position: &AircraftPosition) -> Option<Closest> {
+        let segments: Vec<(&CorridorPoint, &CorridorPoint)> = match self.waypoints.as_slice() {
+            [] => return None,
+            [point] => vec![(point, point)],
+            points => points.windows(2).map(|pair| (&pair[0], &pair[1])).collect(),
+        };
+
+        segments
+            .into_iter()
+            .map(|(from, to)| Corridor::closest_on_segment(from, to, position))
+            .min_by(|a, b| a.lateral.total_cmp(&b.lateral))
+    }
+
+    /// How a position deviates from the corridor, None if it conforms
+    pub fn check(
+        &self,
+        identifier: &str,
+        mission: &str,
+        position: &AircraftPosition,
+    ) -> Option<Nonconformance> {
+        let closest = self.closest(position)?;
+        let mut deviations = vec![];
+        if closest.lateral > self.lateral_tolerance_meters {
+            deviations.push(Deviation::Lateral);
+        }
+
+        if closest.vertical.abs() > self.vertical_tolerance_meters {
+            deviations.push(Deviation::Vertical);
+        }
+
+        let time_offset = self.time_tolerance_seconds.and(closest.time_offset);
+        if let Some((offset, tolerance)) = time_offset.zip(self.time_tolerance_seconds) {
+            match offset {
+                offset if offset < -(tolerance as f64) => deviations.push(Deviation::Early),
+                offset if offset > tolerance as f64 => deviations.push(Deviation::Late),
+                _ => (),
+            }
+        }
+
+        (!deviations.is_empty()).then(|| Nonconformance {
+            identifier: identifier.to_string(),
+            mission: mission.to_string(),
+            deviations,
+            lateral_distance_meters: closest.lateral,
+            vertical_distance_meters: closest.vertical,
+            time_offset_seconds: time_offset,
+            latitude: position.position.latitude,
+            longitude: position.position.longitude,
+            altitude_meters: position.position.altitude_meters,
+            timestamp_network: position.timestamp_network,
+        })
+    }
+}
+
+/// Corridor of a mission as last fetched
+#[derive(Debug, Clone)]
+struct CachedCorridor {
+    /// The corridor, None if the mission has none
+    corridor: Option<Arc<Corridor>>,
+
+    /// When it was fetched
+    fetched: DateTime<Utc>,
+}
+
+/// Checks the positions of aircraft against the corridors of their
+///  missions, keeping the fetched corridors for a while
+#[derive(Debug)]
+pub struct ConformanceMonitor {
+    /// Corridors are fetched again after this long
+    refresh: Duration,
+
+    /// Corridor of each mission
+    corridors: HashMap<String, CachedCorridor>,
+
+    /// Aircraft and missions outside their corridor, already alerted
+    nonconforming: HashSet<(String, String)>,
+}
+
+impl ConformanceMonitor {
+    /// Create a monitor fetching corridors again after `refresh_ms`
+    pub fn new(refresh_ms: u32) -> Self {
+        ConformanceMonitor {
+            refresh: Duration::try_milliseconds(refresh_ms as i64).unwrap_or(Duration::zero()),
+            corridors: HashMap::new(),
+            nonconforming: HashSet::new(),
+        }
+    }
+
+    /// Create a monitor shared between the sinks
+    pub fn shared(refresh_ms: u32) -> SharedConformance {
+        Arc::new(Mutex::new(ConformanceMonitor::new(refresh_ms)))
+    }
+
+    /// Corridor of a mission fetched less than the refresh interval ago,
+    ///  None if it must be fetched
+    pub fn corridor(&self, mission: &str, now: DateTime<Utc>) -> Option<Option<Arc<Corridor>>> {
+        self.corridors
+            .get(mission)
+            .filter(|cached| now - cached.fetched < self.refresh)
+            .map(|cached| cached.corridor.clone())
+    }
+
+    /// Keep the fetched corridor of a mission, None if it has none,
+    ///  forgetting the corridors due for a refresh
+    pub fn store(&mut self, mission: &str, corridor: Option<Corridor>, now: DateTime<Utc>) {
+        let refresh = self.refresh;
+        self.corridors
+            .retain(|_, cached| now - cached.fetched < refresh);
+        self.corridors.insert(
+            mission.to_string(),
+            CachedCorridor {
+                corridor: corridor.map(Arc::new),
+                fetched: now,
+            },
+        );
+    }
+
+    /// Check a position against the corridor of its mission
+    ///
+    /// Returns the nonconformance to alert if the aircraft newly left its
+    ///  corridor. Aircraft returning to their corridor are alerted again
+    ///  when they leave it.
+    pub fn observe(
+        &mut self,
+        identifier: &str,
+        mission: &str,
+        corridor: &Corridor,
+        position: &AircraftPosition,
+    ) -> Option<Nonconformance> {
+        let key = (identifier.to_string(), mission.to_string());
+        match corridor.check(identifier, mission, position) {
+            Some(nonconformance) => self.nonconforming.insert(key).then_some(nonconformance),
+            None => {
+                self.nonconforming.remove(&key);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use svc_gis_client_grpc::prelude::types::Position;
+
+    /// Corridor heading north from (52.0, 4.0) for about 1.1 km, climbing
+    ///  from 100 to 200 meters in 100 seconds
+    fn corridor(start: DateTime<Utc>) -> Corridor {
+        Corridor {
+            waypoints: vec![
+                CorridorPoint {
+                    latitude: 52.0,
+                    longitude: 4.0,
+                    altitude_meters: 100.0,
+                    timestamp: Some(start),
+                },
+                CorridorPoint {
+                    latitude: 52.01,
+                    longitude: 4.0,
+                    altitude_meters: 200.0,
+                    timestamp: Some(start + Duration::try_seconds(100).unwrap()),
+                },
+            ],
+            lateral_tolerance_meters: 50.0,
+            vertical_tolerance_meters: 20.0,
+            time_tolerance_seconds: Some(30),
+        }
+    }
+
+    fn position(
+        latitude: f64,
+        longitude: f64,
+        altitude_meters: f64,
+        at: DateTime<Utc>,
+    ) -> AircraftPosition {
+        AircraftPosition {
+            identifier: "drone-1".to_string(),
+            position: Position {
+                latitude,
+                longitude,
+                altitude_meters,
+            },
+            timestamp_network: at,
+            timestamp_asset: None,
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let start = Utc::now();
+        let middle = start + Duration::try_seconds(50).unwrap();
+        let corridor = corridor(start);
+
+        // halfway, on the centerline
+        let inside = position(52.005, 4.0, 150.0, middle);
+        assert_eq!(corridor.check("drone-1", "m", &inside), None);
+
+        // about 68 meters east
+        let east = position(52.005, 4.001, 150.0, middle);
+        let nonconformance = corridor.check("drone-1", "m", &east).unwrap();
+        assert_eq!(nonconformance.deviations, vec![Deviation::Lateral]);
+        assert!((nonconformance.lateral_distance_meters - 68.5).abs() < 1.0);
+
+        let high = position(52.005, 4.0, 180.0, middle);
+        let nonconformance = corridor.check("drone-1", "m", &high).unwrap();
+        assert_eq!(nonconformance.deviations, vec![Deviation::Vertical]);
+        assert!((nonconformance.vertical_distance_meters - 30.0).abs() < 0.1);
+
+        let late = position(
+            52.005,
+            4.0,
+            150.0,
+            middle + Duration::try_seconds(40).unwrap(),
+        );
+        let nonconformance = corridor.check("drone-1", "m", &late).unwrap();
+        assert_eq!(nonconformance.deviations, vec![Deviation::Late]);
+        assert!((nonconformance.time_offset_seconds.unwrap() - 40.0).abs() < 0.1);
+
+        let early = position(52.005, 4.0, 150.0, start);
+        let nonconformance = corridor.check("drone-1", "m", &early).unwrap();
+        assert_eq!(nonconformance.deviations, vec![Deviation::Early]);
+
+        // beyond the last waypoint
+        let beyond = position(
+            52.02,
+            4.0,
+            200.0,
+            start + Duration::try_seconds(100).unwrap(),
+        );
+        let nonconformance = corridor.check("drone-1", "m", &beyond).unwrap();
+        assert_eq!(nonconformance.deviations, vec![Deviation::Lateral]);
+
+        // untimed corridors
+        let untimed = Corridor {
+            time_tolerance_seconds: None,
+            ..corridor.clone()
+        };
+        assert_eq!(untimed.check("drone-1", "m", &late), None);
+
+        let empty = Corridor {
+            waypoints: vec![],
+            ..corridor
+        };
+        assert_eq!(empty.check("drone-1", "m", &east), None);
+    }
+
+    #[test]
+    fn test_monitor() {
+        let now = Utc::now();
+        let corridor = corridor(now);
+        let mut monitor = ConformanceMonitor::new(60_000);
+        assert_eq!(monitor.corridor("m", now), None);
+
+        monitor.store("m", Some(corridor.clone()), now);
+        monitor.store("other", None, now);
+        assert_eq!(
+            monitor.corridor("m", now).unwrap().as_deref(),
+            Some(&corridor)
+        );
+        assert_eq!(monitor.corridor("other", now), Some(None));
+
+        // fetched again once due
+        let later = now + Duration::try_seconds(60).unwrap();
+        assert_eq!(monitor.corridor("m", later), None);
+        monitor.store("m", None, later);
+        assert_eq!(monitor.corridor("other", later), None);
+
+        // alerted once per excursion
+        let middle = now + Duration::try_seconds(50).unwrap();
+        let outside = position(52.005, 4.001, 150.0, middle);
+        let inside = position(52.005, 4.0, 150.0, middle);
+        assert!(monitor
+            .observe("drone-1", "m", &corridor, &outside)
+            .is_some());
+        assert!(monitor
+            .observe("drone-1", "m", &corridor, &outside)
+            .is_none());
+        assert!(monitor
+            .observe("drone-2", "m", &corridor, &outside)
+            .is_some());
+        assert!(monitor
+            .observe("drone-1", "m", &corridor, &inside)
+            .is_none());
+        assert!(monitor
+            .observe("drone-1", "m", &corridor, &outside)
+            .is_some());
+    }
+}
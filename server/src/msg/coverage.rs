@@ -0,0 +1,418 @@
+//! Coverage of the receivers, aggregated per geohash cell
+//!
+//! Positions decoded from packets whose receiver declared its location are
+//!  binned into geohash cells, per receiver, with the signal strength and
+//!  signal to noise ratio they were received with. Cells where a receiver
+//!  observes few aircraft, or only weak signals, show where coverage is
+//!  weak. Receivers are identified by the geohash of their location.
+
+use crate::amqp::envelope::SignalMetadata;
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Coverage map shared between the sinks and request handlers
+pub type SharedCoverage = Arc<Mutex<CoverageMap>>;
+
+/// Characters of a geohash, each encoding 5 bits
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Longest geohash handled, cells of a few centimeters
+pub const GEOHASH_MAX_PRECISION: usize = 12;
+
+/// Precision of the geohash identifying a receiver, about 40 meters
+const RECEIVER_PRECISION: usize = 8;
+
+/// Cells not observed for an hour are dropped
+const CELL_EXPIRE_HOURS: i64 = 1;
+
+/// Geohash of a location, with `precision` characters
+pub fn geohash(latitude: f64, longitude: f64, precision: usize) -> String {
+    let mut latitude_range = (-90.0, 90.0);
+    let mut longitude_range = (-180.0, 180.0);
+    let mut hash = String::with_capacity(precision);
+    let mut even = true;
+    let mut bits = 0;
+    let mut index = 0;
+
+    while hash.len() < precision.min(GEOHASH_MAX_PRECISION) {
+        let (range, value) = match even {
+            true => (&mut longitude_range, longitude),
+            false => (&mut latitude_range, latitude),
+        };
+
+        let middle = (range.0 + range.1) / 2.0;
+        index <<= 1;
+        if value >= middle {
+            index |= 1;
+            range.0 = middle;
+        } else {
+            range.1 = middle;
+        }
+
+        even = !even;
+        bits += 1;
+        if bits == 5 {
+            hash.push(GEOHASH_ALPHABET[index] as char);
+            bits = 0;
+            index = 0;
+        }
+    }
+
+    hash
+}
+
+/// Bounds of a geohash cell in degrees, as
+///  `(min_latitude, min_longitude, max_latitude, max_longitude)`
+pub fn geohash_bounds(hash: &str) -> Option<(f64, f64, f64, f64)> {
+    let mut latitude_range = (-90.0, 90.0);
+    let mut longitude_range = (-180.0, 180.0);
+    let mut even = true;
+
+    for c in hash.bytes() {
+        let index = GEOHASH_ALPHABET.iter().position(|&a| a == c)?;
+        for bit in (0..5).rev() {
+            let range = match even {
+                true => &mut longitude_range,
+                false => &mut latitude_range,
+            };
+
+            let middle = (range.0 + range.1) / 2.0;
+            if index & (1 << bit) != 0 {
+                range.0 = middle;
+            } else {
+                range.1 = middle;
+            }
+
+            even = !even;
+        }
+    }
+
+    Some((
+        latitude_range.0,
+        longitude_range.0,
+        latitude_range.1,
+        longitude_range.1,
+    ))
+}
+
+/// Running mean of a signal value
+#[derive(Debug, Clone, Copy, Default)]
+struct Mean {
+    /// Sum of the values
+    sum: f64,
+
+    /// Number of values
+    count: u64,
+}
+
+impl Mean {
+    /// Add a value, if known
+    fn add(&mut self, value: Option<f32>) {
+        if let Some(value) = value {
+            self.sum += value as f64;
+            self.count += 1;
+        }
+    }
+
+    /// Add the values of another mean
+    fn merge(&mut self, other: &Mean) {
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+
+    /// The mean, if any value was added
+    fn value(&self) -> Option<f32> {
+        (self.count > 0).then(|| (self.sum / self.count as f64) as f32)
+    }
+}
+
+/// Observations of a receiver in a cell
+#[derive(Debug, Clone, Copy)]
+struct CellStats {
+    /// Number of positions observed
+    observations: u64,
+
+    /// Received signal strength in dBm
+    rssi_dbm: Mean,
+
+    /// Signal to noise ratio in dB
+    snr_db: Mean,
+
+    /// When the last position was observed
+    last_observed: DateTime<Utc>,
+}
+
+/// Observations of a receiver in a cell, as reported
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoverageCell {
+    /// Geohash of the receiver location
+    pub receiver: String,
+
+    /// Geohash of the cell
+    pub geohash: String,
+
+    /// Number of positions observed
+    pub observations: u64,
+
+    /// Mean received signal strength in dBm, if declared
+    pub rssi_dbm_mean: Option<f32>,
+
+    /// Mean signal to noise ratio in dB, if declared
+    pub snr_db_mean: Option<f32>,
+
+    /// When the last position was observed
+    pub last_observed: DateTime<Utc>,
+}
+
+/// Coverage of a receiver, published periodically
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoverageSummary {
+    /// Geohash of the receiver location
+    pub receiver: String,
+
+    /// Number of cells where the receiver observed positions
+    pub cells: usize,
+
+    /// Number of positions observed
+    pub observations: u64,
+
+    /// Mean received signal strength in dBm, if declared
+    pub rssi_dbm_mean: Option<f32>,
+
+    /// Mean signal to noise ratio in dB, if declared
+    pub snr_db_mean: Option<f32>,
+
+    /// When the summary was made
+    pub timestamp_network: DateTime<Utc>,
+}
+
+/// Observations of each receiver, per geohash cell
+#[derive(Debug)]
+pub struct CoverageMap {
+    /// Length of the geohash of the cells
+    precision: usize,
+
+    /// Observations per receiver and cell
+    cells: HashMap<(String, String), CellStats>,
+}
+
+impl CoverageMap {
+    /// Create a map binning positions into cells of the given precision
+    pub fn new(precision: usize) -> Self {
+        CoverageMap {
+            precision: precision.clamp(1, GEOHASH_MAX_PRECISION),
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Create a map shared between the sinks and request handlers
+    pub fn shared(precision: usize) -> SharedCoverage {
+        Arc::new(Mutex::new(CoverageMap::new(precision)))
+    }
+
+    /// Record a position received with the given signal metadata
+    ///
+    /// Returns false if the receiver didn't declare its location.
+    pub fn record(
+        &mut self,
+        signal: &SignalMetadata,
+        latitude: f64,
+        longitude: f64,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let (Some(receiver_latitude), Some(receiver_longitude)) =
+            (signal.receiver_latitude, signal.receiver_longitude)
+        else {
+            return false;
+        };
+
+        let receiver = geohash(receiver_latitude, receiver_longitude, RECEIVER_PRECISION);
+        let cell = geohash(latitude, longitude, self.precision);
+        let stats = self.cells.entry((receiver, cell)).or_insert(CellStats {
+            observations: 0,
+            rssi_dbm: Mean::default(),
+            snr_db: Mean::default(),
+            last_observed: now,
+        });
+
+        stats.observations += 1;
+        stats.rssi_dbm.add(signal.rssi_dbm);
+        stats.snr_db.add(signal.snr_db);
+        stats.last_observed = now;
+        true
+    }
+
+    /// Drop the cells not observed for an hour
+    pub fn expire(&mut self, now: DateTime<Utc>) {
+        let max_age = Duration::hours(CELL_EXPIRE_HOURS);
+        self.cells
+            .retain(|_, stats| now - stats.last_observed < max_age);
+    }
+
+    /// Observed cells, of a single receiver if given, sorted by receiver
+    ///  and cell
+    pub fn cells(&self, receiver: Option<&str>) -> Vec<CoverageCell> {
+        let mut cells: Vec<CoverageCell> = self
+            .cells
+            .iter()
+            .filter(|((cell_receiver, _), _)| receiver.is_none_or(|r| r == cell_receiver))
+            .map(|((receiver, geohash), stats)| CoverageCell {
+                receiver: receiver.clone(),
+                geohash: geohash.clone(),
+                observations: stats.observations,
+                rssi_dbm_mean: stats.rssi_dbm.value(),
+                snr_db_mean: stats.snr_db.value(),
+                last_observed: stats.last_observed,
+            })
+            .collect();
+
+        cells.sort_by(|a, b| (&a.receiver, &a.geohash).cmp(&(&b.receiver, &b.geohash)));
+        cells
+    }
+
+    /// Coverage of each receiver, sorted by receiver
+    pub fn summaries(&self, now: DateTime<Utc>) -> Vec<CoverageSummary> {
+        let mut receivers: HashMap<&str, (usize, u64, Mean, Mean)> = HashMap::new();
+        for ((receiver, _), stats) in &self.cells {
+            let entry = receivers.entry(receiver).or_default();
+            entry.0 += 1;
+            entry.1 += stats.observations;
+            entry.2.merge(&stats.rssi_dbm);
+            entry.3.merge(&stats.snr_db);
+        }
+
+        let mut summaries: Vec<CoverageSummary> = receivers
+            .into_iter()
+            .map(
+                |(receiver, (cells, observations, rssi_dbm, snr_db))| CoverageSummary {
+                    receiver: receiver.to_string(),
+                    cells,
+                    observations,
+                    rssi_dbm_mean: rssi_dbm.value(),
+                    snr_db_mean: snr_db.value(),
+                    timestamp_network: now,
+                },
+            )
+            .collect();
+
+        summaries.sort_by(|a, b| a.receiver.cmp(&b.receiver));
+        summaries
+    }
+}
+
+/// GeoJSON feature collection of cells, each a polygon whose properties
+///  hold the observations of the receiver
+pub fn geojson(cells: &[CoverageCell]) -> Value {
+    let features: Vec<Value> = cells
+        .iter()
+        .filter_map(|cell| {
+            let (min_latitude, min_longitude, max_latitude, max_longitude) =
+                geohash_bounds(&cell.geohash)?;
+
+            Some(json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [[
+                        [min_longitude, min_latitude],
+                        [max_longitude, min_latitude],
+                        [max_longitude, max_latitude],
+                        [min_longitude, max_latitude],
+                        [min_longitude, min_latitude],
+                    ]],
+                },
+                "properties": cell,
+            }))
+        })
+        .collect();
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(rssi_dbm: Option<f32>) -> SignalMetadata {
+        SignalMetadata {
+            receiver_latitude: Some(57.64911),
+            receiver_longitude: Some(10.40744),
+            rssi_dbm,
+            snr_db: None,
+            broadcaster_mac: None,
+        }
+    }
+
+    #[test]
+    fn test_geohash() {
+        assert_eq!(geohash(57.64911, 10.40744, 11), "u4pruydqqvj");
+        assert_eq!(geohash(-25.382708, -49.265506, 5), "6gkzw");
+        assert_eq!(geohash(0.0, 0.0, 0), "");
+
+        let (min_latitude, min_longitude, max_latitude, max_longitude) =
+            geohash_bounds("u4pru").unwrap();
+        assert!((min_latitude..=max_latitude).contains(&57.64911));
+        assert!((min_longitude..=max_longitude).contains(&10.40744));
+        assert!(max_latitude - min_latitude < 0.05);
+
+        assert_eq!(geohash_bounds("u4a"), None);
+    }
+
+    #[test]
+    fn test_record() {
+        let now = Utc::now();
+        let mut map = CoverageMap::new(5);
+        assert!(!map.record(&SignalMetadata::default(), 57.6, 10.4, now));
+
+        assert!(map.record(&signal(Some(-80.0)), 57.64911, 10.40744, now));
+        assert!(map.record(&signal(Some(-90.0)), 57.64911, 10.40744, now));
+        assert!(map.record(&signal(None), 52.37, 4.9, now));
+
+        let cells = map.cells(None);
+        assert_eq!(cells.len(), 2);
+        assert_eq!(cells[0].receiver, "u4pruydq");
+        assert_eq!(cells[1].geohash, "u4pru");
+        assert_eq!(cells[1].observations, 2);
+        assert_eq!(cells[1].rssi_dbm_mean, Some(-85.0));
+        assert_eq!(cells[0].rssi_dbm_mean, None);
+        assert!(map.cells(Some("u4pruydq")).len() == 2);
+        assert!(map.cells(Some("u1hcvkxk")).is_empty());
+
+        let summaries = map.summaries(now);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].cells, 2);
+        assert_eq!(summaries[0].observations, 3);
+        assert_eq!(summaries[0].rssi_dbm_mean, Some(-85.0));
+
+        map.expire(now + Duration::minutes(59));
+        assert_eq!(map.cells(None).len(), 2);
+        map.expire(now + Duration::hours(1));
+        assert!(map.cells(None).is_empty());
+    }
+
+    #[test]
+    fn test_geojson() {
+        let mut map = CoverageMap::new(5);
+        map.record(&signal(Some(-80.0)), 57.64911, 10.40744, Utc::now());
+
+        let value = geojson(&map.cells(None));
+        assert_eq!(value["type"], "FeatureCollection");
+        let feature = &value["features"][0];
+        assert_eq!(feature["geometry"]["type"], "Polygon");
+        assert_eq!(
+            feature["geometry"]["coordinates"][0]
+                .as_array()
+                .unwrap()
+                .len(),
+            5
+        );
+        assert_eq!(feature["properties"]["geohash"], "u4pru");
+        assert_eq!(feature["properties"]["observations"], 1);
+    }
+}
@@ -0,0 +1,261 @@
+//! Per-aircraft smoothing of decoded velocities
+//!
+//! Remote ID quantizes speeds to 0.25/0.75 m/s and vertical rates to
+//!  0.5 m/s, which makes raw velocities jump between reports. An
+//!  alpha-beta filter tracks each value and its rate of change.
+
+use lib_common::time::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use svc_gis_client_grpc::prelude::types::AircraftVelocity;
+
+/// Filter state is reset after this long without a velocity report
+const FILTER_RESET_MS: i64 = 10000;
+
+/// Number of filtered aircraft above which stale filters are removed
+const FILTER_PRUNE_THRESHOLD: usize = 1024;
+
+/// Filter state shared between request handlers
+pub type SharedFilters = Arc<Mutex<VelocityFilters>>;
+
+/// Alpha-beta filter of a single value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlphaBetaFilter {
+    /// Estimated value
+    value: f64,
+
+    /// Estimated rate of change per second
+    rate: f64,
+}
+
+impl AlphaBetaFilter {
+    /// Start filtering from a first measurement
+    pub fn new(measurement: f64) -> Self {
+        AlphaBetaFilter {
+            value: measurement,
+            rate: 0.0,
+        }
+    }
+
+    /// Current estimate
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Predict the value `seconds` after the last update
+    fn predict(&self, seconds: f64) -> f64 {
+        self.value + self.rate * seconds
+    }
+
+    /// Apply a correction to the prediction
+    fn correct(&mut self, predicted: f64, residual: f64, seconds: f64, alpha: f64, beta: f64) {
+        self.value = predicted + alpha * residual;
+        if seconds > 0.0 {
+            self.rate += beta * residual / seconds;
+        }
+    }
+
+    /// Update with a new measurement taken `seconds` after the last one
+    pub fn update(&mut self, measurement: f64, seconds: f64, alpha: f64, beta: f64) -> f64 {
+        let predicted = self.predict(seconds);
+        self.correct(predicted, measurement - predicted, seconds, alpha, beta);
+        self.value
+    }
+
+    /// Update with a new angle in degrees taken `seconds` after the last one
+    ///  The estimate wraps around at 360 degrees.
+    pub fn update_angle(&mut self, measurement: f64, seconds: f64, alpha: f64, beta: f64) -> f64 {
+        let predicted = self.predict(seconds);
+        let residual = (measurement - predicted + 180.0).rem_euclid(360.0) - 180.0;
+        self.correct(predicted, residual, seconds, alpha, beta);
+        self.value = self.value.rem_euclid(360.0);
+        self.value
+    }
+}
+
+/// Smoothing of the velocity components of one aircraft
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VelocityFilter {
+    /// Horizontal ground speed
+    ground_speed: AlphaBetaFilter,
+
+    /// Vertical rate
+    vertical_speed: AlphaBetaFilter,
+
+    /// Track angle
+    track_angle: AlphaBetaFilter,
+
+    /// Time of the last measurement
+    updated: DateTime<Utc>,
+}
+
+/// Smooths the velocities of each aircraft
+#[derive(Debug)]
+pub struct VelocityFilters {
+    /// Velocities are passed through unchanged if disabled
+    enabled: bool,
+
+    /// Weight of the measurement in the value estimate (0.0 - 1.0)
+    alpha: f64,
+
+    /// Weight of the measurement in the rate estimate (0.0 - 1.0)
+    beta: f64,
+
+    /// Filter of each aircraft
+    filters: HashMap<String, VelocityFilter>,
+}
+
+/// Time of a velocity report, preferring the asset's own clock
+fn report_time(velocity: &AircraftVelocity) -> DateTime<Utc> {
+    velocity
+        .timestamp_asset
+        .unwrap_or(velocity.timestamp_network)
+}
+
+impl VelocityFilters {
+    /// Create filters with the given gains
+    pub fn new(enabled: bool, alpha: f32, beta: f32) -> Self {
+        VelocityFilters {
+            enabled,
+            alpha: alpha.clamp(0.0, 1.0) as f64,
+            beta: beta.clamp(0.0, 1.0) as f64,
+            filters: HashMap::new(),
+        }
+    }
+
+    /// Create filters shared between request handlers
+    pub fn shared(enabled: bool, alpha: f32, beta: f32) -> SharedFilters {
+        Arc::new(Mutex::new(VelocityFilters::new(enabled, alpha, beta)))
+    }
+
+    /// Smooth a velocity report of an aircraft
+    pub fn apply(&mut self, velocity: AircraftVelocity) -> AircraftVelocity {
+        if !self.enabled {
+            return velocity;
+        }
+
+        let time = report_time(&velocity);
+        let reset = Duration::try_milliseconds(FILTER_RESET_MS).unwrap_or(Duration::zero());
+        if self.filters.len() > FILTER_PRUNE_THRESHOLD {
+            self.filters
+                .retain(|_, filter| time - filter.updated < reset);
+        }
+
+        let measured = (
+            velocity.velocity_horizontal_ground_mps as f64,
+            velocity.velocity_vertical_mps as f64,
+            velocity.track_angle_degrees as f64,
+        );
+
+        let filter = match self.filters.get_mut(&velocity.identifier) {
+            Some(filter) if time >= filter.updated && time - filter.updated < reset => filter,
+            _ => {
+                self.filters.insert(
+                    velocity.identifier.clone(),
+                    VelocityFilter {
+                        ground_speed: AlphaBetaFilter::new(measured.0),
+                        vertical_speed: AlphaBetaFilter::new(measured.1),
+                        track_angle: AlphaBetaFilter::new(measured.2),
+                        updated: time,
+                    },
+                );
+
+                return velocity;
+            }
+        };
+
+        let seconds = (time - filter.updated).num_milliseconds() as f64 / 1000.0;
+        let (alpha, beta) = (self.alpha, self.beta);
+        let ground_speed = filter
+            .ground_speed
+            .update(measured.0, seconds, alpha, beta)
+            .max(0.0);
+        let vertical_speed = filter
+            .vertical_speed
+            .update(measured.1, seconds, alpha, beta);
+        let track_angle = filter
+            .track_angle
+            .update_angle(measured.2, seconds, alpha, beta);
+        filter.updated = time;
+
+        AircraftVelocity {
+            velocity_horizontal_ground_mps: ground_speed as f32,
+            velocity_vertical_mps: vertical_speed as f32,
+            track_angle_degrees: track_angle as f32,
+            ..velocity
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn velocity(ms: i64, speed: f32, vertical: f32, track: f32) -> AircraftVelocity {
+        AircraftVelocity {
+            identifier: "a".to_string(),
+            velocity_horizontal_ground_mps: speed,
+            velocity_horizontal_air_mps: None,
+            velocity_vertical_mps: vertical,
+            track_angle_degrees: track,
+            timestamp_network: Utc::now(),
+            timestamp_asset: Some(
+                DateTime::from_timestamp(0, 0).unwrap() + Duration::try_milliseconds(ms).unwrap(),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_alpha_beta_filter() {
+        let mut filter = AlphaBetaFilter::new(10.0);
+        assert_eq!(filter.value(), 10.0);
+
+        // half way to the measurement
+        assert_eq!(filter.update(20.0, 1.0, 0.5, 0.0), 15.0);
+
+        // a steady measurement is converged on
+        for _ in 0..50 {
+            filter.update(12.0, 1.0, 0.5, 0.1);
+        }
+        assert!((filter.value() - 12.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_alpha_beta_filter_angle() {
+        // crossing north smooths through 0 degrees, not through 180
+        let mut filter = AlphaBetaFilter::new(350.0);
+        let value = filter.update_angle(10.0, 1.0, 0.5, 0.0);
+        assert!(value.abs() < 1e-9 || (value - 360.0).abs() < 1e-9);
+
+        let value = filter.update_angle(10.0, 1.0, 0.5, 0.0);
+        assert!((value - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_velocity_filters() {
+        let mut filters = VelocityFilters::new(false, 0.5, 0.0);
+        let raw = velocity(0, 10.0, 0.0, 90.0);
+        filters.apply(raw.clone());
+        let passed = filters.apply(velocity(1000, 10.5, 1.0, 100.0));
+        assert_eq!(passed.velocity_horizontal_ground_mps, 10.5);
+
+        let mut filters = VelocityFilters::new(true, 0.5, 0.0);
+
+        // first report is passed through
+        let first = filters.apply(velocity(0, 10.0, 0.0, 90.0));
+        assert_eq!(first.velocity_horizontal_ground_mps, 10.0);
+
+        let smoothed = filters.apply(velocity(1000, 10.5, 1.0, 100.0));
+        assert_eq!(smoothed.velocity_horizontal_ground_mps, 10.25);
+        assert_eq!(smoothed.velocity_vertical_mps, 0.5);
+        assert_eq!(smoothed.track_angle_degrees, 95.0);
+
+        // filter is reset after a long gap
+        let reset = filters.apply(velocity(1000 + FILTER_RESET_MS, 20.0, 0.0, 0.0));
+        assert_eq!(reset.velocity_horizontal_ground_mps, 20.0);
+
+        // out of order reports reset the filter too
+        let reset = filters.apply(velocity(0, 5.0, 0.0, 0.0));
+        assert_eq!(reset.velocity_horizontal_ground_mps, 5.0);
+    }
+}
@@ -0,0 +1,166 @@
+//! Operating regions of telemetry reporters
+//!
+//! A reporter only receives packets of aircraft within radio range. Positions
+//!  far outside the region a reporter is registered for are spoofed or were
+//!  decoded wrongly.
+
+use std::collections::HashMap;
+
+/// Mean radius of the Earth
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// Great circle distance between two locations given as latitude and
+///  longitude in degrees
+pub fn distance_meters(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (latitude_1, longitude_1) = (from.0.to_radians(), from.1.to_radians());
+    let (latitude_2, longitude_2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((latitude_2 - latitude_1) / 2.0).sin().powi(2)
+        + latitude_1.cos() * latitude_2.cos() * ((longitude_2 - longitude_1) / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().min(1.0).asin()
+}
+
+/// Circular region a reporter operates in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OperatingRegion {
+    /// Latitude of the center, in degrees
+    pub latitude: f64,
+
+    /// Longitude of the center, in degrees
+    pub longitude: f64,
+
+    /// Radius of the region
+    pub radius_meters: f64,
+}
+
+impl OperatingRegion {
+    /// Distance of a location beyond the region, None if within
+    pub fn excess_meters(&self, latitude: f64, longitude: f64) -> Option<f64> {
+        let distance = distance_meters((self.latitude, self.longitude), (latitude, longitude));
+        (distance > self.radius_meters).then_some(distance - self.radius_meters)
+    }
+}
+
+/// Parse a region entry formatted `reporter=latitude,longitude,radius_km`
+pub fn parse_region(entry: &str) -> Option<(String, OperatingRegion)> {
+    let (reporter, region) = entry.split_once('=')?;
+    let values = region
+        .split(',')
+        .map(|value| value.trim().parse::<f64>().ok().filter(|v| v.is_finite()))
+        .collect::<Option<Vec<f64>>>()?;
+
+    let [latitude, longitude, radius_km] = values[..] else {
+        return None;
+    };
+
+    let reporter = reporter.trim();
+    let valid = !reporter.is_empty()
+        && latitude.abs() <= 90.0
+        && longitude.abs() <= 180.0
+        && radius_km > 0.0;
+
+    valid.then(|| {
+        let region = OperatingRegion {
+            latitude,
+            longitude,
+            radius_meters: radius_km * 1000.0,
+        };
+
+        (reporter.to_string(), region)
+    })
+}
+
+/// Operating regions of the reporters, reporters without a region are
+///  not checked
+#[derive(Debug, Clone, Default)]
+pub struct Geofence {
+    /// Regions by reporter
+    regions: HashMap<String, OperatingRegion>,
+}
+
+impl Geofence {
+    /// Create from a semicolon separated list of regions, see
+    ///  [`parse_region`]. Invalid entries are skipped.
+    pub fn new(regions: &str) -> Self {
+        Geofence {
+            regions: Self::entries(regions).filter_map(parse_region).collect(),
+        }
+    }
+
+    /// Non-empty entries of a list of regions
+    pub fn entries(regions: &str) -> impl Iterator<Item = &str> {
+        regions.split(';').filter(|entry| !entry.trim().is_empty())
+    }
+
+    /// Region of a reporter, if registered
+    pub fn region(&self, reporter: &str) -> Option<&OperatingRegion> {
+        self.regions.get(reporter)
+    }
+
+    /// Distance of a position reported by a reporter beyond its region,
+    ///  None if within or if the reporter has no region
+    pub fn excess_meters(&self, reporter: &str, latitude: f64, longitude: f64) -> Option<f64> {
+        self.region(reporter)?.excess_meters(latitude, longitude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance_meters((52.0, 4.0), (52.0, 4.0)), 0.0);
+
+        // a degree of latitude
+        let degree = distance_meters((0.0, 0.0), (1.0, 0.0));
+        assert!((degree - 111_195.0).abs() < 1.0);
+
+        // Amsterdam to New York, about 5860 km
+        let distance = distance_meters((52.37, 4.90), (40.71, -74.01));
+        assert!((distance - 5_860_000.0).abs() < 20_000.0);
+
+        // across the antimeridian
+        let distance = distance_meters((0.0, 179.5), (0.0, -179.5));
+        assert!((distance - degree).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_parse_region() {
+        let (reporter, region) = parse_region(" station-1 = 52.3, 4.8, 50").unwrap();
+        assert_eq!(reporter, "station-1");
+        assert_eq!(
+            region,
+            OperatingRegion {
+                latitude: 52.3,
+                longitude: 4.8,
+                radius_meters: 50_000.0,
+            }
+        );
+
+        assert!(parse_region("station-1").is_none());
+        assert!(parse_region("=52.3,4.8,50").is_none());
+        assert!(parse_region("station-1=52.3,4.8").is_none());
+        assert!(parse_region("station-1=52.3,4.8,50,1").is_none());
+        assert!(parse_region("station-1=91,4.8,50").is_none());
+        assert!(parse_region("station-1=52.3,181,50").is_none());
+        assert!(parse_region("station-1=52.3,4.8,0").is_none());
+        assert!(parse_region("station-1=52.3,4.8,NaN").is_none());
+    }
+
+    #[test]
+    fn test_geofence() {
+        let geofence = Geofence::new("station-1=52.3,4.8,50; ;station-2=garbled;");
+        assert!(geofence.region("station-1").is_some());
+        assert!(geofence.region("station-2").is_none());
+        assert_eq!(Geofence::entries("a; ;b;").count(), 2);
+
+        // within the region, or no region
+        assert_eq!(geofence.excess_meters("station-1", 52.5, 4.9), None);
+        assert_eq!(geofence.excess_meters("station-2", 0.0, 0.0), None);
+
+        // about 110 km north of the center
+        let excess = geofence.excess_meters("station-1", 53.3, 4.8).unwrap();
+        assert!((excess - 61_195.0).abs() < 100.0);
+    }
+}
@@ -0,0 +1,320 @@
+/// Vehicle Health Reports
+use lib_common::time::{DateTime, Utc};
+use packed_struct::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// Version of the health message format
+pub const HEALTH_MESSAGE_VERSION: u8 = 1;
+
+/// Length of a health message
+pub const HEALTH_MESSAGE_LENGTH: usize = 16;
+
+/// Seconds between the Unix epoch and the epoch of health message
+///  timestamps (00:00:00 01/01/2019 UTC, as Remote ID)
+const HEALTH_EPOCH_UNIX_SECONDS: i64 = 1_546_300_800;
+
+/// Type of GNSS fix, as MAVLink `GPS_FIX_TYPE`
+#[derive(PrimitiveEnum_u8, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GpsFix {
+    /// No GNSS receiver connected
+    NoGps = 0,
+
+    /// No position information
+    NoFix = 1,
+
+    /// 2D position
+    Fix2d = 2,
+
+    /// 3D position
+    Fix3d = 3,
+
+    /// DGPS or SBAS aided 3D position
+    Dgps = 4,
+
+    /// RTK float 3D position
+    RtkFloat = 5,
+
+    /// RTK fixed 3D position
+    RtkFixed = 6,
+
+    /// Static fixed position, typically of base stations
+    Static = 7,
+
+    /// Precise point positioning
+    Ppp = 8,
+}
+
+/// Errors decoding a health message
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HealthDecodeError {
+    /// The message is not 16 bytes long
+    InvalidLength,
+
+    /// The message fields could not be unpacked
+    InvalidMessage,
+
+    /// The message is of another version of the format
+    UnsupportedVersion,
+}
+
+impl Display for HealthDecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            HealthDecodeError::InvalidLength => write!(f, "Invalid length"),
+            HealthDecodeError::InvalidMessage => write!(f, "Invalid message"),
+            HealthDecodeError::UnsupportedVersion => write!(f, "Unsupported version"),
+        }
+    }
+}
+
+/// Vehicle Health Message
+///  Vehicles of the fleet report their battery, GNSS fix and command link
+///  state. Unknown values are sent as the maximum of their field.
+#[derive(PackedStruct, Debug, Clone, Copy, PartialEq)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb", size_bytes = "16")]
+pub struct HealthMessage {
+    /// Version of the format, [`HEALTH_MESSAGE_VERSION`]
+    #[packed_field(size_bytes = "1")]
+    pub version: u8,
+
+    /// Battery voltage in millivolts
+    #[packed_field(size_bytes = "2")]
+    pub battery_voltage_mv: u16,
+
+    /// Battery current in centiamperes, positive when discharging
+    #[packed_field(size_bytes = "2")]
+    pub battery_current_ca: i16,
+
+    /// Remaining battery capacity in percent
+    #[packed_field(size_bytes = "1")]
+    pub battery_remaining_percent: u8,
+
+    /// Type of GNSS fix
+    #[packed_field(size_bytes = "1", ty = "enum")]
+    pub gps_fix: GpsFix,
+
+    /// Number of satellites used by the fix
+    #[packed_field(size_bytes = "1")]
+    pub satellites_visible: u8,
+
+    /// Horizontal dilution of precision, times 100
+    #[packed_field(size_bytes = "2")]
+    pub hdop: u16,
+
+    /// Signal strength of the command link in dBm
+    #[packed_field(size_bytes = "1")]
+    pub link_rssi_dbm: i8,
+
+    /// Share of command link packets received, in percent
+    #[packed_field(size_bytes = "1")]
+    pub link_quality_percent: u8,
+
+    /// Seconds since 00:00:00 01/01/2019 UTC, 0 if unknown
+    #[packed_field(size_bytes = "4")]
+    pub timestamp: u32,
+}
+
+/// Decoded health of a vehicle, as published
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VehicleHealth {
+    /// Identifier of the vehicle
+    pub identifier: String,
+
+    /// Battery voltage in volts
+    pub battery_voltage_v: Option<f32>,
+
+    /// Battery current in amperes, positive when discharging
+    pub battery_current_a: Option<f32>,
+
+    /// Remaining battery capacity in percent
+    pub battery_remaining_percent: Option<u8>,
+
+    /// Type of GNSS fix
+    pub gps_fix: GpsFix,
+
+    /// Number of satellites used by the fix
+    pub satellites_visible: Option<u8>,
+
+    /// Horizontal dilution of precision
+    pub hdop: Option<f32>,
+
+    /// Signal strength of the command link in dBm
+    pub link_rssi_dbm: Option<i8>,
+
+    /// Share of command link packets received, in percent
+    pub link_quality_percent: Option<u8>,
+
+    /// When the vehicle sent the report
+    pub timestamp_asset: Option<DateTime<Utc>>,
+
+    /// When the report was received
+    pub timestamp_network: DateTime<Utc>,
+}
+
+impl HealthMessage {
+    /// Unpacks a received message
+    pub fn decode(payload: &[u8]) -> Result<Self, HealthDecodeError> {
+        let payload = <[u8; HEALTH_MESSAGE_LENGTH]>::try_from(payload)
+            .map_err(|_| HealthDecodeError::InvalidLength)?;
+
+        let message =
+            HealthMessage::unpack(&payload).map_err(|_| HealthDecodeError::InvalidMessage)?;
+
+        match message.version {
+            HEALTH_MESSAGE_VERSION => Ok(message),
+            _ => Err(HealthDecodeError::UnsupportedVersion),
+        }
+    }
+
+    /// Decode the timestamp, if known
+    pub fn decode_timestamp(&self) -> Option<DateTime<Utc>> {
+        match self.timestamp {
+            0 => None,
+            seconds => DateTime::from_timestamp(HEALTH_EPOCH_UNIX_SECONDS + seconds as i64, 0),
+        }
+    }
+
+    /// Encode a timestamp, which must be after the epoch of the format
+    pub fn encode_timestamp(timestamp: DateTime<Utc>) -> Option<u32> {
+        u32::try_from(timestamp.timestamp() - HEALTH_EPOCH_UNIX_SECONDS).ok()
+    }
+
+    /// Health of the vehicle, received at `now`
+    pub fn to_health(&self, identifier: &str, now: DateTime<Utc>) -> VehicleHealth {
+        VehicleHealth {
+            identifier: identifier.to_string(),
+            battery_voltage_v: (self.battery_voltage_mv != u16::MAX)
+                .then(|| self.battery_voltage_mv as f32 / 1000.0),
+            battery_current_a: (self.battery_current_ca != i16::MAX)
+                .then(|| self.battery_current_ca as f32 / 100.0),
+            battery_remaining_percent: Some(self.battery_remaining_percent)
+                .filter(|percent| *percent <= 100),
+            gps_fix: self.gps_fix,
+            satellites_visible: Some(self.satellites_visible).filter(|n| *n != u8::MAX),
+            hdop: (self.hdop != u16::MAX).then(|| self.hdop as f32 / 100.0),
+            link_rssi_dbm: Some(self.link_rssi_dbm).filter(|rssi| *rssi != i8::MAX),
+            link_quality_percent: Some(self.link_quality_percent).filter(|percent| *percent <= 100),
+            timestamp_asset: self.decode_timestamp(),
+            timestamp_network: now,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> HealthMessage {
+        HealthMessage {
+            version: HEALTH_MESSAGE_VERSION,
+            battery_voltage_mv: 22_200,
+            battery_current_ca: 1_550,
+            battery_remaining_percent: 76,
+            gps_fix: GpsFix::RtkFixed,
+            satellites_visible: 17,
+            hdop: 85,
+            link_rssi_dbm: -71,
+            link_quality_percent: 98,
+            timestamp: 3600,
+        }
+    }
+
+    #[test]
+    fn test_pack() {
+        let bytes = message().pack().unwrap();
+        assert_eq!(bytes.len(), HEALTH_MESSAGE_LENGTH);
+        assert_eq!(bytes[0], HEALTH_MESSAGE_VERSION);
+        assert_eq!(&bytes[1..3], &22_200_u16.to_le_bytes());
+        assert_eq!(bytes[6], GpsFix::RtkFixed as u8);
+        assert_eq!(bytes[10], (-71_i8) as u8);
+        assert_eq!(HealthMessage::decode(&bytes), Ok(message()));
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let bytes = message().pack().unwrap();
+        assert_eq!(
+            HealthMessage::decode(&bytes[1..]),
+            Err(HealthDecodeError::InvalidLength)
+        );
+
+        let mut invalid = bytes;
+        invalid[6] = 0xFF;
+        assert_eq!(
+            HealthMessage::decode(&invalid),
+            Err(HealthDecodeError::InvalidMessage)
+        );
+
+        let mut version = bytes;
+        version[0] = HEALTH_MESSAGE_VERSION + 1;
+        assert_eq!(
+            HealthMessage::decode(&version),
+            Err(HealthDecodeError::UnsupportedVersion)
+        );
+    }
+
+    #[test]
+    fn test_to_health() {
+        let health = message().to_health("drone-1", Utc::now());
+        assert_eq!(health.identifier, "drone-1");
+        assert_eq!(health.battery_voltage_v, Some(22.2));
+        assert_eq!(health.battery_current_a, Some(15.5));
+        assert_eq!(health.battery_remaining_percent, Some(76));
+        assert_eq!(health.gps_fix, GpsFix::RtkFixed);
+        assert_eq!(health.satellites_visible, Some(17));
+        assert_eq!(health.hdop, Some(0.85));
+        assert_eq!(health.link_rssi_dbm, Some(-71));
+        assert_eq!(health.link_quality_percent, Some(98));
+        assert_eq!(
+            health.timestamp_asset,
+            DateTime::from_timestamp(HEALTH_EPOCH_UNIX_SECONDS + 3600, 0)
+        );
+
+        let unknown = HealthMessage {
+            battery_voltage_mv: u16::MAX,
+            battery_current_ca: i16::MAX,
+            battery_remaining_percent: u8::MAX,
+            satellites_visible: u8::MAX,
+            hdop: u16::MAX,
+            link_rssi_dbm: i8::MAX,
+            link_quality_percent: u8::MAX,
+            timestamp: 0,
+            ..message()
+        };
+        let health = unknown.to_health("drone-1", Utc::now());
+        assert_eq!(health.battery_voltage_v, None);
+        assert_eq!(health.battery_current_a, None);
+        assert_eq!(health.battery_remaining_percent, None);
+        assert_eq!(health.satellites_visible, None);
+        assert_eq!(health.hdop, None);
+        assert_eq!(health.link_rssi_dbm, None);
+        assert_eq!(health.link_quality_percent, None);
+        assert_eq!(health.timestamp_asset, None);
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let timestamp = DateTime::from_timestamp(HEALTH_EPOCH_UNIX_SECONDS + 60, 0).unwrap();
+        assert_eq!(HealthMessage::encode_timestamp(timestamp), Some(60));
+        assert_eq!(
+            HealthMessage::encode_timestamp(DateTime::from_timestamp(0, 0).unwrap()),
+            None
+        );
+
+        let message = HealthMessage {
+            timestamp: 60,
+            ..message()
+        };
+        assert_eq!(message.decode_timestamp(), Some(timestamp));
+    }
+
+    #[test]
+    fn test_serialize() {
+        let health = message().to_health("drone-1", Utc::now());
+        let value = serde_json::to_value(&health).unwrap();
+        assert_eq!(value["gps_fix"], "rtk_fixed");
+        assert_eq!(value["link_rssi_dbm"], -71);
+    }
+}
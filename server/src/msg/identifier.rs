@@ -0,0 +1,370 @@
+//! Normalization of aircraft identifiers
+//!
+//! Aircraft are identified by their ICAO address (ADS-B and OGN), their
+//!  Remote ID UAS ID, or the APRS source of their OGN beacons. Each kind
+//!  of identifier is written by the rules of `IDENTIFIER_RULES` before it
+//!  keys the caches or is pushed to the sinks, so consumers joining the
+//!  outputs of several protocols see one identifier per aircraft.
+//!
+//! Without rules, identifiers keep the formats of earlier releases: ICAO
+//!  addresses in lowercase hex without padding, other identifiers as
+//!  received.
+
+use std::str::FromStr;
+
+/// Longest padding of identifiers, longer paddings are invalid
+const MAX_PAD: usize = 64;
+
+/// Hex digits of an ICAO address
+const ICAO_DIGITS: usize = 6;
+
+/// Kinds of aircraft identifiers, each written by its own rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentifierKind {
+    /// 24 bit ICAO address in hex, of ADS-B aircraft and OGN beacons
+    Icao,
+
+    /// Remote ID UAS ID (serial number, registration or UTM session UUID),
+    ///  also the subject of the login tokens
+    RemoteId,
+
+    /// APRS source of OGN beacons without an ICAO address
+    Ogn,
+}
+
+impl FromStr for IdentifierKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "icao" => Ok(IdentifierKind::Icao),
+            "remote_id" => Ok(IdentifierKind::RemoteId),
+            "ogn" => Ok(IdentifierKind::Ogn),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Case of the letters of identifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentifierCase {
+    /// As received
+    Keep,
+
+    /// Lowercase
+    Lower,
+
+    /// Uppercase
+    Upper,
+}
+
+impl FromStr for IdentifierCase {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "keep" => Ok(IdentifierCase::Keep),
+            "lower" => Ok(IdentifierCase::Lower),
+            "upper" => Ok(IdentifierCase::Upper),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How the identifiers of a kind are written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentifierRule {
+    /// Case of their letters
+    pub case: IdentifierCase,
+
+    /// Minimum length without the prefix, shorter identifiers are padded
+    ///  with leading zeros
+    pub pad: usize,
+
+    /// Prefix of the identifiers, e.g. `icao:`
+    pub prefix: String,
+}
+
+impl IdentifierRule {
+    /// Rule keeping identifiers as received
+    fn keep() -> Self {
+        IdentifierRule {
+            case: IdentifierCase::Keep,
+            pad: 0,
+            prefix: String::new(),
+        }
+    }
+
+    /// The identifier without its prefix, matched regardless of case
+    fn strip_prefix<'a>(&self, identifier: &'a str) -> Option<&'a str> {
+        let head = identifier.get(..self.prefix.len())?;
+        match !self.prefix.is_empty() && head.eq_ignore_ascii_case(&self.prefix) {
+            true => identifier.get(self.prefix.len()..),
+            false => None,
+        }
+    }
+
+    /// The identifier written by this rule
+    ///
+    /// Identifiers already written by the rule are unchanged, empty ones
+    ///  stay empty.
+    fn apply(&self, identifier: &str) -> String {
+        let identifier = identifier.trim();
+        let identifier = self.strip_prefix(identifier).unwrap_or(identifier);
+        if identifier.is_empty() {
+            return String::new();
+        }
+
+        let identifier = match self.case {
+            IdentifierCase::Keep => identifier.to_string(),
+            IdentifierCase::Lower => identifier.to_lowercase(),
+            IdentifierCase::Upper => identifier.to_uppercase(),
+        };
+
+        format!("{}{identifier:0>pad$}", self.prefix, pad = self.pad)
+    }
+}
+
+/// Setting of a rule entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleSetting {
+    /// Case of the letters
+    Case(IdentifierCase),
+
+    /// Minimum length
+    Pad(usize),
+
+    /// Prefix, empty for none
+    Prefix(String),
+}
+
+/// Parse a rule entry formatted `kind.setting=value`, e.g. `icao.pad=6`,
+///  none if the kind, setting or value is invalid
+pub fn parse_entry(entry: &str) -> Option<(IdentifierKind, RuleSetting)> {
+    let (name, value) = entry.split_once('=')?;
+    let (kind, setting) = name.split_once('.')?;
+    let kind = IdentifierKind::from_str(kind).ok()?;
+    let setting = match setting.trim().to_lowercase().as_str() {
+        "case" => RuleSetting::Case(IdentifierCase::from_str(value).ok()?),
+        "pad" => RuleSetting::Pad(value.trim().parse().ok().filter(|pad| *pad <= MAX_PAD)?),
+        "prefix" => RuleSetting::Prefix(value.trim().to_string()),
+        _ => return None,
+    };
+
+    Some((kind, setting))
+}
+
+/// Rules of each kind of identifier, as configured
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentifierRules {
+    /// ICAO addresses
+    icao: IdentifierRule,
+
+    /// Remote ID identifiers
+    remote_id: IdentifierRule,
+
+    /// APRS sources of OGN beacons
+    ogn: IdentifierRule,
+}
+
+impl Default for IdentifierRules {
+    fn default() -> Self {
+        IdentifierRules::new("")
+    }
+}
+
+impl IdentifierRules {
+    /// Comma separated entries of a rule list, see [`parse_entry`]
+    pub fn entries(list: &str) -> impl Iterator<Item = &str> {
+        list.split(',').filter(|entry| !entry.trim().is_empty())
+    }
+
+    /// Rules of the listed entries over the formats of earlier releases,
+    ///  skipping invalid entries
+    pub fn new(list: &str) -> Self {
+        let mut rules = IdentifierRules {
+            icao: IdentifierRule {
+                case: IdentifierCase::Lower,
+                ..IdentifierRule::keep()
+            },
+            remote_id: IdentifierRule::keep(),
+            ogn: IdentifierRule::keep(),
+        };
+
+        for (kind, setting) in Self::entries(list).filter_map(parse_entry) {
+            let rule = rules.rule_mut(kind);
+            match setting {
+                RuleSetting::Case(case) => rule.case = case,
+                RuleSetting::Pad(pad) => rule.pad = pad,
+                RuleSetting::Prefix(prefix) => rule.prefix = prefix,
+            }
+        }
+
+        rules
+    }
+
+    /// Mutable rule of a kind of identifier
+    fn rule_mut(&mut self, kind: IdentifierKind) -> &mut IdentifierRule {
+        match kind {
+            IdentifierKind::Icao => &mut self.icao,
+            IdentifierKind::RemoteId => &mut self.remote_id,
+            IdentifierKind::Ogn => &mut self.ogn,
+        }
+    }
+
+    /// Rule of a kind of identifier
+    pub fn rule(&self, kind: IdentifierKind) -> &IdentifierRule {
+        match kind {
+            IdentifierKind::Icao => &self.icao,
+            IdentifierKind::RemoteId => &self.remote_id,
+            IdentifierKind::Ogn => &self.ogn,
+        }
+    }
+
+    /// Identifier of a kind, written by its rule
+    pub fn normalize(&self, kind: IdentifierKind, identifier: &str) -> String {
+        self.rule(kind).apply(identifier)
+    }
+
+    /// Identifier of an ICAO address
+    pub fn icao(&self, address: u32) -> String {
+        self.icao.apply(&format!("{address:x}"))
+    }
+
+    /// Identifier given by a client (e.g. a path parameter or the
+    ///  watchlist), in the configured format
+    ///
+    /// Clients may still use earlier formats: identifiers with the ICAO
+    ///  prefix, or of up to six hex digits, are ICAO addresses; those with
+    ///  the OGN prefix are OGN sources; others are Remote ID identifiers.
+    pub fn resolve(&self, identifier: &str) -> String {
+        let identifier = identifier.trim();
+        let (icao, prefixed) = match self.icao.strip_prefix(identifier) {
+            Some(icao) => (icao, true),
+            None => (identifier, false),
+        };
+
+        let is_icao = prefixed || (1..=ICAO_DIGITS).contains(&icao.len());
+        match u32::from_str_radix(icao, 16) {
+            Ok(address) if is_icao => self.icao(address),
+            _ if self.ogn.strip_prefix(identifier).is_some() => self.ogn.apply(identifier),
+            _ => self.remote_id.apply(identifier),
+        }
+    }
+
+    /// Comma separated identifiers given by a client, resolved
+    pub fn resolve_list(&self, list: &str) -> String {
+        list.split(',')
+            .map(|identifier| self.resolve(identifier))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entry() {
+        assert_eq!(
+            parse_entry("icao.case=UPPER"),
+            Some((
+                IdentifierKind::Icao,
+                RuleSetting::Case(IdentifierCase::Upper)
+            ))
+        );
+        assert_eq!(
+            parse_entry(" remote_id.pad = 8"),
+            Some((IdentifierKind::RemoteId, RuleSetting::Pad(8)))
+        );
+        assert_eq!(
+            parse_entry("ogn.prefix=ogn:"),
+            Some((IdentifierKind::Ogn, RuleSetting::Prefix("ogn:".to_string())))
+        );
+        for invalid in [
+            "icao",
+            "icao=upper",
+            "flarm.case=upper",
+            "icao.case=title",
+            "icao.pad=-1",
+            "icao.pad=65",
+            "icao.suffix=x",
+        ] {
+            assert_eq!(parse_entry(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_default_rules() {
+        // the formats of earlier releases
+        let rules = IdentifierRules::default();
+        assert_eq!(rules.icao(0x4840d6), "4840d6");
+        assert_eq!(rules.icao(0x00a1b2), "a1b2");
+        assert_eq!(
+            rules.normalize(IdentifierKind::RemoteId, " Drone-1 "),
+            "Drone-1"
+        );
+        assert_eq!(
+            rules.normalize(IdentifierKind::Ogn, "FLR3F1234"),
+            "FLR3F1234"
+        );
+        assert_eq!(rules.resolve("4840D6"), "4840d6");
+        assert_eq!(rules.resolve("drone-1"), "drone-1");
+    }
+
+    #[test]
+    fn test_normalize() {
+        let rules = IdentifierRules::new(
+            "icao.case=upper,icao.pad=6,icao.prefix=icao:,remote_id.case=upper,invalid,ogn.case=lower",
+        );
+        assert_eq!(rules.icao(0x00a1b2), "icao:00A1B2");
+        assert_eq!(
+            rules.normalize(IdentifierKind::Icao, "ICAO:a1b2"),
+            "icao:00A1B2"
+        );
+        assert_eq!(
+            rules.normalize(IdentifierKind::RemoteId, "1596f000000000000a1"),
+            "1596F000000000000A1"
+        );
+        assert_eq!(
+            rules.normalize(IdentifierKind::Ogn, "FLR3F1234"),
+            "flr3f1234"
+        );
+        assert_eq!(rules.normalize(IdentifierKind::RemoteId, " "), "");
+
+        // normalized identifiers are unchanged
+        let identifier = rules.icao(0x4840d6);
+        assert_eq!(
+            rules.normalize(IdentifierKind::Icao, &identifier),
+            identifier
+        );
+
+        // later entries override earlier ones
+        let rules = IdentifierRules::new("icao.prefix=x-,icao.prefix=");
+        assert_eq!(rules.icao(0x4840d6), "4840d6");
+    }
+
+    #[test]
+    fn test_resolve() {
+        let rules = IdentifierRules::new(
+            "icao.case=upper,icao.pad=6,icao.prefix=icao:,remote_id.case=upper,ogn.prefix=ogn:",
+        );
+
+        // earlier formats of ICAO addresses
+        assert_eq!(rules.resolve("a1b2"), "icao:00A1B2");
+        assert_eq!(rules.resolve("4840d6"), "icao:4840D6");
+        assert_eq!(rules.resolve("icao:4840d6"), "icao:4840D6");
+        assert_eq!(rules.resolve("ICAO:004840d6"), "icao:4840D6");
+
+        assert_eq!(rules.resolve("ogn:FLR3F1234"), "ogn:FLR3F1234");
+        assert_eq!(rules.resolve("drone-1"), "DRONE-1");
+        assert_eq!(rules.resolve("1596F000000000000A1"), "1596F000000000000A1");
+        assert_eq!(rules.resolve(""), "");
+
+        assert_eq!(
+            rules.resolve_list("4840d6, drone-1,"),
+            "icao:4840D6,DRONE-1,"
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Detection of conflicting aircraft identities
+//!
+//! An identifier is claimed by one value at a time: the token subject
+//!  sending the Basic messages of a Remote ID aircraft, or the callsign of
+//!  an ADS-B aircraft. Another value within the conflict window means two
+//!  aircraft, or a spoofer, use the same identifier.
+
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// What two values of an identifier contradict each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// Two token subjects sent Basic messages with the same UAS ID
+    Subject,
+
+    /// An ICAO address was identified with two callsigns
+    Callsign,
+}
+
+impl ConflictKind {
+    /// Name of the kind in cache keys
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConflictKind::Subject => "subject",
+            ConflictKind::Callsign => "callsign",
+        }
+    }
+}
+
+/// Identifier claimed by two values, alerted and kept for the admin API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct IdentityConflict {
+    /// UAS ID or ICAO address (hex) of the aircraft
+    pub identifier: String,
+
+    /// What the values are
+    pub kind: ConflictKind,
+
+    /// Value which claimed the identifier first
+    pub previous: String,
+
+    /// Value now claiming the identifier
+    pub current: String,
+
+    /// When the previous value was last seen
+    pub previous_seen: DateTime<Utc>,
+
+    /// When the conflict was detected
+    pub timestamp_network: DateTime<Utc>,
+}
+
+/// Last value seen for an identifier
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdentityClaim {
+    /// The value
+    pub value: String,
+
+    /// When it was last seen
+    pub seen: DateTime<Utc>,
+}
+
+/// Conflict of a value with the last claim of the identifier, if the
+///  claim differs and was seen within the window
+pub fn detect(
+    identifier: &str,
+    kind: ConflictKind,
+    claim: Option<&IdentityClaim>,
+    value: &str,
+    now: DateTime<Utc>,
+    window: Duration,
+) -> Option<IdentityConflict> {
+    let claim = claim?;
+    if claim.value == value || now - claim.seen > window {
+        return None;
+    }
+
+    Some(IdentityConflict {
+        identifier: identifier.to_string(),
+        kind,
+        previous: claim.value.clone(),
+        current: value.to_string(),
+        previous_seen: claim.seen,
+        timestamp_network: now,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let now = Utc::now();
+        let window = Duration::seconds(60);
+        let claim = IdentityClaim {
+            value: "KLM1023".to_string(),
+            seen: now - Duration::seconds(10),
+        };
+
+        let kind = ConflictKind::Callsign;
+        assert_eq!(detect("4840d6", kind, None, "KLM1023", now, window), None);
+        assert_eq!(
+            detect("4840d6", kind, Some(&claim), "KLM1023", now, window),
+            None
+        );
+
+        let conflict = detect("4840d6", kind, Some(&claim), "TRA6011", now, window).unwrap();
+        assert_eq!(conflict.previous, "KLM1023");
+        assert_eq!(conflict.current, "TRA6011");
+        assert_eq!(conflict.previous_seen, claim.seen);
+
+        // a new flight long after the last identification
+        let later = now + Duration::seconds(120);
+        assert_eq!(
+            detect("4840d6", kind, Some(&claim), "TRA6011", later, window),
+            None
+        );
+    }
+
+    #[test]
+    fn test_conflict_serialization() {
+        let now = Utc::now();
+        let claim = IdentityClaim {
+            value: "drone-1".to_string(),
+            seen: now,
+        };
+
+        let kind = ConflictKind::Subject;
+        let conflict = detect(
+            "1596F123",
+            kind,
+            Some(&claim),
+            "drone-2",
+            now,
+            Duration::seconds(60),
+        );
+        let json = serde_json::to_value(conflict.unwrap()).unwrap();
+        assert_eq!(json["kind"], "subject");
+        assert_eq!(json["previous"], "drone-1");
+        assert_eq!(json["current"], "drone-2");
+        assert_eq!(kind.name(), "subject");
+    }
+}
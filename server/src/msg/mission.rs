@@ -0,0 +1,59 @@
+//! Flight plans and missions flown by the aircraft
+//!
+//! An aircraft may declare at login the flight plan or mission it flies,
+//!  by UUID. Its telemetry then carries that UUID downstream, so it can be
+//!  correlated with the planned operation without a lookup.
+
+/// Groups of hex digits of a UUID, separated by hyphens
+const UUID_GROUPS: [usize; 5] = [8, 4, 4, 4, 12];
+
+/// Canonical form (lowercase, hyphenated) of a flight plan or mission
+///  UUID, None if the value isn't a UUID
+///
+/// The hyphenated form is expected, with or without surrounding braces.
+pub fn parse_mission_id(value: &str) -> Option<String> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('{')
+        .and_then(|value| value.strip_suffix('}'))
+        .unwrap_or(value);
+
+    let groups: Vec<&str> = value.split('-').collect();
+    let valid = groups.len() == UUID_GROUPS.len()
+        && groups
+            .iter()
+            .zip(UUID_GROUPS)
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()));
+
+    valid.then(|| value.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mission_id() {
+        let expected = Some("7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5e".to_string());
+        assert_eq!(
+            parse_mission_id("7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5e"),
+            expected
+        );
+        assert_eq!(
+            parse_mission_id(" {7B0C3E4A-1F2D-4C5B-9A8E-0D1C2B3A4F5E} "),
+            expected
+        );
+
+        for value in [
+            "",
+            "mission-1",
+            "7b0c3e4a1f2d4c5b9a8e0d1c2b3a4f5e",
+            "7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5",
+            "7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5g",
+            "7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5e-0",
+            "{7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5e",
+        ] {
+            assert_eq!(parse_mission_id(value), None, "{value}");
+        }
+    }
+}
@@ -5,3 +5,63 @@ pub mod adsb;
 
 /// Remote ID Packet Structures and Types
 pub mod netrid;
+
+/// Remote ID broadcast transport frames
+pub mod odid;
+
+/// Vehicle health report structures
+pub mod health;
+
+/// Command and control link monitoring
+pub mod c2;
+
+/// Coverage of the receivers
+pub mod coverage;
+
+/// Open Glider Network (FLARM) beacons
+pub mod ogn;
+
+/// Universal Access Transceiver (978 MHz) ADS-B messages
+pub mod uat;
+
+/// Ordering and merging of position reports
+pub mod track;
+
+/// Recent positions of each aircraft
+pub mod trail;
+
+/// Smoothing of decoded velocities
+pub mod filter;
+
+/// Watchlist of aircraft of interest
+pub mod watchlist;
+
+/// Scrubbing of operator personal data
+pub mod privacy;
+
+/// Operating regions of reporters
+pub mod geofence;
+
+/// Detection of anomalies in decoded telemetry
+pub mod anomaly;
+
+/// Health state changes of the dependencies
+pub mod system;
+
+/// Conflicting aircraft identities
+pub mod identity;
+
+/// Normalization of aircraft identifiers
+pub mod identifier;
+
+/// Flight plans and missions flown by the aircraft
+pub mod mission;
+
+/// Conformance to the corridors of the flight plans
+pub mod conformance;
+
+/// Blocklist of aircraft whose telemetry is rejected
+pub mod blocklist;
+
+/// Replay of the stored ADS-B packets
+pub mod backfill;
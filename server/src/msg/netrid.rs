@@ -2,7 +2,9 @@
 use lib_common::time::{DateTime, Duration, Timelike, Utc};
 use packed_struct::prelude::packed_bits::Bits;
 use packed_struct::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display, Formatter};
+use svc_gis_client_grpc::prelude::types::{AircraftId, AircraftPosition, AircraftVelocity};
 
 ///////////////////////////////////////////////
 // Field Enumerations
@@ -11,8 +13,38 @@ use std::fmt::{self, Display, Formatter};
 ///////////////////
 //////////
 
-/// Remote ID Protocol Version
-pub const REMOTE_ID_PROTOCOL_VERSION: u8 = 0x2;
+/// Remote ID Protocol Version of the encoded frames, the latest
+///  [`ProtocolVersion`]
+pub const REMOTE_ID_PROTOCOL_VERSION: u8 = ProtocolVersion::F3411_22a as u8;
+
+/// Encoded track direction of an unknown direction (361 - 180)
+const DIRECTION_UNKNOWN_ENCODED: u16 = 181;
+
+/// Fastest ground speed encoded with the 0.25 multiplier (255 * 0.25)
+const GROUND_SPEED_LOW_RANGE_MPS: f32 = 63.75;
+
+/// Ground speed encoded as the maximum speed, and faster speeds
+pub const GROUND_SPEED_MAXIMUM_MPS: f32 = 254.25;
+
+/// Encoded ground speed, with the 0.75 multiplier, of 254.25 m/s or more
+pub const GROUND_SPEED_MAXIMUM_ENCODED: u8 = 254;
+
+/// Encoded ground speed, with the 0.75 multiplier, of an unknown speed
+pub const GROUND_SPEED_UNKNOWN_ENCODED: u8 = 255;
+
+/// Largest vertical speed encoded in a location message, faster aircraft
+///  are reported at this speed
+pub const VERTICAL_SPEED_MAX_MPS: f32 = 62.0;
+
+/// Encoded vertical speed of an unknown vertical speed (63 m/s)
+pub const VERTICAL_SPEED_UNKNOWN_ENCODED: i8 = 126;
+
+/// Location timestamps are tenths of seconds since the start of the hour
+pub const LOCATION_TIMESTAMP_TENTHS_PER_HOUR: u16 = 36_000;
+
+/// Time a location timestamp may be ahead of its reference time, covering
+///  senders with a clock running ahead of the receiver's
+pub const LOCATION_TIMESTAMP_LEAD_MS: i64 = 60_000;
 
 /// Remote ID Message Types
 #[derive(PrimitiveEnum_u8, Clone, Copy, Debug, PartialEq)]
@@ -110,6 +142,21 @@ pub enum IdType {
     SpecificSession = 0x4,
 }
 
+/// Remote ID Protocol Version, by revision of ASTM F3411
+#[derive(PrimitiveEnum_u8, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProtocolVersion {
+    /// ASTM F3411-19
+    F3411_19 = 0x0,
+
+    /// ASTM F3411-20, adding the classification, operator altitude and
+    ///  timestamp of the System message
+    F3411_20 = 0x1,
+
+    /// ASTM F3411-22a, adding the system failure operational status
+    F3411_22a = 0x2,
+    // 0x3 - 0xE are reserved, 0xF is for private use
+}
+
 /// Operation Status
 #[derive(PrimitiveEnum_u8, Clone, Copy, Debug, PartialEq)]
 pub enum OperationalStatus {
@@ -174,6 +221,27 @@ pub enum HorizontalAccuracyMeters {
     // 0xD - 0xF are reserved
 }
 
+impl HorizontalAccuracyMeters {
+    /// Upper bound of the accuracy in meters, `None` if 18520 meters or more
+    pub fn bound_meters(&self) -> Option<f32> {
+        match self {
+            HorizontalAccuracyMeters::Gte18520 => None,
+            HorizontalAccuracyMeters::Lt18520 => Some(18520.0),
+            HorizontalAccuracyMeters::Lt7408 => Some(7408.0),
+            HorizontalAccuracyMeters::Lt3704 => Some(3704.0),
+            HorizontalAccuracyMeters::Lt1852 => Some(1852.0),
+            HorizontalAccuracyMeters::Lt926 => Some(926.0),
+            HorizontalAccuracyMeters::Lt555_6 => Some(555.6),
+            HorizontalAccuracyMeters::Lt185_2 => Some(185.2),
+            HorizontalAccuracyMeters::Lt92_6 => Some(92.6),
+            HorizontalAccuracyMeters::Lt30 => Some(30.0),
+            HorizontalAccuracyMeters::Lt10 => Some(10.0),
+            HorizontalAccuracyMeters::Lt3 => Some(3.0),
+            HorizontalAccuracyMeters::Lt1 => Some(1.0),
+        }
+    }
+}
+
 /// Vertical Accuracy (in meters)
 #[derive(PrimitiveEnum_u8, Clone, Copy, Debug, PartialEq)]
 pub enum VerticalAccuracyMeters {
@@ -200,6 +268,22 @@ pub enum VerticalAccuracyMeters {
     // 0x7 - 0xF are reserved
 }
 
+impl VerticalAccuracyMeters {
+    /// Upper bound of the accuracy in meters, `None` if unknown or 150
+    ///  meters or more
+    pub fn bound_meters(&self) -> Option<f32> {
+        match self {
+            VerticalAccuracyMeters::Gte150Unknown => None,
+            VerticalAccuracyMeters::Lt150 => Some(150.0),
+            VerticalAccuracyMeters::Lt45 => Some(45.0),
+            VerticalAccuracyMeters::Lt25 => Some(25.0),
+            VerticalAccuracyMeters::Lt10 => Some(10.0),
+            VerticalAccuracyMeters::Lt3 => Some(3.0),
+            VerticalAccuracyMeters::Lt1 => Some(1.0),
+        }
+    }
+}
+
 /// Speed Accuracy (in meters per second)
 #[derive(PrimitiveEnum_u8, Clone, Copy, Debug, PartialEq)]
 pub enum SpeedAccuracyMetersPerSecond {
@@ -385,6 +469,13 @@ impl Default for Header {
     }
 }
 
+impl Header {
+    /// The protocol version of the frame, `None` if unknown
+    pub fn version(&self) -> Option<ProtocolVersion> {
+        ProtocolVersion::from_primitive(self.protocol_version)
+    }
+}
+
 /// Remote ID Packet Frame
 #[derive(PackedStruct, Debug, Clone, Copy, PartialEq)]
 #[packed_struct(bit_numbering = "msb0", endian = "msb")]
@@ -412,7 +503,9 @@ pub enum Message {
 
     /// Remote ID Location Message
     Location(LocationMessage),
-    // Authentication(AuthenticationMessage),
+
+    /// Remote ID Authentication Message (single page)
+    Authentication(AuthenticationMessage),
     // SelfId(SelfIdMessage),
     // System(SystemMessage),
     // OperatorId(OperatorIdMessage),
@@ -559,6 +652,12 @@ pub enum LocationDecodeError {
 
     /// Unknown timestamp
     UnknownTimestamp,
+
+    /// Unknown track direction
+    UnknownDirection,
+
+    /// Encoded track direction out of range
+    InvalidDirection,
 }
 
 impl Display for LocationDecodeError {
@@ -570,6 +669,8 @@ impl Display for LocationDecodeError {
             LocationDecodeError::UnknownSpeed => write!(f, "Unknown speed"),
             LocationDecodeError::UnknownAltitude => write!(f, "Unknown altitude"),
             LocationDecodeError::UnknownTimestamp => write!(f, "Unknown timestamp"),
+            LocationDecodeError::UnknownDirection => write!(f, "Unknown direction"),
+            LocationDecodeError::InvalidDirection => write!(f, "Invalid direction"),
         }
     }
 }
@@ -577,7 +678,7 @@ impl Display for LocationDecodeError {
 /// Errors decoding a location message
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub enum LocationEncodeError {
-    /// Track angle is larger than
+    /// Track angle is larger than 359 degrees
     InvalidTrackAngle,
 
     /// Supplied ground speed was negative
@@ -585,14 +686,66 @@ pub enum LocationEncodeError {
 
     /// Unknown timestamp
     UnknownTimestamp,
+
+    /// Vertical speed is not a number
+    InvalidVerticalSpeed,
+
+    /// Ground speed is not a number
+    InvalidGroundSpeed,
+}
+
+/// Ground speed of a location message
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum GroundSpeed {
+    /// Speed in meters per second, below 254.25 m/s
+    Known(f32),
+
+    /// Speed of 254.25 m/s or more
+    Maximum,
+
+    /// Unknown speed
+    Unknown,
+}
+
+/// Vertical speed encoded in a location message
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct EncodedVerticalSpeed {
+    /// Encoded value, in half meters per second
+    pub value: i8,
+
+    /// If the speed was beyond +/- [`VERTICAL_SPEED_MAX_MPS`] and encoded
+    ///  as the maximum
+    pub saturated: bool,
 }
 
 impl LocationMessage {
-    /// Decode the direction
-    pub fn decode_direction(&self) -> u16 {
-        match self.ew_direction {
-            EastWestDirection::East => self.track_direction as u16,
-            EastWestDirection::West => self.track_direction as u16 + 180,
+    /// The message as defined by the latest protocol version
+    ///
+    /// The system failure status is reserved before F3411-22a, and read
+    ///  as undeclared.
+    pub fn for_version(mut self, version: ProtocolVersion) -> Self {
+        if version < ProtocolVersion::F3411_22a
+            && self.operational_status == OperationalStatus::SystemFailure
+        {
+            self.operational_status = OperationalStatus::Undeclared;
+        }
+
+        self
+    }
+
+    /// Decode the direction, in degrees clockwise from true north
+    ///
+    /// Directions are encoded from 0 to 179, the East/West bit adding 180.
+    ///  The unknown direction (361) is encoded as 181 with the bit set.
+    pub fn decode_direction(&self) -> Result<u16, LocationDecodeError> {
+        let direction = self.track_direction as u16;
+        match (self.ew_direction, direction) {
+            (EastWestDirection::West, DIRECTION_UNKNOWN_ENCODED) => {
+                Err(LocationDecodeError::UnknownDirection)
+            }
+            (_, 180..) => Err(LocationDecodeError::InvalidDirection),
+            (EastWestDirection::East, _) => Ok(direction),
+            (EastWestDirection::West, _) => Ok(direction + 180),
         }
     }
 
@@ -627,65 +780,112 @@ impl LocationMessage {
         ((altitude + 1000.0) * 2.0) as u16
     }
 
+    /// Decode the ground speed
+    ///
+    /// Speeds up to 63.75 m/s are encoded in quarters of meters per second,
+    ///  faster speeds in steps of 0.75 m/s above 63.75 m/s. With the 0.75
+    ///  multiplier, 254 stands for 254.25 m/s or more and 255 for unknown.
+    pub fn decode_ground_speed(&self) -> GroundSpeed {
+        match (self.speed_multiplier, self.speed) {
+            (SpeedMultiplier::X0_25, speed) => GroundSpeed::Known(speed as f32 * 0.25),
+            (SpeedMultiplier::X0_75, GROUND_SPEED_UNKNOWN_ENCODED) => GroundSpeed::Unknown,
+            (SpeedMultiplier::X0_75, GROUND_SPEED_MAXIMUM_ENCODED) => GroundSpeed::Maximum,
+            (SpeedMultiplier::X0_75, speed) => {
+                GroundSpeed::Known(speed as f32 * 0.75 + GROUND_SPEED_LOW_RANGE_MPS)
+            }
+        }
+    }
+
     /// Decode the speed in meters per second
     pub fn decode_speed(&self) -> Result<f32, LocationDecodeError> {
-        // Speed addition is added when the speed multiplier is 0.75
-        //  0.75 is used when speed exceeds 63.75 m/s
-        static HIGH_SPEED_ADDITION: f32 = 63.75; // (255.0 * 0.25);
-
-        let speed = match self.speed_multiplier {
-            SpeedMultiplier::X0_25 => self.speed as f32 * 0.25,
-            SpeedMultiplier::X0_75 => (self.speed as f32 * 0.75) + HIGH_SPEED_ADDITION,
-        };
-
-        if speed == 255.0 {
-            Err(LocationDecodeError::UnknownSpeed)
-        } else if speed == 254.25 {
-            Err(LocationDecodeError::SpeedGte254_25)
-        } else {
-            Ok(speed)
+        match self.decode_ground_speed() {
+            GroundSpeed::Known(speed) => Ok(speed),
+            GroundSpeed::Maximum => Err(LocationDecodeError::SpeedGte254_25),
+            GroundSpeed::Unknown => Err(LocationDecodeError::UnknownSpeed),
         }
     }
 
-    /// Encode the speed in meters per second
-    pub fn encode_speed(speed: f32) -> Result<(SpeedMultiplier, u8), LocationEncodeError> {
-        static THRESHOLD: f32 = 63.75; // 255 * 0.25
+    /// Encode the ground speed, rounded to the nearest encoded speed
+    ///
+    /// Speeds of 254.25 m/s or more are encoded as [`GroundSpeed::Maximum`].
+    pub fn encode_ground_speed(
+        speed: GroundSpeed,
+    ) -> Result<(SpeedMultiplier, u8), LocationEncodeError> {
+        let speed = match speed {
+            GroundSpeed::Known(speed) => speed,
+            GroundSpeed::Maximum => {
+                return Ok((SpeedMultiplier::X0_75, GROUND_SPEED_MAXIMUM_ENCODED))
+            }
+            GroundSpeed::Unknown => {
+                return Ok((SpeedMultiplier::X0_75, GROUND_SPEED_UNKNOWN_ENCODED))
+            }
+        };
 
         // TODO(R5): What if facing a direction but moving backwards due to wind?
         // Casting to a u8 here would eliminate sign data
+        if speed.is_nan() {
+            return Err(LocationEncodeError::InvalidGroundSpeed);
+        }
+
         if speed < 0.0 {
             return Err(LocationEncodeError::NegativeGroundSpeed);
         }
 
-        if speed <= THRESHOLD {
-            Ok((SpeedMultiplier::X0_25, (speed * 4.0) as u8))
-        } else if speed > THRESHOLD && speed < 254.25 {
-            Ok((SpeedMultiplier::X0_75, ((speed - THRESHOLD) / 0.75) as u8))
-        } else {
-            Ok((SpeedMultiplier::X0_75, 254))
+        if speed <= GROUND_SPEED_LOW_RANGE_MPS {
+            return Ok((SpeedMultiplier::X0_25, (speed * 4.0).round() as u8));
         }
+
+        if speed >= GROUND_SPEED_MAXIMUM_MPS {
+            return Ok((SpeedMultiplier::X0_75, GROUND_SPEED_MAXIMUM_ENCODED));
+        }
+
+        // rounding up to the maximum would report speeds at or above it
+        let encoded = ((speed - GROUND_SPEED_LOW_RANGE_MPS) / 0.75).round() as u8;
+        Ok((
+            SpeedMultiplier::X0_75,
+            encoded.min(GROUND_SPEED_MAXIMUM_ENCODED - 1),
+        ))
+    }
+
+    /// Encode the speed in meters per second
+    pub fn encode_speed(speed: f32) -> Result<(SpeedMultiplier, u8), LocationEncodeError> {
+        Self::encode_ground_speed(GroundSpeed::Known(speed))
     }
 
     /// Decode the vertical speed in meters per second
     pub fn decode_vertical_speed(&self) -> Result<f32, LocationDecodeError> {
-        let mut speed = (self.vertical_speed as f32) * 0.5;
-
-        if speed == 63.0 {
+        if self.vertical_speed == VERTICAL_SPEED_UNKNOWN_ENCODED {
             return Err(LocationDecodeError::UnknownSpeed);
         }
 
-        if speed >= 62.0 {
-            speed = 62.0;
-        } else if speed <= -62.0 {
-            speed = -62.0;
+        let speed = (self.vertical_speed as f32) * 0.5;
+        Ok(speed.clamp(-VERTICAL_SPEED_MAX_MPS, VERTICAL_SPEED_MAX_MPS))
+    }
+
+    /// Encode the vertical speed in meters per second, saturated at
+    ///  +/- [`VERTICAL_SPEED_MAX_MPS`]
+    ///
+    /// Speeds of 63 m/s or more are encoded as the maximum speed, never as
+    ///  the unknown speed: use [`VERTICAL_SPEED_UNKNOWN_ENCODED`] for those.
+    pub fn encode_vertical_speed_checked(
+        speed: f32,
+    ) -> Result<EncodedVerticalSpeed, LocationEncodeError> {
+        if speed.is_nan() {
+            return Err(LocationEncodeError::InvalidVerticalSpeed);
         }
 
-        Ok(speed)
+        let clamped = speed.clamp(-VERTICAL_SPEED_MAX_MPS, VERTICAL_SPEED_MAX_MPS);
+        Ok(EncodedVerticalSpeed {
+            value: (clamped * 2.0).round() as i8,
+            saturated: clamped != speed,
+        })
     }
 
-    /// Encode the vertical speed
+    /// Encode the vertical speed in meters per second, saturated at
+    ///  +/- [`VERTICAL_SPEED_MAX_MPS`], or unknown if not a number
     pub fn encode_vertical_speed(speed: f32) -> i8 {
-        (speed * 2.0) as i8
+        Self::encode_vertical_speed_checked(speed)
+            .map_or(VERTICAL_SPEED_UNKNOWN_ENCODED, |encoded| encoded.value)
     }
 
     /// Decode the latitude
@@ -708,34 +908,45 @@ impl LocationMessage {
         (longitude * 1e7) as i32
     }
 
-    /// Decode the timestamp
-    pub fn decode_timestamp(&self) -> Result<DateTime<Utc>, LocationDecodeError> {
-        // The timestamp is encoded as the number of
-        let now = Utc::now();
-        let current_hour = now
+    /// Decode the timestamp near a reference time
+    ///
+    /// The timestamp is encoded as tenths of seconds since the start of
+    ///  the hour, the hour is deduced from the reference time: the time of
+    ///  reception of a live message, or of recording of a replayed one.
+    ///
+    /// The decoded time is the one, in the hour before, of or after the
+    ///  reference time, which falls in the hour ending
+    ///  [`LOCATION_TIMESTAMP_LEAD_MS`] after the reference time. Received at
+    ///  00:00.1, 59:59.9 is decoded in the previous hour; received at 59:59.9,
+    ///  00:00.1 is decoded in the next hour as the clock of the sender may
+    ///  run ahead of the reference clock.
+    ///
+    /// Encoded values of an hour or more are unknown timestamps.
+    pub fn decode_timestamp(
+        &self,
+        reference: DateTime<Utc>,
+    ) -> Result<DateTime<Utc>, LocationDecodeError> {
+        if self.timestamp >= LOCATION_TIMESTAMP_TENTHS_PER_HOUR {
+            return Err(LocationDecodeError::UnknownTimestamp);
+        }
+
+        let reference_hour = reference
             .with_minute(0)
             .and_then(|x| x.with_second(0))
             .and_then(|x| x.with_nanosecond(0))
             .ok_or(LocationDecodeError::UnknownTimestamp)?;
 
-        let ms_since_hour = (now - current_hour).num_milliseconds();
-        let tenths_since_hour = (ms_since_hour / 100) as u16; // 36000 is max value, so safe to cast
-        let encoded_duration_ms = Duration::try_milliseconds(self.timestamp as i64 * 100)
-            .ok_or(LocationDecodeError::UnknownTimestamp)?;
-
-        #[cfg(not(tarpaulin_include))]
-        // no_coverage: (Rnever) won't fail
-        let delta = Duration::try_hours(1).ok_or(LocationDecodeError::UnknownTimestamp)?;
+        let hour = Duration::milliseconds(LOCATION_TIMESTAMP_TENTHS_PER_HOUR as i64 * 100);
+        let latest = reference + Duration::milliseconds(LOCATION_TIMESTAMP_LEAD_MS);
+        let timestamp = reference_hour + Duration::milliseconds(self.timestamp as i64 * 100);
 
-        let timestamp = if self.timestamp > tenths_since_hour {
-            // the encoded timestamp refers to tenths of seconds since the previous hour
-            (current_hour - delta) + encoded_duration_ms
+        if timestamp > latest {
+            Ok(timestamp - hour)
+        } else if timestamp <= latest - hour {
+            Ok(timestamp + hour)
         } else {
-            // the encoded timestamp refers to tenths of seconds since the current hour
-            current_hour + encoded_duration_ms
-        };
-
-        Ok(timestamp)
+            Ok(timestamp)
+        }
     }
 
     /// Encode the timestamp
@@ -751,9 +962,515 @@ impl LocationMessage {
     }
 }
 
+/// Authentication timestamps are seconds since 00:00:00 01/01/2019 UTC
+const AUTHENTICATION_EPOCH_UNIX_SECONDS: i64 = 1_546_300_800;
+
+/// Authentication data bytes carried by the first page (page 0)
+const AUTHENTICATION_FIRST_PAGE_DATA_BYTES: usize = 17;
+
+/// Authentication data bytes carried by pages 1 through 15
+const AUTHENTICATION_PAGE_DATA_BYTES: usize = 23;
+
+/// Highest page index allowed in a multi-page authentication message
+pub const AUTHENTICATION_MAX_PAGE_INDEX: u8 = 15;
+
+/// Remote ID Authentication Message (one page of a signature)
+#[derive(PackedStruct, Debug, Clone, Copy, PartialEq)]
+#[packed_struct(bit_numbering = "msb0", endian = "msb", size_bytes = "24")]
+pub struct AuthenticationMessage {
+    /// Authentication Type
+    #[packed_field(size_bits = "4", ty = "enum")]
+    pub authentication_type: UaAuthenticationType,
+
+    /// Page Number (0 - 15)
+    #[packed_field(size_bits = "4")]
+    pub page_number: Integer<u8, Bits<4>>,
+
+    /// Page contents
+    ///  Page 0 is an [`AuthenticationFirstPage`], later pages are
+    ///  raw authentication data
+    pub data: [u8; 23],
+}
+
+/// Contents of the first page (page 0) of an authentication message
+#[derive(PackedStruct, Debug, Clone, Copy, PartialEq)]
+#[packed_struct(bit_numbering = "msb0", endian = "msb", size_bytes = "23")]
+pub struct AuthenticationFirstPage {
+    /// Index of the last page of this authentication message
+    #[packed_field(size_bytes = "1")]
+    pub last_page_index: u8,
+
+    /// Total length in bytes of the authentication data across all pages
+    #[packed_field(size_bytes = "1")]
+    pub length: u8,
+
+    /// Seconds since 00:00:00 01/01/2019 UTC
+    #[packed_field(size_bytes = "4", endian = "lsb")]
+    pub timestamp: u32,
+
+    /// First bytes of the authentication data
+    pub data: [u8; 17],
+}
+
+/// Errors assembling an authentication message
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum AuthenticationDecodeError {
+    /// Page 0 was not received or could not be unpacked
+    MissingFirstPage,
+
+    /// The last page index is greater than 15
+    InvalidPageIndex,
+
+    /// A page between 0 and the last page index was not received
+    MissingPage(u8),
+
+    /// Pages disagree on the authentication type
+    MismatchedType,
+
+    /// The declared length doesn't fit in the received pages
+    InvalidLength,
+
+    /// Unknown timestamp
+    UnknownTimestamp,
+}
+
+impl Display for AuthenticationDecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthenticationDecodeError::MissingFirstPage => write!(f, "Missing first page"),
+            AuthenticationDecodeError::InvalidPageIndex => write!(f, "Invalid page index"),
+            AuthenticationDecodeError::MissingPage(page) => write!(f, "Missing page {page}"),
+            AuthenticationDecodeError::MismatchedType => {
+                write!(f, "Pages have different authentication types")
+            }
+            AuthenticationDecodeError::InvalidLength => write!(f, "Invalid length"),
+            AuthenticationDecodeError::UnknownTimestamp => write!(f, "Unknown timestamp"),
+        }
+    }
+}
+
+/// Errors encoding an authentication message
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum AuthenticationEncodeError {
+    /// Timestamp is before 01/01/2019 or too far in the future
+    InvalidTimestamp,
+}
+
+impl AuthenticationFirstPage {
+    /// Decode the timestamp
+    pub fn decode_timestamp(&self) -> Result<DateTime<Utc>, AuthenticationDecodeError> {
+        DateTime::from_timestamp(AUTHENTICATION_EPOCH_UNIX_SECONDS + self.timestamp as i64, 0)
+            .ok_or(AuthenticationDecodeError::UnknownTimestamp)
+    }
+
+    /// Encode the timestamp
+    pub fn encode_timestamp(timestamp: DateTime<Utc>) -> Result<u32, AuthenticationEncodeError> {
+        u32::try_from(timestamp.timestamp() - AUTHENTICATION_EPOCH_UNIX_SECONDS)
+            .map_err(|_| AuthenticationEncodeError::InvalidTimestamp)
+    }
+}
+
+/// A complete authentication message assembled from one or more pages
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthenticationSignature {
+    /// Authentication Type
+    pub authentication_type: UaAuthenticationType,
+
+    /// Time at which the authentication data was generated
+    pub timestamp: DateTime<Utc>,
+
+    /// Authentication data (e.g. a signature), trimmed to the declared length
+    pub data: Vec<u8>,
+}
+
+impl AuthenticationSignature {
+    /// Assemble a signature from its pages
+    ///
+    /// Pages may be provided in any order. Duplicate pages are tolerated,
+    ///  the last one provided wins.
+    pub fn assemble(
+        pages: &[AuthenticationMessage],
+    ) -> Result<AuthenticationSignature, AuthenticationDecodeError> {
+        let mut ordered: [Option<&AuthenticationMessage>; 16] = [None; 16];
+        for page in pages {
+            ordered[u8::from(page.page_number) as usize] = Some(page);
+        }
+
+        let first = ordered[0].ok_or(AuthenticationDecodeError::MissingFirstPage)?;
+        let first_page = AuthenticationFirstPage::unpack(&first.data)
+            .map_err(|_| AuthenticationDecodeError::MissingFirstPage)?;
+
+        if first_page.last_page_index > AUTHENTICATION_MAX_PAGE_INDEX {
+            return Err(AuthenticationDecodeError::InvalidPageIndex);
+        }
+
+        let length = first_page.length as usize;
+        let capacity = AUTHENTICATION_FIRST_PAGE_DATA_BYTES
+            + first_page.last_page_index as usize * AUTHENTICATION_PAGE_DATA_BYTES;
+
+        if length > capacity {
+            return Err(AuthenticationDecodeError::InvalidLength);
+        }
+
+        let mut data = first_page.data.to_vec();
+        for index in 1..=first_page.last_page_index {
+            let page =
+                ordered[index as usize].ok_or(AuthenticationDecodeError::MissingPage(index))?;
+            if page.authentication_type != first.authentication_type {
+                return Err(AuthenticationDecodeError::MismatchedType);
+            }
+
+            data.extend_from_slice(&page.data);
+        }
+
+        data.truncate(length);
+        Ok(AuthenticationSignature {
+            authentication_type: first.authentication_type,
+            timestamp: first_page.decode_timestamp()?,
+            data,
+        })
+    }
+}
+
+impl BasicMessage {
+    /// Decode the UAS identifier, without padding
+    ///
+    /// Returns `None` if the identifier is not valid UTF-8 or empty.
+    pub fn decode_uas_id(&self) -> Option<String> {
+        let identifier = std::str::from_utf8(&self.uas_id).ok()?;
+        let identifier = identifier.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        match identifier.is_empty() {
+            true => None,
+            false => Some(identifier.to_string()),
+        }
+    }
+}
+
+/// Remote ID System Message, locating the operator of the aircraft
+#[derive(PackedStruct, Debug, Clone, Copy, PartialEq)]
+#[packed_struct(bit_numbering = "msb0", endian = "msb", size_bytes = "24")]
+pub struct SystemMessage {
+    /// Reserved Field
+    #[packed_field(size_bits = "3")]
+    pub reserved_0: Integer<u8, Bits<3>>,
+
+    /// Classification Type
+    #[packed_field(size_bits = "3", ty = "enum")]
+    pub classification_type: UaClassification,
+
+    /// Operator Location Type
+    #[packed_field(size_bits = "2", ty = "enum")]
+    pub operator_location_source: OperatorLocationSource,
+
+    /// Operator Latitude
+    #[packed_field(size_bytes = "4", endian = "lsb")]
+    pub operator_latitude: i32,
+
+    /// Operator Longitude
+    #[packed_field(size_bytes = "4", endian = "lsb")]
+    pub operator_longitude: i32,
+
+    /// Number of aircraft in the area of a group operation
+    #[packed_field(size_bytes = "2", endian = "lsb")]
+    pub area_count: u16,
+
+    /// Radius of the area of a group operation, in units of 10 meters
+    #[packed_field(size_bytes = "1")]
+    pub area_radius: u8,
+
+    /// Ceiling of the area of a group operation
+    #[packed_field(size_bytes = "2", endian = "lsb")]
+    pub area_ceiling: u16,
+
+    /// Floor of the area of a group operation
+    #[packed_field(size_bytes = "2", endian = "lsb")]
+    pub area_floor: u16,
+
+    /// UA Category, if the classification type is EU
+    #[packed_field(size_bits = "4", ty = "enum")]
+    pub category: EuropeanUnionCategory,
+
+    /// UA Class, if the classification type is EU
+    #[packed_field(size_bits = "4", ty = "enum")]
+    pub class: EuropeanUnionClass,
+
+    /// Geodetic altitude of the operator
+    #[packed_field(size_bytes = "2", endian = "lsb")]
+    pub operator_altitude: u16,
+
+    /// Seconds since 00:00:00 01/01/2019 UTC
+    #[packed_field(size_bytes = "4", endian = "lsb")]
+    pub timestamp: u32,
+
+    /// Reserved Field
+    #[packed_field(size_bytes = "1")]
+    pub reserved_1: u8,
+}
+
+impl SystemMessage {
+    /// The message as defined by the latest protocol version
+    ///
+    /// The fields following the area floor are reserved by F3411-19, and
+    ///  read as undeclared or unknown.
+    pub fn for_version(mut self, version: ProtocolVersion) -> Self {
+        if version < ProtocolVersion::F3411_20 {
+            self.classification_type = UaClassification::Undeclared;
+            self.category = EuropeanUnionCategory::Undefined;
+            self.class = EuropeanUnionClass::Undefined;
+            self.operator_altitude = 0;
+            self.timestamp = 0;
+        }
+
+        self
+    }
+
+    /// Decode the operator location as latitude and longitude
+    ///
+    /// Returns `None` if the location is unknown (both zero) or invalid.
+    pub fn decode_operator_location(&self) -> Option<(f64, f64)> {
+        if self.operator_latitude == 0 && self.operator_longitude == 0 {
+            return None;
+        }
+
+        let latitude = self.operator_latitude as f64 * 1e-7;
+        let longitude = self.operator_longitude as f64 * 1e-7;
+        match latitude.abs() <= 90.0 && longitude.abs() <= 180.0 {
+            true => Some((latitude, longitude)),
+            false => None,
+        }
+    }
+
+    /// Encode a latitude or longitude of the operator
+    pub fn encode_coordinate(degrees: f64) -> i32 {
+        (degrees * 1e7) as i32
+    }
+
+    /// Decode the geodetic altitude of the operator in meters
+    ///
+    /// Returns `None` if the altitude is unknown.
+    pub fn decode_operator_altitude(&self) -> Option<f32> {
+        match self.operator_altitude {
+            0 => None,
+            altitude => Some((altitude as f32 * 0.5) - 1000.0),
+        }
+    }
+
+    /// Decode the timestamp
+    pub fn decode_timestamp(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(AUTHENTICATION_EPOCH_UNIX_SECONDS + self.timestamp as i64, 0)
+    }
+}
+
+/// Remote ID Operator ID Message
+#[derive(PackedStruct, Debug, Clone, Copy, PartialEq)]
+#[packed_struct(bit_numbering = "msb0", endian = "msb", size_bytes = "24")]
+pub struct OperatorIdMessage {
+    /// Operator ID Type, 0 for an operator registration number
+    #[packed_field(size_bytes = "1")]
+    pub operator_id_type: u8,
+
+    /// Operator ID, ASCII padded with nulls
+    pub operator_id: [u8; 20],
+
+    /// Reserved Field
+    pub reserved: [u8; 3],
+}
+
+impl OperatorIdMessage {
+    /// Decode the operator identifier, without padding
+    ///
+    /// Returns `None` if the identifier is not valid UTF-8 or empty.
+    pub fn decode_operator_id(&self) -> Option<String> {
+        let identifier = std::str::from_utf8(&self.operator_id).ok()?;
+        let identifier = identifier.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        match identifier.is_empty() {
+            true => None,
+            false => Some(identifier.to_string()),
+        }
+    }
+}
+
+/// Length of each message of a Message Pack, including its header
+pub const MESSAGE_PACK_MESSAGE_SIZE: u8 = 25;
+
+/// Maximum number of messages in a Message Pack
+pub const MESSAGE_PACK_MAX_MESSAGES: usize = 9;
+
+/// Length of the Message Pack header, message size and message count
+const MESSAGE_PACK_HEADER_BYTES: usize = 3;
+
+/// Remote ID Message Pack, several messages of one aircraft sent together
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessagePack {
+    /// The packed messages
+    pub frames: Vec<Frame>,
+}
+
+/// Errors unpacking a Message Pack
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum MessagePackError {
+    /// The header is not a Message Pack header
+    InvalidHeader,
+
+    /// The declared message size is not 25 bytes
+    InvalidMessageSize,
+
+    /// No messages, or more than [`MESSAGE_PACK_MAX_MESSAGES`]
+    InvalidMessageCount,
+
+    /// The length doesn't match the declared message count
+    InvalidLength,
+
+    /// A Message Pack contains another Message Pack
+    Nested,
+}
+
+impl Display for MessagePackError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MessagePackError::InvalidHeader => write!(f, "Invalid header"),
+            MessagePackError::InvalidMessageSize => write!(f, "Invalid message size"),
+            MessagePackError::InvalidMessageCount => write!(f, "Invalid message count"),
+            MessagePackError::InvalidLength => write!(f, "Invalid length"),
+            MessagePackError::Nested => write!(f, "Nested message pack"),
+        }
+    }
+}
+
+impl MessagePack {
+    /// Unpack a Message Pack from its bytes
+    pub fn unpack(bytes: &[u8]) -> Result<Self, MessagePackError> {
+        if bytes.len() < MESSAGE_PACK_HEADER_BYTES {
+            return Err(MessagePackError::InvalidLength);
+        }
+
+        let header = Header::unpack(&[bytes[0]]).map_err(|_| MessagePackError::InvalidHeader)?;
+        if header.message_type != MessageType::MessagePack {
+            return Err(MessagePackError::InvalidHeader);
+        }
+
+        if bytes[1] != MESSAGE_PACK_MESSAGE_SIZE {
+            return Err(MessagePackError::InvalidMessageSize);
+        }
+
+        let count = bytes[2] as usize;
+        if count == 0 || count > MESSAGE_PACK_MAX_MESSAGES {
+            return Err(MessagePackError::InvalidMessageCount);
+        }
+
+        let messages = &bytes[MESSAGE_PACK_HEADER_BYTES..];
+        if messages.len() != count * MESSAGE_PACK_MESSAGE_SIZE as usize {
+            return Err(MessagePackError::InvalidLength);
+        }
+
+        let frames = messages
+            .chunks_exact(MESSAGE_PACK_MESSAGE_SIZE as usize)
+            .map(|chunk| {
+                let chunk =
+                    <[u8; 25]>::try_from(chunk).map_err(|_| MessagePackError::InvalidLength)?;
+                let frame = Frame::unpack(&chunk).map_err(|_| MessagePackError::InvalidLength)?;
+                match frame.header.message_type {
+                    MessageType::MessagePack => Err(MessagePackError::Nested),
+                    _ => Ok(frame),
+                }
+            })
+            .collect::<Result<Vec<Frame>, MessagePackError>>()?;
+
+        Ok(MessagePack { frames })
+    }
+
+    /// Pack the Message Pack to its bytes
+    pub fn pack(&self) -> Result<Vec<u8>, MessagePackError> {
+        if self.frames.is_empty() || self.frames.len() > MESSAGE_PACK_MAX_MESSAGES {
+            return Err(MessagePackError::InvalidMessageCount);
+        }
+
+        let header = Header {
+            message_type: MessageType::MessagePack,
+            ..Default::default()
+        }
+        .pack()
+        .map_err(|_| MessagePackError::InvalidHeader)?;
+
+        let mut bytes = vec![
+            header[0],
+            MESSAGE_PACK_MESSAGE_SIZE,
+            self.frames.len() as u8,
+        ];
+        for frame in &self.frames {
+            if frame.header.message_type == MessageType::MessagePack {
+                return Err(MessagePackError::Nested);
+            }
+
+            let frame = frame.pack().map_err(|_| MessagePackError::InvalidLength)?;
+            bytes.extend_from_slice(&frame);
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Latest known state of a Remote ID aircraft, combined from the
+///  identification, position and velocity it reported separately
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetridState {
+    /// Identifier of the aircraft
+    pub identifier: String,
+
+    /// Last identification
+    pub identification: Option<AircraftId>,
+
+    /// Last position
+    pub position: Option<AircraftPosition>,
+
+    /// Last velocity
+    pub velocity: Option<AircraftVelocity>,
+
+    /// If the identification is missing or was received too long ago
+    pub identification_stale: bool,
+
+    /// If the position is missing or was received too long ago
+    pub position_stale: bool,
+
+    /// If the velocity is missing or was received too long ago
+    pub velocity_stale: bool,
+
+    /// When the state was published
+    pub timestamp_network: DateTime<Utc>,
+}
+
+impl NetridState {
+    /// Combines the last components of an aircraft, those received more
+    ///  than `stale_ms` before `now` being flagged as stale
+    pub fn new(
+        identifier: &str,
+        identification: Option<AircraftId>,
+        position: Option<AircraftPosition>,
+        velocity: Option<AircraftVelocity>,
+        now: DateTime<Utc>,
+        stale_ms: u32,
+    ) -> Self {
+        let since = now - Duration::try_milliseconds(stale_ms as i64).unwrap_or(Duration::zero());
+        let stale = |received: Option<DateTime<Utc>>| received.is_none_or(|at| at < since);
+
+        NetridState {
+            identifier: identifier.to_string(),
+            identification_stale: stale(identification.as_ref().map(|i| i.timestamp_network)),
+            position_stale: stale(position.as_ref().map(|p| p.timestamp_network)),
+            velocity_stale: stale(velocity.as_ref().map(|v| v.timestamp_network)),
+            identification,
+            position,
+            velocity,
+            timestamp_network: now,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::{any, prop_assert, prop_assert_eq, proptest};
 
     #[test]
     fn test_basic_id_message() {
@@ -818,6 +1535,48 @@ mod tests {
 
         let bytes = frame.clone().pack().unwrap();
         assert_eq!(bytes.len(), 25);
+
+        assert_eq!(msg.horizontal_accuracy.bound_meters(), Some(1852.0));
+        assert_eq!(msg.vertical_accuracy.bound_meters(), Some(150.0));
+        assert_eq!(HorizontalAccuracyMeters::Gte18520.bound_meters(), None);
+        assert_eq!(VerticalAccuracyMeters::Gte150Unknown.bound_meters(), None);
+
+        let failure = LocationMessage {
+            operational_status: OperationalStatus::SystemFailure,
+            ..msg
+        };
+        assert_eq!(failure.for_version(ProtocolVersion::F3411_22a), failure);
+        assert_eq!(
+            failure
+                .for_version(ProtocolVersion::F3411_20)
+                .operational_status,
+            OperationalStatus::Undeclared
+        );
+        assert_eq!(msg.for_version(ProtocolVersion::F3411_19), msg);
+    }
+
+    #[test]
+    fn test_protocol_version() {
+        let header = Header::default();
+        assert_eq!(header.version(), Some(ProtocolVersion::F3411_22a));
+
+        for (version, expected) in [
+            (0, Some(ProtocolVersion::F3411_19)),
+            (1, Some(ProtocolVersion::F3411_20)),
+            (2, Some(ProtocolVersion::F3411_22a)),
+            (3, None),
+            (0xF, None),
+        ] {
+            let header = Header {
+                protocol_version: version,
+                ..header
+            };
+            assert_eq!(header.version(), expected);
+
+            // unknown versions still unpack, to be rejected by the receiver
+            let bytes = header.pack().unwrap();
+            assert_eq!(Header::unpack(&bytes).unwrap().protocol_version, version);
+        }
     }
 
     #[test]
@@ -828,7 +1587,8 @@ mod tests {
         let actual_vertical_speed = 0.0;
         let actual_altitude = 102.0;
 
-        let actual_timestamp = Utc::now();
+        // a whole number of tenths of seconds, as encoded
+        let actual_timestamp = DateTime::from_timestamp(1_700_000_000, 300_000_000).unwrap();
         let actual_track_direction = 190;
 
         let (ew_direction, track_direction) =
@@ -864,15 +1624,14 @@ mod tests {
             reserved_2: 0,
         };
 
-        assert_eq!(msg.decode_direction(), actual_track_direction);
+        assert_eq!(msg.decode_direction(), Ok(actual_track_direction));
         assert_eq!(msg.decode_speed(), Ok(actual_speed));
         assert_eq!(msg.decode_vertical_speed(), Ok(actual_vertical_speed));
         assert_eq!(msg.decode_latitude(), actual_latitude);
         assert_eq!(msg.decode_longitude(), actual_longitude);
         assert_eq!(msg.decode_altitude(), Ok(actual_altitude));
 
-        let delta = msg.decode_timestamp().unwrap() - actual_timestamp;
-        assert!(delta < Duration::try_milliseconds(10).unwrap());
+        assert_eq!(msg.decode_timestamp(actual_timestamp), Ok(actual_timestamp));
 
         // direction
         assert_eq!(
@@ -888,6 +1647,25 @@ mod tests {
             (EastWestDirection::West, 0)
         );
 
+        msg.ew_direction = EastWestDirection::West;
+        msg.track_direction = 181;
+        assert_eq!(
+            msg.decode_direction(),
+            Err(LocationDecodeError::UnknownDirection)
+        );
+
+        msg.track_direction = 200;
+        assert_eq!(
+            msg.decode_direction(),
+            Err(LocationDecodeError::InvalidDirection)
+        );
+
+        msg.ew_direction = EastWestDirection::East;
+        assert_eq!(
+            msg.decode_direction(),
+            Err(LocationDecodeError::InvalidDirection)
+        );
+
         // altitude
         msg.pressure_altitude = 0;
         assert_eq!(
@@ -920,6 +1698,60 @@ mod tests {
         assert_eq!(multiplier, SpeedMultiplier::X0_75);
         assert!(((speed as f32) - 253.986).abs() < 1.0);
 
+        // ground speeds, as tabulated by ASTM F3411
+        for (speed, encoded, decoded) in [
+            (0.0, (SpeedMultiplier::X0_25, 0), GroundSpeed::Known(0.0)),
+            (10.1, (SpeedMultiplier::X0_25, 40), GroundSpeed::Known(10.0)),
+            (
+                10.2,
+                (SpeedMultiplier::X0_25, 41),
+                GroundSpeed::Known(10.25),
+            ),
+            (
+                63.75,
+                (SpeedMultiplier::X0_25, 255),
+                GroundSpeed::Known(63.75),
+            ),
+            (64.5, (SpeedMultiplier::X0_75, 1), GroundSpeed::Known(64.5)),
+            (
+                100.0,
+                (SpeedMultiplier::X0_75, 48),
+                GroundSpeed::Known(99.75),
+            ),
+            (
+                253.5,
+                (SpeedMultiplier::X0_75, 253),
+                GroundSpeed::Known(253.5),
+            ),
+            (
+                254.0,
+                (SpeedMultiplier::X0_75, 253),
+                GroundSpeed::Known(253.5),
+            ),
+            (254.25, (SpeedMultiplier::X0_75, 254), GroundSpeed::Maximum),
+            (1000.0, (SpeedMultiplier::X0_75, 254), GroundSpeed::Maximum),
+        ] {
+            assert_eq!(LocationMessage::encode_speed(speed), Ok(encoded));
+            (msg.speed_multiplier, msg.speed) = encoded;
+            assert_eq!(msg.decode_ground_speed(), decoded);
+        }
+
+        assert_eq!(
+            LocationMessage::encode_ground_speed(GroundSpeed::Unknown),
+            Ok((SpeedMultiplier::X0_75, GROUND_SPEED_UNKNOWN_ENCODED))
+        );
+        assert_eq!(
+            LocationMessage::encode_ground_speed(GroundSpeed::Maximum),
+            Ok((SpeedMultiplier::X0_75, GROUND_SPEED_MAXIMUM_ENCODED))
+        );
+        assert_eq!(
+            LocationMessage::encode_speed(f32::NAN),
+            Err(LocationEncodeError::InvalidGroundSpeed)
+        );
+        msg.speed_multiplier = SpeedMultiplier::X0_25;
+        msg.speed = 255;
+        assert_eq!(msg.decode_speed(), Ok(63.75));
+
         // vertical speed
         msg.vertical_speed = 126;
         assert_eq!(
@@ -935,21 +1767,497 @@ mod tests {
         msg.vertical_speed = -123;
         assert_eq!(msg.decode_vertical_speed().unwrap(), -61.5);
 
-        // timestamp
-        // let now = Utc::now();
-        // let current_hour = now
-        //     .with_minute(0)
-        //     .and_then(|x| x.with_second(0))
-        //     .and_then(|x| x.with_nanosecond(0))
-        //     .ok_or_else(|| LocationDecodeError::UnknownTimestamp)?;
-
-        // let ms_since_hour = (now - current_hour).num_milliseconds();
-        // let tenths_since_hour = (ms_since_hour / 100) as u16; // 36000 is max value, so safe to cast
-        // let encoded_duration_ms = Duration::try_milliseconds(self.timestamp as i64 * 100)
-        //     .ok_or_else(|| LocationDecodeError::UnknownTimestamp)?;
-
-        // let delta = Duration::try_hours(1).ok_or_else(|| LocationDecodeError::UnknownTimestamp)?;
-        // msg.timestamp = tenths_since_hour + 100;
-        // assert_eq!(msg.decode_timestamp().unwrap(), current_hour + Duration::try_hours(1).unwrap());
+        // the unknown speed is never encoded from a speed
+        for speed in [63.0, 62.5, 1000.0, f32::INFINITY] {
+            let encoded = LocationMessage::encode_vertical_speed_checked(speed).unwrap();
+            assert_eq!(
+                encoded,
+                EncodedVerticalSpeed {
+                    value: 124,
+                    saturated: true
+                }
+            );
+        }
+        let encoded = LocationMessage::encode_vertical_speed_checked(-63.0).unwrap();
+        assert_eq!((encoded.value, encoded.saturated), (-124, true));
+        let encoded = LocationMessage::encode_vertical_speed_checked(-10.25).unwrap();
+        assert!(!encoded.saturated);
+        assert_eq!(
+            LocationMessage::encode_vertical_speed_checked(f32::NAN),
+            Err(LocationEncodeError::InvalidVerticalSpeed)
+        );
+        msg.vertical_speed = LocationMessage::encode_vertical_speed(63.0);
+        assert_eq!(msg.decode_vertical_speed().unwrap(), 62.0);
+        msg.vertical_speed = LocationMessage::encode_vertical_speed(f32::NAN);
+        assert_eq!(
+            msg.decode_vertical_speed().unwrap_err(),
+            LocationDecodeError::UnknownSpeed
+        );
+
+        // timestamp, received at 12:30:00
+        let current_hour = DateTime::from_timestamp(1_700_000_000 / 3600 * 3600, 0).unwrap();
+        let now = current_hour + Duration::try_minutes(30).unwrap();
+        msg.timestamp = 9000; // 15:00.0
+        assert_eq!(
+            msg.decode_timestamp(now).unwrap(),
+            current_hour + Duration::try_minutes(15).unwrap()
+        );
+
+        // later in the hour than the reception, so in the previous hour
+        msg.timestamp = 27000; // 45:00.0
+        assert_eq!(
+            msg.decode_timestamp(now).unwrap(),
+            current_hour - Duration::try_minutes(15).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_timestamp_hour_boundary() {
+        let hour = DateTime::from_timestamp(1_700_000_000 / 3600 * 3600, 0).unwrap();
+        let tenths = |tenths: i64| Duration::try_milliseconds(tenths * 100).unwrap();
+        let mut msg = LocationMessage::unpack(&[0; 24]).unwrap();
+
+        // sent at 59:59.9, received at 00:00.1 of the next hour
+        msg.timestamp = 35999;
+        assert_eq!(
+            msg.decode_timestamp(hour + tenths(1)).unwrap(),
+            hour - tenths(1)
+        );
+
+        // sent at 00:00.1 by a clock running ahead, received at 59:59.9
+        msg.timestamp = 1;
+        assert_eq!(
+            msg.decode_timestamp(hour - tenths(1)).unwrap(),
+            hour + tenths(1)
+        );
+
+        // both in the same hour
+        assert_eq!(
+            msg.decode_timestamp(hour + tenths(1)).unwrap(),
+            hour + tenths(1)
+        );
+        msg.timestamp = 35999;
+        assert_eq!(
+            msg.decode_timestamp(hour - tenths(1)).unwrap(),
+            hour - tenths(1)
+        );
+
+        // up to the lead ahead of the reference, else in the previous hour
+        let lead = Duration::try_milliseconds(LOCATION_TIMESTAMP_LEAD_MS).unwrap();
+        msg.timestamp = LocationMessage::encode_timestamp(hour + lead).unwrap();
+        assert_eq!(msg.decode_timestamp(hour).unwrap(), hour + lead);
+        msg.timestamp += 1;
+        assert_eq!(
+            msg.decode_timestamp(hour).unwrap(),
+            hour + lead + tenths(1) - Duration::try_hours(1).unwrap()
+        );
+
+        // decoded timestamps are within the hour before the reference
+        for timestamp in (0..LOCATION_TIMESTAMP_TENTHS_PER_HOUR).step_by(7) {
+            msg.timestamp = timestamp;
+            let reference = hour + tenths(timestamp as i64 * 3 % 36000);
+            let decoded = msg.decode_timestamp(reference).unwrap();
+            assert!(decoded <= reference + lead);
+            assert!(decoded > reference + lead - Duration::try_hours(1).unwrap());
+            assert_eq!(LocationMessage::encode_timestamp(decoded), Ok(timestamp));
+        }
+
+        msg.timestamp = LOCATION_TIMESTAMP_TENTHS_PER_HOUR;
+        assert_eq!(
+            msg.decode_timestamp(hour),
+            Err(LocationDecodeError::UnknownTimestamp)
+        );
+        msg.timestamp = u16::MAX;
+        assert_eq!(
+            msg.decode_timestamp(hour),
+            Err(LocationDecodeError::UnknownTimestamp)
+        );
+    }
+
+    #[test]
+    fn test_direction_round_trip() {
+        let mut msg = LocationMessage::unpack(&[0; 24]).unwrap();
+        for direction in 0..=359 {
+            let (ew_direction, track_direction) =
+                LocationMessage::encode_direction(direction).unwrap();
+            assert_eq!(ew_direction == EastWestDirection::West, direction >= 180);
+            assert!(track_direction < 180, "{direction}");
+
+            msg.ew_direction = ew_direction;
+            msg.track_direction = track_direction;
+            let msg = LocationMessage::unpack(&msg.pack().unwrap()).unwrap();
+            assert_eq!(msg.decode_direction(), Ok(direction));
+        }
+    }
+    #[test]
+    fn test_authentication_single_page() {
+        let timestamp = Utc::now().with_nanosecond(0).unwrap();
+        let first_page = AuthenticationFirstPage {
+            last_page_index: 0,
+            length: 4,
+            timestamp: AuthenticationFirstPage::encode_timestamp(timestamp).unwrap(),
+            data: [0xAB; 17],
+        };
+
+        let msg = AuthenticationMessage {
+            authentication_type: UaAuthenticationType::UasIdSignature,
+            page_number: 0.into(),
+            data: first_page.pack().unwrap(),
+        };
+
+        let frame = Frame {
+            header: Header {
+                message_type: MessageType::Authentication,
+                ..Default::default()
+            },
+            message: msg.pack().unwrap(),
+        };
+        let bytes = frame.pack().unwrap();
+        assert_eq!(bytes.len(), 25);
+
+        let frame = Frame::unpack(&bytes).unwrap();
+        let msg = AuthenticationMessage::unpack(&frame.message).unwrap();
+        let signature = AuthenticationSignature::assemble(&[msg]).unwrap();
+        assert_eq!(
+            signature.authentication_type,
+            UaAuthenticationType::UasIdSignature
+        );
+        assert_eq!(signature.timestamp, timestamp);
+        assert_eq!(signature.data, vec![0xAB; 4]);
+    }
+
+    #[test]
+    fn test_authentication_multiple_pages() {
+        let first_page = AuthenticationFirstPage {
+            last_page_index: 2,
+            length: 17 + 23 + 5,
+            timestamp: 0,
+            data: [0x00; 17],
+        };
+
+        let page = |page_number: u8, data: [u8; 23]| AuthenticationMessage {
+            authentication_type: UaAuthenticationType::UasIdSignature,
+            page_number: page_number.into(),
+            data,
+        };
+
+        let pages = vec![
+            page(2, [0x02; 23]),
+            page(0, first_page.pack().unwrap()),
+            page(1, [0x01; 23]),
+        ];
+
+        // out of order pages
+        let signature = AuthenticationSignature::assemble(&pages).unwrap();
+        assert_eq!(signature.data.len(), 45);
+        assert_eq!(signature.data[..17], [0x00; 17]);
+        assert_eq!(signature.data[17..40], [0x01; 23]);
+        assert_eq!(signature.data[40..], [0x02; 5]);
+        assert_eq!(
+            signature.timestamp,
+            DateTime::from_timestamp(AUTHENTICATION_EPOCH_UNIX_SECONDS, 0).unwrap()
+        );
+
+        // missing page
+        assert_eq!(
+            AuthenticationSignature::assemble(&pages[..2]).unwrap_err(),
+            AuthenticationDecodeError::MissingPage(1)
+        );
+
+        // missing first page
+        assert_eq!(
+            AuthenticationSignature::assemble(&[pages[0], pages[2]]).unwrap_err(),
+            AuthenticationDecodeError::MissingFirstPage
+        );
+
+        // mismatched types
+        let mut mismatched = pages.clone();
+        mismatched[0].authentication_type = UaAuthenticationType::OperatorIdSignature;
+        assert_eq!(
+            AuthenticationSignature::assemble(&mismatched).unwrap_err(),
+            AuthenticationDecodeError::MismatchedType
+        );
+
+        // declared length longer than the pages can carry
+        let mut too_long = pages.clone();
+        too_long[1].data = AuthenticationFirstPage {
+            length: 17 + 23 + 23 + 1,
+            ..first_page
+        }
+        .pack()
+        .unwrap();
+        assert_eq!(
+            AuthenticationSignature::assemble(&too_long).unwrap_err(),
+            AuthenticationDecodeError::InvalidLength
+        );
+
+        // timestamp before the authentication epoch
+        let timestamp = DateTime::from_timestamp(AUTHENTICATION_EPOCH_UNIX_SECONDS - 1, 0).unwrap();
+        assert_eq!(
+            AuthenticationFirstPage::encode_timestamp(timestamp).unwrap_err(),
+            AuthenticationEncodeError::InvalidTimestamp
+        );
+    }
+
+    #[test]
+    fn test_decode_uas_id() {
+        let mut uas_id = [0; 20];
+        uas_id[..6].copy_from_slice(b"N12345");
+        let msg = BasicMessage {
+            uas_id,
+            ..Default::default()
+        };
+        assert_eq!(msg.decode_uas_id(), Some("N12345".to_string()));
+
+        // padding only
+        assert_eq!(BasicMessage::default().decode_uas_id(), None);
+
+        let msg = BasicMessage {
+            uas_id: [0xff; 20],
+            ..Default::default()
+        };
+        assert_eq!(msg.decode_uas_id(), None);
+    }
+
+    #[test]
+    fn test_message_pack() {
+        let basic = Frame {
+            header: Header::default(),
+            message: BasicMessage::default().pack().unwrap(),
+        };
+
+        let location = Frame {
+            header: Header {
+                message_type: MessageType::Location,
+                ..Default::default()
+            },
+            message: [0; 24],
+        };
+
+        let pack = MessagePack {
+            frames: vec![basic, location],
+        };
+
+        let bytes = pack.pack().unwrap();
+        assert_eq!(bytes.len(), 3 + 2 * 25);
+        assert_eq!(bytes[0] >> 4, MessageType::MessagePack as u8);
+        assert_eq!(MessagePack::unpack(&bytes).unwrap(), pack);
+
+        // truncated
+        assert_eq!(
+            MessagePack::unpack(&bytes[..bytes.len() - 1]).unwrap_err(),
+            MessagePackError::InvalidLength
+        );
+
+        // wrong message count
+        let mut invalid = bytes.clone();
+        invalid[2] = 0;
+        assert_eq!(
+            MessagePack::unpack(&invalid).unwrap_err(),
+            MessagePackError::InvalidMessageCount
+        );
+
+        // wrong message size
+        let mut invalid = bytes.clone();
+        invalid[1] = 24;
+        assert_eq!(
+            MessagePack::unpack(&invalid).unwrap_err(),
+            MessagePackError::InvalidMessageSize
+        );
+
+        // not a message pack
+        let frame = basic.pack().unwrap();
+        assert_eq!(
+            MessagePack::unpack(&frame).unwrap_err(),
+            MessagePackError::InvalidHeader
+        );
+
+        // nested message pack
+        let mut invalid = bytes.clone();
+        invalid[3] = bytes[0];
+        assert_eq!(
+            MessagePack::unpack(&invalid).unwrap_err(),
+            MessagePackError::Nested
+        );
+
+        assert_eq!(
+            MessagePack { frames: vec![] }.pack().unwrap_err(),
+            MessagePackError::InvalidMessageCount
+        );
+    }
+
+    #[test]
+    fn test_system_message() {
+        let msg = SystemMessage {
+            reserved_0: 0.into(),
+            classification_type: UaClassification::EuropeanUnion,
+            operator_location_source: OperatorLocationSource::Dynamic,
+            operator_latitude: SystemMessage::encode_coordinate(52.3676),
+            operator_longitude: SystemMessage::encode_coordinate(-4.9041),
+            area_count: 1,
+            area_radius: 0,
+            area_ceiling: 0,
+            area_floor: 0,
+            category: EuropeanUnionCategory::Open,
+            class: EuropeanUnionClass::C1,
+            operator_altitude: 2040,
+            timestamp: 3600,
+            reserved_1: 0,
+        };
+
+        let bytes = msg.pack().unwrap();
+        assert_eq!(bytes[0], 0b0000_0101);
+        assert_eq!(&bytes[1..5], &msg.operator_latitude.to_le_bytes());
+        assert_eq!(bytes[16], 0x12);
+        assert_eq!(SystemMessage::unpack(&bytes).unwrap(), msg);
+
+        let (latitude, longitude) = msg.decode_operator_location().unwrap();
+        assert!((latitude - 52.3676).abs() < 1e-6);
+        assert!((longitude + 4.9041).abs() < 1e-6);
+        assert_eq!(msg.decode_operator_altitude(), Some(20.0));
+        assert_eq!(
+            msg.decode_timestamp(),
+            DateTime::from_timestamp(AUTHENTICATION_EPOCH_UNIX_SECONDS + 3600, 0)
+        );
+
+        let unknown = SystemMessage {
+            operator_latitude: 0,
+            operator_longitude: 0,
+            operator_altitude: 0,
+            ..msg
+        };
+        assert_eq!(unknown.decode_operator_location(), None);
+        assert_eq!(unknown.decode_operator_altitude(), None);
+
+        let invalid = SystemMessage {
+            operator_latitude: SystemMessage::encode_coordinate(91.0),
+            ..msg
+        };
+        assert_eq!(invalid.decode_operator_location(), None);
+
+        // fields added by F3411-20
+        assert_eq!(msg.for_version(ProtocolVersion::F3411_20), msg);
+        let legacy = msg.for_version(ProtocolVersion::F3411_19);
+        assert_eq!(legacy.classification_type, UaClassification::Undeclared);
+        assert_eq!(legacy.category, EuropeanUnionCategory::Undefined);
+        assert_eq!(legacy.class, EuropeanUnionClass::Undefined);
+        assert_eq!(legacy.decode_operator_altitude(), None);
+        assert_eq!(legacy.timestamp, 0);
+        assert_eq!(
+            legacy.decode_operator_location(),
+            msg.decode_operator_location()
+        );
+    }
+
+    #[test]
+    fn test_decode_operator_id() {
+        let mut operator_id = [0; 20];
+        operator_id[..16].copy_from_slice(b"FIN87astrdge12k8");
+        let msg = OperatorIdMessage {
+            operator_id_type: 0,
+            operator_id,
+            reserved: [0; 3],
+        };
+        assert_eq!(
+            msg.decode_operator_id(),
+            Some("FIN87astrdge12k8".to_string())
+        );
+
+        let bytes = msg.pack().unwrap();
+        assert_eq!(&bytes[1..4], b"FIN");
+        assert_eq!(OperatorIdMessage::unpack(&bytes).unwrap(), msg);
+
+        let padding = OperatorIdMessage {
+            operator_id: [0; 20],
+            ..msg
+        };
+        assert_eq!(padding.decode_operator_id(), None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_unpack_arbitrary_bytes(bytes in any::<[u8; 25]>()) {
+            let Ok(frame) = Frame::unpack(&bytes) else {
+                return Ok(());
+            };
+
+            if let Ok(msg) = LocationMessage::unpack(&frame.message) {
+                let _ = msg.decode_direction();
+                let _ = msg.decode_altitude();
+                let _ = msg.decode_speed();
+                let _ = msg.decode_vertical_speed();
+                let _ = msg.decode_timestamp(Utc::now());
+                prop_assert!(msg.decode_latitude().abs() <= 215.);
+                prop_assert!(msg.decode_longitude().abs() <= 215.);
+            }
+
+            if let Ok(msg) = BasicMessage::unpack(&frame.message) {
+                let _ = msg.decode_uas_id();
+            }
+
+            if let Ok(msg) = SystemMessage::unpack(&frame.message) {
+                let _ = msg.decode_operator_location();
+                let _ = msg.decode_operator_altitude();
+                let _ = msg.decode_timestamp();
+            }
+
+            if let Ok(msg) = OperatorIdMessage::unpack(&frame.message) {
+                let _ = msg.decode_operator_id();
+            }
+
+            if let Ok(msg) = AuthenticationMessage::unpack(&frame.message) {
+                let _ = AuthenticationSignature::assemble(&[msg]);
+            }
+        }
+
+        #[test]
+        fn test_message_pack_arbitrary_bytes(bytes in vec(any::<u8>(), 0..256)) {
+            let _ = MessagePack::unpack(&bytes);
+        }
+
+        #[test]
+        fn test_location_round_trip(
+            latitude in -90.0f64..90.0,
+            longitude in -180.0f64..180.0,
+            altitude in -999.0f32..31000.0,
+            speed in 0.0f32..254.0,
+            vertical_speed in -62.0f32..62.0,
+        ) {
+            let mut msg = LocationMessage::unpack(&[0; 24]).unwrap();
+            msg.latitude = LocationMessage::encode_latitude(latitude);
+            msg.longitude = LocationMessage::encode_longitude(longitude);
+            msg.pressure_altitude = LocationMessage::encode_altitude(altitude);
+            (msg.speed_multiplier, msg.speed) = LocationMessage::encode_speed(speed).unwrap();
+            msg.vertical_speed = LocationMessage::encode_vertical_speed(vertical_speed);
+            let msg = LocationMessage::unpack(&msg.pack().unwrap()).unwrap();
+
+            prop_assert!((msg.decode_latitude() - latitude).abs() <= 1e-7);
+            prop_assert!((msg.decode_longitude() - longitude).abs() <= 1e-7);
+            prop_assert!((msg.decode_altitude().unwrap() - altitude).abs() <= 0.5);
+            prop_assert!((msg.decode_speed().unwrap() - speed).abs() <= 0.75);
+            prop_assert!((msg.decode_vertical_speed().unwrap() - vertical_speed).abs() <= 0.5);
+        }
+
+        #[test]
+        fn test_vertical_speed_saturation(vertical_speed in -1000.0f32..1000.0) {
+            let encoded = LocationMessage::encode_vertical_speed_checked(vertical_speed).unwrap();
+            let mut msg = LocationMessage::unpack(&[0; 24]).unwrap();
+            msg.vertical_speed = encoded.value;
+
+            let decoded = msg.decode_vertical_speed().unwrap();
+            let expected = vertical_speed.clamp(-VERTICAL_SPEED_MAX_MPS, VERTICAL_SPEED_MAX_MPS);
+            prop_assert!((decoded - expected).abs() <= 0.25);
+            prop_assert_eq!(encoded.saturated, vertical_speed.abs() > VERTICAL_SPEED_MAX_MPS);
+        }
+
+        #[test]
+        fn test_direction_property(direction in 0u16..360) {
+            let (ew_direction, track_direction) =
+                LocationMessage::encode_direction(direction).unwrap();
+            let mut msg = LocationMessage::unpack(&[0; 24]).unwrap();
+            msg.ew_direction = ew_direction;
+            msg.track_direction = track_direction;
+            prop_assert_eq!(msg.decode_direction(), Ok(direction));
+        }
     }
 }
@@ -0,0 +1,396 @@
+//! Open Drone ID broadcast transports
+//!
+//! Receivers may forward the frames Remote ID messages were broadcast in
+//!  rather than the bare messages: the Bluetooth advertisement or the WiFi
+//!  NAN service discovery frame. The ASTM F3411 transport headers are
+//!  stripped to recover the single message or Message Pack broadcast, and
+//!  the MAC address of the broadcaster.
+
+use crate::amqp::envelope::MacAddress;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Length of a MAC address
+const MAC_LENGTH: usize = 6;
+
+/// Bluetooth AD type of service data with a 16-bit UUID
+const AD_TYPE_SERVICE_DATA: u8 = 0x16;
+
+/// 16-bit service UUID assigned to ASTM Remote ID, least significant
+///  byte first
+const ASTM_SERVICE_UUID: [u8; 2] = [0xFA, 0xFF];
+
+/// Application code of Open Drone ID service data
+const ODID_APP_CODE: u8 = 0x0D;
+
+/// Length of the service data header: AD type, UUID and application code
+const SERVICE_DATA_HEADER_LENGTH: usize = 4;
+
+/// Length of the header of an 802.11 management frame
+const WIFI_HEADER_LENGTH: usize = 24;
+
+/// Offset of the transmitter address in an 802.11 management frame
+const WIFI_TRANSMITTER_OFFSET: usize = 10;
+
+/// Public action category, vendor specific action, Wi-Fi Alliance OUI
+///  and NAN OUI type of a NAN service discovery frame
+const NAN_ACTION_HEADER: [u8; 6] = [0x04, 0x09, 0x50, 0x6F, 0x9A, 0x13];
+
+/// NAN attribute ID of a service descriptor
+const NAN_SERVICE_DESCRIPTOR: u8 = 0x03;
+
+/// NAN service ID of Open Drone ID, the first bytes of the SHA-256
+///  digest of `org.opendroneid.remoteid`
+const ODID_SERVICE_ID: [u8; 6] = [0x88, 0x69, 0x19, 0x9D, 0x92, 0x09];
+
+/// Service control bit of a matching filter in a service descriptor
+const SERVICE_CONTROL_MATCHING_FILTER: u8 = 0x04;
+
+/// Service control bit of a service response filter
+const SERVICE_CONTROL_RESPONSE_FILTER: u8 = 0x08;
+
+/// Service control bit of service info
+const SERVICE_CONTROL_SERVICE_INFO: u8 = 0x10;
+
+/// Service control bit of a binding bitmap
+const SERVICE_CONTROL_BINDING_BITMAP: u8 = 0x40;
+
+/// Transport a Remote ID message was broadcast with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// Bluetooth advertisement: the advertiser address, least significant
+    ///  byte first as transmitted, followed by the advertising data.
+    ///  Legacy advertisements carry a single message, extended ones a
+    ///  Message Pack.
+    Bluetooth,
+
+    /// WiFi NAN service discovery frame, from its 802.11 header,
+    ///  carrying a Message Pack
+    WifiNan,
+}
+
+impl FromStr for Transport {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bluetooth" => Ok(Transport::Bluetooth),
+            "wifi-nan" => Ok(Transport::WifiNan),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Errors stripping a transport frame
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum TransportError {
+    /// The frame ends before one of its fields
+    Truncated,
+
+    /// The frame isn't of the transport
+    InvalidFrame,
+
+    /// The frame carries no Open Drone ID service data
+    NotRemoteId,
+
+    /// The service data holds no message
+    Empty,
+}
+
+impl Display for TransportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::Truncated => write!(f, "Truncated frame"),
+            TransportError::InvalidFrame => write!(f, "Invalid frame"),
+            TransportError::NotRemoteId => write!(f, "No Remote ID service data"),
+            TransportError::Empty => write!(f, "Empty service data"),
+        }
+    }
+}
+
+/// Remote ID messages recovered from a broadcast frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Broadcast {
+    /// MAC address of the broadcaster
+    pub broadcaster: MacAddress,
+
+    /// Message counter of the broadcaster, incremented for each message
+    pub counter: u8,
+
+    /// The single message or Message Pack broadcast
+    pub payload: Vec<u8>,
+}
+
+impl Transport {
+    /// Strip the frame of a broadcast
+    pub fn unwrap(&self, frame: &[u8]) -> Result<Broadcast, TransportError> {
+        match self {
+            Transport::Bluetooth => unwrap_bluetooth(frame),
+            Transport::WifiNan => unwrap_nan(frame),
+        }
+    }
+}
+
+/// Split the message counter from the messages of Open Drone ID
+///  service data
+fn broadcast(broadcaster: MacAddress, data: &[u8]) -> Result<Broadcast, TransportError> {
+    match data {
+        [] => Err(TransportError::Truncated),
+        [_] => Err(TransportError::Empty),
+        [counter, payload @ ..] => Ok(Broadcast {
+            broadcaster,
+            counter: *counter,
+            payload: payload.to_vec(),
+        }),
+    }
+}
+
+/// Strip a Bluetooth advertisement
+///
+/// The advertising data is a sequence of length-prefixed AD structures,
+///  the one of ASTM service data holding the messages.
+fn unwrap_bluetooth(advertisement: &[u8]) -> Result<Broadcast, TransportError> {
+    if advertisement.len() < MAC_LENGTH {
+        return Err(TransportError::Truncated);
+    }
+
+    let (address, mut data) = advertisement.split_at(MAC_LENGTH);
+    let mut mac = [0; MAC_LENGTH];
+    mac.copy_from_slice(address);
+    mac.reverse();
+
+    while let Some((&length, rest)) = data.split_first() {
+        let length = length as usize;
+        if length == 0 {
+            // significant part of the advertising data ended
+            break;
+        }
+
+        if rest.len() < length {
+            return Err(TransportError::Truncated);
+        }
+
+        let (structure, next) = rest.split_at(length);
+        if structure.len() >= SERVICE_DATA_HEADER_LENGTH
+            && structure[0] == AD_TYPE_SERVICE_DATA
+            && structure[1..3] == ASTM_SERVICE_UUID
+            && structure[3] == ODID_APP_CODE
+        {
+            return broadcast(MacAddress(mac), &structure[SERVICE_DATA_HEADER_LENGTH..]);
+        }
+
+        data = next;
+    }
+
+    Err(TransportError::NotRemoteId)
+}
+
+/// Split the given number of bytes from the start of a slice
+fn take(bytes: &[u8], count: usize) -> Result<(&[u8], &[u8]), TransportError> {
+    match bytes.len() >= count {
+        true => Ok(bytes.split_at(count)),
+        false => Err(TransportError::Truncated),
+    }
+}
+
+/// Service info of a NAN service descriptor, skipping the optional
+///  fields preceding it
+fn service_info(descriptor: &[u8]) -> Result<&[u8], TransportError> {
+    // service ID, instance ID, requestor instance ID
+    let (_, rest) = take(descriptor, ODID_SERVICE_ID.len() + 2)?;
+    let (control, mut rest) = take(rest, 1)?;
+    let control = control[0];
+
+    if control & SERVICE_CONTROL_BINDING_BITMAP != 0 {
+        rest = take(rest, 2)?.1;
+    }
+
+    for filter in [
+        SERVICE_CONTROL_MATCHING_FILTER,
+        SERVICE_CONTROL_RESPONSE_FILTER,
+    ] {
+        if control & filter != 0 {
+            let (length, filter) = take(rest, 1)?;
+            rest = take(filter, length[0] as usize)?.1;
+        }
+    }
+
+    if control & SERVICE_CONTROL_SERVICE_INFO == 0 {
+        return Err(TransportError::Empty);
+    }
+
+    let (length, rest) = take(rest, 1)?;
+    Ok(take(rest, length[0] as usize)?.0)
+}
+
+/// Strip a WiFi NAN service discovery frame
+///
+/// The NAN attributes follow the action header, the service descriptor
+///  of Open Drone ID holding the messages as service info.
+fn unwrap_nan(frame: &[u8]) -> Result<Broadcast, TransportError> {
+    let (header, body) = take(frame, WIFI_HEADER_LENGTH)?;
+    let mut mac = [0; MAC_LENGTH];
+    mac.copy_from_slice(&header[WIFI_TRANSMITTER_OFFSET..WIFI_TRANSMITTER_OFFSET + MAC_LENGTH]);
+
+    let (action, mut attributes) = take(body, NAN_ACTION_HEADER.len())?;
+    if action != NAN_ACTION_HEADER {
+        return Err(TransportError::InvalidFrame);
+    }
+
+    while !attributes.is_empty() {
+        let (header, rest) = take(attributes, 3)?;
+        let length = u16::from_le_bytes([header[1], header[2]]) as usize;
+        let (attribute, next) = take(rest, length)?;
+        if header[0] == NAN_SERVICE_DESCRIPTOR && attribute.starts_with(&ODID_SERVICE_ID) {
+            return broadcast(MacAddress(mac), service_info(attribute)?);
+        }
+
+        attributes = next;
+    }
+
+    Err(TransportError::NotRemoteId)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Remote ID message: a Basic message of protocol version 2
+    fn message() -> Vec<u8> {
+        let mut message = vec![0x02, 0x12];
+        message.extend_from_slice(b"1596F3411A0000000001");
+        message.resize(25, 0);
+        message
+    }
+
+    fn advertisement(counter: u8, payload: &[u8]) -> Vec<u8> {
+        // advertiser address, least significant byte first
+        let mut advertisement = vec![0x5f, 0x4e, 0x3d, 0x2c, 0x1b, 0x0a];
+
+        // flags
+        advertisement.extend_from_slice(&[0x02, 0x01, 0x06]);
+
+        let length = (SERVICE_DATA_HEADER_LENGTH + 1 + payload.len()) as u8;
+        advertisement.extend_from_slice(&[length, 0x16, 0xFA, 0xFF, 0x0D, counter]);
+        advertisement.extend_from_slice(payload);
+        advertisement
+    }
+
+    fn nan_frame(control: u8, optional: &[u8], service_info: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0xd0, 0x00, 0x00, 0x00];
+        frame.extend_from_slice(&[0x51, 0x6f, 0x9a, 0x01, 0x00, 0x00]);
+        frame.extend_from_slice(&[0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f]);
+        frame.extend_from_slice(&[0x51, 0x6f, 0x9a, 0x01, 0x00, 0x00]);
+        frame.extend_from_slice(&[0x00, 0x00]);
+        frame.extend_from_slice(&NAN_ACTION_HEADER);
+
+        // an unrelated attribute first
+        frame.extend_from_slice(&[0x01, 0x02, 0x00, 0xaa, 0xbb]);
+
+        let mut descriptor = ODID_SERVICE_ID.to_vec();
+        descriptor.extend_from_slice(&[0x01, 0x00, control]);
+        descriptor.extend_from_slice(optional);
+        if control & SERVICE_CONTROL_SERVICE_INFO != 0 {
+            descriptor.push(service_info.len() as u8);
+            descriptor.extend_from_slice(service_info);
+        }
+
+        frame.push(NAN_SERVICE_DESCRIPTOR);
+        frame.extend_from_slice(&(descriptor.len() as u16).to_le_bytes());
+        frame.extend_from_slice(&descriptor);
+        frame
+    }
+
+    fn broadcaster() -> MacAddress {
+        MacAddress([0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f])
+    }
+
+    #[test]
+    fn test_transport_from_str() {
+        assert_eq!("bluetooth".parse(), Ok(Transport::Bluetooth));
+        assert_eq!(" WiFi-NAN".parse(), Ok(Transport::WifiNan));
+        assert_eq!("wifi".parse::<Transport>(), Err(()));
+    }
+
+    #[test]
+    fn test_unwrap_bluetooth() {
+        let broadcast = Transport::Bluetooth
+            .unwrap(&advertisement(7, &message()))
+            .unwrap();
+        assert_eq!(broadcast.broadcaster, broadcaster());
+        assert_eq!(broadcast.counter, 7);
+        assert_eq!(broadcast.payload, message());
+
+        // trailing padding
+        let mut padded = advertisement(7, &message());
+        padded.extend_from_slice(&[0, 0, 0]);
+        assert_eq!(Transport::Bluetooth.unwrap(&padded), Ok(broadcast));
+
+        let mut truncated = advertisement(7, &message());
+        truncated.pop();
+        assert_eq!(
+            Transport::Bluetooth.unwrap(&truncated),
+            Err(TransportError::Truncated)
+        );
+
+        let mut other_uuid = advertisement(7, &message());
+        other_uuid[11] = 0xFE;
+        assert_eq!(
+            Transport::Bluetooth.unwrap(&other_uuid),
+            Err(TransportError::NotRemoteId)
+        );
+
+        assert_eq!(
+            Transport::Bluetooth.unwrap(&advertisement(7, &[])),
+            Err(TransportError::Empty)
+        );
+        assert_eq!(
+            Transport::Bluetooth.unwrap(&[0x5f, 0x4e]),
+            Err(TransportError::Truncated)
+        );
+    }
+
+    #[test]
+    fn test_unwrap_nan() {
+        let mut service_info = vec![3];
+        service_info.extend_from_slice(&message());
+
+        let frame = nan_frame(SERVICE_CONTROL_SERVICE_INFO, &[], &service_info);
+        let broadcast = Transport::WifiNan.unwrap(&frame).unwrap();
+        assert_eq!(broadcast.broadcaster, broadcaster());
+        assert_eq!(broadcast.counter, 3);
+        assert_eq!(broadcast.payload, message());
+
+        // optional fields preceding the service info
+        let control = SERVICE_CONTROL_SERVICE_INFO
+            | SERVICE_CONTROL_BINDING_BITMAP
+            | SERVICE_CONTROL_MATCHING_FILTER;
+        let frame = nan_frame(control, &[0x00, 0x00, 0x02, 0x01, 0x02], &service_info);
+        assert_eq!(Transport::WifiNan.unwrap(&frame), Ok(broadcast));
+
+        let frame = nan_frame(SERVICE_CONTROL_SERVICE_INFO, &[], &service_info);
+        assert_eq!(
+            Transport::WifiNan.unwrap(&frame[..frame.len() - 1]),
+            Err(TransportError::Truncated)
+        );
+
+        let mut other_service = nan_frame(SERVICE_CONTROL_SERVICE_INFO, &[], &service_info);
+        other_service[WIFI_HEADER_LENGTH + 11] = 0x00;
+        assert_eq!(
+            Transport::WifiNan.unwrap(&other_service),
+            Err(TransportError::NotRemoteId)
+        );
+
+        let mut not_nan = nan_frame(SERVICE_CONTROL_SERVICE_INFO, &[], &service_info);
+        not_nan[WIFI_HEADER_LENGTH + 5] = 0x12;
+        assert_eq!(
+            Transport::WifiNan.unwrap(&not_nan),
+            Err(TransportError::InvalidFrame)
+        );
+
+        assert_eq!(
+            Transport::WifiNan.unwrap(&nan_frame(0x00, &[], &[])),
+            Err(TransportError::Empty)
+        );
+    }
+}
@@ -0,0 +1,477 @@
+//! Open Glider Network (OGN) position beacons
+//!
+//! FLARM and OGN trackers broadcast their position, which OGN ground
+//!  stations relay to the APRS network as sentences like
+//!  `FLRDDA5BA>APRS,qAS,LFMX:/165334h4414.38N/00614.86E'086/007/A=000843 !W70! id0ADDA5BA -019fpm +0.0rot`
+
+use super::identifier::{IdentifierKind, IdentifierRules};
+use lib_common::time::{DateTime, Duration, NaiveTime, Utc};
+use std::fmt::{self, Display, Formatter};
+
+/// Meters per foot
+const METERS_PER_FOOT: f64 = 0.3048;
+
+/// Meters per second per knot
+const MPS_PER_KNOT: f32 = 0.514_444;
+
+/// Meters per second per foot per minute
+const MPS_PER_FPM: f32 = 0.3048 / 60.0;
+
+/// Length of the position of a beacon: time, latitude, symbol table,
+///  longitude and symbol
+const POSITION_LENGTH: usize = 26;
+
+/// Errors decoding an OGN beacon
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OgnDecodeError {
+    /// The sentence has no `source>destination:` header
+    MissingHeader,
+
+    /// The sentence is not a position report with a timestamp
+    UnsupportedSentence,
+
+    /// The timestamp is not a valid `HHMMSSh` time
+    InvalidTimestamp,
+
+    /// The latitude is not a valid `DDMM.mm` N/S coordinate
+    InvalidLatitude,
+
+    /// The longitude is not a valid `DDDMM.mm` E/W coordinate
+    InvalidLongitude,
+
+    /// The course and speed are not valid `CCC/SSS` values
+    InvalidCourse,
+
+    /// The `/A=` altitude is missing or invalid
+    InvalidAltitude,
+
+    /// The beacon has no `id` field, as sent by ground stations
+    MissingId,
+}
+
+impl Display for OgnDecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OgnDecodeError::MissingHeader => write!(f, "Missing header"),
+            OgnDecodeError::UnsupportedSentence => write!(f, "Unsupported sentence"),
+            OgnDecodeError::InvalidTimestamp => write!(f, "Invalid timestamp"),
+            OgnDecodeError::InvalidLatitude => write!(f, "Invalid latitude"),
+            OgnDecodeError::InvalidLongitude => write!(f, "Invalid longitude"),
+            OgnDecodeError::InvalidCourse => write!(f, "Invalid course or speed"),
+            OgnDecodeError::InvalidAltitude => write!(f, "Invalid altitude"),
+            OgnDecodeError::MissingId => write!(f, "Missing id"),
+        }
+    }
+}
+
+/// Kind of the address of an OGN tracker
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AddressType {
+    /// Random address
+    Random,
+
+    /// ICAO 24-bit address, as used by ADS-B
+    Icao,
+
+    /// FLARM device address
+    Flarm,
+
+    /// OGN tracker address
+    Ogn,
+}
+
+impl From<u8> for AddressType {
+    fn from(bits: u8) -> Self {
+        match bits & 0b11 {
+            1 => AddressType::Icao,
+            2 => AddressType::Flarm,
+            3 => AddressType::Ogn,
+            _ => AddressType::Random,
+        }
+    }
+}
+
+/// Aircraft type announced by an OGN tracker
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OgnAircraftType {
+    /// Unknown
+    Unknown,
+
+    /// Glider or motor glider
+    Glider,
+
+    /// Tow or tug plane
+    TowPlane,
+
+    /// Helicopter or rotorcraft
+    Helicopter,
+
+    /// Skydiver
+    Skydiver,
+
+    /// Drop plane for skydivers
+    DropPlane,
+
+    /// Hang glider
+    HangGlider,
+
+    /// Paraglider
+    Paraglider,
+
+    /// Reciprocating engine aircraft
+    PoweredAircraft,
+
+    /// Jet or turboprop aircraft
+    JetAircraft,
+
+    /// Unidentified flying object
+    Ufo,
+
+    /// Balloon
+    Balloon,
+
+    /// Airship
+    Airship,
+
+    /// Unmanned aerial vehicle
+    Uav,
+
+    /// Ground support vehicle
+    GroundSupport,
+
+    /// Static object
+    StaticObject,
+}
+
+impl From<u8> for OgnAircraftType {
+    fn from(bits: u8) -> Self {
+        match bits & 0x0F {
+            0x1 => OgnAircraftType::Glider,
+            0x2 => OgnAircraftType::TowPlane,
+            0x3 => OgnAircraftType::Helicopter,
+            0x4 => OgnAircraftType::Skydiver,
+            0x5 => OgnAircraftType::DropPlane,
+            0x6 => OgnAircraftType::HangGlider,
+            0x7 => OgnAircraftType::Paraglider,
+            0x8 => OgnAircraftType::PoweredAircraft,
+            0x9 => OgnAircraftType::JetAircraft,
+            0xA => OgnAircraftType::Ufo,
+            0xB => OgnAircraftType::Balloon,
+            0xC => OgnAircraftType::Airship,
+            0xD => OgnAircraftType::Uav,
+            0xE => OgnAircraftType::GroundSupport,
+            0xF => OgnAircraftType::StaticObject,
+            _ => OgnAircraftType::Unknown,
+        }
+    }
+}
+
+/// A decoded OGN aircraft beacon
+#[derive(Debug, Clone, PartialEq)]
+pub struct OgnPosition {
+    /// APRS source of the beacon, e.g. `FLRDDA5BA`
+    pub source: String,
+
+    /// 24-bit address of the tracker
+    pub address: u32,
+
+    /// Kind of the address
+    pub address_type: AddressType,
+
+    /// Aircraft type
+    pub aircraft_type: OgnAircraftType,
+
+    /// The pilot asked for the aircraft not to be displayed
+    pub stealth: bool,
+
+    /// The pilot asked for the aircraft not to be tracked
+    pub no_tracking: bool,
+
+    /// When the position was fixed
+    pub timestamp: DateTime<Utc>,
+
+    /// Latitude in degrees
+    pub latitude: f64,
+
+    /// Longitude in degrees
+    pub longitude: f64,
+
+    /// Altitude above mean sea level in meters
+    pub altitude_meters: f64,
+
+    /// Course over ground in degrees, if reported
+    pub track_degrees: Option<f32>,
+
+    /// Ground speed in meters per second, if reported
+    pub ground_speed_mps: Option<f32>,
+
+    /// Climb rate in meters per second, if reported
+    pub climb_rate_mps: Option<f32>,
+}
+
+impl OgnPosition {
+    /// Identifier of the aircraft
+    ///
+    /// Aircraft with an ICAO address are identified as over ADS-B, others
+    ///  by the APRS source of their beacons.
+    pub fn identifier(&self, identifiers: &IdentifierRules) -> String {
+        match self.address_type {
+            AddressType::Icao => identifiers.icao(self.address),
+            _ => identifiers.normalize(IdentifierKind::Ogn, &self.source),
+        }
+    }
+}
+
+/// Decodes a `HHMMSSh` time of day, on the day of `now`
+///
+/// Beacons are relayed within seconds, so a time later than `now` is
+///  from the day before.
+fn parse_timestamp(text: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let digits = text.strip_suffix('h')?;
+    if digits.len() != 6 || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let time = NaiveTime::parse_from_str(digits, "%H%M%S").ok()?;
+    let timestamp = now.date_naive().and_time(time).and_utc();
+    match timestamp > now + Duration::try_minutes(1)? {
+        true => Some(timestamp - Duration::try_days(1)?),
+        false => Some(timestamp),
+    }
+}
+
+/// Decodes a `DDMM.mmN` latitude or `DDDMM.mmE` longitude in degrees
+fn parse_coordinate(text: &str, degree_digits: usize, hemispheres: [char; 2]) -> Option<f64> {
+    let hemisphere = text.chars().last()?;
+    let degrees: u32 = text.get(..degree_digits)?.parse().ok()?;
+    let minutes: f64 = text.get(degree_digits..text.len() - 1)?.parse().ok()?;
+    if !(0.0..60.0).contains(&minutes) {
+        return None;
+    }
+
+    let degrees = degrees as f64 + minutes / 60.0;
+    match hemisphere {
+        c if c == hemispheres[0] => Some(degrees),
+        c if c == hemispheres[1] => Some(-degrees),
+        _ => None,
+    }
+}
+
+/// Decodes a `CCC/SSS` course in degrees and speed in knots
+fn parse_course(text: &str) -> Option<(f32, f32)> {
+    let (course, speed) = text.split_once('/')?;
+    let course: u16 = course.parse().ok()?;
+    let speed: u16 = speed.parse().ok()?;
+    match course <= 360 {
+        true => Some(((course % 360) as f32, speed as f32 * MPS_PER_KNOT)),
+        false => None,
+    }
+}
+
+/// Decodes an `idXXYYYYYY` field: flags and 24-bit address
+fn parse_id(token: &str) -> Option<(u8, u32)> {
+    let id = token.strip_prefix("id")?;
+    if id.len() != 8 {
+        return None;
+    }
+
+    let flags = u8::from_str_radix(id.get(..2)?, 16).ok()?;
+    let address = u32::from_str_radix(id.get(2..)?, 16).ok()?;
+    Some((flags, address))
+}
+
+/// Decodes a `!Wab!` extra digit of the latitude and longitude minutes
+fn parse_precision(token: &str) -> Option<(f64, f64)> {
+    let digits = token.strip_prefix("!W")?.strip_suffix('!')?.as_bytes();
+    match digits {
+        [a, b] if a.is_ascii_digit() && b.is_ascii_digit() => Some((
+            (a - b'0') as f64 / 1000.0 / 60.0,
+            (b - b'0') as f64 / 1000.0 / 60.0,
+        )),
+        _ => None,
+    }
+}
+
+/// Decodes an OGN aircraft beacon
+///
+/// `now` resolves the day of the beacon, which only holds a time of day.
+pub fn parse(sentence: &str, now: DateTime<Utc>) -> Result<OgnPosition, OgnDecodeError> {
+    let (header, body) = sentence
+        .trim()
+        .split_once(':')
+        .ok_or(OgnDecodeError::MissingHeader)?;
+
+    let source = match header.split_once('>') {
+        Some((source, _)) if !source.is_empty() => source.to_string(),
+        _ => return Err(OgnDecodeError::MissingHeader),
+    };
+
+    let body = body
+        .strip_prefix(|c| c == '/' || c == '@')
+        .ok_or(OgnDecodeError::UnsupportedSentence)?;
+
+    if body.get(..POSITION_LENGTH).is_none() {
+        return Err(OgnDecodeError::UnsupportedSentence);
+    }
+
+    let timestamp = body
+        .get(..7)
+        .and_then(|text| parse_timestamp(text, now))
+        .ok_or(OgnDecodeError::InvalidTimestamp)?;
+    let mut latitude = body
+        .get(7..15)
+        .and_then(|text| parse_coordinate(text, 2, ['N', 'S']))
+        .filter(|latitude| latitude.abs() <= 90.0)
+        .ok_or(OgnDecodeError::InvalidLatitude)?;
+    let mut longitude = body
+        .get(16..25)
+        .and_then(|text| parse_coordinate(text, 3, ['E', 'W']))
+        .filter(|longitude| longitude.abs() <= 180.0)
+        .ok_or(OgnDecodeError::InvalidLongitude)?;
+
+    let mut rest = body.get(POSITION_LENGTH..).unwrap_or_default();
+    let mut course = None;
+    if !rest.starts_with("/A=") {
+        course = Some(
+            rest.get(..7)
+                .and_then(parse_course)
+                .ok_or(OgnDecodeError::InvalidCourse)?,
+        );
+        rest = rest.get(7..).unwrap_or_default();
+    }
+
+    let (altitude, comment) = rest
+        .strip_prefix("/A=")
+        .map(|rest| rest.split_once(' ').unwrap_or((rest, "")))
+        .ok_or(OgnDecodeError::InvalidAltitude)?;
+    let altitude_feet: i32 = altitude
+        .parse()
+        .map_err(|_| OgnDecodeError::InvalidAltitude)?;
+
+    let mut id = None;
+    let mut climb_rate_mps = None;
+    for token in comment.split_whitespace() {
+        if let Some((latitude_extra, longitude_extra)) = parse_precision(token) {
+            latitude += latitude_extra.copysign(latitude);
+            longitude += longitude_extra.copysign(longitude);
+        } else if let Some(parsed) = parse_id(token) {
+            id = Some(parsed);
+        } else if let Some(fpm) = token.strip_suffix("fpm") {
+            climb_rate_mps = fpm.parse::<f32>().ok().map(|fpm| fpm * MPS_PER_FPM);
+        }
+    }
+
+    let (flags, address) = id.ok_or(OgnDecodeError::MissingId)?;
+    Ok(OgnPosition {
+        source,
+        address,
+        address_type: AddressType::from(flags),
+        aircraft_type: OgnAircraftType::from(flags >> 2),
+        stealth: flags & 0x80 != 0,
+        no_tracking: flags & 0x40 != 0,
+        timestamp,
+        latitude,
+        longitude,
+        altitude_meters: altitude_feet as f64 * METERS_PER_FOOT,
+        track_degrees: course.map(|(track, _)| track),
+        ground_speed_mps: course.map(|(_, speed)| speed),
+        climb_rate_mps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SENTENCE: &str = "FLRDDA5BA>APRS,qAS,LFMX:/165334h4414.38N/00614.86E'086/007/A=000843 !W70! id0ADDA5BA -019fpm +0.0rot 5.5dB 3e -4.3kHz";
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-06-01T16:54:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse() {
+        let position = parse(SENTENCE, now()).unwrap();
+        assert_eq!(position.source, "FLRDDA5BA");
+        assert_eq!(position.address, 0xDDA5BA);
+        assert_eq!(position.address_type, AddressType::Flarm);
+        assert_eq!(position.aircraft_type, OgnAircraftType::TowPlane);
+        assert!(!position.stealth);
+        assert!(!position.no_tracking);
+        assert_eq!(
+            position.timestamp,
+            DateTime::parse_from_rfc3339("2024-06-01T16:53:34Z").unwrap()
+        );
+        assert!((position.latitude - (44.0 + 14.387 / 60.0)).abs() < 1e-9);
+        assert!((position.longitude - (6.0 + 14.860 / 60.0)).abs() < 1e-9);
+        assert!((position.altitude_meters - 256.9464).abs() < 1e-6);
+        assert_eq!(position.track_degrees, Some(86.0));
+        assert!((position.ground_speed_mps.unwrap() - 3.601_108).abs() < 1e-4);
+        assert!((position.climb_rate_mps.unwrap() + 0.096_52).abs() < 1e-4);
+        assert_eq!(
+            position.identifier(&IdentifierRules::default()),
+            "FLRDDA5BA"
+        );
+    }
+
+    #[test]
+    fn test_parse_icao() {
+        let sentence = "ICA4840D6>OGFLR,qAS,EDER:/235959h5130.00S/00030.00W^/A=-00100 idE54840D6";
+        let position = parse(sentence, now()).unwrap();
+        assert_eq!(position.address_type, AddressType::Icao);
+        assert_eq!(position.aircraft_type, OgnAircraftType::JetAircraft);
+        assert!(position.stealth);
+        assert!(position.no_tracking);
+        assert_eq!(position.identifier(&IdentifierRules::default()), "4840d6");
+        let identifiers = IdentifierRules::new("icao.case=upper,icao.prefix=icao:");
+        assert_eq!(position.identifier(&identifiers), "icao:4840D6");
+        assert_eq!(position.latitude, -51.5);
+        assert_eq!(position.longitude, -0.5);
+        assert!(position.altitude_meters < 0.0);
+        assert_eq!(position.track_degrees, None);
+        assert_eq!(position.climb_rate_mps, None);
+
+        // sent before midnight
+        assert_eq!(
+            position.timestamp,
+            DateTime::parse_from_rfc3339("2024-05-31T23:59:59Z").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let cases = [
+            ("no header", OgnDecodeError::MissingHeader),
+            (">APRS:/165334h", OgnDecodeError::MissingHeader),
+            ("LFMX>APRS:>status", OgnDecodeError::UnsupportedSentence),
+            ("LFMX>APRS:/165334h", OgnDecodeError::UnsupportedSentence),
+        ];
+
+        for (sentence, error) in cases {
+            assert_eq!(parse(sentence, now()), Err(error));
+        }
+
+        let cases = [
+            ("/165334h", "/166034h", OgnDecodeError::InvalidTimestamp),
+            ("4414.38N", "4475.38N", OgnDecodeError::InvalidLatitude),
+            ("4414.38N", "9414.38N", OgnDecodeError::InvalidLatitude),
+            ("00614.86E", "00614.86X", OgnDecodeError::InvalidLongitude),
+            ("086/007", "400/007", OgnDecodeError::InvalidCourse),
+            ("/A=000843", "/A=high", OgnDecodeError::InvalidAltitude),
+            (" id0ADDA5BA", "", OgnDecodeError::MissingId),
+        ];
+
+        for (valid, invalid, error) in cases {
+            let sentence = SENTENCE.replace(valid, invalid);
+            assert_eq!(parse(&sentence, now()), Err(error), "{sentence}");
+        }
+    }
+
+    #[test]
+    fn test_ground_station() {
+        let sentence = "LFMX>OGNSDR,TCPIP*,qAC,GLIDERN2:/165321h4414.56NI00614.99E&/A=001732";
+        assert_eq!(parse(sentence, now()), Err(OgnDecodeError::MissingId));
+    }
+}
@@ -0,0 +1,300 @@
+//! Scrubbing of personal data of aircraft operators
+//!
+//! Operator identifiers and locations are personal data. They are scrubbed
+//!  before being published, except on the routing key reserved for
+//!  authorized consumers.
+//!
+//! Display providers serving the public may have to delay the positions of
+//!  small unmanned aircraft and blur them. The public read routes serve
+//!  positions `PRIVACY_PUBLIC_DELAY_MS` late, and snap those of Remote ID
+//!  aircraft to the center of a geohash cell. The sinks are not affected.
+
+use crate::config::{Config, OperatorIdPrivacy};
+use crate::msg::coverage::{geohash, geohash_bounds};
+use lib_common::time::{DateTime, Duration, Utc};
+use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug, Formatter};
+
+/// Length of the hash keys generated if none is configured, in bytes
+const HASH_KEY_LENGTH: usize = 32;
+
+/// Bytes of the hash kept in hashed operator identifiers
+const HASH_LENGTH: usize = 16;
+
+/// Operator of an aircraft, from Remote ID System or Operator ID messages
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperatorInfo {
+    /// Identifier of the aircraft
+    pub identifier: String,
+
+    /// Operator registration number
+    pub operator_id: Option<String>,
+
+    /// Latitude of the operator
+    pub latitude: Option<f64>,
+
+    /// Longitude of the operator
+    pub longitude: Option<f64>,
+
+    /// Geodetic altitude of the operator in meters
+    pub altitude_meters: Option<f32>,
+
+    /// If the operator identifier and location were scrubbed
+    pub scrubbed: bool,
+
+    /// When the operator data was received
+    pub timestamp_network: DateTime<Utc>,
+}
+
+/// Scrubbing of operator data, as configured
+#[derive(Clone)]
+pub struct Privacy {
+    /// Protection of operator identifiers
+    operator_id: OperatorIdPrivacy,
+
+    /// Characters of operator identifiers kept when truncated
+    prefix_length: usize,
+
+    /// Key of the operator identifier hash
+    key: Vec<u8>,
+
+    /// Decimal places of operator latitudes and longitudes
+    location_decimals: u8,
+
+    /// Delay of the positions served by the public read routes
+    public_delay_ms: u32,
+
+    /// Length of the geohash cells of public Remote ID positions, 0 to
+    ///  keep them
+    public_precision: usize,
+}
+
+impl Debug for Privacy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Privacy")
+            .field("operator_id", &self.operator_id)
+            .field("prefix_length", &self.prefix_length)
+            .field("location_decimals", &self.location_decimals)
+            .field("public_delay_ms", &self.public_delay_ms)
+            .field("public_precision", &self.public_precision)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Privacy {
+    /// Scrubbing configured by `PRIVACY_*`
+    ///
+    /// Without `PRIVACY_HASH_KEY`, a random key is used, so hashed
+    ///  identifiers differ between instances and restarts.
+    pub fn new(config: &Config) -> Self {
+        let key = match config.privacy_hash_key.is_empty() {
+            false => config.privacy_hash_key.as_bytes().to_vec(),
+            true => {
+                let mut key = vec![0; HASH_KEY_LENGTH];
+                rand::thread_rng().fill(key.as_mut_slice());
+                key
+            }
+        };
+
+        Privacy {
+            operator_id: config.privacy_operator_id,
+            prefix_length: config.privacy_operator_id_prefix_length as usize,
+            key,
+            location_decimals: config.privacy_location_decimals,
+            public_delay_ms: config.privacy_public_delay_ms,
+            public_precision: config.privacy_public_geohash_precision as usize,
+        }
+    }
+
+    /// Hex encoded keyed hash (HMAC-SHA256) of an identifier
+    fn hash(&self, identifier: &str) -> Option<String> {
+        let key = PKey::hmac(&self.key).ok()?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &key).ok()?;
+        signer.update(identifier.as_bytes()).ok()?;
+        let hash = signer.sign_to_vec().ok()?;
+        Some(hex::encode(&hash[..HASH_LENGTH]))
+    }
+
+    /// Scrubbed operator identifier, dropped if it could not be hashed
+    pub fn operator_id(&self, operator_id: &str) -> Option<String> {
+        match self.operator_id {
+            OperatorIdPrivacy::Keep => Some(operator_id.to_string()),
+            OperatorIdPrivacy::Truncate => {
+                Some(operator_id.chars().take(self.prefix_length).collect())
+            }
+            OperatorIdPrivacy::Hash => self.hash(operator_id),
+        }
+    }
+
+    /// Latitude or longitude rounded to the configured decimal places
+    pub fn coarsen(&self, degrees: f64) -> f64 {
+        let factor = 10_f64.powi(self.location_decimals as i32);
+        (degrees * factor).round() / factor
+    }
+
+    /// Operator data with its identifier and location scrubbed
+    pub fn scrub(&self, operator: &OperatorInfo) -> OperatorInfo {
+        OperatorInfo {
+            operator_id: operator
+                .operator_id
+                .as_deref()
+                .and_then(|id| self.operator_id(id)),
+            latitude: operator.latitude.map(|degrees| self.coarsen(degrees)),
+            longitude: operator.longitude.map(|degrees| self.coarsen(degrees)),
+            scrubbed: true,
+            ..operator.clone()
+        }
+    }
+
+    /// Latest time of the positions served by the public read routes
+    pub fn public_until(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::try_milliseconds(self.public_delay_ms as i64).unwrap_or(Duration::zero())
+    }
+
+    /// Latitude and longitude of a Remote ID aircraft as served by the
+    ///  public read routes, at the center of its geohash cell
+    pub fn public_location(&self, latitude: f64, longitude: f64) -> (f64, f64) {
+        if self.public_precision == 0 {
+            return (latitude, longitude);
+        }
+
+        match geohash_bounds(&geohash(latitude, longitude, self.public_precision)) {
+            Some((min_latitude, min_longitude, max_latitude, max_longitude)) => (
+                (min_latitude + max_latitude) / 2.0,
+                (min_longitude + max_longitude) / 2.0,
+            ),
+            None => (latitude, longitude),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn privacy(operator_id: OperatorIdPrivacy, key: &str) -> Privacy {
+        Privacy::new(&Config {
+            privacy_operator_id: operator_id,
+            privacy_hash_key: key.to_string(),
+            ..Config::default()
+        })
+    }
+
+    fn operator() -> OperatorInfo {
+        OperatorInfo {
+            identifier: "drone-1".to_string(),
+            operator_id: Some("FIN87astrdge12k8".to_string()),
+            latitude: Some(52.367_634),
+            longitude: Some(-4.904_139),
+            altitude_meters: Some(20.0),
+            scrubbed: false,
+            timestamp_network: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_operator_id() {
+        let id = "FIN87astrdge12k8";
+        assert_eq!(
+            privacy(OperatorIdPrivacy::Keep, "").operator_id(id),
+            Some(id.to_string())
+        );
+        assert_eq!(
+            privacy(OperatorIdPrivacy::Truncate, "").operator_id(id),
+            Some("FIN".to_string())
+        );
+
+        // stable for a key, and not the identifier
+        let hashed = privacy(OperatorIdPrivacy::Hash, "key")
+            .operator_id(id)
+            .unwrap();
+        assert_eq!(hashed.len(), HASH_LENGTH * 2);
+        assert!(!hashed.contains("FIN"));
+        assert_eq!(
+            privacy(OperatorIdPrivacy::Hash, "key").operator_id(id),
+            Some(hashed.clone())
+        );
+        assert_ne!(
+            privacy(OperatorIdPrivacy::Hash, "other").operator_id(id),
+            Some(hashed.clone())
+        );
+
+        // random key when none is configured
+        assert_ne!(
+            privacy(OperatorIdPrivacy::Hash, "").operator_id(id),
+            Some(hashed)
+        );
+    }
+
+    #[test]
+    fn test_coarsen() {
+        let privacy = privacy(OperatorIdPrivacy::Hash, "key");
+        assert_eq!(privacy.coarsen(52.367_634), 52.37);
+        assert_eq!(privacy.coarsen(-4.904_139), -4.9);
+
+        let privacy = Privacy {
+            location_decimals: 0,
+            ..privacy
+        };
+        assert_eq!(privacy.coarsen(52.567_634), 53.0);
+    }
+
+    #[test]
+    fn test_scrub() {
+        let privacy = privacy(OperatorIdPrivacy::Truncate, "key");
+        let operator = operator();
+        let scrubbed = privacy.scrub(&operator);
+        assert_eq!(scrubbed.identifier, operator.identifier);
+        assert_eq!(scrubbed.operator_id, Some("FIN".to_string()));
+        assert_eq!(scrubbed.latitude, Some(52.37));
+        assert_eq!(scrubbed.longitude, Some(-4.9));
+        assert_eq!(scrubbed.altitude_meters, operator.altitude_meters);
+        assert!(scrubbed.scrubbed);
+
+        let unknown = OperatorInfo {
+            operator_id: None,
+            latitude: None,
+            longitude: None,
+            ..operator
+        };
+        let scrubbed = privacy.scrub(&unknown);
+        assert_eq!(scrubbed.operator_id, None);
+        assert_eq!(scrubbed.latitude, None);
+    }
+
+    #[test]
+    fn test_public() {
+        let now = Utc::now();
+        let privacy = privacy(OperatorIdPrivacy::Hash, "key");
+        assert_eq!(privacy.public_until(now), now);
+        assert_eq!(
+            privacy.public_location(52.367_634, -4.904_139),
+            (52.367_634, -4.904_139)
+        );
+
+        let privacy = Privacy::new(&Config {
+            privacy_public_delay_ms: 30000,
+            privacy_public_geohash_precision: 5,
+            ..Config::default()
+        });
+        assert_eq!(
+            privacy.public_until(now),
+            now - Duration::try_seconds(30).unwrap()
+        );
+
+        // cell gck9b, about 5 kilometers wide
+        let (latitude, longitude) = privacy.public_location(52.367_634, -4.904_139);
+        assert!((latitude - 52.360_839).abs() < 1e-6);
+        assert!((longitude - -4.899_902).abs() < 1e-6);
+        assert_eq!(privacy.public_location(52.37, -4.91), (latitude, longitude));
+    }
+
+    #[test]
+    fn test_debug_hides_key() {
+        let debug = format!("{:?}", privacy(OperatorIdPrivacy::Hash, "secret"));
+        assert!(!debug.contains("secret"));
+        assert!(!debug.contains("key"));
+    }
+}
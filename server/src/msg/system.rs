@@ -0,0 +1,157 @@
+//! Health state changes of the dependencies
+//!
+//! Dependencies are checked periodically, and each change of their state
+//!  is published once, so monitoring can alert on a degraded instance
+//!  without probing `/health` itself. Dependencies are assumed up at
+//!  startup: one found down on the first check is reported as a change.
+
+use crate::stats::Dependency;
+use lib_common::time::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Health state of a dependency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthState {
+    /// The dependency answered the last check
+    Up,
+
+    /// The last check of the dependency failed
+    Down,
+}
+
+/// Change of the health state of a dependency, published once per change
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthTransition {
+    /// The dependency
+    pub dependency: Dependency,
+
+    /// The new state
+    pub state: HealthState,
+
+    /// Why the check failed, for dependencies going down
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// When the dependency entered its previous state, the start of the
+    ///  monitoring if it never changed
+    pub previous_since: DateTime<Utc>,
+
+    /// When the change was detected
+    pub timestamp_network: DateTime<Utc>,
+}
+
+/// Tracks the health state of each dependency between checks
+#[derive(Debug)]
+pub struct HealthMonitor {
+    /// When monitoring started
+    started: DateTime<Utc>,
+
+    /// State of each checked dependency and since when
+    states: HashMap<Dependency, (HealthState, DateTime<Utc>)>,
+}
+
+impl HealthMonitor {
+    /// Monitor started at the given time
+    pub fn new(now: DateTime<Utc>) -> Self {
+        HealthMonitor {
+            started: now,
+            states: HashMap::new(),
+        }
+    }
+
+    /// Record the result of a check of a dependency, an error describing
+    ///  why it is down
+    ///
+    /// Returns the transition to publish if the state changed.
+    pub fn record(
+        &mut self,
+        dependency: Dependency,
+        result: Result<(), String>,
+        now: DateTime<Utc>,
+    ) -> Option<HealthTransition> {
+        let (state, reason) = match result {
+            Ok(()) => (HealthState::Up, None),
+            Err(reason) => (HealthState::Down, Some(reason)),
+        };
+
+        let (previous, since) = self
+            .states
+            .get(&dependency)
+            .copied()
+            .unwrap_or((HealthState::Up, self.started));
+
+        if previous == state {
+            return None;
+        }
+
+        self.states.insert(dependency, (state, now));
+        Some(HealthTransition {
+            dependency,
+            state,
+            reason,
+            previous_since: since,
+            timestamp_network: now,
+        })
+    }
+
+    /// Current state of a dependency
+    pub fn state(&self, dependency: Dependency) -> HealthState {
+        self.states
+            .get(&dependency)
+            .map_or(HealthState::Up, |(state, _)| *state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lib_common::time::Duration;
+
+    #[test]
+    fn test_transitions() {
+        let started = Utc::now();
+        let mut monitor = HealthMonitor::new(started);
+
+        // healthy at startup, nothing to report
+        assert_eq!(monitor.record(Dependency::Redis, Ok(()), started), None);
+
+        let down = started + Duration::seconds(10);
+        let transition = monitor
+            .record(Dependency::Redis, Err("connection refused".into()), down)
+            .unwrap();
+        assert_eq!(transition.state, HealthState::Down);
+        assert_eq!(transition.reason.as_deref(), Some("connection refused"));
+        assert_eq!(transition.previous_since, started);
+        assert_eq!(monitor.state(Dependency::Redis), HealthState::Down);
+
+        // reported once while down
+        let later = down + Duration::seconds(5);
+        assert_eq!(
+            monitor.record(Dependency::Redis, Err("timeout".into()), later),
+            None
+        );
+        assert_eq!(monitor.state(Dependency::Gis), HealthState::Up);
+
+        let up = later + Duration::seconds(5);
+        let transition = monitor.record(Dependency::Redis, Ok(()), up).unwrap();
+        assert_eq!(transition.state, HealthState::Up);
+        assert_eq!(transition.reason, None);
+        assert_eq!(transition.previous_since, down);
+    }
+
+    #[test]
+    fn test_transition_serialization() {
+        let now = Utc::now();
+        let mut monitor = HealthMonitor::new(now);
+        let transition = monitor
+            .record(Dependency::Storage, Err("circuit breaker open".into()), now)
+            .unwrap();
+
+        let json = serde_json::to_value(&transition).unwrap();
+        assert_eq!(json["dependency"], "storage");
+        assert_eq!(json["state"], "down");
+        assert_eq!(json["reason"], "circuit breaker open");
+    }
+}
@@ -0,0 +1,559 @@
+//! Per-aircraft ordering and merging of position reports
+//!
+//! The same aircraft can be reported by several sources (e.g. an MLAT
+//!  provider and direct ADS-B reception). Their reports arrive out of
+//!  order and disagree slightly, which makes the track jitter downstream.
+//!
+//! Reports are ordered by the time the aircraft sent them, which only
+//!  sources stamping an asset time provide (Remote ID and OGN). ADS-B and
+//!  MLAT positions carry no asset time and are ordered by their network
+//!  time, when their packet was received, so a packet processed late
+//!  keeps its place.
+//!
+//! Tracks also keep the last velocity of each aircraft, allowing
+//!  positions to be extrapolated while waiting for the next report.
+//!  Velocities are ordered on their own: a velocity is kept even if the
+//!  position reported with it is discarded.
+
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use svc_gis_client_grpc::prelude::types::{AircraftPosition, AircraftVelocity, Position};
+
+/// Tracks are forgotten if no position was accepted for this long
+const TRACK_EXPIRE_MS: i64 = 60000;
+
+/// Number of tracked aircraft above which expired tracks are removed
+const TRACK_PRUNE_THRESHOLD: usize = 1024;
+
+/// Mean radius of the Earth in meters
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// Track state shared between request handlers
+pub type SharedTracks = Arc<Mutex<TrackMerger>>;
+
+/// Outcome of submitting a position to the [`TrackMerger`]
+#[derive(Debug, Clone)]
+pub enum TrackDecision {
+    /// The position is newer than the track, forward it as-is
+    Accept(AircraftPosition),
+
+    /// The position is a near-simultaneous duplicate,
+    ///  forward the smoothed position instead
+    Merge(AircraftPosition),
+
+    /// The position is older than the track or an exact duplicate
+    Discard,
+}
+
+/// Latest known state of an aircraft
+#[derive(Debug, Clone)]
+struct Track {
+    /// Last accepted position
+    position: AircraftPosition,
+
+    /// Last reported velocity
+    velocity: Option<AircraftVelocity>,
+
+    /// When the last position was accepted
+    received: DateTime<Utc>,
+}
+
+/// Latest state of a tracked aircraft, as exported in snapshots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackSnapshot {
+    /// Last accepted position
+    pub position: AircraftPosition,
+
+    /// Last reported velocity
+    pub velocity: Option<AircraftVelocity>,
+
+    /// When the last position was accepted
+    pub updated: DateTime<Utc>,
+}
+
+impl TrackSnapshot {
+    /// If the aircraft stopped reporting long enough to be forgotten
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        let expire = Duration::try_milliseconds(TRACK_EXPIRE_MS).unwrap_or(Duration::zero());
+        now - self.updated >= expire
+    }
+}
+
+/// Orders and merges position reports per aircraft identifier
+#[derive(Debug)]
+pub struct TrackMerger {
+    /// Reports closer in time than this are merged
+    merge_window: Duration,
+
+    /// Latest state of each aircraft
+    tracks: HashMap<String, Track>,
+}
+
+/// Time of a position report, preferring the asset's own clock
+///
+/// Positions without an asset time (ADS-B, MLAT) fall back to their
+///  network time, so their order is the order they were received in.
+fn report_time(position: &AircraftPosition) -> DateTime<Utc> {
+    position
+        .timestamp_asset
+        .unwrap_or(position.timestamp_network)
+}
+
+/// Time of a velocity report, preferring the asset's own clock
+fn velocity_time(velocity: &AircraftVelocity) -> DateTime<Utc> {
+    velocity
+        .timestamp_asset
+        .unwrap_or(velocity.timestamp_network)
+}
+
+/// Extrapolate a position along a velocity for the given number of seconds
+///
+/// Uses a flat-earth approximation, only suitable for short gaps.
+pub fn extrapolate(
+    position: &AircraftPosition,
+    velocity: &AircraftVelocity,
+    seconds: f64,
+) -> Position {
+    let distance = velocity.velocity_horizontal_ground_mps as f64 * seconds;
+    let track = (velocity.track_angle_degrees as f64).to_radians();
+    let latitude = position.position.latitude.to_radians();
+
+    let delta_latitude = (distance * track.cos() / EARTH_RADIUS_METERS).to_degrees();
+    let delta_longitude =
+        (distance * track.sin() / (EARTH_RADIUS_METERS * latitude.cos())).to_degrees();
+
+    Position {
+        latitude: position.position.latitude + delta_latitude,
+        longitude: position.position.longitude + delta_longitude,
+        altitude_meters: position.position.altitude_meters
+            + velocity.velocity_vertical_mps as f64 * seconds,
+    }
+}
+
+impl TrackMerger {
+    /// Create a merger, reports within `merge_window_ms` of the
+    ///  last accepted report are averaged with it
+    pub fn new(merge_window_ms: u32) -> Self {
+        TrackMerger {
+            merge_window: Duration::try_milliseconds(merge_window_ms as i64)
+                .unwrap_or(Duration::zero()),
+            tracks: HashMap::new(),
+        }
+    }
+
+    /// Create a merger shared between request handlers
+    pub fn shared(merge_window_ms: u32) -> SharedTracks {
+        Arc::new(Mutex::new(TrackMerger::new(merge_window_ms)))
+    }
+
+    /// Store a position accepted at `now`, keeping the last known velocity
+    fn accept(&mut self, position: AircraftPosition, now: DateTime<Utc>) {
+        let velocity = self
+            .tracks
+            .remove(&position.identifier)
+            .and_then(|track| track.velocity);
+
+        self.tracks.insert(
+            position.identifier.clone(),
+            Track {
+                position,
+                velocity,
+                received: now,
+            },
+        );
+    }
+
+    /// Submit a position report processed at `now`, returning what should
+    ///  be forwarded downstream
+    pub fn update(&mut self, position: AircraftPosition, now: DateTime<Utc>) -> TrackDecision {
+        let time = report_time(&position);
+        if self.tracks.len() > TRACK_PRUNE_THRESHOLD {
+            self.prune(time);
+        }
+
+        let Some(track) = self.tracks.get(&position.identifier) else {
+            self.accept(position.clone(), now);
+            return TrackDecision::Accept(position);
+        };
+
+        let last = &track.position;
+        let last_time = report_time(last);
+        let same_position = last.position.latitude == position.position.latitude
+            && last.position.longitude == position.position.longitude
+            && last.position.altitude_meters == position.position.altitude_meters;
+
+        if time < last_time || (time == last_time && same_position) {
+            return TrackDecision::Discard;
+        }
+
+        if time - last_time > self.merge_window {
+            self.accept(position.clone(), now);
+            return TrackDecision::Accept(position);
+        }
+
+        let merged = AircraftPosition {
+            position: Position {
+                latitude: (last.position.latitude + position.position.latitude) / 2.0,
+                longitude: (last.position.longitude + position.position.longitude) / 2.0,
+                altitude_meters: (last.position.altitude_meters
+                    + position.position.altitude_meters)
+                    / 2.0,
+            },
+            ..position
+        };
+
+        self.accept(merged.clone(), now);
+        TrackDecision::Merge(merged)
+    }
+
+    /// Record the latest velocity of a tracked aircraft, whatever became
+    ///  of the position reported with it
+    ///
+    /// Returns false if the velocity is older than the last one recorded,
+    ///  and shouldn't be forwarded. Velocities of untracked aircraft are
+    ///  forwarded but not recorded.
+    pub fn update_velocity(&mut self, velocity: AircraftVelocity) -> bool {
+        let Some(track) = self.tracks.get_mut(&velocity.identifier) else {
+            return true;
+        };
+
+        let newer = track
+            .velocity
+            .as_ref()
+            .map_or(true, |last| velocity_time(&velocity) > velocity_time(last));
+        if newer {
+            track.velocity = Some(velocity);
+        }
+
+        newer
+    }
+
+    /// Extrapolated positions of aircraft that haven't reported a
+    ///  position for at least `min_gap`, but less than `max_gap`
+    ///
+    /// Only aircraft with a velocity reported within `max_gap` are included.
+    pub fn predict(
+        &self,
+        now: DateTime<Utc>,
+        min_gap: Duration,
+        max_gap: Duration,
+    ) -> Vec<AircraftPosition> {
+        self.tracks
+            .values()
+            .filter_map(|track| {
+                let gap = now - track.received;
+                if gap < min_gap || gap >= max_gap {
+                    return None;
+                }
+
+                let velocity = track.velocity.as_ref()?;
+                if now - velocity.timestamp_network >= max_gap {
+                    return None;
+                }
+
+                let seconds = gap.num_milliseconds() as f64 / 1000.0;
+                Some(AircraftPosition {
+                    identifier: track.position.identifier.clone(),
+                    position: extrapolate(&track.position, velocity, seconds),
+                    timestamp_network: now,
+                    timestamp_asset: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Forget aircraft that haven't reported for a while
+    pub fn prune(&mut self, now: DateTime<Utc>) {
+        let expire = Duration::try_milliseconds(TRACK_EXPIRE_MS).unwrap_or(Duration::zero());
+        self.tracks
+            .retain(|_, track| now - report_time(&track.position) < expire);
+    }
+
+    /// If a position of the aircraft was accepted before its track expires
+    pub fn is_live(&self, identifier: &str, now: DateTime<Utc>) -> bool {
+        let expire = Duration::try_milliseconds(TRACK_EXPIRE_MS).unwrap_or(Duration::zero());
+        self.tracks
+            .get(identifier)
+            .is_some_and(|track| now - track.received < expire)
+    }
+
+    /// Last accepted position of an aircraft, if tracked
+    pub fn position(&self, identifier: &str) -> Option<&AircraftPosition> {
+        self.tracks.get(identifier).map(|track| &track.position)
+    }
+
+    /// Latest state of the tracked aircraft
+    pub fn snapshot(&self) -> Vec<TrackSnapshot> {
+        self.tracks
+            .values()
+            .map(|track| TrackSnapshot {
+                position: track.position.clone(),
+                velocity: track.velocity.clone(),
+                updated: track.received,
+            })
+            .collect()
+    }
+
+    /// Number of aircraft currently tracked
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// If no aircraft are currently tracked
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn epoch_ms(ms: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(0, 0).unwrap() + Duration::try_milliseconds(ms).unwrap()
+    }
+
+    fn position(identifier: &str, ms: i64, latitude: f64) -> AircraftPosition {
+        AircraftPosition {
+            identifier: identifier.to_string(),
+            position: Position {
+                latitude,
+                longitude: 5.0,
+                altitude_meters: 100.0,
+            },
+            timestamp_network: epoch_ms(ms),
+            timestamp_asset: Some(epoch_ms(ms)),
+        }
+    }
+
+    #[test]
+    fn test_track_ordering() {
+        let now = epoch_ms(5000);
+        let mut merger = TrackMerger::new(100);
+        assert!(merger.is_empty());
+
+        let first = position("a", 1000, 52.0);
+        let TrackDecision::Accept(accepted) = merger.update(first.clone(), now) else {
+            panic!("expected an accepted position");
+        };
+        assert_eq!(accepted.position.latitude, 52.0);
+
+        // exact duplicate
+        assert!(matches!(merger.update(first, now), TrackDecision::Discard));
+
+        // older than the last accepted position
+        assert!(matches!(
+            merger.update(position("a", 500, 52.1), now),
+            TrackDecision::Discard
+        ));
+
+        // newer, outside of the merge window
+        assert!(matches!(
+            merger.update(position("a", 2000, 52.2), now),
+            TrackDecision::Accept(_)
+        ));
+
+        // other aircraft are tracked separately
+        assert!(matches!(
+            merger.update(position("b", 500, 10.0), now),
+            TrackDecision::Accept(_)
+        ));
+        assert_eq!(merger.len(), 2);
+        assert_eq!(
+            merger.position("a").map(|p| p.position.latitude),
+            Some(52.2)
+        );
+        assert_eq!(merger.position("c"), None);
+    }
+
+    #[test]
+    fn test_track_ordering_without_asset_time() {
+        let adsb = |ms: i64, latitude: f64| AircraftPosition {
+            timestamp_network: epoch_ms(ms),
+            timestamp_asset: None,
+            ..position("a", ms, latitude)
+        };
+
+        let mut merger = TrackMerger::new(100);
+        let newer = adsb(2000, 52.2);
+        assert!(matches!(
+            merger.update(newer, epoch_ms(2000)),
+            TrackDecision::Accept(_)
+        ));
+
+        // received before the last accepted position, but processed late
+        assert!(matches!(
+            merger.update(adsb(1000, 52.0), epoch_ms(4000)),
+            TrackDecision::Discard
+        ));
+
+        // broadcast before the last accepted position but received after it:
+        //  without an asset time, it is taken for the newest
+        let now = epoch_ms(4000);
+        let older = AircraftPosition {
+            timestamp_network: epoch_ms(3000),
+            ..adsb(1000, 52.0)
+        };
+        assert!(matches!(
+            merger.update(older, now),
+            TrackDecision::Accept(_)
+        ));
+        assert_eq!(
+            merger.position("a").map(|p| p.position.latitude),
+            Some(52.0)
+        );
+    }
+
+    #[test]
+    fn test_track_merge() {
+        let now = epoch_ms(2000);
+        let mut merger = TrackMerger::new(100);
+        merger.update(position("a", 1000, 52.0), now);
+
+        let TrackDecision::Merge(merged) = merger.update(position("a", 1050, 52.2), now) else {
+            panic!("expected a merged position");
+        };
+
+        assert!((merged.position.latitude - 52.1).abs() < 1e-9);
+        assert_eq!(merged.timestamp_asset, Some(epoch_ms(1050)));
+
+        // reports older than the merged position are discarded
+        assert!(matches!(
+            merger.update(position("a", 1020, 52.2), now),
+            TrackDecision::Discard
+        ));
+    }
+
+    fn velocity(identifier: &str, ms: i64, track_angle_degrees: f32) -> AircraftVelocity {
+        AircraftVelocity {
+            identifier: identifier.to_string(),
+            velocity_horizontal_ground_mps: 10.0,
+            velocity_horizontal_air_mps: None,
+            velocity_vertical_mps: 1.0,
+            track_angle_degrees,
+            timestamp_network: epoch_ms(ms),
+            timestamp_asset: None,
+        }
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        let start = position("a", 0, 0.0);
+
+        // northbound
+        let predicted = extrapolate(&start, &velocity("a", 0, 0.0), 10.0);
+        let expected_latitude = (100.0 / EARTH_RADIUS_METERS).to_degrees();
+        assert!((predicted.latitude - expected_latitude).abs() < 1e-9);
+        assert!((predicted.longitude - 5.0).abs() < 1e-9);
+        assert!((predicted.altitude_meters - 110.0).abs() < 1e-9);
+
+        // eastbound
+        let predicted = extrapolate(&start, &velocity("a", 0, 90.0), 10.0);
+        assert!(predicted.latitude.abs() < 1e-9);
+        assert!((predicted.longitude - (5.0 + expected_latitude)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_track_predict() {
+        let mut merger = TrackMerger::new(0);
+        let min_gap = Duration::try_milliseconds(1000).unwrap();
+        let max_gap = Duration::try_milliseconds(5000).unwrap();
+
+        let start = epoch_ms(0);
+        merger.update(position("a", 0, 52.0), start);
+        merger.update(position("b", 0, 52.0), start);
+
+        // velocities of unknown aircraft are forwarded, but not tracked
+        assert!(merger.update_velocity(velocity("c", 0, 0.0)));
+        assert_eq!(merger.len(), 2);
+
+        // no velocity, nothing to predict
+        let now = start + Duration::try_milliseconds(2000).unwrap();
+        assert!(merger.predict(now, min_gap, max_gap).is_empty());
+
+        merger.update_velocity(velocity("a", 0, 0.0));
+        let predicted = merger.predict(now, min_gap, max_gap);
+        assert_eq!(predicted.len(), 1);
+        assert_eq!(predicted[0].identifier, "a");
+        assert!(predicted[0].position.latitude > 52.0);
+        assert!(predicted[0].timestamp_asset.is_none());
+
+        // gap too short
+        assert!(merger.predict(start, min_gap, max_gap).is_empty());
+
+        // gap too long
+        let now = start + max_gap;
+        assert!(merger.predict(now, min_gap, max_gap).is_empty());
+    }
+
+    #[test]
+    fn test_track_velocity() {
+        let now = epoch_ms(2000);
+        let mut merger = TrackMerger::new(0);
+        merger.update(position("a", 1000, 52.0), now);
+        assert!(merger.update_velocity(velocity("a", 1000, 0.0)));
+
+        // the velocity of a discarded position is still recorded
+        let decision = merger.update(position("a", 500, 52.1), now);
+        assert!(matches!(decision, TrackDecision::Discard));
+        assert!(merger.update_velocity(velocity("a", 1500, 90.0)));
+
+        // older velocities are not
+        assert!(!merger.update_velocity(velocity("a", 1200, 180.0)));
+        assert!(!merger.update_velocity(velocity("a", 1500, 90.0)));
+
+        let snapshot = merger.snapshot();
+        let velocity = snapshot[0].velocity.as_ref().unwrap();
+        assert_eq!(velocity.track_angle_degrees, 90.0);
+    }
+
+    #[test]
+    fn test_track_prune() {
+        let now = epoch_ms(TRACK_EXPIRE_MS);
+        let mut merger = TrackMerger::new(0);
+        merger.update(position("a", 0, 52.0), now);
+        merger.update(position("b", TRACK_EXPIRE_MS, 52.0), now);
+
+        merger.prune(epoch_ms(TRACK_EXPIRE_MS));
+        assert_eq!(merger.len(), 1);
+    }
+
+    #[test]
+    fn test_track_is_live() {
+        // live for as long after the position was processed, whenever sent
+        let now = epoch_ms(TRACK_EXPIRE_MS * 2);
+        let mut merger = TrackMerger::new(0);
+        merger.update(position("a", 0, 52.0), now);
+
+        assert!(merger.is_live("a", now));
+        assert!(!merger.is_live("b", now));
+
+        let expired = now + Duration::try_milliseconds(TRACK_EXPIRE_MS).unwrap();
+        assert!(!merger.is_live("a", expired));
+    }
+
+    #[test]
+    fn test_track_snapshot() {
+        let now = epoch_ms(1000);
+        let mut merger = TrackMerger::new(0);
+        merger.update(position("a", 1000, 52.0), now);
+        merger.update_velocity(velocity("a", 1000, 90.0));
+
+        let snapshot = merger.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].position.identifier, "a");
+        assert_eq!(
+            snapshot[0].velocity.as_ref().map(|v| v.track_angle_degrees),
+            Some(90.0)
+        );
+
+        let updated = snapshot[0].updated;
+        assert_eq!(updated, now);
+        assert!(!snapshot[0].is_stale(updated));
+        assert!(
+            snapshot[0].is_stale(updated + Duration::try_milliseconds(TRACK_EXPIRE_MS).unwrap())
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Breadcrumb trails of the aircraft
+//!
+//! The last decoded positions of each aircraft are kept in a capped cache
+//!  list, newest first, so user interfaces can draw the recent path of an
+//!  aircraft without querying svc-storage.
+
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use svc_gis_client_grpc::prelude::types::AircraftPosition;
+use utoipa::ToSchema;
+
+/// Suffix of the cache key of the trail of an aircraft
+pub const TRAIL_KEY_SUFFIX: &str = ":trail";
+
+/// Cache key of the trail of an aircraft
+pub fn cache_key(identifier: &str) -> String {
+    format!("{identifier}{TRAIL_KEY_SUFFIX}")
+}
+
+/// Position of an aircraft in its trail
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TrailPoint {
+    /// Latitude in degrees
+    #[schema(example = 52.3731)]
+    pub latitude: f64,
+
+    /// Longitude in degrees
+    #[schema(example = 4.8922)]
+    pub longitude: f64,
+
+    /// Altitude in meters
+    #[schema(example = 120.0)]
+    pub altitude_meters: f64,
+
+    /// When the position was received
+    pub timestamp_network: DateTime<Utc>,
+
+    /// When the position was measured by the aircraft, if reported
+    pub timestamp_asset: Option<DateTime<Utc>>,
+}
+
+impl From<&AircraftPosition> for TrailPoint {
+    fn from(position: &AircraftPosition) -> Self {
+        TrailPoint {
+            latitude: position.position.latitude,
+            longitude: position.position.longitude,
+            altitude_meters: position.position.altitude_meters,
+            timestamp_network: position.timestamp_network,
+            timestamp_asset: position.timestamp_asset,
+        }
+    }
+}
+
+/// Recent positions of an aircraft
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AircraftTrail {
+    /// Identifier of the aircraft
+    pub identifier: String,
+
+    /// Positions received within the requested time, oldest first
+    pub points: Vec<TrailPoint>,
+}
+
+/// Points of cached trails received within `seconds` before `until`, oldest
+///  first. Values which can't be parsed are skipped.
+pub fn recent_points(values: &[String], until: DateTime<Utc>, seconds: u32) -> Vec<TrailPoint> {
+    let since = until - Duration::try_seconds(seconds as i64).unwrap_or(Duration::zero());
+    let mut points: Vec<TrailPoint> = values
+        .iter()
+        .filter_map(|value| serde_json::from_str::<TrailPoint>(value).ok())
+        .filter(|point| point.timestamp_network >= since && point.timestamp_network <= until)
+        .collect();
+
+    points.sort_by_key(|point| point.timestamp_network);
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use svc_gis_client_grpc::prelude::types::Position;
+
+    fn point(seconds_ago: i64, now: DateTime<Utc>) -> String {
+        let position = AircraftPosition {
+            identifier: "drone-1".to_string(),
+            position: Position {
+                latitude: 52.37,
+                longitude: 4.89,
+                altitude_meters: seconds_ago as f64,
+            },
+            timestamp_network: now - Duration::try_seconds(seconds_ago).unwrap(),
+            timestamp_asset: None,
+        };
+
+        serde_json::to_string(&TrailPoint::from(&position)).unwrap()
+    }
+
+    #[test]
+    fn test_recent_points() {
+        let now = Utc::now();
+        let values = vec![
+            point(5, now),
+            "invalid".to_string(),
+            point(30, now),
+            point(90, now),
+        ];
+
+        let points = recent_points(&values, now, 60);
+        let altitudes: Vec<f64> = points.iter().map(|point| point.altitude_meters).collect();
+        assert_eq!(altitudes, vec![30.0, 5.0]);
+
+        assert_eq!(recent_points(&values, now, 300).len(), 3);
+        assert!(recent_points(&values, now, 1).is_empty());
+
+        // delayed
+        let until = now - Duration::try_seconds(10).unwrap();
+        let points = recent_points(&values, until, 60);
+        let altitudes: Vec<f64> = points.iter().map(|point| point.altitude_meters).collect();
+        assert_eq!(altitudes, vec![30.0]);
+        assert_eq!(cache_key("drone-1"), "drone-1:trail");
+    }
+}
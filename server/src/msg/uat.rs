@@ -0,0 +1,486 @@
+//! Universal Access Transceiver (UAT) ADS-B messages
+//!
+//! General aviation in the US broadcasts ADS-B on 978 MHz with UAT
+//!  (RTCA DO-282B). Receivers such as dump978 output the downlink messages,
+//!  once error corrected, as hex lines like `-00a1b2c335809751...;rs=2;`:
+//!  18 byte Basic messages hold the state vector of the aircraft, 34 byte
+//!  Long messages may add its mode status (emitter category and callsign).
+
+use adsb_deku::adsb::TypeCoding;
+use std::fmt::{self, Display, Formatter};
+
+/// Size of a Basic UAT ADS-B message
+pub const UAT_BASIC_SIZE_BYTES: usize = 18;
+
+/// Size of a Long UAT ADS-B message
+pub const UAT_LONG_SIZE_BYTES: usize = 34;
+
+/// Degrees per unit of the encoded latitude and longitude
+const DEGREES_PER_LSB: f64 = 360.0 / 16_777_216.0;
+
+/// Meters per foot
+const METERS_PER_FOOT: f64 = 0.3048;
+
+/// Meters per second per knot
+const MPS_PER_KNOT: f32 = 0.514_444;
+
+/// Meters per second per foot per minute
+const MPS_PER_FPM: f32 = 0.3048 / 60.0;
+
+/// Characters of the base 40 encoding of callsigns
+const BASE40: &[u8; 40] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ  ..";
+
+/// Payload types holding the mode status of the aircraft
+const MODE_STATUS_PAYLOAD_TYPES: [u8; 2] = [1, 3];
+
+/// Last payload type holding a state vector
+const MAX_STATE_VECTOR_PAYLOAD_TYPE: u8 = 10;
+
+/// Errors decoding a UAT message
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum UatDecodeError {
+    /// The message is not hex encoded
+    InvalidHex,
+
+    /// The message is neither a Basic nor a Long message, or its length
+    ///  doesn't match its payload type
+    InvalidLength,
+
+    /// The message is a ground uplink, not an ADS-B message
+    UplinkMessage,
+
+    /// The payload type holds no state vector
+    UnsupportedPayload,
+
+    /// The message was rebroadcast by a ground station (TIS-B, ADS-R),
+    ///  or its address qualifier is reserved
+    UnsupportedAddress,
+
+    /// The aircraft reported no position
+    MissingPosition,
+
+    /// The aircraft reported no altitude
+    MissingAltitude,
+}
+
+impl Display for UatDecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            UatDecodeError::InvalidHex => write!(f, "Invalid hex"),
+            UatDecodeError::InvalidLength => write!(f, "Invalid length"),
+            UatDecodeError::UplinkMessage => write!(f, "Uplink message"),
+            UatDecodeError::UnsupportedPayload => write!(f, "Unsupported payload type"),
+            UatDecodeError::UnsupportedAddress => write!(f, "Unsupported address qualifier"),
+            UatDecodeError::MissingPosition => write!(f, "Missing position"),
+            UatDecodeError::MissingAltitude => write!(f, "Missing altitude"),
+        }
+    }
+}
+
+/// Kind of the address of a UAT message
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AddressQualifier {
+    /// ADS-B target with an ICAO 24-bit address
+    AdsbIcao,
+
+    /// ADS-B target with a self-assigned temporary address
+    AdsbSelfAssigned,
+
+    /// TIS-B target with an ICAO 24-bit address
+    TisbIcao,
+
+    /// TIS-B target with a track file identifier
+    TisbTrackFile,
+
+    /// Surface vehicle
+    SurfaceVehicle,
+
+    /// Fixed ADS-B beacon
+    FixedBeacon,
+
+    /// ADS-R target with a non-ICAO address
+    AdsrOther,
+
+    /// Reserved
+    Reserved,
+}
+
+impl From<u8> for AddressQualifier {
+    fn from(bits: u8) -> Self {
+        match bits & 0b111 {
+            0 => AddressQualifier::AdsbIcao,
+            1 => AddressQualifier::AdsbSelfAssigned,
+            2 => AddressQualifier::TisbIcao,
+            3 => AddressQualifier::TisbTrackFile,
+            4 => AddressQualifier::SurfaceVehicle,
+            5 => AddressQualifier::FixedBeacon,
+            6 => AddressQualifier::AdsrOther,
+            _ => AddressQualifier::Reserved,
+        }
+    }
+}
+
+/// A decoded UAT ADS-B message
+#[derive(Debug, Clone, PartialEq)]
+pub struct UatMessage {
+    /// Payload type, 0 for Basic messages
+    pub payload_type: u8,
+
+    /// Kind of the address
+    pub qualifier: AddressQualifier,
+
+    /// 24-bit address of the aircraft
+    pub address: u32,
+
+    /// Latitude in degrees
+    pub latitude: f64,
+
+    /// Longitude in degrees
+    pub longitude: f64,
+
+    /// Altitude in meters, barometric or geometric
+    pub altitude_meters: f64,
+
+    /// If the altitude is geometric rather than barometric
+    pub geometric_altitude: bool,
+
+    /// If the aircraft is airborne rather than on the ground
+    pub airborne: bool,
+
+    /// Ground speed in meters per second, if reported
+    pub ground_speed_mps: Option<f32>,
+
+    /// Track over the ground in degrees from north, if reported
+    pub track_degrees: Option<f32>,
+
+    /// Climb rate in meters per second, if reported
+    pub climb_rate_mps: Option<f32>,
+
+    /// Emitter category (0 to 39) of the mode status, if sent
+    pub emitter_category: Option<u8>,
+
+    /// Callsign of the mode status, if sent
+    pub callsign: Option<String>,
+}
+
+/// A north/south or east/west velocity in knots, from its 11 bits
+///  (sign and magnitude plus one), none if not available
+fn velocity_knots(raw: u16, supersonic: bool) -> Option<f32> {
+    let magnitude = match raw & 0x3FF {
+        0 => return None,
+        magnitude => (magnitude - 1) as f32,
+    };
+
+    let magnitude = if supersonic {
+        magnitude * 4.0
+    } else {
+        magnitude
+    };
+    match raw & 0x400 {
+        0 => Some(magnitude),
+        _ => Some(-magnitude),
+    }
+}
+
+/// Vertical rate in feet per minute, from its 10 bits (sign and
+///  magnitude plus one, in 64 fpm), none if not available
+fn vertical_rate_fpm(raw: u16) -> Option<f32> {
+    let magnitude = match raw & 0x1FF {
+        0 => return None,
+        magnitude => ((magnitude - 1) * 64) as f32,
+    };
+
+    match raw & 0x200 {
+        0 => Some(magnitude),
+        _ => Some(-magnitude),
+    }
+}
+
+/// Ground speed (m/s) and track (degrees) of north and east velocities
+fn ground_velocity(north_knots: f32, east_knots: f32) -> (f32, f32) {
+    let speed = north_knots.hypot(east_knots) * MPS_PER_KNOT;
+    let track = east_knots.atan2(north_knots).to_degrees().rem_euclid(360.0);
+    (speed, track)
+}
+
+/// Decodes the base 40 callsign of the mode status, none if the aircraft
+///  sent its squawk code instead
+fn callsign(bytes: &[u8]) -> Option<String> {
+    // the callsign type flag, set for callsigns
+    if bytes[26] & 0x02 == 0 {
+        return None;
+    }
+
+    let callsign: String = [(17, 18), (19, 20), (21, 22)]
+        .iter()
+        .map(|(high, low)| u16::from_be_bytes([bytes[*high], bytes[*low]]))
+        .flat_map(|value| [value / 1600, value / 40, value])
+        .skip(1)
+        .map(|value| BASE40[(value % 40) as usize] as char)
+        .collect();
+
+    let callsign = callsign.trim();
+    match callsign.is_empty() {
+        true => None,
+        false => Some(callsign.to_string()),
+    }
+}
+
+/// Decodes a UAT ADS-B message, as bytes
+///
+/// Messages rebroadcast by ground stations (TIS-B and ADS-R) are rejected:
+///  their aircraft are reported by their own transmitters, or by radar.
+pub fn decode(bytes: &[u8]) -> Result<UatMessage, UatDecodeError> {
+    let payload_type = bytes.first().ok_or(UatDecodeError::InvalidLength)? >> 3;
+    let expected_length = match payload_type {
+        0 => UAT_BASIC_SIZE_BYTES,
+        _ => UAT_LONG_SIZE_BYTES,
+    };
+
+    if bytes.len() != expected_length {
+        return Err(UatDecodeError::InvalidLength);
+    }
+
+    if payload_type > MAX_STATE_VECTOR_PAYLOAD_TYPE {
+        return Err(UatDecodeError::UnsupportedPayload);
+    }
+
+    let qualifier = AddressQualifier::from(bytes[0]);
+    if matches!(
+        qualifier,
+        AddressQualifier::TisbIcao
+            | AddressQualifier::TisbTrackFile
+            | AddressQualifier::AdsrOther
+            | AddressQualifier::Reserved
+    ) {
+        return Err(UatDecodeError::UnsupportedAddress);
+    }
+
+    let address = u32::from_be_bytes([0, bytes[1], bytes[2], bytes[3]]);
+
+    //
+    // State vector
+    let b = |i: usize| bytes[i] as u32;
+    let raw_latitude = (b(4) << 15) | (b(5) << 7) | (b(6) >> 1);
+    let raw_longitude = ((b(6) & 0x01) << 23) | (b(7) << 15) | (b(8) << 7) | (b(9) >> 1);
+    let nic = bytes[11] & 0x0F;
+    if nic == 0 && raw_latitude == 0 && raw_longitude == 0 {
+        return Err(UatDecodeError::MissingPosition);
+    }
+
+    let mut latitude = raw_latitude as f64 * DEGREES_PER_LSB;
+    if latitude > 90.0 {
+        latitude -= 180.0;
+    }
+
+    let mut longitude = raw_longitude as f64 * DEGREES_PER_LSB;
+    if longitude > 180.0 {
+        longitude -= 360.0;
+    }
+
+    let raw_altitude = (b(10) << 4) | (b(11) >> 4);
+    if raw_altitude == 0 {
+        return Err(UatDecodeError::MissingAltitude);
+    }
+
+    let altitude_feet = (raw_altitude as i32 - 1) * 25 - 1000;
+
+    // the first field is the north velocity or the ground speed, the
+    //  second the east velocity or the track
+    let first = (((b(12) & 0x1F) << 6) | (b(13) >> 2)) as u16;
+    let second = (((b(13) & 0x03) << 9) | (b(14) << 1) | (b(15) >> 7)) as u16;
+    let (airborne, ground_speed_mps, track_degrees, climb_rate_mps) = match bytes[12] >> 6 {
+        // airborne, subsonic or supersonic
+        state @ (0 | 1) => {
+            let supersonic = state == 1;
+            let velocity = velocity_knots(first, supersonic)
+                .zip(velocity_knots(second, supersonic))
+                .map(|(north, east)| ground_velocity(north, east));
+            let raw_vertical = (((b(15) & 0x7F) << 4) | (b(16) >> 4)) as u16;
+            let climb_rate = vertical_rate_fpm(raw_vertical).map(|fpm| fpm * MPS_PER_FPM);
+            (
+                true,
+                velocity.map(|(speed, _)| speed),
+                velocity.map(|(_, track)| track),
+                climb_rate,
+            )
+        }
+        // on the ground
+        2 => {
+            let speed = match first & 0x3FF {
+                0 => None,
+                knots => Some((knots - 1) as f32 * MPS_PER_KNOT),
+            };
+
+            // track or heading, if available
+            let track = match second >> 9 {
+                0 => None,
+                _ => Some((second & 0x1FF) as f32 * 360.0 / 512.0),
+            };
+
+            (false, speed, track, None)
+        }
+        _ => (true, None, None, None),
+    };
+
+    //
+    // Mode status
+    let (emitter_category, callsign) = match MODE_STATUS_PAYLOAD_TYPES.contains(&payload_type) {
+        true => {
+            let value = u16::from_be_bytes([bytes[17], bytes[18]]);
+            (Some(((value / 1600) % 40) as u8), callsign(bytes))
+        }
+        false => (None, None),
+    };
+
+    Ok(UatMessage {
+        payload_type,
+        qualifier,
+        address,
+        latitude,
+        longitude,
+        altitude_meters: altitude_feet as f64 * METERS_PER_FOOT,
+        geometric_altitude: bytes[9] & 0x01 == 1,
+        airborne,
+        ground_speed_mps,
+        track_degrees,
+        climb_rate_mps,
+        emitter_category,
+        callsign,
+    })
+}
+
+/// Decodes a message as output by dump978: `-<hex>;` followed by
+///  optional metadata (`rs=2;rssi=-20.1;`), or bare hex
+pub fn parse(line: &str) -> Result<UatMessage, UatDecodeError> {
+    let line = line.trim();
+    if line.starts_with('+') {
+        return Err(UatDecodeError::UplinkMessage);
+    }
+
+    let hex = line.strip_prefix('-').unwrap_or(line);
+    let hex = hex.split(';').next().unwrap_or_default();
+    let bytes = hex::decode(hex).map_err(|_| UatDecodeError::InvalidHex)?;
+    decode(&bytes)
+}
+
+/// Type coding and category of an ADS-B message matching a UAT emitter
+///  category, which numbers the sets A, B, C and D by eight
+pub fn emitter_category(code: u8) -> (TypeCoding, u8) {
+    let type_coding = match code / 8 {
+        0 => TypeCoding::A,
+        1 => TypeCoding::B,
+        2 => TypeCoding::C,
+        _ => TypeCoding::D,
+    };
+
+    (type_coding, code % 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Basic message of a1b2c3 at 37.6188, -122.375 and 3500 ft,
+    ///  flying 120 kt north and 50 kt west, climbing 640 fpm
+    const BASIC: &str = "00a1b2c335809751f4a00b5801e61980b000";
+
+    /// Long message of the same aircraft, descending 640 fpm, with the
+    ///  callsign N123AB of a light aircraft
+    const LONG: &str = "08a1b2c335809751f4a00b5801e619a0b009d90d024a840000000200000000000000";
+
+    /// Basic message of 7c1234 (self-assigned) on the ground at -33.9461,
+    ///  151.1772 and 100 ft (geometric), rolling 15 kt on a track of 90°
+    const GROUND: &str = "017c1234cfb89ad701f102d8804140000000";
+
+    #[test]
+    fn test_decode_basic() {
+        let message = parse(&format!("-{BASIC};rs=2;rssi=-21.3;")).unwrap();
+        assert_eq!(message.payload_type, 0);
+        assert_eq!(message.qualifier, AddressQualifier::AdsbIcao);
+        assert_eq!(message.address, 0xa1b2c3);
+        assert!((message.latitude - 37.6188).abs() < 0.0001);
+        assert!((message.longitude + 122.375).abs() < 0.0001);
+        assert!((message.altitude_meters - 3500.0 * METERS_PER_FOOT).abs() < 0.001);
+        assert!(!message.geometric_altitude);
+        assert!(message.airborne);
+
+        let speed = 130.0 * MPS_PER_KNOT;
+        assert!((message.ground_speed_mps.unwrap() - speed).abs() < 0.001);
+        assert!((message.track_degrees.unwrap() - 337.38).abs() < 0.01);
+        assert!((message.climb_rate_mps.unwrap() - 640.0 * MPS_PER_FPM).abs() < 0.001);
+        assert!(message.emitter_category.is_none());
+        assert!(message.callsign.is_none());
+    }
+
+    #[test]
+    fn test_decode_long() {
+        let message = parse(LONG).unwrap();
+        assert_eq!(message.payload_type, 1);
+        assert!((message.climb_rate_mps.unwrap() + 640.0 * MPS_PER_FPM).abs() < 0.001);
+        assert_eq!(message.emitter_category, Some(1));
+        assert_eq!(message.callsign.as_deref(), Some("N123AB"));
+
+        // the squawk code was sent instead of the callsign
+        let mut bytes = hex::decode(LONG).unwrap();
+        bytes[26] = 0;
+        assert!(decode(&bytes).unwrap().callsign.is_none());
+    }
+
+    #[test]
+    fn test_decode_ground() {
+        let message = parse(GROUND).unwrap();
+        assert_eq!(message.qualifier, AddressQualifier::AdsbSelfAssigned);
+        assert_eq!(message.address, 0x7c1234);
+        assert!((message.latitude + 33.9461).abs() < 0.0001);
+        assert!((message.longitude - 151.1772).abs() < 0.0001);
+        assert!((message.altitude_meters - 100.0 * METERS_PER_FOOT).abs() < 0.001);
+        assert!(message.geometric_altitude);
+        assert!(!message.airborne);
+        assert!((message.ground_speed_mps.unwrap() - 15.0 * MPS_PER_KNOT).abs() < 0.001);
+        assert_eq!(message.track_degrees, Some(90.0));
+        assert!(message.climb_rate_mps.is_none());
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(parse("+3514c952..."), Err(UatDecodeError::UplinkMessage));
+        assert_eq!(parse("-zz;"), Err(UatDecodeError::InvalidHex));
+        assert_eq!(parse(""), Err(UatDecodeError::InvalidLength));
+        assert_eq!(parse(&BASIC[..34]), Err(UatDecodeError::InvalidLength));
+        assert_eq!(parse(&LONG[..36]), Err(UatDecodeError::InvalidLength));
+
+        let mut bytes = hex::decode(BASIC).unwrap();
+        bytes[0] = 0x02; // TIS-B
+        assert_eq!(decode(&bytes), Err(UatDecodeError::UnsupportedAddress));
+
+        let mut bytes = hex::decode(LONG).unwrap();
+        bytes[0] = 11 << 3;
+        assert_eq!(decode(&bytes), Err(UatDecodeError::UnsupportedPayload));
+
+        let mut bytes = hex::decode(BASIC).unwrap();
+        bytes[4..10].fill(0);
+        bytes[11] &= 0xF0;
+        assert_eq!(decode(&bytes), Err(UatDecodeError::MissingPosition));
+
+        let mut bytes = hex::decode(BASIC).unwrap();
+        bytes[10] = 0;
+        bytes[11] &= 0x0F;
+        assert_eq!(decode(&bytes), Err(UatDecodeError::MissingAltitude));
+    }
+
+    #[test]
+    fn test_emitter_category() {
+        let code = |category: u8| {
+            let (type_coding, category) = emitter_category(category);
+            crate::msg::adsb::emitter_category_code(type_coding, category)
+        };
+
+        assert_eq!(code(1), "A1");
+        assert_eq!(code(7), "A7");
+        assert_eq!(code(9), "B1");
+        assert_eq!(code(14), "B6");
+        assert_eq!(code(17), "C1");
+        assert_eq!(code(30), "D6");
+    }
+}
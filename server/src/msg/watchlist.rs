@@ -0,0 +1,165 @@
+//! Watchlist of aircraft of interest
+//!
+//! Aircraft are identified by their ICAO address (in hex) or their
+//!  Remote ID identifier. Aircraft are watched by the configuration, or
+//!  through the admin API in a cache hash shared by all instances.
+//!  Observations of a watched aircraft are kept as hits, and an alert is
+//!  raised when it (re)enters coverage.
+
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use utoipa::ToSchema;
+
+/// Cache key of the hash of the aircraft watched through the admin API,
+///  by identifier
+pub const WATCHLIST_KEY: &str = "watchlist";
+
+/// Cache key of the list of the most recent hits, newest first
+pub const WATCHLIST_HITS_KEY: &str = "watchlist:hits";
+
+/// Number of recent hits kept
+pub const MAX_HITS: usize = 100;
+
+/// A watched aircraft is alerted again once unobserved for this long
+const REALERT_INTERVAL_MS: i64 = 60000;
+
+/// Watchlist shared between request handlers
+pub type SharedWatchlist = Arc<Mutex<Watchlist>>;
+
+/// Observation of a watched aircraft
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WatchlistHit {
+    /// Identifier of the aircraft, as listed in the watchlist
+    pub identifier: String,
+
+    /// Protocol the aircraft was observed with (`adsb` or `netrid`)
+    pub source: String,
+
+    /// When the aircraft was observed
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Aircraft watched by the configuration, and when the watched aircraft
+///  were last observed by this instance
+#[derive(Debug, Default)]
+pub struct Watchlist {
+    /// Watched identifiers
+    identifiers: HashSet<String>,
+
+    /// When each watched aircraft was last observed
+    last_seen: HashMap<String, DateTime<Utc>>,
+}
+
+/// Identifiers are matched regardless of case and surrounding whitespace
+pub fn normalize(identifier: &str) -> String {
+    identifier.trim().to_lowercase()
+}
+
+/// Hits read from the cache list, newest first
+///
+/// Cached hits which can't be parsed are skipped.
+pub fn hits(cached: &[String]) -> Vec<WatchlistHit> {
+    cached
+        .iter()
+        .filter_map(|value| serde_json::from_str::<WatchlistHit>(value).ok())
+        .collect()
+}
+
+impl Watchlist {
+    /// Create a watchlist from a comma separated list of identifiers
+    pub fn new(identifiers: &str) -> Self {
+        Watchlist {
+            identifiers: identifiers
+                .split(',')
+                .map(normalize)
+                .filter(|identifier| !identifier.is_empty())
+                .collect(),
+            last_seen: HashMap::new(),
+        }
+    }
+
+    /// Create a watchlist shared between request handlers
+    pub fn shared(identifiers: &str) -> SharedWatchlist {
+        Arc::new(Mutex::new(Watchlist::new(identifiers)))
+    }
+
+    /// If an aircraft is watched by the configuration
+    pub fn contains(&self, identifier: &str) -> bool {
+        self.identifiers.contains(&normalize(identifier))
+    }
+
+    /// Record an observed aircraft, known to be watched
+    ///
+    /// Returns a hit to alert on if the aircraft wasn't observed recently.
+    pub fn observe(
+        &mut self,
+        identifier: &str,
+        source: &str,
+        now: DateTime<Utc>,
+    ) -> Option<WatchlistHit> {
+        let identifier = normalize(identifier);
+        let realert = Duration::try_milliseconds(REALERT_INTERVAL_MS).unwrap_or(Duration::zero());
+        let alert = match self.last_seen.insert(identifier.clone(), now) {
+            Some(last_seen) => now - last_seen >= realert,
+            None => true,
+        };
+
+        if !alert {
+            return None;
+        }
+
+        Some(WatchlistHit {
+            identifier,
+            source: source.to_string(),
+            timestamp: now,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains() {
+        let watchlist = Watchlist::new(" A1B2C3,, drone-1 ");
+        assert!(watchlist.contains("a1b2c3"));
+        assert!(watchlist.contains(" DRONE-1"));
+        assert!(!watchlist.contains("ffffff"));
+        assert!(!watchlist.contains(""));
+        assert!(!Watchlist::default().contains("a1b2c3"));
+    }
+
+    #[test]
+    fn test_observe() {
+        let mut watchlist = Watchlist::new("");
+        let now = Utc::now();
+
+        let hit = watchlist.observe("A1B2C3", "adsb", now).unwrap();
+        assert_eq!(hit.identifier, "a1b2c3");
+        assert_eq!(hit.source, "adsb");
+
+        // continuously observed, no new alert
+        let later = now + Duration::try_milliseconds(REALERT_INTERVAL_MS - 1).unwrap();
+        assert!(watchlist.observe("a1b2c3", "adsb", later).is_none());
+
+        // back after a gap in coverage
+        let later = later + Duration::try_milliseconds(REALERT_INTERVAL_MS).unwrap();
+        let hit = watchlist.observe("a1b2c3", "netrid", later).unwrap();
+        assert_eq!(hit.source, "netrid");
+    }
+
+    #[test]
+    fn test_hits() {
+        let hit = WatchlistHit {
+            identifier: "a1b2c3".to_string(),
+            source: "adsb".to_string(),
+            timestamp: Utc::now(),
+        };
+
+        let cached = vec![serde_json::to_string(&hit).unwrap(), "invalid".to_string()];
+        assert_eq!(hits(&cached), vec![hit]);
+    }
+}
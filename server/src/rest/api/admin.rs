@@ -0,0 +1,59 @@
+//! Authorization of the admin routes
+//!
+//! Logins issue tokens to any aircraft identifier, so a token alone
+//!  doesn't make an operator. Requests to the `/admin` routes carry the
+//!  secret configured in `ADMIN_SECRET` in the `X-Admin-Secret` header
+//!  instead. Without a configured secret, all admin requests are refused.
+
+use super::jwt::secrets_match;
+use super::Pipeline;
+use axum::{extract::Extension, middleware::Next, response::Response};
+use hyper::{Request, StatusCode};
+
+/// Header holding the admin secret of a request
+pub const ADMIN_SECRET_HEADER: &str = "x-admin-secret";
+
+/// Whether the provided secret is the configured admin secret
+fn authorized(admin_secret: &str, provided: Option<&str>) -> bool {
+    match (admin_secret.trim(), provided) {
+        ("", _) | (_, None) => false,
+        (expected, Some(provided)) => secrets_match(expected, provided.trim()),
+    }
+}
+
+/// Authorize a request to an admin route
+pub async fn authorize<B>(
+    Extension(pipeline): Extension<Pipeline>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    let provided = req
+        .headers()
+        .get(ADMIN_SECRET_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    if !authorized(&pipeline.config.admin_secret, provided) {
+        rest_warn!("admin request refused.");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorized() {
+        assert!(authorized("secret", Some("secret")));
+        assert!(authorized(" secret ", Some("secret ")));
+        assert!(!authorized("secret", Some("secret-2")));
+        assert!(!authorized("secret", Some("")));
+        assert!(!authorized("secret", None));
+
+        // no admin secret configured
+        assert!(!authorized("", Some("")));
+        assert!(!authorized(" ", Some(" ")));
+    }
+}
@@ -1,26 +1,47 @@
 //! Endpoints for updating aircraft positions
 
-use crate::cache::pool::{GisPool, TelemetryPool};
-use crate::cache::TelemetryPools;
-use crate::grpc::client::GrpcClients;
+use super::api_key::ApiKeyReporter;
+use super::enrichment::{enrich, Update};
+use super::identity;
+use super::quorum::Confirmation;
+use super::reporter::{self, ReporterOutcome};
+use super::signal::{signal_metadata, SignalHeaders};
+use super::state::{self, update_state};
+use super::Pipeline;
+use crate::amqp::envelope::{PositionAccuracy, SignalMetadata};
+use crate::amqp::raw::Reception;
+use crate::amqp::ROUTING_KEY_RAW_ADSB;
+use crate::cache::batch::DedupCounter;
+use crate::cache::pool::TelemetryPool;
+use crate::config::Config;
+use crate::degradation::DegradationPolicy;
+use crate::dispatcher::{enqueue, StreamEntry};
+use crate::logging::context;
 use crate::msg::adsb::{
-    decode_altitude, decode_cpr, decode_speed_direction, decode_vertical_speed,
-    get_adsb_icao_address, get_adsb_message_type, ADSB_SIZE_BYTES,
+    decode_altitude, decode_cpr, decode_cpr_surface, decode_speed_direction, decode_vertical_speed,
+    emitter_category_code, get_adsb_icao_address, get_downlink_format, get_emergency_status,
+    get_operational_status, get_reply_icao_address, get_squawk, get_target_state, is_cpr_pair,
+    EmitterCategory, ADSB_SIZE_BYTES, CPR_MAX_PAIR_AGE_MS, CPR_SURFACE_MAX_PAIR_AGE_MS,
+    DF_COMM_B_IDENTITY_REPLY,
 };
+use crate::msg::filter::SharedFilters;
+use crate::msg::identity::ConflictKind;
+use crate::msg::track::{SharedTracks, TrackDecision};
+use crate::sink::{EventData, EventSource, TelemetryEvent};
+use crate::stats::{Dependency, Stats};
+use adsb_deku::adsb::ME;
 use adsb_deku::adsb::ME::AirbornePositionBaroAltitude as AirbornePosition;
 use adsb_deku::adsb::ME::AirborneVelocity as Velocity;
 use adsb_deku::adsb::ME::AircraftIdentification as Identification;
+use adsb_deku::adsb::ME::SurfacePosition as Surface;
 use adsb_deku::adsb::{AirborneVelocitySubType, GroundSpeedDecoding, TypeCoding};
 use adsb_deku::deku::DekuContainerRead;
 use adsb_deku::{CPRFormat, Sign};
 use svc_gis_client_grpc::prelude::types::*;
-use svc_storage_client_grpc::prelude::*;
-use svc_storage_client_grpc::resources::adsb;
 
-use axum::{body::Bytes, extract::Extension, Json};
+use axum::{body::Bytes, extract::Extension, http::HeaderMap, Json};
 use hyper::StatusCode;
-use lib_common::time::Utc;
-use std::cmp::Ordering;
+use lib_common::time::{DateTime, Utc};
 
 /// ADSB entries in the cache will expire after 60 seconds
 const CACHE_EXPIRE_MS_ADSB: u32 = 10000;
@@ -28,22 +49,34 @@ const CACHE_EXPIRE_MS_ADSB: u32 = 10000;
 /// CPR lat/lon entries in the cache will expire after 1 second
 const CACHE_EXPIRE_MS_AIRCRAFT_CPR: u32 = 1000;
 
-/// Number of times a packet must be received
-///  from unique senders before it is considered valid
-const N_REPORTERS_NEEDED: u32 = 1;
+/// Surface CPR lat/lon entries in the cache will expire after 25 seconds,
+///  as surface positions are broadcast less often
+const CACHE_EXPIRE_MS_SURFACE_CPR: u32 = 25000;
 
 /// Data structure of encoded position data
 struct GisPositionData {
     icao: u32,
+    identifier: String,
     lat_cpr: u32,
     lon_cpr: u32,
     alt: u16,
     odd_flag: CPRFormat,
+    received: DateTime<Utc>,
+}
+
+/// Data structure of encoded surface position data
+struct GisSurfacePositionData {
+    icao: u32,
+    identifier: String,
+    lat_cpr: u32,
+    lon_cpr: u32,
+    odd_flag: CPRFormat,
+    received: DateTime<Utc>,
 }
 
 /// Data structure of encoded velocity data
 struct GisVelocityData {
-    icao: u32,
+    identifier: String,
     st: u8,
     ew_sign: Sign,
     ew_vel: u16,
@@ -57,115 +90,272 @@ struct GisVelocityData {
 }
 
 // Decode aircraft type from ADS-B message type coding and aircraft category
-fn get_aircraft_type(type_coding: TypeCoding, aircraft_category: u8) -> AircraftType {
-    // in type coding
-    // A = 4
-    // B = 3
-    // C = 2
-    // D = 1
-    match (type_coding, aircraft_category) {
-        (TypeCoding::D, _) => AircraftType::Other,
-        (_, 0) => AircraftType::Other,
-        (TypeCoding::C, 1) => AircraftType::Other,
-        (TypeCoding::C, 3) => AircraftType::Other,
-        (TypeCoding::C, 4) => AircraftType::Groundobstacle,
-        (TypeCoding::C, 5) => AircraftType::Groundobstacle,
-        (TypeCoding::C, 6) => AircraftType::Groundobstacle,
-        (TypeCoding::C, 7) => AircraftType::Groundobstacle,
-        (TypeCoding::B, 1) => AircraftType::Glider,
-        (TypeCoding::B, 2) => AircraftType::Airship,
-        (TypeCoding::B, 3) => AircraftType::Unpowered,
-        (TypeCoding::B, 4) => AircraftType::Glider,
-        (TypeCoding::B, 5) => AircraftType::Other,
-        // (TypeCoding::B, 6) => AircraftType::Uas, // Unmanned Aerial Vehicle
-        (TypeCoding::B, 7) => AircraftType::Rocket,
-        (TypeCoding::A, 7) => AircraftType::Rotorcraft,
-        // TODO(R5): Support other types
-        _ => AircraftType::Other,
+pub(crate) fn get_aircraft_type(type_coding: TypeCoding, aircraft_category: u8) -> AircraftType {
+    match EmitterCategory::new(type_coding, aircraft_category) {
+        EmitterCategory::Light
+        | EmitterCategory::Medium1
+        | EmitterCategory::Medium2
+        | EmitterCategory::HighVortex
+        | EmitterCategory::Heavy
+        | EmitterCategory::HighPerformance => AircraftType::Aeroplane,
+        EmitterCategory::Rotorcraft => AircraftType::Rotorcraft,
+        EmitterCategory::Glider | EmitterCategory::Ultralight => AircraftType::Glider,
+        EmitterCategory::LighterThanAir => AircraftType::Airship,
+        EmitterCategory::Parachutist => AircraftType::Unpowered,
+        EmitterCategory::SpaceVehicle => AircraftType::Rocket,
+        EmitterCategory::GroundObstruction => AircraftType::Groundobstacle,
+        // svc-gis types are the Remote ID types of unmanned aircraft,
+        //  of which an ADS-B UAV doesn't declare any
+        EmitterCategory::Uav => AircraftType::Undeclared,
+        EmitterCategory::SurfaceEmergencyVehicle
+        | EmitterCategory::SurfaceServiceVehicle
+        | EmitterCategory::NoInformation
+        | EmitterCategory::Reserved => AircraftType::Other,
     }
 }
 
-/// Pushes an aircraft identifier message to the queue
-#[cfg(not(tarpaulin_include))]
-// no_coverage: (R5) requires redis backend to test
-async fn gis_identifier_push(
+/// Aircraft identification of an identification message
+fn aircraft_id(
     identifier: String,
     type_coding: TypeCoding,
     aircraft_category: u8,
-    mut gis_pool: GisPool,
-) -> Result<(), ()> {
-    let aircraft_type = get_aircraft_type(type_coding, aircraft_category);
-    let item = AircraftId {
+    now: DateTime<Utc>,
+) -> AircraftId {
+    AircraftId {
         identifier: Some(identifier),
         session_id: None,
-        aircraft_type,
-        timestamp_network: Utc::now(),
+        aircraft_type: get_aircraft_type(type_coding, aircraft_category),
+        timestamp_network: now,
         timestamp_asset: None,
-    };
-
-    gis_pool
-        .push::<AircraftId>(item, REDIS_KEY_AIRCRAFT_ID)
-        .await
+    }
 }
 
 ///
-/// Pushes a position telemetry message to the queue
+/// Decodes a position from the CPR pair of the aircraft, returning
+///  the position to push unless the track discarded it
 ///
 #[cfg(not(tarpaulin_include))]
 // no_coverage: (R5) requires redis backend to test
-async fn gis_position_push(
+async fn decode_position(
     data: GisPositionData,
     mut tlm_pool: TelemetryPool,
-    mut gis_pool: GisPool,
-) -> Result<(), ()> {
+    tracks: SharedTracks,
+    stats: &Stats,
+    policy: &DegradationPolicy,
+    now: DateTime<Utc>,
+) -> Result<Option<AircraftPosition>, StatusCode> {
     if data.odd_flag == CPRFormat::Odd {
         rest_info!("received an odd flag CPR format message.");
-        return Ok(()); // ignore even CPR format messages
+        return Ok(None); // ignore even CPR format messages
     }
 
     // Get the even packet from the cache
     let keys = vec![
         format!("{:x}:lat_cpr:{}", data.icao, CPRFormat::Odd as u8),
         format!("{:x}:lon_cpr:{}", data.icao, CPRFormat::Odd as u8),
+        format!("{:x}:cpr_received:{}", data.icao, CPRFormat::Odd as u8),
     ];
 
     let n_expected_results = keys.len();
-    let results = tlm_pool.multiple_get::<u32>(keys).await.map_err(|e| {
-        rest_warn!("could not get packet from cache: {e}");
-    })?;
+    let results = match tlm_pool.multiple_get::<i64>(keys).await {
+        Ok(results) => results,
+        Err(e) => {
+            rest_warn!("could not get packet from cache: {e}");
+            stats.record_error(Dependency::Redis);
+            policy.on_failure(Dependency::Redis)?;
+
+            // degraded, the pair can't be decoded
+            return Ok(None);
+        }
+    };
 
     if results.len() != n_expected_results {
         rest_warn!("unexpected result from cache.");
-        return Err(());
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    // the cache may hold messages for longer than they can be paired
+    let received_ms = data.received.timestamp_millis();
+    if !is_cpr_pair(received_ms, results[2], CPR_MAX_PAIR_AGE_MS) {
+        rest_info!("discarded stale CPR pair.");
+        return Ok(None);
     }
 
-    let (e_lat_cpr, e_lon_cpr) = (results[0], results[1]);
+    let (e_lat_cpr, e_lon_cpr) = (results[0] as u32, results[1] as u32);
     let (latitude, longitude) = decode_cpr(e_lat_cpr, e_lon_cpr, data.lat_cpr, data.lon_cpr)
         .map_err(|e| {
             rest_warn!("could not decode CPR: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-    let identifier = format!("{:x}", data.icao);
     let item = AircraftPosition {
-        identifier: identifier.clone(),
+        identifier: data.identifier,
         position: Position {
             latitude,
             longitude,
             altitude_meters: decode_altitude(data.alt) as f64,
         },
-        timestamp_network: Utc::now(),
+        timestamp_network: data.received,
         timestamp_asset: None,
     };
 
-    gis_pool
-        .push::<AircraftPosition>(item, REDIS_KEY_AIRCRAFT_POSITION)
-        .await
+    track(item, tracks, now).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Records a decoded position in the track of the aircraft at `now`,
+///  returning the position to push unless the track discarded it
+fn track(
+    item: AircraftPosition,
+    tracks: SharedTracks,
+    now: DateTime<Utc>,
+) -> Result<Option<AircraftPosition>, ()> {
+    let identifier = item.identifier.clone();
+    let decision = tracks
+        .lock()
+        .map_err(|e| {
+            rest_error!("could not lock tracks: {e}");
+        })?
+        .update(item, now);
+
+    match decision {
+        TrackDecision::Accept(item) | TrackDecision::Merge(item) => Ok(Some(item)),
+        TrackDecision::Discard => {
+            rest_info!("discarded out of order position for {identifier}.");
+            Ok(None)
+        }
+    }
+}
+
+/// Location of the ADS-B receivers configured as `latitude,longitude`
+fn configured_location(location: &str) -> Option<(f64, f64)> {
+    let (latitude, longitude) = location.split_once(',')?;
+    let latitude = latitude.trim().parse::<f64>().ok()?;
+    let longitude = longitude.trim().parse::<f64>().ok()?;
+    ((-90. ..=90.).contains(&latitude) && (-180. ..=180.).contains(&longitude))
+        .then_some((latitude, longitude))
+}
+
+/// Reference location to decode a surface position, with the altitude
+///  to report: the last known position of the aircraft, else the location
+///  of the receiver declared with the packet or configured
+fn surface_reference(
+    identifier: &str,
+    tracks: &SharedTracks,
+    signal: Option<SignalMetadata>,
+    config: &Config,
+) -> Option<Position> {
+    let last = tracks
+        .lock()
+        .map_err(|e| {
+            rest_error!("could not lock tracks: {e}");
+        })
+        .ok()
+        .and_then(|tracks| tracks.position(identifier).map(|item| item.position));
+
+    if last.is_some() {
+        return last;
+    }
+
+    let receiver = signal
+        .and_then(|signal| signal.receiver_latitude.zip(signal.receiver_longitude))
+        .or_else(|| configured_location(&config.adsb_receiver_location))?;
+
+    Some(Position {
+        latitude: receiver.0,
+        longitude: receiver.1,
+        altitude_meters: 0.,
+    })
 }
 
-/// Pushes a velocity telemetry message to the queue
+///
+/// Decodes a surface position from the CPR pair of the aircraft near the
+///  reference location, returning the position to push unless the track
+///  discarded it
+///
 #[cfg(not(tarpaulin_include))]
 // no_coverage: (R5) requires redis backend to test
-async fn gis_velocity_push(data: GisVelocityData, mut gis_pool: GisPool) -> Result<(), ()> {
+async fn decode_surface_position(
+    data: GisSurfacePositionData,
+    reference: Position,
+    mut tlm_pool: TelemetryPool,
+    tracks: SharedTracks,
+    stats: &Stats,
+    policy: &DegradationPolicy,
+    now: DateTime<Utc>,
+) -> Result<Option<AircraftPosition>, StatusCode> {
+    if data.odd_flag == CPRFormat::Odd {
+        return Ok(None);
+    }
+
+    let keys = vec![
+        format!("{:x}:surface_lat_cpr:{}", data.icao, CPRFormat::Odd as u8),
+        format!("{:x}:surface_lon_cpr:{}", data.icao, CPRFormat::Odd as u8),
+        format!(
+            "{:x}:surface_cpr_received:{}",
+            data.icao,
+            CPRFormat::Odd as u8
+        ),
+    ];
+
+    let n_expected_results = keys.len();
+    let results = match tlm_pool.multiple_get::<i64>(keys).await {
+        Ok(results) => results,
+        Err(e) => {
+            rest_warn!("could not get packet from cache: {e}");
+            stats.record_error(Dependency::Redis);
+            policy.on_failure(Dependency::Redis)?;
+
+            // degraded, the pair can't be decoded
+            return Ok(None);
+        }
+    };
+
+    if results.len() != n_expected_results {
+        rest_warn!("unexpected result from cache.");
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let received_ms = data.received.timestamp_millis();
+    if !is_cpr_pair(received_ms, results[2], CPR_SURFACE_MAX_PAIR_AGE_MS) {
+        rest_info!("discarded stale surface CPR pair.");
+        return Ok(None);
+    }
+
+    let location = (reference.latitude, reference.longitude);
+    let (o_lat_cpr, o_lon_cpr) = (results[0] as u32, results[1] as u32);
+    let (latitude, longitude) =
+        decode_cpr_surface(data.lat_cpr, data.lon_cpr, o_lat_cpr, o_lon_cpr, location).map_err(
+            |e| {
+                rest_warn!("could not decode surface CPR: {e}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            },
+        )?;
+
+    // surface messages carry no altitude, the aircraft stays where it landed
+    let item = AircraftPosition {
+        identifier: data.identifier,
+        position: Position {
+            latitude,
+            longitude,
+            altitude_meters: reference.altitude_meters,
+        },
+        timestamp_network: data.received,
+        timestamp_asset: None,
+    };
+
+    track(item, tracks, now).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Decodes a velocity, smoothed and recorded in the track of the aircraft,
+///  returning the velocity to push unless the track holds a newer one
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) requires redis backend to test
+fn decode_velocity(
+    data: GisVelocityData,
+    tracks: SharedTracks,
+    filters: SharedFilters,
+    received: DateTime<Utc>,
+) -> Result<Option<AircraftVelocity>, ()> {
     let (velocity_horizontal_ground_mps, track_angle_degrees) = decode_speed_direction(
         data.st,
         data.ew_sign,
@@ -183,87 +373,78 @@ async fn gis_velocity_push(data: GisVelocityData, mut gis_pool: GisPool) -> Resu
         })?;
 
     let item = AircraftVelocity {
-        identifier: format!("{:x}", data.icao),
+        identifier: data.identifier,
         velocity_horizontal_ground_mps,
         velocity_horizontal_air_mps: None,
         velocity_vertical_mps,
         track_angle_degrees,
         timestamp_asset: None,
-        timestamp_network: Utc::now(),
+        timestamp_network: received,
     };
 
-    gis_pool
-        .push::<AircraftVelocity>(item, REDIS_KEY_AIRCRAFT_VELOCITY)
-        .await
-}
-
-/// Post ADS-B Telemetry
-/// Min 8 bytes, max 263 bytes
-#[utoipa::path(
-    post,
-    path = "/telemetry/adsb",
-    tag = "svc-telemetry",
-    request_body = Vec<u8>,
-    responses(
-        (status = 200, description = "Telemetry received."),
-        (status = 400, description = "Malformed packet."),
-        (status = 500, description = "Something went wrong."),
-        (status = 503, description = "Dependencies of svc-telemetry were down."),
-    )
-)]
-#[cfg(not(tarpaulin_include))]
-// no_coverage: (R5) requires redis backend to test
-pub async fn adsb(
-    Extension(mut tlm_pools): Extension<TelemetryPools>,
-    Extension(gis_pool): Extension<GisPool>,
-    Extension(mq_channel): Extension<lapin::Channel>,
-    Extension(grpc_clients): Extension<GrpcClients>,
-    payload: Bytes,
-) -> Result<Json<u32>, StatusCode> {
-    rest_info!("entry.");
-    //
-    // ADS-B messages are 14 bytes long, small enough for a unique key
-    // If the key is not in the cache, add it
-    // If the key is in the cache, increment the count
-    //
-    let payload = <[u8; ADSB_SIZE_BYTES]>::try_from(payload.as_ref()).map_err(|_| {
-        rest_error!("received ads-b message not {ADSB_SIZE_BYTES} bytes.");
-        StatusCode::BAD_REQUEST
-    })?;
+    let item = filters
+        .lock()
+        .map_err(|e| {
+            rest_error!("could not lock velocity filters: {e}");
+        })?
+        .apply(item);
 
-    let key = crate::cache::bytes_to_key(&payload);
-    let count = tlm_pools
-        .adsb
-        .increment(&key, CACHE_EXPIRE_MS_ADSB)
-        .await
+    let newer = tracks
+        .lock()
         .map_err(|e| {
-            rest_error!("{e}");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+            rest_error!("could not lock tracks: {e}");
+        })?
+        .update_velocity(item.clone());
 
-    match count.cmp(&N_REPORTERS_NEEDED) {
-        Ordering::Less => {
-            rest_error!("ADS-B reporter count should be impossible: {count}.");
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-        Ordering::Greater => {
-            rest_info!("ADS-B reporter count is greater than needed: {count}.");
+    if !newer {
+        rest_info!("discarded out of order velocity for {}.", item.identifier);
+        return Ok(None);
+    }
 
-            // TODO(R5) push up to N reporter confirmations to svc-storage with user_ids
-            return Ok(Json(count));
-        }
-        _ => (), // continue
+    Ok(Some(item))
+}
+
+/// Packet type of an ADS-B message, for logging
+fn packet_type(me: &ME) -> &'static str {
+    match me {
+        Identification(_) => "adsb:identification",
+        AirbornePosition(_) | ME::AirbornePositionGNSSAltitude(_) => "adsb:position",
+        ME::SurfacePosition(_) => "adsb:surface_position",
+        Velocity(_) => "adsb:velocity",
+        _ => "adsb:other",
     }
+}
+
+/// Decodes a received ADS-B packet
+pub(crate) fn decode_frame(
+    payload: &[u8; ADSB_SIZE_BYTES],
+) -> Result<adsb_deku::Frame, StatusCode> {
+    adsb_deku::Frame::from_bytes((payload, 0))
+        .map(|(_, frame)| frame)
+        .map_err(|e| {
+            rest_info!("could not parse ads-b message: {e}");
+            StatusCode::BAD_REQUEST
+        })
+}
 
+/// Pushes a validated and deduplicated ADS-B packet to the sinks
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) requires redis backend to test
+pub(crate) async fn process_adsb(
+    payload: [u8; ADSB_SIZE_BYTES],
+    signal: Option<SignalMetadata>,
+    received: DateTime<Utc>,
+    pipeline: Pipeline,
+) -> Result<(), StatusCode> {
+    let mq_channel = pipeline.mq_channel.clone();
     //
     // Deconstruct Packet
     //
-    let frame = adsb_deku::Frame::from_bytes((&payload, 0)).map_err(|e| {
-        rest_info!("could not parse ads-b message: {e}");
-        StatusCode::BAD_REQUEST
-    })?;
+    if get_downlink_format(&payload) == Some(DF_COMM_B_IDENTITY_REPLY) {
+        return process_identity_reply(payload, pipeline).await;
+    }
 
-    let frame = frame.1;
+    let frame = decode_frame(&payload)?;
     let adsb_deku::DF::ADSB(msg) = &frame.df else {
         rest_info!("received a non-ADSB format message.");
         return Err(StatusCode::BAD_REQUEST);
@@ -279,17 +460,86 @@ pub async fn adsb(
     // The odd/even flag is used to differentiate between two packets
     //  that are part of the same message.
     let icao = get_adsb_icao_address(&msg.icao.0);
+    let identifier = pipeline.identifiers.icao(icao);
+    pipeline.stats.record_aircraft(&identifier);
+    context::set_aircraft(&identifier);
+    context::set_packet_type(packet_type(&msg.me));
+    pipeline.admit(&[&identifier], "adsb").await?;
 
-    match &msg.me {
+    let now = pipeline.clock.now();
+    let sinks = pipeline.sinks.clone();
+    let event = |data: EventData| {
+        let event =
+            TelemetryEvent::new(EventSource::Adsb, &identifier, data, now).with_received(received);
+        match signal {
+            Some(signal) => event.with_signal(signal),
+            None => event,
+        }
+    };
+    let Pipeline {
+        config,
+        tlm_pools,
+        tracks,
+        filters,
+        stats,
+        degradation,
+        ..
+    } = pipeline;
+    let mut tlm_pool = tlm_pools.adsb;
+    let interval_ms = config.adsb_state_interval_ms;
+    let received_ms = received.timestamp_millis();
+
+    // decoded airborne or surface position
+    let position = match &msg.me {
         Identification(adsb_deku::adsb::Identification { tc, ca, cn }) => {
-            gis_identifier_push(cn.clone(), *tc, *ca, gis_pool)
-                .await
-                .map_err(|_| {
-                    rest_error!("could not push position to queue.");
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })?;
+            let item = aircraft_id(cn.clone(), *tc, *ca, now);
+            let category = emitter_category_code(*tc, *ca);
+            sinks
+                .push(
+                    &event(EventData::Identification(item)).with_emitter_category(category.clone()),
+                )
+                .await?;
+
+            rest_info!("pushed aircraft id to sinks.");
 
-            rest_info!("pushed position to queue.");
+            let callsign = Update::Callsign(cn.trim().to_string());
+            enrich(
+                &mut tlm_pool,
+                &mq_channel,
+                &stats,
+                &identifier,
+                callsign,
+                now,
+            )
+            .await;
+            identity::observe(
+                &mut tlm_pool,
+                &mq_channel,
+                &stats,
+                config.identity_conflict_window_ms,
+                ConflictKind::Callsign,
+                &identifier,
+                cn.trim(),
+                now,
+            )
+            .await;
+
+            let update = state::Update::Identification {
+                callsign: cn.trim().to_string(),
+                category,
+            };
+            update_state(
+                &mut tlm_pool,
+                &mq_channel,
+                &stats,
+                &identifier,
+                update,
+                interval_ms,
+                now,
+            )
+            .await;
+
+            None
         }
         AirbornePosition(adsb_deku::Altitude {
             odd_flag,
@@ -312,35 +562,106 @@ pub async fn adsb(
                     format!("{:x}:lon_cpr:{}", icao, odd_flag),
                     lon_cpr.to_string(),
                 ),
+                (
+                    format!("{:x}:cpr_received:{}", icao, odd_flag),
+                    received_ms.to_string(),
+                ),
             ];
 
-            tlm_pools
-                .adsb
+            match tlm_pool
                 .multiple_set(keyvals, CACHE_EXPIRE_MS_AIRCRAFT_CPR)
                 .await
-                .map_err(|e| {
+            {
+                Ok(_) => rest_info!("added lat/lon to cache."),
+                Err(e) => {
                     rest_error!("could not add lat/lon to cache: {e}");
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })?;
+                    stats.record_error(Dependency::Redis);
+                    degradation.on_failure(Dependency::Redis)?;
+                }
+            }
 
-            rest_info!("added lat/lon to cache.");
+            if get_emergency_status(&payload) {
+                let update = Update::EmergencyStatus;
+                enrich(&mut tlm_pool, &mq_channel, &stats, &identifier, update, now).await;
+            }
 
             let data = GisPositionData {
                 icao,
+                identifier: identifier.clone(),
                 lat_cpr: *lat_cpr,
                 lon_cpr: *lon_cpr,
                 alt,
                 odd_flag: *odd_flag,
+                received,
             };
 
-            gis_position_push(data, tlm_pools.adsb, gis_pool)
+            let pool = tlm_pool.clone();
+            decode_position(data, pool, tracks, &stats, &degradation, now)
+                .await
+                .inspect_err(|_| rest_error!("could not decode position."))?
+        }
+        Surface(adsb_deku::adsb::SurfacePosition {
+            f,
+            lat_cpr,
+            lon_cpr,
+            ..
+        }) => {
+            let keyvals = vec![
+                (
+                    format!("{:x}:surface_lat_cpr:{}", icao, *f as u8),
+                    lat_cpr.to_string(),
+                ),
+                (
+                    format!("{:x}:surface_lon_cpr:{}", icao, *f as u8),
+                    lon_cpr.to_string(),
+                ),
+                (
+                    format!("{:x}:surface_cpr_received:{}", icao, *f as u8),
+                    received_ms.to_string(),
+                ),
+            ];
+
+            match tlm_pool
+                .multiple_set(keyvals, CACHE_EXPIRE_MS_SURFACE_CPR)
                 .await
-                .map_err(|_| {
-                    rest_error!("could not push position to queue.");
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })?;
+            {
+                Ok(_) => rest_info!("added surface lat/lon to cache."),
+                Err(e) => {
+                    rest_error!("could not add surface lat/lon to cache: {e}");
+                    stats.record_error(Dependency::Redis);
+                    degradation.on_failure(Dependency::Redis)?;
+                }
+            }
 
-            rest_info!("pushed position to queue.");
+            let data = GisSurfacePositionData {
+                icao,
+                identifier: identifier.clone(),
+                lat_cpr: *lat_cpr,
+                lon_cpr: *lon_cpr,
+                odd_flag: *f,
+                received,
+            };
+
+            match surface_reference(&identifier, &tracks, signal, &config) {
+                Some(reference) => {
+                    let pool = tlm_pool.clone();
+                    decode_surface_position(
+                        data,
+                        reference,
+                        pool,
+                        tracks,
+                        &stats,
+                        &degradation,
+                        now,
+                    )
+                    .await
+                    .inspect_err(|_| rest_error!("could not decode surface position."))?
+                }
+                None => {
+                    rest_info!("no reference location to decode the surface position.");
+                    None
+                }
+            }
         }
         Velocity(adsb_deku::adsb::AirborneVelocity {
             st,
@@ -365,7 +686,7 @@ pub async fn adsb(
             };
 
             let data = GisVelocityData {
-                icao,
+                identifier: identifier.clone(),
                 st: *st,
                 ew_sign: *ew_sign,
                 ew_vel: *ew_vel,
@@ -378,56 +699,379 @@ pub async fn adsb(
                 // gnss_baro_diff: *gnss_baro_diff,
             };
 
-            gis_velocity_push(data, gis_pool).await.map_err(|_| {
-                rest_error!("could not push velocity to queue.");
+            let item = decode_velocity(data, tracks, filters, received).map_err(|_| {
+                rest_error!("could not decode velocity.");
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
 
-            rest_info!("pushed velocity to queue.");
+            // the packet is still pushed if the track holds a newer velocity
+            if let Some(item) = item {
+                sinks
+                    .push(&event(EventData::Velocity(item.clone())))
+                    .await?;
+                rest_info!("pushed velocity to sinks.");
+
+                let update = state::Update::Velocity(item);
+                update_state(
+                    &mut tlm_pool,
+                    &mq_channel,
+                    &stats,
+                    &identifier,
+                    update,
+                    interval_ms,
+                    now,
+                )
+                .await;
+            }
+
+            None
         }
         _ => {
-            // for now, reject non-position messages
-            rest_info!("received an unrecognized message.");
-            return Err(StatusCode::BAD_REQUEST);
+            let update = get_target_state(&payload)
+                .map(state::Update::TargetState)
+                .or_else(|| get_operational_status(&payload).map(state::Update::OperationalStatus));
+
+            if let Some(update) = update {
+                update_state(
+                    &mut tlm_pool,
+                    &mq_channel,
+                    &stats,
+                    &identifier,
+                    update,
+                    interval_ms,
+                    now,
+                )
+                .await;
+            } else {
+                // emergency status messages carry the squawk
+                let Some(squawk) = get_squawk(&payload) else {
+                    rest_info!("received an unrecognized message.");
+                    return Err(StatusCode::BAD_REQUEST);
+                };
+
+                let update = Update::Squawk(squawk);
+                enrich(&mut tlm_pool, &mq_channel, &stats, &identifier, update, now).await;
+            }
+
+            None
         }
     };
 
-    //
-    // Send Telemetry to RabbitMQ
-    //
-    let _ = mq_channel
-        .basic_publish(
-            crate::amqp::EXCHANGE_NAME_TELEMETRY,
-            crate::amqp::ROUTING_KEY_ADSB,
-            lapin::options::BasicPublishOptions::default(),
-            &payload,
-            lapin::BasicProperties::default(),
+    if let Some(item) = position {
+        // the accuracy is reported by the last operational status
+        let update = state::Update::Position(item.clone());
+        let aircraft = update_state(
+            &mut tlm_pool,
+            &mq_channel,
+            &stats,
+            &identifier,
+            update,
+            interval_ms,
+            now,
         )
+        .await;
+
+        let mut position = event(EventData::Position(item));
+        // the NACp and geometric vertical accuracy bounds are used as is
+        let accuracy = aircraft.operational_status.and_then(|status| {
+            PositionAccuracy::from_bounds(
+                status.horizontal_accuracy_meters,
+                status.vertical_accuracy_meters,
+            )
+        });
+        if let Some(accuracy) = accuracy {
+            position = position.with_accuracy(accuracy);
+        }
+
+        sinks.push(&position).await?;
+        rest_info!("pushed position to sinks.");
+    }
+
+    // The packet itself, e.g. for RabbitMQ and svc-storage
+    sinks
+        .push(&event(EventData::Packet(payload.to_vec())))
+        .await?;
+
+    Ok(())
+}
+
+/// If the aircraft of an identity reply was seen recently, as a live track
+///  or by the cached state of its extended squitters
+async fn recently_seen(pipeline: &Pipeline, identifier: &str, now: DateTime<Utc>) -> bool {
+    let tracked = match pipeline.tracks.lock() {
+        Ok(tracks) => tracks.is_live(identifier, now),
+        Err(e) => {
+            rest_error!("could not lock tracks: {e}");
+            false
+        }
+    };
+
+    if tracked {
+        return true;
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.adsb.clone();
+    state::is_known(&mut tlm_pool, identifier)
         .await
-        .map_err(|e| rest_error!("telemetry push to RabbitMQ failed: {e}."))
-        .map(|_| rest_info!("telemetry pushed to RabbitMQ."));
+        .unwrap_or_else(|e| {
+            rest_warn!("could not get state of {identifier}: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            false
+        })
+}
+
+/// Propagates the squawk of a Comm-B identity reply (DF21)
+///
+/// The ICAO address of the aircraft is recovered from the parity, so any
+///  corrupted reply yields an address: replies of aircraft not seen
+///  recently are dropped. Replies aren't pushed to svc-gis nor svc-storage.
+async fn process_identity_reply(
+    payload: [u8; ADSB_SIZE_BYTES],
+    pipeline: Pipeline,
+) -> Result<(), StatusCode> {
+    let mq_channel = pipeline.mq_channel.clone();
+    let (Some(icao), Some(squawk)) = (get_reply_icao_address(&payload), get_squawk(&payload))
+    else {
+        rest_info!("could not decode identity reply.");
+        return Err(StatusCode::BAD_REQUEST);
+    };
+
+    let now = pipeline.clock.now();
+    let identifier = pipeline.identifiers.icao(icao);
+    if !recently_seen(&pipeline, &identifier, now).await {
+        rest_info!("identity reply of {identifier}, not seen recently, dropped.");
+        return Ok(());
+    }
+
+    pipeline.stats.record_aircraft(&identifier);
+    context::set_aircraft(&identifier);
+    context::set_packet_type("adsb:identity_reply");
+    pipeline.admit(&[&identifier], "adsb").await?;
+
+    let Pipeline {
+        tlm_pools, stats, ..
+    } = pipeline;
+
+    let mut tlm_pool = tlm_pools.adsb;
+    let update = Update::Squawk(squawk);
+    enrich(&mut tlm_pool, &mq_channel, &stats, &identifier, update, now).await;
+
+    Ok(())
+}
 
+/// Validates a received ADS-B packet and counts how often it was reported
+async fn receive(
+    counter: &DedupCounter,
+    stats: &Stats,
+    policy: &DegradationPolicy,
+    payload: &Bytes,
+    quorum: u32,
+) -> Result<([u8; ADSB_SIZE_BYTES], u32, Confirmation), StatusCode> {
     //
-    // Send to svc-storage
+    // ADS-B messages are 14 bytes long, small enough for a unique key
+    // If the key is not in the cache, add it
+    // If the key is in the cache, increment the count
     //
-    let data = adsb::Data {
-        icao_address: icao as i64,
-        message_type: get_adsb_message_type(&payload),
-        network_timestamp: Some(Utc::now().into()),
-        payload: payload.to_vec(),
+    let payload = <[u8; ADSB_SIZE_BYTES]>::try_from(payload.as_ref()).map_err(|_| {
+        rest_error!("received ads-b message not {ADSB_SIZE_BYTES} bytes.");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let key = crate::cache::packet_key("adsb", &payload);
+    let count = match counter.increment(&key, CACHE_EXPIRE_MS_ADSB).await {
+        Ok(count) => count,
+        Err(e) => {
+            rest_error!("{e}");
+            stats.record_error(Dependency::Redis);
+            policy.on_failure(Dependency::Redis)?;
+
+            // without deduplication, each report is the one reaching quorum
+            quorum.max(1)
+        }
     };
 
-    // Make request
-    let request = data;
-    let client = &grpc_clients.storage.adsb;
+    let confirmation = Confirmation::of(count, quorum);
+    stats.record_packet("adsb", confirmation == Confirmation::Confirmed);
+    match confirmation {
+        Confirmation::Pending => {
+            rest_info!("ADS-B packet awaiting quorum, reported {count} times.");
+        }
+        Confirmation::Confirmed => {
+            rest_info!("ADS-B packet already pushed, confirmed {count} times.");
 
-    client.insert(request).await.map_err(|e| {
-        rest_error!("telemetry push to svc-storage failed: {}.", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+            // TODO(R5) push up to N reporter confirmations to svc-storage with user_ids
+        }
+        Confirmation::Reached => (), // continue
+    }
 
-    rest_info!("telemetry pushed to svc-storage.");
+    Ok((payload, count, confirmation))
+}
 
+/// Counts the outcome of a packet for its reporter, if identified
+async fn score(pipeline: &Pipeline, reporter: Option<&str>, outcome: Option<ReporterOutcome>) {
+    if let (Some(reporter_id), Some(outcome)) = (reporter, outcome) {
+        reporter::record(pipeline, reporter_id, outcome, false).await;
+    }
+}
+
+/// Receives an ADS-B packet, rejecting it if its reporter is quarantined
+///  and counting its decode failures and duplicates
+async fn receive_reported(
+    pipeline: &Pipeline,
+    reporter: Option<&str>,
+    payload: &Bytes,
+) -> Result<([u8; ADSB_SIZE_BYTES], u32, Confirmation), StatusCode> {
+    if let Some(reporter_id) = reporter {
+        reporter::check(pipeline, reporter_id).await?;
+    }
+
+    let quorum = pipeline.config.reporter_quorum;
+    let received = receive(
+        &pipeline.dedup.adsb,
+        &pipeline.stats,
+        &pipeline.degradation,
+        payload,
+        quorum,
+    )
+    .await;
+
+    // valid packets are counted once processed
+    let outcome = match &received {
+        Ok((_, _, Confirmation::Confirmed)) => Some(ReporterOutcome::Duplicate),
+        Ok(_) => None,
+        Err(code) => reporter::outcome(&Err::<(), _>(*code), ReporterOutcome::DecodeFailure),
+    };
+    score(pipeline, reporter, outcome).await;
+
+    received
+}
+
+/// Post ADS-B Telemetry
+/// Min 8 bytes, max 263 bytes
+#[utoipa::path(
+    post,
+    path = "/v1/telemetry/adsb",
+    tag = "svc-telemetry",
+    params(SignalHeaders),
+    request_body(
+        content = BinaryPacket,
+        description = "ADS-B extended squitter or Comm-B identity reply (14 bytes).",
+        content_type = "application/octet-stream"
+    ),
+    responses(
+        (status = 200, description = "Telemetry received, with the number of times the packet was reported.", body = u32),
+        (status = 400, description = "Malformed packet or signal metadata."),
+        (status = 401, description = "Unknown API key."),
+        (status = 403, description = "The reporter of the API key is quarantined."),
+        (status = 413, description = "Text encoded packet too large."),
+        (status = 500, description = "Something went wrong."),
+        (status = 503, description = "Dependencies of svc-telemetry were down."),
+    )
+)]
+pub async fn adsb(
+    Extension(pipeline): Extension<Pipeline>,
+    reporter: Option<Extension<ApiKeyReporter>>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<Json<u32>, StatusCode> {
+    rest_info!("entry.");
+    let signal = signal_metadata(&headers)?;
+    let reporter = reporter.map(|Extension(ApiKeyReporter(reporter))| reporter);
+    let reception = Reception::new("/telemetry/adsb", reporter, signal, pipeline.clock.now());
+    handle(pipeline, reception, payload).await
+}
+
+/// Receives an ADS-B packet and pushes it to the backends
+pub(crate) async fn handle(
+    pipeline: Pipeline,
+    reception: Reception,
+    payload: Bytes,
+) -> Result<Json<u32>, StatusCode> {
+    let reporter = reception.reporter.as_deref();
+    let (payload, count, confirmation) = receive_reported(&pipeline, reporter, &payload).await?;
+    pipeline
+        .publish_raw(ROUTING_KEY_RAW_ADSB, &payload, &reception)
+        .await;
+    if confirmation != Confirmation::Reached {
+        return Ok(Json(count));
+    }
+
+    let result = process_adsb(
+        payload,
+        reception.signal,
+        reception.received,
+        pipeline.clone(),
+    )
+    .await;
+    let outcome = reporter::outcome(&result, ReporterOutcome::PlausibilityRejection);
+    score(&pipeline, reporter, outcome).await;
+    result?;
+
+    Ok(Json(count))
+}
+
+/// Post ADS-B Telemetry, queueing it for a dispatcher
+///  Serves `/telemetry/adsb` in `ingest` mode, see [`adsb`].
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) requires redis backend to test
+pub async fn adsb_ingest(
+    Extension(pipeline): Extension<Pipeline>,
+    reporter: Option<Extension<ApiKeyReporter>>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<Json<u32>, StatusCode> {
+    rest_info!("entry.");
+    let signal = signal_metadata(&headers)?;
+    let reporter = reporter.map(|Extension(ApiKeyReporter(reporter))| reporter);
+    let reception = Reception::new("/telemetry/adsb", reporter, signal, pipeline.clock.now());
+    handle_ingest(pipeline, reception, payload).await
+}
+
+/// Receives an ADS-B packet and queues it for a dispatcher
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) requires redis backend to test
+pub(crate) async fn handle_ingest(
+    pipeline: Pipeline,
+    reception: Reception,
+    payload: Bytes,
+) -> Result<Json<u32>, StatusCode> {
+    let reporter = reception.reporter.as_deref();
+    let (payload, count, confirmation) = receive_reported(&pipeline, reporter, &payload).await?;
+    pipeline
+        .publish_raw(ROUTING_KEY_RAW_ADSB, &payload, &reception)
+        .await;
+    if confirmation != Confirmation::Reached {
+        return Ok(Json(count));
+    }
+
+    // the contents are checked once dispatched
+    let decoded = decode_frame(&payload);
+    let outcome = reporter::outcome(&decoded, ReporterOutcome::DecodeFailure);
+    score(&pipeline, reporter, outcome).await;
+    decoded?;
+
+    let Pipeline {
+        mut tlm_pools,
+        stats,
+        ..
+    } = pipeline;
+    let entry = StreamEntry {
+        payload: payload.to_vec(),
+        identifier: None,
+        session: None,
+        mission: None,
+        signal: reception.signal,
+        received: Some(reception.received),
+    };
+
+    // the dispatchers can't be reached without the stream
+    enqueue(&mut tlm_pools.adsb, &entry).await.map_err(|e| {
+        rest_error!("could not queue ads-b message: {e}");
+        stats.record_error(Dependency::Redis);
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
+
+    rest_info!("queued ads-b message for dispatch.");
     Ok(Json(count))
 }
 
@@ -435,6 +1079,65 @@ pub async fn adsb(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_packet_type() {
+        let identification = Identification(adsb_deku::adsb::Identification {
+            tc: TypeCoding::A,
+            ca: 1,
+            cn: "TEST1234".to_string(),
+        });
+        assert_eq!(packet_type(&identification), "adsb:identification");
+        assert_eq!(packet_type(&ME::Reserved0([0; 6])), "adsb:other");
+    }
+
+    #[test]
+    fn test_surface_reference() {
+        let tracks = crate::msg::track::TrackMerger::shared(100);
+        let mut config = Config::new();
+        assert_eq!(surface_reference("4840d6", &tracks, None, &config), None);
+
+        config.adsb_receiver_location = "52.3, 4.8".to_string();
+        let reference = surface_reference("4840d6", &tracks, None, &config).unwrap();
+        assert_eq!((reference.latitude, reference.longitude), (52.3, 4.8));
+
+        // the location declared with the packet is preferred
+        let signal = SignalMetadata {
+            receiver_latitude: Some(51.99),
+            receiver_longitude: Some(4.375),
+            ..Default::default()
+        };
+        let reference = surface_reference("4840d6", &tracks, Some(signal), &config).unwrap();
+        assert_eq!((reference.latitude, reference.longitude), (51.99, 4.375));
+
+        // then the last known position of the aircraft
+        let position = Position {
+            latitude: 52.31,
+            longitude: 4.76,
+            altitude_meters: -3.,
+        };
+        tracks.lock().unwrap().update(
+            AircraftPosition {
+                identifier: "4840d6".to_string(),
+                position,
+                timestamp_network: Utc::now(),
+                timestamp_asset: None,
+            },
+            Utc::now(),
+        );
+        let reference = surface_reference("4840d6", &tracks, Some(signal), &config);
+        assert_eq!(reference, Some(position));
+    }
+
+    #[test]
+    fn test_configured_location() {
+        assert_eq!(configured_location("52.3,4.8"), Some((52.3, 4.8)));
+        assert_eq!(configured_location(" -33.9 , 151.2 "), Some((-33.9, 151.2)));
+        assert_eq!(configured_location(""), None);
+        assert_eq!(configured_location("52.3"), None);
+        assert_eq!(configured_location("91,0"), None);
+        assert_eq!(configured_location("0,east"), None);
+    }
+
     #[test]
     fn test_get_aircraft_type() {
         // in type coding (TC)
@@ -484,8 +1187,11 @@ mod tests {
         // TC = 3 (B) and category 5 is a reserved field
         assert_eq!(get_aircraft_type(TypeCoding::B, 5), AircraftType::Other);
 
-        // TC = 3 (B) and category 6 is a UAV
-        // assert_eq!(get_aircraft_type(TypeCoding::B, 6), AircraftType::Other);
+        // TC = 3 (B) and category 6 is a UAV of unknown type
+        assert_eq!(
+            get_aircraft_type(TypeCoding::B, 6),
+            AircraftType::Undeclared
+        );
 
         // TC = 3 (B) and category 7 is a rocket
         assert_eq!(get_aircraft_type(TypeCoding::B, 7), AircraftType::Rocket);
@@ -496,6 +1202,118 @@ mod tests {
             AircraftType::Rotorcraft
         );
 
+        // TC = 4 (A) and categories 1 to 6 are aeroplanes, light to heavy
+        for category in 1..=6 {
+            assert_eq!(
+                get_aircraft_type(TypeCoding::A, category),
+                AircraftType::Aeroplane
+            );
+        }
+
         // everything else is 'other' for now
+        assert_eq!(get_aircraft_type(TypeCoding::A, 0), AircraftType::Other);
+    }
+
+    /// Pipeline publishing to RabbitMQ only, its channel held in memory
+    async fn amqp_pipeline() -> Pipeline {
+        let config = Config {
+            telemetry_sinks: "amqp".to_string(),
+            ..Default::default()
+        };
+        crate::rest::api::test_pipeline(config).await
+    }
+
+    #[tokio::test]
+    async fn test_adsb_identity_reply() {
+        let pipeline = amqp_pipeline().await;
+
+        // DF21 reply of 4840d6 squawking 7700
+        let mut payload = [0u8; ADSB_SIZE_BYTES];
+        payload[0] = DF_COMM_B_IDENTITY_REPLY << 3;
+        payload[2] = 0x0A;
+        payload[3] = 0xAA;
+        let parity = (crate::msg::adsb::modes_crc(&payload[..11]) ^ 0x4840D6).to_be_bytes();
+        payload[11..].copy_from_slice(&parity[1..]);
+
+        let payload = Bytes::from(payload.to_vec());
+        let reply = || {
+            adsb(
+                Extension(pipeline.clone()),
+                None,
+                HeaderMap::new(),
+                payload.clone(),
+            )
+        };
+        assert_eq!(reply().await.unwrap().0, 1);
+
+        // the address of replies is only trusted for aircraft seen recently
+        let channel = &pipeline.mq_channel;
+        let enrichments = channel.drain(crate::amqp::QUEUE_NAME_ADSB_ENRICHMENT);
+        assert!(enrichments.unwrap_or_default().is_empty());
+
+        let position = AircraftPosition {
+            identifier: pipeline.identifiers.icao(0x4840D6),
+            position: Position {
+                latitude: 52.37,
+                longitude: 4.89,
+                altitude_meters: 1000.0,
+            },
+            timestamp_network: Utc::now(),
+            timestamp_asset: None,
+        };
+        let now = pipeline.clock.now();
+        pipeline.tracks.lock().unwrap().update(position, now);
+        assert_eq!(reply().await.unwrap().0, 1);
+
+        // replies only update the enrichment of the aircraft
+        let enrichments = channel.drain(crate::amqp::QUEUE_NAME_ADSB_ENRICHMENT);
+        assert_eq!(enrichments.unwrap().len(), 1);
+        assert!(channel
+            .drain(crate::amqp::QUEUE_NAME_ADSB)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_adsb_identification() {
+        let pipeline = amqp_pipeline().await;
+
+        // identification of 4840d6 as KLM1023
+        let payload = Bytes::from(hex::decode("8D4840D6202CC371C32CE0576098").unwrap());
+        let result = adsb(Extension(pipeline.clone()), None, HeaderMap::new(), payload).await;
+        assert_eq!(result.unwrap().0, 1);
+
+        let channel = &pipeline.mq_channel;
+        let ids = channel.drain(crate::amqp::QUEUE_NAME_ADSB_ID).unwrap();
+        assert_eq!(ids.len(), 1);
+        assert!(String::from_utf8_lossy(&ids[0]).contains("KLM1023"));
+    }
+
+    #[tokio::test]
+    async fn test_adsb_rejected() {
+        let pipeline = amqp_pipeline().await;
+
+        // not 14 bytes
+        let payload = Bytes::from_static(&[0x8D, 0x48, 0x40, 0xD6]);
+        let result = adsb(Extension(pipeline.clone()), None, HeaderMap::new(), payload).await;
+        assert_eq!(result.unwrap_err(), StatusCode::BAD_REQUEST);
+
+        // not an extended squitter
+        let payload = Bytes::from_static(&[0x28; ADSB_SIZE_BYTES]);
+        let result = adsb(Extension(pipeline.clone()), None, HeaderMap::new(), payload).await;
+        assert_eq!(result.unwrap_err(), StatusCode::BAD_REQUEST);
+
+        // malformed signal metadata
+        let mut headers = HeaderMap::new();
+        headers.insert(super::super::signal::HEADER_RSSI, "loud".parse().unwrap());
+        let payload = Bytes::from_static(&[0x8D; ADSB_SIZE_BYTES]);
+        let result = adsb(Extension(pipeline.clone()), None, headers, payload).await;
+        assert_eq!(result.unwrap_err(), StatusCode::BAD_REQUEST);
+
+        let channel = &pipeline.mq_channel;
+        assert!(channel
+            .drain(crate::amqp::QUEUE_NAME_ADSB)
+            .unwrap()
+            .is_empty());
     }
 }
@@ -0,0 +1,293 @@
+//! API keys of machine to machine feeders
+//!
+//! Feeders such as SDR ground stations have no aircraft identity to log
+//!  in with. They authenticate with a long-lived key in the `X-Api-Key`
+//!  header, mapped to the reporter identity their packets are counted and
+//!  scored under. Only SHA-256 digests of the keys are kept: in `API_KEYS`
+//!  for keys issued out of band, in the cache for keys issued through the
+//!  admin API.
+
+use super::Pipeline;
+use crate::cache::bytes_to_key;
+use crate::cache::pool::CacheError;
+use crate::stats::Dependency;
+use axum::{
+    extract::{Extension, Path},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use hyper::{Request, StatusCode};
+use lib_common::time::{DateTime, Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Header holding the API key of a request
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Length of the generated keys
+const API_KEY_LENGTH: usize = 40;
+
+/// Keys issued through the admin API expire after 30 days
+const CACHE_EXPIRE_MS_API_KEY: u32 = 30 * 24 * 3_600_000;
+
+/// Hash field holding the reporter of a key, or the digest of the key of
+///  a reporter
+const FIELD_API_KEY: &str = "api_key";
+
+/// Reporter identity of a request authenticated by API key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyReporter(pub String);
+
+/// API key issued to a reporter
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiKeyResponse {
+    /// Reporter identity of the key
+    #[schema(example = "sdr-station-1")]
+    pub reporter: String,
+
+    /// The key, to send in the `X-Api-Key` header. Only returned once.
+    pub api_key: String,
+
+    /// When the key expires
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Hex SHA-256 digest of a key, as kept at rest
+pub fn digest(api_key: &str) -> String {
+    bytes_to_key(&openssl::sha::sha256(api_key.as_bytes()))
+}
+
+/// Reporter of the `reporter=digest` entries holding the digest
+fn configured<'a>(api_keys: &'a str, digest: &str) -> Option<&'a str> {
+    api_keys
+        .split(',')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(reporter, expected)| (reporter.trim(), expected.trim().to_lowercase()))
+        .find(|(reporter, expected)| !reporter.is_empty() && *expected == digest)
+        .map(|(reporter, _)| reporter)
+}
+
+/// Cache key of a key digest, holding its reporter
+fn key_of_digest(digest: &str) -> String {
+    format!("api-key:{digest}")
+}
+
+/// Cache key of a reporter, holding the digest of its key
+fn key_of_reporter(reporter: &str) -> String {
+    format!("{reporter}:api-key")
+}
+
+/// Reporter of an API key, none if the key is unknown
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+async fn reporter(pipeline: &Pipeline, api_key: &str) -> Result<Option<String>, StatusCode> {
+    let digest = digest(api_key);
+    if let Some(reporter) = configured(&pipeline.config.api_keys, &digest) {
+        return Ok(Some(reporter.to_string()));
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    tlm_pool
+        .hash_get(&key_of_digest(&digest), FIELD_API_KEY)
+        .await
+        .map_err(|e| {
+            rest_warn!("could not get api key: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            StatusCode::SERVICE_UNAVAILABLE
+        })
+}
+
+/// Authenticate a request with an API key, if it holds one
+///
+/// The reporter of the key is passed to the handler as an
+///  [`ApiKeyReporter`]. Requests without key are left to the handler.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn authenticate<B>(
+    Extension(pipeline): Extension<Pipeline>,
+    mut req: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    let Some(api_key) = req.headers().get(API_KEY_HEADER) else {
+        return Ok(next.run(req).await);
+    };
+
+    let api_key = api_key.to_str().map_err(|_| {
+        rest_warn!("could not parse api key header.");
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    let reporter = reporter(&pipeline, api_key).await?.ok_or_else(|| {
+        rest_warn!("unknown api key refused.");
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    rest_debug!("request of reporter {reporter} authenticated by api key.");
+    req.extensions_mut().insert(ApiKeyReporter(reporter));
+    Ok(next.run(req).await)
+}
+
+/// Issue an API key to a reporter, revoking its previous key
+#[utoipa::path(
+    post,
+    path = "/v1/admin/api-keys/{reporter}",
+    tag = "svc-telemetry",
+    params(
+        ("reporter" = String, Path, description = "Reporter identity of the key"),
+    ),
+    responses(
+        (status = 201, description = "Key issued, only returned once.", body = ApiKeyResponse),
+        (status = 400, description = "Empty reporter."),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn issue_api_key(
+    Extension(pipeline): Extension<Pipeline>,
+    Path(reporter): Path<String>,
+) -> Result<(StatusCode, Json<ApiKeyResponse>), StatusCode> {
+    rest_debug!("entry.");
+    let reporter = reporter.trim().to_string();
+    if reporter.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    revoke(&pipeline, &reporter).await?;
+
+    let api_key: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(API_KEY_LENGTH)
+        .map(char::from)
+        .collect();
+
+    let digest = digest(&api_key);
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    for (key, value) in [
+        (key_of_digest(&digest), &reporter),
+        (key_of_reporter(&reporter), &digest),
+    ] {
+        tlm_pool
+            .hash_set(&key, FIELD_API_KEY, value, CACHE_EXPIRE_MS_API_KEY)
+            .await
+            .map_err(|e| {
+                rest_error!("could not store api key of {reporter}: {e}");
+                pipeline.stats.record_error(Dependency::Redis);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+    }
+
+    rest_info!("issued api key of reporter {reporter}.");
+    let expires_at = pipeline.clock.now()
+        + Duration::try_milliseconds(CACHE_EXPIRE_MS_API_KEY as i64).unwrap_or(Duration::zero());
+    Ok((
+        StatusCode::CREATED,
+        Json(ApiKeyResponse {
+            reporter,
+            api_key,
+            expires_at,
+        }),
+    ))
+}
+
+/// Revokes the key issued to a reporter, returning if it had one
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+async fn revoke(pipeline: &Pipeline, reporter: &str) -> Result<bool, StatusCode> {
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let internal_error = |e: CacheError| {
+        rest_error!("could not revoke api key of {reporter}: {e}");
+        pipeline.stats.record_error(Dependency::Redis);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let key = key_of_reporter(reporter);
+    let Some(digest) = tlm_pool
+        .hash_get(&key, FIELD_API_KEY)
+        .await
+        .map_err(internal_error)?
+    else {
+        return Ok(false);
+    };
+
+    tlm_pool
+        .delete(&key_of_digest(&digest))
+        .await
+        .map_err(internal_error)?;
+    tlm_pool.delete(&key).await.map_err(internal_error)?;
+    Ok(true)
+}
+
+/// Revoke the key issued to a reporter through the admin API
+///
+/// Keys listed in `API_KEYS` can only be revoked by removing them there.
+#[utoipa::path(
+    delete,
+    path = "/v1/admin/api-keys/{reporter}",
+    tag = "svc-telemetry",
+    params(
+        ("reporter" = String, Path, description = "Reporter identity of the key"),
+    ),
+    responses(
+        (status = 204, description = "Key revoked."),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 404, description = "No key was issued to the reporter."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn revoke_api_key(
+    Extension(pipeline): Extension<Pipeline>,
+    Path(reporter): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    rest_debug!("entry.");
+    match revoke(&pipeline, &reporter).await? {
+        true => {
+            rest_info!("revoked api key of reporter {reporter}.");
+            Ok(StatusCode::NO_CONTENT)
+        }
+        false => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest() {
+        // SHA-256 of "abc"
+        assert_eq!(
+            digest("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_configured() {
+        let api_keys = format!(
+            "sdr-station-1={}, sdr-station-2 = {},={},malformed",
+            digest("key-1"),
+            digest("key-2").to_uppercase(),
+            digest("key-3")
+        );
+
+        assert_eq!(
+            configured(&api_keys, &digest("key-1")),
+            Some("sdr-station-1")
+        );
+        assert_eq!(
+            configured(&api_keys, &digest("key-2")),
+            Some("sdr-station-2")
+        );
+
+        // entries without reporter, unknown keys
+        assert_eq!(configured(&api_keys, &digest("key-3")), None);
+        assert_eq!(configured(&api_keys, &digest("key-4")), None);
+        assert_eq!(configured("", &digest("key-1")), None);
+    }
+}
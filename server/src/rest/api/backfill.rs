@@ -0,0 +1,260 @@
+//! REST API endpoints replaying the ADS-B packets stored in svc-storage
+//!  into the svc-gis queues, after an outage of svc-gis
+//!
+//! The backfill runs in the background of the instance it was requested
+//!  from, one at a time. Positions are pushed with their original
+//!  timestamps, flagged as historical: svc-gis doesn't drop them as stale.
+
+use super::Pipeline;
+use crate::msg::adsb::ADSB_SIZE_BYTES;
+use crate::msg::backfill::{BackfillStatus, PositionReplay};
+use crate::stats::Dependency;
+use axum::{
+    extract::{Extension, Query},
+    Json,
+};
+use hyper::StatusCode;
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use svc_storage_client_grpc::prelude::*;
+use svc_storage_client_grpc::resources::adsb;
+
+/// Longest period replayed by a backfill
+const BACKFILL_MAX_PERIOD_HOURS: i64 = 24;
+
+/// Stored packets are read by slices of this length of the period, each
+///  slice sorted by reception before it is decoded
+const BACKFILL_SLICE_MS: i64 = 60000;
+
+/// Stored packets read per svc-storage search
+const BACKFILL_PAGE_SIZE: i32 = 1000;
+
+/// Period to replay
+#[derive(Debug, Clone, Deserialize)]
+pub struct BackfillQuery {
+    /// Start of the period (RFC 3339)
+    pub from: DateTime<Utc>,
+
+    /// End of the period (RFC 3339)
+    pub to: DateTime<Utc>,
+}
+
+/// If a period can be replayed: it ends after it starts, in the past, and
+///  spans at most [`BACKFILL_MAX_PERIOD_HOURS`]
+fn valid_period(from: DateTime<Utc>, to: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    let max_period = Duration::try_hours(BACKFILL_MAX_PERIOD_HOURS).unwrap_or(Duration::zero());
+    from < to && to <= now && to - from <= max_period
+}
+
+/// Stored packets received in `[from, to)`, sorted by reception
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) requires svc-storage to test
+async fn stored_packets(
+    pipeline: &Pipeline,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<(DateTime<Utc>, [u8; ADSB_SIZE_BYTES])>, String> {
+    let client = &pipeline.grpc_clients.storage.adsb;
+    let breaker = &pipeline.grpc_clients.breakers.storage;
+    let mut packets = vec![];
+    let mut page = 1;
+    loop {
+        let filter = AdvancedSearchFilter::search_between(
+            "network_timestamp".to_owned(),
+            from.to_rfc3339(),
+            to.to_rfc3339(),
+        )
+        .page_number(page)
+        .results_per_page(BACKFILL_PAGE_SIZE);
+
+        let list = breaker
+            .call(client.search(filter))
+            .await
+            .map_err(|e| format!("svc-storage search failed: {e}"))?
+            .into_inner()
+            .list;
+
+        let count = list.len();
+        packets.extend(
+            list.into_iter()
+                .filter_map(|object| object.data)
+                .filter_map(|data: adsb::Data| {
+                    let received: DateTime<Utc> = data.network_timestamp?.into();
+                    let payload = data.payload.try_into().ok()?;
+                    Some((received, payload))
+                })
+                // the search bounds are inclusive, slices share their bounds
+                .filter(|(received, _)| *received >= from && *received < to),
+        );
+
+        if count < BACKFILL_PAGE_SIZE as usize {
+            break;
+        }
+
+        page += 1;
+    }
+
+    packets.sort_by_key(|(received, _)| *received);
+    Ok(packets)
+}
+
+/// Replays the packets stored over the period into the svc-gis queues
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) requires svc-storage to test
+async fn backfill_job(pipeline: Pipeline, from: DateTime<Utc>, to: DateTime<Utc>) {
+    let slice = Duration::try_milliseconds(BACKFILL_SLICE_MS).unwrap_or(Duration::zero());
+    let mut replay = PositionReplay::default();
+    let mut gis_pool = pipeline.gis_pool.clone();
+    let mut start = from;
+    let mut error = None;
+
+    rest_info!("backfill of {from} to {to} started.");
+    'slices: while start < to {
+        let end = (start + slice).min(to);
+        let packets = match stored_packets(&pipeline, start, end).await {
+            Ok(packets) => packets,
+            Err(e) => {
+                pipeline.stats.record_error(Dependency::Storage);
+                error = Some(e);
+                break;
+            }
+        };
+
+        let mut positions = 0;
+        for (received, payload) in &packets {
+            let Some(position) = replay.replay(payload, *received, &pipeline.identifiers) else {
+                continue;
+            };
+
+            if let Err(e) = gis_pool.push_historical(position).await {
+                pipeline.stats.record_error(Dependency::Redis);
+                error = Some(format!("could not push position to svc-gis: {e}"));
+                break 'slices;
+            }
+
+            positions += 1;
+        }
+
+        if let Ok(mut status) = pipeline.backfill.lock() {
+            status.record(packets.len() as u64, positions);
+        }
+
+        start = end;
+    }
+
+    match &error {
+        Some(e) => rest_error!("backfill of {from} to {to} stopped at {start}: {e}"),
+        None => rest_info!("backfill of {from} to {to} done."),
+    }
+
+    match pipeline.backfill.lock() {
+        Ok(mut status) => status.finish(error),
+        Err(e) => rest_error!("could not lock backfill status: {e}"),
+    }
+}
+
+/// Replay the ADS-B packets stored over a period into svc-gis
+#[utoipa::path(
+    post,
+    path = "/v1/admin/backfill",
+    tag = "svc-telemetry",
+    params(
+        ("from" = String, Query, description = "Start of the period (RFC 3339)"),
+        ("to" = String, Query, description = "End of the period (RFC 3339), at most 24 hours after its start"),
+    ),
+    responses(
+        (status = 202, description = "Backfill started.", body = BackfillStatus),
+        (status = 400, description = "Invalid period."),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 409, description = "A backfill is running."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+pub async fn backfill(
+    Extension(pipeline): Extension<Pipeline>,
+    Query(query): Query<BackfillQuery>,
+) -> Result<(StatusCode, Json<BackfillStatus>), StatusCode> {
+    rest_debug!("entry.");
+    let BackfillQuery { from, to } = query;
+    if !valid_period(from, to, pipeline.clock.now()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let status = {
+        let mut status = pipeline.backfill.lock().map_err(|e| {
+            rest_error!("could not lock backfill status: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        if !status.start(from, to) {
+            return Err(StatusCode::CONFLICT);
+        }
+
+        status.clone()
+    };
+
+    tokio::spawn(backfill_job(pipeline, from, to));
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+/// Progress of the latest backfill of this instance
+#[utoipa::path(
+    get,
+    path = "/v1/admin/backfill",
+    tag = "svc-telemetry",
+    responses(
+        (status = 200, description = "Progress of the latest backfill.", body = BackfillStatus),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+pub async fn backfill_status(
+    Extension(pipeline): Extension<Pipeline>,
+) -> Result<Json<BackfillStatus>, StatusCode> {
+    rest_debug!("entry.");
+    let status = pipeline.backfill.lock().map_err(|e| {
+        rest_error!("could not lock backfill status: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(status.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_period() {
+        let now = Utc::now();
+        let hours = |n: i64| Duration::try_hours(n).unwrap();
+
+        assert!(valid_period(now - hours(2), now - hours(1), now));
+        assert!(valid_period(now - hours(24), now, now));
+        assert!(!valid_period(now - hours(1), now - hours(2), now));
+        assert!(!valid_period(now - hours(1), now - hours(1), now));
+        assert!(!valid_period(now - hours(1), now + hours(1), now));
+        assert!(!valid_period(now - hours(25), now, now));
+    }
+
+    #[tokio::test]
+    async fn test_backfill_conflict() {
+        let pipeline = super::super::test_pipeline(Default::default()).await;
+        let now = Utc::now();
+        let from = now - Duration::try_hours(2).unwrap();
+        let to = now - Duration::try_hours(1).unwrap();
+        pipeline.backfill.lock().unwrap().start(from, to);
+
+        let query = Query(BackfillQuery { from, to });
+        let result = backfill(Extension(pipeline.clone()), query).await;
+        assert_eq!(result.unwrap_err(), StatusCode::CONFLICT);
+
+        let query = Query(BackfillQuery { from: to, to: from });
+        let result = backfill(Extension(pipeline.clone()), query).await;
+        assert_eq!(result.unwrap_err(), StatusCode::BAD_REQUEST);
+
+        let Json(status) = backfill_status(Extension(pipeline)).await.unwrap();
+        assert!(status.running);
+        assert_eq!(status.from, Some(from));
+    }
+}
@@ -0,0 +1,293 @@
+//! Rejection of the telemetry of blocked aircraft, and REST API endpoints
+//!  managing the blocklist
+//!
+//! Every ingest path admits the aircraft of a packet through
+//!  [`Pipeline::admit`] once it is decoded, so packets queued in `ingest`
+//!  mode are screened by the dispatchers.
+//!  Aircraft blocked through the admin API are kept in a hash of the
+//!  Remote ID cache, expiring 30 days after its last change: aircraft to
+//!  block for good belong in `BLOCKLIST`.
+
+use super::Pipeline;
+use crate::cache::pool::CacheError;
+use crate::msg::blocklist::{normalize, reason, BlocklistEntry, BLOCKLIST_KEY, CONFIGURED_REASON};
+use crate::stats::Dependency;
+use axum::{
+    extract::{Extension, Path},
+    Json,
+};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// The blocklist hash expires 30 days after its last change
+const CACHE_EXPIRE_MS_BLOCKLIST: u32 = 30 * 24 * 3_600_000;
+
+/// Aircraft to block
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BlockRequest {
+    /// Why the aircraft is blocked, logged with each rejected packet
+    #[schema(example = "spoofed positions")]
+    pub reason: String,
+}
+
+/// Why an aircraft is blocked, if it is
+///
+/// The blocklist hash is read again once its local copy expires.
+///  Aircraft are let through if the cache can't be read: a Redis outage
+///  must not stop the ingest.
+async fn blocked(pipeline: &Pipeline, identifier: &str) -> Option<String> {
+    if pipeline.blocklist.contains(identifier) {
+        return Some(CONFIGURED_REASON.to_string());
+    }
+
+    let now = pipeline.clock.now();
+    if let Some(reason) = pipeline.blocklist.cached_reason(identifier, now) {
+        return reason;
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    match tlm_pool.hash_get_all(BLOCKLIST_KEY).await {
+        Ok(entries) => {
+            let reason = entries
+                .get(&normalize(identifier))
+                .map(|value| reason(value));
+            pipeline.blocklist.cache(entries, now);
+            reason
+        }
+        Err(e) => {
+            rest_warn!("could not check if {identifier} is blocked: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            None
+        }
+    }
+}
+
+/// Rejects the packets of blocked aircraft, logging each of them
+pub(crate) async fn screen(
+    pipeline: &Pipeline,
+    identifier: &str,
+    source: &str,
+) -> Result<(), StatusCode> {
+    let Some(reason) = blocked(pipeline, identifier).await else {
+        return Ok(());
+    };
+
+    rest_warn!("rejected {source} packet of blocked aircraft {identifier} ({reason}).");
+    pipeline.stats.record_blocked(source);
+    Err(StatusCode::FORBIDDEN)
+}
+
+/// Blocked aircraft, by identifier
+#[utoipa::path(
+    get,
+    path = "/v1/admin/blocklist",
+    tag = "svc-telemetry",
+    responses(
+        (status = 200, description = "Blocked aircraft, sorted by identifier.", body = [BlocklistEntry]),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+pub async fn blocklist(
+    Extension(pipeline): Extension<Pipeline>,
+) -> Result<Json<Vec<BlocklistEntry>>, StatusCode> {
+    rest_debug!("entry.");
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let cached = tlm_pool.hash_get_all(BLOCKLIST_KEY).await.map_err(|e| {
+        rest_error!("could not get blocklist: {e}");
+        pipeline.stats.record_error(Dependency::Redis);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(pipeline.blocklist.entries(&cached)))
+}
+
+/// Block an aircraft, rejecting its telemetry on all instances
+#[utoipa::path(
+    put,
+    path = "/v1/admin/blocklist/{identifier}",
+    tag = "svc-telemetry",
+    params(
+        ("identifier" = String, Path, description = "ICAO address (hex) or Remote ID identifier"),
+    ),
+    request_body = BlockRequest,
+    responses(
+        (status = 201, description = "Aircraft blocked.", body = BlocklistEntry),
+        (status = 400, description = "Empty identifier or reason."),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+pub async fn block(
+    Extension(pipeline): Extension<Pipeline>,
+    Path(identifier): Path<String>,
+    Json(request): Json<BlockRequest>,
+) -> Result<(StatusCode, Json<BlocklistEntry>), StatusCode> {
+    rest_debug!("entry.");
+    let identifier = normalize(&pipeline.identifiers.resolve(&identifier));
+    let reason = request.reason.trim().to_string();
+    if identifier.is_empty() || reason.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let entry = BlocklistEntry {
+        identifier,
+        reason,
+        added_at: Some(pipeline.clock.now()),
+    };
+
+    let value = serde_json::to_string(&entry).map_err(|e| {
+        rest_error!("could not serialize blocklist entry: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    tlm_pool
+        .hash_set(
+            BLOCKLIST_KEY,
+            &entry.identifier,
+            &value,
+            CACHE_EXPIRE_MS_BLOCKLIST,
+        )
+        .await
+        .map_err(|e| {
+            rest_error!("could not block {}: {e}", entry.identifier);
+            pipeline.stats.record_error(Dependency::Redis);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    pipeline.blocklist.invalidate();
+    rest_info!("blocked aircraft {} ({}).", entry.identifier, entry.reason);
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+/// Unblock an aircraft blocked through the admin API
+///
+/// Aircraft listed in `BLOCKLIST` can only be unblocked by removing them
+///  there.
+#[utoipa::path(
+    delete,
+    path = "/v1/admin/blocklist/{identifier}",
+    tag = "svc-telemetry",
+    params(
+        ("identifier" = String, Path, description = "ICAO address (hex) or Remote ID identifier"),
+    ),
+    responses(
+        (status = 204, description = "Aircraft unblocked."),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 404, description = "Aircraft not blocked."),
+        (status = 409, description = "Aircraft listed in BLOCKLIST."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+pub async fn unblock(
+    Extension(pipeline): Extension<Pipeline>,
+    Path(identifier): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    rest_debug!("entry.");
+    let identifier = normalize(&pipeline.identifiers.resolve(&identifier));
+    if pipeline.blocklist.contains(&identifier) {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let internal_error = |e: CacheError| {
+        rest_error!("could not unblock {identifier}: {e}");
+        pipeline.stats.record_error(Dependency::Redis);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let blocked = tlm_pool
+        .hash_get(BLOCKLIST_KEY, &identifier)
+        .await
+        .map_err(internal_error)?;
+    if blocked.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    tlm_pool
+        .hash_delete(BLOCKLIST_KEY, &[identifier.clone()])
+        .await
+        .map_err(internal_error)?;
+
+    pipeline.blocklist.invalidate();
+    rest_info!("unblocked aircraft {identifier}.");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_screen() {
+        let config = crate::Config {
+            identifier_rules: "icao.prefix=icao:,icao.pad=6".to_string(),
+            blocklist: "b2c3,test-drone".to_string(),
+            ..Default::default()
+        };
+        let pipeline = super::super::test_pipeline(config).await;
+
+        let identifier = pipeline.identifiers.icao(0xb2c3);
+        let result = screen(&pipeline, &identifier, "adsb").await;
+        assert_eq!(result, Err(StatusCode::FORBIDDEN));
+        let result = screen(&pipeline, "TEST-DRONE", "netrid").await;
+        assert_eq!(result, Err(StatusCode::FORBIDDEN));
+
+        let identifier = pipeline.identifiers.icao(0xa1b2c3);
+        assert_eq!(screen(&pipeline, &identifier, "adsb").await, Ok(()));
+
+        // a relayed aircraft is screened as well as its reporter
+        let result = pipeline.admit(&["drone-1", "test-drone"], "netrid").await;
+        assert_eq!(result, Err(StatusCode::FORBIDDEN));
+        assert_eq!(pipeline.admit(&["drone-1"], "netrid").await, Ok(()));
+
+        let summary = pipeline.stats.summary(Default::default());
+        assert_eq!(summary.blocked_packets["adsb"], 1);
+        assert_eq!(summary.blocked_packets["netrid"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_blocklist_endpoints() {
+        let config = crate::Config {
+            blocklist: "test-drone".to_string(),
+            ..Default::default()
+        };
+        let pipeline = super::super::test_pipeline(config).await;
+        let request = |reason: &str| {
+            Json(BlockRequest {
+                reason: reason.to_string(),
+            })
+        };
+
+        let (status, Json(entry)) = block(
+            Extension(pipeline.clone()),
+            Path(" Drone-1 ".to_string()),
+            request(" spoofed positions "),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(entry.identifier, "drone-1");
+        assert_eq!(entry.reason, "spoofed positions");
+        assert!(entry.added_at.is_some());
+
+        let result = block(
+            Extension(pipeline.clone()),
+            Path("drone-1".to_string()),
+            request(" "),
+        )
+        .await;
+        assert_eq!(result.unwrap_err(), StatusCode::BAD_REQUEST);
+
+        let Json(entries) = blocklist(Extension(pipeline.clone())).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].identifier, "test-drone");
+
+        let result = unblock(Extension(pipeline.clone()), Path("test-drone".to_string())).await;
+        assert_eq!(result, Err(StatusCode::CONFLICT));
+        let result = unblock(Extension(pipeline), Path("drone-2".to_string())).await;
+        assert_eq!(result, Err(StatusCode::NOT_FOUND));
+    }
+}
@@ -0,0 +1,207 @@
+//! C2 link status REST API
+//!  Aircraft flown beyond visual line of sight report the state of their
+//!  command and control link, published on the `c2_status` queue.
+
+use super::jwt::Claim;
+use super::Pipeline;
+use crate::logging::context;
+use crate::msg::c2::{C2LinkReport, C2LinkStatus};
+use crate::sink::{EventData, EventSource, TelemetryEvent};
+use crate::stats::Dependency;
+use axum::{
+    extract::{Extension, Path},
+    Json,
+};
+use hyper::StatusCode;
+
+/// Link state of an aircraft expires after 10 minutes without reports
+const CACHE_EXPIRE_MS_C2: u32 = 600000;
+
+/// Hash field holding the latest link state of an aircraft
+const FIELD_LATEST: &str = "latest";
+
+/// Cache key of the link state of an aircraft
+fn cache_key(identifier: &str) -> String {
+    format!("{identifier}:c2")
+}
+
+/// If the reported values are plausible
+fn is_plausible(report: &C2LinkReport) -> bool {
+    let rssi = report
+        .rssi_dbm
+        .is_none_or(|rssi| (-150.0..=0.0).contains(&rssi));
+    let quality = report
+        .link_quality_percent
+        .is_none_or(|percent| percent <= 100);
+
+    rssi && quality
+}
+
+/// Report the state of the C2 link of an aircraft
+///  The aircraft is identified by the subject of its token.
+#[utoipa::path(
+    post,
+    path = "/v1/telemetry/c2-status",
+    tag = "svc-telemetry",
+    request_body = C2LinkReport,
+    responses(
+        (status = 200, description = "Link state cached and published."),
+        (status = 400, description = "Implausible value."),
+        (status = 401, description = "Missing or invalid JWT token.", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body."),
+        (status = 500, description = "Something went wrong."),
+        (status = 501, description = "Not served in ingest mode."),
+        (status = 503, description = "Dependencies of svc-telemetry were down."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP and redis backends to test
+pub async fn c2_status(
+    Extension(pipeline): Extension<Pipeline>,
+    Extension(claim): Extension<Claim>,
+    Json(report): Json<C2LinkReport>,
+) -> Result<StatusCode, StatusCode> {
+    rest_info!("entry.");
+    context::set_aircraft(&claim.sub);
+    context::set_packet_type("c2:status");
+    pipeline.stats.record_packet("c2", false);
+
+    if !is_plausible(&report) {
+        rest_warn!("implausible C2 link state reported by {}.", claim.sub);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let status = C2LinkStatus {
+        identifier: claim.sub.clone(),
+        report,
+        timestamp_network: pipeline.clock.now(),
+    };
+
+    let value = serde_json::to_string(&status).map_err(|e| {
+        rest_error!("could not serialize C2 link state: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    if let Err(e) = tlm_pool
+        .hash_set(
+            &cache_key(&claim.sub),
+            FIELD_LATEST,
+            &value,
+            CACHE_EXPIRE_MS_C2,
+        )
+        .await
+    {
+        rest_error!("could not cache C2 link state: {e}");
+        pipeline.stats.record_error(Dependency::Redis);
+        pipeline.degradation.on_failure(Dependency::Redis)?;
+    }
+
+    let loss = pipeline
+        .c2_links
+        .lock()
+        .map_err(|e| {
+            rest_error!("could not lock C2 links: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .report(&status);
+
+    if let Some(loss) = loss {
+        crate::amqp::c2::publish_loss(&pipeline.mq_channel, &pipeline.stats, &loss).await;
+    }
+
+    let mut event = TelemetryEvent::new(
+        EventSource::Vehicle,
+        &claim.sub,
+        EventData::C2Link(status),
+        pipeline.clock.now(),
+    );
+    if let Some(session) = claim.sid {
+        event = event.with_session(session);
+    }
+
+    if let Some(mission) = claim.mission {
+        event = event.with_mission(mission);
+    }
+
+    pipeline.sinks.push(&event).await?;
+    rest_debug!("pushed C2 link state to sinks.");
+
+    Ok(StatusCode::OK)
+}
+
+/// Latest state of the C2 link of an aircraft, reported within the last
+///  10 minutes
+#[utoipa::path(
+    get,
+    path = "/v1/telemetry/c2-status/{identifier}",
+    tag = "svc-telemetry",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the aircraft"),
+    ),
+    responses(
+        (status = 200, description = "Latest link state of the aircraft.", body = C2LinkStatus),
+        (status = 404, description = "No recent link state from the aircraft."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn latest_c2_status(
+    Extension(pipeline): Extension<Pipeline>,
+    Path(identifier): Path<String>,
+) -> Result<Json<C2LinkStatus>, StatusCode> {
+    rest_debug!("entry.");
+    let identifier = pipeline.identifiers.resolve(&identifier);
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let fields = tlm_pool
+        .hash_get_all(&cache_key(&identifier))
+        .await
+        .map_err(|e| {
+            rest_error!("could not get C2 link state: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let value = fields.get(FIELD_LATEST).ok_or(StatusCode::NOT_FOUND)?;
+    let status = serde_json::from_str(value).map_err(|e| {
+        rest_error!("could not parse cached C2 link state: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::c2::C2LinkType;
+
+    #[test]
+    fn test_is_plausible() {
+        let report = C2LinkReport {
+            link_type: C2LinkType::Radio,
+            rssi_dbm: Some(-90.0),
+            latency_ms: Some(40),
+            link_quality_percent: Some(97),
+            timestamp_asset: None,
+        };
+        assert!(is_plausible(&report));
+
+        assert!(!is_plausible(&C2LinkReport {
+            rssi_dbm: Some(12.0),
+            ..report
+        }));
+        assert!(!is_plausible(&C2LinkReport {
+            link_quality_percent: Some(101),
+            ..report
+        }));
+        assert!(is_plausible(&C2LinkReport {
+            link_type: C2LinkType::None,
+            rssi_dbm: None,
+            latency_ms: None,
+            link_quality_percent: None,
+            timestamp_asset: None,
+        }));
+    }
+}
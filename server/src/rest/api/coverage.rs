@@ -0,0 +1,90 @@
+//! Coverage map REST API
+//!  Where the receivers declaring their location observe aircraft, for
+//!  operations to see where ADS-B and Remote ID coverage is weak.
+
+use super::Pipeline;
+use crate::msg::coverage::geojson;
+use axum::{
+    extract::{Extension, Query},
+    Json,
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Filter of the coverage map
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoverageQuery {
+    /// Only the cells of this receiver, by the geohash of its location
+    pub receiver: Option<String>,
+}
+
+/// Coverage of the receivers as a GeoJSON heatmap
+///
+/// Each feature is a geohash cell where a receiver observed positions
+///  within the last hour, with the number of observations and the mean
+///  signal strength and signal to noise ratio as properties.
+#[utoipa::path(
+    get,
+    path = "/v1/telemetry/coverage",
+    tag = "svc-telemetry",
+    params(
+        ("receiver" = Option<String>, Query, description = "Only the cells of this receiver, by the geohash of its location"),
+    ),
+    responses(
+        (status = 200, description = "GeoJSON feature collection of the observed cells, sorted by receiver and cell."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+pub async fn coverage(
+    Extension(pipeline): Extension<Pipeline>,
+    Query(query): Query<CoverageQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    rest_debug!("entry.");
+    let cells = pipeline
+        .coverage
+        .lock()
+        .map_err(|e| {
+            rest_error!("could not lock coverage map: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .cells(query.receiver.as_deref());
+
+    Ok(Json(geojson(&cells)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amqp::envelope::SignalMetadata;
+    use lib_common::time::Utc;
+
+    #[tokio::test]
+    async fn test_coverage() {
+        let pipeline = super::super::test_pipeline(crate::Config::default()).await;
+        let signal = SignalMetadata {
+            receiver_latitude: Some(52.3),
+            receiver_longitude: Some(4.8),
+            rssi_dbm: Some(-70.0),
+            snr_db: None,
+            broadcaster_mac: None,
+        };
+        pipeline
+            .coverage
+            .lock()
+            .unwrap()
+            .record(&signal, 52.37, 4.9, Utc::now());
+
+        let query = CoverageQuery { receiver: None };
+        let Json(value) = coverage(Extension(pipeline.clone()), Query(query))
+            .await
+            .unwrap();
+        assert_eq!(value["features"].as_array().unwrap().len(), 1);
+
+        let query = CoverageQuery {
+            receiver: Some("unknown".to_string()),
+        };
+        let Json(value) = coverage(Extension(pipeline), Query(query)).await.unwrap();
+        assert!(value["features"].as_array().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,28 @@
+//! Development REST API
+//!  Without a RabbitMQ node, the published telemetry is held in memory and
+//!  read back here by front-end developers.
+
+use super::Pipeline;
+use crate::amqp::memory::message_json;
+use axum::{
+    extract::{Extension, Path},
+    Json,
+};
+use hyper::StatusCode;
+use serde_json::Value;
+
+/// Take the messages published to a queue since the last call, oldest first
+///
+/// Envelopes are returned as JSON, raw packets hex encoded.
+pub async fn queue_messages(
+    Extension(pipeline): Extension<Pipeline>,
+    Path(queue): Path<String>,
+) -> Result<Json<Vec<Value>>, StatusCode> {
+    rest_debug!("entry.");
+    let messages = pipeline.mq_channel.drain(&queue).ok_or_else(|| {
+        rest_info!("unknown queue {queue}.");
+        StatusCode::NOT_FOUND
+    })?;
+
+    Ok(Json(messages.iter().map(|m| message_json(m)).collect()))
+}
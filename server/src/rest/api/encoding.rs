@@ -0,0 +1,163 @@
+//! Text encoded telemetry packets
+//!
+//! Clients which can only send text post packets as `text/plain`,
+//!  hex or base64 encoded. Such bodies are decoded to bytes before
+//!  reaching the telemetry handlers.
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use base64::Engine;
+use http_body::{LengthLimitError, Limited};
+use hyper::StatusCode;
+
+/// Media type of text encoded packets
+const MIME_TEXT_PLAIN: &str = "text/plain";
+
+/// Media type of packets once decoded
+const MIME_OCTET_STREAM: &str = "application/octet-stream";
+
+/// Largest request body read by the middlewares, the limit the axum
+///  extractors of the other routes default to
+pub const BODY_LIMIT_BYTES: usize = 2 * 1024 * 1024;
+
+/// If the request body is text
+fn is_text(request: &Request<Body>) -> bool {
+    request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case(MIME_TEXT_PLAIN))
+}
+
+/// Decodes a hex or base64 encoded packet
+///
+/// Whitespace is ignored. Text made only of hex digits is decoded as hex,
+///  anything else as (padded) base64.
+pub fn decode(text: &[u8]) -> Result<Vec<u8>, StatusCode> {
+    let text: Vec<u8> = text
+        .iter()
+        .copied()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+
+    if text.is_empty() {
+        rest_warn!("received empty text packet.");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if text.iter().all(u8::is_ascii_hexdigit) {
+        return hex::decode(&text).map_err(|e| {
+            rest_warn!("could not decode hex packet: {e}.");
+            StatusCode::BAD_REQUEST
+        });
+    }
+
+    base64::engine::general_purpose::STANDARD
+        .decode(&text)
+        .map_err(|e| {
+            rest_warn!("could not decode base64 packet: {e}.");
+            StatusCode::BAD_REQUEST
+        })
+}
+
+/// Reads a request body of up to [`BODY_LIMIT_BYTES`]
+pub async fn read_body(body: Body) -> Result<Bytes, StatusCode> {
+    hyper::body::to_bytes(Limited::new(body, BODY_LIMIT_BYTES))
+        .await
+        .map_err(|e| match e.downcast_ref::<LengthLimitError>() {
+            Some(_) => {
+                rest_warn!("request body larger than {BODY_LIMIT_BYTES} bytes.");
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            None => {
+                rest_warn!("could not read request body: {e}.");
+                StatusCode::BAD_REQUEST
+            }
+        })
+}
+
+/// Decodes text encoded request bodies, passing binary bodies through
+pub async fn decode_text(request: Request<Body>, next: Next<Body>) -> Result<Response, StatusCode> {
+    if !is_text(&request) {
+        return Ok(next.run(request).await);
+    }
+
+    let (mut parts, body) = request.into_parts();
+    let text = read_body(body).await?;
+
+    let payload = decode(&text)?;
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(MIME_OCTET_STREAM),
+    );
+    parts.headers.remove(header::CONTENT_LENGTH);
+
+    let request = Request::from_parts(parts, Body::from(Bytes::from(payload)));
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_hex() {
+        let payload = decode(b"8D4840D6202CC371C32CE0576098").unwrap();
+        assert_eq!(payload.len(), 14);
+        assert_eq!(payload[0], 0x8D);
+
+        // whitespace and lowercase
+        assert_eq!(decode(b" 8d 48 40\n").unwrap(), vec![0x8D, 0x48, 0x40]);
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        let payload = decode(b"jUhA1iAsw3HDLOBXYJg=").unwrap();
+        assert_eq!(payload, decode(b"8D4840D6202CC371C32CE0576098").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_read_body() {
+        let body = Body::from(vec![b'0'; BODY_LIMIT_BYTES]);
+        assert_eq!(read_body(body).await.unwrap().len(), BODY_LIMIT_BYTES);
+
+        let body = Body::from(vec![b'0'; BODY_LIMIT_BYTES + 1]);
+        assert_eq!(read_body(body).await, Err(StatusCode::PAYLOAD_TOO_LARGE));
+    }
+
+    #[test]
+    fn test_decode_malformed() {
+        assert_eq!(decode(b""), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(decode(b" \n"), Err(StatusCode::BAD_REQUEST));
+
+        // odd number of hex digits
+        assert_eq!(decode(b"8D4"), Err(StatusCode::BAD_REQUEST));
+
+        // invalid characters
+        assert_eq!(decode(b"8D!!"), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(decode(&[0xFF, 0xFE]), Err(StatusCode::BAD_REQUEST));
+
+        // missing base64 padding
+        assert_eq!(decode(b"jUhA1iAsw3HDLOBXYJg"), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn test_is_text() {
+        let request = |content_type: &str| {
+            Request::builder()
+                .header(header::CONTENT_TYPE, content_type)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        assert!(is_text(&request("text/plain")));
+        assert!(is_text(&request("Text/Plain; charset=utf-8")));
+        assert!(!is_text(&request(MIME_OCTET_STREAM)));
+        assert!(!is_text(&Request::new(Body::empty())));
+    }
+}
@@ -0,0 +1,234 @@
+//! Enrichment of ADS-B aircraft with their callsign and squawk
+//!
+//! Callsigns and squawks arrive in separate messages, so the last ones
+//!  seen are cached per aircraft and published together. Emergencies
+//!  are also published to the alert queue, once per change.
+
+use crate::amqp::envelope::TelemetryEnvelope;
+use crate::cache::pool::TelemetryPool;
+use crate::msg::adsb::{AircraftEnrichment, Emergency, Squawk};
+use crate::stats::{Dependency, Stats};
+use lib_common::time::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Enrichment entries in the cache expire after 10 minutes without updates
+const CACHE_EXPIRE_MS_ENRICHMENT: u32 = 600000;
+
+/// Hash field of the cached callsign
+const FIELD_CALLSIGN: &str = "callsign";
+
+/// Hash field of the cached squawk
+const FIELD_SQUAWK: &str = "squawk";
+
+/// Hash field of the last emergency alerted
+const FIELD_EMERGENCY: &str = "emergency";
+
+/// Information received about an aircraft
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Update {
+    /// Callsign from an identification message
+    Callsign(String),
+
+    /// Squawk from an identity reply or emergency status message
+    Squawk(Squawk),
+
+    /// Airborne position with an emergency surveillance status
+    EmergencyStatus,
+}
+
+/// Name of an emergency in the cache
+fn emergency_name(emergency: Option<Emergency>) -> String {
+    emergency
+        .and_then(|emergency| serde_json::to_value(emergency).ok())
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Merges an update with the cached enrichment of an aircraft,
+///  returning the enrichment and whether a new emergency must be alerted
+fn merge(
+    identifier: &str,
+    cached: &HashMap<String, String>,
+    update: &Update,
+    now: DateTime<Utc>,
+) -> (AircraftEnrichment, bool) {
+    let mut callsign = cached.get(FIELD_CALLSIGN).cloned();
+    let mut squawk = cached
+        .get(FIELD_SQUAWK)
+        .and_then(|squawk| u16::from_str_radix(squawk, 8).ok())
+        .map(Squawk);
+
+    match update {
+        Update::Callsign(value) => callsign = Some(value.clone()),
+        Update::Squawk(value) => squawk = Some(*value),
+        Update::EmergencyStatus => (),
+    }
+
+    let mut emergency = squawk.and_then(|squawk| squawk.emergency());
+    if *update == Update::EmergencyStatus {
+        emergency = emergency.or(Some(Emergency::Unspecified));
+    }
+
+    let alerted = cached.get(FIELD_EMERGENCY).cloned().unwrap_or_default();
+    let alert = emergency.is_some() && emergency_name(emergency) != alerted;
+
+    let enrichment = AircraftEnrichment {
+        identifier: identifier.to_string(),
+        callsign,
+        squawk: squawk.map(|squawk| squawk.to_string()),
+        emergency,
+        timestamp_network: now,
+    };
+
+    (enrichment, alert)
+}
+
+/// Publishes an item to RabbitMQ
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP backend to test
+async fn publish(
+    mq_channel: &crate::amqp::MqChannel,
+    stats: &Stats,
+    routing_key: &str,
+    enrichment: &AircraftEnrichment,
+) {
+    let Ok(msg) = serde_json::to_vec(&TelemetryEnvelope::new(enrichment)) else {
+        rest_warn!("could not serialize enrichment.");
+        return;
+    };
+
+    let _ = mq_channel
+        .basic_publish(
+            crate::amqp::EXCHANGE_NAME_TELEMETRY,
+            routing_key,
+            lapin::options::BasicPublishOptions::default(),
+            &msg,
+            lapin::BasicProperties::default(),
+        )
+        .await
+        .map_err(|e| {
+            rest_warn!("could not push enrichment to RabbitMQ ({routing_key}): {e}.");
+            stats.record_error(Dependency::Amqp);
+        });
+}
+
+/// Caches and publishes information received about an aircraft
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis and AMQP backends to test
+pub(crate) async fn enrich(
+    tlm_pool: &mut TelemetryPool,
+    mq_channel: &crate::amqp::MqChannel,
+    stats: &Stats,
+    identifier: &str,
+    update: Update,
+    now: DateTime<Utc>,
+) {
+    let key = format!("{identifier}:enrichment");
+    let cached = tlm_pool.hash_get_all(&key).await.unwrap_or_else(|e| {
+        rest_warn!("could not get enrichment of {identifier} from cache: {e}");
+        stats.record_error(Dependency::Redis);
+        HashMap::new()
+    });
+
+    let (enrichment, alert) = merge(identifier, &cached, &update, now);
+    let field = match &update {
+        Update::Callsign(callsign) => Some((FIELD_CALLSIGN, callsign.clone())),
+        Update::Squawk(squawk) => Some((FIELD_SQUAWK, squawk.to_string())),
+        Update::EmergencyStatus => None,
+    };
+
+    // a squawk clearing the emergency allows alerting it again
+    let emergency = emergency_name(enrichment.emergency);
+    let changed = cached.get(FIELD_EMERGENCY).cloned().unwrap_or_default() != emergency;
+    let alerted = match &update {
+        Update::Callsign(_) => None,
+        _ if changed => Some((FIELD_EMERGENCY, emergency)),
+        _ => None,
+    };
+
+    for (field, value) in field.into_iter().chain(alerted) {
+        if let Err(e) = tlm_pool
+            .hash_set(&key, field, &value, CACHE_EXPIRE_MS_ENRICHMENT)
+            .await
+        {
+            rest_warn!("could not cache {field} of {identifier}: {e}");
+            stats.record_error(Dependency::Redis);
+        }
+    }
+
+    publish(
+        mq_channel,
+        stats,
+        crate::amqp::ROUTING_KEY_ADSB_ENRICHMENT,
+        &enrichment,
+    )
+    .await;
+
+    if alert {
+        rest_warn!(
+            "aircraft {identifier} declared an emergency ({:?}).",
+            enrichment.emergency
+        );
+
+        publish(
+            mq_channel,
+            stats,
+            crate::amqp::ROUTING_KEY_ALERT,
+            &enrichment,
+        )
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let now = Utc::now();
+        let mut cached = HashMap::new();
+
+        let update = Update::Callsign("KLM1023".to_string());
+        let (enrichment, alert) = merge("4840d6", &cached, &update, now);
+        assert_eq!(enrichment.identifier, "4840d6");
+        assert_eq!(enrichment.callsign, Some("KLM1023".to_string()));
+        assert_eq!(enrichment.squawk, None);
+        assert_eq!(enrichment.emergency, None);
+        assert!(!alert);
+
+        // squawk added to the cached callsign
+        cached.insert(FIELD_CALLSIGN.to_string(), "KLM1023".to_string());
+        let (enrichment, alert) = merge("4840d6", &cached, &Update::Squawk(Squawk(0o1200)), now);
+        assert_eq!(enrichment.callsign, Some("KLM1023".to_string()));
+        assert_eq!(enrichment.squawk, Some("1200".to_string()));
+        assert!(!alert);
+    }
+
+    #[test]
+    fn test_merge_emergency() {
+        let now = Utc::now();
+        let mut cached = HashMap::new();
+
+        let (enrichment, alert) = merge("4840d6", &cached, &Update::Squawk(Squawk(0o7600)), now);
+        assert_eq!(enrichment.emergency, Some(Emergency::RadioFailure));
+        assert!(alert);
+
+        // alerted once
+        cached.insert(FIELD_SQUAWK.to_string(), "7600".to_string());
+        cached.insert(FIELD_EMERGENCY.to_string(), "radio_failure".to_string());
+        let (enrichment, alert) = merge("4840d6", &cached, &Update::EmergencyStatus, now);
+        assert_eq!(enrichment.emergency, Some(Emergency::RadioFailure));
+        assert!(!alert);
+
+        // emergency changed
+        let (enrichment, alert) = merge("4840d6", &cached, &Update::Squawk(Squawk(0o7500)), now);
+        assert_eq!(enrichment.emergency, Some(Emergency::Hijack));
+        assert!(alert);
+
+        // surveillance status without an emergency squawk
+        let (enrichment, alert) = merge("4840d6", &HashMap::new(), &Update::EmergencyStatus, now);
+        assert_eq!(enrichment.emergency, Some(Emergency::Unspecified));
+        assert!(alert);
+    }
+}
@@ -1,15 +1,42 @@
 //! REST API endpoint for health check
 
+use super::Pipeline;
 use crate::grpc::client::GrpcClients;
+use crate::stats::Dependency;
 use axum::extract::Extension;
 use hyper::StatusCode;
 use svc_gis_client_grpc::prelude::*;
 use svc_storage_client_grpc::prelude::*;
 
+/// Check if each gRPC dependency is ready, through its circuit breaker
+///
+/// Services whose breaker is open are reported unavailable until a probe
+///  succeeds, see [`crate::grpc::breaker`].
+pub(crate) async fn readiness(grpc_clients: &GrpcClients) -> Vec<(Dependency, Result<(), String>)> {
+    let breakers = &grpc_clients.breakers;
+    let storage = breakers
+        .storage
+        .call(grpc_clients.storage.adsb.is_ready(ReadyRequest {}))
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("svc-storage adsb unavailable: {e}"));
+
+    let gis = breakers
+        .gis
+        .call(grpc_clients.gis.is_ready(gis::ReadyRequest {}))
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("svc-gis unavailable: {e}"));
+
+    vec![(Dependency::Storage, storage), (Dependency::Gis, gis)]
+}
+
 /// Health check for load balancing
+///  Services whose circuit breaker is open are reported unavailable
+///  until a probe succeeds, see [`crate::grpc::breaker`].
 #[utoipa::path(
     get,
-    path = "/health",
+    path = "/v1/health",
     tag = "svc-telemetry",
     responses(
         (status = 200, description = "Service is healthy, all dependencies running."),
@@ -17,33 +44,16 @@ use svc_storage_client_grpc::prelude::*;
     )
 )]
 pub async fn health_check(
-    Extension(grpc_clients): Extension<GrpcClients>,
+    Extension(Pipeline { grpc_clients, .. }): Extension<Pipeline>,
 ) -> Result<(), StatusCode> {
     rest_debug!("entry.");
 
     let mut ok = true;
-
-    if grpc_clients
-        .storage
-        .adsb
-        .is_ready(ReadyRequest {})
-        .await
-        .is_err()
-    {
-        let error_msg = "svc-storage adsb unavailable.".to_string();
-        rest_error!("{}.", &error_msg);
-        ok = false;
-    }
-
-    if grpc_clients
-        .gis
-        .is_ready(gis::ReadyRequest {})
-        .await
-        .is_err()
-    {
-        let error_msg = "svc-gis unavailable".to_string();
-        rest_error!("{}.", &error_msg);
-        ok = false;
+    for (_, result) in readiness(&grpc_clients).await {
+        if let Err(error_msg) = result {
+            rest_error!("{}.", &error_msg);
+            ok = false;
+        }
     }
 
     match ok {
@@ -64,10 +74,10 @@ mod tests {
 
     #[tokio::test]
     async fn test_health_check_success() {
-        // Mock the GrpcClients extension
+        // Mock the Pipeline extension
         let config = crate::config::Config::default();
-        let grpc_clients = GrpcClients::default(config);
-        let extension = Extension(grpc_clients);
+        let pipeline = super::super::test_pipeline(config).await;
+        let extension = Extension(pipeline);
 
         // Call the health_check function
         let result = health_check(extension).await;
@@ -0,0 +1,115 @@
+//! Vehicle health REST API
+//!  Vehicles of the fleet report their battery, GNSS fix and command link
+//!  state, published for operators on the `vehicle_health` queue.
+
+use super::jwt::Claim;
+use super::Pipeline;
+use crate::logging::context;
+use crate::msg::health::{HealthMessage, VehicleHealth};
+use crate::sink::{EventData, EventSource, TelemetryEvent};
+
+use axum::{body::Bytes, extract::Extension};
+use hyper::StatusCode;
+use lib_common::time::{DateTime, Utc};
+
+/// Decodes a health report of a vehicle, received at `now`
+fn decode_report(
+    identifier: &str,
+    payload: &[u8],
+    now: DateTime<Utc>,
+) -> Result<VehicleHealth, StatusCode> {
+    let message = HealthMessage::decode(payload).map_err(|e| {
+        rest_warn!("could not decode health report: {e}.");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    Ok(message.to_health(identifier, now))
+}
+
+/// Vehicle health report
+///  The vehicle is identified by the subject of its token.
+#[utoipa::path(
+    post,
+    path = "/v1/telemetry/health-report",
+    tag = "svc-telemetry",
+    request_body(
+        content = BinaryPacket,
+        description = "Vehicle health message (16 bytes).",
+        content_type = "application/octet-stream"
+    ),
+    responses(
+        (status = 200, description = "Health report published."),
+        (status = 400, description = "Malformed health message."),
+        (status = 401, description = "Missing or invalid JWT token.", body = ErrorResponse),
+        (status = 500, description = "Something went wrong."),
+        (status = 501, description = "Not served in ingest mode."),
+        (status = 503, description = "Dependencies of svc-telemetry were down."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP backend to test
+pub async fn health_report(
+    Extension(pipeline): Extension<Pipeline>,
+    Extension(claim): Extension<Claim>,
+    payload: Bytes,
+) -> Result<StatusCode, StatusCode> {
+    rest_info!("entry.");
+    context::set_aircraft(&claim.sub);
+    context::set_packet_type("vehicle:health");
+    pipeline.stats.record_packet("health", false);
+
+    let now = pipeline.clock.now();
+    let health = decode_report(&claim.sub, &payload, now)?;
+    let mut event = TelemetryEvent::new(
+        EventSource::Vehicle,
+        &claim.sub,
+        EventData::Health(health),
+        now,
+    );
+    if let Some(session) = claim.sid {
+        event = event.with_session(session);
+    }
+
+    if let Some(mission) = claim.mission {
+        event = event.with_mission(mission);
+    }
+
+    pipeline.sinks.push(&event).await?;
+    rest_debug!("pushed vehicle health to sinks.");
+
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::health::{GpsFix, HEALTH_MESSAGE_VERSION};
+    use packed_struct::PackedStruct;
+
+    #[test]
+    fn test_decode_report() {
+        let message = HealthMessage {
+            version: HEALTH_MESSAGE_VERSION,
+            battery_voltage_mv: 11_100,
+            battery_current_ca: 300,
+            battery_remaining_percent: 40,
+            gps_fix: GpsFix::Fix3d,
+            satellites_visible: 9,
+            hdop: 120,
+            link_rssi_dbm: -80,
+            link_quality_percent: 90,
+            timestamp: 0,
+        };
+
+        let payload = message.pack().unwrap();
+        let health = decode_report("drone-1", &payload, Utc::now()).unwrap();
+        assert_eq!(health.identifier, "drone-1");
+        assert_eq!(health.gps_fix, GpsFix::Fix3d);
+        assert_eq!(health.battery_remaining_percent, Some(40));
+
+        assert_eq!(
+            decode_report("drone-1", &payload[..8], Utc::now()),
+            Err(StatusCode::BAD_REQUEST)
+        );
+    }
+}
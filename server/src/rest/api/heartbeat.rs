@@ -0,0 +1,132 @@
+//! Heartbeat REST API
+//!  Aircraft with intermittent telemetry tell they're still online, so
+//!  their session and C2 link aren't considered lost between reports.
+
+use super::jwt::{login_response, Claim};
+use super::Pipeline;
+use crate::logging::context;
+use axum::{
+    extract::{Extension, Query},
+    response::{IntoResponse, Response},
+    Json,
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+
+/// Options of a heartbeat
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct HeartbeatQuery {
+    /// Return a token of the same session, expiring later
+    #[serde(default)]
+    pub renew: bool,
+}
+
+/// Tell that an aircraft is online
+///  The aircraft is identified by the subject of its token.
+///
+/// Records when the aircraft was last seen in its session, and postpones
+///  the loss of its C2 link. With `?renew=true`, a token of the same
+///  session expiring later is returned, if the token was issued by
+///  `/telemetry/login`.
+#[utoipa::path(
+    post,
+    path = "/v1/telemetry/heartbeat",
+    tag = "svc-telemetry",
+    params(
+        ("renew" = Option<bool>, Query, description = "Return a token of the same session, expiring later"),
+    ),
+    responses(
+        (status = 200, description = "Heartbeat recorded, renewed token returned.", body = LoginResponse),
+        (status = 204, description = "Heartbeat recorded."),
+        (status = 401, description = "Missing or invalid JWT token.", body = ErrorResponse),
+        (status = 403, description = "Renewal of a token of the identity provider."),
+        (status = 500, description = "Something went wrong."),
+        (status = 501, description = "Not served in ingest mode."),
+        (status = 503, description = "Dependencies of svc-telemetry were down."),
+    )
+)]
+pub async fn heartbeat(
+    Extension(pipeline): Extension<Pipeline>,
+    Extension(claim): Extension<Claim>,
+    Query(query): Query<HeartbeatQuery>,
+) -> Result<Response, StatusCode> {
+    rest_debug!("entry.");
+    context::set_aircraft(&claim.sub);
+
+    let now = pipeline.clock.now();
+    let claim = match query.renew {
+        true => claim.renew(now)?,
+        false => claim,
+    };
+
+    super::session::touch(&pipeline, &claim).await?;
+    pipeline
+        .c2_links
+        .lock()
+        .map_err(|e| {
+            rest_error!("could not lock C2 links: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .heartbeat(&claim.sub, now);
+
+    match query.renew {
+        true => {
+            rest_info!("token of {} renewed.", claim.sub);
+            Ok(Json(login_response(claim)?).into_response())
+        }
+        false => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rest::api::jwt::JWT_SECRET;
+    use lib_common::time::Utc;
+
+    #[tokio::test]
+    async fn test_heartbeat() {
+        let _ = JWT_SECRET.set("test".to_string());
+        let pipeline = super::super::test_pipeline(crate::Config::default()).await;
+        let claim = Claim::new("drone-1".to_string(), Utc::now()).unwrap();
+
+        let response = heartbeat(
+            Extension(pipeline.clone()),
+            Extension(claim.clone()),
+            Query(HeartbeatQuery::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = heartbeat(
+            Extension(pipeline.clone()),
+            Extension(claim.clone()),
+            Query(HeartbeatQuery { renew: true }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let renewed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let renewed = Claim::decode(renewed["token"].as_str().unwrap().to_string()).unwrap();
+        assert_eq!(renewed.sub, "drone-1");
+        assert_eq!(renewed.sid, claim.sid);
+        assert!(renewed.exp >= claim.exp);
+
+        // tokens of the identity provider aren't renewed by this service
+        let provided = Claim {
+            local: false,
+            ..claim
+        };
+        let error = heartbeat(
+            Extension(pipeline),
+            Extension(provided),
+            Query(HeartbeatQuery { renew: true }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,294 @@
+//! Identity conflicts of aircraft
+//!
+//! The last value claiming each identifier is cached per protocol. A
+//!  conflict is alerted at most once per window, marks the identifier as
+//!  contested in its state and is listed by the admin API for an hour.
+
+use super::Pipeline;
+use crate::amqp::envelope::TelemetryEnvelope;
+use crate::cache::pool::TelemetryPool;
+use crate::msg::identity::{detect, ConflictKind, IdentityClaim, IdentityConflict};
+use crate::stats::{Dependency, Stats};
+use axum::{extract::Extension, Json};
+use hyper::StatusCode;
+use lib_common::time::{DateTime, Duration, Utc};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Hash of the recent conflicts of a protocol, by identifier
+const CONFLICTS_KEY: &str = "identity:conflicts";
+
+/// Conflicts are listed for an hour after the last one
+const CACHE_EXPIRE_MS_CONFLICTS: u32 = 3_600_000;
+
+/// Hash field of the claiming value
+const FIELD_VALUE: &str = "value";
+
+/// Hash field of when the value was last seen (milliseconds)
+const FIELD_SEEN: &str = "seen";
+
+/// Hash field of when a conflict was last alerted (milliseconds)
+const FIELD_ALERTED: &str = "alerted";
+
+/// Pool of the protocol of a kind of conflict
+fn pool(pipeline: &Pipeline, kind: ConflictKind) -> TelemetryPool {
+    match kind {
+        ConflictKind::Subject => pipeline.tlm_pools.netrid.clone(),
+        ConflictKind::Callsign => pipeline.tlm_pools.adsb.clone(),
+    }
+}
+
+/// Time of a cached field in milliseconds
+fn cached_time(cached: &HashMap<String, String>, field: &str) -> Option<DateTime<Utc>> {
+    cached
+        .get(field)
+        .and_then(|millis| millis.parse::<i64>().ok())
+        .and_then(DateTime::from_timestamp_millis)
+}
+
+/// Last claim of an identifier in its cached hash
+fn cached_claim(cached: &HashMap<String, String>) -> Option<IdentityClaim> {
+    Some(IdentityClaim {
+        value: cached.get(FIELD_VALUE)?.clone(),
+        seen: cached_time(cached, FIELD_SEEN)?,
+    })
+}
+
+/// Publishes a conflict to the alert queue
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP backend to test
+async fn publish(mq_channel: &crate::amqp::MqChannel, stats: &Stats, conflict: &IdentityConflict) {
+    let Ok(msg) = serde_json::to_vec(&TelemetryEnvelope::new(conflict)) else {
+        rest_warn!("could not serialize identity conflict.");
+        return;
+    };
+
+    let _ = mq_channel
+        .basic_publish(
+            crate::amqp::EXCHANGE_NAME_TELEMETRY,
+            crate::amqp::ROUTING_KEY_ALERT,
+            lapin::options::BasicPublishOptions::default(),
+            &msg,
+            lapin::BasicProperties::default(),
+        )
+        .await
+        .map_err(|e| {
+            rest_warn!("could not push identity conflict to RabbitMQ: {e}.");
+            stats.record_error(Dependency::Amqp);
+        });
+}
+
+/// Records a conflict: alerted, marked in the state of the aircraft and
+///  listed by the admin API
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis and AMQP backends to test
+async fn report(
+    tlm_pool: &mut TelemetryPool,
+    mq_channel: &crate::amqp::MqChannel,
+    stats: &Stats,
+    conflict: &IdentityConflict,
+    now: DateTime<Utc>,
+) {
+    rest_warn!(
+        "identity conflict on {}: {} {} claimed by {} too.",
+        conflict.identifier,
+        conflict.kind.name(),
+        conflict.previous,
+        conflict.current
+    );
+
+    publish(mq_channel, stats, conflict).await;
+
+    if let Err(e) = super::state::mark_contested(tlm_pool, &conflict.identifier, now).await {
+        rest_warn!("could not mark {} as contested: {e}", conflict.identifier);
+        stats.record_error(Dependency::Redis);
+    }
+
+    let Ok(value) = serde_json::to_string(conflict) else {
+        rest_warn!("could not serialize identity conflict.");
+        return;
+    };
+
+    if let Err(e) = tlm_pool
+        .hash_set(
+            CONFLICTS_KEY,
+            &conflict.identifier,
+            &value,
+            CACHE_EXPIRE_MS_CONFLICTS,
+        )
+        .await
+    {
+        rest_warn!("could not cache identity conflict: {e}");
+        stats.record_error(Dependency::Redis);
+    }
+}
+
+/// Checks the value claiming an identifier against its last claim,
+///  reporting a conflict. Skipped if the window is 0.
+///
+/// `tlm_pool` is the pool of the protocol of the kind of conflict.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis and AMQP backends to test
+pub(crate) async fn observe(
+    tlm_pool: &mut TelemetryPool,
+    mq_channel: &crate::amqp::MqChannel,
+    stats: &Stats,
+    window_ms: u32,
+    kind: ConflictKind,
+    identifier: &str,
+    value: &str,
+    now: DateTime<Utc>,
+) {
+    if window_ms == 0 {
+        return;
+    }
+
+    let key = format!("{identifier}:claim:{}", kind.name());
+    let cached = match tlm_pool.hash_get_all(&key).await {
+        Ok(cached) => cached,
+        Err(e) => {
+            rest_warn!("could not get claim of {identifier}: {e}");
+            stats.record_error(Dependency::Redis);
+            return;
+        }
+    };
+
+    let window = Duration::try_milliseconds(window_ms as i64).unwrap_or(Duration::zero());
+    let conflict = detect(
+        identifier,
+        kind,
+        cached_claim(&cached).as_ref(),
+        value,
+        now,
+        window,
+    );
+
+    let millis = now.timestamp_millis().to_string();
+    let mut fields = vec![
+        (FIELD_VALUE.to_string(), value.to_string()),
+        (FIELD_SEEN.to_string(), millis.clone()),
+    ];
+
+    // alternating values are alerted once per window
+    let alerted =
+        cached_time(&cached, FIELD_ALERTED).is_some_and(|alerted| now - alerted <= window);
+    let conflict = conflict.filter(|_| !alerted);
+    if conflict.is_some() {
+        fields.push((FIELD_ALERTED.to_string(), millis));
+    }
+
+    if let Err(e) = tlm_pool.hash_set_multiple(&key, &fields, window_ms).await {
+        rest_warn!("could not cache claim of {identifier}: {e}");
+        stats.record_error(Dependency::Redis);
+    }
+
+    if let Some(conflict) = conflict {
+        report(tlm_pool, mq_channel, stats, &conflict, now).await;
+    }
+}
+
+/// Recent conflicts of the cached hash, newest first
+fn recent(
+    cached: HashMap<String, String>,
+    now: DateTime<Utc>,
+    conflicts: &mut Vec<IdentityConflict>,
+) {
+    let oldest = now - Duration::milliseconds(CACHE_EXPIRE_MS_CONFLICTS as i64);
+    conflicts.extend(
+        cached
+            .values()
+            .filter_map(|value| serde_json::from_str::<IdentityConflict>(value).ok())
+            .filter(|conflict| conflict.timestamp_network >= oldest),
+    );
+
+    conflicts.sort_by_key(|conflict| Reverse(conflict.timestamp_network));
+}
+
+/// Identity conflicts detected by all instances within the last hour
+#[utoipa::path(
+    get,
+    path = "/v1/admin/identity-conflicts",
+    tag = "svc-telemetry",
+    responses(
+        (status = 200, description = "Last conflict of each contested identifier, newest first.", body = [IdentityConflict]),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn identity_conflicts(
+    Extension(pipeline): Extension<Pipeline>,
+) -> Result<Json<Vec<IdentityConflict>>, StatusCode> {
+    rest_debug!("entry.");
+    let now = pipeline.clock.now();
+    let mut conflicts = vec![];
+    for kind in [ConflictKind::Subject, ConflictKind::Callsign] {
+        let cached = pool(&pipeline, kind)
+            .hash_get_all(CONFLICTS_KEY)
+            .await
+            .map_err(|e| {
+                rest_error!("could not get identity conflicts: {e}");
+                pipeline.stats.record_error(Dependency::Redis);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+        recent(cached, now, &mut conflicts);
+    }
+
+    Ok(Json(conflicts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conflict(identifier: &str, timestamp_network: DateTime<Utc>) -> IdentityConflict {
+        IdentityConflict {
+            identifier: identifier.to_string(),
+            kind: ConflictKind::Callsign,
+            previous: "KLM1023".to_string(),
+            current: "TRA6011".to_string(),
+            previous_seen: timestamp_network,
+            timestamp_network,
+        }
+    }
+
+    #[test]
+    fn test_cached_claim() {
+        let now = Utc::now();
+        let mut cached = HashMap::new();
+        assert_eq!(cached_claim(&cached), None);
+
+        cached.insert(FIELD_VALUE.to_string(), "drone-1".to_string());
+        cached.insert(FIELD_SEEN.to_string(), now.timestamp_millis().to_string());
+        let claim = cached_claim(&cached).unwrap();
+        assert_eq!(claim.value, "drone-1");
+        assert_eq!(claim.seen.timestamp_millis(), now.timestamp_millis());
+    }
+
+    #[test]
+    fn test_recent() {
+        let now = Utc::now();
+        let cached = HashMap::from([
+            (
+                "4840d6".to_string(),
+                serde_json::to_string(&conflict("4840d6", now - Duration::minutes(5))).unwrap(),
+            ),
+            (
+                "a1b2c3".to_string(),
+                serde_json::to_string(&conflict("a1b2c3", now)).unwrap(),
+            ),
+            (
+                "3c6444".to_string(),
+                serde_json::to_string(&conflict("3c6444", now - Duration::hours(2))).unwrap(),
+            ),
+            ("bad".to_string(), "{".to_string()),
+        ]);
+
+        let mut conflicts = vec![];
+        recent(cached, now, &mut conflicts);
+        let identifiers: Vec<&str> = conflicts.iter().map(|c| c.identifier.as_str()).collect();
+        assert_eq!(identifiers, vec!["a1b2c3", "4840d6"]);
+    }
+}
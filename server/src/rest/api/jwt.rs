@@ -13,20 +13,41 @@
 //!  may be a PKI certificate that our network (as a certificate authority)
 //!  issues to the device
 
+use super::Pipeline;
+use crate::msg::identifier::IdentifierKind;
+use crate::msg::mission::parse_mission_id;
+use crate::Config;
 use axum::{
     body::Bytes,
-    http::{header, StatusCode},
+    extract::Extension,
+    http::{header, HeaderMap, StatusCode},
     middleware::Next,
     response::Response,
     Json,
 };
 use hyper::Request;
-use lib_common::time::{Duration, Utc};
+use lib_common::time::{DateTime, Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
 use serde::{Deserialize, Serialize};
+use std::sync::{OnceLock, RwLock};
 use tokio::sync::OnceCell;
+use utoipa::ToSchema;
 
 use axum_extra::extract::cookie::CookieJar;
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+
+/// Length of the generated session identifiers
+const SESSION_ID_LENGTH: usize = 16;
+
+/// Login request header declaring the flight plan or mission flown, if
+///  not in the body
+pub const HEADER_MISSION_ID: &str = "x-mission-id";
+
+/// Most identifiers logged in by a bulk login
+const MAX_BULK_LOGIN_IDENTIFIERS: usize = 500;
 
 /// JWT Encryption Type
 const JWT_ENCRYPTION_TYPE: Algorithm = Algorithm::HS256;
@@ -35,16 +56,21 @@ const JWT_ENCRYPTION_TYPE: Algorithm = Algorithm::HS256;
 // TODO(R5): This is a temporary solution, replace with PKI certificates
 pub static JWT_SECRET: OnceCell<String> = OnceCell::const_new();
 
+/// Validation of the tokens, set at startup
+pub static JWT_SETTINGS: OnceLock<JwtSettings> = OnceLock::new();
+
 /// JWT Expiration time in seconds
-const JWT_EXPIRE_SECONDS: i64 = 360; // TODO(R5): To configuration file
+pub(crate) const JWT_EXPIRE_SECONDS: i64 = 360; // TODO(R5): To configuration file
 
 /// Error Response
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ErrorResponse {
     /// Status
+    #[schema(example = "fail")]
     status: String,
 
     /// Message
+    #[schema(example = "Invalid token")]
     message: String,
 }
 
@@ -59,13 +85,322 @@ pub struct Claim {
 
     /// Expiration time in seconds
     pub exp: usize,
+
+    /// Login session the token was issued for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+
+    /// Flight plan or mission flown during the session (UUID)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mission: Option<String>,
+
+    /// Issuer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+
+    /// Audience
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Audience>,
+
+    /// If the token was issued by this service rather than the identity
+    ///  provider, see [`Claim::renew`]
+    #[serde(skip)]
+    pub local: bool,
+}
+
+/// Audience of a token, a single recipient or several
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Audience {
+    /// Single recipient
+    One(String),
+
+    /// Several recipients
+    Many(Vec<String>),
+}
+
+/// Validation of the tokens presented to the service
+///
+/// Tokens without a key ID are verified with [`JWT_SECRET`], as those
+///  issued by `/telemetry/login`, and must be signed with
+///  [`JWT_ENCRYPTION_TYPE`]. Tokens with a key ID were issued by the
+///  identity provider and are verified with the key of its JSON Web Key Set,
+///  which must be meant for the algorithm the token is signed with.
+#[derive(Debug)]
+pub struct JwtSettings {
+    /// Issuer of the tokens issued, and required of the tokens presented
+    issuer: Option<String>,
+
+    /// Audience of the tokens issued, and required of the tokens presented
+    audience: Option<String>,
+
+    /// Algorithms the tokens of the identity provider may be signed with
+    algorithms: Vec<Algorithm>,
+
+    /// Keys of the identity provider
+    jwks: RwLock<JwkSet>,
+}
+
+impl Default for JwtSettings {
+    fn default() -> Self {
+        JwtSettings {
+            issuer: None,
+            audience: None,
+            algorithms: vec![JWT_ENCRYPTION_TYPE],
+            jwks: RwLock::new(JwkSet { keys: vec![] }),
+        }
+    }
+}
+
+impl JwtSettings {
+    /// Entries of a comma separated list of algorithms
+    pub fn entries(list: &str) -> impl Iterator<Item = &str> {
+        list.split(',').filter(|entry| !entry.trim().is_empty())
+    }
+
+    /// Settings of the configuration, invalid algorithms being ignored
+    pub fn new(config: &Config) -> Self {
+        let setting = |value: &str| match value.trim() {
+            "" => None,
+            value => Some(value.to_string()),
+        };
+
+        JwtSettings {
+            issuer: setting(&config.jwt_issuer),
+            audience: setting(&config.jwt_audience),
+            algorithms: Self::entries(&config.jwt_algorithms)
+                .filter_map(|entry| entry.trim().parse().ok())
+                .collect(),
+            jwks: RwLock::new(JwkSet { keys: vec![] }),
+        }
+    }
+
+    /// Settings set at startup, or the defaults
+    pub fn current() -> &'static JwtSettings {
+        JWT_SETTINGS.get_or_init(JwtSettings::default)
+    }
+
+    /// Replace the keys of the identity provider
+    pub fn set_jwks(&self, jwks: JwkSet) {
+        match self.jwks.write() {
+            Ok(mut keys) => *keys = jwks,
+            Err(e) => rest_error!("could not replace JWKS: {e}"),
+        }
+    }
+
+    /// Key verifying a token, if meant for the algorithm of the token
+    fn decoding_key(&self, header: &Header, secret: &str) -> Result<DecodingKey, StatusCode> {
+        let Some(kid) = &header.kid else {
+            if header.alg != JWT_ENCRYPTION_TYPE {
+                rest_warn!("JWT algorithm {:?} not issued by this service.", header.alg);
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+
+            return Ok(DecodingKey::from_secret(secret.as_bytes()));
+        };
+
+        if !self.algorithms.contains(&header.alg) {
+            rest_warn!("JWT algorithm {:?} not allowed.", header.alg);
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+
+        let jwks = self.jwks.read().map_err(|e| {
+            rest_error!("could not read JWKS: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let jwk = jwks.find(kid).ok_or_else(|| {
+            rest_warn!("unknown JWT key ID {kid}.");
+            StatusCode::UNAUTHORIZED
+        })?;
+
+        if !key_algorithm_matches(jwk, header.alg) {
+            rest_warn!("JWK {kid} not meant for algorithm {:?}.", header.alg);
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+
+        DecodingKey::from_jwk(jwk).map_err(|e| {
+            rest_warn!("invalid JWK {kid}: {e}");
+            StatusCode::UNAUTHORIZED
+        })
+    }
+
+    /// Decode a token, checking its algorithm, signature, issuer and
+    ///  audience
+    pub fn decode(&self, token: &str, secret: &str) -> Result<Claim, StatusCode> {
+        let header = decode_header(token).map_err(|e| {
+            rest_error!("could not decode JWT header: {e}");
+            StatusCode::UNAUTHORIZED
+        })?;
+
+        let key = self.decoding_key(&header, secret)?;
+        let mut validation = Validation::new(header.alg);
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+            validation.required_spec_claims.insert("iss".to_string());
+        }
+
+        match &self.audience {
+            Some(audience) => {
+                validation.set_audience(&[audience]);
+                validation.required_spec_claims.insert("aud".to_string());
+            }
+            None => validation.validate_aud = false,
+        }
+
+        // tokens of the identity provider are the ones with a key ID
+        decode(token, &key, &validation)
+            .map(|data| Claim {
+                local: header.kid.is_none(),
+                ..data.claims
+            })
+            .map_err(|e| {
+                rest_error!("could not decode JWT: {e}");
+                StatusCode::UNAUTHORIZED
+            })
+    }
+}
+
+/// If a key of the identity provider declares the algorithm, keys without
+///  one being refused
+fn key_algorithm_matches(jwk: &Jwk, alg: Algorithm) -> bool {
+    let Some(key_algorithm) = &jwk.common.key_algorithm else {
+        return false;
+    };
+
+    match (
+        serde_json::to_value(key_algorithm),
+        serde_json::to_value(alg),
+    ) {
+        (Ok(key_algorithm), Ok(alg)) => key_algorithm == alg,
+        _ => false,
+    }
+}
+
+/// Fetches the JSON Web Key Set of the identity provider at an interval,
+///  over HTTPS only
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need an identity provider to test
+pub async fn jwks_loop(url: String, interval_ms: u32) {
+    let https = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_webpki_roots()
+        .https_only()
+        .enable_http1()
+        .build();
+    let client = hyper::Client::builder().build::<_, hyper::Body>(https);
+    let interval = std::time::Duration::from_millis(interval_ms.max(1000).into());
+    loop {
+        let jwks = async {
+            let uri = url.parse::<hyper::Uri>().map_err(|e| e.to_string())?;
+            let response = client.get(uri).await.map_err(|e| e.to_string())?;
+            if !response.status().is_success() {
+                return Err(format!("replied {}", response.status()));
+            }
+
+            let body = hyper::body::to_bytes(response.into_body())
+                .await
+                .map_err(|e| e.to_string())?;
+
+            serde_json::from_slice::<JwkSet>(&body).map_err(|e| e.to_string())
+        };
+
+        match jwks.await {
+            Ok(jwks) => {
+                rest_debug!("fetched {} JWKS keys.", jwks.keys.len());
+                JwtSettings::current().set_jwks(jwks);
+            }
+            Err(e) => rest_warn!("could not fetch JWKS from {url}: {e}"),
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Login request
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct LoginRequest {
+    /// Identifier of the aircraft
+    #[schema(example = "drone-1")]
+    pub identifier: String,
+
+    /// Flight plan or mission flown during the session (UUID), attached
+    ///  to its telemetry
+    #[serde(default)]
+    #[schema(example = "7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5e")]
+    pub mission_id: Option<String>,
+}
+
+/// Login response
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LoginResponse {
+    /// JWT to provide as `Bearer` token
+    pub token: String,
+
+    /// When the token expires
+    pub expires_at: DateTime<Utc>,
+
+    /// Login session the token was issued for
+    pub session_id: String,
+}
+
+/// Bulk login request of a fleet gateway
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct BulkLoginRequest {
+    /// Identifiers of the aircraft managed by the gateway
+    #[schema(example = json!(["drone-1", "drone-2"]))]
+    pub identifiers: Vec<String>,
+}
+
+/// Login of one identifier of a bulk login
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BulkLoginResult {
+    /// Identifier of the aircraft
+    pub identifier: String,
+
+    /// Status the login of the identifier alone would have returned
+    #[schema(example = 200)]
+    pub status: u16,
+
+    /// Token of the identifier, if its login succeeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub login: Option<LoginResponse>,
+}
+
+/// Bulk login response
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BulkLoginResponse {
+    /// Login of each identifier, in request order
+    pub results: Vec<BulkLoginResult>,
+}
+
+/// If two secrets are equal, comparing all their bytes so the time taken
+///  doesn't tell how much of a guess was right
+pub(crate) fn secrets_match(expected: &str, provided: &str) -> bool {
+    expected.len() == provided.len()
+        && expected
+            .bytes()
+            .zip(provided.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Gateway of the `gateway=secret` entries holding the provided secret
+fn gateway<'a>(credentials: &'a str, secret: &str) -> Option<&'a str> {
+    credentials
+        .split(',')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(gateway, expected)| (gateway.trim(), expected.trim()))
+        .filter(|(_, expected)| !expected.is_empty())
+        .fold(None, |found, (gateway, expected)| {
+            found.or(secrets_match(expected, secret).then_some(gateway))
+        })
 }
 
 impl Claim {
-    /// Create and encode a JWT token
-    pub fn create(sub: String) -> Result<String, StatusCode> {
-        let header = Header::new(JWT_ENCRYPTION_TYPE);
-        let iat = Utc::now().timestamp();
+    /// Create the claim of a new login session of the subject, issued at
+    ///  `now`
+    pub fn new(sub: String, now: DateTime<Utc>) -> Result<Claim, StatusCode> {
+        let iat = now.timestamp();
         let iat = <usize>::try_from(iat).map_err(|e| {
             rest_error!("could not convert IAT timestamp {iat} to usize: {e}");
             StatusCode::INTERNAL_SERVER_ERROR
@@ -79,26 +414,71 @@ impl Claim {
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-        let exp = (Utc::now() + delta).timestamp();
+        let exp = (now + delta).timestamp();
         let exp = <usize>::try_from(exp).map_err(|e| {
             rest_error!("could not convert EXP timestamp {exp} to usize: {e}");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-        let claims = Claim { sub, iat, exp };
+        let sid = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(SESSION_ID_LENGTH)
+            .map(char::from)
+            .collect();
 
+        let settings = JwtSettings::current();
+        Ok(Claim {
+            sub,
+            iat,
+            exp,
+            sid: Some(sid),
+            mission: None,
+            iss: settings.issuer.clone(),
+            aud: settings.audience.clone().map(Audience::One),
+            local: true,
+        })
+    }
+
+    /// Claim of the same session, expiring later
+    ///
+    /// Only tokens issued by this service are renewed: tokens of the
+    ///  identity provider are renewed with it.
+    pub fn renew(&self, now: DateTime<Utc>) -> Result<Claim, StatusCode> {
+        if !self.local {
+            rest_warn!(
+                "token of {} not issued by this service, not renewed.",
+                self.sub
+            );
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        Ok(Claim {
+            sid: self.sid.clone(),
+            mission: self.mission.clone(),
+            ..Claim::new(self.sub.clone(), now)?
+        })
+    }
+
+    /// Encode the claim as a JWT token
+    pub fn encode(&self) -> Result<String, StatusCode> {
+        let header = Header::new(JWT_ENCRYPTION_TYPE);
         let jwt_secret = JWT_SECRET.get().ok_or_else(|| {
             rest_error!("JWT_SECRET not set.");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
         let key = EncodingKey::from_secret(jwt_secret.as_bytes());
-        encode(&header, &claims, &key).map_err(|e| {
+        encode(&header, self, &key).map_err(|e| {
             rest_error!("could not encode JWT: {e}");
             StatusCode::INTERNAL_SERVER_ERROR
         })
     }
 
+    /// Create and encode a JWT token
+    pub fn create(sub: String, now: DateTime<Utc>) -> Result<String, StatusCode> {
+        Claim::new(sub, now)?.encode()
+    }
+
     /// Decode a JWT token
     pub fn decode(token: String) -> Result<Claim, StatusCode> {
         let jwt_secret = JWT_SECRET.get().ok_or_else(|| {
@@ -106,13 +486,7 @@ impl Claim {
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-        let key = DecodingKey::from_secret(jwt_secret.as_bytes());
-        decode(&token, &key, &Validation::default())
-            .map(|data| data.claims)
-            .map_err(|e| {
-                rest_error!("could not decode JWT: {e}");
-                StatusCode::UNAUTHORIZED
-            })
+        JwtSettings::current().decode(&token, jwt_secret)
     }
 }
 
@@ -196,34 +570,195 @@ where
     Ok(next.run(req).await)
 }
 
-/// Remote ID Login
+/// Remote ID Login (deprecated)
+///
+/// Takes the identifier as raw body, which some clients and proxies don't
+///  send with GET requests. Replaced by [`login_json`], only served if
+///  `REST_LEGACY_LOGIN_ENABLED`.
 #[utoipa::path(
     get,
-    path = "/telemetry/login",
+    path = "/v1/telemetry/login",
     tag = "svc-telemetry",
-    request_body = String, // identifier TODO(R5)
+    request_body(
+        content = String,
+        description = "Identifier of the aircraft.", // TODO(R5)
+        content_type = "text/plain"
+    ),
     responses(
-        (status = 200, description = "Login successful, token returned."),
+        (status = 200, description = "Deprecated, use POST. Login successful, token returned.", body = String),
         (status = 400, description = "Bad request."),
+        (status = 409, description = "The identifier has an active session, if `SESSION_POLICY` is `reject`."),
         (status = 500, description = "Something went wrong."),
         (status = 503, description = "Dependencies of svc-telemetry were down."),
     )
 )]
-pub async fn login(identifier: Bytes) -> Result<Json<String>, StatusCode> {
+pub async fn login(
+    Extension(pipeline): Extension<Pipeline>,
+    identifier: Bytes,
+) -> Result<Json<String>, StatusCode> {
     let identifier = String::from_utf8(identifier.to_vec()).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let identifier = pipeline
+        .identifiers
+        .normalize(IdentifierKind::RemoteId, &identifier);
+    if identifier.is_empty() {
+        rest_warn!("empty identifier, failing login request.");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let claim = Claim::new(identifier, pipeline.clock.now())?;
+    super::session::open(&pipeline, &claim).await?;
+    Ok(Json(claim.encode()?))
+}
+
+/// Remote ID Login
+#[utoipa::path(
+    post,
+    path = "/v1/telemetry/login",
+    tag = "svc-telemetry",
+    request_body = LoginRequest,
+    params(
+        ("X-Mission-Id" = Option<String>, Header, description = "Flight plan or mission flown during the session (UUID), if not in the body"),
+    ),
+    responses(
+        (status = 200, description = "Login successful, token returned.", body = LoginResponse),
+        (status = 400, description = "Bad request, or the mission ID isn't a UUID."),
+        (status = 409, description = "The identifier has an active session, if `SESSION_POLICY` is `reject`."),
+        (status = 422, description = "Malformed JSON body."),
+        (status = 500, description = "Something went wrong."),
+        (status = 503, description = "Dependencies of svc-telemetry were down."),
+    )
+)]
+pub async fn login_json(
+    Extension(pipeline): Extension<Pipeline>,
+    headers: HeaderMap,
+    Json(request): Json<LoginRequest>,
+) -> Result<Json<LoginResponse>, StatusCode> {
+    let mission = mission_id(request.mission_id.as_deref(), &headers)?;
+    open_login(&pipeline, &request.identifier, mission)
+        .await
+        .map(Json)
+}
+
+/// Mission declared by a login, in its body or else its header
+fn mission_id(body: Option<&str>, headers: &HeaderMap) -> Result<Option<String>, StatusCode> {
+    let header = headers
+        .get(HEADER_MISSION_ID)
+        .map(|value| value.to_str().unwrap_or_default());
+
+    let Some(value) = body.or(header) else {
+        return Ok(None);
+    };
+
+    parse_mission_id(value).map(Some).ok_or_else(|| {
+        rest_warn!("invalid mission ID, failing login request.");
+        StatusCode::BAD_REQUEST
+    })
+}
+
+/// Opens a login session of an identifier, returning its token
+async fn open_login(
+    pipeline: &Pipeline,
+    identifier: &str,
+    mission: Option<String>,
+) -> Result<LoginResponse, StatusCode> {
+    let identifier = pipeline
+        .identifiers
+        .normalize(IdentifierKind::RemoteId, identifier);
     if identifier.is_empty() {
         rest_warn!("empty identifier, failing login request.");
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    let token = Claim::create(identifier)?;
-    Ok(Json(token))
+    let claim = Claim {
+        mission,
+        ..Claim::new(identifier, pipeline.clock.now())?
+    };
+    super::session::open(pipeline, &claim).await?;
+    rest_info!("login of {}.", claim.sub);
+    login_response(claim)
+}
+
+/// Token of a claim, with its expiration and session
+pub(crate) fn login_response(claim: Claim) -> Result<LoginResponse, StatusCode> {
+    let token = claim.encode()?;
+    let expires_at = i64::try_from(claim.exp)
+        .ok()
+        .and_then(|exp| DateTime::from_timestamp(exp, 0))
+        .ok_or_else(|| {
+            rest_error!("could not convert EXP timestamp {} to a date.", claim.exp);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(LoginResponse {
+        token,
+        expires_at,
+        session_id: claim.sid.unwrap_or_default(),
+    })
+}
+
+/// Bulk login of a fleet gateway
+///
+/// Logs in each identifier as `POST /telemetry/login` would, reporting
+///  the failures per identifier.
+#[utoipa::path(
+    post,
+    path = "/v1/telemetry/login/bulk",
+    tag = "svc-telemetry",
+    request_body = BulkLoginRequest,
+    responses(
+        (status = 200, description = "Login of each identifier, successful or not.", body = BulkLoginResponse),
+        (status = 400, description = "No identifiers, or more than 500."),
+        (status = 401, description = "Missing or unknown gateway credential."),
+        (status = 422, description = "Malformed JSON body."),
+    )
+)]
+pub async fn login_bulk(
+    Extension(pipeline): Extension<Pipeline>,
+    headers: HeaderMap,
+    Json(request): Json<BulkLoginRequest>,
+) -> Result<Json<BulkLoginResponse>, StatusCode> {
+    let secret = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| {
+            rest_warn!("bulk login without gateway credential.");
+            StatusCode::UNAUTHORIZED
+        })?;
+
+    let gateway = gateway(&pipeline.config.gateway_credentials, secret).ok_or_else(|| {
+        rest_warn!("bulk login with unknown gateway credential.");
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    let count = request.identifiers.len();
+    if count == 0 || count > MAX_BULK_LOGIN_IDENTIFIERS {
+        rest_warn!("bulk login of {count} identifiers by {gateway} refused.");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    rest_info!("bulk login of {count} identifiers by {gateway}.");
+    let mut results = Vec::with_capacity(count);
+    for identifier in request.identifiers {
+        let (status, login) = match open_login(&pipeline, &identifier, None).await {
+            Ok(login) => (StatusCode::OK, Some(login)),
+            Err(status) => (status, None),
+        };
+
+        results.push(BulkLoginResult {
+            identifier,
+            status: status.as_u16(),
+            login,
+        });
+    }
+
+    Ok(Json(BulkLoginResponse { results }))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use axum::{middleware, routing::post, Extension, Router};
+    use axum::{middleware, routing::post, Router};
     use hyper::{Method, Request};
     use tower::ServiceExt;
 
@@ -235,13 +770,14 @@ mod tests {
             serde_json::to_string(&claim).unwrap();
         }
 
-        JWT_SECRET.set("test".to_string()).unwrap();
+        // may already be set by another test
+        let _ = JWT_SECRET.set("test".to_string());
 
         let router: Router = Router::new()
             .route("/", post(handler))
             .route_layer(middleware::from_fn(auth));
 
-        let token = Claim::create("test".to_string()).unwrap();
+        let token = Claim::create("test".to_string(), Utc::now()).unwrap();
         let req = Request::builder()
             .uri("/")
             .method(Method::POST)
@@ -252,4 +788,278 @@ mod tests {
 
         router.oneshot(req).await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_login_json() {
+        let _ = JWT_SECRET.set("test".to_string());
+        let pipeline = crate::rest::api::test_pipeline(Config::default()).await;
+
+        let request = LoginRequest {
+            identifier: " ".to_string(),
+            mission_id: None,
+        };
+        let error = login_json(Extension(pipeline.clone()), HeaderMap::new(), Json(request))
+            .await
+            .unwrap_err();
+        assert_eq!(error, StatusCode::BAD_REQUEST);
+
+        let request = LoginRequest {
+            identifier: "drone-1".to_string(),
+            mission_id: None,
+        };
+        let Json(response) =
+            login_json(Extension(pipeline.clone()), HeaderMap::new(), Json(request))
+                .await
+                .unwrap();
+        assert_eq!(response.session_id.len(), SESSION_ID_LENGTH);
+        assert!(response.expires_at > Utc::now());
+
+        let claim = Claim::decode(response.token).unwrap();
+        assert_eq!(claim.sub, "drone-1");
+        assert_eq!(claim.sid, Some(response.session_id));
+        assert_eq!(claim.mission, None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HEADER_MISSION_ID,
+            "7B0C3E4A-1F2D-4C5B-9A8E-0D1C2B3A4F5E".parse().unwrap(),
+        );
+        let request = LoginRequest {
+            identifier: "drone-1".to_string(),
+            mission_id: None,
+        };
+        let Json(response) = login_json(Extension(pipeline.clone()), headers, Json(request))
+            .await
+            .unwrap();
+        let claim = Claim::decode(response.token).unwrap();
+        assert_eq!(
+            claim.mission,
+            Some("7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5e".to_string())
+        );
+        assert_eq!(claim.renew(Utc::now()).unwrap().mission, claim.mission);
+    }
+
+    #[test]
+    fn test_mission_id() {
+        let mission = "7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5e";
+        let mut headers = HeaderMap::new();
+        assert_eq!(mission_id(None, &headers), Ok(None));
+        assert_eq!(
+            mission_id(Some(mission), &headers),
+            Ok(Some(mission.to_string()))
+        );
+        assert_eq!(
+            mission_id(Some("mission-1"), &headers),
+            Err(StatusCode::BAD_REQUEST)
+        );
+
+        // the body wins over the header
+        headers.insert(HEADER_MISSION_ID, "mission-1".parse().unwrap());
+        assert_eq!(mission_id(None, &headers), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(
+            mission_id(Some(mission), &headers),
+            Ok(Some(mission.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_jwt_settings() {
+        let config = Config {
+            jwt_issuer: "https://id.example.com".to_string(),
+            jwt_audience: "svc-telemetry".to_string(),
+            jwt_algorithms: "HS256, HS512, none".to_string(),
+            ..Default::default()
+        };
+        let settings = JwtSettings::new(&config);
+        assert_eq!(
+            settings.algorithms,
+            vec![Algorithm::HS256, Algorithm::HS512]
+        );
+
+        let token = |header: Header, secret: &[u8], claims: serde_json::Value| {
+            encode(&header, &claims, &EncodingKey::from_secret(secret)).unwrap()
+        };
+        let exp = Utc::now().timestamp() + 60;
+        let claims = serde_json::json!({
+            "sub": "drone-1", "iat": 0, "exp": exp,
+            "iss": "https://id.example.com", "aud": ["svc-telemetry", "svc-gis"]
+        });
+
+        let claim = settings
+            .decode(&token(Header::default(), b"test", claims.clone()), "test")
+            .unwrap();
+        assert_eq!(claim.sub, "drone-1");
+        assert_eq!(claim.iss.as_deref(), Some("https://id.example.com"));
+        assert_eq!(
+            claim.aud,
+            Some(Audience::Many(vec![
+                "svc-telemetry".to_string(),
+                "svc-gis".to_string()
+            ]))
+        );
+
+        // wrong secret, algorithm not allowed
+        let valid = token(Header::default(), b"test", claims.clone());
+        assert_eq!(
+            settings.decode(&valid, "other").unwrap_err(),
+            StatusCode::UNAUTHORIZED
+        );
+        let header = Header::new(Algorithm::HS384);
+        let hs384 = token(header, b"test", claims.clone());
+        assert_eq!(
+            settings.decode(&hs384, "test").unwrap_err(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // allowed for the identity provider, but not issued by this service
+        let hs512 = token(Header::new(Algorithm::HS512), b"test", claims.clone());
+        assert_eq!(
+            settings.decode(&hs512, "test").unwrap_err(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // other issuer or audience, or none
+        for (field, value) in [
+            ("iss", serde_json::json!("https://other.example.com")),
+            ("aud", serde_json::json!("svc-gis")),
+        ] {
+            let mut claims = claims.clone();
+            claims[field] = value;
+            let invalid = token(Header::default(), b"test", claims.clone());
+            assert_eq!(
+                settings.decode(&invalid, "test").unwrap_err(),
+                StatusCode::UNAUTHORIZED
+            );
+
+            claims.as_object_mut().unwrap().remove(field);
+            let invalid = token(Header::default(), b"test", claims);
+            assert_eq!(
+                settings.decode(&invalid, "test").unwrap_err(),
+                StatusCode::UNAUTHORIZED
+            );
+        }
+
+        // tokens of the identity provider are verified with its key
+        let header = Header {
+            kid: Some("key-1".to_string()),
+            ..Default::default()
+        };
+        let provided = token(header, b"idp-secret", claims.clone());
+        assert_eq!(
+            settings.decode(&provided, "test").unwrap_err(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let jwks = serde_json::json!({
+            "keys": [{"kty": "oct", "kid": "key-1", "k": "aWRwLXNlY3JldA", "alg": "HS256"}]
+        });
+        settings.set_jwks(serde_json::from_value(jwks).unwrap());
+        let claim = settings.decode(&provided, "test").unwrap();
+        assert_eq!(claim.sub, "drone-1");
+
+        // keys are bound to the algorithm they declare
+        for key in [
+            serde_json::json!({"kty": "oct", "kid": "key-1", "k": "aWRwLXNlY3JldA", "alg": "HS512"}),
+            serde_json::json!({"kty": "oct", "kid": "key-1", "k": "aWRwLXNlY3JldA"}),
+        ] {
+            let jwks = serde_json::json!({ "keys": [key] });
+            settings.set_jwks(serde_json::from_value(jwks).unwrap());
+            assert_eq!(
+                settings.decode(&provided, "test").unwrap_err(),
+                StatusCode::UNAUTHORIZED
+            );
+        }
+
+        let jwks = serde_json::json!({
+            "keys": [{"kty": "oct", "kid": "key-1", "k": "aWRwLXNlY3JldA", "alg": "HS256"}]
+        });
+        settings.set_jwks(serde_json::from_value(jwks).unwrap());
+
+        // holding the configured issuer, but renewed by the identity provider only
+        assert!(!claim.local);
+        assert_eq!(claim.renew(Utc::now()).unwrap_err(), StatusCode::FORBIDDEN);
+        assert!(settings.decode(&valid, "test").unwrap().local);
+
+        // without issuer or audience, neither is required
+        let settings = JwtSettings::default();
+        let mut claims = claims;
+        claims.as_object_mut().unwrap().remove("iss");
+        claims.as_object_mut().unwrap().remove("aud");
+        let token = token(Header::default(), b"test", claims);
+        assert_eq!(settings.decode(&token, "test").unwrap().sub, "drone-1");
+        assert_eq!(settings.decode(&valid, "test").unwrap().sub, "drone-1");
+    }
+
+    #[test]
+    fn test_gateway() {
+        let credentials = "gateway-1=secret-1, gateway-2 = secret-2,gateway-3=,malformed";
+        assert_eq!(gateway(credentials, "secret-1"), Some("gateway-1"));
+        assert_eq!(gateway(credentials, "secret-2"), Some("gateway-2"));
+        assert_eq!(gateway(credentials, "secret"), None);
+        assert_eq!(gateway(credentials, "secret-10"), None);
+
+        // gateways without secret can't log in
+        assert_eq!(gateway(credentials, ""), None);
+        assert_eq!(gateway("", ""), None);
+    }
+
+    #[tokio::test]
+    async fn test_login_bulk() {
+        let _ = JWT_SECRET.set("test".to_string());
+        let config = Config {
+            gateway_credentials: "gateway-1=secret-1".to_string(),
+            ..Default::default()
+        };
+        let pipeline = crate::rest::api::test_pipeline(config).await;
+
+        let request = |identifiers: Vec<&str>| {
+            Json(BulkLoginRequest {
+                identifiers: identifiers.into_iter().map(String::from).collect(),
+            })
+        };
+        let mut headers = HeaderMap::new();
+        let error = login_bulk(
+            Extension(pipeline.clone()),
+            headers.clone(),
+            request(vec!["drone-1"]),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error, StatusCode::UNAUTHORIZED);
+
+        headers.insert(header::AUTHORIZATION, "Bearer secret-2".parse().unwrap());
+        let error = login_bulk(
+            Extension(pipeline.clone()),
+            headers.clone(),
+            request(vec!["drone-1"]),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error, StatusCode::UNAUTHORIZED);
+
+        headers.insert(header::AUTHORIZATION, "Bearer secret-1".parse().unwrap());
+        let error = login_bulk(
+            Extension(pipeline.clone()),
+            headers.clone(),
+            request(vec![]),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error, StatusCode::BAD_REQUEST);
+
+        let Json(response) = login_bulk(
+            Extension(pipeline.clone()),
+            headers,
+            request(vec!["drone-1", " ", "drone-2"]),
+        )
+        .await
+        .unwrap();
+        let statuses: Vec<u16> = response.results.iter().map(|r| r.status).collect();
+        assert_eq!(statuses, vec![200, 400, 200]);
+        assert!(response.results[1].login.is_none());
+
+        let login = response.results[2].login.clone().unwrap();
+        let claim = Claim::decode(login.token).unwrap();
+        assert_eq!(claim.sub, "drone-2");
+    }
 }
@@ -0,0 +1,114 @@
+//! REST API endpoint adjusting log levels at runtime
+
+use super::Pipeline;
+use crate::logging;
+use axum::{extract::Extension, Json};
+use hyper::StatusCode;
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Log level change request
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct LogLevelRequest {
+    /// Log target to adjust, `root` for the root logger
+    #[schema(example = "app::rest")]
+    pub target: String,
+
+    /// One of `off`, `error`, `warn`, `info`, `debug` or `trace`,
+    ///  or none to reset the target to its configured level
+    #[schema(example = "debug")]
+    pub level: Option<String>,
+}
+
+/// Log levels overridden at runtime
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LogLevelResponse {
+    /// Level per log target
+    pub overrides: BTreeMap<String, String>,
+}
+
+impl From<BTreeMap<String, LevelFilter>> for LogLevelResponse {
+    fn from(overrides: BTreeMap<String, LevelFilter>) -> Self {
+        Self {
+            overrides: overrides
+                .into_iter()
+                .map(|(target, level)| (target, level.to_string().to_lowercase()))
+                .collect(),
+        }
+    }
+}
+
+/// Parses the requested level, none resetting the target
+fn parse_level(level: Option<&str>) -> Result<Option<LevelFilter>, StatusCode> {
+    level
+        .map(|level| {
+            LevelFilter::from_str(level.trim()).map_err(|_| {
+                rest_warn!("invalid log level: {level}.");
+                StatusCode::BAD_REQUEST
+            })
+        })
+        .transpose()
+}
+
+/// Set the log level of a target without restarting the service
+///
+/// Overrides apply on top of the log configuration file and are dropped
+///  when it is reloaded with `SIGHUP`.
+#[utoipa::path(
+    put,
+    path = "/v1/admin/log_level",
+    tag = "svc-telemetry",
+    request_body = LogLevelRequest,
+    responses(
+        (status = 200, description = "Log level applied, overrides returned.", body = LogLevelResponse),
+        (status = 400, description = "Empty target or invalid level."),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 422, description = "Malformed JSON body."),
+        (status = 500, description = "The log configuration file could not be loaded."),
+        (status = 503, description = "The logger can't be reconfigured."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) needs the logger of the process
+pub async fn log_level(
+    Extension(pipeline): Extension<Pipeline>,
+    Json(request): Json<LogLevelRequest>,
+) -> Result<Json<LogLevelResponse>, StatusCode> {
+    rest_debug!("entry.");
+    let target = request.target.trim();
+    if target.is_empty() {
+        rest_warn!("empty log target.");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let level = parse_level(request.level.as_deref())?;
+    let overrides = logging::set_level(&pipeline.config.log_config, target, level).await?;
+    Ok(Json(overrides.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level(None), Ok(None));
+        assert_eq!(parse_level(Some("debug")), Ok(Some(LevelFilter::Debug)));
+        assert_eq!(parse_level(Some(" WARN ")), Ok(Some(LevelFilter::Warn)));
+        assert_eq!(parse_level(Some("off")), Ok(Some(LevelFilter::Off)));
+        assert_eq!(parse_level(Some("loud")), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn test_response() {
+        let response = LogLevelResponse::from(BTreeMap::from([
+            ("root".to_string(), LevelFilter::Warn),
+            ("app::rest".to_string(), LevelFilter::Debug),
+        ]));
+        assert_eq!(response.overrides["root"], "warn");
+        assert_eq!(response.overrides["app::rest"], "debug");
+    }
+}
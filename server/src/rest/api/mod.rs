@@ -1,6 +1,299 @@
 //! API
 
+pub mod admin;
 pub mod adsb;
+pub mod api_key;
+pub mod backfill;
+pub mod blocklist;
+pub mod c2;
+pub mod coverage;
+#[cfg(all(not(test), feature = "memory_backends"))]
+pub mod dev;
+pub mod encoding;
+pub mod enrichment;
 pub mod health;
+pub mod health_report;
+pub mod heartbeat;
+pub mod identity;
 pub mod jwt;
+pub mod log_level;
 pub mod netrid;
+pub mod ogn;
+pub mod quarantine;
+pub mod quorum;
+pub mod receipt;
+pub mod reporter;
+pub mod request_id;
+pub mod session;
+pub mod signal;
+pub mod signature;
+pub mod snapshot;
+pub mod state;
+pub mod stats;
+pub mod telemetry;
+pub mod trail;
+pub mod uat;
+pub mod watchlist;
+pub mod weather;
+
+use crate::amqp::raw::Reception;
+use crate::cache::{
+    batch::DedupCounters,
+    pool::{GisPool, TelemetryPool},
+    TelemetryPools,
+};
+use crate::clock::SharedClock;
+use crate::degradation::DegradationPolicy;
+use crate::grpc::client::GrpcClients;
+use crate::msg::{
+    anomaly::AnomalyDetectors, backfill::SharedBackfill, blocklist::Blocklist, c2::SharedC2Links,
+    conformance::SharedConformance, coverage::SharedCoverage, filter::SharedFilters,
+    geofence::Geofence, identifier::IdentifierRules, privacy::Privacy, track::SharedTracks,
+    watchlist::SharedWatchlist,
+};
+use crate::sink::{
+    amqp::AmqpSink, anomaly::AnomalySink, conformance::ConformanceSink, coverage::CoverageSink,
+    gis::GisSink, kafka::KafkaSink, netrid_state::NetridStateSink, storage::StorageSink,
+    trail::TrailSink, NoopSink, SinkKind, Sinks, TelemetrySink,
+};
+use crate::stats::Stats;
+use crate::Config;
+use hyper::StatusCode;
+use receipt::ReceiptSigner;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Packet posted to the telemetry endpoints, as raw bytes
+///  (or hex or base64 encoded text if posted as `text/plain`)
+#[derive(Debug, ToSchema)]
+#[schema(value_type = String, format = Binary)]
+pub struct BinaryPacket(pub Vec<u8>);
+
+/// Shared state of the telemetry processing pipeline, the only extension
+///  the handlers need
+///
+/// Its backends are selected at build time: Redis, RabbitMQ and gRPC, the
+///  in-memory backends of the `memory_backends` feature, or the stubs of
+///  the tests (see [`test_pipeline`]) which drive the handlers end to end.
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    /// Server configuration
+    pub config: Arc<Config>,
+
+    /// RabbitMQ channel publishing telemetry
+    pub mq_channel: crate::amqp::MqChannel,
+
+    /// Redis pools holding the decoding state
+    pub tlm_pools: TelemetryPools,
+
+    /// Counters of the packets reported by several receivers
+    pub dedup: DedupCounters,
+
+    /// Redis pool for svc-gis queues
+    pub gis_pool: GisPool,
+
+    /// Redis pool holding the latest weather of each ground station
+    pub weather_pool: TelemetryPool,
+
+    /// Redis pool holding the snapshot of the tracked aircraft
+    pub snapshot_pool: TelemetryPool,
+
+    /// gRPC clients of other services
+    pub grpc_clients: GrpcClients,
+
+    /// Ordering and merging of position reports per aircraft
+    pub tracks: SharedTracks,
+
+    /// Smoothing of velocities per aircraft
+    pub filters: SharedFilters,
+
+    /// Statistics of the received telemetry
+    pub stats: Stats,
+
+    /// Aircraft to alert on
+    pub watchlist: SharedWatchlist,
+
+    /// Aircraft whose telemetry is rejected, as configured
+    pub blocklist: Arc<Blocklist>,
+
+    /// Progress of the replay of stored packets into svc-gis
+    pub backfill: SharedBackfill,
+
+    /// Loss of link detection per aircraft
+    pub c2_links: SharedC2Links,
+
+    /// Coverage of the receivers, fed by the `coverage` sink
+    pub coverage: SharedCoverage,
+
+    /// Outputs of decoded telemetry, in push order, built once by
+    ///  [`Pipeline::with_sinks`]
+    pub sinks: Arc<Sinks>,
+
+    /// Scrubbing of operator data before it is published
+    pub privacy: Arc<Privacy>,
+
+    /// How aircraft identifiers are written
+    pub identifiers: Arc<IdentifierRules>,
+
+    /// Operating regions of the reporters
+    pub geofence: Arc<Geofence>,
+
+    /// Detectors run by the `anomaly` sink
+    pub anomalies: Arc<AnomalyDetectors>,
+
+    /// Corridors of the missions, checked by the `conformance` sink
+    pub conformance: SharedConformance,
+
+    /// Source of the current time
+    pub clock: SharedClock,
+
+    /// What to do with telemetry when a dependency fails
+    pub degradation: DegradationPolicy,
+
+    /// Signer of the delivery receipts, if the service has a receipt key
+    pub receipts: Option<Arc<ReceiptSigner>>,
+}
+
+impl Pipeline {
+    /// Build the sinks decoded telemetry is pushed to, from the clients
+    ///  of the pipeline
+    pub fn with_sinks(mut self, kinds: &[SinkKind]) -> Self {
+        let sinks = kinds
+            .iter()
+            .map(|kind| -> Box<dyn TelemetrySink> {
+                match kind {
+                    SinkKind::Gis => {
+                        Box::new(GisSink::new(self.gis_pool.clone(), self.stats.clone()))
+                    }
+                    SinkKind::Storage => Box::new(StorageSink::new(
+                        self.grpc_clients.clone(),
+                        self.tlm_pools.adsb.clone(),
+                        self.config.storage_idempotency_enabled,
+                        self.stats.clone(),
+                    )),
+                    SinkKind::Amqp => Box::new(AmqpSink::new(
+                        self.mq_channel.clone(),
+                        self.clock.clone(),
+                        self.stats.clone(),
+                    )),
+                    SinkKind::Kafka => Box::new(KafkaSink::new(
+                        &self.config.kafka_rest_url,
+                        &self.config.kafka_topic,
+                        self.config.kafka_timeout_ms,
+                        self.stats.clone(),
+                    )),
+                    SinkKind::Coverage => Box::new(CoverageSink::new(self.coverage.clone())),
+                    SinkKind::Anomaly => Box::new(AnomalySink::new(
+                        self.anomalies.clone(),
+                        self.mq_channel.clone(),
+                        self.stats.clone(),
+                    )),
+                    SinkKind::Trail => Box::new(TrailSink::new(
+                        self.tlm_pools.clone(),
+                        self.config.trail_max_points,
+                        self.config.trail_expire_ms,
+                        self.stats.clone(),
+                    )),
+                    SinkKind::NetridState => Box::new(NetridStateSink::new(
+                        self.tlm_pools.netrid.clone(),
+                        self.mq_channel.clone(),
+                        self.config.netrid_state_stale_ms,
+                        self.clock.clone(),
+                        self.stats.clone(),
+                    )),
+                    SinkKind::Conformance => Box::new(ConformanceSink::new(
+                        self.conformance.clone(),
+                        &self.config.conformance_corridor_url,
+                        self.mq_channel.clone(),
+                        self.clock.clone(),
+                        self.stats.clone(),
+                    )),
+                    SinkKind::Noop => Box::new(NoopSink),
+                }
+            })
+            .collect();
+
+        self.sinks = Arc::new(Sinks::new(sinks).with_policy(self.degradation));
+        self
+    }
+
+    /// Ingest stage of a decoded packet, once its aircraft are known:
+    ///  rejects the packets of blocked aircraft, then alerts on watched
+    ///  ones
+    ///
+    /// A packet naming an aircraft other than its reporter, such as a
+    ///  relayed one, is admitted under both identifiers.
+    pub async fn admit(&self, aircraft: &[&str], source: &str) -> Result<(), StatusCode> {
+        for identifier in aircraft {
+            blocklist::screen(self, identifier, source).await?;
+        }
+
+        for identifier in aircraft {
+            watchlist::observe(self, identifier, source).await;
+        }
+
+        Ok(())
+    }
+
+    /// Publishes a received packet as-is, if enabled. Packets carrying
+    ///  personal data must not be published, see [`crate::amqp::raw`]
+    pub async fn publish_raw(&self, routing_key: &str, payload: &[u8], reception: &Reception) {
+        if self.config.raw_exchange_enabled {
+            let mq_channel = &self.mq_channel;
+            crate::amqp::raw::publish(mq_channel, &self.stats, routing_key, payload, reception)
+                .await;
+        }
+    }
+}
+
+/// Pipeline backed by the test stubs of the cache pools, publishing to a
+///  RabbitMQ channel of its own held in memory
+#[cfg(test)]
+pub(crate) async fn test_pipeline(config: Config) -> Pipeline {
+    let tlm_pools = TelemetryPools {
+        adsb: TelemetryPool::new(config.clone(), "adsb").await.unwrap(),
+        netrid: TelemetryPool::new(config.clone(), "netrid").await.unwrap(),
+    };
+
+    let identifiers = IdentifierRules::new(&config.identifier_rules);
+    Pipeline {
+        config: Arc::new(config.clone()),
+        mq_channel: crate::amqp::init_mq(config.clone()).await.unwrap(),
+        dedup: DedupCounters::new(&tlm_pools, config.dedup_batch_window_ms),
+        tlm_pools,
+        gis_pool: GisPool::new(config.clone()).await.unwrap(),
+        weather_pool: TelemetryPool::new(config.clone(), "weather").await.unwrap(),
+        snapshot_pool: TelemetryPool::new(config.clone(), "snapshot")
+            .await
+            .unwrap(),
+        grpc_clients: GrpcClients::default(config.clone()),
+        tracks: crate::msg::track::TrackMerger::shared(config.track_merge_window_ms),
+        filters: crate::msg::filter::VelocityFilters::shared(
+            config.velocity_filter_enabled,
+            config.velocity_filter_alpha,
+            config.velocity_filter_beta,
+        ),
+        stats: Stats::default(),
+        watchlist: crate::msg::watchlist::Watchlist::shared(
+            &identifiers.resolve_list(&config.watchlist),
+        ),
+        blocklist: Arc::new(Blocklist::new(&identifiers.resolve_list(&config.blocklist))),
+        backfill: crate::msg::backfill::BackfillStatus::shared(),
+        c2_links: crate::msg::c2::C2LinkMonitor::shared(config.c2_link_timeout_ms),
+        coverage: crate::msg::coverage::CoverageMap::shared(
+            config.coverage_geohash_precision as usize,
+        ),
+        sinks: Arc::default(),
+        privacy: Arc::new(Privacy::new(&config)),
+        identifiers: Arc::new(identifiers),
+        geofence: Arc::new(Geofence::new(&config.reporter_regions)),
+        anomalies: Arc::new(AnomalyDetectors::new(&config)),
+        conformance: crate::msg::conformance::ConformanceMonitor::shared(
+            config.conformance_refresh_ms,
+        ),
+        clock: crate::clock::SystemClock::shared(),
+        degradation: DegradationPolicy::new(&config.degradation_policy),
+        receipts: None,
+    }
+    .with_sinks(&SinkKind::parse_list(&config.telemetry_sinks))
+}
@@ -3,29 +3,58 @@
 //!  It will be required for use of U-Space airspace by unmanned aircraft.
 //! Endpoints for updating aircraft positions
 
-use crate::cache::pool::GisPool;
-use crate::cache::TelemetryPools;
+use super::identity;
+use super::jwt::Claim;
+use super::quorum::Confirmation;
+use super::reporter::{self, ReporterOutcome};
+use super::signal::{signal_metadata, SignalHeaders, HEADER_ODID_TRANSPORT};
+use super::signature::{verifier, AuthenticationStatus};
+use super::Pipeline;
+use crate::amqp::envelope::{PositionAccuracy, SignalMetadata, TelemetryEnvelope};
+use crate::amqp::raw::Reception;
+use crate::amqp::ROUTING_KEY_RAW_NETRID;
+use crate::cache::pool::TelemetryPool;
+use crate::degradation::DegradationPolicy;
+use crate::dispatcher::{enqueue, StreamEntry};
+use crate::logging::context;
+use crate::msg::geofence::Geofence;
+use crate::msg::identifier::{IdentifierKind, IdentifierRules};
+use crate::msg::identity::ConflictKind;
 use crate::msg::netrid::{
-    BasicMessage, Frame, IdType, LocationMessage, MessageType, UaType as NetridAircraftType,
+    AuthenticationMessage, AuthenticationSignature, BasicMessage, Frame, IdType,
+    LocationDecodeError, LocationMessage, MessagePack, MessageType, OperatorIdMessage,
+    ProtocolVersion, SystemMessage, UaType as NetridAircraftType,
 };
+use crate::msg::odid::Transport;
+use crate::msg::privacy::OperatorInfo;
+use crate::msg::track::TrackDecision;
+use crate::sink::{EventData, EventSource, Sinks, TelemetryEvent};
+use crate::stats::{Dependency, Stats};
 use svc_gis_client_grpc::prelude::types::*;
 
-use axum::{body::Bytes, extract::Extension, Json};
+use axum::{body::Bytes, extract::Extension, http::HeaderMap, Json};
 use hyper::StatusCode;
-use lib_common::time::Utc;
+use lib_common::time::{DateTime, Utc};
 use packed_struct::PackedStruct;
-use std::cmp::Ordering;
 
 /// Remote ID entries in the cache will expire after 60 seconds
 const CACHE_EXPIRE_MS_NETRID: u32 = 10000;
 
-/// Number of times a packet must be received
-///  from unique senders before it is considered valid
-const N_REPORTERS_NEEDED: u32 = 1;
-
 /// Length of a remote id packet
 const REMOTE_ID_PACKET_LENGTH: usize = 25;
 
+/// Authentication pages and status expire if not refreshed by the aircraft
+const CACHE_EXPIRE_MS_AUTHENTICATION: u32 = 60000;
+
+/// Hash field holding the authentication status of an aircraft
+const AUTHENTICATION_STATUS_FIELD: &str = "status";
+
+/// Stream of the packets positioned outside the operating region of their reporter
+pub(crate) const QUARANTINE_STREAM: &str = "quarantine";
+
+/// The quarantine stream is trimmed to this many packets
+const QUARANTINE_MAX_LEN: usize = 1000;
+
 impl From<NetridAircraftType> for AircraftType {
     fn from(t: NetridAircraftType) -> Self {
         match t {
@@ -49,32 +78,77 @@ impl From<NetridAircraftType> for AircraftType {
     }
 }
 
+/// What is known of the reporter of a message, attached to its telemetry
+#[derive(Debug, Clone)]
+struct Reporter {
+    /// Authentication status of the aircraft
+    authentication: AuthenticationStatus,
+
+    /// Login session of the reporter, to audit identifiers used concurrently
+    session: Option<String>,
+
+    /// Flight plan or mission declared by the session
+    mission: Option<String>,
+
+    /// Signal metadata declared by the receiver
+    signal: Option<SignalMetadata>,
+
+    /// When the packet carrying the message was received
+    received: DateTime<Utc>,
+
+    /// Protocol version of the message
+    version: ProtocolVersion,
+}
+
+impl Reporter {
+    /// Attach the reporter to an event
+    fn tag(&self, event: TelemetryEvent) -> TelemetryEvent {
+        let event = event
+            .with_authentication(self.authentication)
+            .with_received(self.received)
+            .with_protocol_version(self.version);
+        let event = match &self.session {
+            Some(session) => event.with_session(session.clone()),
+            None => event,
+        };
+        let event = match &self.mission {
+            Some(mission) => event.with_mission(mission.clone()),
+            None => event,
+        };
+
+        match self.signal {
+            Some(signal) => event.with_signal(signal),
+            None => event,
+        }
+    }
+}
+
 /// Processes a basic remote id message type
 #[cfg(not(tarpaulin_include))]
 // no_coverage: (R5) need AMQP and redis backends to test
 async fn process_basic_message(
     jwt_identifier: String,
     message: BasicMessage,
-    mut gis_pool: GisPool,
-    mq_channel: lapin::Channel,
+    reporter: Reporter,
+    sinks: &Sinks,
+    identifiers: &IdentifierRules,
+    now: DateTime<Utc>,
 ) -> Result<(), StatusCode> {
     rest_debug!("entry.");
     let aircraft_type = AircraftType::from(message.ua_type);
     let mut id_item = AircraftId {
-        identifier: Some(jwt_identifier),
+        identifier: Some(jwt_identifier.clone()),
         session_id: None,
         aircraft_type,
-        timestamp_network: Utc::now(),
+        timestamp_network: now,
         timestamp_asset: None,
     };
 
-    let identifier = String::from_utf8(message.uas_id.to_vec())
-        .map_err(|_| {
-            rest_warn!("could not parse identifier to string.");
-            StatusCode::BAD_REQUEST
-        })?
-        .trim()
-        .to_string();
+    let identifier = message.decode_uas_id().ok_or_else(|| {
+        rest_warn!("could not parse identifier to string.");
+        StatusCode::BAD_REQUEST
+    })?;
+    let identifier = identifiers.normalize(IdentifierKind::RemoteId, &identifier);
 
     match message.id_type {
         IdType::UtmAssigned => id_item.session_id = Some(identifier),
@@ -82,55 +156,26 @@ async fn process_basic_message(
         _ => id_item.identifier = Some(identifier),
     }
 
-    gis_pool
-        .push::<AircraftId>(id_item.clone(), REDIS_KEY_AIRCRAFT_ID)
-        .await
-        .map_err(|_| {
-            rest_warn!("could not push aircraft id to cache.");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let event = TelemetryEvent::new(
+        EventSource::Netrid,
+        &jwt_identifier,
+        EventData::Identification(id_item),
+        now,
+    );
 
-    rest_debug!("pushed aircraft id to redis.");
-
-    //
-    // Send Telemetry to RabbitMQ
-    //
-    let msg = match serde_json::to_vec(&id_item) {
-        Ok(msg) => msg,
-        Err(_) => {
-            rest_warn!("could not serialize id item.");
-            return Ok(()); // fine, not a critical error
-        }
-    };
-
-    let _ = mq_channel
-        .basic_publish(
-            crate::amqp::EXCHANGE_NAME_TELEMETRY,
-            crate::amqp::ROUTING_KEY_NETRID_ID,
-            lapin::options::BasicPublishOptions::default(),
-            &msg,
-            lapin::BasicProperties::default(),
-        )
-        .await
-        .map_err(|e| {
-            rest_warn!("could not push aircraft id to RabbitMQ: {e}.");
-        })
-        .map(|_| {
-            rest_debug!("pushed aircraft id to RabbitMQ.");
-        });
+    sinks.push(&reporter.tag(event)).await?;
+    rest_debug!("pushed aircraft id to sinks.");
 
     Ok(())
 }
 
-/// Processes a basic remote id message type
-#[cfg(not(tarpaulin_include))]
-// no_coverage: (R5) need AMQP and redis backends to test
-async fn process_location_message(
-    identifier: String,
-    message: LocationMessage,
-    mut gis_pool: GisPool,
-    mq_channel: lapin::Channel,
-) -> Result<(), StatusCode> {
+/// Position and, if its direction is known, velocity of a location
+///  message received at `now`
+fn location_items(
+    identifier: &str,
+    message: &LocationMessage,
+    now: DateTime<Utc>,
+) -> Result<(AircraftPosition, Option<AircraftVelocity>), StatusCode> {
     //
     // TODO(R5): Decide what to do when a field is UNKNOWN
     //  Reject the whole message? Use the 'unknown' value (e.g. 63.0 for vertical rate)?
@@ -152,125 +197,311 @@ async fn process_location_message(
         StatusCode::BAD_REQUEST
     })?;
 
-    let timestamp_asset = match message.decode_timestamp() {
-        Ok(ts) => Some(ts),
-        Err(_) => None,
-    };
+    let timestamp_asset = message.decode_timestamp(now).ok();
 
     let latitude = message.decode_latitude();
     let longitude = message.decode_longitude();
 
     let position_item = AircraftPosition {
-        identifier: identifier.clone(),
+        identifier: identifier.to_string(),
         position: Position {
             latitude,
             longitude,
             altitude_meters: altitude_meters as f64,
         },
-        timestamp_network: Utc::now(),
+        timestamp_network: now,
         timestamp_asset,
     };
 
-    let velocity_item = AircraftVelocity {
-        identifier,
+    // hovering aircraft may not know their direction, their position is
+    //  still pushed
+    let track_angle_degrees = match message.decode_direction() {
+        Ok(direction) => Some(direction as f32),
+        Err(LocationDecodeError::UnknownDirection) => None,
+        Err(e) => {
+            rest_warn!("could not parse direction: {e}.");
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    };
+
+    let velocity_item = track_angle_degrees.map(|track_angle_degrees| AircraftVelocity {
+        identifier: identifier.to_string(),
         velocity_vertical_mps,
         velocity_horizontal_ground_mps,
         velocity_horizontal_air_mps: None,
-        track_angle_degrees: message.decode_direction() as f32,
+        track_angle_degrees,
         timestamp_asset,
-        timestamp_network: Utc::now(),
+        timestamp_network: now,
+    });
+
+    Ok((position_item, velocity_item))
+}
+
+/// Processes a location remote id message type
+async fn process_location_message(
+    identifier: String,
+    message: LocationMessage,
+    reporter: Reporter,
+    pipeline: Pipeline,
+) -> Result<(), StatusCode> {
+    let sinks = pipeline.sinks.clone();
+    let now = pipeline.clock.now();
+    let (position_item, velocity_item) = location_items(&identifier, &message, now)?;
+    let Pipeline {
+        tracks, filters, ..
+    } = pipeline;
+
+    // the track records the velocity pushed, smoothed
+    let velocity_item = match velocity_item {
+        Some(item) => Some(
+            filters
+                .lock()
+                .map_err(|e| {
+                    rest_error!("could not lock velocity filters: {e}");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?
+                .apply(item),
+        ),
+        None => None,
     };
 
-    gis_pool
-        .push::<AircraftPosition>(position_item.clone(), REDIS_KEY_AIRCRAFT_POSITION)
-        .await
-        .map_err(|_| {
-            rest_warn!("could not push aircraft position to cache.");
+    // Older reports than the last accepted one would make the track jump back
+    let (decision, velocity_item) = {
+        let mut tracks = tracks.lock().map_err(|e| {
+            rest_error!("could not lock tracks: {e}");
             StatusCode::INTERNAL_SERVER_ERROR
-        })?; // TODO(R5): Do we want to bail here or still send the velocity to postgis?
+        })?;
 
-    rest_debug!("pushed aircraft position to redis.");
+        // the velocity is ordered on its own, whatever became of the position
+        let decision = tracks.update(position_item, now);
+        let velocity_item = velocity_item.filter(|item| tracks.update_velocity(item.clone()));
+        (decision, velocity_item)
+    };
 
-    let _ = gis_pool
-        .push::<AircraftVelocity>(velocity_item.clone(), REDIS_KEY_AIRCRAFT_VELOCITY)
-        .await
-        .map_err(|_| {
-            rest_warn!("could not push aircraft velocity to cache.");
-            // StatusCode::INTERNAL_SERVER_ERROR
-        });
+    let event = |data: EventData| {
+        reporter.tag(TelemetryEvent::new(
+            EventSource::Netrid,
+            &identifier,
+            data,
+            now,
+        ))
+    };
 
-    rest_debug!("pushed aircraft velocity to redis.");
+    match decision {
+        TrackDecision::Accept(position_item) | TrackDecision::Merge(position_item) => {
+            let accuracy = PositionAccuracy {
+                horizontal_meters: message.horizontal_accuracy.bound_meters(),
+                vertical_meters: message.vertical_accuracy.bound_meters(),
+            };
 
-    //
-    // Send Telemetry to RabbitMQ
-    //
-    if let Ok(msg) = serde_json::to_vec(&position_item) {
-        let _ = mq_channel
-            .basic_publish(
-                crate::amqp::EXCHANGE_NAME_TELEMETRY,
-                crate::amqp::ROUTING_KEY_NETRID_POSITION,
-                lapin::options::BasicPublishOptions::default(),
-                &msg,
-                lapin::BasicProperties::default(),
-            )
-            .await
-            .map_err(|e| {
-                rest_warn!("could not push aircraft id to RabbitMQ: {e}.");
-            });
+            sinks
+                .push(&event(EventData::Position(position_item)).with_accuracy(accuracy))
+                .await?;
+            rest_debug!("pushed aircraft position to sinks.");
+        }
+        TrackDecision::Discard => {
+            rest_info!("discarded out of order location report for {identifier}.");
+        }
+    }
 
-        rest_debug!("pushed aircraft position to RabbitMQ.");
-    } else {
-        rest_warn!("could not serialize position item.");
+    // failures are handled as for positions, by the degradation policy
+    if let Some(velocity_item) = velocity_item {
+        sinks
+            .push(&event(EventData::Velocity(velocity_item)))
+            .await?;
+        rest_debug!("pushed aircraft velocity to sinks.");
     }
 
-    //
-    // Send Telemetry to RabbitMQ
-    //
-    if let Ok(msg) = serde_json::to_vec(&velocity_item) {
-        let _ = mq_channel
-            .basic_publish(
-                crate::amqp::EXCHANGE_NAME_TELEMETRY,
-                crate::amqp::ROUTING_KEY_NETRID_VELOCITY,
-                lapin::options::BasicPublishOptions::default(),
-                &msg,
-                lapin::BasicProperties::default(),
-            )
-            .await
-            .map_err(|e| {
-                rest_warn!("could not push aircraft id to RabbitMQ: {e}.");
-            });
+    Ok(())
+}
+
+/// Pushes the operator of an aircraft to the sinks, scrubbed of personal
+///  data, and publishes it unscrubbed to authorized consumers if enabled
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP backend to test
+async fn process_operator(
+    operator: OperatorInfo,
+    reporter: Reporter,
+    pipeline: Pipeline,
+) -> Result<(), StatusCode> {
+    let mq_channel = pipeline.mq_channel.clone();
+    rest_debug!("entry.");
+    if pipeline.config.privacy_full_fidelity_enabled {
+        match serde_json::to_vec(&TelemetryEnvelope::new(&operator)) {
+            Ok(msg) => {
+                let published = mq_channel
+                    .basic_publish(
+                        crate::amqp::EXCHANGE_NAME_TELEMETRY,
+                        crate::amqp::ROUTING_KEY_NETRID_OPERATOR_FULL,
+                        lapin::options::BasicPublishOptions::default(),
+                        &msg,
+                        lapin::BasicProperties::default(),
+                    )
+                    .await;
 
-        rest_debug!("pushed aircraft position to RabbitMQ.");
-    } else {
-        rest_warn!("could not serialize velocity item.");
+                if let Err(e) = published {
+                    rest_warn!("could not publish unscrubbed operator to RabbitMQ: {e}.");
+                    pipeline.stats.record_error(Dependency::Amqp);
+                    pipeline.degradation.on_failure(Dependency::Amqp)?;
+                }
+            }
+            Err(e) => rest_warn!("could not serialize operator: {e}."),
+        }
     }
 
+    let scrubbed = pipeline.privacy.scrub(&operator);
+    let event = TelemetryEvent::new(
+        EventSource::Netrid,
+        &operator.identifier,
+        EventData::Operator(scrubbed),
+        pipeline.clock.now(),
+    );
+
+    pipeline.sinks.push(&reporter.tag(event)).await?;
+    rest_debug!("pushed aircraft operator to sinks.");
+
     Ok(())
 }
 
-/// Remote ID
-#[utoipa::path(
-    post,
-    path = "/telemetry/netrid",
-    tag = "svc-telemetry",
-    request_body = Vec<u8>,
-    responses(
-        (status = 200, description = "Telemetry received."),
-        (status = 400, description = "Malformed packet."),
-        (status = 500, description = "Something went wrong."),
-        (status = 503, description = "Dependencies of svc-telemetry were down."),
-    )
-)]
-pub async fn network_remote_id(
-    Extension(mut tlm_pools): Extension<TelemetryPools>,
-    Extension(gis_pool): Extension<GisPool>,
-    Extension(mq_channel): Extension<lapin::Channel>,
-    Extension(claim): Extension<crate::rest::api::jwt::Claim>,
-    payload: Bytes,
-) -> Result<Json<u32>, StatusCode> {
-    rest_info!("entry.");
+/// Operator of an aircraft located by a system message
+fn system_operator(identifier: &str, message: &SystemMessage, now: DateTime<Utc>) -> OperatorInfo {
+    let location = message.decode_operator_location();
+    OperatorInfo {
+        identifier: identifier.to_string(),
+        operator_id: None,
+        latitude: location.map(|(latitude, _)| latitude),
+        longitude: location.map(|(_, longitude)| longitude),
+        altitude_meters: message.decode_operator_altitude(),
+        scrubbed: false,
+        timestamp_network: now,
+    }
+}
 
-    let payload = <[u8; REMOTE_ID_PACKET_LENGTH]>::try_from(payload.as_ref()).map_err(|_| {
+/// Operator of an aircraft identified by an operator id message
+fn identified_operator(
+    identifier: &str,
+    message: &OperatorIdMessage,
+    now: DateTime<Utc>,
+) -> Result<OperatorInfo, StatusCode> {
+    let operator_id = message.decode_operator_id().ok_or_else(|| {
+        rest_warn!("could not parse operator identifier.");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    Ok(OperatorInfo {
+        identifier: identifier.to_string(),
+        operator_id: Some(operator_id),
+        latitude: None,
+        longitude: None,
+        altitude_meters: None,
+        scrubbed: false,
+        timestamp_network: now,
+    })
+}
+
+/// Processes one page of a remote id authentication message
+///  Once every page of the message has been received, the assembled
+///  signature is verified and the resulting status stored for the aircraft.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+async fn process_authentication_message(
+    identifier: &str,
+    message: AuthenticationMessage,
+    mut tlm_pool: TelemetryPool,
+    stats: &Stats,
+    policy: &DegradationPolicy,
+) -> Result<(), StatusCode> {
+    rest_debug!("entry.");
+    let key = format!("{identifier}:auth");
+    let page_number = u8::from(message.page_number);
+    let page = message.pack().map_err(|_| {
+        rest_warn!("could not pack authentication page.");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    // degraded, the page is dropped and the aircraft stays unverified
+    if let Err(e) = tlm_pool
+        .hash_set(
+            &key,
+            &page_number.to_string(),
+            &crate::cache::bytes_to_key(&page),
+            CACHE_EXPIRE_MS_AUTHENTICATION,
+        )
+        .await
+    {
+        rest_warn!("could not store authentication page: {e}");
+        stats.record_error(Dependency::Redis);
+        return policy.on_failure(Dependency::Redis);
+    }
+
+    let fields = match tlm_pool.hash_get_all(&key).await {
+        Ok(fields) => fields,
+        Err(e) => {
+            rest_warn!("could not get authentication pages: {e}");
+            stats.record_error(Dependency::Redis);
+            return policy.on_failure(Dependency::Redis);
+        }
+    };
+
+    let pages = fields
+        .iter()
+        .filter(|(field, _)| field.as_str() != AUTHENTICATION_STATUS_FIELD)
+        .filter_map(|(_, value)| {
+            let bytes = crate::cache::key_to_bytes(value)?;
+            let bytes = <[u8; 24]>::try_from(bytes.as_slice()).ok()?;
+            AuthenticationMessage::unpack(&bytes).ok()
+        })
+        .collect::<Vec<AuthenticationMessage>>();
+
+    let signature = match AuthenticationSignature::assemble(&pages) {
+        Ok(signature) => signature,
+        Err(e) => {
+            rest_debug!("authentication message incomplete: {e}.");
+            return Ok(());
+        }
+    };
+
+    let status = verifier().verify(identifier, &signature);
+    rest_info!("aircraft {identifier} authentication status: {status}.");
+
+    tlm_pool
+        .hash_set(
+            &key,
+            AUTHENTICATION_STATUS_FIELD,
+            &status.to_string(),
+            CACHE_EXPIRE_MS_AUTHENTICATION,
+        )
+        .await
+        .or_else(|e| {
+            rest_warn!("could not store authentication status: {e}");
+            stats.record_error(Dependency::Redis);
+            policy.on_failure(Dependency::Redis)
+        })
+}
+
+/// Gets the last known authentication status of an aircraft
+async fn get_authentication_status(
+    identifier: &str,
+    tlm_pool: &mut TelemetryPool,
+    stats: &Stats,
+) -> AuthenticationStatus {
+    let key = format!("{identifier}:auth");
+    match tlm_pool.hash_get_all(&key).await {
+        Ok(fields) => fields
+            .get(AUTHENTICATION_STATUS_FIELD)
+            .and_then(|status| status.parse().ok())
+            .unwrap_or(AuthenticationStatus::Unverified),
+        Err(e) => {
+            rest_warn!("could not get authentication status: {e}");
+            stats.record_error(Dependency::Redis);
+            AuthenticationStatus::Unverified
+        }
+    }
+}
+
+/// Decodes a received remote id packet, rejecting unsupported message types
+pub(crate) fn decode_frame(payload: &[u8]) -> Result<Frame, StatusCode> {
+    let payload = <[u8; REMOTE_ID_PACKET_LENGTH]>::try_from(payload).map_err(|_| {
         rest_warn!("could not parse payload.");
         StatusCode::BAD_REQUEST
     })?;
@@ -280,37 +511,160 @@ pub async fn network_remote_id(
         StatusCode::BAD_REQUEST
     })?;
 
-    //
-    // BasicMessage is identical throughout the whole flight,
-    //  don't want to toss repeats of the same message
-    let mut count = 1;
-    if frame.header.message_type != MessageType::Basic {
-        let key = crate::cache::bytes_to_key(&payload);
-        count = tlm_pools
-            .netrid
-            .increment(&key, CACHE_EXPIRE_MS_NETRID)
-            .await
-            .map_err(|_| {
-                rest_warn!("could not increment key.");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-
-        match count.cmp(&N_REPORTERS_NEEDED) {
-            Ordering::Less => {
-                rest_error!("netrid reporter count should be impossible: {count}.");
-                return Err(StatusCode::INTERNAL_SERVER_ERROR);
-            }
-            Ordering::Greater => {
-                rest_info!("netrid reporter count is greater than needed: {count}.");
-                return Ok(Json(count));
-            }
-            _ => (), // continue
+    frame_version(&frame)?;
+    match is_supported(frame.header.message_type) {
+        true => Ok(frame),
+        false => {
+            rest_warn!(
+                "unsupported message type: {:#?}.",
+                frame.header.message_type
+            );
+            Err(StatusCode::BAD_REQUEST)
         }
     }
+}
+
+/// The protocol version of a frame, rejecting unknown versions
+///
+/// Unknown versions may be newer revisions of the standard, which are
+///  not the reporter's fault and are rejected as unsupported.
+fn frame_version(frame: &Frame) -> Result<ProtocolVersion, StatusCode> {
+    frame.header.version().ok_or_else(|| {
+        rest_warn!(
+            "unsupported protocol version: {}.",
+            frame.header.protocol_version
+        );
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    })
+}
+
+/// If frames of this message type are processed
+fn is_supported(message_type: MessageType) -> bool {
+    matches!(
+        message_type,
+        MessageType::Basic
+            | MessageType::Location
+            | MessageType::Authentication
+            | MessageType::System
+            | MessageType::OperatorId
+    )
+}
+
+/// A decoded remote id packet
+#[derive(Debug, Clone)]
+struct Packet {
+    /// Identifier of the aircraft, if relayed by another reporter
+    aircraft: Option<String>,
+
+    /// Supported frames of the packet
+    frames: Vec<Frame>,
+}
+
+impl Packet {
+    /// If the packet carries personal data of the operator (System and
+    ///  Operator ID messages), scrubbed before the sinks, and which is
+    ///  never published to the raw exchange
+    fn has_operator_data(&self) -> bool {
+        self.frames.iter().any(|frame| {
+            matches!(
+                frame.header.message_type,
+                MessageType::System | MessageType::OperatorId
+            )
+        })
+    }
+}
+
+/// Decodes a received remote id packet
+///
+/// A relayed packet is a single Basic message or a Message Pack, either
+///  of which identifies the aircraft the relay observed.
+fn decode_packet(payload: &[u8], relayed: bool) -> Result<Packet, StatusCode> {
+    if !relayed {
+        return Ok(Packet {
+            aircraft: None,
+            frames: vec![decode_frame(payload)?],
+        });
+    }
+
+    let frames = match payload.len() {
+        REMOTE_ID_PACKET_LENGTH => vec![decode_frame(payload)?],
+        _ => MessagePack::unpack(payload)
+            .map_err(|e| {
+                rest_warn!("could not parse message pack: {e}.");
+                StatusCode::BAD_REQUEST
+            })?
+            .frames
+            .into_iter()
+            .filter(|frame| is_supported(frame.header.message_type))
+            .map(|frame| frame_version(&frame).map(|_| frame))
+            .collect::<Result<_, _>>()?,
+    };
+
+    let aircraft = frames
+        .iter()
+        .filter(|frame| frame.header.message_type == MessageType::Basic)
+        .find_map(|frame| BasicMessage::unpack(&frame.message).ok()?.decode_uas_id())
+        .ok_or_else(|| {
+            rest_warn!("relayed packet does not identify the aircraft.");
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok(Packet {
+        aircraft: Some(aircraft),
+        frames,
+    })
+}
+
+/// Packet type of a remote id message, for logging
+fn packet_type(message_type: MessageType) -> &'static str {
+    match message_type {
+        MessageType::Basic => "netrid:basic",
+        MessageType::Location => "netrid:location",
+        MessageType::Authentication => "netrid:authentication",
+        MessageType::System => "netrid:system",
+        MessageType::OperatorId => "netrid:operator_id",
+        MessageType::MessagePack => "netrid:message_pack",
+        _ => "netrid:other",
+    }
+}
+
+/// Pushes a validated and deduplicated remote id frame to svc-gis and RabbitMQ
+pub(crate) async fn process_netrid(
+    jwt_identifier: String,
+    session: Option<String>,
+    mission: Option<String>,
+    signal: Option<SignalMetadata>,
+    received: DateTime<Utc>,
+    frame: Frame,
+    pipeline: Pipeline,
+) -> Result<(), StatusCode> {
+    let version = frame_version(&frame)?;
+    let mq_channel = pipeline.mq_channel.clone();
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let stats = pipeline.stats.clone();
+
+    // tokens issued before the identifier rules changed keep their subject
+    let identifiers = pipeline.identifiers.clone();
+    let jwt_identifier = identifiers.normalize(IdentifierKind::RemoteId, &jwt_identifier);
+    stats.record_aircraft(&jwt_identifier);
+    context::set_aircraft(&jwt_identifier);
+    context::set_packet_type(packet_type(frame.header.message_type));
+
+    // self-identified aircraft, such as relayed ones, claim their own ID
+    let uas_id = match frame.header.message_type {
+        MessageType::Basic => BasicMessage::unpack(&frame.message)
+            .ok()
+            .and_then(|msg| msg.decode_uas_id())
+            .map(|id| identifiers.normalize(IdentifierKind::RemoteId, &id))
+            .filter(|id| *id != jwt_identifier),
+        _ => None,
+    };
+
+    let aircraft: Vec<&str> = std::iter::once(jwt_identifier.as_str())
+        .chain(uas_id.as_deref())
+        .collect();
+    pipeline.admit(&aircraft, "netrid").await?;
 
-    // Eventually allow forwarding of packets from other aircraft
-    // TODO(R5)
-    let jwt_identifier = claim.sub;
     match frame.header.message_type {
         MessageType::Basic => {
             let msg = BasicMessage::unpack(&frame.message).map_err(|_| {
@@ -318,15 +672,96 @@ pub async fn network_remote_id(
                 StatusCode::BAD_REQUEST
             })?;
 
-            process_basic_message(jwt_identifier, msg, gis_pool, mq_channel).await?;
+            if let Some(uas_id) = &uas_id {
+                identity::observe(
+                    &mut tlm_pool,
+                    &mq_channel,
+                    &stats,
+                    pipeline.config.identity_conflict_window_ms,
+                    ConflictKind::Subject,
+                    uas_id,
+                    &jwt_identifier,
+                    pipeline.clock.now(),
+                )
+                .await;
+            }
+
+            let authentication =
+                get_authentication_status(&jwt_identifier, &mut tlm_pool, &stats).await;
+            let sinks = pipeline.sinks.clone();
+            let reporter = Reporter {
+                authentication,
+                session,
+                mission,
+                signal,
+                received,
+                version,
+            };
+            let now = pipeline.clock.now();
+            process_basic_message(jwt_identifier, msg, reporter, &sinks, &identifiers, now).await?;
         }
         MessageType::Location => {
-            let msg = LocationMessage::unpack(&frame.message).map_err(|_| {
-                rest_warn!("could not parse location message.");
+            let msg = LocationMessage::unpack(&frame.message)
+                .map_err(|_| {
+                    rest_warn!("could not parse location message.");
+                    StatusCode::BAD_REQUEST
+                })?
+                .for_version(version);
+
+            let authentication =
+                get_authentication_status(&jwt_identifier, &mut tlm_pool, &stats).await;
+            let reporter = Reporter {
+                authentication,
+                session,
+                mission,
+                signal,
+                received,
+                version,
+            };
+            process_location_message(jwt_identifier, msg, reporter, pipeline).await?;
+        }
+        MessageType::Authentication => {
+            let msg = AuthenticationMessage::unpack(&frame.message).map_err(|_| {
+                rest_warn!("could not parse authentication message.");
                 StatusCode::BAD_REQUEST
             })?;
 
-            process_location_message(jwt_identifier, msg, gis_pool, mq_channel).await?;
+            let policy = &pipeline.degradation;
+            process_authentication_message(&jwt_identifier, msg, tlm_pool, &stats, policy).await?;
+        }
+        MessageType::System | MessageType::OperatorId => {
+            let operator = match frame.header.message_type {
+                MessageType::System => {
+                    let msg = SystemMessage::unpack(&frame.message)
+                        .map_err(|_| {
+                            rest_warn!("could not parse system message.");
+                            StatusCode::BAD_REQUEST
+                        })?
+                        .for_version(version);
+
+                    system_operator(&jwt_identifier, &msg, pipeline.clock.now())
+                }
+                _ => {
+                    let msg = OperatorIdMessage::unpack(&frame.message).map_err(|_| {
+                        rest_warn!("could not parse operator id message.");
+                        StatusCode::BAD_REQUEST
+                    })?;
+
+                    identified_operator(&jwt_identifier, &msg, pipeline.clock.now())?
+                }
+            };
+
+            let authentication =
+                get_authentication_status(&jwt_identifier, &mut tlm_pool, &stats).await;
+            let reporter = Reporter {
+                authentication,
+                session,
+                mission,
+                signal,
+                received,
+                version,
+            };
+            process_operator(operator, reporter, pipeline).await?;
         }
         _ => {
             rest_warn!(
@@ -337,43 +772,514 @@ pub async fn network_remote_id(
         }
     }
 
+    Ok(())
+}
+
+/// Counts the distinct reporters of a decoded remote id packet
+///
+/// A reporter sending the same packet again doesn't count towards the
+///  quorum. If the reporters can't be counted, the report only counts
+///  for itself.
+async fn receive(
+    tlm_pool: &TelemetryPool,
+    stats: &Stats,
+    policy: &DegradationPolicy,
+    payload: &Bytes,
+    packet: &Packet,
+    reporter_id: &str,
+    quorum: u32,
+) -> Result<(u32, Confirmation), StatusCode> {
+    //
+    // BasicMessage is identical throughout the whole flight,
+    //  don't want to toss repeats of the same message
+    if packet
+        .frames
+        .iter()
+        .all(|frame| frame.header.message_type == MessageType::Basic)
+    {
+        stats.record_packet("netrid", false);
+        return Ok((1, Confirmation::Reached));
+    }
+
+    let key = format!("{}:reporters", crate::cache::packet_key("netrid", payload));
+    let mut tlm_pool = tlm_pool.clone();
+    let (added, count) = match tlm_pool
+        .set_add(&key, reporter_id, CACHE_EXPIRE_MS_NETRID)
+        .await
+    {
+        Ok(reporters) => reporters,
+        Err(_) => {
+            rest_warn!("could not count reporters of packet.");
+            stats.record_error(Dependency::Redis);
+            policy.on_failure(Dependency::Redis)?;
+            (true, 1)
+        }
+    };
+
+    let confirmation = Confirmation::of_report(added, count, quorum);
+    match confirmation {
+        Confirmation::Pending => {
+            rest_info!("netrid packet awaiting quorum, reported {count} times.");
+        }
+        Confirmation::Confirmed => {
+            rest_info!("netrid packet already pushed, confirmed {count} times.");
+        }
+        Confirmation::Reached => (), // continue
+    }
+
+    stats.record_packet("netrid", confirmation == Confirmation::Confirmed);
+    Ok((count, confirmation))
+}
+
+/// Known positions of the Location frames of a packet
+fn positions(packet: &Packet) -> Vec<(f64, f64)> {
+    packet
+        .frames
+        .iter()
+        .filter(|frame| frame.header.message_type == MessageType::Location)
+        .filter_map(|frame| LocationMessage::unpack(&frame.message).ok())
+        // 0, 0 is an unknown position
+        .filter(|msg| msg.latitude != 0 || msg.longitude != 0)
+        .map(|msg| (msg.decode_latitude(), msg.decode_longitude()))
+        .collect()
+}
+
+/// Largest distance of the positions of a packet beyond the operating
+///  region of its reporter, None if all are within
+fn out_of_region(geofence: &Geofence, reporter_id: &str, packet: &Packet) -> Option<f64> {
+    positions(packet)
+        .into_iter()
+        .filter_map(|(latitude, longitude)| {
+            geofence.excess_meters(reporter_id, latitude, longitude)
+        })
+        .reduce(f64::max)
+}
+
+/// Quarantines packets positioned outside the operating region of their
+///  reporter, see `REPORTER_REGIONS`
+async fn check_region(
+    pipeline: &Pipeline,
+    reporter_id: &str,
+    payload: &Bytes,
+    packet: &Packet,
+    relayed: bool,
+) -> Result<(), StatusCode> {
+    let Some(excess) = out_of_region(&pipeline.geofence, reporter_id, packet) else {
+        return Ok(());
+    };
+
+    rest_warn!(
+        "quarantined packet of {reporter_id}, positioned {:.1} km outside its region.",
+        excess / 1000.0
+    );
+
+    reporter::record(pipeline, reporter_id, ReporterOutcome::OutOfRegion, relayed).await;
+    let fields = [
+        ("reporter", reporter_id.to_string()),
+        ("payload", hex::encode(payload)),
+        ("excess_meters", format!("{excess:.0}")),
+        ("received", pipeline.clock.now().to_rfc3339()),
+        ("relayed", relayed.to_string()),
+    ];
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    if let Err(e) = tlm_pool
+        .stream_add(QUARANTINE_STREAM, &fields, QUARANTINE_MAX_LEN)
+        .await
+    {
+        rest_warn!("could not quarantine packet: {e}");
+        pipeline.stats.record_error(Dependency::Redis);
+    }
+
+    Err(StatusCode::UNPROCESSABLE_ENTITY)
+}
+
+/// Rejects packets of quarantined reporters, then decodes a packet,
+///  quarantines it if positioned outside the reporter's region, and
+///  counts how often it was reported and its outcome for the reporter
+async fn receive_reported(
+    pipeline: &Pipeline,
+    reporter_id: &str,
+    payload: &Bytes,
+    relayed: bool,
+) -> Result<(Packet, u32, Confirmation), StatusCode> {
+    reporter::check(pipeline, reporter_id).await?;
+
+    let packet = match decode_packet(payload, relayed) {
+        Ok(packet) => packet,
+        Err(code) => {
+            let decoded = Err::<Packet, _>(code);
+            if let Some(outcome) = reporter::outcome(&decoded, ReporterOutcome::DecodeFailure) {
+                reporter::record(pipeline, reporter_id, outcome, relayed).await;
+            }
+
+            return Err(code);
+        }
+    };
+
+    check_region(pipeline, reporter_id, payload, &packet, relayed).await?;
+
+    let quorum = pipeline.config.reporter_quorum;
+    let (count, confirmation) = receive(
+        &pipeline.tlm_pools.netrid,
+        &pipeline.stats,
+        &pipeline.degradation,
+        payload,
+        &packet,
+        reporter_id,
+        quorum,
+    )
+    .await?;
+    if confirmation == Confirmation::Confirmed {
+        let outcome = ReporterOutcome::Duplicate;
+        reporter::record(pipeline, reporter_id, outcome, relayed).await;
+    }
+
+    Ok((packet, count, confirmation))
+}
+
+/// Receives a remote id packet and pushes it to the backends
+pub(crate) async fn handle(
+    pipeline: Pipeline,
+    reporter: Claim,
+    reception: Reception,
+    payload: Bytes,
+    relayed: bool,
+) -> Result<Json<u32>, StatusCode> {
+    let Claim {
+        sub: reporter_id,
+        sid: session,
+        mission,
+        ..
+    } = reporter;
+    let (packet, count, confirmation) =
+        receive_reported(&pipeline, &reporter_id, &payload, relayed).await?;
+    if !packet.has_operator_data() {
+        pipeline
+            .publish_raw(ROUTING_KEY_RAW_NETRID, &payload, &reception)
+            .await;
+    }
+    if confirmation != Confirmation::Reached {
+        return Ok(Json(count));
+    }
+
+    let aircraft = packet.aircraft.unwrap_or_else(|| reporter_id.clone());
+    if relayed {
+        rest_info!("{reporter_id} relayed a packet of {aircraft}.");
+    }
+
+    // Every frame is processed, the first failure is returned
+    let mut result = Ok(());
+    for frame in packet.frames {
+        let processed = process_netrid(
+            aircraft.clone(),
+            session.clone(),
+            mission.clone(),
+            reception.signal,
+            reception.received,
+            frame,
+            pipeline.clone(),
+        )
+        .await;
+        result = result.and(processed);
+    }
+
+    if let Some(outcome) = reporter::outcome(&result, ReporterOutcome::PlausibilityRejection) {
+        reporter::record(&pipeline, &reporter_id, outcome, relayed).await;
+    }
+
+    result.map(|_| Json(count))
+}
+
+/// Processes a quarantined packet as if its reporter was allowed to
+///  report it, without checking its region or counting it again. The
+///  packet keeps the time it was received at before its quarantine.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP and redis backends to test
+pub(crate) async fn replay(
+    pipeline: Pipeline,
+    reporter_id: &str,
+    payload: &[u8],
+    relayed: bool,
+    received: DateTime<Utc>,
+) -> Result<(), StatusCode> {
+    let packet = decode_packet(payload, relayed)?;
+    let aircraft = packet.aircraft.unwrap_or_else(|| reporter_id.to_string());
+    rest_info!("replaying quarantined packet of {aircraft} reported by {reporter_id}.");
+
+    // Every frame is processed, the first failure is returned
+    let mut result = Ok(());
+    for frame in packet.frames {
+        let processed = process_netrid(
+            aircraft.clone(),
+            None,
+            None,
+            None,
+            received,
+            frame,
+            pipeline.clone(),
+        )
+        .await;
+        result = result.and(processed);
+    }
+
+    result
+}
+
+/// Receives a remote id packet and queues its frames for a dispatcher
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub(crate) async fn handle_ingest(
+    pipeline: Pipeline,
+    reporter: Claim,
+    reception: Reception,
+    payload: Bytes,
+    relayed: bool,
+) -> Result<Json<u32>, StatusCode> {
+    let Claim {
+        sub: reporter_id,
+        sid: session,
+        mission,
+        ..
+    } = reporter;
+    let (packet, count, confirmation) =
+        receive_reported(&pipeline, &reporter_id, &payload, relayed).await?;
+    if !packet.has_operator_data() {
+        pipeline
+            .publish_raw(ROUTING_KEY_RAW_NETRID, &payload, &reception)
+            .await;
+    }
+    if confirmation != Confirmation::Reached {
+        return Ok(Json(count));
+    }
+
+    // the contents are checked once dispatched
+    reporter::record(&pipeline, &reporter_id, ReporterOutcome::Valid, relayed).await;
+
+    let aircraft = packet.aircraft.unwrap_or(reporter_id);
+    context::set_aircraft(&aircraft);
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    for frame in packet.frames {
+        let payload = frame.pack().map_err(|_| {
+            rest_warn!("could not pack remote id frame.");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let entry = StreamEntry {
+            payload: payload.to_vec(),
+            identifier: Some(aircraft.clone()),
+            session: session.clone(),
+            mission: mission.clone(),
+            signal: reception.signal,
+            received: Some(reception.received),
+        };
+
+        // the dispatchers can't be reached without the stream
+        enqueue(&mut tlm_pool, &entry).await.map_err(|e| {
+            rest_warn!("could not queue remote id message: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            StatusCode::SERVICE_UNAVAILABLE
+        })?;
+    }
+
+    rest_info!("queued remote id message for dispatch.");
     Ok(Json(count))
 }
 
+/// Strips the broadcast frame of a payload, if the receiver declared
+///  its transport, and attaches the broadcaster to the signal metadata
+fn unwrap_broadcast(
+    headers: &HeaderMap,
+    payload: Bytes,
+    signal: Option<SignalMetadata>,
+) -> Result<(Bytes, Option<SignalMetadata>), StatusCode> {
+    let Some(transport) = headers.get(HEADER_ODID_TRANSPORT) else {
+        return Ok((payload, signal));
+    };
+
+    let transport: Transport = transport
+        .to_str()
+        .ok()
+        .and_then(|transport| transport.parse().ok())
+        .ok_or_else(|| {
+            rest_warn!("invalid {HEADER_ODID_TRANSPORT} header.");
+            StatusCode::BAD_REQUEST
+        })?;
+
+    let broadcast = transport.unwrap(&payload).map_err(|e| {
+        rest_warn!("could not strip {transport:?} frame: {e}.");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let signal = SignalMetadata {
+        broadcaster_mac: Some(broadcast.broadcaster),
+        ..signal.unwrap_or_default()
+    };
+
+    Ok((Bytes::from(broadcast.payload), Some(signal)))
+}
+
+/// Remote ID
+#[utoipa::path(
+    post,
+    path = "/v1/telemetry/netrid",
+    tag = "svc-telemetry",
+    params(SignalHeaders),
+    request_body(
+        content = BinaryPacket,
+        description = "Network Remote ID message or Message Pack, or the broadcast frame carrying it (see `X-Odid-Transport`).",
+        content_type = "application/octet-stream"
+    ),
+    responses(
+        (status = 200, description = "Telemetry received, with the number of times the packet was reported.", body = u32,
+            headers(("x-delivery-receipt" = String, description = "Signed delivery receipt, if asked with `X-Delivery-Receipt: signed`."))),
+        (status = 400, description = "Malformed packet or signal metadata."),
+        (status = 401, description = "Missing or invalid JWT token.", body = ErrorResponse),
+        (status = 403, description = "Reporter quarantined."),
+        (status = 415, description = "Unknown Remote ID protocol version."),
+        (status = 500, description = "Something went wrong."),
+        (status = 503, description = "Dependencies of svc-telemetry were down."),
+    )
+)]
+pub async fn network_remote_id(
+    Extension(pipeline): Extension<Pipeline>,
+    Extension(claim): Extension<Claim>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<Json<u32>, StatusCode> {
+    rest_info!("entry.");
+    let signal = signal_metadata(&headers)?;
+    let (payload, signal) = unwrap_broadcast(&headers, payload, signal)?;
+    let reception = Reception::new(
+        "/telemetry/netrid",
+        Some(claim.sub.clone()),
+        signal,
+        pipeline.clock.now(),
+    );
+    handle(pipeline, claim, reception, payload, false).await
+}
+
+/// Remote ID, queueing the packet for a dispatcher
+///  Serves `/telemetry/netrid` in `ingest` mode, see [`network_remote_id`].
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn network_remote_id_ingest(
+    Extension(pipeline): Extension<Pipeline>,
+    Extension(claim): Extension<Claim>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<Json<u32>, StatusCode> {
+    rest_info!("entry.");
+    let signal = signal_metadata(&headers)?;
+    let (payload, signal) = unwrap_broadcast(&headers, payload, signal)?;
+    let reception = Reception::new(
+        "/telemetry/netrid",
+        Some(claim.sub.clone()),
+        signal,
+        pipeline.clock.now(),
+    );
+    handle_ingest(pipeline, claim, reception, payload, false).await
+}
+
+/// Remote ID observed from another aircraft
+///  The packet must identify the aircraft, either as a Basic message or
+///  a Message Pack containing one. The JWT identifies the relay.
+#[utoipa::path(
+    post,
+    path = "/v1/telemetry/netrid/relay",
+    tag = "svc-telemetry",
+    params(SignalHeaders),
+    request_body(
+        content = BinaryPacket,
+        description = "Network Remote ID message or Message Pack, or the broadcast frame carrying it (see `X-Odid-Transport`).",
+        content_type = "application/octet-stream"
+    ),
+    responses(
+        (status = 200, description = "Telemetry received, with the number of times the packet was reported.", body = u32,
+            headers(("x-delivery-receipt" = String, description = "Signed delivery receipt, if asked with `X-Delivery-Receipt: signed`."))),
+        (status = 400, description = "Malformed packet or signal metadata, or the aircraft is not identified."),
+        (status = 401, description = "Missing or invalid JWT token.", body = ErrorResponse),
+        (status = 403, description = "Relay quarantined."),
+        (status = 415, description = "Unknown Remote ID protocol version."),
+        (status = 500, description = "Something went wrong."),
+        (status = 503, description = "Dependencies of svc-telemetry were down."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP and redis backends to test
+pub async fn network_remote_id_relay(
+    Extension(pipeline): Extension<Pipeline>,
+    Extension(claim): Extension<Claim>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<Json<u32>, StatusCode> {
+    rest_info!("entry.");
+    let signal = signal_metadata(&headers)?;
+    let (payload, signal) = unwrap_broadcast(&headers, payload, signal)?;
+    let reception = Reception::new(
+        "/telemetry/netrid/relay",
+        Some(claim.sub.clone()),
+        signal,
+        pipeline.clock.now(),
+    );
+    handle(pipeline, claim, reception, payload, true).await
+}
+
+/// Remote ID observed from another aircraft, queueing the packet for a dispatcher
+///  Serves `/telemetry/netrid/relay` in `ingest` mode, see [`network_remote_id_relay`].
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn network_remote_id_relay_ingest(
+    Extension(pipeline): Extension<Pipeline>,
+    Extension(claim): Extension<Claim>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<Json<u32>, StatusCode> {
+    rest_info!("entry.");
+    let signal = signal_metadata(&headers)?;
+    let (payload, signal) = unwrap_broadcast(&headers, payload, signal)?;
+    let reception = Reception::new(
+        "/telemetry/netrid/relay",
+        Some(claim.sub.clone()),
+        signal,
+        pipeline.clock.now(),
+    );
+    handle_ingest(pipeline, claim, reception, payload, true).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::{Clock, MockClock};
+    use crate::msg::netrid::Header;
     // use crate::cache::pool::TelemetryPool;
     // use crate::msg::netrid::*;
 
     #[tokio::test]
-    #[cfg(not(feature = "stub_backends"))]
     async fn test_network_remote_id_valid() {
         let mut config = crate::config::Config::default();
         // arbitrary addresses
         config.redis.url = Some("redis://localhost:11111".to_string());
         config.amqp.url = Some("amqp://localhost:5672".to_string());
-        let pools = TelemetryPools {
-            netrid: TelemetryPool::new(config.clone(), "netrid").await.unwrap(),
-            adsb: TelemetryPool::new(config.clone(), "adsb").await.unwrap(),
-        };
-
-        let gis_pool = GisPool::new(config.clone()).await.unwrap();
-        let mq_channel = crate::amqp::init_mq(config.clone()).await.unwrap();
+        let pipeline = super::super::test_pipeline(config.clone()).await;
 
-        let claim = crate::rest::api::jwt::Claim {
+        let claim = Claim {
             iat: 0,
             sub: "test".to_string(),
             exp: 0,
+            sid: None,
+            mission: None,
+            iss: None,
+            aud: None,
+            local: true,
         };
 
         // invalid packet length
         let payload = Bytes::from(vec![0; REMOTE_ID_PACKET_LENGTH - 1]);
         let result = network_remote_id(
-            Extension(pools.clone()),
-            Extension(gis_pool.clone()),
-            Extension(mq_channel.clone()),
+            Extension(pipeline.clone()),
             Extension(claim.clone()),
+            HeaderMap::new(),
             payload,
         )
         .await
@@ -397,10 +1303,9 @@ mod tests {
         };
         let payload = Bytes::from(frame.pack().unwrap().to_vec());
         let result = network_remote_id(
-            Extension(pools.clone()),
-            Extension(gis_pool.clone()),
-            Extension(mq_channel.clone()),
+            Extension(pipeline.clone()),
             Extension(claim.clone()),
+            HeaderMap::new(),
             payload,
         )
         .await
@@ -424,10 +1329,9 @@ mod tests {
         };
         let payload = Bytes::from(frame.pack().unwrap().to_vec());
         let result = network_remote_id(
-            Extension(pools.clone()),
-            Extension(gis_pool.clone()),
-            Extension(mq_channel.clone()),
+            Extension(pipeline.clone()),
             Extension(claim.clone()),
+            HeaderMap::new(),
             payload,
         )
         .await
@@ -437,6 +1341,158 @@ mod tests {
         // assert_eq!(result, Ok(Json(1)));
     }
 
+    fn location_frame(latitude: f64, longitude: f64) -> Frame {
+        let mut msg = LocationMessage::unpack(&[0; 24]).unwrap();
+        msg.latitude = LocationMessage::encode_latitude(latitude);
+        msg.longitude = LocationMessage::encode_longitude(longitude);
+        msg.pressure_altitude = LocationMessage::encode_altitude(120.0);
+        Frame {
+            header: Header {
+                message_type: MessageType::Location,
+                ..Default::default()
+            },
+            message: msg.pack().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_out_of_region() {
+        let geofence = Geofence::new("station-1=52.3,4.8,50");
+        let packet = |frames: Vec<Frame>| Packet {
+            aircraft: None,
+            frames,
+        };
+
+        let near = packet(vec![location_frame(52.4, 4.9)]);
+        assert_eq!(positions(&near).len(), 1);
+        assert_eq!(out_of_region(&geofence, "station-1", &near), None);
+
+        // New York, thousands of km away
+        let far = packet(vec![location_frame(52.4, 4.9), location_frame(40.7, -74.0)]);
+        let excess = out_of_region(&geofence, "station-1", &far).unwrap();
+        assert!(excess > 5_000_000.0);
+
+        // reporters without a region aren't checked
+        assert_eq!(out_of_region(&geofence, "station-2", &far), None);
+
+        // unknown positions and other messages are ignored
+        let unknown = packet(vec![
+            location_frame(0.0, 0.0),
+            Frame {
+                header: Header::default(),
+                message: [0; 24],
+            },
+        ]);
+        assert!(positions(&unknown).is_empty());
+        assert_eq!(out_of_region(&geofence, "station-1", &unknown), None);
+    }
+
+    #[test]
+    fn test_has_operator_data() {
+        let frame = |message_type| Frame {
+            header: Header {
+                message_type,
+                ..Default::default()
+            },
+            message: [0; 24],
+        };
+        let packet = |frames: Vec<Frame>| Packet {
+            aircraft: None,
+            frames,
+        };
+
+        let location = packet(vec![location_frame(52.4, 4.9)]);
+        assert!(!location.has_operator_data());
+        assert!(!packet(vec![frame(MessageType::Basic)]).has_operator_data());
+
+        // alone or relayed with other messages
+        assert!(packet(vec![frame(MessageType::OperatorId)]).has_operator_data());
+        let relayed = packet(vec![location_frame(52.4, 4.9), frame(MessageType::System)]);
+        assert!(relayed.has_operator_data());
+    }
+
+    #[test]
+    fn test_packet_type() {
+        assert_eq!(packet_type(MessageType::Basic), "netrid:basic");
+        assert_eq!(packet_type(MessageType::Location), "netrid:location");
+        assert_eq!(
+            packet_type(MessageType::Authentication),
+            "netrid:authentication"
+        );
+        assert_eq!(packet_type(MessageType::System), "netrid:system");
+        assert_eq!(packet_type(MessageType::OperatorId), "netrid:operator_id");
+        assert_eq!(packet_type(MessageType::MessagePack), "netrid:message_pack");
+        assert_eq!(packet_type(MessageType::SelfId), "netrid:other");
+    }
+
+    #[test]
+    fn test_operator() {
+        let mut operator_id = [0; 20];
+        operator_id[..3].copy_from_slice(b"FIN");
+        let msg = OperatorIdMessage {
+            operator_id_type: 0,
+            operator_id,
+            reserved: [0; 3],
+        };
+        let now = Utc::now();
+        let operator = identified_operator("drone-1", &msg, now).unwrap();
+        assert_eq!(operator.identifier, "drone-1");
+        assert_eq!(operator.operator_id, Some("FIN".to_string()));
+        assert!(!operator.scrubbed);
+
+        let msg = OperatorIdMessage {
+            operator_id: [0; 20],
+            ..msg
+        };
+        assert_eq!(
+            identified_operator("drone-1", &msg, now),
+            Err(StatusCode::BAD_REQUEST)
+        );
+
+        let mut bytes = [0; 24];
+        bytes[1..5].copy_from_slice(&SystemMessage::encode_coordinate(52.5).to_le_bytes());
+        bytes[5..9].copy_from_slice(&SystemMessage::encode_coordinate(-4.5).to_le_bytes());
+        let msg = SystemMessage::unpack(&bytes).unwrap();
+        let operator = system_operator("drone-1", &msg, now);
+        assert_eq!(operator.timestamp_network, now);
+        assert!((operator.latitude.unwrap() - 52.5).abs() < 1e-6);
+        assert!((operator.longitude.unwrap() + 4.5).abs() < 1e-6);
+        assert_eq!(operator.altitude_meters, None);
+        assert_eq!(operator.operator_id, None);
+    }
+
+    #[test]
+    fn test_location_items() {
+        let clock = MockClock::new("2024-05-01T10:00:30Z".parse().unwrap());
+        let mut msg = LocationMessage::unpack(&[0; 24]).unwrap();
+        msg.latitude = LocationMessage::encode_latitude(52.5);
+        msg.longitude = LocationMessage::encode_longitude(13.4);
+        msg.pressure_altitude = LocationMessage::encode_altitude(120.);
+        msg.timestamp =
+            LocationMessage::encode_timestamp("2024-05-01T09:59:50Z".parse().unwrap()).unwrap();
+
+        let (position, velocity) = location_items("drone-1", &msg, clock.now()).unwrap();
+        assert_eq!(position.identifier, "drone-1");
+        assert_eq!(position.timestamp_network, clock.now());
+        assert_eq!(
+            position.timestamp_asset,
+            Some("2024-05-01T09:59:50Z".parse().unwrap())
+        );
+        assert!((position.position.latitude - 52.5).abs() < 1e-6);
+
+        let velocity = velocity.unwrap();
+        assert_eq!(velocity.timestamp_network, clock.now());
+        assert_eq!(velocity.timestamp_asset, position.timestamp_asset);
+
+        clock.advance(lib_common::time::Duration::seconds(20));
+        let (position, _) = location_items("drone-1", &msg, clock.now()).unwrap();
+        assert_eq!(position.timestamp_network, clock.now());
+        assert_eq!(
+            position.timestamp_asset,
+            Some("2024-05-01T09:59:50Z".parse().unwrap())
+        );
+    }
+
     #[test]
     fn test_aircraft_type() {
         assert_eq!(
@@ -504,4 +1560,104 @@ mod tests {
             AircraftType::Other
         );
     }
+
+    #[tokio::test]
+    async fn test_network_remote_id_location() {
+        let config = crate::config::Config {
+            telemetry_sinks: "amqp".to_string(),
+            ..Default::default()
+        };
+        let pipeline = super::super::test_pipeline(config).await;
+        let claim = Claim {
+            iat: 0,
+            sub: "test".to_string(),
+            exp: 0,
+            sid: None,
+            mission: None,
+            iss: None,
+            aud: None,
+            local: true,
+        };
+
+        let mut frame = location_frame(52.37, 4.89);
+        frame.header.protocol_version = ProtocolVersion::F3411_20 as u8;
+        let payload = Bytes::from(frame.pack().unwrap().to_vec());
+        let result = network_remote_id(
+            Extension(pipeline.clone()),
+            Extension(claim.clone()),
+            HeaderMap::new(),
+            payload,
+        )
+        .await;
+        assert_eq!(result.unwrap().0, 1);
+
+        let positions = pipeline
+            .mq_channel
+            .drain(crate::amqp::QUEUE_NAME_NETRID_POSITION)
+            .unwrap();
+        assert_eq!(positions.len(), 1);
+        let position: serde_json::Value = serde_json::from_slice(&positions[0]).unwrap();
+        assert_eq!(position["protocol_version"], 1);
+
+        // a later revision of the standard
+        frame.header.protocol_version = 3;
+        let payload = Bytes::from(frame.pack().unwrap().to_vec());
+        let result = network_remote_id(
+            Extension(pipeline.clone()),
+            Extension(claim),
+            HeaderMap::new(),
+            payload,
+        )
+        .await;
+        assert_eq!(result.unwrap_err(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[test]
+    fn test_unwrap_broadcast() {
+        let message = location_frame(52.37, 4.89).pack().unwrap().to_vec();
+        let signal = SignalMetadata {
+            rssi_dbm: Some(-70.0),
+            ..Default::default()
+        };
+
+        // bare messages are left as posted
+        let payload = Bytes::from(message.clone());
+        let (unwrapped, unchanged) =
+            unwrap_broadcast(&HeaderMap::new(), payload.clone(), Some(signal)).unwrap();
+        assert_eq!(unwrapped, payload);
+        assert_eq!(unchanged, Some(signal));
+
+        // advertiser address, then the ASTM service data with counter 9
+        let mut advertisement = vec![0x5f, 0x4e, 0x3d, 0x2c, 0x1b, 0x0a];
+        advertisement.extend_from_slice(&[0x1E, 0x16, 0xFA, 0xFF, 0x0D, 0x09]);
+        advertisement.extend_from_slice(&message);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_ODID_TRANSPORT, "bluetooth".parse().unwrap());
+        let (unwrapped, signal) =
+            unwrap_broadcast(&headers, Bytes::from(advertisement.clone()), Some(signal)).unwrap();
+        assert_eq!(unwrapped.to_vec(), message);
+
+        let signal = signal.unwrap();
+        assert_eq!(signal.rssi_dbm, Some(-70.0));
+        assert_eq!(
+            signal.broadcaster_mac.map(|mac| mac.to_string()),
+            Some("0a:1b:2c:3d:4e:5f".to_string())
+        );
+
+        let (_, signal) =
+            unwrap_broadcast(&headers, Bytes::from(advertisement.clone()), None).unwrap();
+        assert!(signal.unwrap().broadcaster_mac.is_some());
+
+        assert_eq!(
+            unwrap_broadcast(&headers, Bytes::from(message), None).unwrap_err(),
+            StatusCode::BAD_REQUEST
+        );
+
+        headers.insert(HEADER_ODID_TRANSPORT, "lora".parse().unwrap());
+        assert_eq!(
+            unwrap_broadcast(&headers, Bytes::from(advertisement), None).unwrap_err(),
+            StatusCode::BAD_REQUEST
+        );
+    }
 }
@@ -0,0 +1,317 @@
+//! Open Glider Network REST API
+//!  Gliders and light aircraft broadcast their position with FLARM and
+//!  OGN trackers, relayed by OGN ground stations as APRS sentences.
+
+use super::Pipeline;
+use crate::logging::context;
+use crate::msg::ogn::{self, OgnAircraftType, OgnPosition};
+use crate::msg::track::TrackDecision;
+use crate::sink::{EventData, EventSource, TelemetryEvent};
+use svc_gis_client_grpc::prelude::types::*;
+
+use axum::{extract::Extension, Json};
+use hyper::StatusCode;
+use lib_common::time::{DateTime, Utc};
+
+/// Most sentences accepted per request
+const MAX_SENTENCES: usize = 100;
+
+impl From<OgnAircraftType> for AircraftType {
+    fn from(t: OgnAircraftType) -> Self {
+        match t {
+            OgnAircraftType::Glider | OgnAircraftType::HangGlider | OgnAircraftType::Paraglider => {
+                AircraftType::Glider
+            }
+            OgnAircraftType::TowPlane
+            | OgnAircraftType::DropPlane
+            | OgnAircraftType::PoweredAircraft
+            | OgnAircraftType::JetAircraft => AircraftType::Aeroplane,
+            OgnAircraftType::Helicopter => AircraftType::Rotorcraft,
+            OgnAircraftType::Skydiver => AircraftType::Unpowered,
+            OgnAircraftType::Balloon => AircraftType::Freeballoon,
+            OgnAircraftType::Airship => AircraftType::Airship,
+            OgnAircraftType::StaticObject => AircraftType::Groundobstacle,
+            OgnAircraftType::Unknown => AircraftType::Undeclared,
+            // svc-gis has no type for unmanned aircraft nor ground vehicles
+            OgnAircraftType::Uav | OgnAircraftType::GroundSupport | OgnAircraftType::Ufo => {
+                AircraftType::Other
+            }
+        }
+    }
+}
+
+/// Identification of the aircraft of a beacon
+fn aircraft_id(identifier: &str, beacon: &OgnPosition, now: DateTime<Utc>) -> AircraftId {
+    AircraftId {
+        identifier: Some(identifier.to_string()),
+        session_id: None,
+        aircraft_type: AircraftType::from(beacon.aircraft_type),
+        timestamp_network: now,
+        timestamp_asset: Some(beacon.timestamp),
+    }
+}
+
+/// Position of the aircraft of a beacon
+fn aircraft_position(
+    identifier: &str,
+    beacon: &OgnPosition,
+    now: DateTime<Utc>,
+) -> AircraftPosition {
+    AircraftPosition {
+        identifier: identifier.to_string(),
+        position: Position {
+            latitude: beacon.latitude,
+            longitude: beacon.longitude,
+            altitude_meters: beacon.altitude_meters,
+        },
+        timestamp_network: now,
+        timestamp_asset: Some(beacon.timestamp),
+    }
+}
+
+/// Velocity of the aircraft of a beacon, if it reports its course
+fn aircraft_velocity(
+    identifier: &str,
+    beacon: &OgnPosition,
+    now: DateTime<Utc>,
+) -> Option<AircraftVelocity> {
+    Some(AircraftVelocity {
+        identifier: identifier.to_string(),
+        velocity_horizontal_ground_mps: beacon.ground_speed_mps?,
+        velocity_horizontal_air_mps: None,
+        velocity_vertical_mps: beacon.climb_rate_mps.unwrap_or(0.0),
+        track_angle_degrees: beacon.track_degrees?,
+        timestamp_network: now,
+        timestamp_asset: Some(beacon.timestamp),
+    })
+}
+
+/// Decodes the beacons of a request received at `now`, skipping blank
+///  lines, APRS-IS comments (`#`) and sentences which aren't aircraft beacons
+fn decode_beacons(body: &str, now: DateTime<Utc>) -> Result<Vec<OgnPosition>, StatusCode> {
+    let sentences: Vec<&str> = body
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    if sentences.is_empty() || sentences.len() > MAX_SENTENCES {
+        rest_warn!("received {} ogn sentences.", sentences.len());
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let beacons: Vec<OgnPosition> = sentences
+        .iter()
+        .filter_map(|sentence| {
+            ogn::parse(sentence, now)
+                .inspect_err(|e| rest_debug!("skipped ogn sentence ({e}): {sentence}"))
+                .ok()
+        })
+        .collect();
+
+    match beacons.is_empty() {
+        true => {
+            rest_warn!("no ogn aircraft beacon could be decoded.");
+            Err(StatusCode::BAD_REQUEST)
+        }
+        false => Ok(beacons),
+    }
+}
+
+/// Pushes the identification, position and velocity of a beacon
+///
+/// Returns if it was pushed, beacons of aircraft whose pilot opted out
+///  of tracking are dropped.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP and redis backends to test
+async fn process_beacon(
+    beacon: OgnPosition,
+    now: DateTime<Utc>,
+    pipeline: &Pipeline,
+) -> Result<bool, StatusCode> {
+    pipeline.stats.record_packet("ogn", false);
+    if beacon.no_tracking {
+        rest_debug!("dropped beacon of untracked aircraft {}.", beacon.source);
+        return Ok(false);
+    }
+
+    let identifier = beacon.identifier(&pipeline.identifiers);
+    pipeline.stats.record_aircraft(&identifier);
+    context::set_aircraft(&identifier);
+    context::set_packet_type("ogn:position");
+    if pipeline.admit(&[&identifier], "ogn").await.is_err() {
+        return Ok(false);
+    }
+
+    let sinks = pipeline.sinks.clone();
+    let event = |data: EventData| TelemetryEvent::new(EventSource::Ogn, &identifier, data, now);
+    sinks
+        .push(&event(EventData::Identification(aircraft_id(
+            &identifier,
+            &beacon,
+            now,
+        ))))
+        .await?;
+
+    // the track records the velocity pushed, smoothed
+    let velocity_item = match aircraft_velocity(&identifier, &beacon, now) {
+        Some(item) => Some(
+            pipeline
+                .filters
+                .lock()
+                .map_err(|e| {
+                    rest_error!("could not lock velocity filters: {e}");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?
+                .apply(item),
+        ),
+        None => None,
+    };
+
+    let (decision, velocity_item) = {
+        let mut tracks = pipeline.tracks.lock().map_err(|e| {
+            rest_error!("could not lock tracks: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        // the velocity is ordered on its own, whatever became of the position
+        let decision = tracks.update(aircraft_position(&identifier, &beacon, now), now);
+        let velocity_item = velocity_item.filter(|item| tracks.update_velocity(item.clone()));
+        (decision, velocity_item)
+    };
+
+    match decision {
+        TrackDecision::Accept(item) | TrackDecision::Merge(item) => {
+            sinks.push(&event(EventData::Position(item))).await?;
+            rest_debug!("pushed aircraft position to sinks.");
+        }
+        TrackDecision::Discard => rest_info!("discarded out of order beacon of {identifier}."),
+    }
+
+    if let Some(item) = velocity_item {
+        sinks.push(&event(EventData::Velocity(item))).await?;
+        rest_debug!("pushed aircraft velocity to sinks.");
+    }
+
+    Ok(true)
+}
+
+/// Post OGN (FLARM) aircraft beacons
+///
+/// The body holds APRS sentences, one per line, as relayed by OGN ground
+///  stations. Sentences other than aircraft beacons are skipped.
+#[utoipa::path(
+    post,
+    path = "/v1/telemetry/ogn",
+    tag = "svc-telemetry",
+    request_body(
+        content = String,
+        description = "OGN APRS aircraft beacons, one per line (at most 100).",
+        content_type = "text/plain"
+    ),
+    responses(
+        (status = 200, description = "Beacons received, with the number of beacons pushed.", body = u32),
+        (status = 400, description = "No aircraft beacon could be decoded, or too many sentences."),
+        (status = 500, description = "Something went wrong."),
+        (status = 503, description = "Dependencies of svc-telemetry were down."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP and redis backends to test
+pub async fn ogn(
+    Extension(pipeline): Extension<Pipeline>,
+    body: String,
+) -> Result<Json<u32>, StatusCode> {
+    rest_info!("entry.");
+    let now = pipeline.clock.now();
+    let mut pushed = 0;
+    for beacon in decode_beacons(&body, now)? {
+        if process_beacon(beacon, now, &pipeline).await? {
+            pushed += 1;
+        }
+    }
+
+    Ok(Json(pushed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEACON: &str = "FLRDDA5BA>APRS,qAS,LFMX:/165334h4414.38N/00614.86E'086/007/A=000843 !W70! id0ADDA5BA -019fpm +0.0rot";
+
+    #[test]
+    fn test_decode_beacons() {
+        let body = format!(
+            "# aprsc 2.1.4\n\n{BEACON}\nLFMX>OGNSDR,TCPIP*,qAC,GLIDERN2:/165321h4414.56NI00614.99E&/A=001732\n"
+        );
+        let beacons = decode_beacons(&body, Utc::now()).unwrap();
+        assert_eq!(beacons.len(), 1);
+        assert_eq!(beacons[0].source, "FLRDDA5BA");
+
+        assert_eq!(decode_beacons("", Utc::now()), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(
+            decode_beacons("# comment", Utc::now()),
+            Err(StatusCode::BAD_REQUEST)
+        );
+        assert_eq!(
+            decode_beacons("garbage", Utc::now()),
+            Err(StatusCode::BAD_REQUEST)
+        );
+
+        let body = vec![BEACON; MAX_SENTENCES + 1].join("\n");
+        assert_eq!(
+            decode_beacons(&body, Utc::now()),
+            Err(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    #[test]
+    fn test_items() {
+        let now = Utc::now();
+        let beacon = decode_beacons(BEACON, now).unwrap().remove(0);
+        let id = aircraft_id("FLRDDA5BA", &beacon, now);
+        assert_eq!(id.aircraft_type, AircraftType::Aeroplane);
+        assert_eq!(id.timestamp_network, now);
+        assert_eq!(id.timestamp_asset, Some(beacon.timestamp));
+
+        let position = aircraft_position("FLRDDA5BA", &beacon, now);
+        assert_eq!(position.position.latitude, beacon.latitude);
+        assert_eq!(position.position.altitude_meters, beacon.altitude_meters);
+
+        let velocity = aircraft_velocity("FLRDDA5BA", &beacon, now).unwrap();
+        assert_eq!(velocity.track_angle_degrees, 86.0);
+        assert_eq!(Some(velocity.velocity_vertical_mps), beacon.climb_rate_mps);
+
+        let beacon = OgnPosition {
+            track_degrees: None,
+            ground_speed_mps: None,
+            ..beacon
+        };
+        assert!(aircraft_velocity("FLRDDA5BA", &beacon, now).is_none());
+    }
+
+    #[test]
+    fn test_aircraft_type() {
+        assert_eq!(
+            AircraftType::from(OgnAircraftType::Glider),
+            AircraftType::Glider
+        );
+        assert_eq!(
+            AircraftType::from(OgnAircraftType::Helicopter),
+            AircraftType::Rotorcraft
+        );
+        assert_eq!(
+            AircraftType::from(OgnAircraftType::Balloon),
+            AircraftType::Freeballoon
+        );
+        assert_eq!(
+            AircraftType::from(OgnAircraftType::Uav),
+            AircraftType::Other
+        );
+        assert_eq!(
+            AircraftType::from(OgnAircraftType::Unknown),
+            AircraftType::Undeclared
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! REST API endpoints for the quarantined Remote ID packets
+//!  Packets positioned outside the operating region of their reporter are
+//!  held in a stream, for operators to inspect and replay them once the
+//!  region of the reporter is fixed.
+
+use super::netrid::{self, QUARANTINE_STREAM};
+use super::Pipeline;
+use crate::stats::Dependency;
+use axum::{
+    extract::{Extension, Query},
+    Json,
+};
+use hyper::StatusCode;
+use lib_common::time::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// Packets listed per page if not requested otherwise
+const PAGE_DEFAULT_LIMIT: usize = 20;
+
+/// Most packets listed per page, or replayed per request
+const PAGE_MAX_LIMIT: usize = 100;
+
+/// Packet quarantined by the Remote ID endpoints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct QuarantinedPacket {
+    /// ID of the packet in the quarantine, see [`ReplayRequest`]
+    pub id: String,
+
+    /// Subject of the JWT of the reporter
+    pub reporter: String,
+
+    /// Packet as received, hex encoded
+    pub payload: String,
+
+    /// Distance of the packet beyond the operating region of the reporter
+    pub excess_meters: Option<f64>,
+
+    /// Time the packet was received (RFC 3339)
+    pub received: Option<String>,
+
+    /// If the packet was relayed on behalf of another aircraft
+    pub relayed: bool,
+}
+
+impl QuarantinedPacket {
+    /// Packet from its entry in the quarantine stream, none if malformed
+    fn from_entry((id, mut fields): (String, HashMap<String, String>)) -> Option<Self> {
+        Some(QuarantinedPacket {
+            id,
+            reporter: fields.remove("reporter")?,
+            payload: fields.remove("payload")?,
+            excess_meters: fields.get("excess_meters").and_then(|v| v.parse().ok()),
+            received: fields.remove("received"),
+            relayed: fields.get("relayed").is_some_and(|v| v == "true"),
+        })
+    }
+
+    /// Time the packet was received, or else quarantined according to its
+    ///  entry ID
+    fn received_at(&self) -> Option<DateTime<Utc>> {
+        let received = self
+            .received
+            .as_deref()
+            .and_then(|received| DateTime::parse_from_rfc3339(received).ok());
+
+        match received {
+            Some(received) => Some(received.with_timezone(&Utc)),
+            None => self
+                .id
+                .split_once('-')
+                .and_then(|(ms, _)| ms.parse().ok())
+                .and_then(DateTime::from_timestamp_millis),
+        }
+    }
+}
+
+/// Page of the quarantine listing
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuarantineQuery {
+    /// Only packets following this ID, the `next` ID of the previous page
+    pub after: Option<String>,
+
+    /// Number of packets scanned
+    pub limit: Option<usize>,
+
+    /// Only packets of this reporter
+    pub reporter: Option<String>,
+}
+
+/// Page of quarantined packets, oldest first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct QuarantinePage {
+    /// Packets of the page matching the filter
+    pub packets: Vec<QuarantinedPacket>,
+
+    /// ID to list the next page after, none on the last page
+    pub next: Option<String>,
+}
+
+/// Quarantined packets to replay
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReplayRequest {
+    /// IDs of the packets in the quarantine
+    pub ids: Vec<String>,
+}
+
+/// Outcome of replaying quarantined packets, by packet ID
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReplayResponse {
+    /// Packets processed and removed from the quarantine
+    pub replayed: Vec<String>,
+
+    /// Packets which could not be processed, left in the quarantine
+    pub failed: Vec<String>,
+
+    /// Packets no longer in the quarantine
+    pub missing: Vec<String>,
+}
+
+/// If the ID has the format of a stream entry ID (`<ms>-<seq>`)
+fn valid_id(id: &str) -> bool {
+    id.split_once('-').is_some_and(|(ms, seq)| {
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        digits(ms) && digits(seq)
+    })
+}
+
+/// Number of packets to scan for a page
+fn page_limit(limit: Option<usize>) -> Result<usize, StatusCode> {
+    match limit.unwrap_or(PAGE_DEFAULT_LIMIT) {
+        0 => Err(StatusCode::BAD_REQUEST),
+        limit => Ok(limit.min(PAGE_MAX_LIMIT)),
+    }
+}
+
+/// Page of the scanned entries, filtered by reporter
+///
+/// Filtered pages may hold fewer packets than scanned, the next page
+///  follows the last entry scanned until fewer than `limit` were found.
+fn page(
+    entries: Vec<(String, HashMap<String, String>)>,
+    limit: usize,
+    reporter: Option<&str>,
+) -> QuarantinePage {
+    let next = match entries.len() >= limit {
+        true => entries.last().map(|(id, _)| id.clone()),
+        false => None,
+    };
+
+    let packets = entries
+        .into_iter()
+        .filter_map(QuarantinedPacket::from_entry)
+        .filter(|packet| reporter.is_none_or(|reporter| packet.reporter == reporter))
+        .collect();
+
+    QuarantinePage { packets, next }
+}
+
+/// Quarantined Remote ID packets, oldest first
+#[utoipa::path(
+    get,
+    path = "/v1/admin/quarantine",
+    tag = "svc-telemetry",
+    params(
+        ("after" = Option<String>, Query, description = "Only packets following this ID, the `next` ID of the previous page"),
+        ("limit" = Option<usize>, Query, description = "Number of packets scanned, 20 by default and at most 100"),
+        ("reporter" = Option<String>, Query, description = "Only packets of this reporter"),
+    ),
+    responses(
+        (status = 200, description = "Page of quarantined packets.", body = QuarantinePage),
+        (status = 400, description = "Invalid page."),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+pub async fn quarantine(
+    Extension(pipeline): Extension<Pipeline>,
+    Query(query): Query<QuarantineQuery>,
+) -> Result<Json<QuarantinePage>, StatusCode> {
+    rest_debug!("entry.");
+    let limit = page_limit(query.limit)?;
+    if query.after.as_deref().is_some_and(|id| !valid_id(id)) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let entries = tlm_pool
+        .stream_range(QUARANTINE_STREAM, query.after.as_deref(), limit)
+        .await
+        .map_err(|e| {
+            rest_error!("could not read quarantine: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(page(entries, limit, query.reporter.as_deref())))
+}
+
+/// Replay quarantined Remote ID packets through the pipeline
+///
+/// The packets are processed as if their reporter was allowed to report
+///  them. Replayed packets are removed from the quarantine.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/quarantine/replay",
+    tag = "svc-telemetry",
+    request_body = ReplayRequest,
+    responses(
+        (status = 200, description = "Outcome of each packet.", body = ReplayResponse),
+        (status = 400, description = "No packets, too many packets or invalid IDs."),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP and redis backends to test
+pub async fn replay_quarantine(
+    Extension(pipeline): Extension<Pipeline>,
+    Json(request): Json<ReplayRequest>,
+) -> Result<Json<ReplayResponse>, StatusCode> {
+    rest_debug!("entry.");
+    let ids = request.ids;
+    if ids.is_empty() || ids.len() > PAGE_MAX_LIMIT || !ids.iter().all(|id| valid_id(id)) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let entries = tlm_pool
+        .stream_entries(QUARANTINE_STREAM, &ids)
+        .await
+        .map_err(|e| {
+            rest_error!("could not read quarantine: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let mut response = ReplayResponse {
+        missing: ids
+            .iter()
+            .filter(|id| !entries.iter().any(|(found, _)| found == *id))
+            .cloned()
+            .collect(),
+        ..Default::default()
+    };
+
+    for (id, fields) in entries {
+        let Some(packet) = QuarantinedPacket::from_entry((id.clone(), fields)) else {
+            rest_warn!("malformed quarantined packet {id}.");
+            response.failed.push(id);
+            continue;
+        };
+
+        let Ok(payload) = hex::decode(&packet.payload) else {
+            rest_warn!("could not decode quarantined packet {id}.");
+            response.failed.push(id);
+            continue;
+        };
+
+        let received = packet.received_at().unwrap_or_else(|| pipeline.clock.now());
+        let replayed = netrid::replay(
+            pipeline.clone(),
+            &packet.reporter,
+            &payload,
+            packet.relayed,
+            received,
+        )
+        .await;
+
+        match replayed {
+            Ok(()) => response.replayed.push(id),
+            Err(code) => {
+                rest_warn!("could not replay quarantined packet {id}: {code}.");
+                response.failed.push(id);
+            }
+        }
+    }
+
+    if let Err(e) = tlm_pool
+        .stream_remove(QUARANTINE_STREAM, &response.replayed)
+        .await
+    {
+        rest_warn!("could not remove replayed packets from quarantine: {e}");
+        pipeline.stats.record_error(Dependency::Redis);
+    }
+
+    rest_info!(
+        "replayed {} quarantined packets, {} failed.",
+        response.replayed.len(),
+        response.failed.len()
+    );
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, reporter: &str) -> (String, HashMap<String, String>) {
+        let fields = [
+            ("reporter", reporter),
+            ("payload", "0102"),
+            ("excess_meters", "1500"),
+            ("received", "2024-01-01T00:00:00+00:00"),
+            ("relayed", "true"),
+        ];
+
+        (
+            id.to_string(),
+            fields
+                .iter()
+                .map(|(field, value)| (field.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_from_entry() {
+        let packet = QuarantinedPacket::from_entry(entry("1-0", "reporter")).unwrap();
+        assert_eq!(packet.id, "1-0");
+        assert_eq!(packet.reporter, "reporter");
+        assert_eq!(packet.payload, "0102");
+        assert_eq!(packet.excess_meters, Some(1500.0));
+        assert!(packet.relayed);
+
+        // quarantined before the relayed flag was recorded
+        let (id, mut fields) = entry("1-0", "reporter");
+        fields.remove("relayed");
+        fields.remove("excess_meters");
+        let packet = QuarantinedPacket::from_entry((id.clone(), fields.clone())).unwrap();
+        assert!(!packet.relayed);
+        assert_eq!(packet.excess_meters, None);
+
+        fields.remove("payload");
+        assert!(QuarantinedPacket::from_entry((id, fields)).is_none());
+    }
+
+    #[test]
+    fn test_received_at() {
+        let packet = QuarantinedPacket::from_entry(entry("1700000000000-0", "reporter")).unwrap();
+        assert_eq!(
+            packet.received_at(),
+            DateTime::from_timestamp(1_704_067_200, 0)
+        );
+
+        // quarantined before the receive time was recorded
+        let packet = QuarantinedPacket {
+            received: None,
+            ..packet
+        };
+        assert_eq!(
+            packet.received_at(),
+            DateTime::from_timestamp(1_700_000_000, 0)
+        );
+
+        let packet = QuarantinedPacket {
+            received: Some("garbage".to_string()),
+            id: "garbage".to_string(),
+            ..packet
+        };
+        assert_eq!(packet.received_at(), None);
+    }
+
+    #[test]
+    fn test_valid_id() {
+        assert!(valid_id("1700000000000-0"));
+        assert!(!valid_id("1700000000000"));
+        assert!(!valid_id("-0"));
+        assert!(!valid_id("1-"));
+        assert!(!valid_id("(1-0"));
+        assert!(!valid_id("+"));
+    }
+
+    #[test]
+    fn test_page_limit() {
+        assert_eq!(page_limit(None), Ok(PAGE_DEFAULT_LIMIT));
+        assert_eq!(page_limit(Some(5)), Ok(5));
+        assert_eq!(page_limit(Some(1000)), Ok(PAGE_MAX_LIMIT));
+        assert_eq!(page_limit(Some(0)), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn test_page() {
+        let entries = vec![entry("1-0", "a"), entry("2-0", "b"), entry("3-0", "a")];
+
+        let result = page(entries.clone(), 3, None);
+        assert_eq!(result.packets.len(), 3);
+        assert_eq!(result.next, Some("3-0".to_string()));
+
+        // the last page
+        let result = page(entries.clone(), 5, None);
+        assert_eq!(result.next, None);
+
+        // filtered pages continue after the last entry scanned
+        let result = page(entries[..2].to_vec(), 2, Some("a"));
+        let ids: Vec<&str> = result.packets.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["1-0"]);
+        assert_eq!(result.next, Some("2-0".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_quarantine() {
+        let pipeline = super::super::test_pipeline(crate::Config::default()).await;
+        let query = QuarantineQuery {
+            after: None,
+            limit: None,
+            reporter: None,
+        };
+        let Json(result) = quarantine(Extension(pipeline.clone()), Query(query))
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            QuarantinePage {
+                packets: vec![],
+                next: None
+            }
+        );
+
+        let query = QuarantineQuery {
+            after: Some("garbled".to_string()),
+            limit: None,
+            reporter: None,
+        };
+        let result = quarantine(Extension(pipeline), Query(query)).await;
+        assert_eq!(result.unwrap_err(), StatusCode::BAD_REQUEST);
+    }
+}
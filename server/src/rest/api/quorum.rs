@@ -0,0 +1,83 @@
+//! Reporter quorum of received packets
+//!  The distinct reporters of identical packets are kept in a Redis set
+//!  as they report them. A packet is pushed downstream once, by the report
+//!  of the reporter reaching `REPORTER_QUORUM`. Later reports only confirm
+//!  it, and a reporter sending a packet again never counts twice.
+
+use std::cmp::Ordering;
+
+/// Where a packet stands with respect to the reporter quorum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirmation {
+    /// Fewer reporters than the quorum sent the packet, it isn't pushed yet
+    Pending,
+
+    /// This report reached the quorum, the packet is pushed
+    Reached,
+
+    /// The packet was already pushed, this report only confirms it
+    Confirmed,
+}
+
+impl Confirmation {
+    /// Confirmation of a packet reported `count` times, a quorum of 0
+    ///  being handled as 1
+    pub fn of(count: u32, quorum: u32) -> Self {
+        match count.cmp(&quorum.max(1)) {
+            Ordering::Less => Confirmation::Pending,
+            Ordering::Equal => Confirmation::Reached,
+            Ordering::Greater => Confirmation::Confirmed,
+        }
+    }
+
+    /// Confirmation of a report of a packet now sent by `reporters`
+    ///  distinct reporters, `added` if this reporter wasn't one of them yet
+    pub fn of_report(added: bool, reporters: u32, quorum: u32) -> Self {
+        match Confirmation::of(reporters, quorum) {
+            // the report of this reporter already reached the quorum
+            Confirmation::Reached if !added => Confirmation::Confirmed,
+            confirmation => confirmation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirmation() {
+        assert_eq!(Confirmation::of(1, 1), Confirmation::Reached);
+        assert_eq!(Confirmation::of(2, 1), Confirmation::Confirmed);
+        assert_eq!(Confirmation::of(1, 0), Confirmation::Reached);
+
+        // pushed once, by the report reaching the quorum
+        let confirmations: Vec<Confirmation> =
+            (1..=4).map(|count| Confirmation::of(count, 3)).collect();
+        assert_eq!(
+            confirmations,
+            vec![
+                Confirmation::Pending,
+                Confirmation::Pending,
+                Confirmation::Reached,
+                Confirmation::Confirmed
+            ]
+        );
+    }
+
+    #[test]
+    fn test_confirmation_of_report() {
+        assert_eq!(Confirmation::of_report(true, 2, 2), Confirmation::Reached);
+
+        // the reporter of the packet sending it again
+        assert_eq!(Confirmation::of_report(false, 1, 2), Confirmation::Pending);
+        assert_eq!(
+            Confirmation::of_report(false, 2, 2),
+            Confirmation::Confirmed
+        );
+        assert_eq!(
+            Confirmation::of_report(false, 3, 2),
+            Confirmation::Confirmed
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! Signed delivery receipts of Remote ID packets
+//!
+//! Some regulators require proof that Remote ID data was delivered to the
+//!  USS. Reporters asking for it with the `X-Delivery-Receipt: signed`
+//!  header get a receipt with each packet accepted: a JWS signed with the
+//!  Ed25519 key of the service, holding the digest of the packet, when it
+//!  was received and by which service. The receipt can be stored by the
+//!  aircraft or gateway and verified later with the public key served by
+//!  `/telemetry/receipts/keys`.
+
+use super::jwt::Claim;
+use super::Pipeline;
+use crate::Config;
+use axum::{
+    body::{Body, Bytes},
+    extract::{Extension, OriginalUri},
+    http::{HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse,
+};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lib_common::time::{DateTime, Utc};
+use openssl::pkey::{Id, PKey};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// Header asking for a receipt, and carrying it in the response
+pub const HEADER_DELIVERY_RECEIPT: &str = "x-delivery-receipt";
+
+/// Value of [`HEADER_DELIVERY_RECEIPT`] asking for a signed receipt
+const RECEIPT_SIGNED: &str = "signed";
+
+/// Identity of the service in receipts, without `JWT_ISSUER`
+const DEFAULT_ISSUER: &str = "svc-telemetry";
+
+/// Hex digits of the key digest identifying the receipt key
+const KEY_ID_LENGTH: usize = 16;
+
+/// Why the receipt key could not be loaded
+#[derive(Debug)]
+pub enum ReceiptKeyError {
+    /// The key file could not be read
+    Read(std::io::Error),
+
+    /// The file doesn't hold an Ed25519 private key in PEM
+    Invalid(String),
+}
+
+impl Display for ReceiptKeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReceiptKeyError::Read(e) => write!(f, "could not read key: {e}"),
+            ReceiptKeyError::Invalid(e) => write!(f, "invalid Ed25519 key: {e}"),
+        }
+    }
+}
+
+/// Delivery receipt of a packet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Receipt {
+    /// Service the packet was delivered to
+    pub iss: String,
+
+    /// Reporter of the packet (subject of its token)
+    pub sub: String,
+
+    /// When the receipt was issued, in seconds
+    pub iat: i64,
+
+    /// When the packet was received
+    pub received: DateTime<Utc>,
+
+    /// Hex SHA-256 digest of the packet, as posted
+    pub sha256: String,
+
+    /// Route the packet was posted to
+    pub endpoint: String,
+}
+
+/// Signs the delivery receipts with the key of the service
+#[derive(Clone)]
+pub struct ReceiptSigner {
+    /// Identity of the service
+    issuer: String,
+
+    /// Private key
+    key: EncodingKey,
+
+    /// Public key, as published
+    jwk: Jwk,
+}
+
+impl Debug for ReceiptSigner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReceiptSigner")
+            .field("issuer", &self.issuer)
+            .field("jwk", &self.jwk)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ReceiptSigner {
+    /// Signer of an Ed25519 private key in PEM (PKCS#8)
+    pub fn from_pem(pem: &[u8], issuer: &str) -> Result<Self, ReceiptKeyError> {
+        let invalid = |e: &dyn Display| ReceiptKeyError::Invalid(e.to_string());
+        let private = PKey::private_key_from_pem(pem).map_err(|e| invalid(&e))?;
+        if private.id() != Id::ED25519 {
+            return Err(invalid(&"not an Ed25519 key"));
+        }
+
+        let public = private.raw_public_key().map_err(|e| invalid(&e))?;
+        let key = EncodingKey::from_ed_pem(pem).map_err(|e| invalid(&e))?;
+        let key_id = hex::encode(openssl::sha::sha256(&public));
+
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(KeyAlgorithm::EdDSA),
+                key_id: Some(key_id[..KEY_ID_LENGTH].to_string()),
+                ..Default::default()
+            },
+            algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: base64::Engine::encode(
+                    &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+                    public,
+                ),
+            }),
+        };
+
+        Ok(ReceiptSigner {
+            issuer: issuer.to_string(),
+            key,
+            jwk,
+        })
+    }
+
+    /// Signer of `RECEIPT_KEY_FILE`, if set
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) reads the key file of the deployment
+    pub fn load(config: &Config) -> Result<Option<Self>, ReceiptKeyError> {
+        if config.receipt_key_file.is_empty() {
+            return Ok(None);
+        }
+
+        let issuer = match config.jwt_issuer.trim() {
+            "" => DEFAULT_ISSUER,
+            issuer => issuer,
+        };
+
+        let pem = std::fs::read(&config.receipt_key_file).map_err(ReceiptKeyError::Read)?;
+        Self::from_pem(&pem, issuer).map(Some)
+    }
+
+    /// Public key verifying the receipts
+    pub fn jwk(&self) -> &Jwk {
+        &self.jwk
+    }
+
+    /// Signed receipt of a packet, issued at `now`
+    pub fn sign(
+        &self,
+        reporter: &str,
+        endpoint: &str,
+        payload: &[u8],
+        received: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<String, StatusCode> {
+        let receipt = Receipt {
+            iss: self.issuer.clone(),
+            sub: reporter.to_string(),
+            iat: now.timestamp(),
+            received,
+            sha256: hex::encode(openssl::sha::sha256(payload)),
+            endpoint: endpoint.to_string(),
+        };
+
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = self.jwk.common.key_id.clone();
+        encode(&header, &receipt, &self.key).map_err(|e| {
+            rest_error!("could not sign delivery receipt: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+    }
+}
+
+/// If the request asks for a signed receipt
+fn receipt_requested(request: &Request<Body>) -> bool {
+    request
+        .headers()
+        .get(HEADER_DELIVERY_RECEIPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case(RECEIPT_SIGNED))
+}
+
+/// Adds a signed receipt to the successful responses of the requests
+///  asking for one, if the service has a receipt key
+pub async fn sign(
+    Extension(pipeline): Extension<Pipeline>,
+    Extension(claim): Extension<Claim>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, StatusCode> {
+    let signer = match (&pipeline.receipts, receipt_requested(&request)) {
+        (Some(signer), true) => signer.clone(),
+        _ => return Ok(next.run(request).await),
+    };
+
+    let received = pipeline.clock.now();
+    let endpoint = match request.extensions().get::<OriginalUri>() {
+        Some(OriginalUri(uri)) => uri.path().to_string(),
+        None => request.uri().path().to_string(),
+    };
+    let (parts, body) = request.into_parts();
+    let payload = super::encoding::read_body(body).await?;
+
+    let request = Request::from_parts(parts, Body::from(payload.clone()));
+    let mut response = next.run(request).await;
+    if !response.status().is_success() {
+        return Ok(response);
+    }
+
+    let now = pipeline.clock.now();
+    let receipt = signer.sign(&claim.sub, &endpoint, &payload, received, now)?;
+    let receipt = HeaderValue::from_str(&receipt).map_err(|e| {
+        rest_error!("invalid delivery receipt header: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    response
+        .headers_mut()
+        .insert(HEADER_DELIVERY_RECEIPT, receipt);
+    Ok(response)
+}
+
+/// Keys verifying the delivery receipts
+#[utoipa::path(
+    get,
+    path = "/v1/telemetry/receipts/keys",
+    tag = "svc-telemetry",
+    responses(
+        (status = 200, description = "JSON Web Key Set of the Ed25519 key signing the delivery receipts."),
+        (status = 404, description = "Delivery receipts are not signed by this service."),
+    )
+)]
+pub async fn receipt_keys(
+    Extension(pipeline): Extension<Pipeline>,
+) -> Result<Json<JwkSet>, StatusCode> {
+    let signer = pipeline.receipts.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(JwkSet {
+        keys: vec![signer.jwk().clone()],
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware, routing::post, Router};
+    use jsonwebtoken::{decode, DecodingKey, Validation};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn generated_signer() -> ReceiptSigner {
+        let key = PKey::generate_ed25519().unwrap();
+        let pem = key.private_key_to_pem_pkcs8().unwrap();
+        ReceiptSigner::from_pem(&pem, "svc-telemetry").unwrap()
+    }
+
+    fn verify(signer: &ReceiptSigner, receipt: &str) -> Receipt {
+        let key = DecodingKey::from_jwk(signer.jwk()).unwrap();
+        let mut validation = Validation::new(Algorithm::EdDSA);
+        validation.required_spec_claims.clear();
+        decode(receipt, &key, &validation).unwrap().claims
+    }
+
+    #[test]
+    fn test_from_pem() {
+        let signer = generated_signer();
+        assert_eq!(signer.jwk().common.key_id.as_ref().unwrap().len(), 16);
+
+        assert!(ReceiptSigner::from_pem(b"not a key", "svc-telemetry").is_err());
+
+        // only Ed25519 keys
+        let rsa = openssl::rsa::Rsa::generate(2048).unwrap();
+        let pem = PKey::from_rsa(rsa)
+            .unwrap()
+            .private_key_to_pem_pkcs8()
+            .unwrap();
+        assert!(ReceiptSigner::from_pem(&pem, "svc-telemetry").is_err());
+    }
+
+    #[test]
+    fn test_sign() {
+        let signer = generated_signer();
+        let received = Utc::now();
+        let receipt = signer
+            .sign(
+                "drone-1",
+                "/v1/telemetry/netrid",
+                &[0x02; 25],
+                received,
+                received,
+            )
+            .unwrap();
+
+        let receipt = verify(&signer, &receipt);
+        assert_eq!(receipt.iss, "svc-telemetry");
+        assert_eq!(receipt.sub, "drone-1");
+        assert_eq!(receipt.received, received);
+        assert_eq!(receipt.endpoint, "/v1/telemetry/netrid");
+        assert_eq!(
+            receipt.sha256,
+            hex::encode(openssl::sha::sha256(&[0x02; 25]))
+        );
+
+        // signed by another key
+        let other = generated_signer();
+        let key = DecodingKey::from_jwk(other.jwk()).unwrap();
+        let receipt = other
+            .sign(
+                "drone-1",
+                "/v1/telemetry/netrid",
+                &[0x02; 25],
+                received,
+                received,
+            )
+            .unwrap();
+        let mut validation = Validation::new(Algorithm::EdDSA);
+        validation.required_spec_claims.clear();
+        assert!(decode::<Receipt>(&receipt, &key, &validation).is_ok());
+        let key = DecodingKey::from_jwk(signer.jwk()).unwrap();
+        assert!(decode::<Receipt>(&receipt, &key, &validation).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sign_middleware() {
+        let mut pipeline = crate::rest::api::test_pipeline(Config::default()).await;
+        let signer = generated_signer();
+        pipeline.receipts = Some(Arc::new(signer.clone()));
+        let claim = Claim {
+            sub: "drone-1".to_string(),
+            iat: 0,
+            exp: 0,
+            sid: None,
+            mission: None,
+            iss: None,
+            aud: None,
+            local: true,
+        };
+
+        let router: Router = Router::new()
+            .route("/ok", post(|| async { StatusCode::OK }))
+            .route("/rejected", post(|| async { StatusCode::BAD_REQUEST }))
+            .route_layer(middleware::from_fn(sign))
+            .layer(Extension(claim))
+            .layer(Extension(pipeline));
+
+        let receipt = |path: &str, requested: bool| {
+            let mut request = Request::post(path);
+            if requested {
+                request = request.header(HEADER_DELIVERY_RECEIPT, "signed");
+            }
+
+            let request = request.body(Body::from(vec![0x02; 25])).unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                response
+                    .headers()
+                    .get(HEADER_DELIVERY_RECEIPT)
+                    .map(|value| value.to_str().unwrap().to_string())
+            }
+        };
+
+        let signed = receipt("/ok", true).await.unwrap();
+        let signed = verify(&signer, &signed);
+        assert_eq!(signed.sub, "drone-1");
+        assert_eq!(signed.endpoint, "/ok");
+
+        assert_eq!(receipt("/ok", false).await, None);
+        assert_eq!(receipt("/rejected", true).await, None);
+    }
+}
@@ -0,0 +1,368 @@
+//! Quality scoring of telemetry reporters
+//!
+//! Reporters are identified by the subject of their JWT, or by the
+//!  reporter of their API key (see [`super::api_key`]). Outcomes of their
+//!  packets are counted in Redis, and reporters sending mostly unusable
+//!  packets can be quarantined.
+
+use super::Pipeline;
+use crate::stats::Dependency;
+use axum::{
+    extract::{Extension, Path},
+    Json,
+};
+use hyper::StatusCode;
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// Reporter statistics are forgotten after this long without packets
+const CACHE_EXPIRE_MS_REPORTER: u32 = 3_600_000;
+
+/// Hash field counting all packets of a reporter
+const FIELD_RECEIVED: &str = "received";
+
+/// Hash field counting packets which could not be decoded
+const FIELD_DECODE_FAILURES: &str = "decode_failures";
+
+/// Hash field counting packets with implausible contents
+const FIELD_PLAUSIBILITY_REJECTIONS: &str = "plausibility_rejections";
+
+/// Hash field counting packets positioned outside the reporter's region
+const FIELD_OUT_OF_REGION: &str = "out_of_region";
+
+/// Hash field counting packets already received
+const FIELD_DUPLICATES: &str = "duplicates";
+
+/// Hash field counting packets relayed on behalf of other aircraft
+const FIELD_RELAYED: &str = "relayed";
+
+/// Outcome of a packet sent by a reporter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReporterOutcome {
+    /// The packet was valid
+    Valid,
+
+    /// The packet could not be decoded
+    DecodeFailure,
+
+    /// The packet was decoded but its contents were rejected
+    PlausibilityRejection,
+
+    /// The packet was positioned outside the reporter's operating region
+    OutOfRegion,
+
+    /// The packet was already received
+    Duplicate,
+}
+
+impl ReporterOutcome {
+    /// Hash fields counting this outcome
+    fn fields(self) -> &'static [&'static str] {
+        match self {
+            ReporterOutcome::Valid => &[FIELD_RECEIVED],
+            ReporterOutcome::DecodeFailure => &[FIELD_RECEIVED, FIELD_DECODE_FAILURES],
+            ReporterOutcome::PlausibilityRejection => {
+                &[FIELD_RECEIVED, FIELD_PLAUSIBILITY_REJECTIONS]
+            }
+            ReporterOutcome::OutOfRegion => &[FIELD_RECEIVED, FIELD_OUT_OF_REGION],
+            ReporterOutcome::Duplicate => &[FIELD_RECEIVED, FIELD_DUPLICATES],
+        }
+    }
+}
+
+/// Quality statistics of a reporter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+pub struct ReporterStats {
+    /// Packets received from the reporter
+    pub received: u64,
+
+    /// Packets which could not be decoded
+    pub decode_failures: u64,
+
+    /// Packets with implausible contents
+    pub plausibility_rejections: u64,
+
+    /// Packets positioned outside the reporter's operating region
+    pub out_of_region: u64,
+
+    /// Packets already received from any reporter
+    pub duplicates: u64,
+
+    /// Packets relayed on behalf of other aircraft
+    pub relayed: u64,
+
+    /// Share of packets which could not be used (0.0 - 1.0)
+    pub error_rate: f64,
+
+    /// Share of packets already received (0.0 - 1.0)
+    pub duplicate_ratio: f64,
+
+    /// If packets of the reporter are rejected
+    pub quarantined: bool,
+}
+
+impl ReporterStats {
+    /// Score a reporter from its hash fields
+    pub fn from_fields(fields: &HashMap<String, String>, config: &crate::Config) -> Self {
+        let field = |name: &str| {
+            fields
+                .get(name)
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or(0)
+        };
+
+        let received = field(FIELD_RECEIVED);
+        let decode_failures = field(FIELD_DECODE_FAILURES);
+        let plausibility_rejections = field(FIELD_PLAUSIBILITY_REJECTIONS);
+        let out_of_region = field(FIELD_OUT_OF_REGION);
+        let duplicates = field(FIELD_DUPLICATES);
+        let relayed = field(FIELD_RELAYED);
+
+        let ratio = |count: u64| match received {
+            0 => 0.0,
+            received => count as f64 / received as f64,
+        };
+
+        let error_rate = ratio(decode_failures + plausibility_rejections + out_of_region);
+        let quarantined = config.reporter_quarantine_enabled
+            && received >= config.reporter_min_packets as u64
+            && error_rate > config.reporter_max_error_rate as f64;
+
+        ReporterStats {
+            received,
+            decode_failures,
+            plausibility_rejections,
+            out_of_region,
+            duplicates,
+            relayed,
+            error_rate,
+            duplicate_ratio: ratio(duplicates),
+            quarantined,
+        }
+    }
+}
+
+/// Key of the statistics of a reporter
+fn reporter_key(identifier: &str) -> String {
+    format!("{identifier}:reporter")
+}
+
+/// Outcome of receiving or processing a packet, if attributable to the reporter
+pub(crate) fn outcome<T>(
+    result: &Result<T, StatusCode>,
+    rejected: ReporterOutcome,
+) -> Option<ReporterOutcome> {
+    match result {
+        Ok(_) => Some(ReporterOutcome::Valid),
+        Err(StatusCode::BAD_REQUEST) => Some(rejected),
+        // not the reporter's fault
+        Err(_) => None,
+    }
+}
+
+/// Counts the outcome of a packet sent by a reporter, and if the
+///  reporter relayed it on behalf of another aircraft
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub(crate) async fn record(
+    pipeline: &Pipeline,
+    identifier: &str,
+    outcome: ReporterOutcome,
+    relayed: bool,
+) {
+    let mut fields = outcome.fields().to_vec();
+    if relayed {
+        fields.push(FIELD_RELAYED);
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    if let Err(e) = tlm_pool
+        .hash_increment(&reporter_key(identifier), &fields, CACHE_EXPIRE_MS_REPORTER)
+        .await
+    {
+        rest_warn!("could not record reporter outcome: {e}");
+        pipeline.stats.record_error(Dependency::Redis);
+    }
+}
+
+/// Rejects packets of quarantined reporters
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub(crate) async fn check(pipeline: &Pipeline, identifier: &str) -> Result<(), StatusCode> {
+    if !pipeline.config.reporter_quarantine_enabled {
+        return Ok(());
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let fields = match tlm_pool.hash_get_all(&reporter_key(identifier)).await {
+        Ok(fields) => fields,
+        Err(e) => {
+            // don't reject reporters because the cache is unavailable
+            rest_warn!("could not get reporter statistics: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            return Ok(());
+        }
+    };
+
+    match ReporterStats::from_fields(&fields, &pipeline.config).quarantined {
+        true => {
+            rest_info!("rejected packet of quarantined reporter {identifier}.");
+            Err(StatusCode::FORBIDDEN)
+        }
+        false => Ok(()),
+    }
+}
+
+/// Quality statistics of a reporter
+#[utoipa::path(
+    get,
+    path = "/v1/admin/reporters/{identifier}",
+    tag = "svc-telemetry",
+    params(
+        ("identifier" = String, Path, description = "Subject of the reporter's JWT"),
+    ),
+    responses(
+        (status = 200, description = "Reporter statistics.", body = ReporterStats),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 404, description = "No packets received from the reporter recently."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+pub async fn reporter_stats(
+    Extension(pipeline): Extension<Pipeline>,
+    Path(identifier): Path<String>,
+) -> Result<Json<ReporterStats>, StatusCode> {
+    rest_debug!("entry.");
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let fields = tlm_pool
+        .hash_get_all(&reporter_key(&identifier))
+        .await
+        .map_err(|e| {
+            rest_error!("could not get reporter statistics: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if fields.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(ReporterStats::from_fields(&fields, &pipeline.config)))
+}
+
+/// Reset the statistics of a reporter, lifting its quarantine
+#[utoipa::path(
+    delete,
+    path = "/v1/admin/reporters/{identifier}",
+    tag = "svc-telemetry",
+    params(
+        ("identifier" = String, Path, description = "Subject of the reporter's JWT"),
+    ),
+    responses(
+        (status = 204, description = "Reporter statistics reset."),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+pub async fn reset_reporter(
+    Extension(pipeline): Extension<Pipeline>,
+    Path(identifier): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    rest_debug!("entry.");
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    tlm_pool
+        .delete(&reporter_key(&identifier))
+        .await
+        .map_err(|e| {
+            rest_error!("could not reset reporter statistics: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    rest_info!("reset statistics of reporter {identifier}.");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(values: &[(&str, u64)]) -> HashMap<String, String> {
+        values
+            .iter()
+            .map(|(field, value)| (field.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_reporter_stats() {
+        let mut config = crate::Config {
+            reporter_quarantine_enabled: true,
+            reporter_min_packets: 10,
+            reporter_max_error_rate: 0.5,
+            ..Default::default()
+        };
+
+        let stats = ReporterStats::from_fields(&HashMap::new(), &config);
+        assert_eq!(stats.received, 0);
+        assert_eq!(stats.error_rate, 0.0);
+        assert!(!stats.quarantined);
+
+        let values = fields(&[
+            (FIELD_RECEIVED, 20),
+            (FIELD_DECODE_FAILURES, 6),
+            (FIELD_PLAUSIBILITY_REJECTIONS, 3),
+            (FIELD_OUT_OF_REGION, 2),
+            (FIELD_DUPLICATES, 2),
+            (FIELD_RELAYED, 4),
+        ]);
+        let stats = ReporterStats::from_fields(&values, &config);
+        assert_eq!(stats.error_rate, 0.55);
+        assert_eq!(stats.duplicate_ratio, 0.1);
+        assert_eq!(stats.relayed, 4);
+        assert_eq!(stats.out_of_region, 2);
+        assert!(stats.quarantined);
+
+        // not enough packets to judge
+        config.reporter_min_packets = 21;
+        assert!(!ReporterStats::from_fields(&values, &config).quarantined);
+
+        // quarantine disabled
+        config.reporter_min_packets = 10;
+        config.reporter_quarantine_enabled = false;
+        assert!(!ReporterStats::from_fields(&values, &config).quarantined);
+    }
+
+    #[test]
+    fn test_outcome_fields() {
+        assert_eq!(ReporterOutcome::Valid.fields(), &[FIELD_RECEIVED]);
+        assert!(ReporterOutcome::Duplicate
+            .fields()
+            .contains(&FIELD_DUPLICATES));
+    }
+
+    #[test]
+    fn test_outcome() {
+        let rejected = ReporterOutcome::DecodeFailure;
+        assert_eq!(outcome(&Ok(()), rejected), Some(ReporterOutcome::Valid));
+        assert_eq!(
+            outcome::<()>(&Err(StatusCode::BAD_REQUEST), rejected),
+            Some(rejected)
+        );
+        assert_eq!(
+            outcome::<()>(&Err(StatusCode::INTERNAL_SERVER_ERROR), rejected),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reporter_endpoints() {
+        let pipeline = super::super::test_pipeline(crate::Config::default()).await;
+
+        // the stub cache holds no statistics
+        let result = reporter_stats(Extension(pipeline.clone()), Path("test".to_string())).await;
+        assert_eq!(result.unwrap_err(), StatusCode::NOT_FOUND);
+
+        let result = reset_reporter(Extension(pipeline), Path("test".to_string())).await;
+        assert_eq!(result, Ok(StatusCode::NO_CONTENT));
+    }
+}
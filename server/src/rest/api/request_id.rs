@@ -0,0 +1,72 @@
+//! Request identifiers
+//!
+//! Each request is handled with an identifier, taken from its
+//!  `x-request-id` header or generated, which is returned in the response
+//!  header of the same name and added to JSON log lines.
+
+use crate::logging::context;
+use axum::{
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use rand::{distributions::Alphanumeric, Rng};
+
+/// Header holding the request identifier
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Length of generated request identifiers
+const REQUEST_ID_LENGTH: usize = 16;
+
+/// Longest request identifier accepted from clients
+const REQUEST_ID_MAX_LENGTH: usize = 64;
+
+/// Identifier of a request, from its header if usable
+///
+/// Provided identifiers are only kept if printable without escaping, so
+///  clients can't forge log lines.
+fn request_id(header: Option<&HeaderValue>) -> String {
+    header
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= REQUEST_ID_MAX_LENGTH)
+        .filter(|id| id.bytes().all(|c| c.is_ascii_graphic() && c != b'"'))
+        .map(String::from)
+        .unwrap_or_else(|| {
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(REQUEST_ID_LENGTH)
+                .map(char::from)
+                .collect()
+        })
+}
+
+/// Handles a request in its own log context
+pub async fn request_context<B>(request: Request<B>, next: Next<B>) -> Response {
+    let id = request_id(request.headers().get(REQUEST_ID_HEADER));
+    let mut response = context::scope(id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_id() {
+        let id = request_id(Some(&HeaderValue::from_static("req-1")));
+        assert_eq!(id, "req-1");
+
+        let generated = request_id(None);
+        assert_eq!(generated.len(), REQUEST_ID_LENGTH);
+        assert!(generated.chars().all(|c| c.is_ascii_alphanumeric()));
+
+        for invalid in ["", "two words", "quote\"d", &"x".repeat(65)] {
+            let id = request_id(Some(&HeaderValue::from_str(invalid).unwrap()));
+            assert_eq!(id.len(), REQUEST_ID_LENGTH);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Login sessions per aircraft identifier
+//!
+//! The last session opened by each identifier is kept in the cache until
+//!  its token expires. Depending on `SESSION_POLICY`, logging in with an
+//!  identifier which has an active session is rejected, or invalidates the
+//!  tokens of the previous session. Under the `reject` policy, a login
+//!  claims the identifier in a key set only if absent, expiring with its
+//!  token, so of concurrent logins on any instance only one succeeds.
+
+use super::jwt::{Claim, JWT_EXPIRE_SECONDS};
+use super::Pipeline;
+use crate::config::SessionPolicy;
+use crate::stats::Dependency;
+use axum::{extract::Extension, middleware::Next, response::Response};
+use hyper::{Request, StatusCode};
+use lib_common::time::{DateTime, Utc};
+
+/// Sessions expire with the tokens issued for them
+const CACHE_EXPIRE_MS_SESSION: u32 = JWT_EXPIRE_SECONDS as u32 * 1000;
+
+/// Hash field holding the active session of an identifier
+const FIELD_SESSION: &str = "sid";
+
+/// Hash field holding when the identifier was last seen (milliseconds)
+const FIELD_LAST_SEEN: &str = "last_seen";
+
+/// Whether a login may open a session, given the active session
+fn admit(policy: SessionPolicy, active: Option<&str>, sid: &str) -> Result<(), StatusCode> {
+    match (policy, active) {
+        (SessionPolicy::Reject, Some(active)) if active != sid => Err(StatusCode::CONFLICT),
+        _ => Ok(()),
+    }
+}
+
+/// Whether a token of the given session may still be used
+fn is_current(policy: SessionPolicy, active: Option<&str>, sid: Option<&str>) -> bool {
+    match (policy, active) {
+        (SessionPolicy::Replace, Some(active)) => sid == Some(active),
+        _ => true,
+    }
+}
+
+/// Cache key of the sessions of an identifier
+fn key(identifier: &str) -> String {
+    format!("{identifier}:session")
+}
+
+/// Cache key of the claim of an identifier by its session, under the
+///  `reject` policy
+fn claim_key(identifier: &str) -> String {
+    format!("{identifier}:session:claim")
+}
+
+/// Claims an identifier for a new session, failing if another session
+///  holds it
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+async fn claim(pipeline: &Pipeline, identifier: &str) -> Result<(), StatusCode> {
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let claimed = tlm_pool
+        .set_if_absent(&claim_key(identifier), CACHE_EXPIRE_MS_SESSION)
+        .await
+        .map_err(|e| {
+            rest_warn!("could not claim session of {identifier}: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            StatusCode::SERVICE_UNAVAILABLE
+        })?;
+
+    match claimed {
+        true => Ok(()),
+        false => {
+            rest_warn!("login of {identifier} rejected, a session is active.");
+            Err(StatusCode::CONFLICT)
+        }
+    }
+}
+
+/// Active session of an identifier
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+async fn active(pipeline: &Pipeline, identifier: &str) -> Result<Option<String>, StatusCode> {
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    tlm_pool
+        .hash_get_all(&key(identifier))
+        .await
+        .map(|mut fields| fields.remove(FIELD_SESSION))
+        .map_err(|e| {
+            rest_warn!("could not get session of {identifier}: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            StatusCode::SERVICE_UNAVAILABLE
+        })
+}
+
+/// Opens the session of a login, if the policy allows it
+///
+/// The session is stored under every policy. Only under the `allow`
+///  policy is a cache failure tolerated, the login succeeding without a
+///  stored session; the other policies refuse the login.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub(crate) async fn open(pipeline: &Pipeline, claim: &Claim) -> Result<(), StatusCode> {
+    let policy = pipeline.config.session_policy;
+    let sid = claim.sid.clone().unwrap_or_default();
+    let enforced = policy != SessionPolicy::Allow;
+
+    if policy == SessionPolicy::Reject {
+        // sessions opened before logins claimed their identifier
+        let active = active(pipeline, &claim.sub).await?;
+        admit(policy, active.as_deref(), &sid).inspect_err(|_| {
+            rest_warn!("login of {} rejected, a session is active.", claim.sub);
+        })?;
+
+        self::claim(pipeline, &claim.sub).await?;
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let result = tlm_pool
+        .hash_set(
+            &key(&claim.sub),
+            FIELD_SESSION,
+            &sid,
+            CACHE_EXPIRE_MS_SESSION,
+        )
+        .await;
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            rest_warn!("could not store session of {}: {e}", claim.sub);
+            pipeline.stats.record_error(Dependency::Redis);
+            if policy == SessionPolicy::Reject {
+                // the failed login must not keep the identifier claimed
+                let _ = tlm_pool.delete(&claim_key(&claim.sub)).await;
+            }
+
+            match enforced {
+                true => Err(StatusCode::SERVICE_UNAVAILABLE),
+                false => Ok(()),
+            }
+        }
+    }
+}
+
+/// Milliseconds until a token expires, at least 1
+fn remaining_ms(claim: &Claim, now: DateTime<Utc>) -> u32 {
+    let remaining = (claim.exp as i64 - now.timestamp()).saturating_mul(1000);
+    remaining.clamp(1, u32::MAX as i64) as u32
+}
+
+/// Records that the identifier of a token is online, keeping its session
+///  until the token expires
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub(crate) async fn touch(pipeline: &Pipeline, claim: &Claim) -> Result<(), StatusCode> {
+    let now = pipeline.clock.now();
+    let mut fields = vec![(
+        FIELD_LAST_SEEN.to_string(),
+        now.timestamp_millis().to_string(),
+    )];
+
+    if let Some(sid) = &claim.sid {
+        fields.push((FIELD_SESSION.to_string(), sid.clone()));
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    tlm_pool
+        .hash_set_multiple(&key(&claim.sub), &fields, remaining_ms(claim, now))
+        .await
+        .or_else(|e| {
+            rest_warn!("could not refresh session of {}: {e}", claim.sub);
+            pipeline.stats.record_error(Dependency::Redis);
+            pipeline.degradation.on_failure(Dependency::Redis)
+        })
+}
+
+/// Rejects tokens of sessions replaced by a later login
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn validate<B>(
+    Extension(pipeline): Extension<Pipeline>,
+    Extension(claim): Extension<Claim>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    let policy = pipeline.config.session_policy;
+    if policy != SessionPolicy::Replace {
+        return Ok(next.run(req).await);
+    }
+
+    let active = active(&pipeline, &claim.sub).await?;
+    if !is_current(policy, active.as_deref(), claim.sid.as_deref()) {
+        rest_info!("token of a replaced session of {} refused.", claim.sub);
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admit() {
+        assert_eq!(admit(SessionPolicy::Allow, Some("a"), "b"), Ok(()));
+        assert_eq!(admit(SessionPolicy::Replace, Some("a"), "b"), Ok(()));
+        assert_eq!(admit(SessionPolicy::Reject, None, "b"), Ok(()));
+        assert_eq!(admit(SessionPolicy::Reject, Some("b"), "b"), Ok(()));
+        assert_eq!(
+            admit(SessionPolicy::Reject, Some("a"), "b"),
+            Err(StatusCode::CONFLICT)
+        );
+    }
+
+    #[test]
+    fn test_is_current() {
+        assert!(is_current(SessionPolicy::Allow, Some("a"), Some("b")));
+        assert!(is_current(SessionPolicy::Reject, Some("a"), None));
+        assert!(is_current(SessionPolicy::Replace, None, Some("b")));
+        assert!(is_current(SessionPolicy::Replace, Some("b"), Some("b")));
+        assert!(!is_current(SessionPolicy::Replace, Some("a"), Some("b")));
+
+        // tokens issued before sessions were tracked
+        assert!(!is_current(SessionPolicy::Replace, Some("a"), None));
+    }
+
+    #[test]
+    fn test_remaining_ms() {
+        let now = Utc::now();
+        let claim = Claim {
+            sub: "drone-1".to_string(),
+            iat: now.timestamp() as usize,
+            exp: now.timestamp() as usize + 300,
+            sid: None,
+            mission: None,
+            iss: None,
+            aud: None,
+            local: true,
+        };
+        assert_eq!(remaining_ms(&claim, now), 300_000);
+
+        let expired = Claim {
+            exp: now.timestamp() as usize - 1,
+            ..claim
+        };
+        assert_eq!(remaining_ms(&expired, now), 1);
+    }
+}
@@ -0,0 +1,174 @@
+//! Signal metadata declared by receivers
+//!  Receivers may describe how they received a packet with optional
+//!  request headers: their location, the signal strength and the signal
+//!  to noise ratio. Invalid values reject the request.
+
+use crate::amqp::envelope::SignalMetadata;
+use axum::http::HeaderMap;
+use hyper::StatusCode;
+use serde::Deserialize;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use utoipa::IntoParams;
+
+/// Request header holding the latitude of the receiver in degrees
+pub const HEADER_RECEIVER_LATITUDE: &str = "x-receiver-lat";
+
+/// Request header holding the longitude of the receiver in degrees
+pub const HEADER_RECEIVER_LONGITUDE: &str = "x-receiver-lon";
+
+/// Request header holding the received signal strength in dBm
+pub const HEADER_RSSI: &str = "x-rssi";
+
+/// Request header holding the signal to noise ratio in dB
+pub const HEADER_SNR: &str = "x-snr";
+
+/// Request header naming the transport of a forwarded Remote ID
+///  broadcast frame
+pub const HEADER_ODID_TRANSPORT: &str = "x-odid-transport";
+
+/// Latitudes in degrees
+const LATITUDE_RANGE_DEGREES: RangeInclusive<f64> = -90.0..=90.0;
+
+/// Longitudes in degrees
+const LONGITUDE_RANGE_DEGREES: RangeInclusive<f64> = -180.0..=180.0;
+
+/// Plausible received signal strengths in dBm
+const RSSI_RANGE_DBM: RangeInclusive<f32> = -150.0..=0.0;
+
+/// Plausible signal to noise ratios in dB
+const SNR_RANGE_DB: RangeInclusive<f32> = -30.0..=100.0;
+
+/// Signal metadata request headers, as documented in the API
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Header)]
+pub struct SignalHeaders {
+    /// Latitude of the receiver in degrees, requires `X-Receiver-Lon`
+    #[serde(rename = "X-Receiver-Lat")]
+    pub receiver_latitude: Option<f64>,
+
+    /// Longitude of the receiver in degrees, requires `X-Receiver-Lat`
+    #[serde(rename = "X-Receiver-Lon")]
+    pub receiver_longitude: Option<f64>,
+
+    /// Received signal strength in dBm, from -150 to 0
+    #[serde(rename = "X-Rssi")]
+    pub rssi_dbm: Option<f32>,
+
+    /// Signal to noise ratio in dB, from -30 to 100
+    #[serde(rename = "X-Snr")]
+    pub snr_db: Option<f32>,
+
+    /// Remote ID endpoints only: transport of the broadcast frame posted
+    ///  instead of the bare messages, `bluetooth` (advertiser address
+    ///  followed by the advertising data) or `wifi-nan` (service
+    ///  discovery frame). The broadcaster MAC is attached to the signal
+    ///  metadata.
+    #[serde(rename = "X-Odid-Transport")]
+    pub odid_transport: Option<String>,
+}
+
+/// Value of an optional header, rejected if unreadable or out of range
+fn header<T>(
+    headers: &HeaderMap,
+    name: &str,
+    range: RangeInclusive<T>,
+) -> Result<Option<T>, StatusCode>
+where
+    T: FromStr + PartialOrd,
+{
+    let Some(value) = headers.get(name) else {
+        return Ok(None);
+    };
+
+    let value = value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().parse::<T>().ok())
+        .filter(|value| range.contains(value));
+
+    match value {
+        Some(value) => Ok(Some(value)),
+        None => {
+            rest_warn!("invalid {name} header.");
+            Err(StatusCode::BAD_REQUEST)
+        }
+    }
+}
+
+/// Signal metadata of a request, if any header declares it
+///
+/// The receiver location requires both its latitude and longitude.
+pub fn signal_metadata(headers: &HeaderMap) -> Result<Option<SignalMetadata>, StatusCode> {
+    let signal = SignalMetadata {
+        receiver_latitude: header(headers, HEADER_RECEIVER_LATITUDE, LATITUDE_RANGE_DEGREES)?,
+        receiver_longitude: header(headers, HEADER_RECEIVER_LONGITUDE, LONGITUDE_RANGE_DEGREES)?,
+        rssi_dbm: header(headers, HEADER_RSSI, RSSI_RANGE_DBM)?,
+        snr_db: header(headers, HEADER_SNR, SNR_RANGE_DB)?,
+        // taken from forwarded Remote ID broadcast frames, not from a header
+        broadcaster_mac: None,
+    };
+
+    if signal.receiver_latitude.is_some() != signal.receiver_longitude.is_some() {
+        rest_warn!("receiver location needs both latitude and longitude.");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    Ok((signal != SignalMetadata::default()).then_some(signal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(values: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in values {
+            headers.insert(*name, value.parse().unwrap());
+        }
+
+        headers
+    }
+
+    #[test]
+    fn test_signal_metadata() {
+        assert_eq!(signal_metadata(&HeaderMap::new()), Ok(None));
+
+        let signal = signal_metadata(&headers(&[
+            ("X-Receiver-Lat", "52.37"),
+            ("X-Receiver-Lon", "-4.9"),
+            ("X-Rssi", " -81.5"),
+            ("X-Snr", "12"),
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(signal.receiver_latitude, Some(52.37));
+        assert_eq!(signal.receiver_longitude, Some(-4.9));
+        assert_eq!(signal.rssi_dbm, Some(-81.5));
+        assert_eq!(signal.snr_db, Some(12.0));
+
+        let signal = signal_metadata(&headers(&[("X-Rssi", "-60")]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(signal.receiver_latitude, None);
+        assert_eq!(signal.rssi_dbm, Some(-60.0));
+    }
+
+    #[test]
+    fn test_signal_metadata_invalid() {
+        for values in [
+            vec![("X-Receiver-Lat", "91"), ("X-Receiver-Lon", "0")],
+            vec![("X-Receiver-Lat", "0"), ("X-Receiver-Lon", "NaN")],
+            vec![("X-Receiver-Lat", "52.37")],
+            vec![("X-Rssi", "12")],
+            vec![("X-Rssi", "strong")],
+            vec![("X-Snr", "")],
+        ] {
+            assert_eq!(
+                signal_metadata(&headers(&values)),
+                Err(StatusCode::BAD_REQUEST),
+                "{values:?}"
+            );
+        }
+    }
+}
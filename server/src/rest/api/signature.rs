@@ -0,0 +1,135 @@
+//! Verification of Remote ID authentication messages
+//!  Deployments register a [`SignatureVerifier`] to check UAS ID
+//!  signatures. Without one, every aircraft is reported as unverified.
+
+use crate::msg::netrid::AuthenticationSignature;
+use lapin::types::{AMQPValue, FieldTable, LongString};
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+use tokio::sync::OnceCell;
+
+/// AMQP header carrying the authentication status of the aircraft
+pub const AMQP_HEADER_AUTHENTICATION: &str = "authentication";
+
+/// Verifier used for all authentication messages, see [`verifier`]
+pub static SIGNATURE_VERIFIER: OnceCell<Box<dyn SignatureVerifier>> = OnceCell::const_new();
+
+/// Authentication status of an aircraft
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AuthenticationStatus {
+    /// No valid signature has been received from this aircraft
+    Unverified,
+
+    /// The aircraft provided a signature accepted by the verifier
+    Verified,
+}
+
+impl Display for AuthenticationStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthenticationStatus::Unverified => write!(f, "unverified"),
+            AuthenticationStatus::Verified => write!(f, "verified"),
+        }
+    }
+}
+
+impl std::str::FromStr for AuthenticationStatus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unverified" => Ok(AuthenticationStatus::Unverified),
+            "verified" => Ok(AuthenticationStatus::Verified),
+            _ => Err(()),
+        }
+    }
+}
+
+impl AuthenticationStatus {
+    /// AMQP message properties announcing this status to consumers
+    pub fn amqp_properties(&self) -> lapin::BasicProperties {
+        let mut headers = FieldTable::default();
+        headers.insert(
+            AMQP_HEADER_AUTHENTICATION.into(),
+            AMQPValue::LongString(LongString::from(self.to_string())),
+        );
+
+        lapin::BasicProperties::default().with_headers(headers)
+    }
+}
+
+/// Hook for verifying authentication data sent by an aircraft
+pub trait SignatureVerifier: Send + Sync + fmt::Debug {
+    /// Verify a signature assembled from an aircraft's authentication pages
+    ///
+    /// `identifier` is the identifier the reporting aircraft was authorized with.
+    fn verify(&self, identifier: &str, signature: &AuthenticationSignature)
+        -> AuthenticationStatus;
+}
+
+/// Default verifier, which can't validate any signature
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoVerifier;
+
+impl SignatureVerifier for NoVerifier {
+    fn verify(
+        &self,
+        _identifier: &str,
+        _signature: &AuthenticationSignature,
+    ) -> AuthenticationStatus {
+        AuthenticationStatus::Unverified
+    }
+}
+
+/// The registered verifier, or [`NoVerifier`] if none was set
+pub fn verifier() -> &'static dyn SignatureVerifier {
+    static DEFAULT: NoVerifier = NoVerifier;
+    match SIGNATURE_VERIFIER.get() {
+        Some(verifier) => verifier.as_ref(),
+        None => &DEFAULT,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::netrid::UaAuthenticationType;
+    use lib_common::time::Utc;
+
+    #[test]
+    fn test_authentication_status_from_str() {
+        for status in [
+            AuthenticationStatus::Unverified,
+            AuthenticationStatus::Verified,
+        ] {
+            assert_eq!(status.to_string().parse(), Ok(status));
+        }
+
+        assert!("invalid".parse::<AuthenticationStatus>().is_err());
+    }
+
+    #[test]
+    fn test_amqp_properties() {
+        let properties = AuthenticationStatus::Verified.amqp_properties();
+        let headers = properties.headers().clone().unwrap();
+        let value = headers.inner().get(AMQP_HEADER_AUTHENTICATION).unwrap();
+        assert_eq!(
+            value,
+            &AMQPValue::LongString(LongString::from("verified".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_default_verifier() {
+        let signature = AuthenticationSignature {
+            authentication_type: UaAuthenticationType::UasIdSignature,
+            timestamp: Utc::now(),
+            data: vec![0; 17],
+        };
+
+        assert_eq!(
+            verifier().verify("test", &signature),
+            AuthenticationStatus::Unverified
+        );
+    }
+}
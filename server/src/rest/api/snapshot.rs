@@ -0,0 +1,40 @@
+//! REST API endpoint for the snapshot of the tracked aircraft
+
+use super::Pipeline;
+use crate::cache::snapshot::read_snapshot;
+use crate::msg::track::TrackSnapshot;
+use crate::stats::Dependency;
+use axum::{extract::Extension, Json};
+use hyper::StatusCode;
+
+/// Latest state of the aircraft tracked by all instances
+///
+/// Downstream services fetch the current picture on startup instead of
+///  waiting for new packets.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/snapshot",
+    tag = "svc-telemetry",
+    responses(
+        (status = 200, description = "Last position and velocity of each tracked aircraft, sorted by identifier."),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn snapshot(
+    Extension(pipeline): Extension<Pipeline>,
+) -> Result<Json<Vec<TrackSnapshot>>, StatusCode> {
+    rest_debug!("entry.");
+    let mut tlm_pool = pipeline.snapshot_pool.clone();
+    let snapshot = read_snapshot(&mut tlm_pool, pipeline.clock.now())
+        .await
+        .map_err(|e| {
+            rest_error!("could not read snapshot: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(snapshot))
+}
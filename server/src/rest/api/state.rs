@@ -0,0 +1,381 @@
+//! Combined state vectors of ADS-B aircraft
+//!
+//! Identification, position, velocity, target state and operational
+//!  status arrive in separate extended squitters. The last of each is kept per aircraft in
+//!  the cache, and the combined state is published when a new position
+//!  is decoded, at most once per configured interval.
+
+use crate::amqp::envelope::TelemetryEnvelope;
+use crate::cache::pool::{CacheError, TelemetryPool};
+use crate::msg::adsb::{AircraftState, OperationalStatus, TargetState};
+use crate::stats::{Dependency, Stats};
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use svc_gis_client_grpc::prelude::types::{AircraftPosition, AircraftVelocity};
+
+/// State entries in the cache expire after 10 minutes without updates
+const CACHE_EXPIRE_MS_STATE: u32 = 600000;
+
+/// Hash field of the cached callsign
+const FIELD_CALLSIGN: &str = "callsign";
+
+/// Hash field of the cached emitter category
+const FIELD_CATEGORY: &str = "category";
+
+/// Hash field of the cached position (JSON)
+const FIELD_POSITION: &str = "position";
+
+/// Hash field of the cached velocity (JSON)
+const FIELD_VELOCITY: &str = "velocity";
+
+/// Hash field of the cached target state (JSON)
+const FIELD_TARGET_STATE: &str = "target_state";
+
+/// Hash field of the cached operational status (JSON)
+const FIELD_OPERATIONAL_STATUS: &str = "operational_status";
+
+/// Hash field of when the state was last published (milliseconds)
+const FIELD_PUBLISHED: &str = "published";
+
+/// Hash field of when an identity conflict was last detected (milliseconds)
+const FIELD_CONTESTED: &str = "contested";
+
+/// Hash fields cached from the extended squitters of an aircraft
+const SQUITTER_FIELDS: [&str; 6] = [
+    FIELD_CALLSIGN,
+    FIELD_CATEGORY,
+    FIELD_POSITION,
+    FIELD_VELOCITY,
+    FIELD_TARGET_STATE,
+    FIELD_OPERATIONAL_STATUS,
+];
+
+/// Message received about an aircraft
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Update {
+    /// Identification message
+    Identification {
+        /// Callsign of the aircraft
+        callsign: String,
+
+        /// Raw emitter category, e.g. `A3`
+        category: String,
+    },
+
+    /// Decoded airborne position
+    Position(AircraftPosition),
+
+    /// Decoded airborne velocity
+    Velocity(AircraftVelocity),
+
+    /// Target state and status message
+    TargetState(TargetState),
+
+    /// Operational status message
+    OperationalStatus(OperationalStatus),
+}
+
+/// Hash fields to cache for an update
+fn fields(update: &Update) -> Result<Vec<(String, String)>, serde_json::Error> {
+    let fields = match update {
+        Update::Identification { callsign, category } => vec![
+            (FIELD_CALLSIGN.to_string(), callsign.clone()),
+            (FIELD_CATEGORY.to_string(), category.clone()),
+        ],
+        Update::Position(position) => {
+            vec![(FIELD_POSITION.to_string(), serde_json::to_string(position)?)]
+        }
+        Update::Velocity(velocity) => {
+            vec![(FIELD_VELOCITY.to_string(), serde_json::to_string(velocity)?)]
+        }
+        Update::TargetState(state) => {
+            vec![(
+                FIELD_TARGET_STATE.to_string(),
+                serde_json::to_string(state)?,
+            )]
+        }
+        Update::OperationalStatus(status) => vec![(
+            FIELD_OPERATIONAL_STATUS.to_string(),
+            serde_json::to_string(status)?,
+        )],
+    };
+
+    Ok(fields)
+}
+
+/// Cached JSON field, ignored if unreadable
+fn cached_json<T: DeserializeOwned>(cached: &HashMap<String, String>, field: &str) -> Option<T> {
+    cached
+        .get(field)
+        .and_then(|value| serde_json::from_str(value).ok())
+}
+
+/// Merges an update with the cached state of an aircraft
+fn merge(
+    identifier: &str,
+    cached: &HashMap<String, String>,
+    update: &Update,
+    now: DateTime<Utc>,
+) -> AircraftState {
+    let mut state = AircraftState {
+        identifier: identifier.to_string(),
+        callsign: cached.get(FIELD_CALLSIGN).cloned(),
+        emitter_category: cached.get(FIELD_CATEGORY).cloned(),
+        position: cached_json(cached, FIELD_POSITION),
+        velocity: cached_json(cached, FIELD_VELOCITY),
+        target_state: cached_json(cached, FIELD_TARGET_STATE),
+        operational_status: cached_json(cached, FIELD_OPERATIONAL_STATUS),
+        contested: cached.contains_key(FIELD_CONTESTED),
+        timestamp_network: now,
+    };
+
+    match update {
+        Update::Identification { callsign, category } => {
+            state.callsign = Some(callsign.clone());
+            state.emitter_category = Some(category.clone());
+        }
+        Update::Position(position) => state.position = Some(position.clone()),
+        Update::Velocity(velocity) => state.velocity = Some(velocity.clone()),
+        Update::TargetState(target_state) => state.target_state = Some(*target_state),
+        Update::OperationalStatus(status) => state.operational_status = Some(*status),
+    }
+
+    state
+}
+
+/// If the state must be published, on positions received at least
+///  `interval_ms` after the last publication
+fn is_due(
+    cached: &HashMap<String, String>,
+    update: &Update,
+    now: DateTime<Utc>,
+    interval_ms: u32,
+) -> bool {
+    if !matches!(update, Update::Position(_)) {
+        return false;
+    }
+
+    let interval = Duration::try_milliseconds(interval_ms as i64).unwrap_or(Duration::zero());
+    cached
+        .get(FIELD_PUBLISHED)
+        .and_then(|published| published.parse::<i64>().ok())
+        .and_then(DateTime::from_timestamp_millis)
+        .is_none_or(|published| now - published >= interval)
+}
+
+/// Publishes a state to RabbitMQ
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP backend to test
+async fn publish(mq_channel: &crate::amqp::MqChannel, stats: &Stats, state: &AircraftState) {
+    let Ok(msg) = serde_json::to_vec(&TelemetryEnvelope::new(state)) else {
+        rest_warn!("could not serialize aircraft state.");
+        return;
+    };
+
+    let _ = mq_channel
+        .basic_publish(
+            crate::amqp::EXCHANGE_NAME_TELEMETRY,
+            crate::amqp::ROUTING_KEY_ADSB_STATE,
+            lapin::options::BasicPublishOptions::default(),
+            &msg,
+            lapin::BasicProperties::default(),
+        )
+        .await
+        .map_err(|e| {
+            rest_warn!("could not push aircraft state to RabbitMQ: {e}.");
+            stats.record_error(Dependency::Amqp);
+        });
+}
+
+/// Caches a message received about an aircraft, publishing the combined
+///  state when due. Returns the combined state.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis and AMQP backends to test
+pub(crate) async fn update_state(
+    tlm_pool: &mut TelemetryPool,
+    mq_channel: &crate::amqp::MqChannel,
+    stats: &Stats,
+    identifier: &str,
+    update: Update,
+    interval_ms: u32,
+    now: DateTime<Utc>,
+) -> AircraftState {
+    let key = format!("{identifier}:state");
+    let cached = tlm_pool.hash_get_all(&key).await.unwrap_or_else(|e| {
+        rest_warn!("could not get state of {identifier} from cache: {e}");
+        stats.record_error(Dependency::Redis);
+        HashMap::new()
+    });
+
+    let mut fields = fields(&update).unwrap_or_else(|e| {
+        rest_warn!("could not serialize state update of {identifier}: {e}");
+        vec![]
+    });
+
+    let due = is_due(&cached, &update, now, interval_ms);
+    if due {
+        let published = now.timestamp_millis().to_string();
+        fields.push((FIELD_PUBLISHED.to_string(), published));
+    }
+
+    if let Err(e) = tlm_pool
+        .hash_set_multiple(&key, &fields, CACHE_EXPIRE_MS_STATE)
+        .await
+    {
+        rest_warn!("could not cache state of {identifier}: {e}");
+        stats.record_error(Dependency::Redis);
+    }
+
+    let state = merge(identifier, &cached, &update, now);
+    if due {
+        publish(mq_channel, stats, &state).await;
+    }
+
+    state
+}
+
+/// If a cached state holds fields of extended squitters
+fn holds_squitters(cached: &HashMap<String, String>) -> bool {
+    SQUITTER_FIELDS
+        .iter()
+        .any(|field| cached.contains_key(*field))
+}
+
+/// If extended squitters of an aircraft were received since its state
+///  last expired
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub(crate) async fn is_known(
+    tlm_pool: &mut TelemetryPool,
+    identifier: &str,
+) -> Result<bool, CacheError> {
+    let key = format!("{identifier}:state");
+    let cached = tlm_pool.hash_get_all(&key).await?;
+    Ok(holds_squitters(&cached))
+}
+
+/// Marks the identifier of an aircraft as contested by another aircraft,
+///  until its state expires
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub(crate) async fn mark_contested(
+    tlm_pool: &mut TelemetryPool,
+    identifier: &str,
+    now: DateTime<Utc>,
+) -> Result<(), CacheError> {
+    let key = format!("{identifier}:state");
+    let now = now.timestamp_millis().to_string();
+    tlm_pool
+        .hash_set(&key, FIELD_CONTESTED, &now, CACHE_EXPIRE_MS_STATE)
+        .await
+}
+
+/// Last operational status cached for an aircraft
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn operational_status(
+    tlm_pool: &mut TelemetryPool,
+    identifier: &str,
+) -> Result<Option<OperationalStatus>, CacheError> {
+    let key = format!("{identifier}:state");
+    let status = tlm_pool.hash_get(&key, FIELD_OPERATIONAL_STATUS).await?;
+    Ok(status.and_then(|status| serde_json::from_str(&status).ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use svc_gis_client_grpc::prelude::types::Position;
+
+    fn position(now: DateTime<Utc>) -> AircraftPosition {
+        AircraftPosition {
+            identifier: "4840d6".to_string(),
+            position: Position {
+                latitude: 52.37,
+                longitude: 4.9,
+                altitude_meters: 1000.0,
+            },
+            timestamp_network: now,
+            timestamp_asset: None,
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let now = Utc::now();
+        let mut cached = HashMap::new();
+
+        let update = Update::Identification {
+            callsign: "KLM1023".to_string(),
+            category: "A3".to_string(),
+        };
+        let state = merge("4840d6", &cached, &update, now);
+        assert_eq!(state.callsign, Some("KLM1023".to_string()));
+        assert_eq!(state.emitter_category, Some("A3".to_string()));
+        assert_eq!(state.position, None);
+
+        // the position is combined with the cached identification
+        cached.extend(fields(&update).unwrap());
+        let update = Update::Position(position(now));
+        let state = merge("4840d6", &cached, &update, now);
+        assert_eq!(state.identifier, "4840d6");
+        assert_eq!(state.callsign, Some("KLM1023".to_string()));
+        assert_eq!(state.position, Some(position(now)));
+
+        // cached positions are read back
+        cached.extend(fields(&update).unwrap());
+        let update = Update::TargetState(TargetState {
+            selected_altitude_meters: Some(3048.0),
+            selected_altitude_fms: false,
+            barometric_setting_hpa: None,
+            selected_heading_degrees: Some(90.0),
+            modes: None,
+        });
+        let state = merge("4840d6", &cached, &update, now);
+        assert_eq!(state.position, Some(position(now)));
+        assert_eq!(
+            state.target_state.and_then(|s| s.selected_heading_degrees),
+            Some(90.0)
+        );
+
+        // unreadable cached fields are ignored
+        cached.insert(FIELD_POSITION.to_string(), "{".to_string());
+        let state = merge("4840d6", &cached, &update, now);
+        assert_eq!(state.position, None);
+    }
+
+    #[test]
+    fn test_holds_squitters() {
+        let mut cached = HashMap::new();
+        assert!(!holds_squitters(&cached));
+
+        // contested, but never reported
+        cached.insert(FIELD_CONTESTED.to_string(), "0".to_string());
+        assert!(!holds_squitters(&cached));
+
+        cached.insert(FIELD_CALLSIGN.to_string(), "KLM1023".to_string());
+        assert!(holds_squitters(&cached));
+    }
+
+    #[test]
+    fn test_is_due() {
+        let now = Utc::now();
+        let mut cached = HashMap::new();
+        let update = Update::Position(position(now));
+        assert!(is_due(&cached, &update, now, 1000));
+
+        // only positions trigger a publication
+        let identification = Update::Identification {
+            callsign: "KLM1023".to_string(),
+            category: "A3".to_string(),
+        };
+        assert!(!is_due(&cached, &identification, now, 1000));
+
+        let published = now - Duration::try_milliseconds(500).unwrap();
+        let published = published.timestamp_millis().to_string();
+        cached.insert(FIELD_PUBLISHED.to_string(), published);
+        assert!(!is_due(&cached, &update, now, 1000));
+        assert!(is_due(&cached, &update, now, 500));
+    }
+}
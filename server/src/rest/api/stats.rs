@@ -0,0 +1,55 @@
+//! REST API endpoint for telemetry statistics
+
+use super::Pipeline;
+use crate::stats::{Stats, StatsSummary};
+use axum::{
+    extract::{Extension, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use std::time::Instant;
+
+/// Summary of the telemetry received by this instance
+#[utoipa::path(
+    get,
+    path = "/v1/telemetry/stats",
+    tag = "svc-telemetry",
+    responses(
+        (status = 200, description = "Statistics summary.", body = StatsSummary),
+    )
+)]
+pub async fn stats(
+    Extension(Pipeline {
+        stats,
+        grpc_clients,
+        ..
+    }): Extension<Pipeline>,
+) -> Json<StatsSummary> {
+    rest_debug!("entry.");
+    Json(stats.summary(grpc_clients.breakers.states()))
+}
+
+/// Records the time spent handling each request
+pub async fn latency<B>(State(stats): State<Stats>, req: Request<B>, next: Next<B>) -> Response {
+    let start = Instant::now();
+    let response = next.run(req).await;
+    stats.record_latency(start.elapsed());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stats() {
+        let pipeline = super::super::test_pipeline(crate::Config::default()).await;
+
+        pipeline.stats.record_packet("adsb", false);
+        let Json(summary) = stats(Extension(pipeline)).await;
+        assert_eq!(summary.packets["adsb"].last_1_min, 1);
+        assert_eq!(summary.circuit_breakers.len(), 2);
+    }
+}
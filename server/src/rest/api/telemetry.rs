@@ -0,0 +1,391 @@
+//! Unified telemetry endpoint
+//!
+//! Gateways multiplexing several radio front-ends post every packet to
+//!  the same endpoint. The packet format is detected from its length and
+//!  magic bytes, and the packet is handed to the matching processor.
+
+use super::jwt::Claim;
+use super::signal::{signal_metadata, SignalHeaders};
+use super::Pipeline;
+use crate::amqp::raw::Reception;
+use axum::{body::Bytes, extract::Extension, http::HeaderMap, Json};
+use hyper::StatusCode;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Path of the unified endpoint, as recorded with raw packets
+const ENDPOINT: &str = "/telemetry";
+
+/// Length of an ADS-B extended squitter
+const ADSB_PACKET_LENGTH: usize = 14;
+
+/// Length of a single Remote ID message
+const REMOTE_ID_PACKET_LENGTH: usize = 25;
+
+/// Downlink formats of 112 bit Mode S packets handled as ADS-B:
+///  extended squitters (DF17 and DF18) and identity replies (DF21)
+const ADSB_DOWNLINK_FORMATS: [u8; 3] = [17, 18, 21];
+
+/// Highest Remote ID message type sent as a single message (Operator ID)
+const REMOTE_ID_MAX_MESSAGE_TYPE: u8 = 0x5;
+
+/// Start of a MAVLink 1 packet
+const MAVLINK_V1_MAGIC: u8 = 0xFE;
+
+/// Start of a MAVLink 2 packet
+const MAVLINK_V2_MAGIC: u8 = 0xFD;
+
+/// Header and checksum bytes of a MAVLink 1 packet
+const MAVLINK_V1_OVERHEAD: usize = 8;
+
+/// Header and checksum bytes of a MAVLink 2 packet
+const MAVLINK_V2_OVERHEAD: usize = 12;
+
+/// Signature bytes of a signed MAVLink 2 packet
+const MAVLINK_V2_SIGNATURE_LENGTH: usize = 13;
+
+/// MAVLink 2 incompatibility flag of signed packets
+const MAVLINK_V2_FLAG_SIGNED: u8 = 0x01;
+
+/// Length of a CCSDS space packet primary header
+const CCSDS_HEADER_LENGTH: usize = 6;
+
+/// Packet formats recognized by the unified endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadType {
+    /// Network Remote ID message
+    Netrid,
+
+    /// ADS-B extended squitter
+    Adsb,
+
+    /// MAVLink 1 or 2 packet
+    Mavlink,
+
+    /// CCSDS space packet
+    Ccsds,
+}
+
+/// Response of the unified endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct DetectedTelemetry {
+    /// Format the packet was detected as
+    pub payload_type: PayloadType,
+
+    /// Number of times the packet was received
+    pub count: u32,
+}
+
+/// If the payload is a complete MAVLink packet
+fn is_mavlink(payload: &[u8]) -> bool {
+    let (Some(magic), Some(length)) = (payload.first(), payload.get(1)) else {
+        return false;
+    };
+
+    let length = *length as usize;
+    match *magic {
+        MAVLINK_V1_MAGIC => payload.len() == length + MAVLINK_V1_OVERHEAD,
+        MAVLINK_V2_MAGIC => {
+            let signed = payload
+                .get(2)
+                .is_some_and(|flags| flags & MAVLINK_V2_FLAG_SIGNED != 0);
+
+            let signature = match signed {
+                true => MAVLINK_V2_SIGNATURE_LENGTH,
+                false => 0,
+            };
+
+            payload.len() == length + MAVLINK_V2_OVERHEAD + signature
+        }
+        _ => false,
+    }
+}
+
+/// If the payload is an ADS-B extended squitter
+fn is_adsb(payload: &[u8]) -> bool {
+    payload.len() == ADSB_PACKET_LENGTH && ADSB_DOWNLINK_FORMATS.contains(&(payload[0] >> 3))
+}
+
+/// If the payload is a single Remote ID message
+fn is_netrid(payload: &[u8]) -> bool {
+    payload.len() == REMOTE_ID_PACKET_LENGTH && payload[0] >> 4 <= REMOTE_ID_MAX_MESSAGE_TYPE
+}
+
+/// If the payload is a complete CCSDS space packet
+fn is_ccsds(payload: &[u8]) -> bool {
+    if payload.len() <= CCSDS_HEADER_LENGTH {
+        return false;
+    }
+
+    // packet version number is always 0
+    let version = payload[0] >> 5;
+
+    // the data length field holds the number of data bytes minus one
+    let data_length = u16::from_be_bytes([payload[4], payload[5]]) as usize + 1;
+    version == 0 && payload.len() == CCSDS_HEADER_LENGTH + data_length
+}
+
+/// Detects the format of a packet
+///
+/// Formats with magic bytes are checked first. A 25-byte CCSDS packet
+///  starting like a Remote ID message is detected as Remote ID.
+pub fn detect(payload: &[u8]) -> Option<PayloadType> {
+    if is_mavlink(payload) {
+        Some(PayloadType::Mavlink)
+    } else if is_adsb(payload) {
+        Some(PayloadType::Adsb)
+    } else if is_netrid(payload) {
+        Some(PayloadType::Netrid)
+    } else if is_ccsds(payload) {
+        Some(PayloadType::Ccsds)
+    } else {
+        None
+    }
+}
+
+/// Detects the format of a packet, rejecting formats without a processor
+fn detect_supported(payload: &[u8]) -> Result<PayloadType, StatusCode> {
+    match detect(payload) {
+        Some(payload_type @ (PayloadType::Netrid | PayloadType::Adsb)) => {
+            rest_debug!("detected {payload_type:?} packet.");
+            Ok(payload_type)
+        }
+        Some(payload_type) => {
+            rest_warn!("no processor for {payload_type:?} packets.");
+            Err(StatusCode::NOT_IMPLEMENTED)
+        }
+        None => {
+            rest_warn!("could not detect packet format.");
+            Err(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+        }
+    }
+}
+
+/// Telemetry of any supported format
+#[utoipa::path(
+    post,
+    path = "/v1/telemetry",
+    tag = "svc-telemetry",
+    params(SignalHeaders),
+    request_body(
+        content = BinaryPacket,
+        description = "Packet of any supported format.",
+        content_type = "application/octet-stream"
+    ),
+    responses(
+        (status = 200, description = "Telemetry received.", body = DetectedTelemetry,
+            headers(("x-delivery-receipt" = String, description = "Signed delivery receipt, if asked with `X-Delivery-Receipt: signed`."))),
+        (status = 400, description = "Malformed packet or signal metadata."),
+        (status = 401, description = "Missing or invalid JWT token.", body = ErrorResponse),
+        (status = 403, description = "Reporter quarantined."),
+        (status = 415, description = "Packet format or Remote ID protocol version not recognized."),
+        (status = 500, description = "Something went wrong."),
+        (status = 501, description = "Packet format recognized but not processed."),
+        (status = 503, description = "Dependencies of svc-telemetry were down."),
+    )
+)]
+pub async fn telemetry(
+    Extension(pipeline): Extension<Pipeline>,
+    Extension(claim): Extension<Claim>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<Json<DetectedTelemetry>, StatusCode> {
+    rest_info!("entry.");
+    let payload_type = detect_supported(&payload)?;
+    let signal = signal_metadata(&headers)?;
+    let reception = Reception::new(
+        ENDPOINT,
+        Some(claim.sub.clone()),
+        signal,
+        pipeline.clock.now(),
+    );
+    let Json(count) = match payload_type {
+        PayloadType::Netrid => {
+            super::netrid::handle(pipeline, claim, reception, payload, false).await?
+        }
+        _ => super::adsb::handle(pipeline, reception, payload).await?,
+    };
+
+    Ok(Json(DetectedTelemetry {
+        payload_type,
+        count,
+    }))
+}
+
+/// Telemetry of any supported format, queueing it for a dispatcher
+///  Serves `/telemetry` in `ingest` mode, see [`telemetry`].
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn telemetry_ingest(
+    Extension(pipeline): Extension<Pipeline>,
+    Extension(claim): Extension<Claim>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<Json<DetectedTelemetry>, StatusCode> {
+    rest_info!("entry.");
+    let payload_type = detect_supported(&payload)?;
+    let signal = signal_metadata(&headers)?;
+    let reception = Reception::new(
+        ENDPOINT,
+        Some(claim.sub.clone()),
+        signal,
+        pipeline.clock.now(),
+    );
+    let Json(count) = match payload_type {
+        PayloadType::Netrid => {
+            super::netrid::handle_ingest(pipeline, claim, reception, payload, false).await?
+        }
+        _ => super::adsb::handle_ingest(pipeline, reception, payload).await?,
+    };
+
+    Ok(Json(DetectedTelemetry {
+        payload_type,
+        count,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::netrid::{Frame, Header, LocationMessage, MessageType};
+    use packed_struct::PackedStruct;
+    use proptest::collection::vec;
+    use proptest::prelude::{any, proptest};
+
+    #[test]
+    fn test_detect_adsb() {
+        let payload = [
+            0x8D, 0x48, 0x40, 0xD6, 0x20, 0x2C, 0xC3, 0x71, 0xC3, 0x2C, 0xE0, 0x57, 0x60, 0x98,
+        ];
+        assert_eq!(detect(&payload), Some(PayloadType::Adsb));
+
+        // Comm-B reply, also 14 bytes
+        let mut payload = payload;
+        payload[0] = 0xA0;
+        assert_eq!(detect(&payload), None);
+    }
+
+    #[test]
+    fn test_detect_netrid() {
+        let mut payload = [0u8; REMOTE_ID_PACKET_LENGTH];
+        payload[0] = 0x12; // location message, protocol version 2
+        assert_eq!(detect(&payload), Some(PayloadType::Netrid));
+
+        // message packs aren't single messages
+        payload[0] = 0xF2;
+        assert_eq!(detect(&payload), None);
+    }
+
+    #[test]
+    fn test_detect_mavlink() {
+        // heartbeat, 9 byte payload
+        let mut payload = vec![MAVLINK_V1_MAGIC, 9];
+        payload.resize(9 + MAVLINK_V1_OVERHEAD, 0);
+        assert_eq!(detect(&payload), Some(PayloadType::Mavlink));
+
+        let mut payload = vec![MAVLINK_V2_MAGIC, 9, 0];
+        payload.resize(9 + MAVLINK_V2_OVERHEAD, 0);
+        assert_eq!(detect(&payload), Some(PayloadType::Mavlink));
+
+        // signed, but missing the signature
+        payload[2] = MAVLINK_V2_FLAG_SIGNED;
+        assert_eq!(detect(&payload), None);
+        payload.resize(9 + MAVLINK_V2_OVERHEAD + MAVLINK_V2_SIGNATURE_LENGTH, 0);
+        assert_eq!(detect(&payload), Some(PayloadType::Mavlink));
+    }
+
+    #[test]
+    fn test_detect_ccsds() {
+        // telemetry packet with 10 data bytes
+        let mut payload = vec![0x08, 0x01, 0xC0, 0x00, 0x00, 0x09];
+        payload.resize(CCSDS_HEADER_LENGTH + 10, 0);
+        assert_eq!(detect(&payload), Some(PayloadType::Ccsds));
+
+        // truncated
+        payload.pop();
+        assert_eq!(detect(&payload), None);
+
+        assert_eq!(detect(&[]), None);
+    }
+
+    #[test]
+    fn test_detect_supported() {
+        let mut payload = vec![MAVLINK_V1_MAGIC, 0];
+        payload.resize(MAVLINK_V1_OVERHEAD, 0);
+        assert_eq!(detect_supported(&payload), Err(StatusCode::NOT_IMPLEMENTED));
+
+        assert_eq!(
+            detect_supported(&[0x01, 0x02]),
+            Err(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_detect_arbitrary_bytes(payload in vec(any::<u8>(), 0..300)) {
+            let _ = detect(&payload);
+            let _ = detect_supported(&payload);
+        }
+    }
+
+    fn claim() -> Claim {
+        Claim {
+            iat: 0,
+            sub: "test".to_string(),
+            exp: 0,
+            sid: None,
+            mission: None,
+            iss: None,
+            aud: None,
+            local: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_telemetry() {
+        let config = crate::config::Config {
+            telemetry_sinks: "amqp".to_string(),
+            ..Default::default()
+        };
+        let pipeline = super::super::test_pipeline(config).await;
+        let telemetry = |payload: Vec<u8>| {
+            telemetry(
+                Extension(pipeline.clone()),
+                Extension(claim()),
+                HeaderMap::new(),
+                Bytes::from(payload),
+            )
+        };
+
+        let mut location = LocationMessage::unpack(&[0; 24]).unwrap();
+        location.latitude = LocationMessage::encode_latitude(52.37);
+        location.longitude = LocationMessage::encode_longitude(4.89);
+        location.pressure_altitude = LocationMessage::encode_altitude(120.0);
+        let frame = Frame {
+            header: Header {
+                message_type: MessageType::Location,
+                ..Default::default()
+            },
+            message: location.pack().unwrap(),
+        };
+        let Json(detected) = telemetry(frame.pack().unwrap().to_vec()).await.unwrap();
+        assert_eq!(detected.payload_type, PayloadType::Netrid);
+        assert_eq!(detected.count, 1);
+
+        // MAVLink heartbeat, detected but not processed
+        let mut mavlink = vec![MAVLINK_V2_MAGIC, 9, 0];
+        mavlink.resize(9 + MAVLINK_V2_OVERHEAD, 0);
+        let result = telemetry(mavlink).await.unwrap_err();
+        assert_eq!(result, StatusCode::NOT_IMPLEMENTED);
+
+        let result = telemetry(vec![0x01, 0x02]).await.unwrap_err();
+        assert_eq!(result, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        // only the Remote ID position reached the sinks
+        let positions = pipeline
+            .mq_channel
+            .drain(crate::amqp::QUEUE_NAME_NETRID_POSITION);
+        assert_eq!(positions.unwrap().len(), 1);
+    }
+}
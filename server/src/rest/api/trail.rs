@@ -0,0 +1,90 @@
+//! Aircraft track REST API
+//!  Recent positions of an aircraft, kept by the `trail` sink, for user
+//!  interfaces drawing its path without querying svc-storage.
+
+use super::Pipeline;
+use crate::msg::privacy::Privacy;
+use crate::msg::trail::{cache_key, recent_points, AircraftTrail, TrailPoint};
+use crate::stats::Dependency;
+use axum::{
+    extract::{Extension, Path, Query},
+    Json,
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+
+/// Length of the track if not requested
+const DEFAULT_TRACK_SECONDS: u32 = 60;
+
+/// Length of the requested track
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct TrackQuery {
+    /// Positions received within this many seconds
+    pub seconds: Option<u32>,
+}
+
+/// Position of a trail as served to the public, see [`Privacy::public_location`]
+fn public_point(privacy: &Privacy, point: TrailPoint) -> TrailPoint {
+    let (latitude, longitude) = privacy.public_location(point.latitude, point.longitude);
+    TrailPoint {
+        latitude,
+        longitude,
+        ..point
+    }
+}
+
+/// Positions of an aircraft received within the last `seconds` (default
+///  60), oldest first
+///
+/// At most `TRAIL_MAX_POINTS` positions are kept per aircraft, and none
+///  once it stopped reporting for `TRAIL_EXPIRE_MS`. Positions are served
+///  `PRIVACY_PUBLIC_DELAY_MS` late, and those reported over Remote ID are
+///  snapped to geohash cells of `PRIVACY_PUBLIC_GEOHASH_PRECISION`.
+#[utoipa::path(
+    get,
+    path = "/v1/telemetry/aircraft/{identifier}/track",
+    tag = "svc-telemetry",
+    params(
+        ("identifier" = String, Path, description = "Identifier of the aircraft"),
+        ("seconds" = Option<u32>, Query, description = "Positions received within this many seconds (default: 60)"),
+    ),
+    responses(
+        (status = 200, description = "Recent positions of the aircraft, empty if none.", body = AircraftTrail),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn aircraft_track(
+    Extension(pipeline): Extension<Pipeline>,
+    Path(identifier): Path<String>,
+    Query(query): Query<TrackQuery>,
+) -> Result<Json<AircraftTrail>, StatusCode> {
+    rest_debug!("entry.");
+    let identifier = pipeline.identifiers.resolve(&identifier);
+    let key = cache_key(&identifier);
+
+    let until = pipeline.privacy.public_until(pipeline.clock.now());
+    let seconds = query.seconds.unwrap_or(DEFAULT_TRACK_SECONDS);
+    let mut points = vec![];
+    for (mut tlm_pool, remote_id) in [
+        (pipeline.tlm_pools.adsb.clone(), false),
+        (pipeline.tlm_pools.netrid.clone(), true),
+    ] {
+        let trail = tlm_pool.list_range(&key).await.map_err(|e| {
+            rest_error!("could not get trail of {identifier}: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        points.extend(recent_points(&trail, until, seconds).into_iter().map(
+            |point| match remote_id {
+                true => public_point(&pipeline.privacy, point),
+                false => point,
+            },
+        ));
+    }
+
+    points.sort_by_key(|point| point.timestamp_network);
+    Ok(Json(AircraftTrail { points, identifier }))
+}
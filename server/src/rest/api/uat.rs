@@ -0,0 +1,281 @@
+//! Universal Access Transceiver REST API
+//!  General aviation in the US broadcasts ADS-B on 978 MHz with UAT,
+//!  relayed by receivers such as dump978 as hex encoded messages.
+
+use super::adsb::get_aircraft_type;
+use super::enrichment::{enrich, Update};
+use super::Pipeline;
+use crate::logging::context;
+use crate::msg::adsb::emitter_category_code;
+use crate::msg::track::TrackDecision;
+use crate::msg::uat::{self, UatMessage};
+use crate::sink::{EventData, EventSource, TelemetryEvent};
+use adsb_deku::adsb::TypeCoding;
+use svc_gis_client_grpc::prelude::types::*;
+
+use axum::{extract::Extension, Json};
+use hyper::StatusCode;
+use lib_common::time::{DateTime, Utc};
+
+/// Most messages accepted per request
+const MAX_MESSAGES: usize = 100;
+
+/// Identification of an aircraft by the emitter category of its mode status
+fn aircraft_id(
+    identifier: &str,
+    type_coding: TypeCoding,
+    category: u8,
+    now: DateTime<Utc>,
+) -> AircraftId {
+    AircraftId {
+        identifier: Some(identifier.to_string()),
+        session_id: None,
+        aircraft_type: get_aircraft_type(type_coding, category),
+        timestamp_network: now,
+        timestamp_asset: None,
+    }
+}
+
+/// Position of the aircraft of a message
+fn aircraft_position(
+    identifier: &str,
+    message: &UatMessage,
+    now: DateTime<Utc>,
+) -> AircraftPosition {
+    AircraftPosition {
+        identifier: identifier.to_string(),
+        position: Position {
+            latitude: message.latitude,
+            longitude: message.longitude,
+            altitude_meters: message.altitude_meters,
+        },
+        timestamp_network: now,
+        timestamp_asset: None,
+    }
+}
+
+/// Velocity of the aircraft of a message, if it reports its speed and track
+fn aircraft_velocity(
+    identifier: &str,
+    message: &UatMessage,
+    now: DateTime<Utc>,
+) -> Option<AircraftVelocity> {
+    Some(AircraftVelocity {
+        identifier: identifier.to_string(),
+        velocity_horizontal_ground_mps: message.ground_speed_mps?,
+        velocity_horizontal_air_mps: None,
+        velocity_vertical_mps: message.climb_rate_mps.unwrap_or(0.0),
+        track_angle_degrees: message.track_degrees?,
+        timestamp_network: now,
+        timestamp_asset: None,
+    })
+}
+
+/// Decodes the messages of a request, skipping blank lines, comments (`#`)
+///  and lines which aren't ADS-B messages, e.g. ground uplinks
+fn decode_messages(body: &str) -> Result<Vec<UatMessage>, StatusCode> {
+    let lines: Vec<&str> = body
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    if lines.is_empty() || lines.len() > MAX_MESSAGES {
+        rest_warn!("received {} uat messages.", lines.len());
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let messages: Vec<UatMessage> = lines
+        .iter()
+        .filter_map(|line| {
+            uat::parse(line)
+                .inspect_err(|e| rest_debug!("skipped uat message ({e}): {line}"))
+                .ok()
+        })
+        .collect();
+
+    match messages.is_empty() {
+        true => {
+            rest_warn!("no uat message could be decoded.");
+            Err(StatusCode::BAD_REQUEST)
+        }
+        false => Ok(messages),
+    }
+}
+
+/// Pushes the identification, position and velocity of a message
+///
+/// Returns if it was pushed, messages of blocked aircraft are dropped.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP and redis backends to test
+async fn process_message(
+    message: UatMessage,
+    now: DateTime<Utc>,
+    pipeline: &Pipeline,
+) -> Result<bool, StatusCode> {
+    pipeline.stats.record_packet("uat", false);
+    let identifier = pipeline.identifiers.icao(message.address);
+    pipeline.stats.record_aircraft(&identifier);
+    context::set_aircraft(&identifier);
+    context::set_packet_type("uat:position");
+    if pipeline.admit(&[&identifier], "uat").await.is_err() {
+        return Ok(false);
+    }
+
+    let sinks = pipeline.sinks.clone();
+    let event = |data: EventData| TelemetryEvent::new(EventSource::Uat, &identifier, data, now);
+    if let Some(code) = message.emitter_category {
+        let (type_coding, category) = uat::emitter_category(code);
+        let item = aircraft_id(&identifier, type_coding, category, now);
+        let category = emitter_category_code(type_coding, category);
+        sinks
+            .push(&event(EventData::Identification(item)).with_emitter_category(category))
+            .await?;
+    }
+
+    if let Some(callsign) = message.callsign.clone() {
+        let mut tlm_pool = pipeline.tlm_pools.adsb.clone();
+        let update = Update::Callsign(callsign);
+        enrich(
+            &mut tlm_pool,
+            &pipeline.mq_channel,
+            &pipeline.stats,
+            &identifier,
+            update,
+            now,
+        )
+        .await;
+    }
+
+    // the track records the velocity pushed, smoothed
+    let velocity_item = match aircraft_velocity(&identifier, &message, now) {
+        Some(item) => Some(
+            pipeline
+                .filters
+                .lock()
+                .map_err(|e| {
+                    rest_error!("could not lock velocity filters: {e}");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?
+                .apply(item),
+        ),
+        None => None,
+    };
+
+    let (decision, velocity_item) = {
+        let mut tracks = pipeline.tracks.lock().map_err(|e| {
+            rest_error!("could not lock tracks: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        // the velocity is ordered on its own, whatever became of the position
+        let decision = tracks.update(aircraft_position(&identifier, &message, now), now);
+        let velocity_item = velocity_item.filter(|item| tracks.update_velocity(item.clone()));
+        (decision, velocity_item)
+    };
+
+    match decision {
+        TrackDecision::Accept(item) | TrackDecision::Merge(item) => {
+            sinks.push(&event(EventData::Position(item))).await?;
+            rest_debug!("pushed aircraft position to sinks.");
+        }
+        TrackDecision::Discard => rest_info!("discarded out of order uat message of {identifier}."),
+    }
+
+    if let Some(item) = velocity_item {
+        sinks.push(&event(EventData::Velocity(item))).await?;
+        rest_debug!("pushed aircraft velocity to sinks.");
+    }
+
+    Ok(true)
+}
+
+/// Post UAT (978 MHz) ADS-B messages
+///
+/// The body holds hex encoded messages, one per line, as output by
+///  dump978. Ground uplinks and rebroadcasts (TIS-B, ADS-R) are skipped.
+#[utoipa::path(
+    post,
+    path = "/v1/telemetry/uat",
+    tag = "svc-telemetry",
+    request_body(
+        content = String,
+        description = "UAT ADS-B messages in the dump978 format, one per line (at most 100).",
+        content_type = "text/plain"
+    ),
+    responses(
+        (status = 200, description = "Messages received, with the number of messages pushed.", body = u32),
+        (status = 400, description = "No UAT ADS-B message could be decoded, or too many lines."),
+        (status = 500, description = "Something went wrong."),
+        (status = 503, description = "Dependencies of svc-telemetry were down."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP and redis backends to test
+pub async fn uat(
+    Extension(pipeline): Extension<Pipeline>,
+    body: String,
+) -> Result<Json<u32>, StatusCode> {
+    rest_info!("entry.");
+    let now = pipeline.clock.now();
+    let mut pushed = 0;
+    for message in decode_messages(&body)? {
+        if process_message(message, now, &pipeline).await? {
+            pushed += 1;
+        }
+    }
+
+    Ok(Json(pushed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASIC: &str = "-00a1b2c335809751f4a00b5801e61980b000;rs=2;";
+    const LONG: &str = "-08a1b2c335809751f4a00b5801e619a0b009d90d024a840000000200000000000000;";
+
+    #[test]
+    fn test_decode_messages() {
+        let body = format!("# dump978\n\n{BASIC}\n+3514c952d65c2f0e1b6a;\n{LONG}\n");
+        let messages = decode_messages(&body).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].address, 0xa1b2c3);
+        assert_eq!(messages[1].callsign.as_deref(), Some("N123AB"));
+
+        assert_eq!(decode_messages(""), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(decode_messages("# comment"), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(decode_messages("garbage"), Err(StatusCode::BAD_REQUEST));
+
+        let body = vec![BASIC; MAX_MESSAGES + 1].join("\n");
+        assert_eq!(decode_messages(&body), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn test_items() {
+        let now = Utc::now();
+        let basic = decode_messages(BASIC).unwrap().remove(0);
+        assert!(basic.emitter_category.is_none());
+
+        let position = aircraft_position("a1b2c3", &basic, now);
+        assert_eq!(position.position.latitude, basic.latitude);
+        assert_eq!(position.position.altitude_meters, basic.altitude_meters);
+        assert_eq!(position.timestamp_network, now);
+
+        let velocity = aircraft_velocity("a1b2c3", &basic, now).unwrap();
+        assert_eq!(Some(velocity.track_angle_degrees), basic.track_degrees);
+        assert_eq!(Some(velocity.velocity_vertical_mps), basic.climb_rate_mps);
+
+        let long = decode_messages(LONG).unwrap().remove(0);
+        let (type_coding, category) = uat::emitter_category(long.emitter_category.unwrap());
+        let id = aircraft_id("a1b2c3", type_coding, category, now);
+        assert_eq!(id.aircraft_type, AircraftType::Aeroplane);
+        assert_eq!(id.identifier.as_deref(), Some("a1b2c3"));
+
+        let message = UatMessage {
+            track_degrees: None,
+            ..basic
+        };
+        assert!(aircraft_velocity("a1b2c3", &message, now).is_none());
+    }
+}
@@ -0,0 +1,297 @@
+//! REST API endpoints for the aircraft watchlist
+//!
+//! Aircraft watched through the admin API are kept in a hash of the
+//!  Remote ID cache, and the most recent hits in a list next to it, so all
+//!  instances alert on the same aircraft. Both expire 30 days after their
+//!  last change: aircraft to watch for good belong in `WATCHLIST`.
+
+use super::Pipeline;
+use crate::amqp::envelope::TelemetryEnvelope;
+use crate::cache::pool::CacheError;
+use crate::msg::watchlist::{
+    hits, normalize, WatchlistHit, MAX_HITS, WATCHLIST_HITS_KEY, WATCHLIST_KEY,
+};
+use crate::stats::Dependency;
+use axum::{
+    extract::{Extension, Path},
+    Json,
+};
+use hyper::StatusCode;
+
+/// The watchlist hash and hits expire 30 days after their last change
+const CACHE_EXPIRE_MS_WATCHLIST: u32 = 30 * 24 * 3_600_000;
+
+/// If an aircraft is watched
+///
+/// Aircraft are considered unwatched if the cache can't be read: a Redis
+///  outage must not stop the ingest.
+async fn watched(pipeline: &Pipeline, identifier: &str) -> bool {
+    match pipeline.watchlist.lock() {
+        Ok(watchlist) if watchlist.contains(identifier) => return true,
+        Ok(_) => (),
+        Err(e) => rest_error!("could not lock watchlist: {e}"),
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    match tlm_pool
+        .hash_get(WATCHLIST_KEY, &normalize(identifier))
+        .await
+    {
+        Ok(value) => value.is_some(),
+        Err(e) => {
+            rest_warn!("could not check if {identifier} is watched: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+            false
+        }
+    }
+}
+
+/// Records an observed aircraft, alerting if it is watched
+pub(crate) async fn observe(pipeline: &Pipeline, identifier: &str, source: &str) {
+    if !watched(pipeline, identifier).await {
+        return;
+    }
+
+    let hit = match pipeline.watchlist.lock() {
+        Ok(mut watchlist) => watchlist.observe(identifier, source, pipeline.clock.now()),
+        Err(e) => {
+            rest_error!("could not lock watchlist: {e}");
+            return;
+        }
+    };
+
+    let Some(hit) = hit else {
+        return;
+    };
+
+    rest_info!("watched aircraft {} observed.", hit.identifier);
+    let Ok(value) = serde_json::to_string(&hit) else {
+        rest_warn!("could not serialize watchlist hit.");
+        return;
+    };
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let _ = tlm_pool
+        .list_push(
+            WATCHLIST_HITS_KEY,
+            &value,
+            MAX_HITS,
+            CACHE_EXPIRE_MS_WATCHLIST,
+        )
+        .await
+        .map_err(|e| {
+            rest_warn!("could not store watchlist hit: {e}");
+            pipeline.stats.record_error(Dependency::Redis);
+        });
+
+    let Ok(msg) = serde_json::to_vec(&TelemetryEnvelope::new(&hit)) else {
+        rest_warn!("could not serialize watchlist hit.");
+        return;
+    };
+
+    let _ = pipeline
+        .mq_channel
+        .basic_publish(
+            crate::amqp::EXCHANGE_NAME_TELEMETRY,
+            crate::amqp::ROUTING_KEY_WATCHLIST,
+            lapin::options::BasicPublishOptions::default(),
+            &msg,
+            lapin::BasicProperties::default(),
+        )
+        .await
+        .map_err(|e| {
+            rest_warn!("could not push watchlist alert to RabbitMQ: {e}.");
+            pipeline.stats.record_error(Dependency::Amqp);
+        });
+}
+
+/// Recent observations of watched aircraft, newest first
+#[utoipa::path(
+    get,
+    path = "/v1/admin/watchlist/hits",
+    tag = "svc-telemetry",
+    responses(
+        (status = 200, description = "Recent watchlist hits.", body = [WatchlistHit]),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+pub async fn watchlist_hits(
+    Extension(pipeline): Extension<Pipeline>,
+) -> Result<Json<Vec<WatchlistHit>>, StatusCode> {
+    rest_debug!("entry.");
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let cached = tlm_pool.list_range(WATCHLIST_HITS_KEY).await.map_err(|e| {
+        rest_error!("could not get watchlist hits: {e}");
+        pipeline.stats.record_error(Dependency::Redis);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(hits(&cached)))
+}
+
+/// Start watching an aircraft, on all instances
+#[utoipa::path(
+    put,
+    path = "/v1/admin/watchlist/{identifier}",
+    tag = "svc-telemetry",
+    params(
+        ("identifier" = String, Path, description = "ICAO address (hex) or Remote ID identifier"),
+    ),
+    responses(
+        (status = 201, description = "Aircraft added to the watchlist."),
+        (status = 200, description = "Aircraft already watched."),
+        (status = 400, description = "Empty identifier."),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+pub async fn watch(
+    Extension(pipeline): Extension<Pipeline>,
+    Path(identifier): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    rest_debug!("entry.");
+    let identifier = normalize(&pipeline.identifiers.resolve(&identifier));
+    if identifier.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let internal_error = |e: CacheError| {
+        rest_error!("could not watch {identifier}: {e}");
+        pipeline.stats.record_error(Dependency::Redis);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    if watched(&pipeline, &identifier).await {
+        return Ok(StatusCode::OK);
+    }
+
+    let added_at = pipeline.clock.now().to_rfc3339();
+    tlm_pool
+        .hash_set(
+            WATCHLIST_KEY,
+            &identifier,
+            &added_at,
+            CACHE_EXPIRE_MS_WATCHLIST,
+        )
+        .await
+        .map_err(internal_error)?;
+
+    rest_info!("watching aircraft {identifier}.");
+    Ok(StatusCode::CREATED)
+}
+
+/// Stop watching an aircraft watched through the admin API
+///
+/// Aircraft listed in `WATCHLIST` can only be unwatched by removing them
+///  there.
+#[utoipa::path(
+    delete,
+    path = "/v1/admin/watchlist/{identifier}",
+    tag = "svc-telemetry",
+    params(
+        ("identifier" = String, Path, description = "ICAO address (hex) or Remote ID identifier"),
+    ),
+    responses(
+        (status = 204, description = "Aircraft removed from the watchlist."),
+        (status = 401, description = "Missing or invalid admin secret."),
+        (status = 404, description = "Aircraft not watched."),
+        (status = 409, description = "Aircraft listed in WATCHLIST."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+pub async fn unwatch(
+    Extension(pipeline): Extension<Pipeline>,
+    Path(identifier): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    rest_debug!("entry.");
+    let identifier = normalize(&pipeline.identifiers.resolve(&identifier));
+    let configured = pipeline
+        .watchlist
+        .lock()
+        .map(|watchlist| watchlist.contains(&identifier))
+        .map_err(|e| {
+            rest_error!("could not lock watchlist: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if configured {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let mut tlm_pool = pipeline.tlm_pools.netrid.clone();
+    let internal_error = |e: CacheError| {
+        rest_error!("could not unwatch {identifier}: {e}");
+        pipeline.stats.record_error(Dependency::Redis);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let watched = tlm_pool
+        .hash_get(WATCHLIST_KEY, &identifier)
+        .await
+        .map_err(internal_error)?;
+    if watched.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    tlm_pool
+        .hash_delete(WATCHLIST_KEY, &[identifier.clone()])
+        .await
+        .map_err(internal_error)?;
+
+    rest_info!("stopped watching aircraft {identifier}.");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_watchlist_endpoints() {
+        let config = crate::Config {
+            watchlist: "drone-1".to_string(),
+            ..Default::default()
+        };
+        let pipeline = super::super::test_pipeline(config).await;
+
+        let result = watch(Extension(pipeline.clone()), Path("A1B2C3".to_string())).await;
+        assert_eq!(result, Ok(StatusCode::CREATED));
+        let result = watch(Extension(pipeline.clone()), Path("Drone-1".to_string())).await;
+        assert_eq!(result, Ok(StatusCode::OK));
+        let result = watch(Extension(pipeline.clone()), Path(" ".to_string())).await;
+        assert_eq!(result, Err(StatusCode::BAD_REQUEST));
+
+        let Json(hits) = watchlist_hits(Extension(pipeline.clone())).await.unwrap();
+        assert!(hits.is_empty());
+
+        let result = unwatch(Extension(pipeline.clone()), Path("drone-1".to_string())).await;
+        assert_eq!(result, Err(StatusCode::CONFLICT));
+        let result = unwatch(Extension(pipeline), Path("b2c3d4".to_string())).await;
+        assert_eq!(result, Err(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_observe() {
+        let config = crate::Config {
+            identifier_rules: "icao.prefix=icao:,icao.pad=6".to_string(),
+            watchlist: "b2c3".to_string(),
+            ..Default::default()
+        };
+        let pipeline = super::super::test_pipeline(config).await;
+
+        // watched in the format of earlier releases
+        let identifier = pipeline.identifiers.icao(0xb2c3);
+        observe(&pipeline, &identifier, "adsb").await;
+        observe(&pipeline, &identifier, "adsb").await;
+        observe(&pipeline, &pipeline.identifiers.icao(0xa1b2c3), "adsb").await;
+
+        let alerts = pipeline
+            .mq_channel
+            .drain(crate::amqp::QUEUE_NAME_WATCHLIST)
+            .unwrap();
+        assert_eq!(alerts.len(), 1);
+        let alert: TelemetryEnvelope<WatchlistHit> = serde_json::from_slice(&alerts[0]).unwrap();
+        assert_eq!(alert.data.identifier, "icao:00b2c3");
+    }
+}
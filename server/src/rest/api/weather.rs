@@ -0,0 +1,310 @@
+//! Weather REST API
+//!  Vertiport ground stations report the local wind and temperature, kept
+//!  per station and published for routing on the `weather` queue.
+
+use super::jwt::Claim;
+use super::Pipeline;
+use crate::logging::context;
+use crate::sink::{EventData, EventSource, TelemetryEvent};
+use crate::stats::Dependency;
+use axum::{
+    extract::{Extension, Path},
+    Json,
+};
+use hyper::StatusCode;
+use lib_common::time::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use utoipa::ToSchema;
+
+/// Weather of a station expires if not reported again within an hour
+const CACHE_EXPIRE_MS_WEATHER: u32 = 3_600_000;
+
+/// Hash field holding the latest observation of a station
+const FIELD_LATEST: &str = "latest";
+
+/// Plausible wind and gust speeds in meters per second
+const WIND_SPEED_RANGE_MPS: RangeInclusive<f32> = 0.0..=150.0;
+
+/// Wind directions in degrees, 0 and 360 being north
+const WIND_DIRECTION_RANGE_DEGREES: RangeInclusive<f32> = 0.0..=360.0;
+
+/// Plausible temperatures in degrees Celsius
+const TEMPERATURE_RANGE_CELSIUS: RangeInclusive<f32> = -90.0..=60.0;
+
+/// Plausible sea level pressures in hectopascals
+const PRESSURE_RANGE_HPA: RangeInclusive<f32> = 850.0..=1100.0;
+
+/// Relative humidities in percent
+const HUMIDITY_RANGE_PERCENT: RangeInclusive<f32> = 0.0..=100.0;
+
+/// Weather reported by a ground station
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WeatherReport {
+    /// Mean wind speed in meters per second
+    #[schema(example = 4.5)]
+    pub wind_speed_mps: f32,
+
+    /// Direction the wind blows from, in degrees from true north
+    #[schema(example = 270.0)]
+    pub wind_direction_degrees: f32,
+
+    /// Gust speed in meters per second
+    #[serde(default)]
+    pub wind_gust_mps: Option<f32>,
+
+    /// Air temperature in degrees Celsius
+    #[schema(example = 18.5)]
+    pub temperature_celsius: f32,
+
+    /// Sea level pressure (QNH) in hectopascals
+    #[serde(default)]
+    pub pressure_hpa: Option<f32>,
+
+    /// Relative humidity in percent
+    #[serde(default)]
+    pub humidity_percent: Option<f32>,
+
+    /// When the station measured the weather
+    #[serde(default)]
+    pub timestamp_asset: Option<DateTime<Utc>>,
+}
+
+/// Weather of a ground station, as published and cached
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WeatherObservation {
+    /// Identifier of the station, from its token
+    pub station: String,
+
+    /// The reported weather
+    #[serde(flatten)]
+    pub report: WeatherReport,
+
+    /// When the report was received
+    pub timestamp_network: DateTime<Utc>,
+}
+
+/// If an optional value is within its range
+fn within(value: Option<f32>, range: &RangeInclusive<f32>) -> bool {
+    value.is_none_or(|value| range.contains(&value))
+}
+
+impl WeatherReport {
+    /// Name of the first implausible field, if any
+    fn invalid_field(&self) -> Option<&'static str> {
+        if !WIND_SPEED_RANGE_MPS.contains(&self.wind_speed_mps) {
+            Some("wind_speed_mps")
+        } else if !WIND_DIRECTION_RANGE_DEGREES.contains(&self.wind_direction_degrees) {
+            Some("wind_direction_degrees")
+        } else if !within(self.wind_gust_mps, &WIND_SPEED_RANGE_MPS) {
+            Some("wind_gust_mps")
+        } else if !TEMPERATURE_RANGE_CELSIUS.contains(&self.temperature_celsius) {
+            Some("temperature_celsius")
+        } else if !within(self.pressure_hpa, &PRESSURE_RANGE_HPA) {
+            Some("pressure_hpa")
+        } else if !within(self.humidity_percent, &HUMIDITY_RANGE_PERCENT) {
+            Some("humidity_percent")
+        } else {
+            None
+        }
+    }
+}
+
+/// Report the weather at a ground station
+///  The station is identified by the subject of its token.
+#[utoipa::path(
+    post,
+    path = "/v1/telemetry/weather",
+    tag = "svc-telemetry",
+    request_body = WeatherReport,
+    responses(
+        (status = 200, description = "Weather cached and published."),
+        (status = 400, description = "Implausible value."),
+        (status = 401, description = "Missing or invalid JWT token.", body = ErrorResponse),
+        (status = 422, description = "Malformed JSON body."),
+        (status = 500, description = "Something went wrong."),
+        (status = 501, description = "Not served in ingest mode."),
+        (status = 503, description = "Dependencies of svc-telemetry were down."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need AMQP and redis backends to test
+pub async fn weather(
+    Extension(pipeline): Extension<Pipeline>,
+    Extension(claim): Extension<Claim>,
+    Json(report): Json<WeatherReport>,
+) -> Result<StatusCode, StatusCode> {
+    rest_info!("entry.");
+    context::set_aircraft(&claim.sub);
+    context::set_packet_type("weather");
+    pipeline.stats.record_packet("weather", false);
+
+    if let Some(field) = report.invalid_field() {
+        rest_warn!("implausible {field} reported by {}.", claim.sub);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let observation = WeatherObservation {
+        station: claim.sub.clone(),
+        report,
+        timestamp_network: pipeline.clock.now(),
+    };
+
+    let value = serde_json::to_string(&observation).map_err(|e| {
+        rest_error!("could not serialize weather: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut tlm_pool = pipeline.weather_pool.clone();
+    if let Err(e) = tlm_pool
+        .hash_set(&claim.sub, FIELD_LATEST, &value, CACHE_EXPIRE_MS_WEATHER)
+        .await
+    {
+        rest_error!("could not cache weather: {e}");
+        pipeline.stats.record_error(Dependency::Redis);
+        pipeline.degradation.on_failure(Dependency::Redis)?;
+    }
+
+    let event = TelemetryEvent::new(
+        EventSource::Station,
+        &claim.sub,
+        EventData::Weather(observation),
+        pipeline.clock.now(),
+    );
+
+    pipeline.sinks.push(&event).await?;
+    rest_debug!("pushed weather to sinks.");
+
+    Ok(StatusCode::OK)
+}
+
+/// Latest weather reported by a ground station within the last hour
+#[utoipa::path(
+    get,
+    path = "/v1/telemetry/weather/{station}",
+    tag = "svc-telemetry",
+    params(
+        ("station" = String, Path, description = "Identifier of the station"),
+    ),
+    responses(
+        (status = 200, description = "Latest weather of the station.", body = WeatherObservation),
+        (status = 404, description = "No recent weather from the station."),
+        (status = 500, description = "Something went wrong."),
+    )
+)]
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn latest_weather(
+    Extension(pipeline): Extension<Pipeline>,
+    Path(station): Path<String>,
+) -> Result<Json<WeatherObservation>, StatusCode> {
+    rest_debug!("entry.");
+    let mut tlm_pool = pipeline.weather_pool.clone();
+    let fields = tlm_pool.hash_get_all(&station).await.map_err(|e| {
+        rest_error!("could not get weather: {e}");
+        pipeline.stats.record_error(Dependency::Redis);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let value = fields.get(FIELD_LATEST).ok_or(StatusCode::NOT_FOUND)?;
+    let observation = serde_json::from_str(value).map_err(|e| {
+        rest_error!("could not parse cached weather: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(observation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> WeatherReport {
+        WeatherReport {
+            wind_speed_mps: 4.5,
+            wind_direction_degrees: 270.0,
+            wind_gust_mps: Some(9.0),
+            temperature_celsius: 18.5,
+            pressure_hpa: Some(1013.2),
+            humidity_percent: None,
+            timestamp_asset: None,
+        }
+    }
+
+    #[test]
+    fn test_invalid_field() {
+        assert_eq!(report().invalid_field(), None);
+
+        let cases = [
+            (
+                WeatherReport {
+                    wind_speed_mps: -1.0,
+                    ..report()
+                },
+                "wind_speed_mps",
+            ),
+            (
+                WeatherReport {
+                    wind_direction_degrees: 361.0,
+                    ..report()
+                },
+                "wind_direction_degrees",
+            ),
+            (
+                WeatherReport {
+                    wind_gust_mps: Some(f32::NAN),
+                    ..report()
+                },
+                "wind_gust_mps",
+            ),
+            (
+                WeatherReport {
+                    temperature_celsius: 80.0,
+                    ..report()
+                },
+                "temperature_celsius",
+            ),
+            (
+                WeatherReport {
+                    pressure_hpa: Some(101.3),
+                    ..report()
+                },
+                "pressure_hpa",
+            ),
+            (
+                WeatherReport {
+                    humidity_percent: Some(120.0),
+                    ..report()
+                },
+                "humidity_percent",
+            ),
+        ];
+
+        for (report, field) in cases {
+            assert_eq!(report.invalid_field(), Some(field));
+        }
+    }
+
+    #[test]
+    fn test_observation_json() {
+        let observation = WeatherObservation {
+            station: "vertiport-1".to_string(),
+            report: report(),
+            timestamp_network: Utc::now(),
+        };
+
+        let value = serde_json::to_value(&observation).unwrap();
+        assert_eq!(value["station"], "vertiport-1");
+        assert_eq!(value["wind_direction_degrees"], 270.0);
+
+        let parsed: WeatherObservation = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, observation);
+
+        // optional fields may be left out
+        let report: WeatherReport = serde_json::from_str(
+            r#"{"wind_speed_mps": 3, "wind_direction_degrees": 90, "temperature_celsius": 21}"#,
+        )
+        .unwrap();
+        assert_eq!(report.pressure_hpa, None);
+    }
+}
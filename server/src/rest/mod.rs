@@ -14,9 +14,81 @@ use utoipa::OpenApi;
 #[openapi(
     paths(
         api::jwt::login,
+        api::jwt::login_json,
+        api::jwt::login_bulk,
         api::netrid::network_remote_id,
+        api::netrid::network_remote_id_relay,
         api::adsb::adsb,
-        api::health::health_check
+        api::ogn::ogn,
+        api::uat::uat,
+        api::health_report::health_report,
+        api::c2::c2_status,
+        api::c2::latest_c2_status,
+        api::heartbeat::heartbeat,
+        api::weather::weather,
+        api::weather::latest_weather,
+        api::coverage::coverage,
+        api::trail::aircraft_track,
+        api::receipt::receipt_keys,
+        api::telemetry::telemetry,
+        api::health::health_check,
+        api::stats::stats,
+        api::snapshot::snapshot,
+        api::watchlist::watchlist_hits,
+        api::watchlist::watch,
+        api::watchlist::unwatch,
+        api::reporter::reporter_stats,
+        api::reporter::reset_reporter,
+        api::quarantine::quarantine,
+        api::quarantine::replay_quarantine,
+        api::identity::identity_conflicts,
+        api::api_key::issue_api_key,
+        api::api_key::revoke_api_key,
+        api::blocklist::blocklist,
+        api::blocklist::block,
+        api::blocklist::unblock,
+        api::backfill::backfill,
+        api::backfill::backfill_status,
+        api::log_level::log_level
+    ),
+    components(
+        schemas(
+            crate::stats::StatsSummary,
+            crate::stats::PacketCounts,
+            crate::stats::PoolStatus,
+            crate::stats::Dependency,
+            crate::grpc::breaker::BreakerState,
+            crate::msg::watchlist::WatchlistHit,
+            crate::msg::blocklist::BlocklistEntry,
+            crate::msg::backfill::BackfillStatus,
+            crate::msg::c2::C2LinkType,
+            crate::msg::c2::C2LinkReport,
+            crate::msg::c2::C2LinkStatus,
+            crate::msg::trail::TrailPoint,
+            crate::msg::trail::AircraftTrail,
+            api::reporter::ReporterStats,
+            api::quarantine::QuarantinedPacket,
+            api::quarantine::QuarantinePage,
+            api::quarantine::ReplayRequest,
+            api::quarantine::ReplayResponse,
+            crate::msg::identity::ConflictKind,
+            crate::msg::identity::IdentityConflict,
+            api::weather::WeatherReport,
+            api::weather::WeatherObservation,
+            api::telemetry::DetectedTelemetry,
+            api::telemetry::PayloadType,
+            api::jwt::ErrorResponse,
+            api::jwt::LoginRequest,
+            api::jwt::LoginResponse,
+            api::jwt::BulkLoginRequest,
+            api::jwt::BulkLoginResult,
+            api::jwt::BulkLoginResponse,
+            api::api_key::ApiKeyResponse,
+            api::blocklist::BlockRequest,
+            api::log_level::LogLevelRequest,
+            api::log_level::LogLevelResponse,
+            api::BinaryPacket
+        )
     ),
     tags(
         (name = "svc-telemetry", description = "svc-telemetry REST API.")
@@ -48,11 +120,19 @@ impl Display for OpenApiError {
 }
 
 /// Create OpenAPI 3.0 Specification File
-pub fn generate_openapi_spec<T>(target: &str) -> Result<(), OpenApiError>
+///
+/// Paths are relative to the configured `rest_base_path`, listed as server URL.
+pub fn generate_openapi_spec<T>(target: &str, rest_base_path: &str) -> Result<(), OpenApiError>
 where
     T: OpenApi,
 {
-    let output = T::openapi().to_pretty_json().map_err(|e| {
+    let mut spec = T::openapi();
+    let base_path = server::base_path(rest_base_path);
+    if !base_path.is_empty() {
+        spec.servers = Some(vec![utoipa::openapi::server::Server::new(base_path)]);
+    }
+
+    let output = spec.to_pretty_json().map_err(|e| {
         rest_error!("failed to export as JSON string: {e}");
         OpenApiError::Json
     })?;
@@ -70,7 +150,7 @@ mod tests {
     #[test]
     fn test_generate_openapi_spec() {
         let target = "/nonsense/";
-        let error = generate_openapi_spec::<ApiDoc>(target).unwrap_err();
+        let error = generate_openapi_spec::<ApiDoc>(target, "/svc-telemetry").unwrap_err();
         assert_eq!(error, OpenApiError::FileWrite);
 
         // TODO(R5): Is it possible to make the JSON export fail?
@@ -83,6 +163,30 @@ mod tests {
         // assert_eq!(error, OpenApiError::Json);
     }
 
+    #[test]
+    fn test_openapi_schemas() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let schemas = &spec["components"]["schemas"];
+        assert!(schemas["ErrorResponse"]["properties"]["message"].is_object());
+        assert_eq!(schemas["BinaryPacket"]["format"], "binary");
+
+        let adsb = &spec["paths"]["/v1/telemetry/adsb"]["post"];
+        let body = &adsb["requestBody"]["content"]["application/octet-stream"];
+        assert_eq!(body["schema"]["$ref"], "#/components/schemas/BinaryPacket");
+
+        let login = &spec["paths"]["/v1/telemetry/login"];
+        assert!(login["get"].is_object());
+        let body = &login["post"]["requestBody"]["content"]["application/json"];
+        assert_eq!(body["schema"]["$ref"], "#/components/schemas/LoginRequest");
+
+        let netrid = &spec["paths"]["/v1/telemetry/netrid"]["post"];
+        let unauthorized = &netrid["responses"]["401"]["content"]["application/json"];
+        assert_eq!(
+            unauthorized["schema"]["$ref"],
+            "#/components/schemas/ErrorResponse"
+        );
+    }
+
     #[test]
     fn test_openapi_error_display() {
         assert_eq!(
@@ -1,31 +1,275 @@
 //! Rest server implementation
 
 use super::api;
-use crate::amqp::init_mq;
-use crate::cache::pool::{GisPool, TelemetryPool};
-use crate::cache::TelemetryPools;
+use crate::amqp::AMQPError;
+use crate::cache::pool::{CacheError, GisPool, TelemetryPool};
+use crate::cache::{batch::DedupCounters, TelemetryPools};
+use crate::clock::SystemClock;
+use crate::config::ServerMode;
+use crate::degradation::{parse_entry, DegradationPolicy};
 use crate::grpc::client::GrpcClients;
+use crate::msg::anomaly::AnomalyDetectors;
+use crate::msg::backfill::BackfillStatus;
+use crate::msg::blocklist::Blocklist;
+use crate::msg::c2::C2LinkMonitor;
+use crate::msg::conformance::ConformanceMonitor;
+use crate::msg::coverage::CoverageMap;
+use crate::msg::filter::VelocityFilters;
+use crate::msg::geofence::{parse_region, Geofence};
+use crate::msg::identifier::{self, IdentifierRules};
+use crate::msg::privacy::Privacy;
+use crate::msg::track::TrackMerger;
+use crate::msg::watchlist::Watchlist;
+use crate::rest::api::jwt::{JwtSettings, JWT_SETTINGS};
+use crate::rest::api::receipt::{ReceiptKeyError, ReceiptSigner};
 use crate::shutdown_signal;
+use crate::sink::SinkKind;
+use crate::startup;
+use crate::stats::Stats;
 use crate::Config;
 use axum::{
     error_handling::HandleErrorLayer,
     extract::Extension,
     http::{HeaderValue, StatusCode},
-    routing::{get, post},
+    routing::{get, post, put},
     BoxError, Router,
 };
+use futures::FutureExt;
 use rand::{distributions::Alphanumeric, Rng};
+use std::fmt::{self, Display, Formatter};
+use std::future::Future;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tower::{
     buffer::BufferLayer,
     limit::{ConcurrencyLimitLayer, RateLimitLayer},
     ServiceBuilder,
 };
+use tower_http::compression::predicate::{And, DefaultPredicate, NotForContentType, Predicate};
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 
+/// Prefix of the current version of the REST routes
+pub const API_VERSION_PATH: &str = "/v1";
+
+/// Entries of the list settings which can't be parsed, and are ignored
+pub fn invalid_entries(config: &Config) -> Vec<String> {
+    let degradation = DegradationPolicy::entries(&config.degradation_policy)
+        .filter(|entry| parse_entry(entry).is_none())
+        .map(|entry| format!("invalid degradation policy entry '{}'", entry.trim()));
+
+    let algorithms = JwtSettings::entries(&config.jwt_algorithms)
+        .filter(|entry| entry.trim().parse::<jsonwebtoken::Algorithm>().is_err())
+        .map(|entry| format!("invalid JWT algorithm '{}'", entry.trim()));
+
+    let regions = Geofence::entries(&config.reporter_regions)
+        .filter(|entry| parse_region(entry).is_none())
+        .map(|entry| format!("invalid reporter region '{}'", entry.trim()));
+
+    let rules = IdentifierRules::entries(&config.identifier_rules)
+        .filter(|entry| identifier::parse_entry(entry).is_none())
+        .map(|entry| format!("invalid identifier rule '{}'", entry.trim()));
+
+    degradation
+        .chain(algorithms)
+        .chain(regions)
+        .chain(rules)
+        .collect()
+}
+
+/// Gzip compression of the responses of the read routes, for clients
+///  accepting it
+///
+/// Event streams are left uncompressed: the encoder buffers its output,
+///  which would hold back their keep-alives.
+pub fn compression_layer() -> CompressionLayer<And<DefaultPredicate, NotForContentType>> {
+    let predicate = DefaultPredicate::new().and(NotForContentType::const_new("text/event-stream"));
+    CompressionLayer::new().compress_when(predicate)
+}
+
+/// Configured prefix of the REST routes as `/prefix`, or empty
+pub fn base_path(configured: &str) -> String {
+    match configured.trim().trim_matches('/') {
+        "" => String::new(),
+        path => format!("/{path}"),
+    }
+}
+
+/// Why the REST server could not start, or stopped serving
+#[derive(Debug)]
+pub enum RestServerError {
+    /// The listening address is invalid
+    InvalidAddress(String),
+
+    /// `rest_cors_allowed_origin` isn't a valid header value
+    InvalidCorsOrigin(String),
+
+    /// A Redis pool could not be created
+    Cache(CacheError),
+
+    /// The RabbitMQ channel could not be created
+    Amqp(AMQPError),
+
+    /// The JWT secret was already set, or could not be
+    JwtSecret(String),
+
+    /// The delivery receipt key could not be loaded
+    ReceiptKey(ReceiptKeyError),
+
+    /// An address could not be bound
+    Bind(SocketAddr, hyper::Error),
+
+    /// Serving failed
+    Serve(hyper::Error),
+}
+
+impl RestServerError {
+    /// Stable code of the error, for logs and statistics
+    pub fn code(&self) -> &'static str {
+        match self {
+            RestServerError::InvalidAddress(_) => "rest_address",
+            RestServerError::InvalidCorsOrigin(_) => "rest_cors_origin",
+            RestServerError::Cache(e) => e.code(),
+            RestServerError::Amqp(e) => e.code(),
+            RestServerError::JwtSecret(_) => "rest_jwt_secret",
+            RestServerError::ReceiptKey(_) => "rest_receipt_key",
+            RestServerError::Bind(..) => "rest_bind",
+            RestServerError::Serve(_) => "rest_serve",
+        }
+    }
+}
+
+impl Display for RestServerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RestServerError::InvalidAddress(e) => write!(f, "invalid address: {e}"),
+            RestServerError::InvalidCorsOrigin(e) => {
+                write!(f, "invalid cors_allowed_origin address: {e}")
+            }
+            RestServerError::Cache(e) => write!(f, "could not create redis pool: {e}"),
+            RestServerError::Amqp(e) => write!(f, "could not create RabbitMQ channel: {e}"),
+            RestServerError::JwtSecret(e) => write!(f, "could not set JWT_SECRET: {e}"),
+            RestServerError::ReceiptKey(e) => {
+                write!(f, "could not load the delivery receipt key: {e}")
+            }
+            RestServerError::Bind(addr, e) => write!(f, "could not bind {addr}: {e}"),
+            RestServerError::Serve(e) => write!(f, "could not start server: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for RestServerError {}
+
+impl From<CacheError> for RestServerError {
+    fn from(e: CacheError) -> Self {
+        RestServerError::Cache(e)
+    }
+}
+
+impl From<AMQPError> for RestServerError {
+    fn from(e: AMQPError) -> Self {
+        RestServerError::Amqp(e)
+    }
+}
+
+/// Binds the REST server to an address, tuned by the HTTP settings of the
+///  configuration
+///
+/// HTTP/2 clients multiplex their requests on one connection instead of
+///  opening a connection per concurrent request, each with its own
+///  handshake. Idle connections are probed so that those of vanished
+///  clients are closed.
+pub fn bind(
+    addr: &SocketAddr,
+    config: &Config,
+) -> Result<hyper::server::Builder<hyper::server::conn::AddrIncoming>, hyper::Error> {
+    let keep_alive = match config.rest_keep_alive_interval_ms {
+        0 => None,
+        ms => Some(Duration::from_millis(ms.into())),
+    };
+
+    Ok(axum::Server::try_bind(addr)?
+        .http1_only(!config.rest_http2_enabled)
+        .http2_max_concurrent_streams(config.rest_http2_max_concurrent_streams)
+        .http2_keep_alive_interval(keep_alive)
+        .http2_keep_alive_timeout(Duration::from_millis(
+            config.rest_keep_alive_timeout_ms.into(),
+        ))
+        .tcp_keepalive(keep_alive))
+}
+
+/// Serves the routes under the configured prefix, versioned
+///
+/// Unversioned routes are kept as aliases of the v1 routes for one release.
+fn versioned(api: Router, base_path: &str) -> Router {
+    let app = Router::new().nest(&format!("{base_path}{API_VERSION_PATH}"), api.clone());
+    match base_path.is_empty() {
+        true => app.merge(api),
+        false => app.nest(base_path, api),
+    }
+}
+
+/// Serves an application on an address until the shutdown signal
+async fn serve(
+    addr: &SocketAddr,
+    config: &Config,
+    app: Router,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), RestServerError> {
+    bind(addr, config)
+        .map_err(|e| RestServerError::Bind(*addr, e))?
+        .serve(app.into_make_service())
+        .with_graceful_shutdown(shutdown)
+        .await
+        .map_err(RestServerError::Serve)
+}
+
+/// Admin routes, requiring the admin secret and kept out of the telemetry
+///  statistics
+fn admin_routes() -> Router {
+    Router::new()
+        .route("/admin/log_level", put(api::log_level::log_level))
+        .route("/admin/quarantine", get(api::quarantine::quarantine))
+        .route(
+            "/admin/api-keys/:reporter",
+            post(api::api_key::issue_api_key).delete(api::api_key::revoke_api_key),
+        )
+        .route("/admin/blocklist", get(api::blocklist::blocklist))
+        .route(
+            "/admin/blocklist/:identifier",
+            put(api::blocklist::block).delete(api::blocklist::unblock),
+        )
+        .route(
+            "/admin/backfill",
+            get(api::backfill::backfill_status).post(api::backfill::backfill),
+        )
+        .route(
+            "/admin/identity-conflicts",
+            get(api::identity::identity_conflicts),
+        )
+        .route(
+            "/admin/quarantine/replay",
+            post(api::quarantine::replay_quarantine),
+        )
+        .route(
+            "/admin/reporters/:identifier",
+            get(api::reporter::reporter_stats).delete(api::reporter::reset_reporter),
+        )
+        .route("/admin/watchlist/hits", get(api::watchlist::watchlist_hits))
+        .route(
+            "/admin/watchlist/:identifier",
+            put(api::watchlist::watch).delete(api::watchlist::unwatch),
+        )
+        .route("/admin/snapshot", get(api::snapshot::snapshot))
+        .route_layer(axum::middleware::from_fn(api::admin::authorize))
+}
+
 /// Starts the REST API server for this microservice
 ///
+/// Errors are logged with their code before being returned.
+///
 /// # Example:
 /// ```
 /// use svc_telemetry::rest::server::rest_server;
@@ -43,19 +287,27 @@ use tower_http::trace::TraceLayer;
 pub async fn rest_server(
     config: Config,
     shutdown_rx: Option<tokio::sync::oneshot::Receiver<()>>,
-) -> Result<(), ()> {
+) -> Result<(), RestServerError> {
     rest_info!("entry.");
+    start(config, shutdown_rx).await.inspect_err(|e| {
+        rest_error!("[{}] {e}, exiting.", e.code());
+    })
+}
+
+/// Starts the REST API server, see [`rest_server`]
+async fn start(
+    config: Config,
+    shutdown_rx: Option<tokio::sync::oneshot::Receiver<()>>,
+) -> Result<(), RestServerError> {
     let rest_port = config.docker_port_rest;
-    let full_rest_addr: SocketAddr = format!("[::]:{}", rest_port).parse().map_err(|e| {
-        rest_error!("invalid address: {:?}, exiting.", e);
-    })?;
+    let full_rest_addr: SocketAddr = format!("[::]:{}", rest_port)
+        .parse()
+        .map_err(|e| RestServerError::InvalidAddress(format!("{e:?}")))?;
 
     let cors_allowed_origin = config
         .rest_cors_allowed_origin
         .parse::<HeaderValue>()
-        .map_err(|e| {
-            rest_error!("invalid cors_allowed_origin address: {:?}, exiting.", e);
-        })?;
+        .map_err(|e| RestServerError::InvalidCorsOrigin(format!("{e:?}")))?;
 
     // Rate limiting
     let rate_limit = config.rest_request_limit_per_second as u64;
@@ -71,27 +323,162 @@ pub async fn rest_server(
         }))
         .layer(BufferLayer::new(100))
         .layer(ConcurrencyLimitLayer::new(concurrency_limit))
-        .layer(RateLimitLayer::new(
-            rate_limit,
-            std::time::Duration::from_secs(1),
-        ));
+        .layer(RateLimitLayer::new(rate_limit, Duration::from_secs(1)));
 
     //
     // Extensions
     //
 
     // Redis Pools
+    let stats = Stats::default();
     let tlm_pools = TelemetryPools {
-        adsb: TelemetryPool::new(config.clone(), "tlm:adsb").await?,
-        netrid: TelemetryPool::new(config.clone(), "tlm:netrid").await?,
+        adsb: TelemetryPool::new(config.clone(), "tlm:adsb")
+            .await?
+            .with_stats(stats.clone()),
+        netrid: TelemetryPool::new(config.clone(), "tlm:netrid")
+            .await?
+            .with_stats(stats.clone()),
     };
 
-    let gis_pool = GisPool::new(config.clone()).await?;
+    let gis_pool = GisPool::new(config.clone())
+        .await?
+        .with_stats(stats.clone());
+
+    let weather_pool = TelemetryPool::new(config.clone(), "tlm:weather")
+        .await?
+        .with_stats(stats.clone());
+
+    let snapshot_pool = TelemetryPool::new(config.clone(), "tlm:snapshot")
+        .await?
+        .with_stats(stats.clone());
+
+    // Redis and RabbitMQ may still be starting, as may this pod
+    startup::wait_for_redis(&config, &tlm_pools.adsb).await?;
 
     // RabbitMQ Channel
-    let mq_channel = init_mq(config.clone()).await.map_err(|e| {
-        rest_error!("could not create RabbitMQ Channel: {e}");
-    })?;
+    let mq_channel = startup::wait_for_amqp(&config).await?;
+    startup::set_ready();
+
+    // Time read by the handlers and background tasks
+    let clock = SystemClock::shared();
+
+    // Ordering and merging of position reports per aircraft
+    let tracks = TrackMerger::shared(config.track_merge_window_ms);
+
+    // Smoothing of velocities per aircraft
+    let filters = VelocityFilters::shared(
+        config.velocity_filter_enabled,
+        config.velocity_filter_alpha,
+        config.velocity_filter_beta,
+    );
+
+    // Tracks are only updated by instances pushing to the backends
+    #[cfg(not(test))]
+    if config.prediction_enabled && config.mode == ServerMode::All {
+        tokio::spawn(crate::amqp::predict::prediction_loop(
+            config.clone(),
+            tracks.clone(),
+            mq_channel.clone(),
+            clock.clone(),
+        ));
+    }
+
+    #[cfg(not(test))]
+    if config.snapshot_enabled && config.mode == ServerMode::All {
+        tokio::spawn(crate::cache::snapshot::snapshot_loop(
+            config.clone(),
+            tracks.clone(),
+            snapshot_pool.clone(),
+        ));
+    }
+
+    // Loss of link detection, C2 link states are only reported to
+    //  instances pushing to the backends
+    let c2_links = C2LinkMonitor::shared(config.c2_link_timeout_ms);
+    #[cfg(not(test))]
+    if config.c2_link_timeout_ms > 0 && config.mode == ServerMode::All {
+        tokio::spawn(crate::amqp::c2::c2_link_loop(
+            c2_links.clone(),
+            mq_channel.clone(),
+            stats.clone(),
+            clock.clone(),
+        ));
+    }
+
+    // Coverage of the receivers, recorded by the coverage sink
+    let sinks = SinkKind::parse_list(&config.telemetry_sinks);
+    let coverage = CoverageMap::shared(config.coverage_geohash_precision as usize);
+    #[cfg(not(test))]
+    if sinks.contains(&SinkKind::Coverage)
+        && config.coverage_summary_interval_ms > 0
+        && config.mode == ServerMode::All
+    {
+        tokio::spawn(crate::amqp::coverage::coverage_loop(
+            coverage.clone(),
+            mq_channel.clone(),
+            stats.clone(),
+            config.coverage_summary_interval_ms,
+            clock.clone(),
+        ));
+    }
+
+    for entry in invalid_entries(&config) {
+        rest_warn!("{entry} ignored.");
+    }
+
+    // Packets beyond the svc-storage insert budget, or buffered while
+    //  svc-storage is down, are stored later
+    let degradation = DegradationPolicy::new(&config.degradation_policy);
+    let grpc_clients = GrpcClients::default(config.clone());
+    #[cfg(not(test))]
+    if (grpc_clients.storage_budget.is_limited() || degradation.buffers_storage())
+        && sinks.contains(&SinkKind::Storage)
+        && config.mode == ServerMode::All
+    {
+        let tlm_pool = TelemetryPool::new(config.clone(), "tlm:adsb").await?;
+        tokio::spawn(crate::sink::storage::backlog_loop(
+            grpc_clients.clone(),
+            tlm_pool,
+            stats.clone(),
+            crate::dispatcher::consumer_name(),
+        ));
+    }
+
+    // Overflowing queues drop messages without failing the publishes
+    #[cfg(not(test))]
+    if config.amqp_queue_poll_interval_ms > 0 {
+        tokio::spawn(crate::amqp::queue::depth_loop(
+            config.clone(),
+            stats.clone(),
+        ));
+    }
+
+    // Ingest degradation is alerted on even when /health isn't probed
+    #[cfg(not(test))]
+    if config.health_check_interval_ms > 0 {
+        tokio::spawn(crate::amqp::system::health_loop(
+            grpc_clients.clone(),
+            tlm_pools.adsb.clone(),
+            mq_channel.clone(),
+            stats.clone(),
+            config.health_check_interval_ms,
+            clock.clone(),
+        ));
+    }
+
+    // Keys of aircraft which stopped reporting, the snapshot is only
+    //  written by instances pushing to the backends
+    #[cfg(not(test))]
+    if config.retention_purge_interval_ms > 0 {
+        let snapshot = config.snapshot_enabled && config.mode == ServerMode::All;
+        tokio::spawn(crate::cache::retention::retention_loop(
+            config.clone(),
+            vec![tlm_pools.adsb.clone(), tlm_pools.netrid.clone()],
+            snapshot.then(|| snapshot_pool.clone()),
+            stats.clone(),
+            clock.clone(),
+        ));
+    }
 
     // TODO(R5): Replace with PKI certificates
     // Temporarily set JWT token to a random string
@@ -106,61 +493,469 @@ pub async fn rest_server(
         Err(tokio::sync::SetError::AlreadyInitializedError(_)) => {
             const ERROR_STR: &str = "JWT_SECRET already set.";
             #[cfg(not(test))]
-            {
-                rest_error!("{}", ERROR_STR);
-                return Err(());
-            }
+            return Err(RestServerError::JwtSecret(ERROR_STR.to_string()));
 
             #[cfg(test)]
             rest_warn!("{}", ERROR_STR);
         }
-        Err(e) => {
-            rest_error!("could not set JWT_SECRET: {}", e);
-            return Err(());
-        }
+        Err(e) => return Err(RestServerError::JwtSecret(e.to_string())),
     }
 
     rest_info!("set JWT_SECRET.");
 
+    if JWT_SETTINGS.set(JwtSettings::new(&config)).is_err() {
+        rest_warn!("JWT settings already set.");
+    }
+
+    if !config.jwt_jwks_url.is_empty() {
+        tokio::spawn(api::jwt::jwks_loop(
+            config.jwt_jwks_url.clone(),
+            config.jwt_jwks_refresh_interval_ms,
+        ));
+    }
+
+    let receipts = ReceiptSigner::load(&config).map_err(RestServerError::ReceiptKey)?;
+
     //
     // Create Server
     //
-    let grpc_clients = GrpcClients::default(config.clone());
-    let app = Router::new()
-        // must be first with its route layer
-        .route("/telemetry/netrid", post(api::netrid::network_remote_id))
+    let identifiers = IdentifierRules::new(&config.identifier_rules);
+    let pipeline = api::Pipeline {
+        config: Arc::new(config.clone()),
+        mq_channel,
+        dedup: DedupCounters::new(&tlm_pools, config.dedup_batch_window_ms),
+        tlm_pools,
+        gis_pool,
+        weather_pool,
+        snapshot_pool,
+        grpc_clients,
+        tracks,
+        filters,
+        stats: stats.clone(),
+        watchlist: Watchlist::shared(&identifiers.resolve_list(&config.watchlist)),
+        blocklist: Arc::new(Blocklist::new(&identifiers.resolve_list(&config.blocklist))),
+        backfill: BackfillStatus::shared(),
+        c2_links,
+        coverage,
+        sinks: Arc::default(),
+        privacy: Arc::new(Privacy::new(&config)),
+        identifiers: Arc::new(identifiers),
+        geofence: Arc::new(Geofence::new(&config.reporter_regions)),
+        anomalies: Arc::new(AnomalyDetectors::new(&config)),
+        conformance: ConformanceMonitor::shared(config.conformance_refresh_ms),
+        clock,
+        degradation,
+        receipts: receipts.map(Arc::new),
+    }
+    .with_sinks(&sinks);
+
+    // In ingest mode, received telemetry is queued for dispatchers
+    let (netrid_handler, relay_handler, adsb_handler, telemetry_handler) = match config.mode {
+        ServerMode::Ingest => (
+            post(api::netrid::network_remote_id_ingest),
+            post(api::netrid::network_remote_id_relay_ingest),
+            post(api::adsb::adsb_ingest),
+            post(api::telemetry::telemetry_ingest),
+        ),
+        _ => (
+            post(api::netrid::network_remote_id),
+            post(api::netrid::network_remote_id_relay),
+            post(api::adsb::adsb),
+            post(api::telemetry::telemetry),
+        ),
+    };
+
+    // OGN beacons, UAT messages, health, C2 link and weather reports and
+    //  heartbeats are not queued, they are only served by instances pushing
+    //  to the backends
+    let (
+        ogn_handler,
+        uat_handler,
+        health_report_handler,
+        c2_status_handler,
+        weather_handler,
+        heartbeat_handler,
+    ) = match config.mode {
+        ServerMode::Ingest => (
+            post(|| async { StatusCode::NOT_IMPLEMENTED }),
+            post(|| async { StatusCode::NOT_IMPLEMENTED }),
+            post(|| async { StatusCode::NOT_IMPLEMENTED }),
+            post(|| async { StatusCode::NOT_IMPLEMENTED }),
+            post(|| async { StatusCode::NOT_IMPLEMENTED }),
+            post(|| async { StatusCode::NOT_IMPLEMENTED }),
+        ),
+        _ => (
+            post(api::ogn::ogn),
+            post(api::uat::uat),
+            post(api::health_report::health_report),
+            post(api::c2::c2_status),
+            post(api::weather::weather),
+            post(api::heartbeat::heartbeat),
+        ),
+    };
+
+    // The GET login is deprecated, clients and proxies may drop its body
+    let login_handler = match config.rest_legacy_login_enabled {
+        true => get(api::jwt::login).post(api::jwt::login_json),
+        false => post(api::jwt::login_json),
+    };
+
+    // signed delivery receipts of Remote ID packets, once authenticated
+    let receipt_layer = axum::middleware::from_fn(api::receipt::sign);
+
+    // must be first with their route layer
+    let mut app = Router::new()
+        .route("/telemetry", telemetry_handler.layer(receipt_layer.clone()))
+        .route(
+            "/telemetry/netrid",
+            netrid_handler.layer(receipt_layer.clone()),
+        )
+        .route("/telemetry/health-report", health_report_handler)
+        .route("/telemetry/c2-status", c2_status_handler)
+        .route("/telemetry/weather", weather_handler)
+        .route("/telemetry/heartbeat", heartbeat_handler);
+    if config.netrid_relay_enabled {
+        app = app.route(
+            "/telemetry/netrid/relay",
+            relay_handler.layer(receipt_layer),
+        );
+    }
+
+    // telemetry reports and logins, alone on the REST port if an admin port is set
+    let api = app
+        // runs once authenticated
+        .route_layer(axum::middleware::from_fn(api::session::validate))
         .route_layer(axum::middleware::from_fn(crate::rest::api::jwt::auth))
         // other routes after route_layer not affected
+        // feeders without login may authenticate by api key
+        .route(
+            "/telemetry/adsb",
+            adsb_handler.layer(axum::middleware::from_fn(api::api_key::authenticate)),
+        )
+        // text encoded packets of telemetry routes
+        .route_layer(axum::middleware::from_fn(api::encoding::decode_text))
+        // sentences, not encoded packets
+        .route("/telemetry/ogn", ogn_handler)
+        .route("/telemetry/uat", uat_handler)
+        // handling time of telemetry routes only
+        .route_layer(axum::middleware::from_fn_with_state(
+            stats,
+            api::stats::latency,
+        ))
+        .route("/telemetry/login", login_handler)
+        .route("/telemetry/login/bulk", post(api::jwt::login_bulk));
+
+    // read routes, whose responses may be large, and admin routes, served
+    //  on the admin port if set
+    let read = Router::new()
         .route("/health", get(api::health::health_check))
-        .route("/telemetry/login", get(crate::rest::api::jwt::login))
-        .route("/telemetry/adsb", post(api::adsb::adsb))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(cors_allowed_origin)
-                .allow_headers(Any)
-                .allow_methods(Any),
+        .route("/telemetry/stats", get(api::stats::stats))
+        .route("/telemetry/coverage", get(api::coverage::coverage))
+        .route("/telemetry/receipts/keys", get(api::receipt::receipt_keys))
+        .route(
+            "/telemetry/c2-status/:identifier",
+            get(api::c2::latest_c2_status),
         )
-        .layer(limit_middleware)
-        .layer(Extension(tlm_pools))
-        .layer(Extension(gis_pool))
-        .layer(Extension(mq_channel))
-        .layer(Extension(grpc_clients));
+        .route(
+            "/telemetry/aircraft/:identifier/track",
+            get(api::trail::aircraft_track),
+        )
+        .route(
+            "/telemetry/weather/:station",
+            get(api::weather::latest_weather),
+        )
+        .merge(admin_routes());
 
-    axum::Server::bind(&full_rest_addr)
-        .serve(app.into_make_service())
-        .with_graceful_shutdown(shutdown_signal("rest", shutdown_rx))
-        .await
-        .map_err(|e| {
-            rest_error!("could not start server: {}", e);
-        })?;
+    let read = match config.rest_compression_enabled {
+        true => read.layer(compression_layer()),
+        false => read,
+    };
+
+    // published telemetry held in memory, see the `local` feature
+    #[cfg(all(not(test), feature = "memory_backends"))]
+    let read = read.route("/dev/queues/:queue", get(api::dev::queue_messages));
+
+    let base_path = base_path(&config.rest_base_path);
+    let layers = |api: Router| {
+        versioned(api, &base_path)
+            .layer(
+                CorsLayer::new()
+                    .allow_origin(cors_allowed_origin.clone())
+                    .allow_headers(Any)
+                    .allow_methods(Any),
+            )
+            .layer(axum::middleware::from_fn(api::request_id::request_context))
+            .layer(limit_middleware.clone())
+            .layer(Extension(pipeline.clone()))
+    };
+
+    // Both listeners stop on the same signal
+    let shutdown = shutdown_signal("rest", shutdown_rx).shared();
+
+    match config.rest_admin_port {
+        0 => {
+            serve(&full_rest_addr, &config, layers(api.merge(read)), shutdown).await?;
+            rest_info!("hosted at: {}{}.", full_rest_addr, base_path);
+        }
+        admin_port => {
+            let admin_addr = SocketAddr::new(full_rest_addr.ip(), admin_port);
+            let ingest = serve(&full_rest_addr, &config, layers(api), shutdown.clone());
+            let admin = serve(&admin_addr, &config, layers(read), shutdown);
+            futures::try_join!(ingest, admin)?;
+            rest_info!(
+                "hosted at: {}{} (admin: {}{}).",
+                full_rest_addr,
+                base_path,
+                admin_addr,
+                base_path
+            );
+        }
+    }
 
-    rest_info!("hosted at: {}.", full_rest_addr);
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use lib_common::time::Utc;
+
+    #[test]
+    fn test_error_codes() {
+        let e = RestServerError::from(CacheError::PoolExhausted);
+        assert_eq!(e.code(), "cache_pool_exhausted");
+        assert_eq!(
+            e.to_string(),
+            "could not create redis pool: No connection of the redis pool became free in time."
+        );
+
+        let e = RestServerError::from(AMQPError::CouldNotConnect);
+        assert_eq!(e.code(), "amqp_connect");
+        assert_eq!(
+            RestServerError::InvalidCorsOrigin("x".to_string()).code(),
+            "rest_cors_origin"
+        );
+    }
+
+    #[test]
+    fn test_base_path() {
+        assert_eq!(base_path(""), "");
+        assert_eq!(base_path("/"), "");
+        assert_eq!(base_path("svc-telemetry"), "/svc-telemetry");
+        assert_eq!(
+            base_path(" /gateway/svc-telemetry/ "),
+            "/gateway/svc-telemetry"
+        );
+    }
+
+    #[test]
+    fn test_invalid_entries() {
+        assert!(invalid_entries(&Config::default()).is_empty());
+
+        let config = Config {
+            degradation_policy: "redis=degrade,amqp=buffer".to_string(),
+            jwt_algorithms: "HS256, XX1".to_string(),
+            reporter_regions: "station-1=52.3,4.8,50;station-2".to_string(),
+            identifier_rules: "icao.pad=6,icao.pad=x".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            invalid_entries(&config),
+            vec![
+                "invalid degradation policy entry 'amqp=buffer'",
+                "invalid JWT algorithm 'XX1'",
+                "invalid reporter region 'station-2'",
+                "invalid identifier rule 'icao.pad=x'",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_versioned() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use tower::ServiceExt;
+
+        let status = |base_path: &str, path: &str| {
+            let router = versioned(Router::new().route("/x", get(|| async { "x" })), base_path);
+            let request = Request::get(path).body(Body::empty()).unwrap();
+            async move { router.oneshot(request).await.unwrap().status() }
+        };
+
+        assert_eq!(status("", "/v1/x").await, StatusCode::OK);
+        assert_eq!(status("", "/x").await, StatusCode::OK);
+        assert_eq!(status("/p", "/p/v1/x").await, StatusCode::OK);
+        assert_eq!(status("/p", "/p/x").await, StatusCode::OK);
+        assert_eq!(status("/p", "/x").await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_compression_layer() {
+        use axum::body::Body;
+        use axum::http::{header, Request};
+        use tower::ServiceExt;
+
+        let body = "x".repeat(1000);
+        let events = format!("data: {body}\n\n");
+        let router: Router = Router::new()
+            .route("/json", get(move || async move { axum::Json(body) }))
+            .route(
+                "/events",
+                get(move || async move { ([(header::CONTENT_TYPE, "text/event-stream")], events) }),
+            )
+            .layer(compression_layer());
+
+        let encoding = |path: &str, accepted: &str| {
+            let request = Request::builder()
+                .uri(path)
+                .header(header::ACCEPT_ENCODING, accepted)
+                .body(Body::empty())
+                .unwrap();
+
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                response.headers().get(header::CONTENT_ENCODING).cloned()
+            }
+        };
+
+        assert_eq!(encoding("/json", "gzip").await.unwrap(), "gzip");
+        assert_eq!(encoding("/json", "identity").await, None);
+        assert_eq!(encoding("/events", "gzip").await, None);
+    }
+
+    /// Status of an admin route, sending the extra header if set
+    async fn admin_status(method: &str, path: &str, extra: Option<(&str, &str)>) -> StatusCode {
+        use axum::body::Body;
+        use axum::http::{header, Request};
+        use tower::ServiceExt;
+
+        let config = Config {
+            admin_secret: "admin-secret".to_string(),
+            ..Default::default()
+        };
+        let pipeline = api::test_pipeline(config).await;
+        let router = admin_routes().layer(Extension(pipeline));
+
+        let mut request = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some((name, value)) = extra {
+            request = request.header(name, value);
+        }
+
+        let request = request.body(Body::from("{}")).unwrap();
+        router.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes() {
+        let secret = api::admin::ADMIN_SECRET_HEADER;
+        let path = "/admin/log_level";
+        assert_eq!(
+            admin_status("PUT", path, None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            admin_status("PUT", path, Some((secret, "guess"))).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        // reporter statistics are reset by operators only
+        assert_eq!(
+            admin_status("DELETE", "/admin/reporters/drone-1", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        assert_eq!(
+            admin_status("PUT", "/admin/watchlist/drone-1", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        assert_eq!(
+            admin_status("GET", "/admin/snapshot", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        assert_eq!(
+            admin_status("PUT", "/admin/blocklist/drone-1", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        assert_eq!(
+            admin_status("POST", "/admin/quarantine/replay", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        assert_eq!(
+            admin_status("GET", "/admin/identity-conflicts", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        assert_eq!(
+            admin_status("POST", "/admin/backfill", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        // tokens of aircraft logins don't make an operator
+        let _ = api::jwt::JWT_SECRET.set("test".to_string());
+        let token = api::jwt::Claim::create("drone-1".to_string(), Utc::now()).unwrap();
+        let bearer = format!("Bearer {token}");
+        assert_eq!(
+            admin_status(
+                "POST",
+                "/admin/api-keys/sdr-station-1",
+                Some(("authorization", &bearer))
+            )
+            .await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        // the body lacks the target
+        assert_eq!(
+            admin_status("PUT", path, Some((secret, "admin-secret"))).await,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bind() {
+        use axum::body::Body;
+        use axum::http::Request;
+
+        let served = |config: Config| async move {
+            let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+            let server = bind(&addr, &config).unwrap().serve(
+                Router::new()
+                    .route("/", get(|| async { "ok" }))
+                    .into_make_service(),
+            );
+            let addr = server.local_addr();
+            tokio::spawn(server);
+
+            // prior knowledge HTTP/2 client
+            let client = hyper::Client::builder()
+                .http2_only(true)
+                .build_http::<Body>();
+            let request = Request::get(format!("http://{addr}/"))
+                .body(Body::empty())
+                .unwrap();
+            client
+                .request(request)
+                .await
+                .map(|response| response.status())
+        };
+
+        assert_eq!(served(Config::default()).await.unwrap(), StatusCode::OK);
+
+        let config = Config {
+            rest_http2_enabled: false,
+            ..Default::default()
+        };
+        assert!(served(config).await.is_err());
+    }
 
     #[tokio::test]
     async fn test_server_start_and_shutdown() {
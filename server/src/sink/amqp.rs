@@ -0,0 +1,423 @@
+//! RabbitMQ sink
+
+use super::{EventData, EventSource, SinkError, TelemetryEvent, TelemetrySink};
+use crate::amqp::envelope::{ProcessingLatency, TelemetryEnvelope};
+use crate::clock::SharedClock;
+use crate::stats::{Dependency, Stats};
+use futures::future::BoxFuture;
+use lapin::types::{AMQPValue, LongString};
+use lib_common::time::{DateTime, Utc};
+use serde::Serialize;
+
+/// AMQP message header holding the login session of the reporter
+pub const AMQP_HEADER_SESSION: &str = "session";
+
+/// AMQP message header holding the flight plan or mission declared by the
+///  session of the reporter
+pub const AMQP_HEADER_MISSION: &str = "mission";
+
+/// AMQP message header holding when the packet was received, in
+///  milliseconds since the Unix epoch
+pub const AMQP_HEADER_RECEIVED_MS: &str = "received_ms";
+
+/// AMQP message header holding the microseconds from the reception of
+///  the packet to its decoding
+pub const AMQP_HEADER_DECODE_US: &str = "decode_us";
+
+/// AMQP message header holding the microseconds from the decoding of the
+///  packet to its publication
+pub const AMQP_HEADER_PUBLISH_US: &str = "publish_us";
+
+/// Publishes telemetry to the telemetry exchange
+///
+/// Remote ID identifications, positions, velocities and (scrubbed)
+///  operators are published with the authentication status of the aircraft.
+///  ADS-B identifications carry their emitter category, other ADS-B telemetry
+///  is published as the raw packet. Vehicle health, C2 link and weather
+///  reports are published whatever their source. Publishing is best effort, failures
+///  don't fail the push.
+#[derive(Debug, Clone)]
+pub struct AmqpSink {
+    /// RabbitMQ channel
+    mq_channel: crate::amqp::MqChannel,
+
+    /// Clock timing the publication of events
+    clock: SharedClock,
+
+    /// Statistics of the received telemetry
+    stats: Stats,
+}
+
+impl AmqpSink {
+    /// Publish events on the given channel
+    pub fn new(mq_channel: crate::amqp::MqChannel, clock: SharedClock, stats: Stats) -> Self {
+        AmqpSink {
+            mq_channel,
+            clock,
+            stats,
+        }
+    }
+}
+
+/// Serializes an enveloped item, with the signal metadata and position
+///  accuracy of the event, and the time spent on it until `now`
+fn serialized<T: Serialize>(
+    event: &TelemetryEvent,
+    envelope: TelemetryEnvelope<T>,
+    now: DateTime<Utc>,
+) -> Option<Vec<u8>> {
+    let envelope = envelope.with_latency(event.latency(now));
+    let envelope = match event.signal {
+        Some(signal) => envelope.with_signal(signal),
+        None => envelope,
+    };
+
+    let envelope = match event.accuracy {
+        Some(accuracy) => envelope.with_accuracy(accuracy),
+        None => envelope,
+    };
+
+    let envelope = match event.protocol_version {
+        Some(version) => envelope.with_protocol_version(version as u8),
+        None => envelope,
+    };
+
+    serde_json::to_vec(&envelope)
+        .map_err(|e| sink_warn!("could not serialize {} item: {e}", event.identifier))
+        .ok()
+}
+
+/// Routing key and body an event published at `now` is published with,
+///  if it is published
+fn route(event: &TelemetryEvent, now: DateTime<Utc>) -> Option<(&'static str, Vec<u8>)> {
+    match (&event.source, &event.data) {
+        (EventSource::Netrid, EventData::Identification(item)) => Some((
+            crate::amqp::ROUTING_KEY_NETRID_ID,
+            serialized(event, TelemetryEnvelope::new(item), now)?,
+        )),
+        (EventSource::Netrid, EventData::Position(item)) => Some((
+            crate::amqp::ROUTING_KEY_NETRID_POSITION,
+            serialized(event, TelemetryEnvelope::new(item), now)?,
+        )),
+        (EventSource::Netrid, EventData::Velocity(item)) => Some((
+            crate::amqp::ROUTING_KEY_NETRID_VELOCITY,
+            serialized(event, TelemetryEnvelope::new(item), now)?,
+        )),
+        (EventSource::Netrid, EventData::Operator(item)) => Some((
+            crate::amqp::ROUTING_KEY_NETRID_OPERATOR,
+            serialized(event, TelemetryEnvelope::new(item), now)?,
+        )),
+        (EventSource::Adsb, EventData::Identification(item)) => {
+            let mut envelope = TelemetryEnvelope::new(item);
+            if let Some(category) = &event.emitter_category {
+                envelope = envelope.with_emitter_category(category.clone());
+            }
+
+            Some((
+                crate::amqp::ROUTING_KEY_ADSB_ID,
+                serialized(event, envelope, now)?,
+            ))
+        }
+        (EventSource::Adsb, EventData::Packet(payload)) => {
+            Some((crate::amqp::ROUTING_KEY_ADSB, payload.clone()))
+        }
+        (_, EventData::Health(item)) => Some((
+            crate::amqp::ROUTING_KEY_VEHICLE_HEALTH,
+            serialized(event, TelemetryEnvelope::new(item), now)?,
+        )),
+        (_, EventData::C2Link(item)) => Some((
+            crate::amqp::ROUTING_KEY_C2_STATUS,
+            serialized(event, TelemetryEnvelope::new(item), now)?,
+        )),
+        (_, EventData::Weather(item)) => Some((
+            crate::amqp::ROUTING_KEY_WEATHER,
+            serialized(event, TelemetryEnvelope::new(item), now)?,
+        )),
+        _ => None,
+    }
+}
+
+/// Message properties announcing the authentication status of the
+///  aircraft, and the session of the reporter with its mission
+fn properties(event: &TelemetryEvent) -> lapin::BasicProperties {
+    let properties = match event.authentication {
+        Some(authentication) => authentication.amqp_properties(),
+        None => lapin::BasicProperties::default(),
+    };
+
+    let fields = [
+        (AMQP_HEADER_SESSION, &event.session),
+        (AMQP_HEADER_MISSION, &event.mission),
+    ];
+    if fields.iter().all(|(_, value)| value.is_none()) {
+        return properties;
+    }
+
+    let mut headers = properties.headers().clone().unwrap_or_default();
+    for (header, value) in fields {
+        if let Some(value) = value {
+            headers.insert(
+                header.into(),
+                AMQPValue::LongString(LongString::from(value.as_str())),
+            );
+        }
+    }
+
+    properties.with_headers(headers)
+}
+
+/// Message properties with the time spent on a packet, for packets
+///  published without envelope
+fn with_latency(
+    properties: lapin::BasicProperties,
+    latency: ProcessingLatency,
+) -> lapin::BasicProperties {
+    let mut headers = properties.headers().clone().unwrap_or_default();
+    let fields = [
+        (AMQP_HEADER_RECEIVED_MS, latency.received_ms),
+        (AMQP_HEADER_DECODE_US, latency.decode_us as i64),
+        (AMQP_HEADER_PUBLISH_US, latency.publish_us as i64),
+    ];
+
+    for (header, value) in fields {
+        headers.insert(header.into(), AMQPValue::LongLongInt(value));
+    }
+
+    properties.with_headers(headers)
+}
+
+impl TelemetrySink for AmqpSink {
+    fn name(&self) -> &'static str {
+        "amqp"
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires AMQP backend to test
+    fn push<'a>(&'a self, event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let now = self.clock.now();
+            let Some((routing_key, msg)) = route(event, now) else {
+                return Ok(());
+            };
+
+            let properties = match event.data {
+                EventData::Packet(_) => with_latency(properties(event), event.latency(now)),
+                _ => properties(event),
+            };
+
+            self.mq_channel
+                .basic_publish(
+                    crate::amqp::EXCHANGE_NAME_TELEMETRY,
+                    routing_key,
+                    lapin::options::BasicPublishOptions::default(),
+                    &msg,
+                    properties,
+                )
+                .await
+                .map_err(|e| {
+                    sink_warn!("could not publish to RabbitMQ ({routing_key}): {e}.");
+                    self.stats.record_error(Dependency::Amqp);
+                    SinkError::Failed
+                })?;
+
+            sink_debug!("published to RabbitMQ ({routing_key}).");
+            Ok(())
+        })
+    }
+
+    fn dependency(&self) -> Option<Dependency> {
+        Some(Dependency::Amqp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amqp::envelope::{PositionAccuracy, SignalMetadata};
+    use crate::msg::health::{GpsFix, HealthMessage, HEALTH_MESSAGE_VERSION};
+    use crate::msg::netrid::ProtocolVersion;
+    use crate::msg::privacy::OperatorInfo;
+    use crate::rest::api::signature::{AuthenticationStatus, AMQP_HEADER_AUTHENTICATION};
+    use crate::rest::api::weather::{WeatherObservation, WeatherReport};
+    use svc_gis_client_grpc::prelude::types::*;
+
+    #[test]
+    fn test_route() {
+        let item = AircraftId {
+            identifier: Some("4840d6".to_string()),
+            session_id: None,
+            aircraft_type: AircraftType::Aeroplane,
+            timestamp_network: Utc::now(),
+            timestamp_asset: None,
+        };
+
+        let data = EventData::Identification(item);
+        let event = TelemetryEvent::new(EventSource::Adsb, "4840d6", data.clone(), Utc::now())
+            .with_emitter_category("A1".to_string());
+        let (routing_key, msg) = route(&event, Utc::now()).unwrap();
+        assert_eq!(routing_key, crate::amqp::ROUTING_KEY_ADSB_ID);
+        let msg: serde_json::Value = serde_json::from_slice(&msg).unwrap();
+        assert_eq!(msg["emitter_category"], "A1");
+        assert_eq!(
+            msg["latency"]["received_ms"],
+            event.received.timestamp_millis()
+        );
+
+        let signal = SignalMetadata {
+            rssi_dbm: Some(-90.0),
+            ..Default::default()
+        };
+        let event = TelemetryEvent::new(EventSource::Netrid, "4840d6", data, Utc::now())
+            .with_signal(signal);
+        let (routing_key, msg) = route(&event, Utc::now()).unwrap();
+        assert_eq!(routing_key, crate::amqp::ROUTING_KEY_NETRID_ID);
+        let msg: serde_json::Value = serde_json::from_slice(&msg).unwrap();
+        assert_eq!(msg["signal"]["rssi_dbm"], -90.0);
+
+        let position = AircraftPosition {
+            identifier: "drone-1".to_string(),
+            position: Position {
+                latitude: 52.37,
+                longitude: 4.9,
+                altitude_meters: 100.0,
+            },
+            timestamp_network: Utc::now(),
+            timestamp_asset: None,
+        };
+        let accuracy = PositionAccuracy {
+            horizontal_meters: Some(10.0),
+            vertical_meters: Some(3.0),
+        };
+        let data = EventData::Position(position);
+        let event = TelemetryEvent::new(EventSource::Netrid, "drone-1", data, Utc::now())
+            .with_accuracy(accuracy)
+            .with_protocol_version(ProtocolVersion::F3411_20);
+        let (routing_key, msg) = route(&event, Utc::now()).unwrap();
+        assert_eq!(routing_key, crate::amqp::ROUTING_KEY_NETRID_POSITION);
+        let msg: serde_json::Value = serde_json::from_slice(&msg).unwrap();
+        assert_eq!(msg["accuracy"]["horizontal_meters"], 10.0);
+        assert_eq!(msg["accuracy"]["vertical_meters"], 3.0);
+        assert_eq!(msg["protocol_version"], 1);
+
+        // raw packets are only published for ADS-B
+        let data = EventData::Packet(vec![0x8d, 0x48]);
+        let event = TelemetryEvent::new(EventSource::Adsb, "4840d6", data.clone(), Utc::now());
+        assert_eq!(
+            route(&event, Utc::now()),
+            Some((crate::amqp::ROUTING_KEY_ADSB, vec![0x8d, 0x48]))
+        );
+
+        let event = TelemetryEvent::new(EventSource::Netrid, "4840d6", data, Utc::now());
+        assert_eq!(route(&event, Utc::now()), None);
+
+        let operator = OperatorInfo {
+            identifier: "drone-1".to_string(),
+            operator_id: Some("FIN".to_string()),
+            latitude: Some(52.37),
+            longitude: Some(-4.9),
+            altitude_meters: None,
+            scrubbed: true,
+            timestamp_network: Utc::now(),
+        };
+        let data = EventData::Operator(operator);
+        let event = TelemetryEvent::new(EventSource::Netrid, "drone-1", data, Utc::now());
+        let (routing_key, msg) = route(&event, Utc::now()).unwrap();
+        assert_eq!(routing_key, crate::amqp::ROUTING_KEY_NETRID_OPERATOR);
+        let msg: serde_json::Value = serde_json::from_slice(&msg).unwrap();
+        assert_eq!(msg["data"]["operator_id"], "FIN");
+
+        let health = HealthMessage {
+            version: HEALTH_MESSAGE_VERSION,
+            battery_voltage_mv: 11_100,
+            battery_current_ca: 300,
+            battery_remaining_percent: 40,
+            gps_fix: GpsFix::Fix3d,
+            satellites_visible: 9,
+            hdop: 120,
+            link_rssi_dbm: -80,
+            link_quality_percent: 90,
+            timestamp: 0,
+        };
+        let data = EventData::Health(health.to_health("drone-1", Utc::now()));
+        let event = TelemetryEvent::new(EventSource::Vehicle, "drone-1", data, Utc::now());
+        let (routing_key, msg) = route(&event, Utc::now()).unwrap();
+        assert_eq!(routing_key, crate::amqp::ROUTING_KEY_VEHICLE_HEALTH);
+        let msg: serde_json::Value = serde_json::from_slice(&msg).unwrap();
+        assert_eq!(msg["data"]["gps_fix"], "fix3d");
+
+        let weather = WeatherObservation {
+            station: "vertiport-1".to_string(),
+            report: WeatherReport {
+                wind_speed_mps: 4.5,
+                wind_direction_degrees: 270.0,
+                wind_gust_mps: None,
+                temperature_celsius: 18.5,
+                pressure_hpa: None,
+                humidity_percent: None,
+                timestamp_asset: None,
+            },
+            timestamp_network: Utc::now(),
+        };
+        let data = EventData::Weather(weather);
+        let event = TelemetryEvent::new(EventSource::Station, "vertiport-1", data, Utc::now());
+        let (routing_key, msg) = route(&event, Utc::now()).unwrap();
+        assert_eq!(routing_key, crate::amqp::ROUTING_KEY_WEATHER);
+        let msg: serde_json::Value = serde_json::from_slice(&msg).unwrap();
+        assert_eq!(msg["data"]["station"], "vertiport-1");
+    }
+
+    #[test]
+    fn test_properties() {
+        let data = EventData::Packet(vec![0x8d]);
+        let event = TelemetryEvent::new(EventSource::Netrid, "drone-1", data, Utc::now());
+        assert!(properties(&event).headers().is_none());
+
+        let event = event
+            .with_authentication(AuthenticationStatus::Verified)
+            .with_session("Xk2r9QaZ".to_string());
+        let headers = properties(&event).headers().clone().unwrap();
+        assert!(headers.inner().contains_key(AMQP_HEADER_AUTHENTICATION));
+        assert_eq!(
+            headers.inner().get(AMQP_HEADER_SESSION),
+            Some(&AMQPValue::LongString(LongString::from("Xk2r9QaZ")))
+        );
+        assert!(!headers.inner().contains_key(AMQP_HEADER_MISSION));
+
+        let event = event.with_mission("7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5e".to_string());
+        let headers = properties(&event).headers().clone().unwrap();
+        assert_eq!(
+            headers.inner().get(AMQP_HEADER_MISSION),
+            Some(&AMQPValue::LongString(LongString::from(
+                "7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5e"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_with_latency() {
+        let latency = ProcessingLatency {
+            received_ms: 1_700_000_000_000,
+            decode_us: 250,
+            publish_us: 1200,
+        };
+
+        let properties = lapin::BasicProperties::default().with_content_type("a".into());
+        let properties = with_latency(properties, latency);
+        assert_eq!(properties.content_type().as_ref().unwrap().as_str(), "a");
+
+        let headers = properties.headers().clone().unwrap();
+        let headers = headers.inner();
+        assert_eq!(
+            headers.get(AMQP_HEADER_RECEIVED_MS),
+            Some(&AMQPValue::LongLongInt(1_700_000_000_000))
+        );
+        assert_eq!(
+            headers.get(AMQP_HEADER_DECODE_US),
+            Some(&AMQPValue::LongLongInt(250))
+        );
+        assert_eq!(
+            headers.get(AMQP_HEADER_PUBLISH_US),
+            Some(&AMQPValue::LongLongInt(1200))
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Anomaly sink, running the anomaly detectors on decoded telemetry
+
+use super::{SinkError, TelemetryEvent, TelemetrySink};
+use crate::amqp::envelope::TelemetryEnvelope;
+use crate::msg::anomaly::{Anomaly, AnomalyDetectors};
+use crate::stats::{Dependency, Stats};
+use futures::future::BoxFuture;
+use std::sync::Arc;
+
+/// Inspects events with the anomaly detectors and publishes the anomalies
+///  found to the anomaly queue
+///
+/// Publishing is best effort, failures don't fail the push.
+#[derive(Debug, Clone)]
+pub struct AnomalySink {
+    /// Detectors events are inspected by
+    detectors: Arc<AnomalyDetectors>,
+
+    /// RabbitMQ channel
+    mq_channel: crate::amqp::MqChannel,
+
+    /// Statistics of the received telemetry
+    stats: Stats,
+}
+
+impl AnomalySink {
+    /// Inspect events with the given detectors, publishing on the channel
+    pub fn new(
+        detectors: Arc<AnomalyDetectors>,
+        mq_channel: crate::amqp::MqChannel,
+        stats: Stats,
+    ) -> Self {
+        AnomalySink {
+            detectors,
+            mq_channel,
+            stats,
+        }
+    }
+
+    /// Publishes an anomaly to the anomaly queue
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires AMQP backend to test
+    async fn publish(&self, anomaly: &Anomaly) {
+        let Ok(msg) = serde_json::to_vec(&TelemetryEnvelope::new(anomaly)) else {
+            sink_warn!("could not serialize {} anomaly.", anomaly.detector);
+            return;
+        };
+
+        let _ = self
+            .mq_channel
+            .basic_publish(
+                crate::amqp::EXCHANGE_NAME_TELEMETRY,
+                crate::amqp::ROUTING_KEY_ANOMALY,
+                lapin::options::BasicPublishOptions::default(),
+                &msg,
+                lapin::BasicProperties::default(),
+            )
+            .await
+            .map_err(|e| {
+                sink_warn!("could not publish {} anomaly: {e}.", anomaly.detector);
+                self.stats.record_error(Dependency::Amqp);
+            });
+    }
+}
+
+impl TelemetrySink for AnomalySink {
+    fn name(&self) -> &'static str {
+        "anomaly"
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires AMQP backend to test
+    fn push<'a>(&'a self, event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            for anomaly in self.detectors.inspect(event) {
+                sink_info!(
+                    "{} anomaly of {}: {}.",
+                    anomaly.detector,
+                    anomaly.identifier,
+                    anomaly.description
+                );
+                self.publish(&anomaly).await;
+            }
+
+            Ok(())
+        })
+    }
+}
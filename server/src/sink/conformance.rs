@@ -0,0 +1,224 @@
+//! Conformance sink, checking the positions of aircraft flying a mission
+//!  against the corridor assigned to it
+
+use super::{EventData, SinkError, TelemetryEvent, TelemetrySink};
+use crate::amqp::envelope::TelemetryEnvelope;
+use crate::clock::SharedClock;
+use crate::msg::conformance::{Corridor, Nonconformance, SharedConformance};
+use crate::stats::{Dependency, Stats};
+use futures::future::BoxFuture;
+use hyper::client::HttpConnector;
+use hyper::{Client, StatusCode};
+use std::sync::{Arc, OnceLock};
+
+/// Placeholder of the mission UUID in the corridor URL
+const PLACEHOLDER_MISSION: &str = "{mission}";
+
+/// HTTP client shared by the conformance sinks
+static CLIENT: OnceLock<Client<HttpConnector>> = OnceLock::new();
+
+/// Checks positions reported with a mission against its corridor and
+///  publishes the aircraft leaving it to the nonconformance queue
+///
+/// Corridors are fetched from the planning service when first needed and
+///  again after the refresh interval. Positions of missions whose corridor
+///  could not be fetched aren't checked. Publishing is best effort,
+///  failures don't fail the push.
+#[derive(Debug, Clone)]
+pub struct ConformanceSink {
+    /// Corridors of the missions and aircraft outside them
+    monitor: SharedConformance,
+
+    /// URL of the corridor of a mission, with a placeholder for its UUID
+    url: String,
+
+    /// RabbitMQ channel
+    mq_channel: crate::amqp::MqChannel,
+
+    /// Clock the refresh of corridors is timed with
+    clock: SharedClock,
+
+    /// Statistics of the received telemetry
+    stats: Stats,
+}
+
+impl ConformanceSink {
+    /// Check positions against the corridors fetched from the given URL,
+    ///  publishing on the channel
+    pub fn new(
+        monitor: SharedConformance,
+        url: &str,
+        mq_channel: crate::amqp::MqChannel,
+        clock: SharedClock,
+        stats: Stats,
+    ) -> Self {
+        ConformanceSink {
+            monitor,
+            url: url.to_string(),
+            mq_channel,
+            clock,
+            stats,
+        }
+    }
+
+    /// Fetches the corridor of a mission, None if it has none
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires the planning service to test
+    async fn fetch(&self, mission: &str) -> Result<Option<Corridor>, ()> {
+        let uri = corridor_url(&self.url, mission).parse().map_err(|e| {
+            sink_warn!("invalid corridor URL for mission {mission}: {e}");
+        })?;
+
+        let response = CLIENT
+            .get_or_init(Client::new)
+            .get(uri)
+            .await
+            .map_err(|e| {
+                sink_warn!("could not fetch corridor of mission {mission}: {e}");
+            })?;
+
+        match response.status() {
+            StatusCode::OK => (),
+            StatusCode::NOT_FOUND => {
+                sink_info!("mission {mission} has no corridor.");
+                return Ok(None);
+            }
+            status => {
+                sink_warn!("could not fetch corridor of mission {mission}: {status}");
+                return Err(());
+            }
+        }
+
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(|e| {
+                sink_warn!("could not read corridor of mission {mission}: {e}");
+            })?;
+
+        serde_json::from_slice(&body).map(Some).map_err(|e| {
+            sink_warn!("invalid corridor of mission {mission}: {e}");
+        })
+    }
+
+    /// Corridor of a mission, fetched if not known or due for a refresh
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires the planning service to test
+    async fn corridor(&self, mission: &str) -> Option<Arc<Corridor>> {
+        let now = self.clock.now();
+        let cached = match self.monitor.lock() {
+            Ok(monitor) => monitor.corridor(mission, now),
+            Err(e) => {
+                sink_warn!("could not lock conformance monitor: {e}");
+                return None;
+            }
+        };
+
+        if let Some(corridor) = cached {
+            return corridor;
+        }
+
+        let corridor = self.fetch(mission).await.ok()?;
+        match self.monitor.lock() {
+            Ok(mut monitor) => {
+                monitor.store(mission, corridor, now);
+                monitor.corridor(mission, now).flatten()
+            }
+            Err(e) => {
+                sink_warn!("could not lock conformance monitor: {e}");
+                None
+            }
+        }
+    }
+
+    /// Publishes a nonconformance to the nonconformance queue
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires AMQP backend to test
+    async fn publish(&self, nonconformance: &Nonconformance) {
+        let Ok(msg) = serde_json::to_vec(&TelemetryEnvelope::new(nonconformance)) else {
+            sink_warn!(
+                "could not serialize nonconformance of {}.",
+                nonconformance.identifier
+            );
+            return;
+        };
+
+        let _ = self
+            .mq_channel
+            .basic_publish(
+                crate::amqp::EXCHANGE_NAME_TELEMETRY,
+                crate::amqp::ROUTING_KEY_NONCONFORMANCE,
+                lapin::options::BasicPublishOptions::default(),
+                &msg,
+                lapin::BasicProperties::default(),
+            )
+            .await
+            .map_err(|e| {
+                sink_warn!(
+                    "could not publish nonconformance of {}: {e}.",
+                    nonconformance.identifier
+                );
+                self.stats.record_error(Dependency::Amqp);
+            });
+    }
+}
+
+/// URL of the corridor of a mission
+fn corridor_url(url: &str, mission: &str) -> String {
+    url.replace(PLACEHOLDER_MISSION, mission)
+}
+
+impl TelemetrySink for ConformanceSink {
+    fn name(&self) -> &'static str {
+        "conformance"
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires the planning service and AMQP backend to test
+    fn push<'a>(&'a self, event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let (EventData::Position(position), Some(mission)) = (&event.data, &event.mission)
+            else {
+                return Ok(());
+            };
+
+            let Some(corridor) = self.corridor(mission).await else {
+                return Ok(());
+            };
+
+            let nonconformance = match self.monitor.lock() {
+                Ok(mut monitor) => monitor.observe(&event.identifier, mission, &corridor, position),
+                Err(e) => {
+                    sink_warn!("could not lock conformance monitor: {e}");
+                    None
+                }
+            };
+
+            if let Some(nonconformance) = nonconformance {
+                sink_info!(
+                    "{} left the corridor of mission {mission}: {:?}.",
+                    event.identifier,
+                    nonconformance.deviations
+                );
+                self.publish(&nonconformance).await;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corridor_url() {
+        assert_eq!(
+            corridor_url(
+                "http://svc-scheduler:8000/flight-plans/{mission}/corridor",
+                "7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5e"
+            ),
+            "http://svc-scheduler:8000/flight-plans/7b0c3e4a-1f2d-4c5b-9a8e-0d1c2b3a4f5e/corridor"
+        );
+    }
+}
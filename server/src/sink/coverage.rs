@@ -0,0 +1,93 @@
+//! Coverage sink, aggregating the coverage of the receivers
+
+use super::{EventData, SinkError, TelemetryEvent, TelemetrySink};
+use crate::msg::coverage::SharedCoverage;
+use futures::future::BoxFuture;
+
+/// Records positions received with the location of their receiver into
+///  the coverage map of this instance
+#[derive(Debug, Clone)]
+pub struct CoverageSink {
+    /// Coverage map of this instance
+    coverage: SharedCoverage,
+}
+
+impl CoverageSink {
+    /// Record events into the given coverage map
+    pub fn new(coverage: SharedCoverage) -> Self {
+        CoverageSink { coverage }
+    }
+}
+
+impl TelemetrySink for CoverageSink {
+    fn name(&self) -> &'static str {
+        "coverage"
+    }
+
+    fn push<'a>(&'a self, event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let (EventData::Position(item), Some(signal)) = (&event.data, &event.signal) else {
+                return Ok(());
+            };
+
+            match self.coverage.lock() {
+                Ok(mut coverage) => {
+                    let position = &item.position;
+                    coverage.record(
+                        signal,
+                        position.latitude,
+                        position.longitude,
+                        event.received,
+                    );
+                }
+                Err(e) => sink_warn!("could not lock coverage map: {e}"),
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amqp::envelope::SignalMetadata;
+    use crate::msg::coverage::CoverageMap;
+    use crate::sink::EventSource;
+    use lib_common::time::Utc;
+    use svc_gis_client_grpc::prelude::types::{AircraftPosition, Position};
+
+    #[tokio::test]
+    async fn test_push() {
+        let coverage = CoverageMap::shared(5);
+        let sink = CoverageSink::new(coverage.clone());
+        let position = AircraftPosition {
+            identifier: "4840d6".to_string(),
+            position: Position {
+                latitude: 52.37,
+                longitude: 4.9,
+                altitude_meters: 1000.0,
+            },
+            timestamp_network: Utc::now(),
+            timestamp_asset: None,
+        };
+
+        // positions without signal metadata are ignored
+        let data = EventData::Position(position);
+        let event = TelemetryEvent::new(EventSource::Adsb, "4840d6", data, Utc::now());
+        sink.push(&event).await.unwrap();
+        assert!(coverage.lock().unwrap().cells(None).is_empty());
+
+        let signal = SignalMetadata {
+            receiver_latitude: Some(52.3),
+            receiver_longitude: Some(4.8),
+            rssi_dbm: Some(-70.0),
+            snr_db: None,
+            broadcaster_mac: None,
+        };
+        sink.push(&event.with_signal(signal)).await.unwrap();
+        let cells = coverage.lock().unwrap().cells(None);
+        assert_eq!(cells.len(), 1);
+        assert_eq!(cells[0].rssi_dbm_mean, Some(-70.0));
+    }
+}
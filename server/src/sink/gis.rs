@@ -0,0 +1,65 @@
+//! svc-gis sink
+
+use super::{EventData, SinkError, TelemetryEvent, TelemetrySink};
+use crate::cache::pool::GisPool;
+use crate::stats::{Dependency, Stats};
+use futures::future::BoxFuture;
+
+/// Queues identifications, positions and velocities for svc-gis
+#[derive(Debug, Clone)]
+pub struct GisSink {
+    /// Redis pool of the svc-gis queues
+    gis_pool: GisPool,
+
+    /// Statistics of the received telemetry
+    stats: Stats,
+}
+
+impl GisSink {
+    /// Queue events with the given pool
+    pub fn new(gis_pool: GisPool, stats: Stats) -> Self {
+        GisSink { gis_pool, stats }
+    }
+}
+
+impl TelemetrySink for GisSink {
+    fn name(&self) -> &'static str {
+        "gis"
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires redis backend to test
+    fn push<'a>(&'a self, event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let mut gis_pool = self.gis_pool.clone();
+            let result = match &event.data {
+                EventData::Identification(item) => gis_pool.push(item.clone()).await,
+                EventData::Position(item) => gis_pool.push(item.clone()).await,
+                EventData::Velocity(item) => gis_pool.push(item.clone()).await,
+                EventData::Packet(_)
+                | EventData::Operator(_)
+                | EventData::Health(_)
+                | EventData::C2Link(_)
+                | EventData::Weather(_) => return Ok(()),
+            };
+
+            result.map_err(|e| {
+                sink_warn!(
+                    "[{}] could not queue {} item for svc-gis: {e}",
+                    e.code(),
+                    event.identifier
+                );
+                self.stats.record_error(Dependency::Gis);
+                self.stats.record_error_code(e.code());
+                SinkError::Failed
+            })?;
+
+            sink_debug!("queued {} item for svc-gis.", event.identifier);
+            Ok(())
+        })
+    }
+
+    fn dependency(&self) -> Option<Dependency> {
+        Some(Dependency::Gis)
+    }
+}
@@ -0,0 +1,178 @@
+//! Kafka sink, through a Kafka REST proxy
+
+use super::{EventData, SinkError, TelemetryEvent, TelemetrySink};
+use crate::stats::{Dependency, Stats};
+use futures::future::BoxFuture;
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Method, Request};
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Content type of JSON records posted to the REST proxy
+const CONTENT_TYPE_KAFKA_JSON: &str = "application/vnd.kafka.json.v2+json";
+
+/// HTTP client shared by the Kafka sinks
+static CLIENT: OnceLock<Client<HttpConnector>> = OnceLock::new();
+
+/// Produces telemetry to a Kafka topic, keyed by aircraft
+///
+/// Records are produced one per event. A REST proxy which doesn't take a
+///  record within the configured time fails the push, so a hung proxy
+///  doesn't hold up the other sinks.
+#[derive(Debug, Clone)]
+pub struct KafkaSink {
+    /// Base URL of the Kafka REST proxy
+    url: String,
+
+    /// Topic records are produced to
+    topic: String,
+
+    /// Time the REST proxy is given to take a record
+    timeout: Duration,
+
+    /// Statistics of the received telemetry
+    stats: Stats,
+}
+
+impl KafkaSink {
+    /// Produce events to a topic of the REST proxy at the given URL,
+    ///  waiting at most `timeout_ms` for each record
+    pub fn new(url: &str, topic: &str, timeout_ms: u32, stats: Stats) -> Self {
+        KafkaSink {
+            url: url.trim_end_matches('/').to_string(),
+            topic: topic.to_string(),
+            timeout: Duration::from_millis(timeout_ms as u64),
+            stats,
+        }
+    }
+}
+
+/// Body of the REST proxy request producing an event
+fn records(event: &TelemetryEvent) -> Result<Value, serde_json::Error> {
+    let (kind, data) = match &event.data {
+        EventData::Identification(item) => ("identification", serde_json::to_value(item)?),
+        EventData::Position(item) => ("position", serde_json::to_value(item)?),
+        EventData::Velocity(item) => ("velocity", serde_json::to_value(item)?),
+        EventData::Packet(payload) => ("packet", Value::String(hex::encode(payload))),
+        EventData::Operator(item) => ("operator", serde_json::to_value(item)?),
+        EventData::Health(item) => ("health", serde_json::to_value(item)?),
+        EventData::C2Link(item) => ("c2_link", serde_json::to_value(item)?),
+        EventData::Weather(item) => ("weather", serde_json::to_value(item)?),
+    };
+
+    Ok(json!({
+        "records": [{
+            "key": event.identifier,
+            "value": {
+                "source": event.source,
+                "type": kind,
+                "received": event.received,
+                "emitter_category": event.emitter_category,
+                "authentication": event.authentication,
+                "session": event.session,
+                "mission": event.mission,
+                "signal": event.signal,
+                "accuracy": event.accuracy,
+                "protocol_version": event.protocol_version.map(|version| version as u8),
+                "data": data,
+            }
+        }]
+    }))
+}
+
+impl TelemetrySink for KafkaSink {
+    fn name(&self) -> &'static str {
+        "kafka"
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires a Kafka REST proxy to test
+    fn push<'a>(&'a self, event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let body = match records(event) {
+                Ok(body) => body.to_string(),
+                Err(e) => {
+                    sink_warn!("could not serialize {} record: {e}", event.identifier);
+                    return Ok(());
+                }
+            };
+
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri(format!("{}/topics/{}", self.url, self.topic))
+                .header(hyper::header::CONTENT_TYPE, CONTENT_TYPE_KAFKA_JSON)
+                .body(Body::from(body));
+
+            let request = match request {
+                Ok(request) => request,
+                Err(e) => {
+                    sink_warn!("invalid Kafka REST proxy request: {e}");
+                    self.stats.record_error(Dependency::Kafka);
+                    return Ok(());
+                }
+            };
+
+            let response = CLIENT.get_or_init(Client::new).request(request);
+            let Ok(response) = tokio::time::timeout(self.timeout, response).await else {
+                sink_warn!("Kafka REST proxy timed out.");
+                self.stats.record_error(Dependency::Kafka);
+                return Err(SinkError::Failed);
+            };
+
+            match response {
+                Ok(response) if response.status().is_success() => {
+                    sink_debug!("produced {} record to Kafka.", event.identifier);
+                    Ok(())
+                }
+                Ok(response) => {
+                    sink_warn!("Kafka REST proxy replied {}.", response.status());
+                    self.stats.record_error(Dependency::Kafka);
+                    Err(SinkError::Failed)
+                }
+                Err(e) => {
+                    sink_warn!("could not reach Kafka REST proxy: {e}");
+                    self.stats.record_error(Dependency::Kafka);
+                    Err(SinkError::Failed)
+                }
+            }
+        })
+    }
+
+    fn dependency(&self) -> Option<Dependency> {
+        Some(Dependency::Kafka)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::EventSource;
+
+    #[test]
+    fn test_records() {
+        let data = EventData::Packet(vec![0x8d, 0x48, 0x40]);
+        let event = TelemetryEvent::new(EventSource::Adsb, "4840d6", data, Utc::now());
+        let body = records(&event).unwrap();
+
+        let record = &body["records"][0];
+        assert_eq!(record["key"], "4840d6");
+        assert_eq!(record["value"]["source"], "adsb");
+        assert_eq!(record["value"]["type"], "packet");
+        assert_eq!(record["value"]["data"], "8d4840");
+        assert!(record["value"]["authentication"].is_null());
+    }
+
+    #[test]
+    fn test_new() {
+        let sink = KafkaSink::new(
+            "http://kafka-rest:8082/",
+            "telemetry",
+            500,
+            Stats::default(),
+        );
+        assert_eq!(sink.url, "http://kafka-rest:8082");
+        assert_eq!(sink.timeout, Duration::from_millis(500));
+        assert_eq!(sink.name(), "kafka");
+    }
+}
@@ -0,0 +1,610 @@
+//! Downstream outputs of decoded telemetry
+//!
+//! Handlers describe the telemetry they decoded as [`TelemetryEvent`]s and
+//!  push them to the sinks listed in `TELEMETRY_SINKS`, in that order.
+//!  Each sink picks the events it handles and ignores the others.
+
+#[macro_use]
+pub mod macros;
+pub mod amqp;
+pub mod anomaly;
+pub mod conformance;
+pub mod coverage;
+pub mod gis;
+pub mod kafka;
+pub mod netrid_state;
+pub mod storage;
+pub mod trail;
+
+use crate::amqp::envelope::{PositionAccuracy, ProcessingLatency, SignalMetadata};
+use crate::degradation::{Degradation, DegradationPolicy};
+use crate::msg::c2::C2LinkStatus;
+use crate::msg::health::VehicleHealth;
+use crate::msg::netrid::ProtocolVersion;
+use crate::msg::privacy::OperatorInfo;
+use crate::rest::api::signature::AuthenticationStatus;
+use crate::rest::api::weather::WeatherObservation;
+use crate::stats::Dependency;
+use futures::future::BoxFuture;
+use hyper::StatusCode;
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::Serialize;
+use snafu::prelude::Snafu;
+use std::fmt::Debug;
+use std::str::FromStr;
+use svc_gis_client_grpc::prelude::types::{AircraftId, AircraftPosition, AircraftVelocity};
+
+/// Protocol telemetry was received with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventSource {
+    /// ADS-B
+    Adsb,
+
+    /// Network Remote ID
+    Netrid,
+
+    /// Open Glider Network (FLARM) beacons
+    Ogn,
+
+    /// Universal Access Transceiver (978 MHz) ADS-B
+    Uat,
+
+    /// Reports of the vehicles of the fleet, identified by their token
+    Vehicle,
+
+    /// Reports of vertiport ground stations, identified by their token
+    Station,
+}
+
+/// Decoded telemetry item
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventData {
+    /// Aircraft identification
+    Identification(AircraftId),
+
+    /// Aircraft position
+    Position(AircraftPosition),
+
+    /// Aircraft velocity
+    Velocity(AircraftVelocity),
+
+    /// The packet as received, once processed
+    Packet(Vec<u8>),
+
+    /// Operator of the aircraft, scrubbed of personal data
+    Operator(OperatorInfo),
+
+    /// Battery, GNSS fix and command link state of a vehicle
+    Health(VehicleHealth),
+
+    /// Command and control link state of an aircraft
+    C2Link(C2LinkStatus),
+
+    /// Weather at a ground station
+    Weather(WeatherObservation),
+}
+
+/// Telemetry pushed to the sinks
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetryEvent {
+    /// Protocol the telemetry was received with
+    pub source: EventSource,
+
+    /// ICAO address (hex) or Remote ID identifier of the aircraft
+    pub identifier: String,
+
+    /// The decoded item
+    pub data: EventData,
+
+    /// Raw emitter category of ADS-B identifications
+    pub emitter_category: Option<String>,
+
+    /// Authentication status of the aircraft, for Remote ID telemetry
+    pub authentication: Option<AuthenticationStatus>,
+
+    /// Login session of the reporter, for Remote ID telemetry
+    pub session: Option<String>,
+
+    /// Flight plan or mission declared by the session (UUID)
+    pub mission: Option<String>,
+
+    /// Signal metadata declared by the receiver of the packet
+    pub signal: Option<SignalMetadata>,
+
+    /// Accuracy of positions, if the aircraft reported it
+    pub accuracy: Option<PositionAccuracy>,
+
+    /// Protocol version of the message, for Remote ID telemetry
+    pub protocol_version: Option<ProtocolVersion>,
+
+    /// When the packet carrying the telemetry was received
+    pub received: DateTime<Utc>,
+
+    /// When the telemetry was decoded from the packet
+    pub decoded: DateTime<Utc>,
+}
+
+impl TelemetryEvent {
+    /// Create an event decoded at `now`, from a packet received then
+    pub fn new(source: EventSource, identifier: &str, data: EventData, now: DateTime<Utc>) -> Self {
+        TelemetryEvent {
+            source,
+            identifier: identifier.to_string(),
+            data,
+            emitter_category: None,
+            authentication: None,
+            session: None,
+            mission: None,
+            signal: None,
+            accuracy: None,
+            protocol_version: None,
+            received: now,
+            decoded: now,
+        }
+    }
+
+    /// Set the raw emitter category of an ADS-B identification
+    pub fn with_emitter_category(mut self, emitter_category: String) -> Self {
+        self.emitter_category = Some(emitter_category);
+        self
+    }
+
+    /// Set the authentication status of the aircraft
+    pub fn with_authentication(mut self, authentication: AuthenticationStatus) -> Self {
+        self.authentication = Some(authentication);
+        self
+    }
+
+    /// Set the login session of the reporter
+    pub fn with_session(mut self, session: String) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Set the flight plan or mission declared by the session
+    pub fn with_mission(mut self, mission: String) -> Self {
+        self.mission = Some(mission);
+        self
+    }
+
+    /// Set the signal metadata declared by the receiver
+    pub fn with_signal(mut self, signal: SignalMetadata) -> Self {
+        self.signal = Some(signal);
+        self
+    }
+
+    /// Set the accuracy of a position
+    pub fn with_accuracy(mut self, accuracy: PositionAccuracy) -> Self {
+        self.accuracy = Some(accuracy);
+        self
+    }
+
+    /// Set the protocol version of a Remote ID message
+    pub fn with_protocol_version(mut self, protocol_version: ProtocolVersion) -> Self {
+        self.protocol_version = Some(protocol_version);
+        self
+    }
+
+    /// Set when the packet carrying the telemetry was received
+    pub fn with_received(mut self, received: DateTime<Utc>) -> Self {
+        self.received = received;
+        self
+    }
+
+    /// Time spent on the telemetry if published at the given time
+    pub fn latency(&self, published: DateTime<Utc>) -> ProcessingLatency {
+        let micros =
+            |duration: Duration| duration.num_microseconds().unwrap_or(i64::MAX).max(0) as u64;
+
+        ProcessingLatency {
+            received_ms: self.received.timestamp_millis(),
+            decode_us: micros(self.decoded - self.received),
+            publish_us: micros(published - self.decoded),
+        }
+    }
+}
+
+/// Error of a push to a sink
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Snafu)]
+pub enum SinkError {
+    /// The downstream service is considered down
+    #[snafu(display("Downstream service unavailable."))]
+    Unavailable,
+
+    /// The push failed
+    #[snafu(display("Could not push to downstream service."))]
+    Failed,
+}
+
+impl SinkError {
+    /// The error of a push failing in several sinks: unavailable if any
+    ///  of them was
+    pub fn combine(self, other: SinkError) -> SinkError {
+        match (self, other) {
+            (SinkError::Failed, SinkError::Failed) => SinkError::Failed,
+            _ => SinkError::Unavailable,
+        }
+    }
+}
+
+impl From<SinkError> for StatusCode {
+    fn from(e: SinkError) -> Self {
+        match e {
+            SinkError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            SinkError::Failed => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Output of decoded telemetry
+pub trait TelemetrySink: Send + Sync + Debug {
+    /// Name of the sink, for logging
+    fn name(&self) -> &'static str;
+
+    /// Push an event, events the sink doesn't handle are ignored
+    fn push<'a>(&'a self, event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>>;
+
+    /// Dependency the sink pushes to, failures of sinks without one are
+    ///  never degraded
+    fn dependency(&self) -> Option<Dependency> {
+        None
+    }
+
+    /// Hold an event the dependency failed to take until it recovers
+    fn buffer<'a>(&'a self, _event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async { Err(SinkError::Unavailable) })
+    }
+}
+
+/// Sink discarding every event
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopSink;
+
+impl TelemetrySink for NoopSink {
+    fn name(&self) -> &'static str {
+        "noop"
+    }
+
+    fn push<'a>(&'a self, _event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Kinds of sinks which can be listed in `TELEMETRY_SINKS`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkKind {
+    /// svc-gis queues, see [`gis::GisSink`]
+    Gis,
+
+    /// svc-storage, see [`storage::StorageSink`]
+    Storage,
+
+    /// RabbitMQ, see [`amqp::AmqpSink`]
+    Amqp,
+
+    /// Kafka REST proxy, see [`kafka::KafkaSink`]
+    Kafka,
+
+    /// Coverage map of this instance, see [`coverage::CoverageSink`]
+    Coverage,
+
+    /// Anomaly detectors, see [`anomaly::AnomalySink`]
+    Anomaly,
+
+    /// Recent positions of each aircraft, see [`trail::TrailSink`]
+    Trail,
+
+    /// Combined Remote ID states, see [`netrid_state::NetridStateSink`]
+    NetridState,
+
+    /// Conformance to the corridors of the missions, see
+    ///  [`conformance::ConformanceSink`]
+    Conformance,
+
+    /// Discards events, see [`NoopSink`]
+    Noop,
+}
+
+impl FromStr for SinkKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "gis" => Ok(SinkKind::Gis),
+            "storage" => Ok(SinkKind::Storage),
+            "amqp" => Ok(SinkKind::Amqp),
+            "kafka" => Ok(SinkKind::Kafka),
+            "coverage" => Ok(SinkKind::Coverage),
+            "anomaly" => Ok(SinkKind::Anomaly),
+            "trail" => Ok(SinkKind::Trail),
+            "netrid_state" => Ok(SinkKind::NetridState),
+            "conformance" => Ok(SinkKind::Conformance),
+            "noop" => Ok(SinkKind::Noop),
+            _ => Err(()),
+        }
+    }
+}
+
+impl SinkKind {
+    /// Parse a comma separated list of sinks, skipping unknown sinks
+    pub fn parse_list(list: &str) -> Vec<SinkKind> {
+        list.split(',')
+            .filter(|name| !name.trim().is_empty())
+            .filter_map(|name| {
+                SinkKind::from_str(name)
+                    .map_err(|_| sink_warn!("unknown sink '{}' ignored.", name.trim()))
+                    .ok()
+            })
+            .collect()
+    }
+}
+
+/// Ordered sinks events are pushed to
+#[derive(Debug, Default)]
+pub struct Sinks {
+    /// The sinks, in order
+    sinks: Vec<Box<dyn TelemetrySink>>,
+
+    /// Action taken when the dependency of a sink fails
+    policy: DegradationPolicy,
+}
+
+impl Sinks {
+    /// Push events to the given sinks, in order
+    pub fn new(sinks: Vec<Box<dyn TelemetrySink>>) -> Self {
+        Sinks {
+            sinks,
+            policy: DegradationPolicy::default(),
+        }
+    }
+
+    /// Apply the given policy to failed pushes
+    pub fn with_policy(mut self, policy: DegradationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Names of the sinks, in order
+    pub fn names(&self) -> Vec<&'static str> {
+        self.sinks.iter().map(|sink| sink.name()).collect()
+    }
+
+    /// Push an event to every sink in order
+    ///
+    /// A sink failing doesn't keep the event from the sinks after it: the
+    ///  failures the degradation policy rejects are combined into the
+    ///  error of the push once all sinks were pushed to.
+    pub async fn push(&self, event: &TelemetryEvent) -> Result<(), SinkError> {
+        let mut result = Ok(());
+        for sink in &self.sinks {
+            if let Err(e) = self.push_to(sink.as_ref(), event).await {
+                result = match result {
+                    Ok(()) => Err(e),
+                    Err(previous) => Err(e.combine(previous)),
+                };
+            }
+        }
+
+        result
+    }
+
+    /// Push an event to a sink, applying the degradation policy to a failure
+    async fn push_to(
+        &self,
+        sink: &dyn TelemetrySink,
+        event: &TelemetryEvent,
+    ) -> Result<(), SinkError> {
+        let Err(e) = sink.push(event).await else {
+            return Ok(());
+        };
+
+        sink_warn!("could not push event to {}: {e}", sink.name());
+        let Some(dependency) = sink.dependency() else {
+            return Err(e);
+        };
+
+        match self.policy.action(dependency) {
+            Degradation::Degrade => {
+                sink_info!("event not pushed to {}, degraded.", sink.name());
+                Ok(())
+            }
+            Degradation::Buffer => sink.buffer(event).await.inspect_err(|e| {
+                sink_warn!("could not buffer event for {}: {e}", sink.name());
+            }),
+            Degradation::Reject => Err(SinkError::Unavailable),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Sink failing every push
+    #[derive(Debug)]
+    struct FailingSink;
+
+    impl TelemetrySink for FailingSink {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn push<'a>(&'a self, _event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+            Box::pin(async { Err(SinkError::Unavailable) })
+        }
+    }
+
+    /// Sink of a dependency failing every push, counting buffered events
+    #[derive(Debug, Default)]
+    struct FailingDependencySink {
+        buffered: Arc<AtomicUsize>,
+    }
+
+    impl TelemetrySink for FailingDependencySink {
+        fn name(&self) -> &'static str {
+            "failing_dependency"
+        }
+
+        fn push<'a>(&'a self, _event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+            Box::pin(async { Err(SinkError::Failed) })
+        }
+
+        fn dependency(&self) -> Option<Dependency> {
+            Some(Dependency::Storage)
+        }
+
+        fn buffer<'a>(
+            &'a self,
+            _event: &'a TelemetryEvent,
+        ) -> BoxFuture<'a, Result<(), SinkError>> {
+            self.buffered.fetch_add(1, Ordering::Relaxed);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    /// Sink counting the events pushed to it
+    #[derive(Debug, Default)]
+    struct CountingSink {
+        pushed: Arc<AtomicUsize>,
+    }
+
+    impl TelemetrySink for CountingSink {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn push<'a>(&'a self, _event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+            self.pushed.fetch_add(1, Ordering::Relaxed);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(
+            SinkKind::parse_list("gis,amqp,storage"),
+            vec![SinkKind::Gis, SinkKind::Amqp, SinkKind::Storage]
+        );
+
+        assert_eq!(
+            SinkKind::parse_list(
+                " Kafka, unknown,,noop ,coverage,anomaly,trail,netrid_state,conformance"
+            ),
+            vec![
+                SinkKind::Kafka,
+                SinkKind::Noop,
+                SinkKind::Coverage,
+                SinkKind::Anomaly,
+                SinkKind::Trail,
+                SinkKind::NetridState,
+                SinkKind::Conformance
+            ]
+        );
+
+        assert!(SinkKind::parse_list("").is_empty());
+    }
+
+    #[test]
+    fn test_event_latency() {
+        let received = Utc::now() - Duration::milliseconds(5);
+        let event = TelemetryEvent::new(
+            EventSource::Adsb,
+            "4840d6",
+            EventData::Packet(vec![0x8d]),
+            Utc::now(),
+        )
+        .with_received(received);
+
+        let latency = event.latency(event.decoded + Duration::microseconds(800));
+        assert_eq!(latency.received_ms, received.timestamp_millis());
+        assert!(latency.decode_us >= 5000);
+        assert_eq!(latency.publish_us, 800);
+
+        // clocks going backwards
+        let latency = event.latency(event.decoded - Duration::seconds(1));
+        assert_eq!(latency.publish_us, 0);
+    }
+
+    #[tokio::test]
+    async fn test_sinks_push() {
+        let event = TelemetryEvent::new(
+            EventSource::Adsb,
+            "4840d6",
+            EventData::Packet(vec![0x8d]),
+            Utc::now(),
+        );
+
+        let sinks = Sinks::new(vec![Box::new(NoopSink), Box::new(NoopSink)]);
+        assert_eq!(sinks.names(), vec!["noop", "noop"]);
+        assert_eq!(sinks.push(&event).await, Ok(()));
+
+        let sinks = Sinks::new(vec![Box::new(NoopSink), Box::new(FailingSink)]);
+        assert_eq!(sinks.push(&event).await, Err(SinkError::Unavailable));
+        assert_eq!(
+            StatusCode::from(SinkError::Unavailable),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        // the sinks after a failing one still get the event
+        let pushed = Arc::new(AtomicUsize::new(0));
+        let counting = CountingSink {
+            pushed: pushed.clone(),
+        };
+        let sinks = Sinks::new(vec![
+            Box::new(FailingDependencySink::default()),
+            Box::new(counting),
+            Box::new(FailingSink),
+        ]);
+        assert_eq!(sinks.push(&event).await, Err(SinkError::Unavailable));
+        assert_eq!(pushed.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_combine() {
+        let failed = SinkError::Failed;
+        assert_eq!(failed.combine(SinkError::Failed), SinkError::Failed);
+        assert_eq!(
+            failed.combine(SinkError::Unavailable),
+            SinkError::Unavailable
+        );
+        assert_eq!(
+            SinkError::Unavailable.combine(SinkError::Failed),
+            SinkError::Unavailable
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sinks_policy() {
+        let event = TelemetryEvent::new(
+            EventSource::Adsb,
+            "4840d6",
+            EventData::Packet(vec![0x8d]),
+            Utc::now(),
+        );
+
+        let sinks = Sinks::new(vec![Box::new(FailingDependencySink::default())]);
+        assert_eq!(sinks.push(&event).await, Err(SinkError::Unavailable));
+
+        let policy = DegradationPolicy::new("storage=degrade");
+        let sinks =
+            Sinks::new(vec![Box::new(FailingDependencySink::default())]).with_policy(policy);
+        assert_eq!(sinks.push(&event).await, Ok(()));
+
+        let buffered = Arc::new(AtomicUsize::new(0));
+        let sink = FailingDependencySink {
+            buffered: buffered.clone(),
+        };
+        let policy = DegradationPolicy::new("storage=buffer");
+        let sinks = Sinks::new(vec![Box::new(sink)]).with_policy(policy);
+        assert_eq!(sinks.push(&event).await, Ok(()));
+        assert_eq!(buffered.load(Ordering::Relaxed), 1);
+
+        // sinks without a dependency are never degraded
+        let policy = DegradationPolicy::new("storage=degrade");
+        let sinks = Sinks::new(vec![Box::new(FailingSink)]).with_policy(policy);
+        assert_eq!(sinks.push(&event).await, Err(SinkError::Unavailable));
+    }
+}
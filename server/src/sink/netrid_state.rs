@@ -0,0 +1,278 @@
+//! Remote ID state sink, combining the identification, position and
+//!  velocity of each aircraft into a single publication
+
+use super::{EventData, EventSource, SinkError, TelemetryEvent, TelemetrySink};
+use crate::amqp::envelope::TelemetryEnvelope;
+use crate::cache::pool::TelemetryPool;
+use crate::clock::SharedClock;
+use crate::msg::netrid::NetridState;
+use crate::stats::{Dependency, Stats};
+use futures::future::BoxFuture;
+use lib_common::time::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+/// State entries in the cache expire after 10 minutes without updates
+const CACHE_EXPIRE_MS_STATE: u32 = 600000;
+
+/// Hash field of the cached identification (JSON)
+const FIELD_IDENTIFICATION: &str = "identification";
+
+/// Hash field of the cached position (JSON)
+const FIELD_POSITION: &str = "position";
+
+/// Hash field of the cached velocity (JSON)
+const FIELD_VELOCITY: &str = "velocity";
+
+/// Caches the last identification, position and velocity of Remote ID
+///  aircraft and publishes their combined state on every update
+///
+/// Publishing is best effort, failures don't fail the push.
+#[derive(Debug, Clone)]
+pub struct NetridStateSink {
+    /// Pool of the Remote ID cache
+    tlm_pool: TelemetryPool,
+
+    /// RabbitMQ channel
+    mq_channel: crate::amqp::MqChannel,
+
+    /// Components received longer ago are flagged as stale
+    stale_ms: u32,
+
+    /// Clock the staleness of components is checked against
+    clock: SharedClock,
+
+    /// Statistics of the received telemetry
+    stats: Stats,
+}
+
+impl NetridStateSink {
+    /// Cache components in the given pool, publishing on the channel
+    pub fn new(
+        tlm_pool: TelemetryPool,
+        mq_channel: crate::amqp::MqChannel,
+        stale_ms: u32,
+        clock: SharedClock,
+        stats: Stats,
+    ) -> Self {
+        NetridStateSink {
+            tlm_pool,
+            mq_channel,
+            stale_ms,
+            clock,
+            stats,
+        }
+    }
+
+    /// Publishes a state to RabbitMQ
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires AMQP backend to test
+    async fn publish(&self, state: &NetridState) {
+        let Ok(msg) = serde_json::to_vec(&TelemetryEnvelope::new(state)) else {
+            sink_warn!("could not serialize state of {}.", state.identifier);
+            return;
+        };
+
+        let _ = self
+            .mq_channel
+            .basic_publish(
+                crate::amqp::EXCHANGE_NAME_TELEMETRY,
+                crate::amqp::ROUTING_KEY_NETRID_STATE,
+                lapin::options::BasicPublishOptions::default(),
+                &msg,
+                lapin::BasicProperties::default(),
+            )
+            .await
+            .map_err(|e| {
+                sink_warn!("could not publish state of {}: {e}.", state.identifier);
+                self.stats.record_error(Dependency::Amqp);
+            });
+    }
+}
+
+/// Hash field and value to cache for a Remote ID event, none for other
+///  events
+fn component(event: &TelemetryEvent) -> Option<(&'static str, String)> {
+    if event.source != EventSource::Netrid {
+        return None;
+    }
+
+    let component = match &event.data {
+        EventData::Identification(item) => (FIELD_IDENTIFICATION, serde_json::to_string(item)),
+        EventData::Position(item) => (FIELD_POSITION, serde_json::to_string(item)),
+        EventData::Velocity(item) => (FIELD_VELOCITY, serde_json::to_string(item)),
+        _ => return None,
+    };
+
+    match component {
+        (field, Ok(value)) => Some((field, value)),
+        (_, Err(e)) => {
+            sink_warn!("could not serialize state of {}: {e}", event.identifier);
+            None
+        }
+    }
+}
+
+/// Cached JSON field, ignored if unreadable
+fn cached_json<T: DeserializeOwned>(cached: &HashMap<String, String>, field: &str) -> Option<T> {
+    cached
+        .get(field)
+        .and_then(|value| serde_json::from_str(value).ok())
+}
+
+/// State of an aircraft from its cached components
+fn merge(
+    identifier: &str,
+    cached: &HashMap<String, String>,
+    now: DateTime<Utc>,
+    stale_ms: u32,
+) -> NetridState {
+    NetridState::new(
+        identifier,
+        cached_json(cached, FIELD_IDENTIFICATION),
+        cached_json(cached, FIELD_POSITION),
+        cached_json(cached, FIELD_VELOCITY),
+        now,
+        stale_ms,
+    )
+}
+
+impl TelemetrySink for NetridStateSink {
+    fn name(&self) -> &'static str {
+        "netrid_state"
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) need redis and AMQP backends to test
+    fn push<'a>(&'a self, event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let Some((field, value)) = component(event) else {
+                return Ok(());
+            };
+
+            let identifier = &event.identifier;
+            let key = format!("{identifier}:state");
+            let mut tlm_pool = self.tlm_pool.clone();
+            let mut cached = tlm_pool.hash_get_all(&key).await.unwrap_or_else(|e| {
+                sink_warn!("could not get state of {identifier} from cache: {e}");
+                self.stats.record_error(Dependency::Redis);
+                HashMap::new()
+            });
+
+            if let Err(e) = tlm_pool
+                .hash_set(&key, field, &value, CACHE_EXPIRE_MS_STATE)
+                .await
+            {
+                sink_warn!("could not cache state of {identifier}: {e}");
+                self.stats.record_error(Dependency::Redis);
+            }
+
+            cached.insert(field.to_string(), value);
+            let state = merge(identifier, &cached, self.clock.now(), self.stale_ms);
+            self.publish(&state).await;
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lib_common::time::Duration;
+    use svc_gis_client_grpc::prelude::types::{AircraftPosition, AircraftVelocity, Position};
+
+    fn position(at: DateTime<Utc>) -> AircraftPosition {
+        AircraftPosition {
+            identifier: "drone-1".to_string(),
+            position: Position {
+                latitude: 52.37,
+                longitude: 4.9,
+                altitude_meters: 120.0,
+            },
+            timestamp_network: at,
+            timestamp_asset: None,
+        }
+    }
+
+    fn velocity(at: DateTime<Utc>) -> AircraftVelocity {
+        AircraftVelocity {
+            identifier: "drone-1".to_string(),
+            velocity_horizontal_ground_mps: 10.0,
+            velocity_horizontal_air_mps: None,
+            velocity_vertical_mps: 0.5,
+            track_angle_degrees: 90.0,
+            timestamp_network: at,
+            timestamp_asset: None,
+        }
+    }
+
+    #[test]
+    fn test_component() {
+        let now = Utc::now();
+        let event = TelemetryEvent::new(
+            EventSource::Netrid,
+            "drone-1",
+            EventData::Position(position(now)),
+            Utc::now(),
+        );
+        let (field, value) = component(&event).unwrap();
+        assert_eq!(field, FIELD_POSITION);
+        assert_eq!(
+            serde_json::from_str::<AircraftPosition>(&value).unwrap(),
+            position(now)
+        );
+
+        // other sources and items are not combined
+        let event = TelemetryEvent::new(
+            EventSource::Adsb,
+            "4840d6",
+            EventData::Position(position(now)),
+            Utc::now(),
+        );
+        assert_eq!(component(&event), None);
+
+        let event = TelemetryEvent::new(
+            EventSource::Netrid,
+            "drone-1",
+            EventData::Packet(vec![]),
+            Utc::now(),
+        );
+        assert_eq!(component(&event), None);
+    }
+
+    #[test]
+    fn test_merge() {
+        let now = Utc::now();
+        let mut cached = HashMap::new();
+        cached.insert(
+            FIELD_POSITION.to_string(),
+            serde_json::to_string(&position(now)).unwrap(),
+        );
+
+        let state = merge("drone-1", &cached, now, 5000);
+        assert_eq!(state.identifier, "drone-1");
+        assert_eq!(state.position, Some(position(now)));
+        assert!(!state.position_stale);
+        assert_eq!(state.identification, None);
+        assert!(state.identification_stale);
+        assert!(state.velocity_stale);
+
+        // components received before the staleness limit are flagged
+        let earlier = now - Duration::try_milliseconds(6000).unwrap();
+        cached.insert(
+            FIELD_VELOCITY.to_string(),
+            serde_json::to_string(&velocity(earlier)).unwrap(),
+        );
+        let state = merge("drone-1", &cached, now, 5000);
+        assert_eq!(state.velocity, Some(velocity(earlier)));
+        assert!(state.velocity_stale);
+        assert!(!merge("drone-1", &cached, now, 10000).velocity_stale);
+
+        // unreadable cached fields are ignored
+        cached.insert(FIELD_POSITION.to_string(), "{".to_string());
+        let state = merge("drone-1", &cached, now, 5000);
+        assert_eq!(state.position, None);
+        assert!(state.position_stale);
+    }
+}
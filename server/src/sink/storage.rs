@@ -0,0 +1,400 @@
+//! svc-storage sink
+
+use super::{EventData, EventSource, SinkError, TelemetryEvent, TelemetrySink};
+use crate::cache::pool::TelemetryPool;
+use crate::grpc::breaker::BreakerError;
+use crate::grpc::client::GrpcClients;
+use crate::msg::adsb::{get_adsb_message_type, ADSB_SIZE_BYTES};
+use crate::stats::{Dependency, Stats};
+use futures::future::BoxFuture;
+use lib_common::time::{DateTime, Utc};
+use std::collections::HashMap;
+use svc_storage_client_grpc::prelude::*;
+use svc_storage_client_grpc::resources::adsb;
+
+/// Storage inserts of the same packet are idempotent within buckets of this length
+const IDEMPOTENCY_BUCKET_MS: i64 = 10000;
+
+/// Idempotency keys expire once their bucket can no longer be retried
+const CACHE_EXPIRE_MS_IDEMPOTENCY: u32 = 2 * IDEMPOTENCY_BUCKET_MS as u32;
+
+/// Stream of the packets beyond the insert budget, stored by [`backlog_loop`]
+const BACKLOG_STREAM: &str = "storage:backlog";
+
+/// Consumer group of the backlog tasks of all instances
+const BACKLOG_CONSUMER_GROUP: &str = "storage";
+
+/// The backlog is trimmed to this many packets
+const BACKLOG_MAX_LEN: usize = 100000;
+
+/// Packets read from the backlog at once
+const BACKLOG_BATCH_SIZE: usize = 100;
+
+/// Reads of the backlog wait this long for new packets
+const BACKLOG_BLOCK_MS: usize = 1000;
+
+/// Packets left pending this long, e.g. by a crashed instance, are reclaimed
+const BACKLOG_PENDING_IDLE_MS: usize = 30000;
+
+/// Wait after a failure before reading the backlog again
+const BACKLOG_RETRY_MS: u64 = 1000;
+
+/// Stores processed ADS-B packets in svc-storage
+#[derive(Debug, Clone)]
+pub struct StorageSink {
+    /// gRPC clients of other services
+    grpc_clients: GrpcClients,
+
+    /// Redis pool holding the idempotency keys
+    tlm_pool: TelemetryPool,
+
+    /// Skip packets already stored, see `STORAGE_IDEMPOTENCY_ENABLED`
+    idempotency_enabled: bool,
+
+    /// Statistics of the received telemetry
+    stats: Stats,
+}
+
+impl StorageSink {
+    /// Store packets with the given clients
+    pub fn new(
+        grpc_clients: GrpcClients,
+        tlm_pool: TelemetryPool,
+        idempotency_enabled: bool,
+        stats: Stats,
+    ) -> Self {
+        StorageSink {
+            grpc_clients,
+            tlm_pool,
+            idempotency_enabled,
+            stats,
+        }
+    }
+}
+
+/// Fields of a packet queued in the backlog
+fn backlog_fields(data: &adsb::Data, received: DateTime<Utc>) -> [(&'static str, String); 4] {
+    [
+        ("icao_address", data.icao_address.to_string()),
+        ("message_type", data.message_type.to_string()),
+        ("received", received.to_rfc3339()),
+        ("payload", hex::encode(&data.payload)),
+    ]
+}
+
+/// Packet of a backlog entry, none if malformed
+fn backlog_data(fields: &HashMap<String, String>) -> Option<adsb::Data> {
+    let received = DateTime::parse_from_rfc3339(fields.get("received")?).ok()?;
+
+    Some(adsb::Data {
+        icao_address: fields.get("icao_address")?.parse().ok()?,
+        message_type: fields.get("message_type")?.parse().ok()?,
+        network_timestamp: Some(received.with_timezone(&Utc).into()),
+        payload: hex::decode(fields.get("payload")?).ok()?,
+    })
+}
+
+/// Key identifying a storage insert of a packet received at the given time
+fn idempotency_key(payload: &[u8], network_timestamp: DateTime<Utc>) -> String {
+    let bucket = network_timestamp
+        .timestamp_millis()
+        .div_euclid(IDEMPOTENCY_BUCKET_MS);
+
+    format!(
+        "{}:stored:{bucket}",
+        crate::cache::packet_key("adsb", payload)
+    )
+}
+
+impl StorageSink {
+    /// Record of an ADS-B packet event, none for other events
+    fn record(event: &TelemetryEvent) -> Result<Option<adsb::Data>, SinkError> {
+        let (EventSource::Adsb, EventData::Packet(payload)) = (&event.source, &event.data) else {
+            return Ok(None);
+        };
+
+        let Ok(packet) = <[u8; ADSB_SIZE_BYTES]>::try_from(payload.as_slice()) else {
+            sink_warn!("not storing packet of unexpected length {}.", payload.len());
+            return Ok(None);
+        };
+
+        let icao_address = i64::from_str_radix(&event.identifier, 16).map_err(|e| {
+            sink_warn!("invalid icao address {}: {e}", event.identifier);
+            SinkError::Failed
+        })?;
+
+        Ok(Some(adsb::Data {
+            icao_address,
+            message_type: get_adsb_message_type(&packet),
+            network_timestamp: Some(event.received.into()),
+            payload: payload.clone(),
+        }))
+    }
+
+    /// Queue a packet in the backlog, stored within the insert budget
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires redis backend to test
+    async fn spill(
+        &self,
+        data: &adsb::Data,
+        received: DateTime<Utc>,
+        idempotency_key: Option<String>,
+    ) -> Result<(), SinkError> {
+        let mut tlm_pool = self.tlm_pool.clone();
+        let fields = backlog_fields(data, received);
+        match tlm_pool
+            .stream_add(BACKLOG_STREAM, &fields, BACKLOG_MAX_LEN)
+            .await
+        {
+            Ok(_) => {
+                sink_debug!("packet queued in the backlog.");
+                Ok(())
+            }
+            Err(e) => {
+                sink_error!("could not queue packet in the backlog: {e}");
+                self.stats.record_error(Dependency::Redis);
+
+                // Allow the retry to insert the packet
+                if let Some(key) = &idempotency_key {
+                    let _ = tlm_pool.delete(key).await;
+                }
+
+                Err(SinkError::Failed)
+            }
+        }
+    }
+}
+
+/// Stores the packets of the backlog within the insert budget
+///
+/// Packets are acknowledged and removed once stored. Packets left pending
+///  after a failure are read again once idle, by this or another instance.
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) requires redis backend and svc-storage to test
+pub async fn backlog_loop(
+    grpc_clients: GrpcClients,
+    mut tlm_pool: TelemetryPool,
+    stats: Stats,
+    consumer: String,
+) {
+    let retry = std::time::Duration::from_millis(BACKLOG_RETRY_MS);
+    while let Err(e) = tlm_pool
+        .stream_create_group(BACKLOG_STREAM, BACKLOG_CONSUMER_GROUP)
+        .await
+    {
+        sink_error!("could not create the backlog consumer group: {e}");
+        stats.record_error(Dependency::Redis);
+        tokio::time::sleep(retry).await;
+    }
+
+    sink_info!("storing the backlog as {consumer}.");
+    let client = &grpc_clients.storage.adsb;
+    let breaker = &grpc_clients.breakers.storage;
+    loop {
+        let entries = match tlm_pool
+            .stream_reclaim(
+                BACKLOG_STREAM,
+                BACKLOG_CONSUMER_GROUP,
+                &consumer,
+                BACKLOG_PENDING_IDLE_MS,
+                BACKLOG_BATCH_SIZE,
+            )
+            .await
+        {
+            Ok(entries) if !entries.is_empty() => Ok(entries),
+            _ => {
+                tlm_pool
+                    .stream_read_group(
+                        BACKLOG_STREAM,
+                        BACKLOG_CONSUMER_GROUP,
+                        &consumer,
+                        BACKLOG_BATCH_SIZE,
+                        BACKLOG_BLOCK_MS,
+                    )
+                    .await
+            }
+        };
+
+        let entries = match entries {
+            Ok(entries) => entries,
+            Err(e) => {
+                sink_warn!("could not read the backlog: {e}");
+                stats.record_error(Dependency::Redis);
+                tokio::time::sleep(retry).await;
+                continue;
+            }
+        };
+
+        let mut done = vec![];
+        let mut failed = false;
+        for (id, fields) in entries {
+            let Some(data) = backlog_data(&fields) else {
+                sink_warn!("dropped malformed backlog entry {id}.");
+                done.push(id);
+                continue;
+            };
+
+            // the reserved budget, else what the live inserts leave
+            if !grpc_clients.storage_backlog_budget.try_acquire()
+                && !grpc_clients.storage_budget.try_acquire()
+            {
+                grpc_clients.storage_backlog_budget.acquire().await;
+            }
+            if let Err(e) = breaker.call(client.insert(data)).await {
+                sink_warn!("backlog push to svc-storage failed: {e}.");
+                stats.record_error(Dependency::Storage);
+                failed = true;
+                break;
+            }
+
+            done.push(id);
+        }
+
+        if !done.is_empty() {
+            sink_debug!("stored {} packets of the backlog.", done.len());
+            let acked = tlm_pool
+                .stream_ack(BACKLOG_STREAM, BACKLOG_CONSUMER_GROUP, &done)
+                .await;
+            let removed = tlm_pool.stream_remove(BACKLOG_STREAM, &done).await;
+            if let Err(e) = acked.and(removed.map(|_| ())) {
+                sink_warn!("could not remove stored packets from the backlog: {e}");
+                stats.record_error(Dependency::Redis);
+            }
+        }
+
+        if failed {
+            tokio::time::sleep(retry).await;
+        }
+    }
+}
+
+impl TelemetrySink for StorageSink {
+    fn name(&self) -> &'static str {
+        "storage"
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires redis backend and svc-storage to test
+    fn push<'a>(&'a self, event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let Some(data) = Self::record(event)? else {
+                return Ok(());
+            };
+
+            // A retried packet must not be stored twice
+            let mut tlm_pool = self.tlm_pool.clone();
+            let idempotency_key = match self.idempotency_enabled {
+                true => Some(idempotency_key(&data.payload, event.received)),
+                false => None,
+            };
+
+            if let Some(key) = &idempotency_key {
+                let first = tlm_pool
+                    .set_if_absent(key, CACHE_EXPIRE_MS_IDEMPOTENCY)
+                    .await
+                    .map_err(|e| {
+                        sink_error!("could not check idempotency key: {e}");
+                        self.stats.record_error(Dependency::Redis);
+                        SinkError::Failed
+                    })?;
+
+                if !first {
+                    sink_info!("telemetry already pushed to svc-storage.");
+                    return Ok(());
+                }
+            }
+
+            // Bursts beyond the budget are stored later
+            if !self.grpc_clients.storage_budget.try_acquire() {
+                return self.spill(&data, event.received, idempotency_key).await;
+            }
+
+            let client = &self.grpc_clients.storage.adsb;
+            let breaker = &self.grpc_clients.breakers.storage;
+            if let Err(e) = breaker.call(client.insert(data)).await {
+                sink_error!("telemetry push to svc-storage failed: {}.", e);
+                self.stats.record_error(Dependency::Storage);
+
+                // Allow the retry to insert the packet
+                if let Some(key) = &idempotency_key {
+                    let _ = tlm_pool.delete(key).await;
+                }
+
+                return match e {
+                    BreakerError::Open => Err(SinkError::Unavailable),
+                    BreakerError::Failed(_) => Err(SinkError::Failed),
+                };
+            }
+
+            sink_info!("telemetry pushed to svc-storage.");
+            Ok(())
+        })
+    }
+
+    fn dependency(&self) -> Option<Dependency> {
+        Some(Dependency::Storage)
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    // no_coverage: (R5) requires redis backend to test
+    fn buffer<'a>(&'a self, event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let Some(data) = Self::record(event)? else {
+                return Ok(());
+            };
+
+            self.spill(&data, event.received, None).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backlog_fields() {
+        let received = DateTime::from_timestamp(1700000000, 500_000_000).unwrap();
+        let data = adsb::Data {
+            icao_address: 0x4840d6,
+            message_type: 4,
+            network_timestamp: Some(received.into()),
+            payload: vec![0x8d, 0x48, 0x40, 0xd6],
+        };
+
+        let fields: HashMap<String, String> = backlog_fields(&data, received)
+            .into_iter()
+            .map(|(field, value)| (field.to_string(), value))
+            .collect();
+        assert_eq!(fields["payload"], "8d4840d6");
+        assert_eq!(backlog_data(&fields), Some(data));
+
+        let mut malformed = fields.clone();
+        malformed.insert("payload".to_string(), "not hex".to_string());
+        assert_eq!(backlog_data(&malformed), None);
+
+        let mut malformed = fields;
+        malformed.remove("received");
+        assert_eq!(backlog_data(&malformed), None);
+    }
+
+    #[test]
+    fn test_idempotency_key() {
+        let payload = [0x8d, 0x48, 0x40];
+        let time = DateTime::from_timestamp(1000, 0).unwrap();
+        let key = idempotency_key(&payload, time);
+        let packet = crate::cache::packet_key("adsb", &payload);
+        assert_eq!(key, format!("{packet}:stored:100"));
+
+        // same bucket
+        let later = time + lib_common::time::Duration::try_milliseconds(9999).unwrap();
+        assert_eq!(idempotency_key(&payload, later), key);
+
+        // next bucket
+        let later = time + lib_common::time::Duration::try_milliseconds(10000).unwrap();
+        assert_ne!(idempotency_key(&payload, later), key);
+
+        // other packet
+        assert_ne!(idempotency_key(&[0x8d, 0x48, 0x41], time), key);
+    }
+}
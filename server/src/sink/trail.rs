@@ -0,0 +1,73 @@
+//! Trail sink, keeping the recent positions of each aircraft
+
+use super::{EventData, EventSource, SinkError, TelemetryEvent, TelemetrySink};
+use crate::cache::TelemetryPools;
+use crate::msg::trail::{cache_key, TrailPoint};
+use crate::stats::{Dependency, Stats};
+use futures::future::BoxFuture;
+
+/// Prepends positions to the trail of their aircraft, in the pool of
+///  their protocol
+///
+/// Trails are best effort, failures don't fail the push.
+#[derive(Debug, Clone)]
+pub struct TrailSink {
+    /// Pools of the trails
+    tlm_pools: TelemetryPools,
+
+    /// Positions kept per aircraft
+    max_points: usize,
+
+    /// Trails without new positions for this long are deleted
+    expire_ms: u32,
+
+    /// Statistics of the received telemetry
+    stats: Stats,
+}
+
+impl TrailSink {
+    /// Keep up to `max_points` positions per aircraft in the given pools
+    pub fn new(tlm_pools: TelemetryPools, max_points: u32, expire_ms: u32, stats: Stats) -> Self {
+        TrailSink {
+            tlm_pools,
+            max_points: max_points as usize,
+            expire_ms,
+            stats,
+        }
+    }
+}
+
+impl TelemetrySink for TrailSink {
+    fn name(&self) -> &'static str {
+        "trail"
+    }
+
+    fn push<'a>(&'a self, event: &'a TelemetryEvent) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let EventData::Position(position) = &event.data else {
+                return Ok(());
+            };
+
+            let Ok(value) = serde_json::to_string(&TrailPoint::from(position)) else {
+                sink_warn!("could not serialize trail point of {}.", event.identifier);
+                return Ok(());
+            };
+
+            let mut tlm_pool = match event.source {
+                EventSource::Adsb | EventSource::Uat => self.tlm_pools.adsb.clone(),
+                _ => self.tlm_pools.netrid.clone(),
+            };
+
+            let key = cache_key(&event.identifier);
+            if let Err(e) = tlm_pool
+                .list_push(&key, &value, self.max_points, self.expire_ms)
+                .await
+            {
+                sink_warn!("could not extend trail of {}: {e}", event.identifier);
+                self.stats.record_error(Dependency::Redis);
+            }
+
+            Ok(())
+        })
+    }
+}
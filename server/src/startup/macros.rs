@@ -0,0 +1,4 @@
+//! log macro's for startup logging
+
+use lib_common::log_macros;
+log_macros!("startup");
@@ -0,0 +1,200 @@
+//! Waiting for the dependencies at startup
+//!
+//! Pods may start before Redis or RabbitMQ accept connections. Instead of
+//!  exiting and being restarted in a loop, the servers retry initializing
+//!  them with an exponential backoff, for up to `STARTUP_MAX_WAIT_MS`. The
+//!  gRPC health service reports the service as not serving until then.
+
+#[macro_use]
+pub mod macros;
+
+use crate::amqp::system::REDIS_PROBE_KEY;
+use crate::amqp::{init_mq, AMQPError, MqChannel};
+use crate::cache::pool::{CacheError, TelemetryPool};
+use crate::Config;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Whether the dependencies were initialized
+static READY: OnceLock<watch::Sender<bool>> = OnceLock::new();
+
+/// Delays between the attempts to initialize a dependency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Delay before the next attempt
+    delay: Duration,
+
+    /// Longest delay between two attempts
+    max_delay: Duration,
+
+    /// Time left to wait before giving up
+    remaining: Duration,
+}
+
+impl Backoff {
+    /// Backoff of the configured delays and max wait
+    pub fn new(config: &Config) -> Self {
+        Backoff {
+            delay: Duration::from_millis(config.startup_retry_initial_ms as u64),
+            max_delay: Duration::from_millis(config.startup_retry_max_ms as u64),
+            remaining: Duration::from_millis(config.startup_max_wait_ms as u64),
+        }
+    }
+
+    /// Delay before the next attempt, doubled each time up to the longest
+    ///  delay. None once the max wait is spent.
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        if self.remaining.is_zero() {
+            return None;
+        }
+
+        let delay = self.delay.min(self.remaining);
+        self.remaining -= delay;
+        self.delay = (self.delay * 2).min(self.max_delay);
+        Some(delay)
+    }
+}
+
+/// Initializes a dependency, retrying with the configured backoff
+///
+/// Returns the last error once the max wait is spent.
+pub async fn retry<T, E, F, Fut>(dependency: &str, config: &Config, mut init: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    let mut backoff = Backoff::new(config);
+    let mut attempt: u32 = 1;
+    loop {
+        let e = match init().await {
+            Ok(value) => {
+                if attempt > 1 {
+                    startup_info!("{dependency} ready after {attempt} attempts.");
+                }
+
+                return Ok(value);
+            }
+            Err(e) => e,
+        };
+
+        let Some(delay) = backoff.next_delay() else {
+            startup_error!("{dependency} still unavailable after {attempt} attempts: {e}.");
+            return Err(e);
+        };
+
+        startup_warn!(
+            "{dependency} unavailable (attempt {attempt}): {e}, retrying in {} ms.",
+            delay.as_millis()
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Waits for Redis to answer through the pool
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) need redis backend to test
+pub async fn wait_for_redis(config: &Config, pool: &TelemetryPool) -> Result<(), CacheError> {
+    retry("Redis", config, || {
+        let mut pool = pool.clone();
+        async move {
+            pool.hash_get(REDIS_PROBE_KEY, REDIS_PROBE_KEY)
+                .await
+                .map(|_| ())
+        }
+    })
+    .await
+}
+
+/// Waits for RabbitMQ, returning the channel once connected
+#[cfg(not(tarpaulin_include))]
+// no_coverage: (R5) requires AMQP backend to test
+pub async fn wait_for_amqp(config: &Config) -> Result<MqChannel, AMQPError> {
+    retry("RabbitMQ", config, || init_mq(config.clone())).await
+}
+
+/// Sender of the readiness, created not ready
+fn ready_sender() -> &'static watch::Sender<bool> {
+    READY.get_or_init(|| watch::channel(false).0)
+}
+
+/// Marks the dependencies as initialized
+pub fn set_ready() {
+    ready_sender().send_replace(true);
+}
+
+/// If the dependencies were initialized
+pub fn is_ready() -> bool {
+    *ready_sender().borrow()
+}
+
+/// Waits until the dependencies were initialized
+pub async fn ready() {
+    let mut ready_rx = ready_sender().subscribe();
+    let _ = ready_rx.wait_for(|ready| *ready).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(initial: u32, max: u32, max_wait: u32) -> Config {
+        Config {
+            startup_retry_initial_ms: initial,
+            startup_retry_max_ms: max,
+            startup_max_wait_ms: max_wait,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(&config(100, 300, 1000));
+        let delays: Vec<u64> = std::iter::from_fn(|| backoff.next_delay())
+            .map(|delay| delay.as_millis() as u64)
+            .collect();
+        assert_eq!(delays, vec![100, 200, 300, 300, 100]);
+
+        let mut backoff = Backoff::new(&config(100, 300, 0));
+        assert_eq!(backoff.next_delay(), None);
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let config = config(1, 2, 10);
+        let mut attempts = 0;
+        let result: Result<u32, String> = retry("test", &config, || {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                match attempt {
+                    3 => Ok(attempt),
+                    _ => Err("down".to_string()),
+                }
+            }
+        })
+        .await;
+        assert_eq!(result, Ok(3));
+
+        let mut attempts = 0;
+        let result: Result<(), String> = retry("test", &config, || {
+            attempts += 1;
+            async { Err("down".to_string()) }
+        })
+        .await;
+        assert_eq!(result, Err("down".to_string()));
+        // 1 + 2 + 2 + 2 + 2 + 1 ms of delays
+        assert_eq!(attempts, 7);
+    }
+
+    #[tokio::test]
+    async fn test_ready() {
+        set_ready();
+        assert!(is_ready());
+        ready().await;
+    }
+}
@@ -0,0 +1,520 @@
+//! In-process statistics of received telemetry
+//!
+//! Counts are kept per minute for the last 15 minutes, and only cover
+//!  the requests handled by this instance.
+
+use crate::grpc::breaker::BreakerState;
+use lib_common::time::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use utoipa::ToSchema;
+
+/// Number of minutes statistics are kept for
+const WINDOW_MINUTES: i64 = 15;
+
+/// Dependencies whose errors are counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Dependency {
+    /// Redis cache and queues
+    Redis,
+
+    /// svc-gis queues
+    Gis,
+
+    /// RabbitMQ
+    Amqp,
+
+    /// svc-storage
+    Storage,
+
+    /// Kafka REST proxy
+    Kafka,
+}
+
+/// Number of events per minute
+#[derive(Debug, Clone, Default)]
+struct MinuteCounter {
+    /// Minute since the epoch and the number of events during it
+    buckets: VecDeque<(i64, u64)>,
+}
+
+/// Minute since the epoch of a point in time
+fn minute(time: DateTime<Utc>) -> i64 {
+    time.timestamp().div_euclid(60)
+}
+
+impl MinuteCounter {
+    /// Count an event
+    fn add(&mut self, now: DateTime<Utc>) {
+        let minute = minute(now);
+        match self.buckets.back_mut() {
+            Some((last, count)) if *last == minute => *count += 1,
+            _ => self.buckets.push_back((minute, 1)),
+        }
+
+        while let Some((first, _)) = self.buckets.front() {
+            if minute - first < WINDOW_MINUTES {
+                break;
+            }
+
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Number of events during the last `minutes` minutes, including the current one
+    fn sum(&self, now: DateTime<Utc>, minutes: i64) -> u64 {
+        let minute = minute(now);
+        self.buckets
+            .iter()
+            .filter(|(bucket, _)| minute - bucket < minutes)
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+/// Packets received during the last 1, 5 and 15 minutes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct PacketCounts {
+    /// Packets received during the last minute
+    pub last_1_min: u64,
+
+    /// Packets received during the last 5 minutes
+    pub last_5_min: u64,
+
+    /// Packets received during the last 15 minutes
+    pub last_15_min: u64,
+}
+
+/// Connections of a Redis pool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, ToSchema)]
+pub struct PoolStatus {
+    /// Most connections the pool opens
+    pub max_size: usize,
+
+    /// Connections open when last checked out
+    pub size: usize,
+
+    /// Idle connections when last checked out
+    pub available: usize,
+
+    /// Requests waiting for a connection when last checked out
+    pub waiting: usize,
+
+    /// Connections checked out
+    pub checkouts: u64,
+
+    /// Requests given up for lack of a free connection in time
+    pub timeouts: u64,
+
+    /// Average wait for a connection
+    pub average_wait_ms: f64,
+
+    /// Longest wait for a connection
+    pub max_wait_ms: f64,
+}
+
+/// Summary returned by the statistics endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct StatsSummary {
+    /// Received packets per type
+    pub packets: HashMap<String, PacketCounts>,
+
+    /// Aircraft observed during the last 15 minutes
+    pub unique_aircraft: usize,
+
+    /// Share of received packets suppressed as duplicates (0.0 - 1.0)
+    pub dedup_suppression_ratio: f64,
+
+    /// Average time spent handling a telemetry request
+    pub average_latency_ms: f64,
+
+    /// Number of failed requests to each dependency
+    pub dependency_errors: HashMap<Dependency, u64>,
+
+    /// Number of errors of each code, e.g. `cache_pool_exhausted`
+    pub error_codes: HashMap<String, u64>,
+
+    /// State of the circuit breaker of each gRPC dependency
+    pub circuit_breakers: HashMap<Dependency, BreakerState>,
+
+    /// Oldest entries dropped from each full Redis stream
+    pub dropped_entries: HashMap<String, u64>,
+
+    /// Entries of each svc-gis queue dropped at dequeue time for being
+    ///  older than `GIS_STALE_AFTER_MS`
+    pub stale_entries: HashMap<String, u64>,
+
+    /// Messages in each RabbitMQ queue when last polled
+    pub queue_depths: HashMap<String, u32>,
+
+    /// Polls finding each RabbitMQ queue at `AMQP_QUEUE_MAX_LENGTH`,
+    ///  dropping its oldest messages
+    pub queue_overflows: HashMap<String, u64>,
+
+    /// Consumers of each RabbitMQ queue when last polled
+    pub queue_consumers: HashMap<String, u32>,
+
+    /// Polls finding each RabbitMQ queue at `AMQP_QUEUE_LAG_THRESHOLD`
+    ///  messages or more, its consumers falling behind
+    pub queue_lags: HashMap<String, u64>,
+
+    /// Packets rejected per source (`adsb`, `netrid`, `ogn`, `uat`) for coming
+    ///  from a blocked aircraft
+    pub blocked_packets: HashMap<String, u64>,
+
+    /// Idle cache keys and stale snapshot fields purged under each key
+    ///  folder, see `RETENTION_MAX_IDLE_MS`
+    pub purged_keys: HashMap<String, u64>,
+
+    /// Connections of each Redis pool, by key folder (`gis` for the
+    ///  svc-gis queues)
+    pub redis_pools: HashMap<String, PoolStatus>,
+}
+
+/// Aggregated statistics
+#[derive(Debug, Default)]
+struct StatsAggregator {
+    /// Received packets per type
+    packets: HashMap<String, MinuteCounter>,
+
+    /// Total received packets
+    received: u64,
+
+    /// Received packets suppressed as duplicates
+    suppressed: u64,
+
+    /// When each aircraft was last observed
+    aircraft: HashMap<String, DateTime<Utc>>,
+
+    /// Total time spent handling requests
+    latency_total: std::time::Duration,
+
+    /// Number of handled requests
+    latency_count: u32,
+
+    /// Failed requests per dependency
+    errors: HashMap<Dependency, u64>,
+
+    /// Errors per code
+    error_codes: HashMap<String, u64>,
+
+    /// Entries dropped per stream
+    dropped: HashMap<String, u64>,
+
+    /// Stale entries dropped per queue
+    stale: HashMap<String, u64>,
+
+    /// Last polled depth per RabbitMQ queue
+    queue_depths: HashMap<String, u32>,
+
+    /// Polls finding each RabbitMQ queue full
+    queue_overflows: HashMap<String, u64>,
+
+    /// Last polled consumers per RabbitMQ queue
+    queue_consumers: HashMap<String, u32>,
+
+    /// Polls finding each RabbitMQ queue behind its consumers
+    queue_lags: HashMap<String, u64>,
+
+    /// Packets of blocked aircraft rejected per source
+    blocked: HashMap<String, u64>,
+
+    /// Keys purged per key folder
+    purged: HashMap<String, u64>,
+
+    /// Connections of each Redis pool, waits summed up
+    pools: HashMap<String, PoolStatus>,
+
+    /// Total wait for a connection per Redis pool
+    pool_waits: HashMap<String, std::time::Duration>,
+}
+
+/// Statistics shared between request handlers
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// The aggregated statistics
+    inner: Arc<Mutex<StatsAggregator>>,
+}
+
+impl Stats {
+    /// Lock the statistics, which remain usable if a holder panicked
+    fn lock(&self) -> MutexGuard<'_, StatsAggregator> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Count a received packet of the given type
+    pub fn record_packet(&self, packet_type: &str, suppressed: bool) {
+        let mut stats = self.lock();
+        stats
+            .packets
+            .entry(packet_type.to_string())
+            .or_default()
+            .add(Utc::now());
+
+        stats.received += 1;
+        if suppressed {
+            stats.suppressed += 1;
+        }
+    }
+
+    /// Record that an aircraft was observed
+    pub fn record_aircraft(&self, identifier: &str) {
+        let now = Utc::now();
+        let mut stats = self.lock();
+        if let Some(seen) = stats.aircraft.get_mut(identifier) {
+            *seen = now;
+            return;
+        }
+
+        let window = Duration::try_minutes(WINDOW_MINUTES).unwrap_or(Duration::zero());
+        stats.aircraft.retain(|_, seen| now - *seen < window);
+        stats.aircraft.insert(identifier.to_string(), now);
+    }
+
+    /// Record the time spent handling a request
+    pub fn record_latency(&self, latency: std::time::Duration) {
+        let mut stats = self.lock();
+        stats.latency_total += latency;
+        stats.latency_count = stats.latency_count.saturating_add(1);
+    }
+
+    /// Count a failed request to a dependency
+    pub fn record_error(&self, dependency: Dependency) {
+        *self.lock().errors.entry(dependency).or_default() += 1;
+    }
+
+    /// Count an error by its code
+    pub fn record_error_code(&self, code: &str) {
+        *self.lock().error_codes.entry(code.to_string()).or_default() += 1;
+    }
+
+    /// Count entries dropped from a full stream
+    pub fn record_dropped(&self, stream: &str, count: u64) {
+        *self.lock().dropped.entry(stream.to_string()).or_default() += count;
+    }
+
+    /// Count stale entries dropped from a queue when read
+    pub fn record_stale(&self, queue: &str, count: u64) {
+        *self.lock().stale.entry(queue.to_string()).or_default() += count;
+    }
+
+    /// Record the polled depth of a RabbitMQ queue, counting an overflow
+    ///  if it was full
+    pub fn record_queue_depth(&self, queue: &str, depth: u32, full: bool) {
+        let mut stats = self.lock();
+        stats.queue_depths.insert(queue.to_string(), depth);
+        if full {
+            *stats.queue_overflows.entry(queue.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Record the polled consumers of a RabbitMQ queue
+    pub fn record_queue_consumers(&self, queue: &str, consumers: u32) {
+        self.lock()
+            .queue_consumers
+            .insert(queue.to_string(), consumers);
+    }
+
+    /// Count a poll finding the consumers of a RabbitMQ queue behind
+    pub fn record_queue_lag(&self, queue: &str) {
+        *self.lock().queue_lags.entry(queue.to_string()).or_default() += 1;
+    }
+
+    /// Count a packet of a blocked aircraft rejected
+    pub fn record_blocked(&self, source: &str) {
+        *self.lock().blocked.entry(source.to_string()).or_default() += 1;
+    }
+
+    /// Count keys purged from a key folder for being idle
+    pub fn record_purged(&self, folder: &str, count: u64) {
+        *self.lock().purged.entry(folder.to_string()).or_default() += count;
+    }
+
+    /// Record a connection checked out of a Redis pool, with the state of
+    ///  the pool and the wait for it, counting a timeout if none became
+    ///  free in time
+    pub fn record_pool_checkout(
+        &self,
+        pool: &str,
+        status: deadpool_redis::Status,
+        wait: std::time::Duration,
+        timed_out: bool,
+    ) {
+        let mut stats = self.lock();
+        let total = stats.pool_waits.entry(pool.to_string()).or_default();
+        *total += wait;
+        let total = *total;
+
+        let entry = stats.pools.entry(pool.to_string()).or_default();
+        entry.max_size = status.max_size;
+        entry.size = status.size;
+        entry.available = status.available;
+        entry.waiting = status.waiting;
+        entry.checkouts += 1;
+        entry.timeouts += timed_out as u64;
+        entry.average_wait_ms = total.as_secs_f64() * 1000.0 / entry.checkouts as f64;
+        entry.max_wait_ms = entry.max_wait_ms.max(wait.as_secs_f64() * 1000.0);
+    }
+
+    /// Summarize the statistics, with the given circuit breaker states
+    pub fn summary(&self, circuit_breakers: HashMap<Dependency, BreakerState>) -> StatsSummary {
+        let now = Utc::now();
+        let stats = self.lock();
+        let window = Duration::try_minutes(WINDOW_MINUTES).unwrap_or(Duration::zero());
+
+        let packets = stats
+            .packets
+            .iter()
+            .map(|(packet_type, counter)| {
+                (
+                    packet_type.clone(),
+                    PacketCounts {
+                        last_1_min: counter.sum(now, 1),
+                        last_5_min: counter.sum(now, 5),
+                        last_15_min: counter.sum(now, 15),
+                    },
+                )
+            })
+            .collect();
+
+        let dedup_suppression_ratio = match stats.received {
+            0 => 0.0,
+            received => stats.suppressed as f64 / received as f64,
+        };
+
+        let average_latency_ms = match stats.latency_count {
+            0 => 0.0,
+            count => stats.latency_total.as_secs_f64() * 1000.0 / count as f64,
+        };
+
+        StatsSummary {
+            packets,
+            unique_aircraft: stats
+                .aircraft
+                .values()
+                .filter(|seen| now - **seen < window)
+                .count(),
+            dedup_suppression_ratio,
+            average_latency_ms,
+            dependency_errors: stats.errors.clone(),
+            error_codes: stats.error_codes.clone(),
+            circuit_breakers,
+            dropped_entries: stats.dropped.clone(),
+            stale_entries: stats.stale.clone(),
+            queue_depths: stats.queue_depths.clone(),
+            queue_overflows: stats.queue_overflows.clone(),
+            queue_consumers: stats.queue_consumers.clone(),
+            queue_lags: stats.queue_lags.clone(),
+            blocked_packets: stats.blocked.clone(),
+            purged_keys: stats.purged.clone(),
+            redis_pools: stats.pools.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minute_counter() {
+        let start = DateTime::from_timestamp(0, 0).unwrap();
+        let minutes = |n: i64| start + Duration::try_minutes(n).unwrap();
+
+        let mut counter = MinuteCounter::default();
+        counter.add(minutes(0));
+        counter.add(minutes(0));
+        counter.add(minutes(3));
+        counter.add(minutes(10));
+
+        assert_eq!(counter.sum(minutes(10), 1), 1);
+        assert_eq!(counter.sum(minutes(10), 5), 1);
+        assert_eq!(counter.sum(minutes(10), 15), 4);
+        assert_eq!(counter.sum(minutes(16), 15), 2);
+
+        // old buckets are dropped
+        counter.add(minutes(20));
+        assert_eq!(counter.buckets.len(), 2);
+    }
+
+    #[test]
+    fn test_stats_summary() {
+        let stats = Stats::default();
+        let summary = stats.summary(HashMap::new());
+        assert!(summary.packets.is_empty());
+        assert_eq!(summary.dedup_suppression_ratio, 0.0);
+        assert_eq!(summary.average_latency_ms, 0.0);
+
+        stats.record_packet("adsb", false);
+        stats.record_packet("adsb", true);
+        stats.record_packet("netrid", false);
+        stats.record_packet("netrid", true);
+        stats.record_aircraft("a");
+        stats.record_aircraft("a");
+        stats.record_aircraft("b");
+        stats.record_latency(std::time::Duration::from_millis(10));
+        stats.record_latency(std::time::Duration::from_millis(20));
+        stats.record_error(Dependency::Gis);
+        stats.record_error(Dependency::Gis);
+        stats.record_error_code("cache_pool_exhausted");
+        stats.record_dropped("aircraft:position", 1);
+        stats.record_dropped("aircraft:position", 11);
+        stats.record_stale("aircraft:velocity", 3);
+        stats.record_queue_depth("adsb", 1000, true);
+        stats.record_queue_depth("adsb", 10, false);
+        stats.record_queue_consumers("adsb", 0);
+        stats.record_queue_lag("adsb");
+        stats.record_queue_consumers("adsb", 2);
+        stats.record_blocked("netrid");
+        stats.record_blocked("netrid");
+        stats.record_purged("tlm:netrid", 4);
+        stats.record_purged("tlm:netrid", 0);
+        let status = |size, available, waiting| deadpool_redis::Status {
+            max_size: 16,
+            size,
+            available,
+            waiting,
+        };
+        let ms = std::time::Duration::from_millis;
+        stats.record_pool_checkout("tlm:adsb", status(4, 2, 0), ms(2), false);
+        stats.record_pool_checkout("tlm:adsb", status(16, 0, 3), ms(100), true);
+
+        let breakers = HashMap::from([(Dependency::Storage, BreakerState::Open)]);
+        let summary = stats.summary(breakers);
+        assert_eq!(summary.packets["adsb"].last_1_min, 2);
+        assert_eq!(summary.packets["netrid"].last_15_min, 2);
+        assert_eq!(summary.unique_aircraft, 2);
+        assert_eq!(summary.dedup_suppression_ratio, 0.5);
+        assert!((summary.average_latency_ms - 15.0).abs() < 1e-9);
+        assert_eq!(summary.dependency_errors[&Dependency::Gis], 2);
+        assert!(!summary.dependency_errors.contains_key(&Dependency::Redis));
+        assert_eq!(summary.error_codes["cache_pool_exhausted"], 1);
+        assert_eq!(
+            summary.circuit_breakers[&Dependency::Storage],
+            BreakerState::Open
+        );
+        assert_eq!(summary.dropped_entries["aircraft:position"], 12);
+        assert_eq!(summary.stale_entries["aircraft:velocity"], 3);
+        assert!(!summary.stale_entries.contains_key("aircraft:position"));
+        assert_eq!(summary.queue_depths["adsb"], 10);
+        assert_eq!(summary.queue_overflows["adsb"], 1);
+        assert_eq!(summary.queue_consumers["adsb"], 2);
+        assert_eq!(summary.queue_lags["adsb"], 1);
+        assert_eq!(summary.blocked_packets["netrid"], 2);
+        assert!(!summary.blocked_packets.contains_key("adsb"));
+        assert_eq!(summary.purged_keys["tlm:netrid"], 4);
+
+        let pool = summary.redis_pools["tlm:adsb"];
+        assert_eq!((pool.max_size, pool.size, pool.available), (16, 16, 0));
+        assert_eq!(pool.waiting, 3);
+        assert_eq!(pool.checkouts, 2);
+        assert_eq!(pool.timeouts, 1);
+        assert!((pool.average_wait_ms - 51.0).abs() < 1e-9);
+        assert!((pool.max_wait_ms - 100.0).abs() < 1e-9);
+        assert!(!summary.redis_pools.contains_key("gis"));
+    }
+}
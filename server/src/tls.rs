@@ -0,0 +1,178 @@
+//! TLS certificates of the connections to Redis and RabbitMQ
+//!
+//! The connections are encrypted when the URL of the server has the
+//!  `rediss://` or `amqps://` scheme. The server certificate is verified
+//!  with the system certificate authorities, unless others are given, and
+//!  a client certificate can be presented for mutual TLS.
+
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::PKey;
+use openssl::x509::X509;
+use std::fmt::{self, Display, Formatter};
+
+/// Errors loading the TLS certificates of a connection
+#[derive(Debug)]
+pub enum TlsError {
+    /// A file could not be read
+    Read(String, std::io::Error),
+
+    /// Only one of the client certificate and key is given
+    Incomplete,
+
+    /// The client certificate or key is invalid
+    Invalid(String),
+}
+
+impl Display for TlsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsError::Read(path, e) => write!(f, "could not read {path}: {e}"),
+            TlsError::Incomplete => write!(f, "client certificate and key must be set together"),
+            TlsError::Invalid(e) => write!(f, "invalid client certificate or key: {e}"),
+        }
+    }
+}
+
+/// Certificates of a connection, in PEM
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TlsFiles {
+    /// Certificate authorities trusted instead of the system ones
+    pub ca: Option<Vec<u8>>,
+
+    /// Client certificate and private key, for mutual TLS
+    pub client: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Content of a file, none if no path is given
+fn read(path: &str) -> Result<Option<Vec<u8>>, TlsError> {
+    match path.trim() {
+        "" => Ok(None),
+        path => std::fs::read(path)
+            .map(Some)
+            .map_err(|e| TlsError::Read(path.to_string(), e)),
+    }
+}
+
+impl TlsFiles {
+    /// Reads the given files, empty paths being unset
+    pub fn load(ca_file: &str, cert_file: &str, key_file: &str) -> Result<Self, TlsError> {
+        let client = match (read(cert_file)?, read(key_file)?) {
+            (Some(cert), Some(key)) => Some((cert, key)),
+            (None, None) => None,
+            _ => return Err(TlsError::Incomplete),
+        };
+
+        Ok(TlsFiles {
+            ca: read(ca_file)?,
+            client,
+        })
+    }
+
+    /// No certificate is given, the defaults of the TLS library apply
+    pub fn is_empty(&self) -> bool {
+        self.ca.is_none() && self.client.is_none()
+    }
+
+    /// Client certificate and key as a PKCS#12 archive (DER) protected by
+    ///  `password`, as taken by the AMQP client
+    pub fn client_pkcs12(&self, password: &str) -> Result<Option<Vec<u8>>, TlsError> {
+        let Some((cert, key)) = &self.client else {
+            return Ok(None);
+        };
+
+        let invalid = |e: &dyn Display| TlsError::Invalid(e.to_string());
+        let cert = X509::from_pem(cert).map_err(|e| invalid(&e))?;
+        let key = PKey::private_key_from_pem(key).map_err(|e| invalid(&e))?;
+        if !cert.public_key().is_ok_and(|public| public.public_eq(&key)) {
+            return Err(invalid(&"the key doesn't match the certificate"));
+        }
+
+        Pkcs12::builder()
+            .name("svc-telemetry")
+            .pkey(&key)
+            .cert(&cert)
+            .build2(password)
+            .and_then(|archive| archive.to_der())
+            .map(Some)
+            .map_err(|e| invalid(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::x509::X509NameBuilder;
+
+    /// Self-signed certificate and private key, in PEM
+    fn certificate() -> (Vec<u8>, Vec<u8>) {
+        let key = PKey::generate_ed25519().unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "svc-telemetry").unwrap();
+        let name = name.build();
+
+        let mut cert = X509::builder().unwrap();
+        cert.set_subject_name(&name).unwrap();
+        cert.set_issuer_name(&name).unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        cert.sign(&key, MessageDigest::null()).unwrap();
+
+        (
+            cert.build().to_pem().unwrap(),
+            key.private_key_to_pem_pkcs8().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = std::env::temp_dir().join(format!("tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (cert, key) = certificate();
+        let cert_file = dir.join("client.pem");
+        let key_file = dir.join("client.key");
+        std::fs::write(&cert_file, &cert).unwrap();
+        std::fs::write(&key_file, &key).unwrap();
+        let cert_file = cert_file.to_str().unwrap();
+        let key_file = key_file.to_str().unwrap();
+
+        let files = TlsFiles::load("", "", "").unwrap();
+        assert!(files.is_empty());
+        assert_eq!(files.client_pkcs12("").unwrap(), None);
+
+        let files = TlsFiles::load(cert_file, cert_file, key_file).unwrap();
+        assert_eq!(files.ca, Some(cert.clone()));
+        assert_eq!(files.client, Some((cert.clone(), key.clone())));
+        assert!(!files.is_empty());
+
+        let archive = files.client_pkcs12("secret").unwrap().unwrap();
+        let parsed = Pkcs12::from_der(&archive)
+            .unwrap()
+            .parse2("secret")
+            .unwrap();
+        assert_eq!(parsed.cert.unwrap().to_pem().unwrap(), cert);
+
+        assert!(matches!(
+            TlsFiles::load("", cert_file, ""),
+            Err(TlsError::Incomplete)
+        ));
+        assert!(matches!(
+            TlsFiles::load("/nonexistent/ca.pem", "", ""),
+            Err(TlsError::Read(..))
+        ));
+
+        // another key
+        let (_, other) = certificate();
+        let files = TlsFiles {
+            ca: None,
+            client: Some((cert, other)),
+        };
+        assert!(matches!(files.client_pkcs12(""), Err(TlsError::Invalid(_))));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,520 @@
+//! Fixture Tests
+//!
+//! Decodes every frame of the `fixtures/` corpus and compares the decoded
+//!  output with its snapshot in `fixtures/snapshots/`. Set
+//!  `UPDATE_FIXTURES=1` to write the snapshots instead, and review their
+//!  diff before committing.
+
+use adsb_deku::adsb::TypeCoding;
+use adsb_deku::Sign;
+use lib_common::time::{DateTime, Utc};
+use packed_struct::PackedStruct;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+use svc_telemetry::msg::adsb::{self, ADSB_SIZE_BYTES};
+use svc_telemetry::msg::netrid::{
+    AuthenticationFirstPage, AuthenticationMessage, BasicMessage, Frame, LocationMessage,
+    MessagePack, MessageType, OperatorIdMessage, SystemMessage,
+};
+use svc_telemetry::rest::api::telemetry::detect;
+
+/// Reception time of the Remote ID fixtures, for their hour-relative
+///  timestamps
+const NETRID_RECEIVED: &str = "2024-03-14T10:15:00Z";
+
+/// Location of the receiver of the surface position fixtures
+const SURFACE_REFERENCE: (f64, f64) = (51.99, 4.375);
+
+/// Characters of ADS-B identification messages, by 6 bit code
+const ADSB_CHARSET: &[u8; 64] = b"#ABCDEFGHIJKLMNOPQRSTUVWXYZ##### ###############0123456789######";
+
+/// Escape byte and start of Mode S Beast frames
+const BEAST_ESCAPE: u8 = 0x1a;
+
+/// MAVLink CRC_EXTRA seeds of the messages in the corpus, by message id
+const MAVLINK_CRC_EXTRA: [(u32, u8); 2] = [(0, 50), (1, 124)];
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../fixtures")
+}
+
+/// Named frames of a corpus file, skipping comments and blank lines
+fn load(kind: &str) -> Vec<(String, Vec<u8>)> {
+    let path = fixtures_dir().join(format!("{kind}.hex"));
+    let corpus = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path:?}: {e}"));
+    corpus
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (name, hex) = line
+                .split_once(char::is_whitespace)
+                .unwrap_or_else(|| panic!("{kind}: no frame in {line:?}"));
+            let bytes = (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .unwrap_or_else(|e| panic!("{kind}: {name}: {e}"));
+            (name.to_string(), bytes)
+        })
+        .collect()
+}
+
+/// Compare the decoded corpus with its snapshot, or write the snapshot
+fn assert_snapshot(kind: &str, decoded: &str) {
+    let path = fixtures_dir()
+        .join("snapshots")
+        .join(format!("{kind}.snap"));
+    if std::env::var_os("UPDATE_FIXTURES").is_some() {
+        std::fs::write(&path, decoded).unwrap_or_else(|e| panic!("{path:?}: {e}"));
+        return;
+    }
+
+    let snapshot = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path:?}: {e}"));
+    for (line, (expected, actual)) in snapshot.lines().zip(decoded.lines()).enumerate() {
+        assert_eq!(expected, actual, "{kind}.snap line {}", line + 1);
+    }
+
+    assert_eq!(
+        snapshot.lines().count(),
+        decoded.lines().count(),
+        "{kind}.snap length"
+    );
+}
+
+/// Bits `start..start + len` of the 56 bit ME field, MSB first
+fn me_bits(bytes: &[u8; ADSB_SIZE_BYTES], start: u32, len: u32) -> u32 {
+    let me = bytes[4..11]
+        .iter()
+        .fold(0_u64, |me, byte| me << 8 | *byte as u64);
+    ((me >> (56 - start - len)) & ((1 << len) - 1)) as u32
+}
+
+fn sign(bit: u32) -> Sign {
+    match bit {
+        0 => Sign::Positive,
+        _ => Sign::Negative,
+    }
+}
+
+/// Raw CPR fields of a position: (odd, latitude, longitude)
+type Cpr = (bool, u32, u32);
+
+/// Decoded CPR frames waiting for the other frame of their pair, by
+///  aircraft and surface flag
+type CprPairs = HashMap<(u32, bool), [Option<Cpr>; 2]>;
+
+/// Decode a Mode S packet, pairing CPR positions with earlier frames
+fn decode_modes(bytes: &[u8], pairs: &mut CprPairs, out: &mut String) {
+    let Some(df) = adsb::get_downlink_format(bytes) else {
+        writeln!(out, "  empty").unwrap();
+        return;
+    };
+
+    writeln!(out, "  detected: {:?}", detect(bytes)).unwrap();
+    writeln!(out, "  df: {df}").unwrap();
+    if let Some(squawk) = adsb::get_squawk(bytes) {
+        writeln!(
+            out,
+            "  squawk: {squawk} emergency: {:?}",
+            squawk.emergency()
+        )
+        .unwrap();
+    }
+
+    let Ok(bytes) = <[u8; ADSB_SIZE_BYTES]>::try_from(bytes) else {
+        if let Some(icao) = adsb::get_reply_icao_address(bytes) {
+            writeln!(out, "  icao: {icao:06X}").unwrap();
+        }
+
+        return;
+    };
+
+    if !matches!(df, 17 | 18) {
+        return;
+    }
+
+    let icao = adsb::get_adsb_icao_address(&[bytes[1], bytes[2], bytes[3]]);
+    let tc = adsb::get_adsb_message_type(&bytes);
+    writeln!(out, "  icao: {icao:06X}").unwrap();
+    writeln!(out, "  crc residual: {:06X}", adsb::modes_crc(&bytes)).unwrap();
+    writeln!(out, "  tc: {tc}").unwrap();
+    match tc {
+        1..=4 => {
+            let type_coding = match tc {
+                1 => TypeCoding::D,
+                2 => TypeCoding::C,
+                3 => TypeCoding::B,
+                _ => TypeCoding::A,
+            };
+
+            let category = me_bits(&bytes, 5, 3) as u8;
+            let callsign: String = (0..8)
+                .map(|i| ADSB_CHARSET[me_bits(&bytes, 8 + 6 * i, 6) as usize] as char)
+                .collect();
+            writeln!(
+                out,
+                "  emitter category: {} {:?}",
+                adsb::emitter_category_code(type_coding, category),
+                adsb::EmitterCategory::new(type_coding, category)
+            )
+            .unwrap();
+            writeln!(out, "  callsign: {:?}", callsign.trim_end()).unwrap();
+        }
+        5..=8 | 9..=18 | 20..=22 => {
+            let surface = tc <= 8;
+            let cpr = (
+                me_bits(&bytes, 21, 1) == 1,
+                me_bits(&bytes, 22, 17),
+                me_bits(&bytes, 39, 17),
+            );
+
+            if surface {
+                writeln!(
+                    out,
+                    "  movement: {} track: {} valid: {}",
+                    me_bits(&bytes, 5, 7),
+                    me_bits(&bytes, 13, 7),
+                    me_bits(&bytes, 12, 1) == 1
+                )
+                .unwrap();
+            } else {
+                let altitude = adsb::decode_altitude(me_bits(&bytes, 8, 12) as u16);
+                writeln!(out, "  altitude: {altitude:.2} m").unwrap();
+                writeln!(out, "  emergency: {}", adsb::get_emergency_status(&bytes)).unwrap();
+            }
+
+            writeln!(out, "  cpr: odd {} lat {} lon {}", cpr.0, cpr.1, cpr.2).unwrap();
+            let pair = pairs.entry((icao, surface)).or_default();
+            pair[cpr.0 as usize] = Some(cpr);
+            if let (Some(even), Some(odd), true) = (pair[0], pair[1], cpr.0) {
+                let position = match surface {
+                    true => {
+                        adsb::decode_cpr_surface(even.1, even.2, odd.1, odd.2, SURFACE_REFERENCE)
+                    }
+                    false => adsb::decode_cpr(even.1, even.2, odd.1, odd.2),
+                };
+
+                match position {
+                    Ok((lat, lon)) => writeln!(out, "  position: {lat:.6} {lon:.6}").unwrap(),
+                    Err(e) => writeln!(out, "  position: {e}").unwrap(),
+                }
+            }
+        }
+        19 => {
+            let st = me_bits(&bytes, 5, 3) as u8;
+            let velocity = adsb::decode_speed_direction(
+                st,
+                sign(me_bits(&bytes, 13, 1)),
+                me_bits(&bytes, 14, 10) as u16,
+                sign(me_bits(&bytes, 24, 1)),
+                me_bits(&bytes, 25, 10) as u16,
+            );
+
+            match velocity {
+                Ok((speed, direction)) => {
+                    writeln!(out, "  velocity: {speed:.2} m/s {direction:.2} deg").unwrap()
+                }
+                Err(e) => writeln!(out, "  velocity: {e} (subtype {st})").unwrap(),
+            }
+
+            let vertical = adsb::decode_vertical_speed(
+                sign(me_bits(&bytes, 36, 1)),
+                me_bits(&bytes, 37, 9) as u16,
+            );
+            match vertical {
+                Ok(vertical) => writeln!(out, "  vertical speed: {vertical:.2} m/s").unwrap(),
+                Err(e) => writeln!(out, "  vertical speed: {e}").unwrap(),
+            }
+        }
+        29 => writeln!(out, "  target state: {:?}", adsb::get_target_state(&bytes)).unwrap(),
+        31 => writeln!(
+            out,
+            "  operational status: {:?}",
+            adsb::get_operational_status(&bytes)
+        )
+        .unwrap(),
+        _ => (),
+    }
+}
+
+#[test]
+fn test_adsb_fixtures() {
+    let mut pairs = CprPairs::new();
+    let mut out = String::new();
+    for (name, bytes) in load("adsb") {
+        writeln!(out, "{name}").unwrap();
+        decode_modes(&bytes, &mut pairs, &mut out);
+    }
+
+    assert_snapshot("adsb", &out);
+}
+
+/// Remove the escaping of a Beast frame, returning its type and body
+fn unescape_beast(frame: &[u8]) -> Option<(u8, Vec<u8>)> {
+    let (&BEAST_ESCAPE, rest) = frame.split_first()? else {
+        return None;
+    };
+
+    let (&kind, rest) = rest.split_first()?;
+    let mut body = Vec::with_capacity(rest.len());
+    let mut bytes = rest.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte == BEAST_ESCAPE && bytes.next() != Some(&BEAST_ESCAPE) {
+            return None;
+        }
+
+        body.push(byte);
+    }
+
+    Some((kind, body))
+}
+
+#[test]
+fn test_beast_fixtures() {
+    let mut pairs = CprPairs::new();
+    let mut out = String::new();
+    for (name, frame) in load("beast") {
+        writeln!(out, "{name}").unwrap();
+        let Some((kind, body)) = unescape_beast(&frame) else {
+            writeln!(out, "  invalid escaping").unwrap();
+            continue;
+        };
+
+        let length = match kind {
+            b'1' => 2,
+            b'2' => adsb::MODES_SHORT_SIZE_BYTES,
+            b'3' => ADSB_SIZE_BYTES,
+            _ => 0,
+        };
+
+        if body.len() != 7 + length {
+            writeln!(out, "  type {kind:#04x} length {}", body.len()).unwrap();
+            continue;
+        }
+
+        let timestamp = body[..6]
+            .iter()
+            .fold(0_u64, |timestamp, byte| timestamp << 8 | *byte as u64);
+        let message = &body[7..];
+        writeln!(
+            out,
+            "  type: {} timestamp: {timestamp} signal: {}",
+            kind as char, body[6]
+        )
+        .unwrap();
+
+        match kind {
+            b'1' => writeln!(out, "  mode a/c: {:02x}{:02x}", message[0], message[1]).unwrap(),
+            _ => decode_modes(message, &mut pairs, &mut out),
+        }
+    }
+
+    assert_snapshot("beast", &out);
+}
+
+/// MAVLink (X.25) checksum of the given bytes
+fn mavlink_crc(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0xFFFF, |crc: u16, byte| {
+        let tmp = byte ^ crc as u8;
+        let tmp = tmp ^ (tmp << 4);
+        (crc >> 8) ^ (tmp as u16) << 8 ^ (tmp as u16) << 3 ^ (tmp as u16) >> 4
+    })
+}
+
+#[test]
+fn test_mavlink_fixtures() {
+    let mut out = String::new();
+    for (name, packet) in load("mavlink") {
+        writeln!(out, "{name}").unwrap();
+        writeln!(out, "  detected: {:?}", detect(&packet)).unwrap();
+        let (header, msgid) = match packet.first() {
+            Some(0xFE) if packet.len() >= 8 => (6, packet[5] as u32),
+            Some(0xFD) if packet.len() >= 12 => {
+                (10, u32::from_le_bytes([packet[7], packet[8], packet[9], 0]))
+            }
+            _ => continue,
+        };
+
+        let (sequence, system, component) = match header {
+            6 => (packet[2], packet[3], packet[4]),
+            _ => (packet[4], packet[5], packet[6]),
+        };
+
+        let payload_end = header + packet[1] as usize;
+        let Some(&(_, crc_extra)) = MAVLINK_CRC_EXTRA.iter().find(|(id, _)| *id == msgid) else {
+            writeln!(out, "  msgid: {msgid} unknown").unwrap();
+            continue;
+        };
+
+        let mut checked = packet[1..payload_end].to_vec();
+        checked.push(crc_extra);
+        let crc = u16::from_le_bytes([packet[payload_end], packet[payload_end + 1]]);
+        writeln!(
+            out,
+            "  seq: {sequence} system: {system} component: {component} msgid: {msgid}"
+        )
+        .unwrap();
+        writeln!(out, "  payload: {} bytes", packet[1]).unwrap();
+        writeln!(out, "  checksum valid: {}", mavlink_crc(&checked) == crc).unwrap();
+    }
+
+    assert_snapshot("mavlink", &out);
+}
+
+/// Decode a Remote ID frame the way its processor reads it
+fn decode_netrid_frame(frame: &Frame, received: DateTime<Utc>, out: &mut String) {
+    let header = frame.header;
+    let Some(version) = header.version() else {
+        writeln!(
+            out,
+            "  unknown protocol version {}",
+            header.protocol_version
+        )
+        .unwrap();
+        return;
+    };
+
+    writeln!(
+        out,
+        "  type: {:?} version: {version:?}",
+        header.message_type
+    )
+    .unwrap();
+    match header.message_type {
+        MessageType::Basic => {
+            let basic = BasicMessage::unpack(&frame.message).unwrap();
+            let uas_id = String::from_utf8_lossy(&basic.uas_id);
+            writeln!(
+                out,
+                "  {:?} {:?} {:?}",
+                basic.id_type,
+                basic.ua_type,
+                uas_id.trim_end_matches('\0')
+            )
+            .unwrap();
+        }
+        MessageType::Location => {
+            let location = LocationMessage::unpack(&frame.message)
+                .unwrap()
+                .for_version(version);
+            writeln!(out, "  status: {:?}", location.operational_status).unwrap();
+            writeln!(
+                out,
+                "  position: {:.7} {:.7}",
+                location.decode_latitude(),
+                location.decode_longitude()
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "  altitude: {:?} height: {} {:?}",
+                location.decode_altitude(),
+                location.height,
+                location.height_type
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "  speed: {:?} direction: {:?} vertical: {:?}",
+                location.decode_speed(),
+                location.decode_direction(),
+                location.decode_vertical_speed()
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "  accuracy: {:?} {:?} {:?} {:?}",
+                location.horizontal_accuracy,
+                location.vertical_accuracy,
+                location.barometric_altitude_accuracy,
+                location.speed_accuracy
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "  timestamp: {:?}",
+                location.decode_timestamp(received)
+            )
+            .unwrap();
+        }
+        MessageType::System => {
+            let system = SystemMessage::unpack(&frame.message)
+                .unwrap()
+                .for_version(version);
+            writeln!(
+                out,
+                "  {:?} {:?} {:?} {:?}",
+                system.classification_type,
+                system.operator_location_source,
+                system.category,
+                system.class
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "  operator: {:?} altitude: {:?}",
+                system.decode_operator_location(),
+                system.decode_operator_altitude()
+            )
+            .unwrap();
+            writeln!(out, "  timestamp: {:?}", system.decode_timestamp()).unwrap();
+        }
+        MessageType::OperatorId => {
+            let operator = OperatorIdMessage::unpack(&frame.message).unwrap();
+            writeln!(out, "  operator id: {:?}", operator.decode_operator_id()).unwrap();
+        }
+        MessageType::Authentication => {
+            let auth = AuthenticationMessage::unpack(&frame.message).unwrap();
+            writeln!(
+                out,
+                "  {:?} page {}",
+                auth.authentication_type,
+                u8::from(auth.page_number)
+            )
+            .unwrap();
+            if u8::from(auth.page_number) == 0 {
+                let page = AuthenticationFirstPage::unpack(&auth.data).unwrap();
+                writeln!(
+                    out,
+                    "  last page: {} length: {} timestamp: {:?}",
+                    page.last_page_index,
+                    page.length,
+                    page.decode_timestamp()
+                )
+                .unwrap();
+            }
+        }
+        _ => (),
+    }
+}
+
+#[test]
+fn test_netrid_fixtures() {
+    let received: DateTime<Utc> = NETRID_RECEIVED.parse().unwrap();
+    let mut out = String::new();
+    for (name, bytes) in load("netrid") {
+        writeln!(out, "{name}").unwrap();
+        writeln!(out, "  detected: {:?}", detect(&bytes)).unwrap();
+        if bytes.first().is_some_and(|byte| byte >> 4 == 0xF) {
+            match MessagePack::unpack(&bytes) {
+                Ok(pack) => {
+                    for frame in pack.frames {
+                        decode_netrid_frame(&frame, received, &mut out);
+                    }
+                }
+                Err(e) => writeln!(out, "  {e}").unwrap(),
+            }
+
+            continue;
+        }
+
+        match <[u8; 25]>::try_from(bytes.as_slice()).map(|bytes| Frame::unpack(&bytes)) {
+            Ok(Ok(frame)) => decode_netrid_frame(&frame, received, &mut out),
+            _ => writeln!(out, "  invalid frame").unwrap(),
+        }
+    }
+
+    assert_snapshot("netrid", &out);
+}
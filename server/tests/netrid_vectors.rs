@@ -0,0 +1,469 @@
+//! Remote ID Test Vectors
+//!
+//! Frames encoded byte by byte from the message formats of ASTM F3411-22a,
+//!  independently of the packed structs: multi-byte fields are little
+//!  endian, and fields sharing a byte are listed from its most significant
+//!  bits. Each vector is decoded and compared with its fields, and its
+//!  fields are encoded back to the same bytes, so a field out of order or
+//!  of the wrong endianness fails both ways.
+
+use lib_common::time::{DateTime, Utc};
+use packed_struct::PackedStruct;
+use svc_telemetry::msg::netrid::{
+    BasicMessage, EastWestDirection, EuropeanUnionCategory, EuropeanUnionClass, Frame, GroundSpeed,
+    Header, HeightType, HorizontalAccuracyMeters, IdType, LocationDecodeError, LocationMessage,
+    MessageType, OperationalStatus, OperatorIdMessage, OperatorLocationSource,
+    SpeedAccuracyMetersPerSecond, SpeedMultiplier, SystemMessage, UaClassification, UaType,
+    VerticalAccuracyMeters,
+};
+
+/// Protocol version of the vectors, F3411-22a
+const PROTOCOL_VERSION: u8 = 0x2;
+
+/// Basic ID message vector
+struct BasicVector {
+    /// What the vector covers
+    name: &'static str,
+
+    /// Encoded frame, header included
+    frame: [u8; 25],
+
+    /// Fields of the message
+    message: BasicMessage,
+
+    /// Decoded UAS ID
+    uas_id: &'static str,
+}
+
+/// Location/Vector message vector
+struct LocationVector {
+    /// What the vector covers
+    name: &'static str,
+
+    /// Encoded frame, header included
+    frame: [u8; 25],
+
+    /// Fields of the message
+    message: LocationMessage,
+
+    /// Decoded direction, in degrees
+    direction: u16,
+
+    /// Decoded ground speed
+    speed: GroundSpeed,
+
+    /// Decoded vertical speed, in meters per second
+    vertical_speed: f32,
+
+    /// Decoded latitude and longitude, in degrees
+    position: (f64, f64),
+
+    /// Decoded pressure altitude, in meters
+    altitude: Result<f32, LocationDecodeError>,
+}
+
+/// System message vector
+struct SystemVector {
+    /// What the vector covers
+    name: &'static str,
+
+    /// Encoded frame, header included
+    frame: [u8; 25],
+
+    /// Fields of the message
+    message: SystemMessage,
+
+    /// Decoded operator latitude and longitude, in degrees
+    operator_location: Option<(f64, f64)>,
+
+    /// Decoded operator geodetic altitude, in meters
+    operator_altitude: Option<f32>,
+
+    /// Decoded timestamp
+    timestamp: &'static str,
+}
+
+/// Operator ID message vector
+struct OperatorIdVector {
+    /// What the vector covers
+    name: &'static str,
+
+    /// Encoded frame, header included
+    frame: [u8; 25],
+
+    /// Fields of the message
+    message: OperatorIdMessage,
+
+    /// Decoded operator ID
+    operator_id: &'static str,
+}
+
+/// Identifier padded with nulls to 20 bytes
+fn padded(identifier: &str) -> [u8; 20] {
+    let mut padded = [0; 20];
+    padded[..identifier.len()].copy_from_slice(identifier.as_bytes());
+    padded
+}
+
+/// Checks the header of a vector and returns its message, after checking
+///  that the message and header encode back to the frame
+fn unpack<T: PackedStruct<ByteArray = [u8; 24]>>(
+    name: &str,
+    frame: &[u8; 25],
+    message_type: MessageType,
+    expected: &T,
+) -> T {
+    let decoded = Frame::unpack(frame).unwrap();
+    let header = Header {
+        message_type,
+        protocol_version: PROTOCOL_VERSION,
+    };
+    assert_eq!(decoded.header, header, "{name}: header");
+
+    let encoded = Frame {
+        header,
+        message: expected.pack().unwrap(),
+    };
+    assert_eq!(&encoded.pack().unwrap(), frame, "{name}: encoding");
+
+    T::unpack(&decoded.message).unwrap()
+}
+
+fn basic_vectors() -> Vec<BasicVector> {
+    vec![
+        BasicVector {
+            name: "serial number of a rotorcraft",
+            frame: [
+                0x02, // Basic ID, F3411-22a
+                0x12, // serial number, helicopter or multirotor
+                b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9', b'0', // UAS ID
+                b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9', b'0', //
+                0x00, 0x00, 0x00, // reserved
+            ],
+            message: BasicMessage {
+                id_type: IdType::SerialNumber,
+                ua_type: UaType::Rotorcraft,
+                uas_id: padded("12345678901234567890"),
+                reserved: [0; 3],
+            },
+            uas_id: "12345678901234567890",
+        },
+        BasicVector {
+            name: "CAA registration of an aeroplane, padded with nulls",
+            frame: [
+                0x02, // Basic ID, F3411-22a
+                0x21, // CAA assigned registration ID, aeroplane
+                b'N', b'1', b'2', b'3', b'A', b'B', 0x00, 0x00, 0x00, 0x00, // UAS ID
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+                0x00, 0x00, 0x00, // reserved
+            ],
+            message: BasicMessage {
+                id_type: IdType::CaaAssigned,
+                ua_type: UaType::Aeroplane,
+                uas_id: padded("N123AB"),
+                reserved: [0; 3],
+            },
+            uas_id: "N123AB",
+        },
+    ]
+}
+
+fn location_vectors() -> Vec<LocationVector> {
+    vec![
+        LocationVector {
+            name: "airborne, heading west, above ground level",
+            frame: [
+                0x12, // Location/Vector, F3411-22a
+                0x26, // airborne, reserved, above ground level, west, x0.25
+                0x23, // 35 + 180 degrees
+                0x16, // 22 * 0.25 m/s
+                0x05, // 5 * 0.5 m/s
+                0x60, 0xA9, 0x36, 0x1F, // 52.3676 degrees * 10^7
+                0x68, 0x4E, 0xEC, 0x02, // 4.9041 degrees * 10^7
+                0x98, 0x08, // pressure altitude, (100 + 1000) / 0.5 m
+                0xAC, 0x08, // geodetic altitude, (110 + 1000) / 0.5 m
+                0x70, 0x08, // height, (80 + 1000) / 0.5 m
+                0x4A, // vertical < 10 m, horizontal < 10 m
+                0x43, // barometric altitude < 10 m, speed < 1 m/s
+                0x15, 0x0E, // 3605 tenths of seconds since the hour
+                0x02, // reserved, timestamp accuracy 0.2 s
+                0x00, // reserved
+            ],
+            message: LocationMessage {
+                operational_status: OperationalStatus::Airborne,
+                reserved_0: 0.into(),
+                height_type: HeightType::AboveGroundLevel,
+                ew_direction: EastWestDirection::West,
+                speed_multiplier: SpeedMultiplier::X0_25,
+                track_direction: 35,
+                speed: 22,
+                vertical_speed: 5,
+                latitude: LocationMessage::encode_latitude(52.3676),
+                longitude: LocationMessage::encode_longitude(4.9041),
+                pressure_altitude: LocationMessage::encode_altitude(100.0),
+                geodetic_altitude: LocationMessage::encode_altitude(110.0),
+                height: LocationMessage::encode_altitude(80.0),
+                vertical_accuracy: VerticalAccuracyMeters::Lt10,
+                horizontal_accuracy: HorizontalAccuracyMeters::Lt10,
+                barometric_altitude_accuracy: VerticalAccuracyMeters::Lt10,
+                speed_accuracy: SpeedAccuracyMetersPerSecond::Lt1,
+                timestamp: 3605,
+                reserved_1: 0.into(),
+                timestamp_accuracy: 2.into(),
+                reserved_2: 0,
+            },
+            direction: 215,
+            speed: GroundSpeed::Known(5.5),
+            vertical_speed: 2.5,
+            position: (52.3676, 4.9041),
+            altitude: Ok(100.0),
+        },
+        LocationVector {
+            name: "fast and descending, heading east, unknown pressure altitude",
+            frame: [
+                0x12, // Location/Vector, F3411-22a
+                0x21, // airborne, reserved, above takeoff, east, x0.75
+                0x5A, // 90 degrees
+                0x09, // 63.75 + 9 * 0.75 m/s
+                0xFA, // -6 * 0.5 m/s
+                0xC0, 0xDC, 0xD1, 0xEB, // -33.8568 degrees * 10^7
+                0xA8, 0x9F, 0x21, 0x5A, // 151.2153 degrees * 10^7
+                0x00, 0x00, // pressure altitude unknown
+                0x17, 0x08, // geodetic altitude, (35.5 + 1000) / 0.5 m
+                0xD0, 0x07, // height, (0 + 1000) / 0.5 m
+                0x5B, // vertical < 3 m, horizontal < 3 m
+                0x04, // barometric altitude unknown, speed < 0.3 m/s
+                0x00, 0x00, // start of the hour
+                0x01, // reserved, timestamp accuracy 0.1 s
+                0x00, // reserved
+            ],
+            message: LocationMessage {
+                operational_status: OperationalStatus::Airborne,
+                reserved_0: 0.into(),
+                height_type: HeightType::AboveTakeoff,
+                ew_direction: EastWestDirection::East,
+                speed_multiplier: SpeedMultiplier::X0_75,
+                track_direction: 90,
+                speed: 9,
+                vertical_speed: -6,
+                latitude: LocationMessage::encode_latitude(-33.8568),
+                longitude: LocationMessage::encode_longitude(151.2153),
+                pressure_altitude: 0,
+                geodetic_altitude: LocationMessage::encode_altitude(35.5),
+                height: LocationMessage::encode_altitude(0.0),
+                vertical_accuracy: VerticalAccuracyMeters::Lt3,
+                horizontal_accuracy: HorizontalAccuracyMeters::Lt3,
+                barometric_altitude_accuracy: VerticalAccuracyMeters::Gte150Unknown,
+                speed_accuracy: SpeedAccuracyMetersPerSecond::Lt0_3,
+                timestamp: 0,
+                reserved_1: 0.into(),
+                timestamp_accuracy: 1.into(),
+                reserved_2: 0,
+            },
+            direction: 90,
+            speed: GroundSpeed::Known(70.5),
+            vertical_speed: -3.0,
+            position: (-33.8568, 151.2153),
+            altitude: Err(LocationDecodeError::UnknownAltitude),
+        },
+    ]
+}
+
+fn system_vectors() -> Vec<SystemVector> {
+    vec![
+        SystemVector {
+            name: "live operator location, EU open category",
+            frame: [
+                0x42, // System, F3411-22a
+                0x05, // reserved, EU classification, live GNSS operator location
+                0x70, 0xD0, 0x36, 0x1F, // 52.3686 degrees * 10^7
+                0x78, 0x75, 0xEC, 0x02, // 4.9051 degrees * 10^7
+                0x01, 0x00, // area count
+                0x00, // area radius, * 10 m
+                0x00, 0x00, // area ceiling unknown
+                0x00, 0x00, // area floor unknown
+                0x12, // open category, class C1
+                0xF8, 0x07, // operator altitude, (20 + 1000) / 0.5 m
+                0x10, 0x0E, 0x00, 0x00, // 3600 s since 2019-01-01
+                0x00, // reserved
+            ],
+            message: SystemMessage {
+                reserved_0: 0.into(),
+                classification_type: UaClassification::EuropeanUnion,
+                operator_location_source: OperatorLocationSource::Dynamic,
+                operator_latitude: SystemMessage::encode_coordinate(52.3686),
+                operator_longitude: SystemMessage::encode_coordinate(4.9051),
+                area_count: 1,
+                area_radius: 0,
+                area_ceiling: 0,
+                area_floor: 0,
+                category: EuropeanUnionCategory::Open,
+                class: EuropeanUnionClass::C1,
+                operator_altitude: 2040,
+                timestamp: 3600,
+                reserved_1: 0,
+            },
+            operator_location: Some((52.3686, 4.9051)),
+            operator_altitude: Some(20.0),
+            timestamp: "2019-01-01T01:00:00Z",
+        },
+        SystemVector {
+            name: "swarm area, takeoff location unknown, undeclared classification",
+            frame: [
+                0x42, // System, F3411-22a
+                0x00, // reserved, undeclared classification, takeoff location
+                0x00, 0x00, 0x00, 0x00, // latitude unknown
+                0x00, 0x00, 0x00, 0x00, // longitude unknown
+                0x05, 0x00, // area count
+                0x0A, // area radius, 10 * 10 m
+                0xC0, 0x08, // area ceiling, (120 + 1000) / 0.5 m
+                0xD0, 0x07, // area floor, (0 + 1000) / 0.5 m
+                0x00, // undefined category and class
+                0x00, 0x00, // operator altitude unknown
+                0xA4, 0x20, 0xC8, 0x09, // 164110500 s since 2019-01-01
+                0x00, // reserved
+            ],
+            message: SystemMessage {
+                reserved_0: 0.into(),
+                classification_type: UaClassification::Undeclared,
+                operator_location_source: OperatorLocationSource::Takeoff,
+                operator_latitude: 0,
+                operator_longitude: 0,
+                area_count: 5,
+                area_radius: 10,
+                area_ceiling: LocationMessage::encode_altitude(120.0),
+                area_floor: LocationMessage::encode_altitude(0.0),
+                category: EuropeanUnionCategory::Undefined,
+                class: EuropeanUnionClass::Undefined,
+                operator_altitude: 0,
+                timestamp: 164_110_500,
+                reserved_1: 0,
+            },
+            operator_location: None,
+            operator_altitude: None,
+            timestamp: "2024-03-14T10:15:00Z",
+        },
+    ]
+}
+
+fn operator_id_vectors() -> Vec<OperatorIdVector> {
+    vec![OperatorIdVector {
+        name: "EU operator registration number",
+        frame: [
+            0x52, // Operator ID, F3411-22a
+            0x00, // operator ID type
+            b'F', b'I', b'N', b'8', b'7', b'a', b's', b't', b'r', b'd', // operator ID
+            b'g', b'e', b'1', b'2', b'k', b'8', 0x00, 0x00, 0x00, 0x00, //
+            0x00, 0x00, 0x00, // reserved
+        ],
+        message: OperatorIdMessage {
+            operator_id_type: 0,
+            operator_id: padded("FIN87astrdge12k8"),
+            reserved: [0; 3],
+        },
+        operator_id: "FIN87astrdge12k8",
+    }]
+}
+
+#[test]
+fn test_basic_vectors() {
+    for vector in basic_vectors() {
+        let name = vector.name;
+        let message = unpack(name, &vector.frame, MessageType::Basic, &vector.message);
+        assert_eq!(message, vector.message, "{name}");
+        assert_eq!(
+            message.decode_uas_id().as_deref(),
+            Some(vector.uas_id),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn test_location_vectors() {
+    for vector in location_vectors() {
+        let name = vector.name;
+        let message = unpack(name, &vector.frame, MessageType::Location, &vector.message);
+        assert_eq!(message, vector.message, "{name}");
+        assert_eq!(message.decode_direction(), Ok(vector.direction), "{name}");
+        assert_eq!(message.decode_ground_speed(), vector.speed, "{name}");
+        assert_eq!(
+            message.decode_vertical_speed(),
+            Ok(vector.vertical_speed),
+            "{name}"
+        );
+        assert!(
+            (message.decode_latitude() - vector.position.0).abs() < 1e-7,
+            "{name}"
+        );
+        assert!(
+            (message.decode_longitude() - vector.position.1).abs() < 1e-7,
+            "{name}"
+        );
+        assert_eq!(message.decode_altitude(), vector.altitude, "{name}");
+
+        // The message fields encode to the same values
+        let (ew_direction, track_direction) =
+            LocationMessage::encode_direction(vector.direction).unwrap();
+        assert_eq!(ew_direction, message.ew_direction, "{name}");
+        assert_eq!(track_direction, message.track_direction, "{name}");
+        assert_eq!(
+            LocationMessage::encode_ground_speed(vector.speed),
+            Ok((message.speed_multiplier, message.speed)),
+            "{name}"
+        );
+        assert_eq!(
+            LocationMessage::encode_vertical_speed(vector.vertical_speed),
+            message.vertical_speed,
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn test_system_vectors() {
+    for vector in system_vectors() {
+        let name = vector.name;
+        let message = unpack(name, &vector.frame, MessageType::System, &vector.message);
+        assert_eq!(message, vector.message, "{name}");
+
+        let location = message.decode_operator_location();
+        match (location, vector.operator_location) {
+            (Some((latitude, longitude)), Some(expected)) => {
+                assert!((latitude - expected.0).abs() < 1e-7, "{name}");
+                assert!((longitude - expected.1).abs() < 1e-7, "{name}");
+            }
+            (location, expected) => assert_eq!(location, expected, "{name}"),
+        }
+
+        assert_eq!(
+            message.decode_operator_altitude(),
+            vector.operator_altitude,
+            "{name}"
+        );
+        assert_eq!(
+            message.decode_timestamp(),
+            Some(vector.timestamp.parse::<DateTime<Utc>>().unwrap()),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn test_operator_id_vectors() {
+    for vector in operator_id_vectors() {
+        let name = vector.name;
+        let message = unpack(
+            name,
+            &vector.frame,
+            MessageType::OperatorId,
+            &vector.message,
+        );
+        assert_eq!(message, vector.message, "{name}");
+        assert_eq!(
+            message.decode_operator_id().as_deref(),
+            Some(vector.operator_id),
+            "{name}"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! Simulated aircraft flying within an area
+
+use crate::frame::{airborne_position, airborne_velocity, identification, Frame};
+
+/// Nautical miles per degree of latitude
+const NM_PER_DEGREE: f64 = 60.0;
+
+/// Kilometers per nautical mile
+const KM_PER_NM: f64 = 1.852;
+
+/// Frames of an aircraft between two of its identifications
+const IDENTIFICATION_PERIOD: u64 = 20;
+
+/// Circular area the aircraft fly within
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Area {
+    /// Latitude of the center, in degrees
+    pub latitude: f64,
+
+    /// Longitude of the center, in degrees
+    pub longitude: f64,
+
+    /// Radius, in kilometers
+    pub radius_km: f64,
+}
+
+impl Area {
+    /// Distance of a position from the center, in kilometers
+    fn distance_km(&self, latitude: f64, longitude: f64) -> f64 {
+        let (north, east) = self.offset_nm(latitude, longitude);
+        north.hypot(east) * KM_PER_NM
+    }
+
+    /// Offset of a position from the center, in nautical miles north and east
+    fn offset_nm(&self, latitude: f64, longitude: f64) -> (f64, f64) {
+        let north = (latitude - self.latitude) * NM_PER_DEGREE;
+        let east = (longitude - self.longitude) * NM_PER_DEGREE * self.latitude.to_radians().cos();
+        (north, east)
+    }
+}
+
+/// Deterministic pseudo-random numbers, for repeatable traffic
+#[derive(Debug, Clone, Copy)]
+struct Random(u64);
+
+impl Random {
+    /// Next number, by xorshift64*
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    /// Number between min and max
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// State of a simulated aircraft
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aircraft {
+    /// 24-bit ICAO address
+    pub icao: u32,
+
+    /// Callsign
+    pub callsign: String,
+
+    /// Latitude, in degrees
+    pub latitude: f64,
+
+    /// Longitude, in degrees
+    pub longitude: f64,
+
+    /// Barometric altitude, in feet
+    pub altitude_ft: f64,
+
+    /// Altitude the aircraft climbs or descends to, in feet
+    pub target_altitude_ft: f64,
+
+    /// Speed over ground, in knots
+    pub ground_speed_kt: f64,
+
+    /// Track over ground, in degrees clockwise from north
+    pub track_deg: f64,
+
+    /// Rate of climb, in feet per minute
+    pub vertical_rate_fpm: f64,
+
+    /// Frames sent by the aircraft
+    frames: u64,
+}
+
+impl Aircraft {
+    /// Moves the aircraft along its track, turning back towards the center
+    ///  of the area when leaving it
+    fn advance(&mut self, seconds: f64, area: &Area, random: &mut Random) {
+        let distance_nm = self.ground_speed_kt * seconds / 3600.0;
+        let track = self.track_deg.to_radians();
+        self.latitude += distance_nm * track.cos() / NM_PER_DEGREE;
+        self.longitude +=
+            distance_nm * track.sin() / (NM_PER_DEGREE * self.latitude.to_radians().cos());
+
+        let (north, east) = area.offset_nm(self.latitude, self.longitude);
+        let outbound = north * track.cos() + east * track.sin() > 0.0;
+        if outbound && area.distance_km(self.latitude, self.longitude) > area.radius_km {
+            let inbound = (-east).atan2(-north).to_degrees();
+            self.track_deg = (inbound + random.range(-30.0, 30.0)).rem_euclid(360.0);
+        }
+
+        let climb_ft = self.vertical_rate_fpm * seconds / 60.0;
+        if (self.target_altitude_ft - self.altitude_ft).abs() <= climb_ft.abs() {
+            self.altitude_ft = self.target_altitude_ft;
+            self.target_altitude_ft = random.range(1000.0, 10000.0).round();
+            self.vertical_rate_fpm = random
+                .range(500.0, 1500.0)
+                .copysign(self.target_altitude_ft - self.altitude_ft);
+        } else {
+            self.altitude_ft += climb_ft;
+        }
+    }
+
+    /// Next frame of the aircraft: alternating even and odd positions and
+    ///  velocities, and its identification from time to time
+    fn next_frame(&mut self) -> Frame {
+        let frame = self.frames;
+        self.frames += 1;
+        match (frame % IDENTIFICATION_PERIOD, frame % 3) {
+            (0, _) => identification(self.icao, &self.callsign),
+            (_, 0) => {
+                let track = self.track_deg.to_radians();
+                airborne_velocity(
+                    self.icao,
+                    self.ground_speed_kt * track.sin(),
+                    self.ground_speed_kt * track.cos(),
+                    self.vertical_rate_fpm,
+                )
+            }
+            (_, format) => airborne_position(
+                self.icao,
+                self.altitude_ft.round() as i32,
+                self.latitude,
+                self.longitude,
+                format == 2,
+            ),
+        }
+    }
+}
+
+/// Aircraft flying within an area, sending frames in turn at a given rate
+#[derive(Debug, Clone)]
+pub struct Fleet {
+    /// Simulated aircraft
+    pub aircraft: Vec<Aircraft>,
+
+    /// Area the aircraft fly within
+    area: Area,
+
+    /// Simulated time between two frames, in seconds
+    interval_s: f64,
+
+    /// Aircraft sending the next frame
+    next: usize,
+
+    /// Source of the trajectories
+    random: Random,
+}
+
+impl Fleet {
+    /// Fleet of aircraft placed at random within the area, sending the given
+    ///  positive number of frames per second. The same seed gives the same
+    ///  traffic.
+    pub fn new(count: usize, area: Area, rate: f64, seed: u64) -> Self {
+        let mut random = Random(seed.max(1));
+        let aircraft = (0..count)
+            .map(|index| {
+                let bearing = random.range(0.0, 360.0).to_radians();
+                let distance_nm = random.range(0.0, area.radius_km / KM_PER_NM);
+                let latitude = area.latitude + distance_nm * bearing.cos() / NM_PER_DEGREE;
+                let longitude = area.longitude
+                    + distance_nm * bearing.sin()
+                        / (NM_PER_DEGREE * area.latitude.to_radians().cos());
+                let altitude_ft = random.range(1000.0, 10000.0).round();
+
+                Aircraft {
+                    // Distinct addresses for up to 2^20 aircraft
+                    icao: 0xF00000 | (index as u32 & 0xFFFFF),
+                    callsign: format!("GEN{:04}", index % 10000),
+                    latitude,
+                    longitude,
+                    altitude_ft,
+                    target_altitude_ft: altitude_ft,
+                    ground_speed_kt: random.range(80.0, 250.0).round(),
+                    track_deg: random.range(0.0, 360.0),
+                    vertical_rate_fpm: 0.0,
+                    frames: index as u64,
+                }
+            })
+            .collect();
+
+        Fleet {
+            aircraft,
+            area,
+            interval_s: 1.0 / rate,
+            next: 0,
+            random,
+        }
+    }
+
+    /// Next frame, after moving the aircraft by the time between two frames.
+    ///  None without aircraft.
+    pub fn next_frame(&mut self) -> Option<Frame> {
+        let Fleet {
+            aircraft,
+            area,
+            interval_s,
+            random,
+            ..
+        } = self;
+        aircraft
+            .iter_mut()
+            .for_each(|aircraft| aircraft.advance(*interval_s, area, random));
+
+        let frame = self.aircraft.get_mut(self.next)?.next_frame();
+        self.next = (self.next + 1) % self.aircraft.len();
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::parity;
+
+    fn area() -> Area {
+        Area {
+            latitude: 52.37,
+            longitude: 4.89,
+            radius_km: 20.0,
+        }
+    }
+
+    #[test]
+    fn test_fleet() {
+        let mut fleet = Fleet::new(5, area(), 100.0, 42);
+        assert_eq!(fleet.aircraft.len(), 5);
+        for _ in 0..60000 {
+            let frame = fleet.next_frame().unwrap();
+            assert_eq!(frame[0], 0x8D);
+            assert_eq!(parity(&frame), 0);
+        }
+
+        // Ten minutes later, the aircraft are still around the area
+        for aircraft in &fleet.aircraft {
+            assert!(area().distance_km(aircraft.latitude, aircraft.longitude) < 25.0);
+            assert!((1000.0..=10000.0).contains(&aircraft.altitude_ft));
+        }
+
+        let mut other = Fleet::new(5, area(), 100.0, 42);
+        let mut same = Fleet::new(5, area(), 100.0, 42);
+        assert_eq!(other.next_frame(), same.next_frame());
+        assert_eq!(Fleet::new(0, area(), 100.0, 42).next_frame(), None);
+    }
+
+    #[test]
+    fn test_next_frame() {
+        let mut fleet = Fleet::new(1, area(), 1.0, 7);
+        let frames: Vec<Frame> = (0..IDENTIFICATION_PERIOD)
+            .map(|_| fleet.next_frame().unwrap())
+            .collect();
+        let type_codes: Vec<u8> = frames.iter().map(|frame| frame[4] >> 3).collect();
+        assert_eq!(&type_codes[..4], &[4, 11, 11, 19]);
+        assert_eq!(type_codes.iter().filter(|tc| **tc == 4).count(), 1);
+
+        // Even and odd positions alternate
+        assert_eq!(frames[1][6] & 0x04, 0);
+        assert_eq!(frames[2][6] & 0x04, 0x04);
+    }
+}
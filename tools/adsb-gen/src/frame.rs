@@ -0,0 +1,207 @@
+//! Encoding of DF17 extended squitters
+//!
+//! Examples are from the [ADS-B Decoding Guide](https://airmetar.main.jp/radio/ADS-B%20Decoding%20Guide.pdf).
+
+use std::f64::consts::PI;
+
+/// Length of an extended squitter, in bytes
+pub const FRAME_LENGTH: usize = 14;
+
+/// An extended squitter
+pub type Frame = [u8; FRAME_LENGTH];
+
+/// Downlink format 17, capability 5 (airborne)
+const DF17_CA5: u8 = 0x8D;
+
+/// Generator polynomial of the Mode S parity, without its x^24 term
+const PARITY_GENERATOR: u32 = 0xFFF409;
+
+/// Characters of the callsigns, by their 6-bit code
+const CALLSIGN_CHARSET: &[u8; 64] =
+    b"#ABCDEFGHIJKLMNOPQRSTUVWXYZ##### ###############0123456789######";
+
+/// Resolution of the CPR coordinates, 17 bits
+const CPR_RESOLUTION: f64 = 131072.0;
+
+/// Type code of the identifications of aircraft without category
+const TC_IDENTIFICATION: u64 = 4;
+
+/// Type code of the airborne positions with barometric altitude
+const TC_AIRBORNE_POSITION: u64 = 11;
+
+/// Type code of the airborne velocities
+const TC_AIRBORNE_VELOCITY: u64 = 19;
+
+/// Mode S parity of the data bits of a frame
+pub fn parity(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0;
+    for byte in data {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc = match crc & 0x800000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ PARITY_GENERATOR,
+            } & 0xFFFFFF;
+        }
+    }
+
+    crc
+}
+
+/// DF17 frame of an aircraft with the given 56-bit message, and its parity
+pub fn extended_squitter(icao: u32, message: u64) -> Frame {
+    let mut frame = [0; FRAME_LENGTH];
+    frame[0] = DF17_CA5;
+    frame[1..4].copy_from_slice(&icao.to_be_bytes()[1..]);
+    frame[4..11].copy_from_slice(&message.to_be_bytes()[1..]);
+
+    let parity = parity(&frame[..11]);
+    frame[11..].copy_from_slice(&parity.to_be_bytes()[1..]);
+    frame
+}
+
+/// Identification of an aircraft, its callsign padded or truncated to 8
+///  characters. Characters without code are sent as spaces.
+pub fn identification(icao: u32, callsign: &str) -> Frame {
+    let mut message = TC_IDENTIFICATION << 51;
+    let mut characters = callsign.bytes().map(|c| c.to_ascii_uppercase());
+    for shift in (0..8).rev() {
+        let code = characters
+            .next()
+            .and_then(|c| {
+                CALLSIGN_CHARSET
+                    .iter()
+                    .position(|code| *code == c && c != b'#')
+            })
+            .unwrap_or(32);
+        message |= (code as u64) << (shift * 6);
+    }
+
+    extended_squitter(icao, message)
+}
+
+/// Airborne position of an aircraft, in the even or odd CPR format
+pub fn airborne_position(
+    icao: u32,
+    altitude_ft: i32,
+    latitude: f64,
+    longitude: f64,
+    odd: bool,
+) -> Frame {
+    let (lat_cpr, lon_cpr) = cpr_encode(latitude, longitude, odd);
+    let message = TC_AIRBORNE_POSITION << 51
+        | (altitude_code(altitude_ft) as u64) << 36
+        | (odd as u64) << 34
+        | (lat_cpr as u64) << 17
+        | lon_cpr as u64;
+
+    extended_squitter(icao, message)
+}
+
+/// Airborne velocity over ground of an aircraft, in knots east and north
+///  and feet per minute up
+pub fn airborne_velocity(icao: u32, east_kt: f64, north_kt: f64, vertical_rate_fpm: f64) -> Frame {
+    let speed = |knots: f64| (knots.abs().round() as u64 + 1).min(1023);
+    let message = TC_AIRBORNE_VELOCITY << 51
+        | 1 << 48
+        | ((east_kt < 0.0) as u64) << 42
+        | speed(east_kt) << 32
+        | ((north_kt < 0.0) as u64) << 31
+        | speed(north_kt) << 21
+        | ((vertical_rate_fpm < 0.0) as u64) << 19
+        | ((vertical_rate_fpm.abs() / 64.0).round() as u64 + 1).min(511) << 10;
+
+    extended_squitter(icao, message)
+}
+
+/// 12-bit altitude field in 25 ft increments, from -1000 to 50175 ft
+fn altitude_code(altitude_ft: i32) -> u32 {
+    let n = ((altitude_ft + 1000 + 12) / 25).clamp(0, 2047) as u32;
+    (n & 0x7F0) << 1 | 0x10 | (n & 0xF)
+}
+
+/// Number of longitude zones at a latitude
+fn cpr_nl(latitude: f64) -> u32 {
+    let latitude = latitude.abs();
+    if latitude == 0.0 {
+        return 59;
+    } else if latitude == 87.0 {
+        return 2;
+    } else if latitude > 87.0 {
+        return 1;
+    }
+
+    let a = 1.0 - (PI / 30.0).cos();
+    let b = (PI / 180.0 * latitude).cos().powi(2);
+    (2.0 * PI / (1.0 - a / b).acos()).floor() as u32
+}
+
+/// Airborne CPR encoding of a position, in the even or odd format
+fn cpr_encode(latitude: f64, longitude: f64, odd: bool) -> (u32, u32) {
+    let i = odd as u32 as f64;
+    let d_lat = 360.0 / (60.0 - i);
+    let yz = (CPR_RESOLUTION * latitude.rem_euclid(d_lat) / d_lat + 0.5).floor();
+    let r_lat = d_lat * (yz / CPR_RESOLUTION + (latitude / d_lat).floor());
+
+    let d_lon = 360.0 / (cpr_nl(r_lat) as f64 - i).max(1.0);
+    let xz = (CPR_RESOLUTION * longitude.rem_euclid(d_lon) / d_lon + 0.5).floor();
+
+    (yz as u32 & 0x1FFFF, xz as u32 & 0x1FFFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parity() {
+        let frame = [
+            0x8D, 0x40, 0x62, 0x1D, 0x58, 0xC3, 0x82, 0xD6, 0x90, 0xC8, 0xAC, 0x28, 0x63, 0xA7,
+        ];
+        assert_eq!(parity(&frame[..11]), 0x2863A7);
+        // The parity of a whole valid frame is 0
+        assert_eq!(parity(&frame), 0);
+    }
+
+    #[test]
+    fn test_identification() {
+        assert_eq!(
+            identification(0x4840D6, "klm1023"),
+            [0x8D, 0x48, 0x40, 0xD6, 0x20, 0x2C, 0xC3, 0x71, 0xC3, 0x2C, 0xE0, 0x57, 0x60, 0x98]
+        );
+    }
+
+    #[test]
+    fn test_airborne_position() {
+        assert_eq!(
+            airborne_position(0x40621D, 38000, 52.2572021484375, 3.91937255859375, false),
+            [0x8D, 0x40, 0x62, 0x1D, 0x58, 0xC3, 0x82, 0xD6, 0x90, 0xC8, 0xAC, 0x28, 0x63, 0xA7]
+        );
+
+        let frame = airborne_position(0x40621D, 38000, 52.2572021484375, 3.91937255859375, true);
+        assert_eq!(frame[6] & 0x04, 0x04);
+        assert_eq!(parity(&frame), 0);
+    }
+
+    #[test]
+    fn test_airborne_velocity() {
+        let frame = airborne_velocity(0x485020, -8.0, -159.0, -832.0);
+        let mut message = [0; 8];
+        message[1..].copy_from_slice(&frame[4..11]);
+        // The example, without its IFR capability flag and altitude difference
+        assert_eq!(
+            u64::from_be_bytes(message),
+            0x99440994083817 & !(1 << 46) & !0x7F
+        );
+        assert_eq!(parity(&frame), 0);
+    }
+
+    #[test]
+    fn test_cpr_nl() {
+        assert_eq!(cpr_nl(0.0), 59);
+        assert_eq!(cpr_nl(10.0), 59);
+        assert_eq!(cpr_nl(52.2572021484375), 36);
+        assert_eq!(cpr_nl(-87.0), 2);
+        assert_eq!(cpr_nl(89.0), 1);
+    }
+}
@@ -0,0 +1,8 @@
+//! Generator of ADS-B test traffic
+//!
+//! Simulates a fleet of aircraft flying within an area and encodes their
+//!  identifications, airborne positions and velocities as DF17 extended
+//!  squitters with a valid parity, as received by svc-telemetry.
+
+pub mod fleet;
+pub mod frame;
@@ -0,0 +1,216 @@
+//! Generates ADS-B test traffic
+//!
+//! Sends the frames of simulated aircraft at a given rate, as hex lines on
+//!  the standard output, UDP datagrams or posts to svc-telemetry, e.g.
+//!  `adsb-gen --aircraft 20 --rate 50 --output http://localhost:8000/v1/telemetry/adsb`.
+
+use adsb_gen::fleet::{Area, Fleet};
+use adsb_gen::frame::Frame;
+use clap::Parser;
+use hyper::{Body, Client, Method, Request};
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// Command line options
+#[derive(Parser, Debug)]
+#[command(about = "Generates ADS-B test traffic")]
+struct Cli {
+    /// Number of simulated aircraft
+    #[arg(long, default_value_t = 10)]
+    aircraft: usize,
+
+    /// Frames sent per second, by all aircraft
+    #[arg(long, default_value_t = 20.0, value_parser = positive)]
+    rate: f64,
+
+    /// Latitude of the center of the area, in degrees
+    #[arg(long, default_value_t = 52.37, allow_negative_numbers = true)]
+    latitude: f64,
+
+    /// Longitude of the center of the area, in degrees
+    #[arg(long, default_value_t = 4.89, allow_negative_numbers = true)]
+    longitude: f64,
+
+    /// Radius of the area, in kilometers
+    #[arg(long, default_value_t = 20.0, value_parser = positive)]
+    radius_km: f64,
+
+    /// Where to send the frames: `-` for hex lines on the standard output,
+    ///  `udp://host:port` or an `http://` URL of the ADS-B route
+    #[arg(long, default_value = "-")]
+    output: String,
+
+    /// API key of the feeder, sent to the ADS-B route
+    #[arg(long, env = "ADSB_GEN_API_KEY")]
+    api_key: Option<String>,
+
+    /// Frames to send before exiting, 0 to run until interrupted
+    #[arg(long, default_value_t = 0)]
+    frames: u64,
+
+    /// Seed of the simulation, for repeatable traffic. Random if omitted.
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+/// Destination of the frames
+#[derive(Debug)]
+enum Output {
+    /// Hex lines on the standard output
+    Stdout,
+
+    /// A datagram per frame
+    Udp(UdpSocket),
+
+    /// A post of the frame as `application/octet-stream`
+    Http {
+        /// Client of svc-telemetry
+        client: Client<hyper::client::HttpConnector>,
+
+        /// URL of the ADS-B route
+        url: String,
+
+        /// API key of the feeder
+        api_key: Option<String>,
+
+        /// Posts in flight
+        pending: Vec<JoinHandle<()>>,
+    },
+}
+
+impl Output {
+    /// Opens the destination of the frames
+    async fn open(output: &str, api_key: Option<String>) -> Result<Self, String> {
+        if output == "-" {
+            return Ok(Output::Stdout);
+        }
+
+        if let Some(address) = output.strip_prefix("udp://") {
+            let socket = UdpSocket::bind("0.0.0.0:0")
+                .await
+                .map_err(|e| format!("could not bind UDP socket: {e}"))?;
+            socket
+                .connect(address)
+                .await
+                .map_err(|e| format!("could not connect to {address}: {e}"))?;
+            return Ok(Output::Udp(socket));
+        }
+
+        if output.starts_with("http://") {
+            return Ok(Output::Http {
+                client: Client::new(),
+                url: output.to_string(),
+                api_key,
+                pending: vec![],
+            });
+        }
+
+        Err(format!(
+            "unsupported output {output}, expected -, udp://host:port or an http:// URL"
+        ))
+    }
+
+    /// Sends a frame. Failed posts are reported without stopping.
+    async fn send(&mut self, frame: Frame) -> io::Result<()> {
+        match self {
+            Output::Stdout => {
+                let line: String = frame.iter().map(|byte| format!("{byte:02X}")).collect();
+                writeln!(io::stdout(), "{line}")
+            }
+            Output::Udp(socket) => socket.send(&frame).await.map(|_| ()),
+            Output::Http {
+                client,
+                url,
+                api_key,
+                pending,
+            } => {
+                let mut request = Request::builder()
+                    .method(Method::POST)
+                    .uri(url.as_str())
+                    .header("content-type", "application/octet-stream");
+                if let Some(api_key) = api_key {
+                    request = request.header("x-api-key", api_key.as_str());
+                }
+
+                let request = request
+                    .body(Body::from(frame.to_vec()))
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+                // Posts don't hold back the next frames
+                let client = client.clone();
+                pending.retain(|post| !post.is_finished());
+                pending.push(tokio::spawn(async move {
+                    match client.request(request).await {
+                        Ok(response) if response.status().is_success() => (),
+                        Ok(response) => eprintln!("post rejected: {}", response.status()),
+                        Err(e) => eprintln!("could not post: {e}"),
+                    }
+                }));
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Waits for the posts in flight
+    async fn finish(self) {
+        if let Output::Http { pending, .. } = self {
+            for post in pending {
+                let _ = post.await;
+            }
+        }
+    }
+}
+
+/// Validates a strictly positive number
+fn positive(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(value) if value > 0.0 && value.is_finite() => Ok(value),
+        _ => Err("expected a positive number".to_string()),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let mut output = Output::open(&cli.output, cli.api_key.clone()).await?;
+
+    let seed = cli.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or(1)
+    });
+
+    let area = Area {
+        latitude: cli.latitude,
+        longitude: cli.longitude,
+        radius_km: cli.radius_km,
+    };
+
+    let mut fleet = Fleet::new(cli.aircraft, area, cli.rate, seed);
+    eprintln!(
+        "sending {} frames per second of {} aircraft (seed {seed}) to {} ...",
+        cli.rate, cli.aircraft, cli.output
+    );
+
+    let mut ticks = tokio::time::interval(Duration::from_secs_f64(1.0 / cli.rate));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Burst);
+
+    let mut sent: u64 = 0;
+    while cli.frames == 0 || sent < cli.frames {
+        let Some(frame) = fleet.next_frame() else {
+            break;
+        };
+
+        ticks.tick().await;
+        output.send(frame).await?;
+        sent += 1;
+    }
+
+    output.finish().await;
+    Ok(())
+}